@@ -1,14 +1,34 @@
 use anyhow::Result;
+use sha2::{Digest, Sha256};
 use std::env;
 use std::fs::File;
 use std::io::Write;
-use std::path::Path;
+use std::path::{Path, PathBuf};
 
+/// Generates `source_files_list.rs` in `OUT_DIR` with sorted list of crate's source files,
+/// see `gen_source_files_list_ignoring`
 pub fn gen_source_files_list() -> Result<()> {
+    gen_source_files_list_ignoring(&[])
+}
+
+/// Generates `source_files_list.rs` in `OUT_DIR` with sorted list of crate's source files,
+/// their SHA-256 hashes and combined digest, as constants:
+/// * `SOURCE_FILES_COUNT` - number of listed files
+/// * `SOURCE_FILES` - paths relative to manifest dir
+/// * `SOURCE_FILE_HASHES` - hex-encoded hashes of files' contents, in the same order
+/// * `SOURCE_FILES_DIGEST` - hex-encoded hash of all files, each hashed as its path with forward
+///     slashes, zero byte and raw 32-byte hash of contents, in the same order
+///
+/// # Arguments
+/// * `ignored` - paths relative to manifest dir, e.g. `src/fixtures`, files under which
+///     aren't listed; files under `OUT_DIR` are never listed
+pub fn gen_source_files_list_ignoring(ignored: &[&str]) -> Result<()> {
     let manifest_dir = env::var("CARGO_MANIFEST_DIR")?;
     let out_dir = env::var("OUT_DIR")?;
+    let src_dir = Path::new(&manifest_dir).join("src");
+    println!("cargo:rerun-if-changed={}", src_dir.display());
 
-    let mut all_files: Vec<_> = walkdir::WalkDir::new(Path::new(&manifest_dir).join("src"))
+    let mut all_files: Vec<_> = walkdir::WalkDir::new(&src_dir)
         .follow_links(true)
         .into_iter()
         .filter_map(|e| e.ok())
@@ -17,12 +37,19 @@ pub fn gen_source_files_list() -> Result<()> {
                 && Path::new(e.file_name())
                     .extension()
                     .map_or(false, |ex| ex == "rs")
+                && !e.path().starts_with(&out_dir)
         })
         .filter_map(|e| pathdiff::diff_paths(e.into_path(), &manifest_dir))
+        .filter(|path| !ignored.iter().any(|ignored| path.starts_with(ignored)))
         .collect();
 
     all_files.sort();
 
+    let hashes = all_files
+        .iter()
+        .map(|path| Ok(Sha256::digest(std::fs::read(Path::new(&manifest_dir).join(path))?).into()))
+        .collect::<Result<Vec<[u8; 32]>>>()?;
+
     let mut out_file = File::create(Path::new(&out_dir).join("source_files_list.rs"))?;
     writeln!(
         out_file,
@@ -33,9 +60,36 @@ pub fn gen_source_files_list() -> Result<()> {
         out_file,
         "#[allow(unused)]\nconst SOURCE_FILES: [&str; SOURCE_FILES_COUNT] = ["
     )?;
-    for path in all_files {
+    for path in &all_files {
         writeln!(out_file, "    \"{}\",", path.display())?;
     }
     writeln!(out_file, "];")?;
+    writeln!(
+        out_file,
+        "#[allow(unused)]\nconst SOURCE_FILE_HASHES: [&str; SOURCE_FILES_COUNT] = ["
+    )?;
+    for hash in &hashes {
+        writeln!(out_file, "    \"{}\",", to_hex(hash))?;
+    }
+    writeln!(out_file, "];")?;
+    writeln!(
+        out_file,
+        "#[allow(unused)]\nconst SOURCE_FILES_DIGEST: &str = \"{}\";",
+        source_files_digest(&all_files, &hashes)
+    )?;
     Ok(())
 }
+
+fn source_files_digest(files: &[PathBuf], hashes: &[[u8; 32]]) -> String {
+    let mut hasher = Sha256::new();
+    for (path, hash) in files.iter().zip(hashes) {
+        hasher.update(path.to_string_lossy().replace('\\', "/").as_bytes());
+        hasher.update([0]);
+        hasher.update(hash);
+    }
+    to_hex(&hasher.finalize())
+}
+
+fn to_hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|byte| format!("{byte:02x}")).collect()
+}
@@ -1,13 +1,38 @@
 #![deny(warnings)]
 
-use anyhow::{ensure, Result};
-use git2::{ObjectType, Oid, Repository};
+use anyhow::{ensure, Context, Result};
+use git2::{ObjectType, Oid, Repository, Sort, StatusOptions};
 use semver::Version;
-use std::{collections::HashMap, env};
+use std::{collections::HashMap, env, process::Command};
 /// Retrieves version information from current crate's Git repository and pushes it to Cargo's build config
-/// as env variable `DEX_CORE_VERSION`. If no Git repo is found, version is `0.0.0+unknown`
+/// as env variable `DEX_CORE_VERSION`. If no Git repo is found, version is `0.0.0+unknown`.
+/// Also pushes id of HEAD commit as `DEX_CORE_GIT_COMMIT`, `unknown` if no Git repo is found, and versions
+/// of toolchain which builds the crate as `DEX_CORE_RUSTC_VERSION` and `DEX_CORE_CARGO_VERSION`
+///
+/// Version is taken from the nearest ancestor of HEAD tagged with `<semver>` or `v<semver>`,
+/// across all parents, like `git describe` does. Build metadata is HEAD commit id, preceded
+/// with number of commits since tagged one if it's not HEAD itself, and followed with `-dirty`
+/// if worktree has uncommitted changes, e.g. `1.2.3+5.<commit id>-dirty`.
+/// If no tag is reachable, e.g. in shallow clone, version is `0.0.0+<commit id>`.
+/// Repository is opened from `GIT_DIR` if it's set, and whole lookup is skipped
+/// if version is provided with `DEX_CORE_VERSION_OVERRIDE`.
 pub fn version_from_git() {
-    let ver_str = match read_ver_tag() {
+    crate_version_from_git("", DEFAULT_ENV_PREFIX);
+}
+
+/// Same as `version_from_git`, for one of several crates which are released independently
+/// from single repository
+///
+/// # Arguments
+/// * `tag_prefix` - prefix of crate's version tags, e.g. `veax-dex-` for tags like `veax-dex-v1.2.3`;
+///     tags without it are ignored
+/// * `env_prefix` - prefix of pushed env variables, e.g. `VEAX_DEX` for `VEAX_DEX_VERSION`,
+///     `VEAX_DEX_GIT_COMMIT` etc. Version override is read from `<env_prefix>_VERSION_OVERRIDE`
+pub fn crate_version_from_git(tag_prefix: &str, env_prefix: &str) {
+    let override_var = format!("{env_prefix}_VERSION_OVERRIDE");
+    println!("cargo:rerun-if-env-changed={override_var}");
+    println!("cargo:rerun-if-env-changed=GIT_DIR");
+    let ver_str = match read_version(tag_prefix, &override_var) {
         Ok(s) => s,
         Err(e) => {
             println!("cargo:warning=Failed to retrieve version info from Git repository. Will use default version stub. Error: {e}");
@@ -15,27 +40,92 @@ pub fn version_from_git() {
         }
     };
 
-    println!("cargo:rustc-env=DEX_CORE_VERSION={ver_str}");
+    println!("cargo:rustc-env={env_prefix}_VERSION={ver_str}");
+
+    let commit = read_head_commit().unwrap_or_else(|_| "unknown".to_string());
+    println!("cargo:rustc-env={env_prefix}_GIT_COMMIT={commit}");
+    println!(
+        "cargo:rustc-env={env_prefix}_RUSTC_VERSION={}",
+        tool_version("RUSTC", "rustc")
+    );
+    println!(
+        "cargo:rustc-env={env_prefix}_CARGO_VERSION={}",
+        tool_version("CARGO", "cargo")
+    );
+}
+
+fn read_head_commit() -> Result<String> {
+    let repo = open_repo()?;
+    let head_id = repo.head()?.peel_to_commit()?.id();
+    Ok(head_id.to_string())
+}
+
+/// Output of `<tool> --version`, where tool is taken from env variable set by Cargo for build scripts
+fn tool_version(env_var: &str, default: &str) -> String {
+    let tool = env::var(env_var).unwrap_or_else(|_| default.to_string());
+    Command::new(tool)
+        .arg("--version")
+        .output()
+        .ok()
+        .filter(|output| output.status.success())
+        .and_then(|output| String::from_utf8(output.stdout).ok())
+        .map_or_else(
+            || "unknown".to_string(),
+            |version| version.trim().to_string(),
+        )
 }
 
 const VERSION_PREFIX: &str = "v";
 const TAG_PREFIX: &str = "refs/tags/";
+const DEFAULT_ENV_PREFIX: &str = "DEX_CORE";
+const DIRTY_SUFFIX: &str = "-dirty";
+
+/// Opens repository from `GIT_DIR` if it's set, or discovers one which contains crate
+fn open_repo() -> Result<Repository> {
+    if env::var_os("GIT_DIR").is_some() {
+        Ok(Repository::open_from_env()?)
+    } else {
+        Ok(Repository::discover(env::var("CARGO_MANIFEST_DIR")?)?)
+    }
+}
 
-fn read_ver_tag() -> Result<String> {
+fn read_version(tag_prefix: &str, override_var: &str) -> Result<String> {
+    if let Ok(ver) = env::var(override_var) {
+        let ver: Version = ver
+            .strip_prefix(VERSION_PREFIX)
+            .unwrap_or(&ver)
+            .parse()
+            .with_context(|| format!("{override_var} is not a valid version"))?;
+        return Ok(ver.to_string());
+    }
+    read_ver_tag(tag_prefix)
+}
+
+/// Whether worktree has uncommitted changes of tracked files; bare repository is never dirty
+fn is_dirty(repo: &Repository) -> bool {
+    let mut options = StatusOptions::new();
+    options.include_untracked(false).include_ignored(false);
+    repo.statuses(Some(&mut options))
+        .map_or(false, |statuses| !statuses.is_empty())
+}
+
+fn read_ver_tag(tag_prefix: &str) -> Result<String> {
     fn try_run(cb: impl FnOnce() -> Result<()>) -> bool {
         std::mem::drop(cb());
         true
     }
 
-    let manifest_dir = env::var("CARGO_MANIFEST_DIR")?;
-    let repo = Repository::discover(manifest_dir)?;
-    // Read all tags which match `<semver>` or `v<semver>` pattern, store them
+    let repo = open_repo()?;
+    // Read all tags which match `<tag_prefix><semver>` or `<tag_prefix>v<semver>` pattern, store them
     let mut tags: HashMap<Oid, Vec<Version>> = HashMap::new();
 
     repo.tag_foreach(|oid, name| {
         try_run(|| {
             let name = std::str::from_utf8(name)?;
             let name = name.strip_prefix(TAG_PREFIX).unwrap_or(name);
+            let name = name
+                .strip_prefix(tag_prefix)
+                .context("Tags of other crates are skipped")?;
             // Parse tag as SEMVER. Accept both normal "<semver>" and "v<semver>".
             let ver: Version = name.strip_prefix(VERSION_PREFIX).unwrap_or(name).parse()?;
             // Skip tags which have "build" section
@@ -62,18 +152,33 @@ fn read_ver_tag() -> Result<String> {
     for vers in tags.values_mut() {
         vers.sort_by(|l, r| r.cmp(l));
     }
-    // Get head and walk first-parent path until we find any
-    let mut commit = repo.head()?.peel_to_commit()?;
-    let head_id = commit.id();
+    // Walk ancestors of head across all parents, most recent first, until we find tagged one.
+    // In shallow clone history ends at shallow boundary, so walk just stops there
+    let head_id = repo.head()?.peel_to_commit()?.id();
+    let mut walk = repo.revwalk()?;
+    walk.set_sorting(Sort::TOPOLOGICAL | Sort::TIME)?;
+    walk.push(head_id)?;
+    let tagged = walk
+        .map_while(|oid| oid.ok())
+        .find_map(|oid| tags.get(&oid).map(|vers| (oid, vers)));
 
-    loop {
-        if let Some(vers) = tags.get_mut(&commit.id()) {
-            let mut ver = vers.first_mut().unwrap().clone(); // Panicking here is a definite bug
-            ver.build = head_id.to_string().parse().unwrap(); // Panicking here is a definite bug too
-
-            return Ok(ver.to_string());
+    let (mut ver, build) = match tagged {
+        Some((oid, vers)) => {
+            let ver = vers.first().unwrap().clone(); // Panicking here is a definite bug
+            let (distance, _) = repo.graph_ahead_behind(head_id, oid)?;
+            let build = if distance == 0 {
+                head_id.to_string()
+            } else {
+                format!("{distance}.{head_id}")
+            };
+            (ver, build)
         }
-        // Next commit, if any. Not finding version up to repo root is also a failure
-        commit = commit.parent(0)?;
-    }
+        None => {
+            println!("cargo:warning=No version tag is reachable from HEAD, e.g. due to shallow clone. Will use version 0.0.0");
+            (Version::new(0, 0, 0), head_id.to_string())
+        }
+    };
+    let dirty = if is_dirty(&repo) { DIRTY_SUFFIX } else { "" };
+    ver.build = format!("{build}{dirty}").parse().unwrap(); // Panicking here is a definite bug
+    Ok(ver.to_string())
 }
@@ -0,0 +1,121 @@
+//! Benchmarks of hot fixed-point operations
+use criterion::{black_box, criterion_group, criterion_main, BatchSize, Criterion};
+use veax_dex::fp::bench::{float_samples, u128x128_samples, u192x64_samples, u256x256_samples};
+use veax_dex::fp::{U128X128, U192X64, U256X256};
+use veax_dex::Float;
+
+const SAMPLES: usize = 1024;
+
+fn bench_mul(c: &mut Criterion) {
+    let u256x256 = u256x256_samples(SAMPLES);
+    let u192x64 = u192x64_samples(SAMPLES);
+
+    c.bench_function("U256X256 mul", |b| {
+        b.iter(|| {
+            u256x256
+                .iter()
+                .zip(u256x256.iter().rev())
+                .map(|(l, r)| black_box(*l) * black_box(*r))
+                .fold(U256X256::from(0u128), |acc, v| acc.max(v))
+        });
+    });
+    c.bench_function("U192X64 mul", |b| {
+        b.iter(|| {
+            u192x64
+                .iter()
+                .zip(u192x64.iter().rev())
+                .map(|(l, r)| black_box(*l) * black_box(*r))
+                .fold(U192X64::from(0u128), |acc, v| acc.max(v))
+        });
+    });
+}
+
+fn bench_integer_sqrt(c: &mut Criterion) {
+    let u256x256 = u256x256_samples(SAMPLES);
+    let u192x64 = u192x64_samples(SAMPLES);
+    let u128x128 = u128x128_samples(SAMPLES);
+
+    c.bench_function("U256X256 integer_sqrt", |b| {
+        b.iter_batched(
+            || u256x256.clone(),
+            |values| {
+                values
+                    .into_iter()
+                    .map(|v| black_box(v).integer_sqrt())
+                    .count()
+            },
+            BatchSize::SmallInput,
+        );
+    });
+    c.bench_function("U192X64 integer_sqrt", |b| {
+        b.iter_batched(
+            || u192x64.clone(),
+            |values| {
+                values
+                    .into_iter()
+                    .map(|v| black_box(v).integer_sqrt())
+                    .count()
+            },
+            BatchSize::SmallInput,
+        );
+    });
+    c.bench_function("U128X128 integer_sqrt", |b| {
+        b.iter_batched(
+            || u128x128.clone(),
+            |values| {
+                values
+                    .into_iter()
+                    .map(|v| black_box(v).integer_sqrt())
+                    .count()
+            },
+            BatchSize::SmallInput,
+        );
+    });
+}
+
+fn bench_float_conversions(c: &mut Criterion) {
+    let floats = float_samples(SAMPLES);
+    let u192x64 = u192x64_samples(SAMPLES);
+    let u128x128 = u128x128_samples(SAMPLES);
+
+    c.bench_function("Float -> U192X64", |b| {
+        b.iter(|| {
+            floats
+                .iter()
+                .filter_map(|v| U192X64::try_from(black_box(*v)).ok())
+                .count()
+        });
+    });
+    c.bench_function("U192X64 -> Float", |b| {
+        b.iter(|| {
+            u192x64
+                .iter()
+                .map(|v| Float::from(black_box(*v)))
+                .fold(Float::zero(), |acc, v| acc + v)
+        });
+    });
+    c.bench_function("Float -> U128X128", |b| {
+        b.iter(|| {
+            floats
+                .iter()
+                .filter_map(|v| U128X128::try_from(black_box(*v)).ok())
+                .count()
+        });
+    });
+    c.bench_function("U128X128 -> Float", |b| {
+        b.iter(|| {
+            u128x128
+                .iter()
+                .map(|v| Float::from(black_box(*v)))
+                .fold(Float::zero(), |acc, v| acc + v)
+        });
+    });
+}
+
+criterion_group!(
+    benches,
+    bench_mul,
+    bench_integer_sqrt,
+    bench_float_conversions
+);
+criterion_main!(benches);
@@ -0,0 +1,186 @@
+//! Gas measurement of contract calls in local sandbox
+//!
+//! Requires contract WASM built with `build-wasm-release.sh`;
+//! path to it may be overridden with `VEAX_WASM` environment variable.
+//! Also requires NEP-141 token WASM with `new_default_meta` initializer,
+//! path to which is passed with `VEAX_FT_WASM` environment variable.
+//! Reports gas burnt by `open_position` and `swap_exact_in` crossing different number of ticks.
+use near_workspaces::types::NearToken;
+use near_workspaces::{Account, Contract};
+use serde_json::json;
+use std::path::PathBuf;
+
+const DEFAULT_WASM: &str = "../../res/veax_dex.wasm";
+const POSITION_AMOUNT: u128 = 1_000_000_000_000;
+const TICKS_WIDTH: i32 = 100;
+
+fn wasm_path() -> PathBuf {
+    std::env::var_os("VEAX_WASM").map_or_else(
+        || PathBuf::from(env!("CARGO_MANIFEST_DIR")).join(DEFAULT_WASM),
+        PathBuf::from,
+    )
+}
+
+/// Deploys fungible token mock and registers accounts in it
+async fn deploy_token(
+    worker: &near_workspaces::Worker<near_workspaces::network::Sandbox>,
+    ft_wasm: &[u8],
+    holders: &[&Account],
+    dex: &Contract,
+) -> anyhow::Result<Contract> {
+    let token = worker.dev_deploy(ft_wasm).await?;
+    token
+        .call("new_default_meta")
+        .args_json(json!({ "owner_id": token.id(), "total_supply": u128::MAX.to_string() }))
+        .transact()
+        .await?
+        .into_result()?;
+    for account_id in holders.iter().map(|h| h.id()).chain([dex.id()]) {
+        token
+            .call("storage_deposit")
+            .args_json(json!({ "account_id": account_id }))
+            .deposit(NearToken::from_millinear(10))
+            .transact()
+            .await?
+            .into_result()?;
+    }
+    for holder in holders {
+        token
+            .call("ft_transfer")
+            .args_json(json!({ "receiver_id": holder.id(), "amount": (u128::MAX / 4).to_string() }))
+            .deposit(NearToken::from_yoctonear(1))
+            .transact()
+            .await?
+            .into_result()?;
+    }
+    Ok(token)
+}
+
+async fn deposit(account: &Account, token: &Contract, dex: &Contract) -> anyhow::Result<()> {
+    account
+        .call(token.id(), "ft_transfer_call")
+        .args_json(json!({
+            "receiver_id": dex.id(),
+            "amount": (u128::MAX / 8).to_string(),
+            "msg": "",
+        }))
+        .deposit(NearToken::from_yoctonear(1))
+        .max_gas()
+        .transact()
+        .await?
+        .into_result()?;
+    Ok(())
+}
+
+#[tokio::main]
+async fn main() -> anyhow::Result<()> {
+    let wasm_path = wasm_path();
+    let Ok(wasm) = std::fs::read(&wasm_path) else {
+        eprintln!("Contract WASM not found at {}, skipping gas benchmarks", wasm_path.display());
+        return Ok(());
+    };
+    let Some(ft_wasm) = std::env::var_os("VEAX_FT_WASM").and_then(|path| std::fs::read(path).ok())
+    else {
+        eprintln!("Token WASM not specified with VEAX_FT_WASM, skipping gas benchmarks");
+        return Ok(());
+    };
+
+    let worker = near_workspaces::sandbox().await?;
+    let owner = worker.dev_create_account().await?;
+    let provider = worker.dev_create_account().await?;
+    let trader = worker.dev_create_account().await?;
+
+    let dex = worker.dev_deploy(&wasm).await?;
+    dex.call("new")
+        .args_json(json!({ "owner_id": owner.id(), "protocol_fee_fraction": 1300 }))
+        .transact()
+        .await?
+        .into_result()?;
+
+    let token_a = deploy_token(&worker, &ft_wasm, &[&provider, &trader], &dex).await?;
+    let token_b = deploy_token(&worker, &ft_wasm, &[&provider, &trader], &dex).await?;
+
+    for account in [&provider, &trader] {
+        account
+            .call(dex.id(), "storage_deposit")
+            .args_json(json!({}))
+            .deposit(NearToken::from_near(1))
+            .transact()
+            .await?
+            .into_result()?;
+        account
+            .call(dex.id(), "register_tokens")
+            .args_json(json!({ "token_ids": [token_a.id(), token_b.id()] }))
+            .deposit(NearToken::from_yoctonear(1))
+            .transact()
+            .await?
+            .into_result()?;
+        deposit(account, &token_a, &dex).await?;
+        deposit(account, &token_b, &dex).await?;
+    }
+
+    let open_position = |ticks_range: (Option<i32>, Option<i32>)| {
+        provider
+            .call(dex.id(), "open_position")
+            .args_json(json!({
+                "token_a": token_a.id(),
+                "token_b": token_b.id(),
+                "fee_rate": 1,
+                "position": {
+                    "amount_ranges": [
+                        { "min": "0", "max": POSITION_AMOUNT.to_string() },
+                        { "min": "0", "max": POSITION_AMOUNT.to_string() },
+                    ],
+                    "ticks_range": ticks_range,
+                },
+            }))
+            .deposit(NearToken::from_millinear(100))
+            .max_gas()
+            .transact()
+    };
+
+    let outcome = open_position((None, None)).await?.into_result()?;
+    println!(
+        "open_position (full range): {} Tgas",
+        outcome.total_gas_burnt.as_tgas()
+    );
+
+    let mut positions = 0;
+    for crossings in [0, 1, 4, 16] {
+        while positions < crossings {
+            positions += 1;
+            for ticks_range in [
+                (positions * TICKS_WIDTH, (positions + 1) * TICKS_WIDTH),
+                (-(positions + 1) * TICKS_WIDTH, -positions * TICKS_WIDTH),
+            ] {
+                let outcome = open_position((Some(ticks_range.0), Some(ticks_range.1)))
+                    .await?
+                    .into_result()?;
+                println!(
+                    "open_position (ticks {ticks_range:?}): {} Tgas",
+                    outcome.total_gas_burnt.as_tgas()
+                );
+            }
+        }
+        // Swap there and back, so that every measurement starts from the same price
+        for (token_in, token_out) in [(&token_a, &token_b), (&token_b, &token_a)] {
+            let outcome = trader
+                .call(dex.id(), "swap_exact_in")
+                .args_json(json!({
+                    "tokens": [token_in.id(), token_out.id()],
+                    "amount_in": (POSITION_AMOUNT * (crossings as u128 + 1) / 2).to_string(),
+                    "min_amount_out": "0",
+                }))
+                .deposit(NearToken::from_yoctonear(1))
+                .max_gas()
+                .transact()
+                .await?
+                .into_result()?;
+            println!(
+                "swap_exact_in (up to {crossings} tick crossings): {} Tgas",
+                outcome.total_gas_burnt.as_tgas()
+            );
+        }
+    }
+    Ok(())
+}
@@ -0,0 +1,100 @@
+//! Benchmarks of `swap_exact_in` over in-memory contract state,
+//! with swaps crossing different number of initialized ticks
+use criterion::{black_box, criterion_group, criterion_main, BatchSize, BenchmarkId, Criterion};
+use veax_dex::dex::sim::SimState;
+use veax_dex::dex::{PositionInit, Range};
+use veax_dex::{AccountId, Amount, TokenId, WasmAmount};
+
+const FEE_RATE: u16 = 1;
+const POSITION_AMOUNT: Amount = 1_000_000_000_000;
+/// Width of each position, in ticks
+const TICKS_WIDTH: i32 = 100;
+
+fn account(name: &str) -> AccountId {
+    name.parse().unwrap()
+}
+
+/// Pool with `positions` adjacent narrow positions on both sides of initial price,
+/// so that swap of `positions * POSITION_AMOUNT` crosses up to `positions` ticks
+fn setup(positions: i32) -> (SimState, AccountId, (TokenId, TokenId)) {
+    let (owner, provider, trader) = (account("owner"), account("provider"), account("trader"));
+    let tokens = (account("token-a"), account("token-b"));
+    let mut state = SimState::new(owner, 1300).unwrap();
+
+    let total = POSITION_AMOUNT * (2 * positions as Amount + 1);
+    for account_id in [&provider, &trader] {
+        state.deposit(account_id, &tokens.0, total).unwrap();
+        state.deposit(account_id, &tokens.1, total).unwrap();
+    }
+    // Full range position sets initial price
+    state
+        .call(&provider, |dex| {
+            dex.open_position(
+                &tokens.0,
+                &tokens.1,
+                FEE_RATE,
+                PositionInit::new_full_range(0u128, POSITION_AMOUNT, 0u128, POSITION_AMOUNT),
+            )
+        })
+        .unwrap();
+    for index in 1..=positions {
+        for (low, high) in [
+            (index * TICKS_WIDTH, (index + 1) * TICKS_WIDTH),
+            (-(index + 1) * TICKS_WIDTH, -index * TICKS_WIDTH),
+        ] {
+            let position = PositionInit {
+                amount_ranges: (
+                    Range {
+                        min: WasmAmount::from(0),
+                        max: WasmAmount::from(POSITION_AMOUNT),
+                    },
+                    Range {
+                        min: WasmAmount::from(0),
+                        max: WasmAmount::from(POSITION_AMOUNT),
+                    },
+                ),
+                ticks_range: (Some(low), Some(high)),
+                auto_balance: false,
+                locked_until: None,
+            };
+            state
+                .call(&provider, |dex| {
+                    dex.open_position(&tokens.0, &tokens.1, FEE_RATE, position)
+                })
+                .unwrap();
+        }
+    }
+    (state, trader, tokens)
+}
+
+fn bench_swap_exact_in(c: &mut Criterion) {
+    let mut group = c.benchmark_group("swap_exact_in");
+    for positions in [0, 1, 4, 16] {
+        let (state, trader, tokens) = setup(positions);
+        let path = [tokens.0.clone(), tokens.1.clone()];
+        // Swap large enough to cross all the positions on one side
+        let amount_in = POSITION_AMOUNT * (positions as Amount + 1) / 2;
+
+        group.bench_with_input(
+            BenchmarkId::from_parameter(positions),
+            &positions,
+            |b, _| {
+                b.iter_batched_ref(
+                    || state.snapshot(),
+                    |state| {
+                        state
+                            .call(&trader, |dex| {
+                                dex.swap_exact_in(black_box(&path), black_box(amount_in), 0, None)
+                            })
+                            .unwrap()
+                    },
+                    BatchSize::LargeInput,
+                );
+            },
+        );
+    }
+    group.finish();
+}
+
+criterion_group!(benches, bench_swap_exact_in);
+criterion_main!(benches);
@@ -0,0 +1,686 @@
+//! Generates machine-readable schema of the contract's WASM API, to keep SDK bindings in sync.
+//!
+//! Schema is derived from sources: methods are collected from `#[near_bindgen]` impl blocks
+//! in `chain/wasm.rs`, and JSON shapes of their arguments and results are resolved
+//! through serde-annotated structs, enums and type aliases found anywhere in the crate.
+//!
+//! Usage: `cargo run --features schema-gen --bin gen-wasm-schema [output.json]`
+//!
+//! Shapes follow JSON Schema conventions where possible:
+//! * `{"type": "string", "format": "u128"}` etc. for primitives and NEAR JSON wrappers
+//! * `{"type": "array", "items": ...}` for sequences, `"prefixItems"` for tuples
+//! * `{"type": "object", "properties": ..., "required": ...}` for structs
+//! * `{"oneOf": [...]}` for enums and optional values
+//! * `{"$ref": "#/definitions/Name"}` for named types, collected in `definitions`
+//!
+//! Rust types which can't be resolved are emitted as `{"type": "unknown", "rust": "Name"}`
+//! and reported to stderr.
+use quote::ToTokens;
+use serde_json::{json, Map, Value};
+use std::collections::{BTreeMap, HashMap};
+use std::path::{Path, PathBuf};
+use syn::{
+    Attribute, Fields, FnArg, GenericArgument, GenericParam, Generics, ImplItem, Item, Lit, Meta,
+    NestedMeta, Pat, PathArguments, ReturnType, Type, Visibility,
+};
+
+const WASM_API_SOURCE: &str = "src/chain/wasm.rs";
+
+fn main() {
+    let root = Path::new(env!("CARGO_MANIFEST_DIR"));
+    let mut registry = TypeRegistry::default();
+    for path in source_files(&root.join("src")) {
+        registry.add_items(&path, parse_source(&path).items);
+    }
+
+    let mut generator = SchemaGenerator::new(registry);
+    let methods = parse_source(&root.join(WASM_API_SOURCE))
+        .items
+        .iter()
+        .filter_map(|item| match item {
+            Item::Impl(item_impl) if has_attr(&item_impl.attrs, "near_bindgen") => Some(item_impl),
+            _ => None,
+        })
+        .flat_map(|item_impl| {
+            let is_trait_impl = item_impl.trait_.is_some();
+            item_impl.items.iter().filter_map(move |item| match item {
+                ImplItem::Method(method)
+                    if is_trait_impl || matches!(method.vis, Visibility::Public(_)) =>
+                {
+                    Some(method)
+                }
+                _ => None,
+            })
+        })
+        .map(|method| generator.method_schema(method))
+        .collect::<Vec<_>>();
+
+    let schema = json!({
+        "contract": env!("CARGO_PKG_NAME"),
+        "version": env!("CARGO_PKG_VERSION"),
+        "methods": methods,
+        "definitions": generator.definitions,
+    });
+    let output = serde_json::to_string_pretty(&schema).expect("Schema must be serializable");
+    match std::env::args().nth(1) {
+        Some(path) => std::fs::write(&path, output).expect("Could not write schema file"),
+        None => println!("{output}"),
+    }
+}
+
+fn source_files(dir: &Path) -> Vec<PathBuf> {
+    let mut files = Vec::new();
+    let mut entries = std::fs::read_dir(dir)
+        .expect("Could not read sources directory")
+        .map(|entry| entry.expect("Could not read directory entry").path())
+        .collect::<Vec<_>>();
+    entries.sort();
+    for path in entries {
+        if path.is_dir() {
+            files.extend(source_files(&path));
+        } else if path.extension().map_or(false, |ext| ext == "rs") {
+            files.push(path);
+        }
+    }
+    files
+}
+
+fn parse_source(path: &Path) -> syn::File {
+    let source = std::fs::read_to_string(path)
+        .unwrap_or_else(|e| panic!("Could not read {}: {e}", path.display()));
+    syn::parse_file(&source).unwrap_or_else(|e| panic!("Could not parse {}: {e}", path.display()))
+}
+
+/// Attribute metas, including ones under `cfg_attr` regardless of its predicate
+fn metas(attrs: &[Attribute]) -> Vec<Meta> {
+    fn unwrap_cfg_attr(meta: Meta, out: &mut Vec<Meta>) {
+        match meta {
+            Meta::List(list) if list.path.is_ident("cfg_attr") => {
+                for nested in list.nested.into_iter().skip(1) {
+                    if let NestedMeta::Meta(meta) = nested {
+                        unwrap_cfg_attr(meta, out);
+                    }
+                }
+            }
+            meta => out.push(meta),
+        }
+    }
+    let mut out = Vec::new();
+    for attr in attrs {
+        if let Ok(meta) = attr.parse_meta() {
+            unwrap_cfg_attr(meta, &mut out);
+        }
+    }
+    out
+}
+
+fn has_attr(attrs: &[Attribute], name: &str) -> bool {
+    attrs.iter().any(|attr| attr.path.is_ident(name))
+}
+
+/// Nested metas of all attributes named `name`, e.g. `serde` or `derive`
+fn nested_metas(attrs: &[Attribute], name: &str) -> Vec<Meta> {
+    metas(attrs)
+        .into_iter()
+        .filter_map(|meta| match meta {
+            Meta::List(list) if list.path.is_ident(name) => Some(list.nested),
+            _ => None,
+        })
+        .flatten()
+        .filter_map(|nested| match nested {
+            NestedMeta::Meta(meta) => Some(meta),
+            NestedMeta::Lit(_) => None,
+        })
+        .collect()
+}
+
+fn is_serde_type(attrs: &[Attribute]) -> bool {
+    nested_metas(attrs, "derive").iter().any(|meta| {
+        meta.path().segments.last().map_or(false, |segment| {
+            segment.ident == "Serialize" || segment.ident == "Deserialize"
+        })
+    })
+}
+
+fn doc(attrs: &[Attribute]) -> Option<String> {
+    let lines = metas(attrs)
+        .into_iter()
+        .filter_map(|meta| match meta {
+            Meta::NameValue(name_value) if name_value.path.is_ident("doc") => {
+                match name_value.lit {
+                    Lit::Str(lit) => Some(lit.value().trim().to_string()),
+                    _ => None,
+                }
+            }
+            _ => None,
+        })
+        .collect::<Vec<_>>();
+    (!lines.is_empty()).then(|| lines.join("\n").trim().to_string())
+}
+
+/// Serde attributes relevant to JSON shape of container, field or variant
+#[derive(Default)]
+struct SerdeAttrs {
+    rename: Option<String>,
+    rename_all: Option<String>,
+    tag: Option<String>,
+    content: Option<String>,
+    untagged: bool,
+    flatten: bool,
+    skip: bool,
+    default: bool,
+}
+
+impl SerdeAttrs {
+    fn parse(attrs: &[Attribute]) -> Self {
+        let mut result = Self::default();
+        for meta in nested_metas(attrs, "serde") {
+            let str_value = |meta: &Meta| match meta {
+                Meta::NameValue(name_value) => match &name_value.lit {
+                    Lit::Str(lit) => Some(lit.value()),
+                    _ => None,
+                },
+                _ => None,
+            };
+            let Some(ident) = meta.path().get_ident().map(ToString::to_string) else {
+                continue;
+            };
+            match ident.as_str() {
+                "rename" => result.rename = str_value(&meta),
+                "rename_all" => result.rename_all = str_value(&meta),
+                "tag" => result.tag = str_value(&meta),
+                "content" => result.content = str_value(&meta),
+                "untagged" => result.untagged = true,
+                "flatten" => result.flatten = true,
+                "skip" | "skip_serializing" | "skip_deserializing" => result.skip = true,
+                "default" | "skip_serializing_if" => result.default = true,
+                _ => {}
+            }
+        }
+        result
+    }
+}
+
+fn apply_rename_all(name: &str, rule: Option<&str>) -> String {
+    let words = || {
+        let mut words = Vec::new();
+        let mut word = String::new();
+        for c in name.chars() {
+            if (c.is_uppercase() && !word.is_empty()) || c == '_' {
+                words.push(std::mem::take(&mut word));
+            }
+            if c != '_' {
+                word.push(c);
+            }
+        }
+        words.push(word);
+        words
+            .into_iter()
+            .filter(|word| !word.is_empty())
+            .map(|word| word.to_lowercase())
+            .collect::<Vec<_>>()
+    };
+    match rule {
+        Some("lowercase") => name.to_lowercase(),
+        Some("UPPERCASE") => name.to_uppercase(),
+        Some("snake_case") => words().join("_"),
+        Some("SCREAMING_SNAKE_CASE") => words().join("_").to_uppercase(),
+        Some("kebab-case") => words().join("-"),
+        Some("SCREAMING-KEBAB-CASE") => words().join("-").to_uppercase(),
+        Some("camelCase") | Some("PascalCase") => {
+            let mut result = words()
+                .into_iter()
+                .map(|word| {
+                    let mut chars = word.chars();
+                    chars.next().map_or(String::new(), |first| {
+                        first.to_uppercase().chain(chars).collect()
+                    })
+                })
+                .collect::<String>();
+            if rule == Some("camelCase") && !result.is_empty() {
+                let first = result.remove(0).to_lowercase().to_string();
+                result.insert_str(0, &first);
+            }
+            result
+        }
+        _ => name.to_string(),
+    }
+}
+
+/// Named items found in crate sources
+#[derive(Default)]
+struct TypeRegistry {
+    /// Serializable structs and enums
+    definitions: HashMap<String, Item>,
+    aliases: HashMap<String, syn::ItemType>,
+}
+
+impl TypeRegistry {
+    fn add_items(&mut self, path: &Path, items: Vec<Item>) {
+        let in_chain = path
+            .components()
+            .any(|component| component.as_os_str() == "chain");
+        for item in items {
+            match item {
+                Item::Struct(ref item_struct) if is_serde_type(&item_struct.attrs) => {
+                    self.add_definition(item_struct.ident.to_string(), item, in_chain);
+                }
+                Item::Enum(ref item_enum) if is_serde_type(&item_enum.attrs) => {
+                    self.add_definition(item_enum.ident.to_string(), item, in_chain);
+                }
+                Item::Type(item_type) => {
+                    self.aliases
+                        .entry(item_type.ident.to_string())
+                        .or_insert(item_type);
+                }
+                Item::Mod(item_mod) => {
+                    if let Some((_, items)) = item_mod.content {
+                        self.add_items(path, items);
+                    }
+                }
+                _ => {}
+            }
+        }
+    }
+
+    /// On name clash, types from `chain` win, as they define WASM API representation
+    fn add_definition(&mut self, name: String, item: Item, in_chain: bool) {
+        if in_chain || !self.definitions.contains_key(&name) {
+            self.definitions.insert(name, item);
+        }
+    }
+}
+
+struct SchemaGenerator {
+    registry: TypeRegistry,
+    definitions: BTreeMap<String, Value>,
+}
+
+impl SchemaGenerator {
+    fn new(registry: TypeRegistry) -> Self {
+        Self {
+            registry,
+            definitions: BTreeMap::new(),
+        }
+    }
+
+    fn method_schema(&mut self, method: &syn::ImplItemMethod) -> Value {
+        let attrs = &method.attrs;
+        let mut is_view = false;
+        let mut args = Vec::new();
+        for input in &method.sig.inputs {
+            match input {
+                FnArg::Receiver(receiver) => {
+                    is_view = receiver.reference.is_some() && receiver.mutability.is_none();
+                }
+                FnArg::Typed(pat_type) => {
+                    if has_attr(&pat_type.attrs, "callback_result")
+                        || has_attr(&pat_type.attrs, "callback_unwrap")
+                    {
+                        continue;
+                    }
+                    let name = match pat_type.pat.as_ref() {
+                        Pat::Ident(pat_ident) => pat_ident.ident.to_string(),
+                        pat => pat.to_token_stream().to_string(),
+                    };
+                    let shape = self.shape(&pat_type.ty, &HashMap::new());
+                    args.push(json!({ "name": name, "schema": shape }));
+                }
+            }
+        }
+        let kind = if has_attr(attrs, "init") {
+            "init"
+        } else if is_view {
+            "view"
+        } else {
+            "call"
+        };
+        let returns = match &method.sig.output {
+            ReturnType::Default => Value::Null,
+            ReturnType::Type(_, ty) => self.shape(ty, &HashMap::new()),
+        };
+
+        let mut schema = Map::new();
+        schema.insert("name".into(), method.sig.ident.to_string().into());
+        schema.insert("kind".into(), kind.into());
+        schema.insert("payable".into(), has_attr(attrs, "payable").into());
+        schema.insert("private".into(), has_attr(attrs, "private").into());
+        let features = features(attrs);
+        if !features.is_empty() {
+            schema.insert("features".into(), features.into());
+        }
+        if let Some(doc) = doc(attrs) {
+            schema.insert("description".into(), doc.into());
+        }
+        schema.insert("args".into(), args.into());
+        schema.insert("returns".into(), returns);
+        schema.into()
+    }
+
+    /// JSON shape of `ty`; `generics` maps names of generic parameters into their shapes
+    fn shape(&mut self, ty: &Type, generics: &HashMap<String, Value>) -> Value {
+        match ty {
+            Type::Reference(reference) => self.shape(&reference.elem, generics),
+            Type::Paren(paren) => self.shape(&paren.elem, generics),
+            Type::Group(group) => self.shape(&group.elem, generics),
+            Type::Slice(slice) => json!({
+                "type": "array",
+                "items": self.shape(&slice.elem, generics),
+            }),
+            Type::Array(array) => {
+                let items = self.shape(&array.elem, generics);
+                match &array.len {
+                    syn::Expr::Lit(syn::ExprLit {
+                        lit: Lit::Int(len), ..
+                    }) => {
+                        let len: u64 = len.base10_parse().expect("Array length must be integer");
+                        json!({ "type": "array", "items": items, "minItems": len, "maxItems": len })
+                    }
+                    len => json!({
+                        "type": "array",
+                        "items": items,
+                        "length": len.to_token_stream().to_string(),
+                    }),
+                }
+            }
+            Type::Tuple(tuple) if tuple.elems.is_empty() => json!({ "type": "null" }),
+            Type::Tuple(tuple) => {
+                let items = tuple
+                    .elems
+                    .iter()
+                    .map(|elem| self.shape(elem, generics))
+                    .collect::<Vec<_>>();
+                json!({ "type": "array", "prefixItems": items })
+            }
+            Type::Path(type_path) => {
+                let Some(segment) = type_path.path.segments.last() else {
+                    return unknown(ty);
+                };
+                let type_args: Vec<Type> = match &segment.arguments {
+                    PathArguments::AngleBracketed(args) => args
+                        .args
+                        .iter()
+                        .filter_map(|arg| match arg {
+                            GenericArgument::Type(ty) => Some(ty.clone()),
+                            _ => None,
+                        })
+                        .collect(),
+                    _ => Vec::new(),
+                };
+                self.path_shape(&segment.ident.to_string(), &type_args, ty, generics)
+            }
+            _ => unknown(ty),
+        }
+    }
+
+    fn path_shape(
+        &mut self,
+        name: &str,
+        type_args: &[Type],
+        ty: &Type,
+        generics: &HashMap<String, Value>,
+    ) -> Value {
+        if let Some(shape) = generics.get(name) {
+            return shape.clone();
+        }
+        let mut arg = |index: usize| match type_args.get(index) {
+            Some(arg) => self.shape(arg, generics),
+            None => unknown(ty),
+        };
+        match name {
+            "bool" => json!({ "type": "boolean" }),
+            "u8" | "u16" | "u32" | "u64" | "u128" | "usize" | "i8" | "i16" | "i32" | "i64"
+            | "i128" | "isize" => json!({ "type": "integer", "format": name }),
+            "f32" | "f64" => json!({ "type": "number", "format": name }),
+            "String" | "str" => json!({ "type": "string" }),
+            "AccountId" | "TokenId" => json!({ "type": "string", "format": "account_id" }),
+            "U128" | "WasmAmount" => json!({ "type": "string", "format": "u128" }),
+            "U64" => json!({ "type": "string", "format": "u64" }),
+            "I128" => json!({ "type": "string", "format": "i128" }),
+            "I64" => json!({ "type": "string", "format": "i64" }),
+            "LiquidityHex" => json!({ "type": "string", "format": "u192x64_hex" }),
+            "Base58CryptoHash" => json!({ "type": "string", "format": "base58" }),
+            "Base64VecU8" => json!({ "type": "string", "format": "base64" }),
+            "Box" | "Rc" | "Arc" | "PromiseOrValue" => arg(0),
+            "Promise" => json!({ "type": "promise" }),
+            "Option" => json!({ "oneOf": [arg(0), { "type": "null" }] }),
+            "Vec" | "VecDeque" | "HashSet" | "BTreeSet" => {
+                json!({ "type": "array", "items": arg(0) })
+            }
+            "HashMap" | "BTreeMap" => json!({ "type": "object", "additionalProperties": arg(1) }),
+            _ => self.named_shape(name, type_args, ty, generics),
+        }
+    }
+
+    fn named_shape(
+        &mut self,
+        name: &str,
+        type_args: &[Type],
+        ty: &Type,
+        generics: &HashMap<String, Value>,
+    ) -> Value {
+        if let Some(item) = self.registry.definitions.get(name).cloned() {
+            let (item_generics, attrs) = match &item {
+                Item::Struct(item_struct) => (&item_struct.generics, &item_struct.attrs),
+                Item::Enum(item_enum) => (&item_enum.generics, &item_enum.attrs),
+                _ => unreachable!("Only structs and enums are registered"),
+            };
+            let key = if type_args.is_empty() {
+                name.to_string()
+            } else {
+                ty.to_token_stream().to_string().replace(' ', "")
+            };
+            if !self.definitions.contains_key(&key) {
+                // Placeholder allows recursive types
+                self.definitions.insert(key.clone(), Value::Null);
+                let item_generics = self.bind_generics(item_generics, type_args, generics);
+                let mut definition = match &item {
+                    Item::Struct(item_struct) => {
+                        let rename_all = SerdeAttrs::parse(attrs).rename_all;
+                        self.fields_shape(
+                            &item_struct.fields,
+                            rename_all.as_deref(),
+                            &item_generics,
+                        )
+                    }
+                    Item::Enum(item_enum) => self.enum_shape(item_enum, &item_generics),
+                    _ => unreachable!(),
+                };
+                if let (Some(doc), Value::Object(definition)) = (doc(attrs), &mut definition) {
+                    definition.insert("description".into(), doc.into());
+                }
+                self.definitions.insert(key.clone(), definition);
+            }
+            return json!({ "$ref": format!("#/definitions/{key}") });
+        }
+        if let Some(alias) = self.registry.aliases.get(name).cloned() {
+            let alias_generics = self.bind_generics(&alias.generics, type_args, generics);
+            return self.shape(&alias.ty, &alias_generics);
+        }
+        unknown(ty)
+    }
+
+    fn bind_generics(
+        &mut self,
+        item_generics: &Generics,
+        type_args: &[Type],
+        generics: &HashMap<String, Value>,
+    ) -> HashMap<String, Value> {
+        item_generics
+            .params
+            .iter()
+            .filter_map(|param| match param {
+                GenericParam::Type(type_param) => Some(type_param.ident.to_string()),
+                _ => None,
+            })
+            .zip(type_args)
+            .map(|(param, arg)| (param, self.shape(arg, generics)))
+            .collect()
+    }
+
+    /// Shape of struct or enum variant fields, with names of fields renamed by `rename_all` rule
+    fn fields_shape(
+        &mut self,
+        fields: &Fields,
+        rename_all: Option<&str>,
+        generics: &HashMap<String, Value>,
+    ) -> Value {
+        match fields {
+            Fields::Unit => json!({ "type": "null" }),
+            Fields::Unnamed(unnamed) if unnamed.unnamed.len() == 1 => {
+                self.shape(&unnamed.unnamed[0].ty, generics)
+            }
+            Fields::Unnamed(unnamed) => {
+                let items = unnamed
+                    .unnamed
+                    .iter()
+                    .map(|field| self.shape(&field.ty, generics))
+                    .collect::<Vec<_>>();
+                json!({ "type": "array", "prefixItems": items })
+            }
+            Fields::Named(named) => {
+                let mut properties = Map::new();
+                let mut required = Vec::new();
+                let mut flattened = Vec::new();
+                for field in &named.named {
+                    let serde_attrs = SerdeAttrs::parse(&field.attrs);
+                    if serde_attrs.skip {
+                        continue;
+                    }
+                    let mut shape = self.shape(&field.ty, generics);
+                    if serde_attrs.flatten {
+                        flattened.push(shape);
+                        continue;
+                    }
+                    let ident = field.ident.as_ref().expect("Named field").to_string();
+                    let name = serde_attrs
+                        .rename
+                        .unwrap_or_else(|| apply_rename_all(&ident, rename_all));
+                    let is_option = shape.get("oneOf").map_or(false, |one_of| {
+                        one_of.as_array().map_or(false, |variants| {
+                            variants.last() == Some(&json!({ "type": "null" }))
+                        })
+                    });
+                    if !is_option && !serde_attrs.default {
+                        required.push(Value::from(name.clone()));
+                    }
+                    if let (Some(doc), Value::Object(shape)) = (doc(&field.attrs), &mut shape) {
+                        shape.insert("description".into(), doc.into());
+                    }
+                    properties.insert(name, shape);
+                }
+                let object = json!({
+                    "type": "object",
+                    "properties": properties,
+                    "required": required,
+                });
+                if flattened.is_empty() {
+                    object
+                } else {
+                    flattened.insert(0, object);
+                    json!({ "allOf": flattened })
+                }
+            }
+        }
+    }
+
+    fn enum_shape(
+        &mut self,
+        item_enum: &syn::ItemEnum,
+        generics: &HashMap<String, Value>,
+    ) -> Value {
+        let container = SerdeAttrs::parse(&item_enum.attrs);
+        let mut variants = Vec::new();
+        for variant in &item_enum.variants {
+            let variant_attrs = SerdeAttrs::parse(&variant.attrs);
+            if variant_attrs.skip {
+                continue;
+            }
+            let name = variant_attrs.rename.unwrap_or_else(|| {
+                apply_rename_all(&variant.ident.to_string(), container.rename_all.as_deref())
+            });
+            let is_unit = matches!(variant.fields, Fields::Unit);
+            let content = self.fields_shape(
+                &variant.fields,
+                variant_attrs.rename_all.as_deref(),
+                generics,
+            );
+            let mut shape = if container.untagged {
+                content
+            } else {
+                match (&container.tag, &container.content) {
+                    (None, _) if is_unit => json!({ "const": name }),
+                    (None, _) => json!({
+                        "type": "object",
+                        "properties": { name.clone(): content },
+                        "required": [name],
+                    }),
+                    (Some(tag), None) if is_unit => json!({
+                        "type": "object",
+                        "properties": { tag.clone(): { "const": name } },
+                        "required": [tag],
+                    }),
+                    (Some(tag), None) => json!({
+                        "allOf": [
+                            {
+                                "type": "object",
+                                "properties": { tag.clone(): { "const": name } },
+                                "required": [tag],
+                            },
+                            content,
+                        ]
+                    }),
+                    (Some(tag), Some(content_name)) => {
+                        let mut required = vec![tag.clone()];
+                        if !is_unit {
+                            required.push(content_name.clone());
+                        }
+                        json!({
+                            "type": "object",
+                            "properties": {
+                                tag.clone(): { "const": name },
+                                content_name.clone(): content,
+                            },
+                            "required": required,
+                        })
+                    }
+                }
+            };
+            if let (Some(doc), Value::Object(shape)) = (doc(&variant.attrs), &mut shape) {
+                shape.insert("description".into(), doc.into());
+            }
+            variants.push(shape);
+        }
+        json!({ "oneOf": variants })
+    }
+}
+
+/// Features required by `#[cfg(feature = "...")]` attributes
+fn features(attrs: &[Attribute]) -> Vec<String> {
+    attrs
+        .iter()
+        .filter(|attr| attr.path.is_ident("cfg"))
+        .filter_map(|attr| match attr.parse_meta() {
+            Ok(Meta::List(list)) => Some(list.nested),
+            _ => None,
+        })
+        .flatten()
+        .filter_map(|nested| match nested {
+            NestedMeta::Meta(Meta::NameValue(name_value))
+                if name_value.path.is_ident("feature") =>
+            {
+                match name_value.lit {
+                    Lit::Str(lit) => Some(lit.value()),
+                    _ => None,
+                }
+            }
+            _ => None,
+        })
+        .collect()
+}
+
+fn unknown(ty: &Type) -> Value {
+    let rust = ty.to_token_stream().to_string();
+    eprintln!("Warning: could not resolve JSON shape of `{rust}`");
+    json!({ "type": "unknown", "rust": rust })
+}
@@ -0,0 +1,272 @@
+//! Command-line administration of deployed contract, for owner and guard flows.
+//!
+//! Calls are signed with full-access key of `--signer`, read from NEAR credentials directory
+//! (`~/.near-credentials/<network>/<signer>.json`, as written by `near login`).
+//! Events emitted by the call are decoded and printed, one JSON object per line.
+//!
+//! Usage: `cargo run --features admin-cli --bin veax-admin -- \
+//!     [--network testnet|mainnet] [--rpc <url>] --contract <id> --signer <id> <command> [args...]`
+//!
+//! Commands:
+//! * `suspend`, `resume` - suspend or resume payable API; owner or guard
+//! * `add-verified-tokens <token>...`, `remove-verified-tokens <token>...` - owner only
+//! * `set-protocol-fee-fraction <basis points>` - owner only
+//! * `top-pools <token>` - view top pools of token; contract keeps rankings up to date itself,
+//!   so there's nothing to refresh, but operators may check them after liquidity changes
+use near_crypto::{InMemorySigner, SecretKey};
+use near_jsonrpc_client::{methods, JsonRpcClient};
+use near_jsonrpc_primitives::types::query::QueryResponseKind;
+use near_primitives::transaction::{Action, FunctionCallAction, Transaction};
+use near_primitives::types::{AccountId, BlockReference, FunctionArgs};
+use near_primitives::views::{FinalExecutionStatus, QueryRequest};
+use serde::Deserialize;
+use serde_json::{json, Value};
+use std::path::PathBuf;
+
+type Result<T> = std::result::Result<T, Box<dyn std::error::Error>>;
+
+const EVENT_PREFIX: &str = "EVENT_JSON:";
+/// Gas attached to administrative calls, 100 Tgas
+const CALL_GAS: u64 = 100_000_000_000_000;
+/// Administrative methods require exactly one yoctoNEAR attached
+const ONE_YOCTO: u128 = 1;
+
+/// Key file written by `near login`
+#[derive(Deserialize)]
+struct KeyFile {
+    account_id: AccountId,
+    #[serde(alias = "secret_key")]
+    private_key: SecretKey,
+}
+
+struct Args {
+    network: String,
+    rpc_url: Option<String>,
+    contract_id: AccountId,
+    signer_id: AccountId,
+    command: String,
+    params: Vec<String>,
+}
+
+enum Command {
+    Call { method: &'static str, args: Value },
+    View { method: &'static str, args: Value },
+}
+
+#[tokio::main]
+async fn main() {
+    if let Err(e) = run().await {
+        eprintln!("Error: {e}");
+        std::process::exit(1);
+    }
+}
+
+async fn run() -> Result<()> {
+    let args = parse_args(std::env::args().skip(1).collect())?;
+    let rpc_url = args
+        .rpc_url
+        .clone()
+        .unwrap_or_else(|| format!("https://rpc.{}.near.org", args.network));
+    let client = JsonRpcClient::connect(rpc_url);
+
+    match parse_command(&args.command, &args.params)? {
+        Command::Call {
+            method,
+            args: call_args,
+        } => {
+            let signer = load_signer(&args.network, &args.signer_id)?;
+            call(&client, &signer, &args.contract_id, method, call_args).await
+        }
+        Command::View {
+            method,
+            args: view_args,
+        } => view(&client, &args.contract_id, method, view_args).await,
+    }
+}
+
+fn parse_args(raw: Vec<String>) -> Result<Args> {
+    let mut network = "testnet".to_string();
+    let mut rpc_url = None;
+    let mut contract_id = None;
+    let mut signer_id = None;
+    let mut rest = Vec::new();
+
+    let mut iter = raw.into_iter();
+    while let Some(arg) = iter.next() {
+        let mut value = || iter.next().ok_or(format!("Missing value of {arg}"));
+        match arg.as_str() {
+            "--network" => network = value()?,
+            "--rpc" => rpc_url = Some(value()?),
+            "--contract" => contract_id = Some(value()?.parse()?),
+            "--signer" => signer_id = Some(value()?.parse()?),
+            _ => rest.push(arg),
+        }
+    }
+    let mut rest = rest.into_iter();
+    Ok(Args {
+        network,
+        rpc_url,
+        contract_id: contract_id.ok_or("Please specify --contract")?,
+        signer_id: signer_id.ok_or("Please specify --signer")?,
+        command: rest.next().ok_or("Please specify command")?,
+        params: rest.collect(),
+    })
+}
+
+fn parse_command(command: &str, params: &[String]) -> Result<Command> {
+    let single = || match params {
+        [param] => Ok(param.clone()),
+        _ => Err(format!("Command {command} takes exactly one argument")),
+    };
+    let non_empty = || {
+        if params.is_empty() {
+            Err(format!("Command {command} takes at least one argument"))
+        } else {
+            Ok(params.to_vec())
+        }
+    };
+    Ok(match command {
+        "suspend" => Command::Call {
+            method: "suspend_payable_api",
+            args: json!({}),
+        },
+        "resume" => Command::Call {
+            method: "resume_payable_api",
+            args: json!({}),
+        },
+        "add-verified-tokens" => Command::Call {
+            method: "extend_verified_tokens",
+            args: json!({ "tokens": non_empty()? }),
+        },
+        "remove-verified-tokens" => Command::Call {
+            method: "remove_verified_tokens",
+            args: json!({ "tokens": non_empty()? }),
+        },
+        "set-protocol-fee-fraction" => Command::Call {
+            method: "set_protocol_fee_fraction",
+            args: json!({ "protocol_fee_fraction": single()?.parse::<u16>()? }),
+        },
+        "top-pools" => Command::View {
+            method: "get_token_top_pools",
+            args: json!({ "token": single()? }),
+        },
+        _ => return Err(format!("Unknown command {command}").into()),
+    })
+}
+
+fn load_signer(network: &str, signer_id: &AccountId) -> Result<InMemorySigner> {
+    let home = std::env::var("HOME").map_err(|_| "HOME is not set")?;
+    let path: PathBuf = [
+        home.as_str(),
+        ".near-credentials",
+        network,
+        &format!("{signer_id}.json"),
+    ]
+    .iter()
+    .collect();
+    let key_file: KeyFile = serde_json::from_slice(
+        &std::fs::read(&path).map_err(|e| format!("Can't read {}: {e}", path.display()))?,
+    )?;
+    if key_file.account_id != *signer_id {
+        return Err(format!("{} holds key of {}", path.display(), key_file.account_id).into());
+    }
+    Ok(InMemorySigner::from_secret_key(
+        key_file.account_id,
+        key_file.private_key,
+    ))
+}
+
+async fn call(
+    client: &JsonRpcClient,
+    signer: &InMemorySigner,
+    contract_id: &AccountId,
+    method: &str,
+    args: Value,
+) -> Result<()> {
+    let access_key = client
+        .call(methods::query::RpcQueryRequest {
+            block_reference: BlockReference::latest(),
+            request: QueryRequest::ViewAccessKey {
+                account_id: signer.account_id.clone(),
+                public_key: signer.public_key.clone(),
+            },
+        })
+        .await?;
+    let QueryResponseKind::AccessKey(access_key_view) = access_key.kind else {
+        return Err("Unexpected response to access key query".into());
+    };
+
+    let transaction = Transaction {
+        signer_id: signer.account_id.clone(),
+        public_key: signer.public_key.clone(),
+        nonce: access_key_view.nonce + 1,
+        receiver_id: contract_id.clone(),
+        block_hash: access_key.block_hash,
+        actions: vec![Action::FunctionCall(FunctionCallAction {
+            method_name: method.to_string(),
+            args: serde_json::to_vec(&args)?,
+            gas: CALL_GAS,
+            deposit: ONE_YOCTO,
+        })],
+    };
+    let outcome = client
+        .call(methods::broadcast_tx_commit::RpcBroadcastTxCommitRequest {
+            signed_transaction: transaction.sign(signer),
+        })
+        .await?;
+
+    for receipt in &outcome.receipts_outcome {
+        for line in &receipt.outcome.logs {
+            print_log_line(line);
+        }
+    }
+    match outcome.status {
+        FinalExecutionStatus::SuccessValue(_) => {
+            eprintln!("Transaction {} succeeded", outcome.transaction.hash);
+            Ok(())
+        }
+        FinalExecutionStatus::Failure(e) => {
+            Err(format!("Transaction {} failed: {e}", outcome.transaction.hash).into())
+        }
+        status => Err(format!(
+            "Transaction {} has status {status:?}",
+            outcome.transaction.hash
+        )
+        .into()),
+    }
+}
+
+async fn view(
+    client: &JsonRpcClient,
+    contract_id: &AccountId,
+    method: &str,
+    args: Value,
+) -> Result<()> {
+    let response = client
+        .call(methods::query::RpcQueryRequest {
+            block_reference: BlockReference::latest(),
+            request: QueryRequest::CallFunction {
+                account_id: contract_id.clone(),
+                method_name: method.to_string(),
+                args: FunctionArgs::from(serde_json::to_vec(&args)?),
+            },
+        })
+        .await?;
+    let QueryResponseKind::CallResult(result) = response.kind else {
+        return Err("Unexpected response to view call".into());
+    };
+    let value: Value = serde_json::from_slice(&result.result)?;
+    println!("{}", serde_json::to_string_pretty(&value)?);
+    Ok(())
+}
+
+/// Print event as single-line JSON, and other logs as they are, to stderr
+fn print_log_line(line: &str) {
+    match line
+        .strip_prefix(EVENT_PREFIX)
+        .and_then(|event| serde_json::from_str::<Value>(event).ok())
+    {
+        Some(event) => println!("{event}"),
+        None => eprintln!("{line}"),
+    }
+}
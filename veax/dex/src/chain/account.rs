@@ -1,6 +1,7 @@
 //! Account deposit is information per user about their balances in the exchange.
-use super::Error;
-use crate::dex::{self, AccountExtra, Result};
+use super::types::{StorageBreakdown, StorageOperationCosts};
+use super::{ext_wrap_near, wnear_id, Error, GAS_FOR_NEAR_WITHDRAW};
+use crate::dex::{self, AccountExtra, Map, Result};
 use crate::{ensure_here, error_here};
 use near_contract_standards::storage_management::StorageBalance;
 use near_sdk::borsh::{self, BorshDeserialize, BorshSerialize};
@@ -11,8 +12,28 @@ use near_sdk::{env, Balance, StorageUsage};
 pub const INIT_ACCOUNT_STORAGE: StorageUsage = 366;
 pub const TOKEN_REGISTER_STORAGE: StorageUsage = 284;
 
-pub const CREATE_POOL_STORAGE: StorageUsage = 4457;
-pub const OPEN_POSITION_STORAGE: StorageUsage = 1087;
+// Estimates quoted by `storage_operation_costs`; accounts are charged for storage
+// actually taken by pools and positions, which is measured during operations
+pub const CREATE_POOL_STORAGE: StorageUsage = 6255;
+pub const OPEN_POSITION_STORAGE: StorageUsage = 1156;
+// estimate which positions opened before their storage was measured are charged by
+const LEGACY_OPEN_POSITION_STORAGE: StorageUsage = 1087;
+// estimated for maximal AccountId length of owner and both tokens
+pub const PLACE_ORDER_STORAGE: StorageUsage = 512;
+// estimated for maximal AccountId length of owner, and maximal name and metadata lengths
+pub const REGISTER_INTEGRATION_STORAGE: StorageUsage = 1280;
+// estimated for maximal AccountId lengths, and maximal numbers of legs and actions
+pub const PREPARE_DEPOSIT_INTENT_STORAGE: StorageUsage = 3072;
+// estimated for maximal AccountId length of owner
+pub const SET_AUTO_HARVEST_STORAGE: StorageUsage = 128;
+// estimated for maximal AccountId lengths of granting account and viewer
+pub const GRANT_VIEW_ACCESS_STORAGE: StorageUsage = 160;
+// estimated for maximal numbers of vault thresholds and delayed withdrawals
+pub const SET_VAULT_STORAGE: StorageUsage = 2560;
+// estimated for maximal numbers of volume caps and string length of public key
+pub const REGISTER_SESSION_KEY_STORAGE: StorageUsage = 1024;
+// estimated for maximal AccountId length of token and its neighbours in checked tokens list
+pub const CHECK_TOKEN_INTERFACE_STORAGE: StorageUsage = 320;
 
 /// Account deposits information and storage cost.
 #[derive(Default, BorshSerialize, BorshDeserialize)]
@@ -20,18 +41,66 @@ pub struct Extra {
     /// Native NEAR amount sent to the exchange.
     /// Used for storage right now, but in future can be used for trading as well.
     pub near_amount: Balance,
+    /// If set, storage shortfall is automatically covered from account's wNEAR balance
+    pub storage_auto_top_up: bool,
+    /// Storage taken by account's open positions, in bytes, as measured when they were opened
+    pub positions_storage: StorageUsage,
+}
+
+/// Account deposits information stored before account version 1
+#[derive(BorshSerialize, BorshDeserialize)]
+pub struct ExtraV0 {
+    pub near_amount: Balance,
+}
+
+impl From<ExtraV0> for Extra {
+    fn from(legacy: ExtraV0) -> Self {
+        Self {
+            near_amount: legacy.near_amount,
+            ..Self::default()
+        }
+    }
+}
+
+/// Returns amounts of $NEAR necessary to cover storage of operations which occupy additional storage.
+pub(crate) fn storage_operation_costs() -> StorageOperationCosts {
+    let cost = |bytes: StorageUsage| U128(u128::from(bytes) * env::storage_byte_cost());
+    StorageOperationCosts {
+        register_token: cost(TOKEN_REGISTER_STORAGE),
+        open_position: cost(OPEN_POSITION_STORAGE),
+        create_pool: cost(CREATE_POOL_STORAGE),
+        place_order: cost(PLACE_ORDER_STORAGE),
+        register_integration: cost(REGISTER_INTEGRATION_STORAGE),
+        prepare_deposit_intent: cost(PREPARE_DEPOSIT_INTENT_STORAGE),
+        set_auto_harvest: cost(SET_AUTO_HARVEST_STORAGE),
+        grant_view_access: cost(GRANT_VIEW_ACCESS_STORAGE),
+        set_vault: cost(SET_VAULT_STORAGE),
+        register_session_key: cost(REGISTER_SESSION_KEY_STORAGE),
+        check_token_interface: cost(CHECK_TOKEN_INTERFACE_STORAGE),
+    }
 }
 
 impl dex::AccountLatest<super::Types> {
     /// Returns amount of $NEAR necessary to cover storage used by this data structure.
     pub(crate) fn storage_usage(&self) -> Balance {
+        let breakdown = self.storage_breakdown();
         u128::from(
-            INIT_ACCOUNT_STORAGE
-                + self.token_balances.len() as u64 * TOKEN_REGISTER_STORAGE
-                + self.positions.len() * OPEN_POSITION_STORAGE,
+            breakdown.base_account_bytes.0
+                + breakdown.token_registrations_bytes.0
+                + breakdown.positions_bytes.0,
         ) * env::storage_byte_cost()
     }
 
+    /// Returns storage used by this data structure, split by what occupies it.
+    pub(crate) fn storage_breakdown(&self) -> StorageBreakdown {
+        StorageBreakdown {
+            base_account_bytes: INIT_ACCOUNT_STORAGE.into(),
+            token_registrations_bytes: (self.token_balances.len() as u64 * TOKEN_REGISTER_STORAGE)
+                .into(),
+            positions_bytes: self.extra.positions_storage.into(),
+        }
+    }
+
     /// Returns how much NEAR is available for storage.
     pub(crate) fn storage_available(&self) -> Balance {
         self.extra.near_amount.saturating_sub(self.storage_usage())
@@ -46,6 +115,36 @@ impl dex::AccountLatest<super::Types> {
         Ok(())
     }
 
+    /// If account opted into storage auto-top-up, covers storage shortfall from account's wNEAR balance.
+    /// Withdrawn wNEAR is unwrapped, so that storage is backed by native NEAR.
+    ///
+    /// Does nothing if wNEAR balance is insufficient, so that operation fails with `InsufficientStorage`.
+    pub(crate) fn top_up_storage_from_wnear(&mut self) -> Result<()> {
+        if !self.extra.storage_auto_top_up {
+            return Ok(());
+        }
+        let shortfall = self.storage_usage().saturating_sub(self.extra.near_amount);
+        if shortfall == 0 {
+            return Ok(());
+        }
+        let wnear_id = wnear_id();
+        let Some(wnear_balance) = self.token_balances.inspect(&wnear_id, |balance| *balance) else {
+            return Ok(());
+        };
+        if wnear_balance < shortfall {
+            return Ok(());
+        }
+        self.withdraw(&wnear_id, shortfall)
+            .map_err(|e| error_here!(e))?;
+        self.extra.near_amount += shortfall;
+        // Promise is not returned, so it's detached from current call chain
+        ext_wrap_near::ext(wnear_id)
+            .with_attached_deposit(1)
+            .with_static_gas(GAS_FOR_NEAR_WITHDRAW)
+            .near_withdraw(U128(shortfall));
+        Ok(())
+    }
+
     /// Returns minimal account deposit storage usage possible.
     pub(crate) fn min_storage_usage() -> Balance {
         u128::from(INIT_ACCOUNT_STORAGE) * env::storage_byte_cost()
@@ -60,10 +159,117 @@ impl dex::AccountLatest<super::Types> {
 }
 
 impl AccountExtra for Extra {
-    fn on_pool_created(&mut self) -> Result<()> {
+    fn on_pool_created(&mut self, storage_bytes: StorageUsage) -> Result<Balance> {
+        let cost = u128::from(storage_bytes) * env::storage_byte_cost();
+        self.near_amount = self
+            .near_amount
+            .checked_sub(cost)
+            .ok_or(error_here!(Error::InsufficientStorage))?;
+        Ok(cost)
+    }
+
+    fn on_pool_storage_refunded(&mut self, amount: Balance) {
+        self.near_amount += amount;
+    }
+
+    fn on_position_opened(&mut self, storage_bytes: StorageUsage) {
+        self.positions_storage += storage_bytes;
+    }
+
+    fn on_position_closed(&mut self, storage_bytes: StorageUsage) {
+        // Position may release more than it took, e.g. when it was the last one to use its ticks
+        self.positions_storage = self.positions_storage.saturating_sub(storage_bytes);
+    }
+
+    fn on_migrated(&mut self, position_count: usize) {
+        // Positions opened before their storage was measured are charged by former estimate
+        self.positions_storage = position_count as StorageUsage * LEGACY_OPEN_POSITION_STORAGE;
+    }
+
+    fn on_order_placed(&mut self) -> Result<()> {
+        self.near_amount = self
+            .near_amount
+            .checked_sub(u128::from(PLACE_ORDER_STORAGE) * env::storage_byte_cost())
+            .ok_or(error_here!(Error::InsufficientStorage))?;
+        Ok(())
+    }
+
+    fn on_order_removed(&mut self) {
+        self.near_amount += u128::from(PLACE_ORDER_STORAGE) * env::storage_byte_cost();
+    }
+
+    fn on_integration_registered(&mut self) -> Result<()> {
+        self.near_amount = self
+            .near_amount
+            .checked_sub(u128::from(REGISTER_INTEGRATION_STORAGE) * env::storage_byte_cost())
+            .ok_or(error_here!(Error::InsufficientStorage))?;
+        Ok(())
+    }
+
+    fn on_deposit_intent_prepared(&mut self) -> Result<()> {
+        self.near_amount = self
+            .near_amount
+            .checked_sub(u128::from(PREPARE_DEPOSIT_INTENT_STORAGE) * env::storage_byte_cost())
+            .ok_or(error_here!(Error::InsufficientStorage))?;
+        Ok(())
+    }
+
+    fn on_deposit_intent_removed(&mut self) {
+        self.near_amount += u128::from(PREPARE_DEPOSIT_INTENT_STORAGE) * env::storage_byte_cost();
+    }
+
+    fn on_auto_harvest_set(&mut self) -> Result<()> {
+        self.near_amount = self
+            .near_amount
+            .checked_sub(u128::from(SET_AUTO_HARVEST_STORAGE) * env::storage_byte_cost())
+            .ok_or(error_here!(Error::InsufficientStorage))?;
+        Ok(())
+    }
+
+    fn on_auto_harvest_removed(&mut self) {
+        self.near_amount += u128::from(SET_AUTO_HARVEST_STORAGE) * env::storage_byte_cost();
+    }
+
+    fn on_view_access_granted(&mut self) -> Result<()> {
+        self.near_amount = self
+            .near_amount
+            .checked_sub(u128::from(GRANT_VIEW_ACCESS_STORAGE) * env::storage_byte_cost())
+            .ok_or(error_here!(Error::InsufficientStorage))?;
+        Ok(())
+    }
+
+    fn on_view_access_revoked(&mut self) {
+        self.near_amount += u128::from(GRANT_VIEW_ACCESS_STORAGE) * env::storage_byte_cost();
+    }
+
+    fn on_vault_set(&mut self) -> Result<()> {
+        self.near_amount = self
+            .near_amount
+            .checked_sub(u128::from(SET_VAULT_STORAGE) * env::storage_byte_cost())
+            .ok_or(error_here!(Error::InsufficientStorage))?;
+        Ok(())
+    }
+
+    fn on_vault_removed(&mut self) {
+        self.near_amount += u128::from(SET_VAULT_STORAGE) * env::storage_byte_cost();
+    }
+
+    fn on_session_key_registered(&mut self) -> Result<()> {
+        self.near_amount = self
+            .near_amount
+            .checked_sub(u128::from(REGISTER_SESSION_KEY_STORAGE) * env::storage_byte_cost())
+            .ok_or(error_here!(Error::InsufficientStorage))?;
+        Ok(())
+    }
+
+    fn on_session_key_revoked(&mut self) {
+        self.near_amount += u128::from(REGISTER_SESSION_KEY_STORAGE) * env::storage_byte_cost();
+    }
+
+    fn on_token_interface_check_requested(&mut self) -> Result<()> {
         self.near_amount = self
             .near_amount
-            .checked_sub(u128::from(CREATE_POOL_STORAGE) * env::storage_byte_cost())
+            .checked_sub(u128::from(CHECK_TOKEN_INTERFACE_STORAGE) * env::storage_byte_cost())
             .ok_or(error_here!(Error::InsufficientStorage))?;
         Ok(())
     }
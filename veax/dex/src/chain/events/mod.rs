@@ -1,14 +1,308 @@
 use std::fmt::{format, Arguments};
 
 use crate::chain::log::log_str;
-use crate::chain::{AccountId, Amount, TokenId};
+use crate::chain::{AccountId, Amount, Liquidity, LiquidityHex, TokenId};
 use crate::dex;
 use crate::dex::latest::RawFeeLevelsArray;
-use crate::dex::PoolUpdateReason;
-use near_sdk::json_types::{U128, U64};
-use serde::Serialize;
+use crate::dex::tick::Tick;
+use crate::dex::{CodeHash, PoolUpdateReason};
+use near_sdk::json_types::{Base58CryptoHash, U128, U64};
+use serde::{Deserialize, Serialize};
 
-pub(super) struct Logger;
+/// Emits events as NEAR event logs, skipping kinds disabled in contract state
+pub(crate) struct Logger {
+    filter: dex::EventFilter,
+}
+
+impl Logger {
+    pub(crate) const fn new() -> Self {
+        Self {
+            filter: dex::EventFilter::new(),
+        }
+    }
+
+    /// Applies event filter stored in contract state
+    pub(crate) fn set_filter(&mut self, filter: dex::EventFilter) {
+        self.filter = filter;
+    }
+
+    fn emit(&self, event: Event<'_>) {
+        if event
+            .kind()
+            .map_or(true, |kind| self.filter.is_enabled(kind))
+        {
+            write_event(event);
+        }
+    }
+
+    pub(crate) fn log_storage_balance_event(
+        &mut self,
+        user: &AccountId,
+        available: &Amount,
+        total: &Amount,
+    ) {
+        self.emit(Event::StorageBalance {
+            user,
+            available: (*available).into(),
+            total: (*total).into(),
+        });
+    }
+}
+
+/// Name of events standard, reported in each event
+pub const EVENTS_STANDARD: &str = "veax";
+
+/// Version of events schema emitted by current build.
+///
+/// Bumping it enables all additive fields introduced up to that version.
+/// Fields are never removed or changed, so indexers which understand older version
+/// can safely ignore unknown fields.
+pub const EVENTS_VERSION: EventsVersion = EventsVersion::V1_22_0;
+
+/// Changelog of events schema versions, ordered from oldest to newest
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[cfg_attr(not(target_arch = "wasm32"), derive(Debug))]
+pub enum EventsVersion {
+    /// Initial events schema
+    #[serde(rename = "1.0.0")]
+    V1_0_0,
+    /// `close_position` and `harvest_fee` events carry `user` which owns position
+    #[serde(rename = "1.1.0")]
+    V1_1_0,
+    /// `update_pool_state` event carries per-level LP fees earned, top active level and active side
+    #[serde(rename = "1.2.0")]
+    V1_2_0,
+    /// `lock_position` and `unlock_position` events
+    #[serde(rename = "1.3.0")]
+    V1_3_0,
+    /// `action_scheduled`, `action_executed` and `action_cancelled` events
+    #[serde(rename = "1.4.0")]
+    V1_4_0,
+    /// `action_approved` event
+    #[serde(rename = "1.5.0")]
+    V1_5_0,
+    /// `pool_storage_cleanup` and `token_registrations_cleanup` events
+    #[serde(rename = "1.6.0")]
+    V1_6_0,
+    /// `ban_tokens` and `unban_tokens` events
+    #[serde(rename = "1.7.0")]
+    V1_7_0,
+    /// `open_position` event carries exact accounted liquidity
+    #[serde(rename = "1.8.0")]
+    V1_8_0,
+    /// `open_position` event carries position geometry: fee level, ticks, net liquidity
+    /// and initial sqrtprice
+    #[serde(rename = "1.9.0")]
+    V1_9_0,
+    /// `withdraw_protocol_fee` and `owner_withdraw` events
+    #[serde(rename = "1.10.0")]
+    V1_10_0,
+    /// `set_protocol_fee_fraction` and `config_change` events, guard events carry `caller`
+    #[serde(rename = "1.11.0")]
+    V1_11_0,
+    /// `update_pool_state_delta` event replaces `update_pool_state` after swaps
+    #[serde(rename = "1.12.0")]
+    V1_12_0,
+    /// `move_position` event
+    #[serde(rename = "1.13.0")]
+    V1_13_0,
+    /// `order_placed`, `order_executed` and `order_cancelled` events
+    #[serde(rename = "1.14.0")]
+    V1_14_0,
+    /// `fee_auction_update` and `fee_auction_take` events
+    #[serde(rename = "1.15.0")]
+    V1_15_0,
+    /// `transfer_internal` event
+    #[serde(rename = "1.16.0")]
+    V1_16_0,
+    /// `lp_token_mint`, `lp_token_burn` and `lp_token_transfer` events
+    #[serde(rename = "1.17.0")]
+    V1_17_0,
+    /// `swap` event carries `integration_id`
+    #[serde(rename = "1.18.0")]
+    V1_18_0,
+    /// `force_untrack_withdraw` event
+    #[serde(rename = "1.19.0")]
+    V1_19_0,
+    /// `upgrade_staged` and `upgrade` events
+    #[serde(rename = "1.20.0")]
+    V1_20_0,
+    /// `harvest_all_fees` event
+    #[serde(rename = "1.21.0")]
+    V1_21_0,
+    /// `lock_position_until` event
+    #[serde(rename = "1.22.0")]
+    V1_22_0,
+}
+
+impl EventsVersion {
+    /// All known versions, ordered from oldest to newest
+    pub const ALL: [EventsVersion; 23] = [
+        EventsVersion::V1_0_0,
+        EventsVersion::V1_1_0,
+        EventsVersion::V1_2_0,
+        EventsVersion::V1_3_0,
+        EventsVersion::V1_4_0,
+        EventsVersion::V1_5_0,
+        EventsVersion::V1_6_0,
+        EventsVersion::V1_7_0,
+        EventsVersion::V1_8_0,
+        EventsVersion::V1_9_0,
+        EventsVersion::V1_10_0,
+        EventsVersion::V1_11_0,
+        EventsVersion::V1_12_0,
+        EventsVersion::V1_13_0,
+        EventsVersion::V1_14_0,
+        EventsVersion::V1_15_0,
+        EventsVersion::V1_16_0,
+        EventsVersion::V1_17_0,
+        EventsVersion::V1_18_0,
+        EventsVersion::V1_19_0,
+        EventsVersion::V1_20_0,
+        EventsVersion::V1_21_0,
+        EventsVersion::V1_22_0,
+    ];
+
+    pub const fn as_str(self) -> &'static str {
+        match self {
+            EventsVersion::V1_0_0 => "1.0.0",
+            EventsVersion::V1_1_0 => "1.1.0",
+            EventsVersion::V1_2_0 => "1.2.0",
+            EventsVersion::V1_3_0 => "1.3.0",
+            EventsVersion::V1_4_0 => "1.4.0",
+            EventsVersion::V1_5_0 => "1.5.0",
+            EventsVersion::V1_6_0 => "1.6.0",
+            EventsVersion::V1_7_0 => "1.7.0",
+            EventsVersion::V1_8_0 => "1.8.0",
+            EventsVersion::V1_9_0 => "1.9.0",
+            EventsVersion::V1_10_0 => "1.10.0",
+            EventsVersion::V1_11_0 => "1.11.0",
+            EventsVersion::V1_12_0 => "1.12.0",
+            EventsVersion::V1_13_0 => "1.13.0",
+            EventsVersion::V1_14_0 => "1.14.0",
+            EventsVersion::V1_15_0 => "1.15.0",
+            EventsVersion::V1_16_0 => "1.16.0",
+            EventsVersion::V1_17_0 => "1.17.0",
+            EventsVersion::V1_18_0 => "1.18.0",
+            EventsVersion::V1_19_0 => "1.19.0",
+            EventsVersion::V1_20_0 => "1.20.0",
+            EventsVersion::V1_21_0 => "1.21.0",
+            EventsVersion::V1_22_0 => "1.22.0",
+        }
+    }
+
+    /// Short description of changes introduced by version
+    pub const fn changes(self) -> &'static str {
+        match self {
+            EventsVersion::V1_0_0 => "Initial events schema",
+            EventsVersion::V1_1_0 => "Added `user` to `close_position` and `harvest_fee` events",
+            EventsVersion::V1_2_0 => {
+                "Added `lp_fees_a`, `lp_fees_b`, `top_active_level` and `active_side` \
+                to `update_pool_state` event"
+            }
+            EventsVersion::V1_3_0 => "Added `lock_position` and `unlock_position` events",
+            EventsVersion::V1_4_0 => {
+                "Added `action_scheduled`, `action_executed` and `action_cancelled` events"
+            }
+            EventsVersion::V1_5_0 => "Added `action_approved` event",
+            EventsVersion::V1_6_0 => {
+                "Added `pool_storage_cleanup` and `token_registrations_cleanup` events"
+            }
+            EventsVersion::V1_7_0 => "Added `ban_tokens` and `unban_tokens` events",
+            EventsVersion::V1_8_0 => "Added `liquidity` to `open_position` event",
+            EventsVersion::V1_9_0 => {
+                "Added `fee_level`, `range_ticks`, `net_liquidity` and `init_sqrt_price` \
+                to `open_position` event"
+            }
+            EventsVersion::V1_10_0 => "Added `withdraw_protocol_fee` and `owner_withdraw` events",
+            EventsVersion::V1_11_0 => {
+                "Added `set_protocol_fee_fraction` and `config_change` events, \
+                added `caller` to `add_guard_accounts` and `remove_guard_accounts` events"
+            }
+            EventsVersion::V1_12_0 => {
+                "Added `update_pool_state_delta` event, which carries only fee levels \
+                changed by swap and replaces `update_pool_state` after swaps"
+            }
+            EventsVersion::V1_13_0 => "Added `move_position` event",
+            EventsVersion::V1_14_0 => {
+                "Added `order_placed`, `order_executed` and `order_cancelled` events \
+                of keeper-executed conditional orders"
+            }
+            EventsVersion::V1_15_0 => {
+                "Added `fee_auction_update` and `fee_auction_take` events \
+                of Dutch auctions of protocol fees"
+            }
+            EventsVersion::V1_16_0 => {
+                "Added `transfer_internal` event of transfers between accounts"
+            }
+            EventsVersion::V1_17_0 => {
+                "Added `lp_token_mint`, `lp_token_burn` and `lp_token_transfer` events \
+                of LP share tokens of managed full-range positions"
+            }
+            EventsVersion::V1_18_0 => {
+                "Added `integration_id` to `swap` event, see `register_integration`"
+            }
+            EventsVersion::V1_19_0 => {
+                "Added `force_untrack_withdraw` event of recovery from lost withdraw callbacks"
+            }
+            EventsVersion::V1_20_0 => {
+                "Added `upgrade_staged` and `upgrade` events of staged contract code upgrades"
+            }
+            EventsVersion::V1_21_0 => {
+                "Added `harvest_all_fees` event of fees harvested from all positions of account"
+            }
+            EventsVersion::V1_22_0 => {
+                "Added `lock_position_until` event of positions opened with vesting lock"
+            }
+        }
+    }
+
+    /// Checks whether fields introduced in this version should be emitted
+    const fn is_enabled(self) -> bool {
+        self as u8 <= EVENTS_VERSION as u8
+    }
+}
+
+/// Single entry of events schema changelog
+#[derive(Serialize, Deserialize)]
+#[cfg_attr(not(target_arch = "wasm32"), derive(Debug))]
+pub struct EventsVersionInfo {
+    pub version: EventsVersion,
+    pub changes: String,
+}
+
+/// Describes events emitted by contract, so indexers can negotiate payloads to expect
+#[derive(Serialize, Deserialize)]
+#[cfg_attr(not(target_arch = "wasm32"), derive(Debug))]
+pub struct EventsSchema {
+    /// Events standard name
+    pub standard: String,
+    /// Version of events currently emitted
+    pub version: EventsVersion,
+    /// All versions current events are compatible with, from oldest to newest
+    pub supported_versions: Vec<EventsVersionInfo>,
+}
+
+/// Produces description of events schema emitted by current build
+pub fn events_schema() -> EventsSchema {
+    EventsSchema {
+        standard: EVENTS_STANDARD.to_string(),
+        version: EVENTS_VERSION,
+        supported_versions: EventsVersion::ALL
+            .into_iter()
+            .filter(|version| version.is_enabled())
+            .map(|version| EventsVersionInfo {
+                version,
+                changes: version.changes().to_string(),
+            })
+            .collect(),
+    }
+}
+
+/// Returns `value` only if fields of specified version are emitted
+fn since<T>(version: EventsVersion, value: T) -> Option<T> {
+    version.is_enabled().then_some(value)
+}
 
 #[derive(Serialize)]
 #[cfg_attr(not(target_arch = "wasm32"), derive(Debug))]
@@ -20,25 +314,21 @@ struct NearEventWrapper<'a> {
     pub event: Event<'a>,
 }
 
-fn emit<'a>(event: impl Into<Event<'a>>) {
+fn write_event(event: Event<'_>) {
     let event = NearEventWrapper {
-        standard: "veax",
-        version: "1.0.0",
-        event: event.into(),
+        standard: EVENTS_STANDARD,
+        version: EVENTS_VERSION.as_str(),
+        event,
     };
     let Ok(json_string) = serde_json::to_string(&event) else { unreachable!() };
     log_str(&format!("EVENT_JSON:{json_string}"));
 }
 
-pub fn log_storage_balance_event(user: &AccountId, available: &Amount, total: &Amount) {
-    emit(Event::StorageBalance {
-        user,
-        available: (*available).into(),
-        total: (*total).into(),
-    });
-}
-
 impl dex::Logger for Logger {
+    fn is_event_enabled(&self, kind: dex::EventKind) -> bool {
+        self.filter.is_enabled(kind)
+    }
+
     fn log(&mut self, args: Arguments<'_>) {
         log_str(&format(args));
     }
@@ -50,7 +340,7 @@ impl dex::Logger for Logger {
         amount: &Amount,
         balance: &Amount,
     ) {
-        emit(Event::Deposit {
+        self.emit(Event::Deposit {
             user,
             token_id: token,
             amount: (*amount).into(),
@@ -65,7 +355,7 @@ impl dex::Logger for Logger {
         amount: &Amount,
         balance: &Amount,
     ) {
-        emit(Event::Withdraw {
+        self.emit(Event::Withdraw {
             user,
             token_id: token,
             amount: (*amount).into(),
@@ -73,6 +363,7 @@ impl dex::Logger for Logger {
         });
     }
 
+    #[allow(clippy::too_many_arguments)]
     fn log_open_position_event(
         &mut self,
         user: &AccountId,
@@ -80,44 +371,267 @@ impl dex::Logger for Logger {
         amounts: (&Amount, &Amount),
         fee_rate: dex::BasisPoints,
         position_id: dex::PositionId,
+        liquidity: &Liquidity,
+        fee_level: dex::FeeLevel,
+        tick_bounds: (Tick, Tick),
+        net_liquidity: &Liquidity,
+        init_sqrtprice: dex::Float,
     ) {
-        emit(Event::OpenPosition {
+        self.emit(Event::OpenPosition {
             user,
             pool,
             amounts: ((*amounts.0).into(), (*amounts.1).into()),
             fee_rate: u64::from(fee_rate).into(),
             position_id: position_id.into(),
+            liquidity: since(EventsVersion::V1_8_0, (*liquidity).into()),
+            fee_level: since(EventsVersion::V1_9_0, fee_level),
+            range_ticks: since(EventsVersion::V1_9_0, Tick::wrap_range(tick_bounds)),
+            net_liquidity: since(EventsVersion::V1_9_0, (*net_liquidity).into()),
+            init_sqrt_price: since(EventsVersion::V1_9_0, init_sqrtprice.into()),
         });
     }
 
     fn log_harvest_fee_event(
         &mut self,
+        user: &AccountId,
         position_id: dex::PositionId,
         fee_amounts: (Amount, Amount),
     ) {
-        emit(Event::HarvestFee {
+        self.emit(Event::HarvestFee {
+            user: since(EventsVersion::V1_1_0, user),
             position_id: position_id.into(),
             amounts: (fee_amounts.0.into(), fee_amounts.1.into()),
         });
     }
 
+    fn log_harvest_all_fees_event(
+        &mut self,
+        user: &AccountId,
+        harvests: &[(dex::PositionId, (Amount, Amount))],
+    ) {
+        self.emit(Event::HarvestAllFees {
+            user,
+            harvests: harvests
+                .iter()
+                .map(|(position_id, amounts)| {
+                    ((*position_id).into(), (amounts.0.into(), amounts.1.into()))
+                })
+                .collect(),
+        });
+    }
+
     fn log_close_position_event(
         &mut self,
+        user: &AccountId,
         position_id: dex::PositionId,
         amounts: (Amount, Amount),
     ) {
-        emit(Event::ClosePosition {
+        self.emit(Event::ClosePosition {
+            user: since(EventsVersion::V1_1_0, user),
             position_id: position_id.into(),
             amounts: (amounts.0.into(), amounts.1.into()),
         });
     }
 
+    #[allow(clippy::too_many_arguments)]
+    fn log_move_position_event(
+        &mut self,
+        user: &AccountId,
+        position_id: dex::PositionId,
+        pool: (&TokenId, &TokenId),
+        tick_bounds: (Tick, Tick),
+        withdrawn: (Amount, Amount),
+        deposited: (Amount, Amount),
+        net_liquidity: &Liquidity,
+    ) {
+        self.emit(Event::MovePosition {
+            user,
+            position_id: position_id.into(),
+            pool,
+            range_ticks: Tick::wrap_range(tick_bounds),
+            withdrawn: (withdrawn.0.into(), withdrawn.1.into()),
+            deposited: (deposited.0.into(), deposited.1.into()),
+            net_liquidity: (*net_liquidity).into(),
+        });
+    }
+
+    fn log_order_placed_event(&mut self, id: u64, order: &dex::ConditionalOrder) {
+        self.emit(Event::OrderPlaced {
+            id: id.into(),
+            user: &order.owner,
+            tokens: (&order.token_in, &order.token_out),
+            amount_in: order.amount_in.into(),
+            min_amount_out: order.min_amount_out.into(),
+            trigger: order.trigger,
+            trigger_price: order.trigger_price.into(),
+            expires_at: order.expires_at.into(),
+            keeper_fee: order.keeper_fee.into(),
+        });
+    }
+
+    fn log_order_executed_event(
+        &mut self,
+        id: u64,
+        keeper: &AccountId,
+        order: &dex::ConditionalOrder,
+        amount_out: Amount,
+    ) {
+        self.emit(Event::OrderExecuted {
+            id: id.into(),
+            user: &order.owner,
+            keeper,
+            tokens: (&order.token_in, &order.token_out),
+            amounts: (order.amount_in.into(), amount_out.into()),
+            keeper_fee: order.keeper_fee.into(),
+        });
+    }
+
+    fn log_fee_auction_update_event(&mut self, token: &TokenId, auction: &dex::FeeAuction) {
+        self.emit(Event::FeeAuctionUpdate {
+            token,
+            quote_token: &auction.quote_token,
+            lot: auction.lot.into(),
+            start_price: auction.start_price.into(),
+            end_price: auction.end_price.into(),
+            started_at: auction.started_at.into(),
+            ends_at: auction.ends_at.into(),
+        });
+    }
+
+    fn log_fee_auction_take_event(
+        &mut self,
+        user: &AccountId,
+        token: &TokenId,
+        auction: &dex::FeeAuction,
+        amount: Amount,
+        quote_amount: Amount,
+    ) {
+        self.emit(Event::FeeAuctionTake {
+            user,
+            token,
+            quote_token: &auction.quote_token,
+            amount: amount.into(),
+            quote_amount: quote_amount.into(),
+            lot: auction.lot.into(),
+        });
+    }
+
+    fn log_transfer_internal_event(
+        &mut self,
+        sender: &AccountId,
+        receiver: &AccountId,
+        token: &TokenId,
+        amount: Amount,
+        balances: (Amount, Amount),
+    ) {
+        self.emit(Event::TransferInternal {
+            sender,
+            receiver,
+            token_id: token,
+            amount: amount.into(),
+            sender_balance: balances.0.into(),
+            receiver_balance: balances.1.into(),
+        });
+    }
+
+    fn log_force_untrack_withdraw_event(
+        &mut self,
+        caller: &AccountId,
+        user: &AccountId,
+        token: &TokenId,
+        amount: Amount,
+    ) {
+        self.emit(Event::ForceUntrackWithdraw {
+            caller,
+            user,
+            token_id: token,
+            amount: amount.into(),
+        });
+    }
+
+    fn log_upgrade_staged_event(&mut self, caller: &AccountId, code_hash: Option<&CodeHash>) {
+        self.emit(Event::UpgradeStaged {
+            caller,
+            code_hash: code_hash.map(|code_hash| (*code_hash).into()),
+        });
+    }
+
+    fn log_upgrade_event(&mut self, caller: &AccountId, code_hash: &CodeHash) {
+        self.emit(Event::Upgrade {
+            caller,
+            code_hash: (*code_hash).into(),
+        });
+    }
+
+    fn log_lp_token_mint_event(
+        &mut self,
+        user: &AccountId,
+        token: &dex::LpTokenId,
+        amounts: (Amount, Amount),
+        amount: Amount,
+    ) {
+        self.emit(Event::LpTokenMint {
+            user,
+            token_id: token.to_string(),
+            tokens: token.pool_id.as_refs(),
+            amounts: (amounts.0.into(), amounts.1.into()),
+            amount: amount.into(),
+        });
+    }
+
+    fn log_lp_token_burn_event(
+        &mut self,
+        user: &AccountId,
+        token: &dex::LpTokenId,
+        amounts: (Amount, Amount),
+        amount: Amount,
+    ) {
+        self.emit(Event::LpTokenBurn {
+            user,
+            token_id: token.to_string(),
+            tokens: token.pool_id.as_refs(),
+            amounts: (amounts.0.into(), amounts.1.into()),
+            amount: amount.into(),
+        });
+    }
+
+    fn log_lp_token_transfer_event(
+        &mut self,
+        sender: &AccountId,
+        receiver: &AccountId,
+        token: &dex::LpTokenId,
+        amount: Amount,
+        memo: Option<&str>,
+    ) {
+        self.emit(Event::LpTokenTransfer {
+            sender,
+            receiver,
+            token_id: token.to_string(),
+            amount: amount.into(),
+            memo,
+        });
+    }
+
+    fn log_order_cancelled_event(
+        &mut self,
+        id: u64,
+        caller: &AccountId,
+        order: &dex::ConditionalOrder,
+    ) {
+        self.emit(Event::OrderCancelled {
+            id: id.into(),
+            user: &order.owner,
+            caller,
+        });
+    }
+
     fn log_swap_event(
         &mut self,
         user: &AccountId,
         tokens: (&TokenId, &TokenId),
         amounts: (&Amount, &Amount),
         fees: &[(&TokenId, &dex::BasisPoints)],
+        integration_id: Option<dex::IntegrationId>,
     ) {
         let fees = fees
             .iter()
@@ -125,14 +639,16 @@ impl dex::Logger for Logger {
             .map(|(token, fee)| (token, (u128::from(*fee)).into()))
             .collect::<Vec<_>>();
 
-        emit(Event::Swap {
+        self.emit(Event::Swap {
             user,
             tokens,
             amounts: ((*amounts.0).into(), (*amounts.1).into()),
             fees: &fees,
+            integration_id: since(EventsVersion::V1_18_0, integration_id).flatten(),
         });
     }
 
+    #[allow(clippy::too_many_arguments)]
     fn log_update_pool_state_event(
         &mut self,
         reason: PoolUpdateReason,
@@ -141,39 +657,210 @@ impl dex::Logger for Logger {
         amounts_b: &RawFeeLevelsArray<Amount>,
         spot_sqrtprices: &RawFeeLevelsArray<dex::Float>,
         liquidities: &RawFeeLevelsArray<dex::Float>,
+        lp_fees: (&RawFeeLevelsArray<Amount>, &RawFeeLevelsArray<Amount>),
+        top_active_level: dex::FeeLevel,
+        active_side: dex::Side,
+        changed_levels: Option<&RawFeeLevelsArray<bool>>,
     ) {
-        emit(Event::UpdatePoolState {
+        if let Some(changed_levels) = since(EventsVersion::V1_12_0, changed_levels).flatten() {
+            let levels = changed_levels
+                .iter()
+                .enumerate()
+                .filter(|(_, changed)| **changed)
+                .map(|(level, _)| LevelStateDelta {
+                    // Never fails - number of levels fits into `FeeLevel`
+                    level: dex::FeeLevel::try_from(level).unwrap_or_default(),
+                    amount_a: amounts_a[level].into(),
+                    amount_b: amounts_b[level].into(),
+                    sqrt_price: spot_sqrtprices[level].into(),
+                    liquidity: liquidities[level].into(),
+                    lp_fee_a: lp_fees.0[level].into(),
+                    lp_fee_b: lp_fees.1[level].into(),
+                })
+                .collect::<Vec<_>>();
+            self.emit(Event::UpdatePoolStateDelta {
+                pool,
+                r#type: reason,
+                levels: &levels,
+                top_active_level,
+                active_side,
+            });
+            return;
+        }
+
+        let lp_fees_a = lp_fees.0.map(Into::into);
+        let lp_fees_b = lp_fees.1.map(Into::into);
+        self.emit(Event::UpdatePoolState {
             pool,
             r#type: reason,
             amounts_a: &amounts_a.map(Into::into),
             amounts_b: &amounts_b.map(Into::into),
             sqrt_prices: &spot_sqrtprices.map(Into::into),
             liquidities: &liquidities.map(Into::into),
+            lp_fees_a: since(EventsVersion::V1_2_0, &lp_fees_a),
+            lp_fees_b: since(EventsVersion::V1_2_0, &lp_fees_b),
+            top_active_level: since(EventsVersion::V1_2_0, top_active_level),
+            active_side: since(EventsVersion::V1_2_0, active_side),
         });
     }
 
     fn log_add_verified_tokens_event(&mut self, tokens: &[TokenId]) {
-        emit(Event::AddVerifiedTokens { tokens });
+        self.emit(Event::AddVerifiedTokens { tokens });
     }
 
     fn log_remove_verified_tokens_event(&mut self, tokens: &[TokenId]) {
-        emit(Event::RemoveVerifiedTokens { tokens });
+        self.emit(Event::RemoveVerifiedTokens { tokens });
+    }
+
+    fn log_ban_tokens_event(&mut self, tokens: &[TokenId]) {
+        self.emit(Event::BanTokens { tokens });
+    }
+
+    fn log_unban_tokens_event(&mut self, tokens: &[TokenId]) {
+        self.emit(Event::UnbanTokens { tokens });
+    }
+
+    fn log_add_guard_accounts_event(&mut self, caller: &AccountId, accounts: &[AccountId]) {
+        self.emit(Event::AddGuardAccounts {
+            caller: since(EventsVersion::V1_11_0, caller),
+            accounts,
+        });
     }
 
-    fn log_add_guard_accounts_event(&mut self, accounts: &[AccountId]) {
-        emit(Event::AddGuardAccounts { accounts });
+    fn log_remove_guard_accounts_event(&mut self, caller: &AccountId, accounts: &[AccountId]) {
+        self.emit(Event::RemoveGuardAccounts {
+            caller: since(EventsVersion::V1_11_0, caller),
+            accounts,
+        });
+    }
+
+    fn log_lock_position_event(&mut self, position_id: dex::PositionId, locker: &AccountId) {
+        self.emit(Event::LockPosition {
+            position_id: position_id.into(),
+            locker,
+        });
+    }
+
+    fn log_unlock_position_event(&mut self, position_id: dex::PositionId, locker: &AccountId) {
+        self.emit(Event::UnlockPosition {
+            position_id: position_id.into(),
+            locker,
+        });
     }
 
-    fn log_remove_guard_accounts_event(&mut self, accounts: &[AccountId]) {
-        emit(Event::RemoveGuardAccounts { accounts });
+    fn log_lock_position_until_event(
+        &mut self,
+        user: &AccountId,
+        position_id: dex::PositionId,
+        locked_until: u64,
+    ) {
+        self.emit(Event::LockPositionUntil {
+            user,
+            position_id: position_id.into(),
+            locked_until: locked_until.into(),
+        });
     }
 
     fn log_suspend_payable_api_event(&mut self, account: &AccountId) {
-        emit(Event::SuspendPayableAPI { account });
+        self.emit(Event::SuspendPayableAPI { account });
     }
 
     fn log_resume_payable_api_event(&mut self, account: &AccountId) {
-        emit(Event::ResumePayableAPI { account });
+        self.emit(Event::ResumePayableAPI { account });
+    }
+
+    fn log_action_scheduled_event(
+        &mut self,
+        id: u64,
+        action: &dex::AdminAction,
+        execute_after: u64,
+    ) {
+        self.emit(Event::ActionScheduled {
+            id: id.into(),
+            action,
+            execute_after: execute_after.into(),
+        });
+    }
+
+    fn log_action_executed_event(&mut self, id: u64, action: &dex::AdminAction) {
+        self.emit(Event::ActionExecuted {
+            id: id.into(),
+            action,
+        });
+    }
+
+    fn log_action_cancelled_event(&mut self, id: u64, action: &dex::AdminAction) {
+        self.emit(Event::ActionCancelled {
+            id: id.into(),
+            action,
+        });
+    }
+
+    fn log_action_approved_event(
+        &mut self,
+        hash: &dex::ActionHash,
+        action: &dex::AdminAction,
+        approver: &AccountId,
+        approvals: u16,
+    ) {
+        self.emit(Event::ActionApproved {
+            hash: (*hash).into(),
+            action,
+            approver,
+            approvals,
+        });
+    }
+
+    fn log_pool_storage_cleanup_event(
+        &mut self,
+        pool_id: &dex::PoolId,
+        removed_ticks: &[(dex::FeeLevel, dex::Tick)],
+        next_cursor: Option<&dex::CleanupCursor>,
+    ) {
+        self.emit(Event::PoolStorageCleanup {
+            pool: pool_id.as_refs(),
+            removed_ticks,
+            next_cursor,
+        });
+    }
+
+    fn log_token_registrations_cleanup_event(&mut self, user: &AccountId, tokens: &[TokenId]) {
+        self.emit(Event::TokenRegistrationsCleanup { user, tokens });
+    }
+
+    fn log_withdraw_protocol_fee_event(
+        &mut self,
+        pool: (&TokenId, &TokenId),
+        amounts: (&Amount, &Amount),
+    ) {
+        self.emit(Event::WithdrawProtocolFee {
+            pool,
+            amounts: ((*amounts.0).into(), (*amounts.1).into()),
+        });
+    }
+
+    fn log_owner_withdraw_event(&mut self, token: &TokenId, amount: &Amount) {
+        self.emit(Event::OwnerWithdraw {
+            token_id: token,
+            amount: (*amount).into(),
+        });
+    }
+
+    fn log_set_protocol_fee_fraction_event(
+        &mut self,
+        caller: &AccountId,
+        previous: dex::BasisPoints,
+        new: dex::BasisPoints,
+    ) {
+        self.emit(Event::SetProtocolFeeFraction {
+            caller,
+            previous,
+            new,
+        });
+    }
+
+    fn log_config_change_event(&mut self, caller: &AccountId, change: &dex::ConfigChange) {
+        self.emit(Event::ConfigChange { caller, change });
     }
 }
 
@@ -200,20 +887,176 @@ enum Event<'a> {
         amounts: (U128, U128),
         fee_rate: U64,
         position_id: U64,
+        /// Since 1.8.0. Exact accounted liquidity of position
+        #[serde(skip_serializing_if = "Option::is_none")]
+        liquidity: Option<LiquidityHex>,
+        /// Since 1.9.0
+        #[serde(skip_serializing_if = "Option::is_none")]
+        fee_level: Option<dex::FeeLevel>,
+        /// Since 1.9.0. Tick bounds of position, `null` stands for unbounded side
+        #[serde(skip_serializing_if = "Option::is_none")]
+        range_ticks: Option<(Option<i32>, Option<i32>)>,
+        /// Since 1.9.0. Exact accounted net liquidity of position
+        #[serde(skip_serializing_if = "Option::is_none")]
+        net_liquidity: Option<LiquidityHex>,
+        /// Since 1.9.0. Square root of spot price at the moment of position creation
+        #[serde(skip_serializing_if = "Option::is_none")]
+        init_sqrt_price: Option<f64>,
     },
     ClosePosition {
+        /// Since 1.1.0
+        #[serde(skip_serializing_if = "Option::is_none")]
+        user: Option<&'a AccountId>,
         position_id: U64,
         amounts: (U128, U128),
     },
     HarvestFee {
+        /// Since 1.1.0
+        #[serde(skip_serializing_if = "Option::is_none")]
+        user: Option<&'a AccountId>,
         position_id: U64,
         amounts: (U128, U128),
     },
+    /// Since 1.13.0. Position was closed and reopened in new range under the same id;
+    /// preceded by `harvest_fee` event for fees collected in old range
+    MovePosition {
+        user: &'a AccountId,
+        position_id: U64,
+        pool: (&'a TokenId, &'a TokenId),
+        /// Tick bounds of new range, `null` stands for unbounded side
+        range_ticks: (Option<i32>, Option<i32>),
+        /// Amounts withdrawn from old range
+        withdrawn: (U128, U128),
+        /// Amounts deposited into new range; the rest is returned to user's deposits
+        deposited: (U128, U128),
+        /// Exact accounted net liquidity of position in new range
+        net_liquidity: LiquidityHex,
+    },
+    /// Since 1.14.0. Input amount is not reserved, it's taken from user's deposits at execution
+    OrderPlaced {
+        id: U64,
+        user: &'a AccountId,
+        /// Input and output tokens
+        tokens: (&'a TokenId, &'a TokenId),
+        amount_in: U128,
+        min_amount_out: U128,
+        trigger: dex::OrderTrigger,
+        /// Price of input token in units of output token
+        trigger_price: f64,
+        /// Moment after which order can't be executed, in seconds
+        expires_at: U64,
+        /// Amount of input token paid to keeper, in addition to `amount_in`
+        keeper_fee: U128,
+    },
+    /// Since 1.14.0. Followed by `swap` event of order's owner
+    OrderExecuted {
+        id: U64,
+        user: &'a AccountId,
+        keeper: &'a AccountId,
+        tokens: (&'a TokenId, &'a TokenId),
+        amounts: (U128, U128),
+        keeper_fee: U128,
+    },
+    /// Since 1.14.0. Order was cancelled by its owner, or by anyone after expiration
+    OrderCancelled {
+        id: U64,
+        user: &'a AccountId,
+        caller: &'a AccountId,
+    },
+    /// Since 1.15.0. Protocol fee auction of `token` was started, restarted or got larger lot;
+    /// preceded by `withdraw_protocol_fee` event of pool which fee was taken from
+    FeeAuctionUpdate {
+        token: &'a TokenId,
+        quote_token: &'a TokenId,
+        /// Amount of `token` left for sale
+        lot: U128,
+        /// Prices of `token` in units of `quote_token` at start and end of auction
+        start_price: f64,
+        end_price: f64,
+        /// Moments of start and end of auction, in seconds
+        started_at: U64,
+        ends_at: U64,
+    },
+    /// Since 1.15.0. User bought `amount` of `token` from protocol fee auction
+    FeeAuctionTake {
+        user: &'a AccountId,
+        token: &'a TokenId,
+        quote_token: &'a TokenId,
+        amount: U128,
+        quote_amount: U128,
+        /// Amount of `token` left for sale
+        lot: U128,
+    },
+    /// Since 1.16.0. Deposited token was moved between accounts
+    TransferInternal {
+        sender: &'a AccountId,
+        receiver: &'a AccountId,
+        token_id: &'a TokenId,
+        amount: U128,
+        /// Balances of sender and receiver after transfer
+        sender_balance: U128,
+        receiver_balance: U128,
+    },
+    /// Since 1.19.0. Guard stopped tracking withdraw whose callback was lost,
+    /// so account can be unregistered again
+    ForceUntrackWithdraw {
+        caller: &'a AccountId,
+        user: &'a AccountId,
+        token_id: &'a TokenId,
+        amount: U128,
+    },
+    /// Since 1.20.0. Owner allowed upgrade to contract code with specified hash,
+    /// or cancelled staged upgrade if hash is `null`
+    UpgradeStaged {
+        caller: &'a AccountId,
+        code_hash: Option<Base58CryptoHash>,
+    },
+    /// Since 1.20.0. Staged contract code is being deployed, followed by state migration
+    Upgrade {
+        caller: &'a AccountId,
+        code_hash: Base58CryptoHash,
+    },
+    /// Since 1.21.0. Fees of all positions of account were harvested at once
+    HarvestAllFees {
+        user: &'a AccountId,
+        /// Position ids with harvested amounts, in order of position's tokens
+        harvests: Vec<(U64, (U128, U128))>,
+    },
+    /// Since 1.17.0. LP tokens were minted for liquidity added to managed full-range position
+    LpTokenMint {
+        user: &'a AccountId,
+        token_id: String,
+        tokens: (&'a TokenId, &'a TokenId),
+        /// Deposited amounts, ordered as `tokens`
+        amounts: (U128, U128),
+        amount: U128,
+    },
+    /// Since 1.17.0. LP tokens were burnt for liquidity removed from managed full-range position
+    LpTokenBurn {
+        user: &'a AccountId,
+        token_id: String,
+        tokens: (&'a TokenId, &'a TokenId),
+        /// Withdrawn amounts, ordered as `tokens`
+        amounts: (U128, U128),
+        amount: U128,
+    },
+    /// Since 1.17.0. LP tokens were transferred between accounts
+    LpTokenTransfer {
+        sender: &'a AccountId,
+        receiver: &'a AccountId,
+        token_id: String,
+        amount: U128,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        memo: Option<&'a str>,
+    },
     Swap {
         user: &'a AccountId,
         tokens: (&'a TokenId, &'a TokenId),
         amounts: (U128, U128),
         fees: &'a [(&'a TokenId, U128)],
+        /// Since 1.18.0. Integration which swap is attributed to, if any
+        #[serde(skip_serializing_if = "Option::is_none")]
+        integration_id: Option<dex::IntegrationId>,
     },
     UpdatePoolState {
         pool: (&'a TokenId, &'a TokenId),
@@ -222,6 +1065,26 @@ enum Event<'a> {
         amounts_b: &'a RawFeeLevelsArray<U128>,
         sqrt_prices: &'a RawFeeLevelsArray<f64>,
         liquidities: &'a RawFeeLevelsArray<f64>,
+        /// Since 1.2.0. LP fees in token A earned on each level during operation
+        #[serde(skip_serializing_if = "Option::is_none")]
+        lp_fees_a: Option<&'a RawFeeLevelsArray<U128>>,
+        /// Since 1.2.0. LP fees in token B earned on each level during operation
+        #[serde(skip_serializing_if = "Option::is_none")]
+        lp_fees_b: Option<&'a RawFeeLevelsArray<U128>>,
+        /// Since 1.2.0
+        #[serde(skip_serializing_if = "Option::is_none")]
+        top_active_level: Option<dex::FeeLevel>,
+        /// Since 1.2.0
+        #[serde(skip_serializing_if = "Option::is_none")]
+        active_side: Option<dex::Side>,
+    },
+    /// Since 1.12.0. Same as `update_pool_state`, but only for fee levels changed by operation
+    UpdatePoolStateDelta {
+        pool: (&'a TokenId, &'a TokenId),
+        r#type: PoolUpdateReason,
+        levels: &'a [LevelStateDelta],
+        top_active_level: dex::FeeLevel,
+        active_side: dex::Side,
     },
     StorageBalance {
         user: &'a AccountId,
@@ -235,15 +1098,175 @@ enum Event<'a> {
         tokens: &'a [TokenId],
     },
     AddGuardAccounts {
+        /// Since 1.11.0
+        #[serde(skip_serializing_if = "Option::is_none")]
+        caller: Option<&'a AccountId>,
         accounts: &'a [AccountId],
     },
     RemoveGuardAccounts {
+        /// Since 1.11.0
+        #[serde(skip_serializing_if = "Option::is_none")]
+        caller: Option<&'a AccountId>,
         accounts: &'a [AccountId],
     },
+    LockPosition {
+        position_id: U64,
+        locker: &'a AccountId,
+    },
+    UnlockPosition {
+        position_id: U64,
+        locker: &'a AccountId,
+    },
+    /// Since 1.22.0. Position was opened locked, e.g. as protocol-owned liquidity
+    LockPositionUntil {
+        user: &'a AccountId,
+        position_id: U64,
+        /// Moment until which position cannot be closed or moved, in seconds
+        locked_until: U64,
+    },
     SuspendPayableAPI {
         account: &'a AccountId,
     },
     ResumePayableAPI {
         account: &'a AccountId,
     },
+    /// Since 1.4.0
+    ActionScheduled {
+        id: U64,
+        action: &'a dex::AdminAction,
+        /// Moment since which action can be executed, in seconds
+        execute_after: U64,
+    },
+    /// Since 1.4.0
+    ActionExecuted {
+        id: U64,
+        action: &'a dex::AdminAction,
+    },
+    /// Since 1.4.0
+    ActionCancelled {
+        id: U64,
+        action: &'a dex::AdminAction,
+    },
+    /// Since 1.5.0
+    ActionApproved {
+        hash: Base58CryptoHash,
+        action: &'a dex::AdminAction,
+        approver: &'a AccountId,
+        /// Number of approvals collected so far, including this one
+        approvals: u16,
+    },
+    /// Since 1.6.0
+    PoolStorageCleanup {
+        pool: (&'a TokenId, &'a TokenId),
+        /// Removed tick states, as fee levels and ticks
+        removed_ticks: &'a [(dex::FeeLevel, dex::Tick)],
+        /// Where next cleanup call continues from; absent if pool scan is complete
+        next_cursor: Option<&'a dex::CleanupCursor>,
+    },
+    /// Since 1.6.0
+    TokenRegistrationsCleanup {
+        user: &'a AccountId,
+        tokens: &'a [TokenId],
+    },
+    /// Since 1.7.0
+    BanTokens {
+        tokens: &'a [TokenId],
+    },
+    /// Since 1.7.0
+    UnbanTokens {
+        tokens: &'a [TokenId],
+    },
+    /// Since 1.10.0. Protocol fee moved onto owner's account, net of insurance share
+    WithdrawProtocolFee {
+        pool: (&'a TokenId, &'a TokenId),
+        amounts: (U128, U128),
+    },
+    /// Since 1.10.0
+    OwnerWithdraw {
+        token_id: &'a TokenId,
+        amount: U128,
+    },
+    /// Since 1.11.0
+    SetProtocolFeeFraction {
+        caller: &'a AccountId,
+        previous: dex::BasisPoints,
+        new: dex::BasisPoints,
+    },
+    /// Since 1.11.0. Changed parameter name, previous and new values are flattened into event data
+    ConfigChange {
+        caller: &'a AccountId,
+        #[serde(flatten)]
+        change: &'a dex::ConfigChange,
+    },
+}
+
+/// State of single fee level, reported in `update_pool_state_delta` event
+#[cfg_attr(not(target_arch = "wasm32"), derive(Debug))]
+#[derive(Serialize)]
+struct LevelStateDelta {
+    level: dex::FeeLevel,
+    amount_a: U128,
+    amount_b: U128,
+    sqrt_price: f64,
+    liquidity: f64,
+    /// LP fees in token A earned on level during operation
+    lp_fee_a: U128,
+    /// LP fees in token B earned on level during operation
+    lp_fee_b: U128,
+}
+
+impl Event<'_> {
+    /// Kind of event for filtering; `None` if event can't be disabled
+    fn kind(&self) -> Option<dex::EventKind> {
+        use dex::EventKind;
+        let kind = match self {
+            Event::Deposit { .. } => EventKind::Deposit,
+            Event::Withdraw { .. } => EventKind::Withdraw,
+            Event::OpenPosition { .. } => EventKind::OpenPosition,
+            Event::ClosePosition { .. } => EventKind::ClosePosition,
+            Event::HarvestFee { .. } => EventKind::HarvestFee,
+            Event::MovePosition { .. } => EventKind::MovePosition,
+            Event::OrderPlaced { .. } => EventKind::OrderPlaced,
+            Event::OrderExecuted { .. } => EventKind::OrderExecuted,
+            Event::OrderCancelled { .. } => EventKind::OrderCancelled,
+            Event::FeeAuctionUpdate { .. } => EventKind::FeeAuctionUpdate,
+            Event::FeeAuctionTake { .. } => EventKind::FeeAuctionTake,
+            Event::TransferInternal { .. } => EventKind::TransferInternal,
+            Event::ForceUntrackWithdraw { .. } => EventKind::ForceUntrackWithdraw,
+            Event::UpgradeStaged { .. } => EventKind::UpgradeStaged,
+            Event::Upgrade { .. } => EventKind::Upgrade,
+            Event::HarvestAllFees { .. } => EventKind::HarvestAllFees,
+            Event::LpTokenMint { .. } => EventKind::LpTokenMint,
+            Event::LpTokenBurn { .. } => EventKind::LpTokenBurn,
+            Event::LpTokenTransfer { .. } => EventKind::LpTokenTransfer,
+            Event::Swap { .. } => EventKind::Swap,
+            Event::UpdatePoolState { .. } | Event::UpdatePoolStateDelta { .. } => {
+                EventKind::UpdatePoolState
+            }
+            Event::StorageBalance { .. } => EventKind::StorageBalance,
+            Event::AddVerifiedTokens { .. } => EventKind::AddVerifiedTokens,
+            Event::RemoveVerifiedTokens { .. } => EventKind::RemoveVerifiedTokens,
+            Event::AddGuardAccounts { .. } => EventKind::AddGuardAccounts,
+            Event::RemoveGuardAccounts { .. } => EventKind::RemoveGuardAccounts,
+            Event::LockPosition { .. } => EventKind::LockPosition,
+            Event::UnlockPosition { .. } => EventKind::UnlockPosition,
+            Event::LockPositionUntil { .. } => EventKind::LockPositionUntil,
+            Event::SuspendPayableAPI { .. } => EventKind::SuspendPayableApi,
+            Event::ResumePayableAPI { .. } => EventKind::ResumePayableApi,
+            Event::ActionScheduled { .. } => EventKind::ActionScheduled,
+            Event::ActionExecuted { .. } => EventKind::ActionExecuted,
+            Event::ActionCancelled { .. } => EventKind::ActionCancelled,
+            Event::ActionApproved { .. } => EventKind::ActionApproved,
+            Event::PoolStorageCleanup { .. } => EventKind::PoolStorageCleanup,
+            Event::TokenRegistrationsCleanup { .. } => EventKind::TokenRegistrationsCleanup,
+            Event::BanTokens { .. } => EventKind::BanTokens,
+            Event::UnbanTokens { .. } => EventKind::UnbanTokens,
+            Event::WithdrawProtocolFee { .. } => EventKind::WithdrawProtocolFee,
+            Event::OwnerWithdraw { .. } => EventKind::OwnerWithdraw,
+            Event::SetProtocolFeeFraction { .. } => EventKind::SetProtocolFeeFraction,
+            // Changes of event filter itself must never be hidden
+            Event::ConfigChange { .. } => return None,
+        };
+        Some(kind)
+    }
 }
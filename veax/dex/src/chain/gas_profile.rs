@@ -0,0 +1,80 @@
+//! Gas checkpoints of contract call, to guide gas optimization work
+//!
+//! Checkpoints are collected in memory while call is executed, and saved into
+//! dedicated storage entry, outside of contract state, when call completes.
+//! Only the last profiled call is kept; integration tests read it through
+//! `profile_last_call` view after transaction.
+//!
+//! Exists only with `gas-profile` feature; without it, `gas_checkpoint!` expands to nothing.
+use near_sdk::borsh::{self, BorshDeserialize, BorshSerialize};
+use near_sdk::env;
+use near_sdk::json_types::U64;
+use near_sdk::serde::{Deserialize, Serialize};
+use std::cell::RefCell;
+
+/// Storage key of last call profile; can't clash with collections' fixed-size prefixes
+const PROFILE_KEY: &[u8] = b"gas_profile";
+
+#[derive(BorshDeserialize, BorshSerialize, Serialize, Deserialize, Clone, Debug)]
+#[serde(crate = "near_sdk::serde")]
+pub struct GasCheckpoint {
+    /// What was completed by the moment of checkpoint
+    pub stage: String,
+    /// Gas burnt since the beginning of call
+    pub used_gas: U64,
+}
+
+#[derive(BorshDeserialize, BorshSerialize, Serialize, Deserialize, Clone, Debug)]
+#[serde(crate = "near_sdk::serde")]
+pub struct GasProfile {
+    /// Profiled method
+    pub method: String,
+    pub prepaid_gas: U64,
+    /// Checkpoints in the order they were passed
+    pub checkpoints: Vec<GasCheckpoint>,
+}
+
+thread_local! {
+    static PROFILE: RefCell<Option<GasProfile>> = const { RefCell::new(None) };
+}
+
+/// Begin profile of specified method; checkpoints passed before are discarded
+pub fn start(method: &str) {
+    PROFILE.with(|profile| {
+        profile.replace(Some(GasProfile {
+            method: method.to_string(),
+            prepaid_gas: env::prepaid_gas().0.into(),
+            checkpoints: Vec::new(),
+        }))
+    });
+    checkpoint(format_args!("start"));
+}
+
+/// Record gas used so far; ignored if no profile was started.
+/// Use `gas_checkpoint!` instead of calling directly
+pub fn checkpoint(stage: std::fmt::Arguments<'_>) {
+    PROFILE.with(|profile| {
+        if let Some(profile) = profile.borrow_mut().as_mut() {
+            profile.checkpoints.push(GasCheckpoint {
+                stage: std::fmt::format(stage),
+                used_gas: env::used_gas().0.into(),
+            });
+        }
+    });
+}
+
+/// Complete current profile and save it as the last one
+pub fn finish() {
+    checkpoint(format_args!("finish"));
+    let Some(profile) = PROFILE.with(RefCell::take) else {
+        return;
+    };
+    // Serialization into memory buffer never fails
+    let Ok(bytes) = profile.try_to_vec() else { unreachable!() };
+    env::storage_write(PROFILE_KEY, &bytes);
+}
+
+/// Profile of the last call which completed successfully, if any
+pub fn last_call() -> Option<GasProfile> {
+    env::storage_read(PROFILE_KEY).and_then(|bytes| GasProfile::try_from_slice(&bytes).ok())
+}
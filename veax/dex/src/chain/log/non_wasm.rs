@@ -1,4 +1,5 @@
 use std::cell::RefCell;
+use std::rc::Rc;
 
 pub fn log_str_impl(msg: &str) {
     LOGGER.with(|l| l.borrow_mut().log(msg));
@@ -32,10 +33,12 @@ impl Drop for LoggerScope {
     }
 }
 
+/// Sink of log lines
 pub trait Logger {
     fn log(&mut self, s: &str);
 }
 
+/// Writes log lines into NEAR log
 pub struct DefaultLogger;
 
 impl Logger for DefaultLogger {
@@ -43,3 +46,66 @@ impl Logger for DefaultLogger {
         near_sdk::env::log_str(s);
     }
 }
+
+/// Discards all log lines
+pub struct NoopLogger;
+
+impl Logger for NoopLogger {
+    fn log(&mut self, _s: &str) {}
+}
+
+/// Captures log lines in memory, for inspection in tests
+///
+/// Clones share the same buffer, so one clone can be installed
+/// while the other is used to read captured lines.
+#[derive(Clone, Default)]
+pub struct MemoryLogger {
+    lines: Rc<RefCell<Vec<String>>>,
+}
+
+impl MemoryLogger {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn lines(&self) -> Vec<String> {
+        self.lines.borrow().clone()
+    }
+
+    /// Take captured lines, leaving buffer empty
+    pub fn take(&self) -> Vec<String> {
+        self.lines.take()
+    }
+}
+
+impl Logger for MemoryLogger {
+    fn log(&mut self, s: &str) {
+        self.lines.borrow_mut().push(s.to_string());
+    }
+}
+
+/// Passes each log line to all sinks, in the order they were added
+#[derive(Default)]
+pub struct LoggerPipeline {
+    sinks: Vec<Box<dyn Logger>>,
+}
+
+impl LoggerPipeline {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    #[must_use]
+    pub fn with_sink(mut self, sink: impl Logger + 'static) -> Self {
+        self.sinks.push(Box::new(sink));
+        self
+    }
+}
+
+impl Logger for LoggerPipeline {
+    fn log(&mut self, s: &str) {
+        for sink in &mut self.sinks {
+            sink.log(s);
+        }
+    }
+}
@@ -0,0 +1,209 @@
+//! Conversion of contract state stored by previous versions of contract code.
+//!
+//! Accounts and positions of previous layouts are converted when they're read,
+//! so only root state and pools, which can't be converted on read, are handled here.
+//! Root state is converted by `migrate` right after upgrade, while pools are converted
+//! in batches by `migrate_pools`, as converting all of them may not fit into gas
+//! attached to single call. Payable API is suspended until all pools are converted.
+use super::wasm::NearUnwrap;
+#[cfg(feature = "smart-routing")]
+use super::TOP_POOLS_INPLACE_ENTRIES;
+use super::{
+    AccountsMap, Contract, ContractExtra, Error, State, Types, POSITION_TO_POOL_INPLACE_ENTRIES,
+};
+use crate::chain::{AccountId, TokenId};
+use crate::dex::{
+    self, BasisPoints, ContractLatest, EventFilter, GasConfig, ItemFactory as _, PoolId,
+    PositionId, Result, VersionNumber,
+};
+use crate::ensure_here;
+use near_iterable_maps::{DoublyLinkedListMap, LinkedListMap, RecentlyWrittenMap};
+use near_sdk::borsh::{self, BorshDeserialize, BorshSerialize};
+use near_sdk::collections::UnorderedSet;
+use near_sdk::env;
+use std::ptr::addr_of_mut;
+
+/// Key under which `near_bindgen` keeps contract root state
+const STATE_KEY: &[u8] = b"STATE";
+
+/// Pools stored before contract version 1, with their layout versions
+type LegacyPoolsMap = LinkedListMap<PoolId, (VersionNumber, dex::PoolV0<Types>)>;
+
+/// Progress of conversion of pools stored before contract version 1
+#[derive(BorshSerialize, BorshDeserialize)]
+pub(super) struct PoolsMigration {
+    /// Pools which are yet to be converted; converted pools are removed from it
+    legacy_pools: LegacyPoolsMap,
+    /// Whether payable API was suspended before upgrade, restored once all pools are converted
+    suspended: bool,
+}
+
+/// Root state stored before contract version 1
+#[derive(BorshDeserialize)]
+struct ContractV0 {
+    owner_id: AccountId,
+    guards: UnorderedSet<AccountId>,
+    suspended: bool,
+    pools: LegacyPoolsMap,
+    accounts: AccountsMap,
+    verified_tokens: UnorderedSet<TokenId>,
+    pool_count: u64,
+    next_free_position_id: u64,
+    position_to_pool_id: DoublyLinkedListMap<PositionId, PoolId>,
+    protocol_fee_fraction: BasisPoints,
+    #[cfg(feature = "smart-routing")]
+    token_connections: DoublyLinkedListMap<TokenId, UnorderedSet<TokenId>>,
+    #[cfg(feature = "smart-routing")]
+    top_pools: DoublyLinkedListMap<TokenId, UnorderedSet<TokenId>>,
+}
+
+/// Reads contract root state, converting it to current layout if it was stored by previous version
+pub(super) fn read_state() -> Contract {
+    let state =
+        env::storage_read(STATE_KEY).unwrap_or_else(|| env::panic_str("Contract state is missing"));
+    let version = VersionNumber::deserialize(&mut state.as_slice())
+        .unwrap_or_else(|_| env::panic_str("Contract state is corrupted"));
+    match version {
+        0 => {
+            let (_version, legacy) = <(VersionNumber, ContractV0)>::try_from_slice(&state)
+                .unwrap_or_else(|_| env::panic_str("Contract state is corrupted"));
+            migrate_contract_v0(legacy)
+        }
+        _ => Contract::try_from_slice(&state)
+            .unwrap_or_else(|_| env::panic_str("Contract state is corrupted")),
+    }
+}
+
+fn migrate_contract_v0(legacy: ContractV0) -> Contract {
+    let factory = unsafe { &mut *addr_of_mut!(super::ITEM_FACTORY) };
+    let pools_migration = (!legacy.pools.is_empty()).then(|| PoolsMigration {
+        legacy_pools: legacy.pools,
+        suspended: legacy.suspended,
+    });
+    Contract::V1(ContractLatest {
+        owner_id: legacy.owner_id,
+        guards: legacy.guards,
+        // Pools which aren't converted yet can't be accessed
+        suspended: legacy.suspended || pools_migration.is_some(),
+        pools: factory.new_pools_map().into(),
+        accounts: legacy.accounts.into(),
+        verified_tokens: legacy.verified_tokens,
+        pool_count: legacy.pool_count,
+        next_free_position_id: legacy.next_free_position_id,
+        position_to_pool_id: RecentlyWrittenMap::from_map(
+            legacy.position_to_pool_id,
+            POSITION_TO_POOL_INPLACE_ENTRIES,
+        )
+        .into(),
+        protocol_fee_fraction: legacy.protocol_fee_fraction,
+        // Records introduced after previous version start empty
+        position_owners: factory.new_position_owners_map().into(),
+        swap_hook: None,
+        gas_config: GasConfig::default(),
+        timelock_delay: 0,
+        scheduled_actions: factory.new_scheduled_actions_map().into(),
+        next_scheduled_action_id: 0,
+        admins: factory.new_admins(),
+        admin_threshold: 0,
+        pending_approvals: factory.new_pending_approvals_map().into(),
+        staged_upgrade: None,
+        withdraw_limits: factory.new_withdraw_limits_map().into(),
+        swap_guards: factory.new_swap_guards_map().into(),
+        withdraw_limits_disabled: false,
+        insurance_fee_fraction: 0,
+        insurance_reserve: factory.new_insurance_reserve_map().into(),
+        banned_tokens: factory.new_banned_tokens_set(),
+        min_position_liquidity: dex::latest::MIN_LIQUIDITY,
+        min_deposit_amounts: factory.new_min_deposit_amounts_map().into(),
+        #[cfg(feature = "tracing")]
+        trace_verbosity: dex::TraceVerbosity::Off,
+        event_filter: EventFilter::default(),
+        classic_pools: factory.new_classic_pools_map().into(),
+        stable_pools: factory.new_stable_pools_map().into(),
+        orders: factory.new_orders_map().into(),
+        next_order_id: 0,
+        fee_auction_config: None,
+        fee_auctions: factory.new_fee_auctions_map().into(),
+        integrations: factory.new_integrations_map().into(),
+        next_integration_id: 0,
+        deposit_intents: factory.new_deposit_intents_map().into(),
+        next_deposit_intent_id: 0,
+        auto_harvest: factory.new_auto_harvest_map().into(),
+        view_grants: factory.new_view_grants_map().into(),
+        pool_aliases: factory.new_pool_aliases_map().into(),
+        aliases_by_pool: factory.new_aliases_by_pool_map().into(),
+        pool_storage_deposits: factory.new_pool_storage_deposits_map().into(),
+        pool_storage_deposits_total: 0,
+        pool_storage_bytes_total: 0,
+        admin_log: factory.new_admin_log_map().into(),
+        next_admin_log_id: 0,
+        vaults: factory.new_vaults_map().into(),
+        session_keys: factory.new_session_keys_map().into(),
+        reconciled_tokens: factory.new_reconciled_tokens_map().into(),
+        pool_price_init_open: false,
+        seed_period: None,
+        token_interface_check: false,
+        token_interfaces: factory.new_token_interfaces_map().into(),
+        #[cfg(feature = "lp-tokens")]
+        lp_tokens: factory.new_lp_tokens_map().into(),
+        #[cfg(feature = "smart-routing")]
+        token_connections: legacy.token_connections.into(),
+        #[cfg(feature = "smart-routing")]
+        top_pools: RecentlyWrittenMap::from_map(legacy.top_pools, TOP_POOLS_INPLACE_ENTRIES).into(),
+        #[cfg(feature = "smart-routing")]
+        route_cache: Vec::new(),
+        extra: ContractExtra {
+            pools_migration,
+            ..ContractExtra::default()
+        },
+    })
+}
+
+impl State {
+    /// Number of pools stored by previous contract version which are yet to be converted
+    pub(super) fn pools_to_migrate(&self) -> u64 {
+        let dex::Contract::V1(ref contract) = self.0;
+        contract
+            .extra
+            .pools_migration
+            .as_ref()
+            .map_or(0, |migration| migration.legacy_pools.len() as u64)
+    }
+
+    /// Fails if some pools stored by previous contract version aren't converted yet
+    pub(super) fn ensure_pools_migrated(&self) -> Result<()> {
+        ensure_here!(self.pools_to_migrate() == 0, Error::PoolsMigrationPending);
+        Ok(())
+    }
+
+    /// Convert up to `limit` pools stored by previous contract version. Once all pools
+    /// are converted, payable API is resumed, unless it was suspended before upgrade.
+    ///
+    /// # Returns
+    /// Number of pools which are yet to be converted
+    pub(super) fn migrate_pools_batch(&mut self, limit: u32) -> u64 {
+        let factory = unsafe { &mut *addr_of_mut!(super::ITEM_FACTORY) };
+        let dex::Contract::V1(ref mut contract) = self.0;
+        let Some(migration) = contract.extra.pools_migration.as_mut() else {
+            return 0;
+        };
+        // Pools are moved to new map, as they can't be rewritten in-place by map of different value type
+        for _ in 0..limit {
+            let Some((pool_id, (version, pool))) = migration.legacy_pools.pop() else {
+                break;
+            };
+            if version != 0 {
+                env::panic_str("Pool state is corrupted");
+            }
+            let pool = factory.migrate_pool(pool).near_unwrap();
+            contract.pools.insert(&pool_id, &pool);
+        }
+
+        let pools_left = migration.legacy_pools.len() as u64;
+        if pools_left == 0 {
+            contract.suspended = migration.suspended;
+            contract.extra.pools_migration = None;
+        }
+        pools_left
+    }
+}
@@ -1,25 +1,34 @@
 //! NEAR blockchain implementation
-use crate::dex::collection_helpers::{PairKeyIter, StorageRef, StorageRefIter, StorageRefPairIter};
+use crate::dex::collection_helpers::{
+    MapRange, PairKeyIter, StorageRef, StorageRefIter, StorageRefPairIter,
+};
 use crate::dex::{self, KeyAt, Map, PoolId, Result};
-use events::{log_storage_balance_event, Logger};
+use events::Logger;
 use near_contract_standards::fungible_token::core::ext_ft_core;
+use near_contract_standards::fungible_token::metadata::FungibleTokenMetadata;
 use near_contract_standards::storage_management::{StorageBalance, StorageBalanceBounds};
-use near_iterable_maps::{DoublyLinkedListMap, LinkedListMap};
+use near_iterable_maps::{DoublyLinkedListMap, LinkedListMap, RecentlyWrittenMap};
 use near_sdk::borsh::{self, BorshDeserialize, BorshSerialize};
-use near_sdk::collections::{TreeMap, UnorderedMap, UnorderedSet};
+use near_sdk::collections::{LookupMap, LookupSet, TreeMap, UnorderedMap, UnorderedSet};
+use near_sdk::json_types::{U128, U64};
 use near_sdk::Promise;
-use near_sdk::{env, ext_contract, json_types::U128, near_bindgen, Gas, PanicOnDefault};
+use near_sdk::{env, ext_contract, near_bindgen, Balance, Gas, PanicOnDefault};
 use std::ops::{Deref, DerefMut};
+use std::ptr::addr_of_mut;
 use thiserror::Error;
 
 use crate::dex::tick::Tick;
 use crate::dex::TickState;
 use crate::fp::U128X128;
+use crate::{ensure_here, error_here};
 pub use account::{
-    CREATE_POOL_STORAGE, INIT_ACCOUNT_STORAGE, OPEN_POSITION_STORAGE, TOKEN_REGISTER_STORAGE,
+    CREATE_POOL_STORAGE, INIT_ACCOUNT_STORAGE, OPEN_POSITION_STORAGE, PLACE_ORDER_STORAGE,
+    TOKEN_REGISTER_STORAGE,
 };
+pub use events::{EventsSchema, EventsVersion, EventsVersionInfo};
 pub use pairs::Pair;
 pub use types::*;
+pub use wasm_api_types::*;
 
 pub type AccountId = near_sdk::AccountId;
 pub type TokenId = near_sdk::AccountId;
@@ -43,10 +52,16 @@ pub type SqrtpriceSFP = crate::fp::I128X128; // TODO: Replace with I64X128
 pub type AccSqrtpriceSFP = crate::fp::I128X128;
 
 mod account;
-mod events;
+pub(crate) mod events;
+#[cfg(feature = "gas-profile")]
+pub mod gas_profile;
+mod migration;
 mod pairs;
 mod types;
 mod utils;
+mod wasm_api_types;
+#[cfg(feature = "legacy-wasm-api")]
+mod wasm_legacy;
 
 pub mod log;
 pub mod wasm;
@@ -57,10 +72,37 @@ pub use dex::describe_error_code;
 use self::wasm::NearUnwrap;
 /// FIXME: deduce actual amount required for just returning value
 const GAS_FOR_RETURN_AMOUNT: Gas = Gas(1_000_000_000);
-/// `hotfix_insuffient_gas_for_mft_resolve_transfer`.
-const GAS_FOR_RESOLVE_TRANSFER: Gas = Gas(20_000_000_000_000);
-/// Amount of gas for fungible token transfers, increased to 20T to support AS token contracts.
-const GAS_FOR_FT_TRANSFER: Gas = Gas(20_000_000_000_000);
+/// Amount of gas for unwrapping wNEAR used for storage auto-top-up
+const GAS_FOR_NEAR_WITHDRAW: Gas = Gas(10_000_000_000_000);
+/// Amount of gas attached to receiver of LP tokens in `ft_transfer_call`
+#[cfg(feature = "lp-tokens")]
+const GAS_FOR_LP_TOKEN_ON_TRANSFER: Gas = Gas(30_000_000_000_000);
+/// Amount of gas attached to `migrate`, called after deployment of upgraded code
+const GAS_FOR_MIGRATE: Gas = Gas(100_000_000_000_000);
+/// Amount of gas attached to `ft_metadata` of token when its cached metadata is refreshed
+const GAS_FOR_FT_METADATA: Gas = Gas(10_000_000_000_000);
+/// Amount of gas for callback which caches fetched token metadata
+const GAS_FOR_CACHE_TOKEN_METADATA: Gas = Gas(5_000_000_000_000);
+/// Maximal number of tokens whose metadata can be refreshed in one call
+const MAX_TOKENS_METADATA_REFRESH: usize = 10;
+/// Amount of gas attached to `ft_balance_of` of token in reconcile on deposit mode
+const GAS_FOR_FT_BALANCE_OF: Gas = Gas(5_000_000_000_000);
+/// Amount of gas for callback which caches fetched balance of reconciled token
+const GAS_FOR_CACHE_RECONCILED_BALANCE: Gas = Gas(5_000_000_000_000);
+/// Minimal amount of gas for callback which credits deposit of reconciled token;
+/// it also receives unused gas, as it executes actions of `ft_on_transfer` message
+const GAS_FOR_RESOLVE_RECONCILED_DEPOSIT: Gas = Gas(20_000_000_000_000);
+/// Amount of gas for callback which caches outcome of token interface check
+const GAS_FOR_CACHE_TOKEN_INTERFACE: Gas = Gas(5_000_000_000_000);
+/// Minimal amount of gas for callback which credits deposit of token after its interface check;
+/// it also receives unused gas, as it executes actions of `ft_on_transfer` message
+const GAS_FOR_RESOLVE_CHECKED_DEPOSIT: Gas = Gas(20_000_000_000_000);
+
+/// Converts amount of gas in Tgas, as configured in `dex::GasConfig`
+const fn tgas(amount: u64) -> Gas {
+    Gas(amount * Gas::ONE_TERA.0)
+}
+
 /// Maximum value for price tick
 pub const MAX_TICK: i32 = 887_273;
 /// Minimum value for price tick
@@ -71,6 +113,29 @@ pub const MAX_EFF_TICK: i32 = MAX_TICK + 2i32.pow(NUM_FEE_LEVELS as u32 - 1);
 
 /// Number of precalculated ticks
 pub const NUM_PRECALCULATED_TICKS: usize = 20;
+/// Number of most recent asynchronous operations whose callbacks are expected. Callbacks of older
+/// operations are considered lost, e.g. when they ran out of gas, so their ids are dropped
+/// instead of staying in storage forever. Callbacks arrive within few blocks, and contract
+/// can't start that many operations in so short time.
+const MAX_PENDING_CALLBACKS: u64 = 10_000;
+/// Number of most recently written position-to-pool entries kept in contract state record
+///
+/// Contract state record is read and written by every call. At runtime fees of 5.6 Mgas per byte
+/// read and 31 Mgas per byte written, in-place entry of about 70 bytes (position id, two token ids,
+/// length prefixes) costs every call about 2.6 Ggas, while it saves a storage read of
+/// at least 56 Ggas once accessed, i.e. pays off if accessed once per about 20 calls.
+/// Individual positions are rarely accessed that often, except right after they're opened
+/// by rebalancing bots, so only few entries are kept.
+const POSITION_TO_POOL_INPLACE_ENTRIES: u32 = 4;
+/// Number of most recently written top pools entries kept in contract state record
+///
+/// Entry takes about 70 bytes (token id, handle of tokens set), so as with
+/// `POSITION_TO_POOL_INPLACE_ENTRIES`, it pays off if accessed once per about 20 calls.
+/// Top pools are read by every routed swap for its input token, and rewritten when any pool
+/// of the token is re-ranked, so only hub tokens, which have most pools and are in most routes,
+/// reach that rate.
+#[cfg(feature = "smart-routing")]
+const TOP_POOLS_INPLACE_ENTRIES: u32 = 4;
 
 crate::wrap_float! {
     #[derive(BorshDeserialize, BorshSerialize)]
@@ -184,8 +249,12 @@ crate::custom_error! {
         AtLeastOneYocto,
         #[error("Invalid argument")]
         InvalidArgument,
-        #[error("Wrong message format. Message passed to `ft_on_transfer` must be either empty string or JSON-encoded list of actions. Parsing error: {0}")]
+        #[error("Wrong message format. Message passed to `ft_on_transfer` must be either empty string, JSON-encoded envelope of actions or JSON-encoded list of actions. Parsing error: {0}")]
         WrongMsgFormat(near_sdk::serde_json::Error),
+        #[error("Unsupported version {version} of `ft_on_transfer` message, versions up to {supported} are supported")]
+        UnsupportedMsgVersion { version: u64, supported: u64 },
+        #[error("Wrong format of action #{0} in `ft_on_transfer` message, it may be unsupported by this contract version. Parsing error: {1}")]
+        WrongActionFormat(usize, near_sdk::serde_json::Error),
         #[error("`RegisterAccount` action isn't allowed during token transfer - no way to provide storage deposit")]
         RegisterAccountNotAllowedOnDeposit,
         #[error("Received incorrect number of result values from previous async operation")]
@@ -196,6 +265,12 @@ crate::custom_error! {
         PromiseFailed,
         #[error("Could not parse result of previous async operation as {0}: parse error {1}")]
         PromiseResultParseFailed(&'static str, near_sdk::serde_json::Error),
+        #[error("Callback of operation {0} isn't expected: operation is unknown or already completed")]
+        UnexpectedCallback(u64),
+        #[error("Token {0} isn't verified")]
+        TokenNotVerified(AccountId),
+        #[error("Pools stored by previous contract version aren't migrated yet, see `migrate_pools`")]
+        PoolsMigrationPending,
     }
 }
 
@@ -211,17 +286,46 @@ pub type Position = dex::Position<Types>;
 
 impl dex::Types for Types {
     type Bound = ();
-    type ContractExtra = ();
+    type ContractExtra = ContractExtra;
     type AccountsMap = AccountsMap;
     type TickStatesMap = TreeMap<Tick, TickState<Types>>;
     type AccountTokenBalancesMap = DoublyLinkedListMap<AccountId, Amount>;
     type AccountWithdrawTracker = dex::withdraw_trackers::FullTracker;
     type AccountExtra = account::Extra;
+    type AccountExtraV0 = account::ExtraV0;
     type PoolsMap = LinkedListMap<PoolId, Pool>;
     type PoolPositionsMap = DoublyLinkedListMap<dex::PositionId, Position>;
+    type PoolLevelsMap = LinkedListMap<dex::FeeLevel, dex::LevelState<Types>>;
     type AccountPositionsSet = UnorderedSet<dex::PositionId>;
     type VerifiedTokensSet = UnorderedSet<TokenId>;
-    type PositionToPoolMap = DoublyLinkedListMap<dex::PositionId, PoolId>;
+    type PositionToPoolMap = RecentlyWrittenMap<dex::PositionId, PoolId>;
+    type PositionOwnersMap = DoublyLinkedListMap<dex::PositionId, AccountId>;
+    type ScheduledActionsMap = DoublyLinkedListMap<u64, dex::ScheduledAction>;
+    type PendingApprovalsMap = DoublyLinkedListMap<dex::ActionHash, dex::PendingApproval>;
+    type WithdrawLimitsMap = DoublyLinkedListMap<TokenId, dex::WithdrawLimit>;
+    type SwapGuardsMap = DoublyLinkedListMap<PoolId, dex::SwapGuard>;
+    type InsuranceReserveMap = DoublyLinkedListMap<TokenId, Amount>;
+    type BannedTokensSet = UnorderedSet<TokenId>;
+    type MinDepositAmountsMap = DoublyLinkedListMap<PoolId, (Amount, Amount)>;
+    type ClassicPoolsMap = DoublyLinkedListMap<PoolId, dex::ClassicPool<Types>>;
+    type StablePoolsMap = DoublyLinkedListMap<PoolId, dex::StablePool<Types>>;
+    type PoolSharesMap = DoublyLinkedListMap<AccountId, Amount>;
+    type OrdersMap = DoublyLinkedListMap<u64, dex::ConditionalOrder>;
+    type FeeAuctionsMap = DoublyLinkedListMap<TokenId, dex::FeeAuction>;
+    type IntegrationsMap = DoublyLinkedListMap<dex::IntegrationId, dex::Integration>;
+    type DepositIntentsMap = DoublyLinkedListMap<u64, dex::DepositIntent>;
+    type AutoHarvestMap = DoublyLinkedListMap<PoolId, dex::AutoHarvestQueue>;
+    type ViewGrantsMap = DoublyLinkedListMap<AccountId, Vec<AccountId>>;
+    type PoolAliasesMap = DoublyLinkedListMap<String, PoolId>;
+    type AliasesByPoolMap = DoublyLinkedListMap<PoolId, String>;
+    type PoolStorageDepositsMap = DoublyLinkedListMap<PoolId, dex::PoolStorageDeposit>;
+    type AdminLogMap = DoublyLinkedListMap<u64, dex::AdminLogEntry>;
+    type VaultsMap = DoublyLinkedListMap<AccountId, dex::Vault>;
+    type SessionKeysMap = DoublyLinkedListMap<AccountId, Vec<dex::SessionKey>>;
+    type ReconciledTokensMap = DoublyLinkedListMap<TokenId, dex::ReconciledBalance>;
+    type TokenInterfacesMap = DoublyLinkedListMap<TokenId, bool>;
+    #[cfg(feature = "lp-tokens")]
+    type LpTokensMap = DoublyLinkedListMap<dex::LpTokenId, dex::LpToken<Types>>;
     type AccountIdSet = UnorderedSet<AccountId>;
     #[cfg(feature = "smart-routing")]
     type TokenConnectionsMap = DoublyLinkedListMap<TokenId, Self::TokensSet>;
@@ -230,7 +334,50 @@ impl dex::Types for Types {
     #[cfg(feature = "smart-routing")]
     type TokensArraySet = UnorderedSet<TokenId>;
     #[cfg(feature = "smart-routing")]
-    type TopPoolsMap = DoublyLinkedListMap<TokenId, Self::TokensArraySet>;
+    type TopPoolsMap = RecentlyWrittenMap<TokenId, Self::TokensArraySet>;
+
+    fn storage_usage() -> u64 {
+        env::storage_usage()
+    }
+}
+
+/// NEAR-specific extra data of contract
+#[derive(BorshSerialize, BorshDeserialize)]
+pub struct ContractExtra {
+    /// Id of the next asynchronous operation which expects callback
+    next_callback_id: u64,
+    /// Ids of operations whose callbacks are expected. Callback must present its id,
+    /// which is consumed, so callbacks can't be spoofed or replayed
+    /// even if `#[private]` guard is lost in refactoring.
+    /// Only last `MAX_PENDING_CALLBACKS` ids are kept
+    pending_callbacks: LookupSet<u64>,
+    /// Metadata of verified tokens, fetched with `refresh_tokens_metadata`
+    tokens_metadata: LookupMap<TokenId, CachedTokenMetadata>,
+    /// Pools stored by previous contract version which are yet to be converted
+    /// by `migrate_pools`; `None` once all of them are converted
+    pools_migration: Option<migration::PoolsMigration>,
+}
+
+impl Default for ContractExtra {
+    fn default() -> Self {
+        Self {
+            next_callback_id: 0,
+            pending_callbacks: LookupSet::new(ItemFactory::next_prefix()),
+            tokens_metadata: LookupMap::new(ItemFactory::next_prefix()),
+            pools_migration: None,
+        }
+    }
+}
+
+/// Part of NEP-148 metadata of token which frontends need to display it
+#[derive(BorshSerialize, BorshDeserialize)]
+pub struct CachedTokenMetadata {
+    pub symbol: String,
+    pub decimals: u8,
+    /// SHA-256 hash of token icon, which is usually too large to be cached itself
+    pub icon_hash: Option<[u8; 32]>,
+    /// Moment when metadata was fetched, in seconds
+    pub updated_at: u64,
 }
 
 #[near_bindgen]
@@ -243,14 +390,154 @@ impl dex::State<Types> for State {
     }
 }
 
-static mut LOGGER: Logger = Logger;
+static mut LOGGER: Logger = Logger::new();
 
 impl State {
-    fn delay_return_option_amount(amount: Option<wasm::WasmAmount>) -> near_sdk::Promise {
+    fn delay_return_option_amount(
+        &mut self,
+        amount: Option<wasm::WasmAmount>,
+    ) -> near_sdk::Promise {
+        let callback_id = self.expect_callback();
         ext_self::ext(env::current_account_id())
             .with_attached_deposit(0u128)
             .with_static_gas(GAS_FOR_RETURN_AMOUNT)
-            .return_option_amount(amount)
+            .return_option_amount(amount, callback_id.into())
+    }
+
+    /// Register asynchronous operation whose callback is expected
+    ///
+    /// # Returns
+    /// Id which callback must present
+    fn expect_callback(&mut self) -> u64 {
+        let dex::Contract::V1(ref mut contract) = self.0;
+        let extra = &mut contract.extra;
+        let callback_id = extra.next_callback_id;
+        extra.next_callback_id += 1;
+        extra.pending_callbacks.insert(&callback_id);
+        if let Some(lost_callback_id) = callback_id.checked_sub(MAX_PENDING_CALLBACKS) {
+            extra.pending_callbacks.remove(&lost_callback_id);
+        }
+        callback_id
+    }
+
+    /// Consume id presented by callback; fails if callback isn't expected
+    fn consume_callback(&mut self, callback_id: u64) -> Result<()> {
+        let dex::Contract::V1(ref mut contract) = self.0;
+        let expected = contract.extra.pending_callbacks.remove(&callback_id);
+        ensure_here!(expected, Error::UnexpectedCallback(callback_id));
+        Ok(())
+    }
+
+    /// Fetch metadata of verified tokens from their contracts, caching it in callbacks
+    fn fetch_tokens_metadata(&mut self, tokens: Vec<TokenId>) -> Result<Promise> {
+        ensure_here!(
+            tokens.len() <= MAX_TOKENS_METADATA_REFRESH,
+            Error::InvalidArgument
+        );
+        let mut promises = Vec::with_capacity(tokens.len());
+        for token_id in tokens {
+            let dex::Contract::V1(ref contract) = self.0;
+            ensure_here!(
+                contract.verified_tokens.contains(&token_id),
+                Error::TokenNotVerified(token_id.clone())
+            );
+            let callback_id = self.expect_callback();
+            promises.push(
+                ext_ft_metadata::ext(token_id.clone())
+                    .with_static_gas(GAS_FOR_FT_METADATA)
+                    .ft_metadata()
+                    .then(
+                        ext_self::ext(env::current_account_id())
+                            .with_static_gas(GAS_FOR_CACHE_TOKEN_METADATA)
+                            .cache_token_metadata(token_id, callback_id.into()),
+                    ),
+            );
+        }
+        Self::fold_promises(promises).ok_or(error_here!(Error::InvalidArgument))
+    }
+
+    /// Fetch contract's own balance of token put into reconcile on deposit mode,
+    /// caching it in callback as tracked balance
+    fn fetch_reconciled_balance(&mut self, token_id: TokenId) -> Result<Promise> {
+        // Storage top-up unwraps wNEAR without sending it, which tracked balance doesn't see
+        ensure_here!(token_id != wnear_id(), Error::InvalidArgument);
+        let callback_id = self.expect_callback();
+        Ok(ext_ft_core::ext(token_id.clone())
+            .with_static_gas(GAS_FOR_FT_BALANCE_OF)
+            .ft_balance_of(env::current_account_id())
+            .then(
+                ext_self::ext(env::current_account_id())
+                    .with_static_gas(GAS_FOR_CACHE_RECONCILED_BALANCE)
+                    .cache_reconciled_balance(token_id, callback_id.into()),
+            ))
+    }
+
+    /// Fetch contract's own balance of reconciled token after it was transferred,
+    /// and credit transfer by balance delta in callback, see `Dex::reconcile_deposit`
+    fn reconcile_transfer(
+        &mut self,
+        token_id: TokenId,
+        sender_id: AccountId,
+        amount: U128,
+        msg: String,
+    ) -> Promise {
+        let callback_id = self.expect_callback();
+        ext_ft_core::ext(token_id.clone())
+            .with_static_gas(GAS_FOR_FT_BALANCE_OF)
+            .ft_balance_of(env::current_account_id())
+            .then(
+                ext_self::ext(env::current_account_id())
+                    .with_static_gas(GAS_FOR_RESOLVE_RECONCILED_DEPOSIT)
+                    .resolve_reconciled_deposit(
+                        token_id,
+                        sender_id,
+                        amount,
+                        msg,
+                        callback_id.into(),
+                    ),
+            )
+    }
+
+    /// Call `ft_metadata` and `ft_balance_of` of token, to check that it implements
+    /// fungible token interface. `ft_transfer` can't be called without moving tokens,
+    /// so `ft_balance_of` of the same NEP-141 core stands for it
+    fn ping_token_interface(token_id: &TokenId) -> Promise {
+        ext_ft_metadata::ext(token_id.clone())
+            .with_static_gas(GAS_FOR_FT_METADATA)
+            .ft_metadata()
+            .and(
+                ext_ft_core::ext(token_id.clone())
+                    .with_static_gas(GAS_FOR_FT_BALANCE_OF)
+                    .ft_balance_of(env::current_account_id()),
+            )
+    }
+
+    /// Check token interface while check is enabled, caching outcome in callback
+    fn fetch_token_interface(&mut self, token_id: TokenId) -> Result<Promise> {
+        self.as_dex_mut().request_token_interface_check(&token_id)?;
+        let callback_id = self.expect_callback();
+        Ok(Self::ping_token_interface(&token_id).then(
+            ext_self::ext(env::current_account_id())
+                .with_static_gas(GAS_FOR_CACHE_TOKEN_INTERFACE)
+                .cache_token_interface(token_id, callback_id.into()),
+        ))
+    }
+
+    /// Check interface of token transferred for the first time, and credit transfer
+    /// in callback if check passes, see `resolve_checked_deposit`
+    fn check_token_transfer(
+        &mut self,
+        token_id: TokenId,
+        sender_id: AccountId,
+        amount: U128,
+        msg: String,
+    ) -> Promise {
+        let callback_id = self.expect_callback();
+        Self::ping_token_interface(&token_id).then(
+            ext_self::ext(env::current_account_id())
+                .with_static_gas(GAS_FOR_RESOLVE_CHECKED_DEPOSIT)
+                .resolve_checked_deposit(token_id, sender_id, amount, msg, callback_id.into()),
+        )
     }
 
     fn fold_promises(
@@ -265,12 +552,16 @@ impl dex::StateMut<Types> for State {
     type SendTokensExtraParam = ();
 
     fn members_mut(&mut self) -> dex::StateMembersMut<'_, Types> {
+        let dex::Contract::V1(ref contract) = self.0;
+        // Actually safe - contract is executed in single thread,
+        // and logger only keeps copy of event filter
+        let logger = unsafe { &mut *addr_of_mut!(LOGGER) };
+        logger.set_filter(contract.event_filter);
         dex::StateMembersMut {
             contract: &mut self.0,
             // Actually safe - ItemFactory is zero-sized
-            item_factory: unsafe { &mut ITEM_FACTORY },
-            // Actually safe - Logger is zero-sized
-            logger: unsafe { &mut LOGGER },
+            item_factory: unsafe { &mut *addr_of_mut!(ITEM_FACTORY) },
+            logger,
         }
     }
 
@@ -284,11 +575,14 @@ impl dex::StateMut<Types> for State {
     ) -> Self::SendTokensResult {
         // Event logging and deregistration should be handled by callback,
         // here we only start tracking
-        let dex::Contract::V0(ref mut contract) = self.contract_mut();
+        let callback_id = self.expect_callback();
+        self.as_dex_mut().begin_reconciled_outflow(token_id, amount);
+        let dex::Contract::V1(ref mut contract) = self.contract_mut();
+        let gas_config = contract.gas_config;
 
         contract
             .accounts
-            .try_update(account_id, |dex::Account::V0(acc)| {
+            .try_update(account_id, |dex::Account::V1(acc)| {
                 acc.withdraw_tracker.track(token_id.clone(), amount);
                 Ok(())
             })
@@ -296,11 +590,11 @@ impl dex::StateMut<Types> for State {
         // Token transfer
         let builder_ft_core_ext = ext_ft_core::ext(token_id.clone())
             .with_attached_deposit(1u128)
-            .with_static_gas(GAS_FOR_FT_TRANSFER);
+            .with_static_gas(tgas(gas_config.ft_transfer));
         // Result callback
         let builder_ext_self = ext_self::ext(env::current_account_id())
             .with_attached_deposit(0u128)
-            .with_static_gas(GAS_FOR_RESOLVE_TRANSFER);
+            .with_static_gas(tgas(gas_config.resolve_transfer));
         // Finally, promise chain
         builder_ft_core_ext
             .ft_transfer(account_id.clone(), U128::from(amount), None)
@@ -309,9 +603,20 @@ impl dex::StateMut<Types> for State {
                 account_id.clone(),
                 U128::from(amount),
                 unregister,
+                callback_id.into(),
             ))
     }
 
+    fn notify_swap_hook(&mut self, hook_id: &AccountId, notification: dex::SwapNotification) {
+        // Promise is not returned, so it's detached from current call chain
+        // and its failure cannot affect the swap
+        let dex::Contract::V1(ref contract) = self.0;
+        ext_swap_hook::ext(hook_id.clone())
+            .with_attached_deposit(0u128)
+            .with_static_gas(tgas(contract.gas_config.swap_hook))
+            .on_swap(notification);
+    }
+
     fn get_initiator_id(&self) -> AccountId {
         env::signer_account_id()
     }
@@ -319,6 +624,22 @@ impl dex::StateMut<Types> for State {
     fn get_caller_id(&self) -> AccountId {
         env::predecessor_account_id()
     }
+
+    fn get_block_timestamp(&self) -> u64 {
+        env::block_timestamp() / 1_000_000_000
+    }
+
+    fn get_block_height(&self) -> u64 {
+        env::block_height()
+    }
+
+    fn get_signer_public_key(&self) -> Option<String> {
+        Some(String::from(&env::signer_account_pk()))
+    }
+
+    fn get_random_seed(&self) -> [u8; 32] {
+        env::random_seed_array()
+    }
 }
 /// Internal methods implementation.
 impl State {
@@ -387,8 +708,10 @@ impl dex::Map for AccountsMap {
         update_fn: F,
     ) -> Option<Result<R>> {
         self.get(key).map(|mut value| {
-            update_fn(&mut value)
-                .and_then(|result| save_account(&mut self.0, key, value).map(|()| result))
+            let storage_before = account_storage_usage(&value);
+            update_fn(&mut value).and_then(|result| {
+                save_account(&mut self.0, key, value, storage_before).map(|()| result)
+            })
         })
     }
 
@@ -406,8 +729,13 @@ impl dex::Map for AccountsMap {
             .get(key)
             .map_or_else(|| (factory_fn(), false), |value| (Ok(value), true));
         let mut value = value?;
+        let storage_before = if exists {
+            account_storage_usage(&value)
+        } else {
+            0
+        };
         let result = update_fn(&mut value, exists)?;
-        save_account(&mut self.0, key, value)?;
+        save_account(&mut self.0, key, value, storage_before)?;
         Ok(result)
     }
 
@@ -422,21 +750,41 @@ impl dex::MapRemoveKey for AccountsMap {
     }
 }
 
+fn account_storage_usage(account: &Account) -> Balance {
+    let dex::Account::V1(ref account_v0) = account;
+    account_v0.storage_usage()
+}
+
+/// Store updated account, checking its storage is covered
+///
+/// # Parameters
+/// * `storage_before` - storage usage of account before update, or zero for new account;
+///     only updates which grow storage may top it up from wNEAR
 fn save_account(
     map: &mut DoublyLinkedListMap<AccountId, Account>,
     account_id: &AccountId,
-    account: Account,
+    mut account: Account,
+    storage_before: Balance,
 ) -> Result<()> {
     let storage_deposit_total;
     let storage_available;
     {
-        let dex::Account::V0(ref account_v0) = account;
+        let dex::Account::V1(ref mut account_v0) = account;
+        if account_v0.storage_usage() > storage_before {
+            account_v0.top_up_storage_from_wnear()?;
+        }
         account_v0.ensure_storage_usage()?;
         storage_deposit_total = account_v0.extra.near_amount;
         storage_available = account_v0.storage_available();
     }
     map.insert(account_id, account);
-    log_storage_balance_event(account_id, &storage_available, &storage_deposit_total);
+    // Actually safe - contract is executed in single thread; event filter is applied
+    // when contract members are accessed, which always precedes account saving
+    unsafe { &mut *addr_of_mut!(LOGGER) }.log_storage_balance_event(
+        account_id,
+        &storage_available,
+        &storage_deposit_total,
+    );
     Ok(())
 }
 
@@ -448,13 +796,83 @@ trait Exchange {
         sender_id: AccountId,
         amount: U128,
         unregister: bool,
+        callback_id: U64,
     );
 
-    fn return_option_amount(&self, amount: Option<wasm::WasmAmount>) -> Option<wasm::WasmAmount>;
+    fn return_option_amount(
+        &mut self,
+        amount: Option<wasm::WasmAmount>,
+        callback_id: U64,
+    ) -> Option<wasm::WasmAmount>;
 
     fn do_wnear_register(&mut self) -> Promise;
 
     fn finish_wnear_register(&mut self);
+
+    fn lp_ft_resolve_transfer(
+        &mut self,
+        token_id: String,
+        sender_id: AccountId,
+        receiver_id: AccountId,
+        amount: U128,
+    ) -> U128;
+
+    fn cache_token_metadata(&mut self, token_id: AccountId, callback_id: U64);
+
+    fn cache_reconciled_balance(&mut self, token_id: AccountId, callback_id: U64);
+
+    fn resolve_reconciled_deposit(
+        &mut self,
+        token_id: AccountId,
+        sender_id: AccountId,
+        amount: U128,
+        msg: String,
+        callback_id: U64,
+    ) -> near_sdk::PromiseOrValue<U128>;
+
+    fn cache_token_interface(&mut self, token_id: AccountId, callback_id: U64);
+
+    fn resolve_checked_deposit(
+        &mut self,
+        token_id: AccountId,
+        sender_id: AccountId,
+        amount: U128,
+        msg: String,
+        callback_id: U64,
+    ) -> near_sdk::PromiseOrValue<U128>;
+}
+
+#[ext_contract(ext_ft_metadata)]
+trait FungibleTokenMetadataProvider {
+    fn ft_metadata(&self) -> FungibleTokenMetadata;
+}
+
+#[ext_contract(ext_swap_hook)]
+trait SwapHookReceiver {
+    fn on_swap(&mut self, notification: dex::SwapNotification);
+}
+
+/// Receiver of LP tokens sent with `ft_transfer_call`, same as multi-token receiver of REF
+#[cfg(feature = "lp-tokens")]
+#[ext_contract(ext_lp_token_receiver)]
+trait LpTokenReceiver {
+    fn mft_on_transfer(
+        &mut self,
+        token_id: String,
+        sender_id: AccountId,
+        amount: U128,
+        msg: String,
+    ) -> near_sdk::PromiseOrValue<U128>;
+}
+
+/// Wrapped NEAR token contract on the network where DEX is deployed
+fn wnear_id() -> AccountId {
+    let wnear_id = if env::current_account_id().as_str().ends_with(".testnet") {
+        "wrap.testnet"
+    } else {
+        "wrap.near"
+    };
+    wnear_id.parse().unwrap()
 }
 
 #[ext_contract(ext_wrap_near)]
@@ -525,6 +943,15 @@ impl ItemFactory {
     >() -> DoublyLinkedListMap<K, V> {
         DoublyLinkedListMap::new(Self::next_prefix())
     }
+
+    fn new_recently_written_map<
+        K: Ord + Clone + BorshSerialize + BorshDeserialize,
+        V: BorshSerialize + BorshDeserialize,
+    >(
+        capacity: u32,
+    ) -> RecentlyWrittenMap<K, V> {
+        RecentlyWrittenMap::new(Self::next_prefix(), capacity)
+    }
 }
 
 impl dex::ItemFactory<Types> for ItemFactory {
@@ -552,6 +979,10 @@ impl dex::ItemFactory<Types> for ItemFactory {
         Self::new_doubly_linked_list_map()
     }
 
+    fn new_pool_levels_map(&mut self) -> <Types as dex::Types>::PoolLevelsMap {
+        Self::new_linked_list_map()
+    }
+
     fn new_account_positions_set(&mut self) -> UnorderedSet<dex::PositionId> {
         Self::new_set()
     }
@@ -561,6 +992,10 @@ impl dex::ItemFactory<Types> for ItemFactory {
     }
 
     fn new_position_to_pool_map(&mut self) -> <Types as dex::Types>::PositionToPoolMap {
+        Self::new_recently_written_map(POSITION_TO_POOL_INPLACE_ENTRIES)
+    }
+
+    fn new_position_owners_map(&mut self) -> <Types as dex::Types>::PositionOwnersMap {
         Self::new_doubly_linked_list_map()
     }
 
@@ -568,6 +1003,111 @@ impl dex::ItemFactory<Types> for ItemFactory {
         Self::new_set()
     }
 
+    fn new_scheduled_actions_map(&mut self) -> <Types as dex::Types>::ScheduledActionsMap {
+        Self::new_doubly_linked_list_map()
+    }
+
+    fn new_admins(&mut self) -> <Types as dex::Types>::AccountIdSet {
+        Self::new_set()
+    }
+
+    fn new_pending_approvals_map(&mut self) -> <Types as dex::Types>::PendingApprovalsMap {
+        Self::new_doubly_linked_list_map()
+    }
+
+    fn new_withdraw_limits_map(&mut self) -> <Types as dex::Types>::WithdrawLimitsMap {
+        Self::new_doubly_linked_list_map()
+    }
+
+    fn new_swap_guards_map(&mut self) -> <Types as dex::Types>::SwapGuardsMap {
+        Self::new_doubly_linked_list_map()
+    }
+
+    fn new_insurance_reserve_map(&mut self) -> <Types as dex::Types>::InsuranceReserveMap {
+        Self::new_doubly_linked_list_map()
+    }
+
+    fn new_banned_tokens_set(&mut self) -> <Types as dex::Types>::BannedTokensSet {
+        Self::new_set()
+    }
+
+    fn new_min_deposit_amounts_map(&mut self) -> <Types as dex::Types>::MinDepositAmountsMap {
+        Self::new_doubly_linked_list_map()
+    }
+
+    fn new_classic_pools_map(&mut self) -> <Types as dex::Types>::ClassicPoolsMap {
+        Self::new_doubly_linked_list_map()
+    }
+
+    fn new_stable_pools_map(&mut self) -> <Types as dex::Types>::StablePoolsMap {
+        Self::new_doubly_linked_list_map()
+    }
+
+    fn new_pool_shares_map(&mut self) -> <Types as dex::Types>::PoolSharesMap {
+        Self::new_doubly_linked_list_map()
+    }
+
+    fn new_orders_map(&mut self) -> <Types as dex::Types>::OrdersMap {
+        Self::new_doubly_linked_list_map()
+    }
+
+    fn new_fee_auctions_map(&mut self) -> <Types as dex::Types>::FeeAuctionsMap {
+        Self::new_doubly_linked_list_map()
+    }
+
+    fn new_integrations_map(&mut self) -> <Types as dex::Types>::IntegrationsMap {
+        Self::new_doubly_linked_list_map()
+    }
+
+    fn new_deposit_intents_map(&mut self) -> <Types as dex::Types>::DepositIntentsMap {
+        Self::new_doubly_linked_list_map()
+    }
+
+    fn new_auto_harvest_map(&mut self) -> <Types as dex::Types>::AutoHarvestMap {
+        Self::new_doubly_linked_list_map()
+    }
+
+    fn new_view_grants_map(&mut self) -> <Types as dex::Types>::ViewGrantsMap {
+        Self::new_doubly_linked_list_map()
+    }
+
+    fn new_pool_aliases_map(&mut self) -> <Types as dex::Types>::PoolAliasesMap {
+        Self::new_doubly_linked_list_map()
+    }
+
+    fn new_aliases_by_pool_map(&mut self) -> <Types as dex::Types>::AliasesByPoolMap {
+        Self::new_doubly_linked_list_map()
+    }
+
+    fn new_pool_storage_deposits_map(&mut self) -> <Types as dex::Types>::PoolStorageDepositsMap {
+        Self::new_doubly_linked_list_map()
+    }
+
+    fn new_admin_log_map(&mut self) -> <Types as dex::Types>::AdminLogMap {
+        Self::new_doubly_linked_list_map()
+    }
+
+    fn new_vaults_map(&mut self) -> <Types as dex::Types>::VaultsMap {
+        Self::new_doubly_linked_list_map()
+    }
+
+    fn new_session_keys_map(&mut self) -> <Types as dex::Types>::SessionKeysMap {
+        Self::new_doubly_linked_list_map()
+    }
+
+    fn new_reconciled_tokens_map(&mut self) -> <Types as dex::Types>::ReconciledTokensMap {
+        Self::new_doubly_linked_list_map()
+    }
+
+    fn new_token_interfaces_map(&mut self) -> <Types as dex::Types>::TokenInterfacesMap {
+        Self::new_doubly_linked_list_map()
+    }
+
+    #[cfg(feature = "lp-tokens")]
+    fn new_lp_tokens_map(&mut self) -> <Types as dex::Types>::LpTokensMap {
+        Self::new_doubly_linked_list_map()
+    }
+
     #[cfg(feature = "smart-routing")]
     fn new_token_connections_map(&mut self) -> <Types as dex::Types>::TokenConnectionsMap {
         Self::new_doubly_linked_list_map()
@@ -575,7 +1115,7 @@ impl dex::ItemFactory<Types> for ItemFactory {
 
     #[cfg(feature = "smart-routing")]
     fn new_top_pools_map(&mut self) -> <Types as dex::Types>::TopPoolsMap {
-        Self::new_doubly_linked_list_map()
+        Self::new_recently_written_map(TOP_POOLS_INPLACE_ENTRIES)
     }
 
     #[cfg(feature = "smart-routing")]
@@ -933,6 +1473,18 @@ where
     fn insert(&mut self, key: K, value: V) {
         self.insert(&key, value);
     }
+
+    fn insert_many<I: IntoIterator<Item = (K, V)>>(&mut self, entries: I) {
+        self.extend(entries);
+    }
+
+    fn range<'a>(&'a self, from: Option<&K>, to: Option<&K>) -> MapRange<K, Self::Iter<'a>> {
+        let iter = match from {
+            Some(key) => self.iter_from(key),
+            None => self.into_iter(),
+        };
+        MapRange::new(StorageRefPairIter::new(iter), None, to.cloned())
+    }
 }
 
 impl<K, V> dex::MapRemoveKey for DoublyLinkedListMap<K, V>
@@ -945,6 +1497,87 @@ where
     }
 }
 
+pub type RecentlyWrittenMapIter<'a, K, V> =
+    StorageRefPairIter<'a, K, V, <&'a RecentlyWrittenMap<K, V> as IntoIterator>::IntoIter>;
+
+impl<K, V> dex::Map for RecentlyWrittenMap<K, V>
+where
+    K: Ord + Clone + BorshSerialize + BorshDeserialize,
+    V: BorshSerialize + BorshDeserialize,
+{
+    type Key = K;
+    type Value = V;
+    type KeyRef<'a> = StorageRef<'a, K> where Self: 'a;
+    type ValueRef<'a> = StorageRef<'a, V> where Self: 'a;
+    type Iter<'a> = RecentlyWrittenMapIter<'a, K, V> where Self: 'a;
+
+    fn iter(&self) -> Self::Iter<'_> {
+        StorageRefPairIter::new(self.into_iter())
+    }
+
+    fn clear(&mut self) {
+        self.clear();
+    }
+
+    #[allow(clippy::cast_possible_truncation)]
+    fn len(&self) -> usize {
+        self.len()
+    }
+
+    fn is_empty(&self) -> bool {
+        self.is_empty()
+    }
+
+    fn contains_key(&self, key: &K) -> bool {
+        self.contains_key(key)
+    }
+
+    fn inspect<R, F: FnOnce(&V) -> R>(&self, key: &K, inspect_fn: F) -> Option<R> {
+        self.get(key).map(|value| inspect_fn(&value))
+    }
+
+    fn update<R, F: FnOnce(&mut V) -> Result<R>>(
+        &mut self,
+        key: &K,
+        update_fn: F,
+    ) -> Option<Result<R>> {
+        self.get(key).map(|mut value| {
+            update_fn(&mut value).map(|result| {
+                self.insert(key, value);
+                result
+            })
+        })
+    }
+
+    fn update_or_insert<R, F, U>(&mut self, key: &K, factory_fn: F, update_fn: U) -> Result<R>
+    where
+        F: FnOnce() -> Result<V>,
+        U: FnOnce(&mut V, /* exists */ bool) -> Result<R>,
+    {
+        let (value, exists) = self
+            .get(key)
+            .map_or_else(|| (factory_fn(), false), |value| (Ok(value), true));
+        let mut value = value?;
+        let result = update_fn(&mut value, exists)?;
+        self.insert(key, value);
+        Ok(result)
+    }
+
+    fn insert(&mut self, key: K, value: V) {
+        self.insert(&key, value);
+    }
+}
+
+impl<K, V> dex::MapRemoveKey for RecentlyWrittenMap<K, V>
+where
+    K: Ord + Clone + BorshSerialize + BorshDeserialize,
+    V: BorshSerialize + BorshDeserialize,
+{
+    fn remove(&mut self, key: &K) {
+        self.remove(key);
+    }
+}
+
 pub type LinkedListMapIter<'a, K, V> =
     StorageRefPairIter<'a, K, V, <&'a LinkedListMap<K, V> as IntoIterator>::IntoIter>;
 
@@ -1014,4 +1647,16 @@ where
     fn insert(&mut self, key: K, value: V) {
         self.insert(&key, &value);
     }
+
+    fn insert_many<I: IntoIterator<Item = (K, V)>>(&mut self, entries: I) {
+        self.extend(entries);
+    }
+
+    fn range<'a>(&'a self, from: Option<&K>, to: Option<&K>) -> MapRange<K, Self::Iter<'a>> {
+        let iter = match from {
+            Some(key) => self.iter_from(key),
+            None => self.into_iter(),
+        };
+        MapRange::new(StorageRefPairIter::new(iter), None, to.cloned())
+    }
 }
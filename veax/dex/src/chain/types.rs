@@ -1,12 +1,45 @@
-use super::TokenId;
+use super::{AmountUFP, LPFeePerFeeLiquidity, Liquidity, Pair, TokenId};
 use crate::dex::latest::RawFeeLevelsArray;
 use crate::dex::tick::Tick;
 use crate::dex::{self, BasisPoints, PairExt};
 use crate::error_here;
-use crate::fp::U128X128;
-use near_sdk::json_types::U128;
-use near_sdk::serde::{Deserialize, Serialize};
+use crate::fp::{U128X128, U192X64, U256};
+use near_sdk::json_types::{Base58CryptoHash, U128, U64};
+use near_sdk::serde::de::Error as _;
+use near_sdk::serde::{Deserialize, Deserializer, Serialize, Serializer};
 use near_sdk::AccountId;
+use std::collections::HashMap;
+
+/// Lossless JSON representation of `Liquidity`:
+/// `0x`-prefixed hex string of raw 256-bit value, i.e. liquidity scaled by 2^64.
+/// Unlike `f64`, decodes into exactly the same value on any client.
+#[derive(Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(not(target_arch = "wasm32"), derive(Debug))]
+pub struct LiquidityHex(pub Liquidity);
+
+impl From<Liquidity> for LiquidityHex {
+    fn from(liquidity: Liquidity) -> Self {
+        Self(liquidity)
+    }
+}
+
+impl Serialize for LiquidityHex {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&format!("0x{:x}", self.0 .0))
+    }
+}
+
+impl<'de> Deserialize<'de> for LiquidityHex {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let encoded = String::deserialize(deserializer)?;
+        let digits = encoded
+            .strip_prefix("0x")
+            .ok_or_else(|| D::Error::custom("expected 0x-prefixed hex string"))?;
+        U256::from_str_radix(digits, 16)
+            .map(|raw| Self(U192X64(raw)))
+            .map_err(D::Error::custom)
+    }
+}
 
 #[derive(Serialize, Deserialize)]
 #[serde(crate = "near_sdk::serde")]
@@ -36,12 +69,94 @@ pub struct ContractMetadata {
     pub fee_divisor: BasisPoints,
 }
 
+/// Source code metadata of contract, as defined by NEP-330
+#[derive(Serialize)]
+#[serde(crate = "near_sdk::serde")]
+#[cfg_attr(not(target_arch = "wasm32"), derive(Deserialize, Debug))]
+pub struct ContractSourceMetadata {
+    /// Version of contract, derived from Git tags.
+    pub version: Option<String>,
+
+    /// Link to source code repository, if it's specified in crate manifest.
+    pub link: Option<String>,
+
+    /// Standards implemented by contract.
+    pub standards: Vec<Standard>,
+
+    /// Information which lets verify that deployed code is built from published source.
+    pub build_info: SourceBuildInfo,
+}
+
+#[derive(Serialize)]
+#[serde(crate = "near_sdk::serde")]
+#[cfg_attr(not(target_arch = "wasm32"), derive(Deserialize, Debug))]
+pub struct Standard {
+    pub standard: String,
+    pub version: String,
+}
+
+#[derive(Serialize)]
+#[serde(crate = "near_sdk::serde")]
+#[cfg_attr(not(target_arch = "wasm32"), derive(Deserialize, Debug))]
+pub struct SourceBuildInfo {
+    /// Git commit which contract was built from.
+    pub commit: String,
+
+    /// Hex-encoded SHA-256 digest of crate's source files, computed from their paths
+    /// and SHA-256 hashes of their contents.
+    pub source_files_digest: String,
+
+    /// Number of crate's source files covered by the digest.
+    pub source_files_count: u32,
+
+    /// Output of `rustc --version` of toolchain which built contract.
+    pub rustc_version: String,
+
+    /// Output of `cargo --version` of toolchain which built contract.
+    pub cargo_version: String,
+}
+
 #[derive(Serialize, Deserialize, PartialEq, Eq)]
 #[serde(crate = "near_sdk::serde")]
 #[cfg_attr(not(target_arch = "wasm32"), derive(Debug))]
 pub struct RefStorageState {
     pub deposit: U128,
     pub usage: U128,
+    /// Part of `usage` split by what occupies storage
+    pub breakdown: StorageBreakdown,
+    /// Storage deposit required by operations which occupy additional storage
+    pub operation_costs: StorageOperationCosts,
+}
+
+/// Storage occupied by account, in bytes
+#[derive(Serialize, Deserialize, PartialEq, Eq)]
+#[serde(crate = "near_sdk::serde")]
+#[cfg_attr(not(target_arch = "wasm32"), derive(Debug))]
+pub struct StorageBreakdown {
+    /// Account record itself
+    pub base_account_bytes: U64,
+    /// Registered tokens, including ones with zero balance
+    pub token_registrations_bytes: U64,
+    /// Opened positions
+    pub positions_bytes: U64,
+}
+
+/// Storage deposit required by single operation, in yoctoNEAR
+#[derive(Serialize, Deserialize, PartialEq, Eq)]
+#[serde(crate = "near_sdk::serde")]
+#[cfg_attr(not(target_arch = "wasm32"), derive(Debug))]
+pub struct StorageOperationCosts {
+    pub register_token: U128,
+    pub open_position: U128,
+    pub create_pool: U128,
+    pub place_order: U128,
+    pub register_integration: U128,
+    pub prepare_deposit_intent: U128,
+    pub set_auto_harvest: U128,
+    pub grant_view_access: U128,
+    pub set_vault: U128,
+    pub register_session_key: U128,
+    pub check_token_interface: U128,
 }
 
 #[derive(Serialize, Deserialize)]
@@ -54,6 +169,11 @@ pub struct PositionInfo {
     pub reward_since_creation: (U128, U128),
     #[serde(with = "super::utils::serde_as_str")]
     pub init_sqrt_price: f64,
+    /// Account which locked position, if any. Locked position cannot be closed
+    pub locked_by: Option<AccountId>,
+    /// Moment until which position cannot be closed, in seconds, if it was opened locked
+    #[serde(default)]
+    pub locked_until: Option<U64>,
 }
 
 impl From<dex::PositionInfo> for PositionInfo {
@@ -69,6 +189,8 @@ impl From<dex::PositionInfo> for PositionInfo {
             reward_since_last_withdraw: info.reward_since_last_withdraw.map_into(),
             reward_since_creation: info.reward_since_creation.map_into(),
             init_sqrt_price: info.init_sqrtprice.into(),
+            locked_by: info.locked_by,
+            locked_until: info.locked_until.map(Into::into),
         }
     }
 }
@@ -97,6 +219,66 @@ pub struct PoolInfo {
 
     /// Scale factor for the fee levels.
     pub fee_divisor: BasisPoints,
+
+    /// Internal pricing state, present only if requested with `extended` flag.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub pricing_state: Option<PoolPricingInfo>,
+}
+
+/// Internal pricing state of pool, for audit and debugging.
+/// Unlike the rest of `PoolInfo`, it's in pool's own order of tokens, regardless of requested one.
+#[derive(Serialize, Deserialize)]
+#[cfg_attr(not(target_arch = "wasm32"), derive(Debug))]
+#[serde(crate = "near_sdk::serde")]
+pub struct PoolPricingInfo {
+    /// Effective sqrtprices on each of the fee levels, for swaps in left and right direction,
+    /// scaled by 2**64. The value is approximate, as internally a different representation is used.
+    pub eff_sqrtprices: dex::latest::RawFeeLevelsArray<(U128, U128)>,
+
+    /// Next active ticks on each of the fee levels, for swaps in left and right direction.
+    pub next_active_ticks: dex::latest::RawFeeLevelsArray<(Option<i32>, Option<i32>)>,
+
+    pub top_active_level: dex::FeeLevel,
+
+    pub active_side: dex::Side,
+
+    /// Index of effective tick close to current effective sqrtprice in active direction.
+    pub pivot: i32,
+}
+
+impl TryFrom<dex::PoolPricingState> for PoolPricingInfo {
+    type Error = dex::Error;
+
+    fn try_from(state: dex::PoolPricingState) -> Result<Self, Self::Error> {
+        let mut eff_sqrtprices: RawFeeLevelsArray<(U128, U128)> =
+            std::array::from_fn(|_| (U128::from(0), U128::from(0)));
+        for ((left, right), out_prices) in state
+            .eff_sqrtprices
+            .into_iter()
+            .zip(eff_sqrtprices.iter_mut())
+        {
+            *out_prices = (sqrtprice_x64(left)?, sqrtprice_x64(right)?);
+        }
+        Ok(Self {
+            eff_sqrtprices,
+            next_active_ticks: state
+                .next_active_ticks
+                .map(|(left, right)| (left.map(|t| t.index()), right.map(|t| t.index()))),
+            top_active_level: state.top_active_level,
+            active_side: state.active_side,
+            pivot: state.pivot.index(),
+        })
+    }
+}
+
+/// Convert sqrtprice into its representation scaled by 2**64
+#[allow(clippy::cast_precision_loss)]
+fn sqrtprice_x64(sqrtprice: dex::Float) -> Result<U128, dex::Error> {
+    Ok(U128::from(
+        U128X128::try_from(sqrtprice * ((1u128 << 64) as f64).into())
+            .map_err(|e| error_here!(e))?
+            .upper_part(),
+    ))
 }
 
 impl PoolInfo {
@@ -117,17 +299,12 @@ impl TryFrom<dex::PoolInfo> for PoolInfo {
 
     fn try_from(info: dex::PoolInfo) -> Result<Self, Self::Error> {
         let mut sqrt_spot_prices: RawFeeLevelsArray<U128> = std::array::from_fn(|_| U128::from(0));
-        #[allow(clippy::cast_precision_loss)]
         for (in_price, out_price) in info
             .spot_sqrtprices
             .into_iter()
             .zip(sqrt_spot_prices.iter_mut())
         {
-            *out_price = U128::from(
-                U128X128::try_from(in_price * ((1u128 << 64) as f64).into())
-                    .map_err(|e| error_here!(e))?
-                    .upper_part(),
-            );
+            *out_price = sqrtprice_x64(in_price)?;
         }
         Ok(Self {
             total_reserves: (info.total_reserves.0.into(), info.total_reserves.1.into()),
@@ -141,6 +318,346 @@ impl TryFrom<dex::PoolInfo> for PoolInfo {
                 .map(|liquidity| U128::from(u128::try_from(liquidity).unwrap())),
             fee_rates: info.fee_rates,
             fee_divisor: info.fee_divisor,
+            pricing_state: None,
         })
     }
 }
+
+/// LP fee accumulators of concentrated pool, see `get_pool_fee_accumulators`.
+/// Values are lossless `0x`-prefixed hex strings of raw fixed point values,
+/// so that reward math can be reproduced exactly.
+#[derive(Serialize, Deserialize)]
+#[cfg_attr(not(target_arch = "wasm32"), derive(Debug))]
+#[serde(crate = "near_sdk::serde")]
+pub struct PoolFeeAccumulatorsInfo {
+    /// LP fee per unit of fee liquidity, accumulated by swaps with top active level
+    /// equal to each of the fee levels, scaled by 2^128; negative values are prefixed with `-`.
+    pub acc_lp_fees_per_fee_liquidity: RawFeeLevelsArray<(String, String)>,
+
+    /// Total LP fee earned on each of the fee levels since pool creation, scaled by 2^256.
+    pub acc_lp_fees: RawFeeLevelsArray<(String, String)>,
+
+    /// LP fee to be paid out to all LPs, scaled by 2^256.
+    pub acc_lp_fee: (String, String),
+}
+
+impl From<dex::PoolFeeAccumulators> for PoolFeeAccumulatorsInfo {
+    fn from(accumulators: dex::PoolFeeAccumulators) -> Self {
+        let signed_hex = |value: LPFeePerFeeLiquidity| {
+            let sign = if value.non_negative { "" } else { "-" };
+            format!("{sign}0x{:x}", value.value.0)
+        };
+        let unsigned_hex = |value: AmountUFP| format!("0x{:x}", value.0);
+        Self {
+            acc_lp_fees_per_fee_liquidity: accumulators
+                .acc_lp_fees_per_fee_liquidity
+                .map(|(a, b)| (signed_hex(a), signed_hex(b))),
+            acc_lp_fees: accumulators
+                .acc_lp_fees
+                .map(|(a, b)| (unsigned_hex(a), unsigned_hex(b))),
+            acc_lp_fee: (
+                unsigned_hex(accumulators.acc_lp_fee.0),
+                unsigned_hex(accumulators.acc_lp_fee.1),
+            ),
+        }
+    }
+}
+
+/// State of constant product pool, see `get_classic_pool_info`
+#[derive(Serialize, Deserialize)]
+#[cfg_attr(not(target_arch = "wasm32"), derive(Debug))]
+#[serde(crate = "near_sdk::serde")]
+pub struct ClassicPoolInfo {
+    /// Amounts of tokens available for swaps, excluding protocol fees.
+    pub reserves: (U128, U128),
+
+    /// Fee rate scaled up by fee_divisor.
+    pub fee_rate: BasisPoints,
+
+    /// Total amount of issued shares.
+    pub total_shares: U128,
+
+    /// Scale factor for the fee rate.
+    pub fee_divisor: BasisPoints,
+}
+
+impl From<dex::ClassicPoolInfo> for ClassicPoolInfo {
+    fn from(info: dex::ClassicPoolInfo) -> Self {
+        Self {
+            reserves: info.reserves.map_into(),
+            fee_rate: info.fee_rate,
+            total_shares: info.total_shares.into(),
+            fee_divisor: info.fee_divisor,
+        }
+    }
+}
+
+/// State of stable swap pool, see `get_stable_pool_info`
+#[derive(Serialize, Deserialize)]
+#[cfg_attr(not(target_arch = "wasm32"), derive(Debug))]
+#[serde(crate = "near_sdk::serde")]
+pub struct StablePoolInfo {
+    /// Amounts of tokens available for swaps, excluding protocol fees.
+    pub reserves: (U128, U128),
+
+    /// Amplification coefficient of the invariant.
+    pub amplification: u32,
+
+    /// Fee rate scaled up by fee_divisor.
+    pub fee_rate: BasisPoints,
+
+    /// Total amount of issued shares.
+    pub total_shares: U128,
+
+    /// Scale factor for the fee rate.
+    pub fee_divisor: BasisPoints,
+}
+
+impl From<dex::StablePoolInfo> for StablePoolInfo {
+    fn from(info: dex::StablePoolInfo) -> Self {
+        Self {
+            reserves: info.reserves.map_into(),
+            amplification: info.amplification,
+            fee_rate: info.fee_rate,
+            total_shares: info.total_shares.into(),
+            fee_divisor: info.fee_divisor,
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+#[cfg_attr(not(target_arch = "wasm32"), derive(Debug))]
+#[serde(crate = "near_sdk::serde")]
+pub struct PoolStats {
+    /// Total amounts of tokens paid into the pool by traders.
+    pub volume_in: (U128, U128),
+
+    /// Total amounts of tokens paid out of the pool to traders.
+    pub volume_out: (U128, U128),
+
+    /// Total LP fees charged in swaps, rounded down.
+    pub lp_fees: (U128, U128),
+
+    /// Total protocol fees charged in swaps, rounded down.
+    pub protocol_fees: (U128, U128),
+
+    /// Number of swaps performed in the pool.
+    pub swap_count: U64,
+}
+
+impl TryFrom<dex::latest::PoolStats> for PoolStats {
+    type Error = dex::Error;
+
+    fn try_from(stats: dex::latest::PoolStats) -> Result<Self, Self::Error> {
+        let floor = |value: AmountUFP| {
+            u128::try_from(value.floor())
+                .map(U128::from)
+                .map_err(|e| error_here!(e))
+        };
+        Ok(Self {
+            volume_in: stats.volume_in.map_into(),
+            volume_out: stats.volume_out.map_into(),
+            lp_fees: (floor(stats.lp_fees.0)?, floor(stats.lp_fees.1)?),
+            protocol_fees: (floor(stats.protocol_fees.0)?, floor(stats.protocol_fees.1)?),
+            swap_count: stats.swap_count.into(),
+        })
+    }
+}
+
+/// Distribution of swapped volume among fee levels of concentrated pool,
+/// see `Contract::get_level_utilization`
+#[derive(Serialize, Deserialize)]
+#[cfg_attr(not(target_arch = "wasm32"), derive(Debug))]
+#[serde(crate = "near_sdk::serde")]
+pub struct LevelUtilization {
+    /// Total amounts of tokens paid in by traders and absorbed by each fee level, rounded down.
+    pub volumes_in: RawFeeLevelsArray<(U128, U128)>,
+
+    /// Fraction of total input volume of each token absorbed by each fee level;
+    /// zero if there were no swaps in that direction.
+    pub shares: RawFeeLevelsArray<(f64, f64)>,
+}
+
+impl TryFrom<RawFeeLevelsArray<(AmountUFP, AmountUFP)>> for LevelUtilization {
+    type Error = dex::Error;
+
+    fn try_from(
+        volumes_in: RawFeeLevelsArray<(AmountUFP, AmountUFP)>,
+    ) -> Result<Self, Self::Error> {
+        let floor = |value: AmountUFP| {
+            u128::try_from(value.floor())
+                .map(U128::from)
+                .map_err(|e| error_here!(e))
+        };
+        let to_f64 = |value: AmountUFP| f64::from(dex::Float::from(value));
+        let total = volumes_in
+            .iter()
+            .fold((0., 0.), |(a, b), (level_a, level_b)| {
+                (a + to_f64(*level_a), b + to_f64(*level_b))
+            });
+        let share = |value: AmountUFP, total: f64| {
+            if total > 0. {
+                to_f64(value) / total
+            } else {
+                0.
+            }
+        };
+        let mut floored = [(U128(0), U128(0)); dex::latest::NUM_FEE_LEVELS as usize];
+        for (dst, (a, b)) in floored.iter_mut().zip(volumes_in) {
+            *dst = (floor(a)?, floor(b)?);
+        }
+        Ok(Self {
+            volumes_in: floored,
+            shares: volumes_in.map(|(a, b)| (share(a, total.0), share(b, total.1))),
+        })
+    }
+}
+
+/// Point of the effective price curve, see `Contract::sample_price_curve`
+#[derive(Serialize, Deserialize)]
+#[cfg_attr(not(target_arch = "wasm32"), derive(Debug))]
+#[serde(crate = "near_sdk::serde")]
+pub struct PriceCurvePoint {
+    /// Amount of input token taken by the swap.
+    pub amount_in: U128,
+
+    /// Amount of output token received.
+    pub amount_out: U128,
+
+    /// Average price of the swap, as amount in per unit of amount out.
+    pub average_price: f64,
+
+    /// Relative excess of the average price over the marginal price before the swap.
+    pub price_impact: f64,
+}
+
+impl From<dex::PriceCurveSample> for PriceCurvePoint {
+    fn from(sample: dex::PriceCurveSample) -> Self {
+        Self {
+            amount_in: sample.amount_in.into(),
+            amount_out: sample.amount_out.into(),
+            average_price: sample.average_price.into(),
+            price_impact: sample.price_impact.into(),
+        }
+    }
+}
+
+/// TVL of single pool, in reference token, see `Contract::get_tvl`
+#[derive(Serialize, Deserialize)]
+#[cfg_attr(not(target_arch = "wasm32"), derive(Debug))]
+#[serde(crate = "near_sdk::serde")]
+pub struct PoolTvlInfo {
+    pub tokens: Pair<TokenId>,
+
+    /// Value of pool's reserves, in units of reference token.
+    pub tvl: f64,
+}
+
+/// Page of pools valued in reference token, see `Contract::get_tvl`
+#[derive(Serialize, Deserialize)]
+#[cfg_attr(not(target_arch = "wasm32"), derive(Debug))]
+#[serde(crate = "near_sdk::serde")]
+pub struct TvlInfo {
+    /// Sum of TVL of pools within the page.
+    pub total: f64,
+
+    pub pools: Vec<PoolTvlInfo>,
+
+    /// Value of `from` to request the next page with; absent if this page is the last one.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub next_from: Option<U64>,
+}
+
+impl From<dex::TvlReport> for TvlInfo {
+    fn from(report: dex::TvlReport) -> Self {
+        Self {
+            total: report.total.into(),
+            pools: report
+                .pools
+                .into_iter()
+                .map(|pool| PoolTvlInfo {
+                    tokens: pool.tokens.into(),
+                    tvl: pool.tvl.into(),
+                })
+                .collect(),
+            next_from: report.next_from.map(Into::into),
+        }
+    }
+}
+
+/// Hashes over critical contract state, see `dex::digest` for details
+#[derive(Serialize, Deserialize)]
+#[cfg_attr(not(target_arch = "wasm32"), derive(Debug, PartialEq, Eq))]
+#[serde(crate = "near_sdk::serde")]
+pub struct StateDigest {
+    /// Hash over all sections below
+    pub root: Base58CryptoHash,
+    /// Hash over pools' reserves and positions
+    pub pools: Base58CryptoHash,
+    /// Hash over accounts' token balances and owned positions
+    pub accounts: Base58CryptoHash,
+}
+
+impl From<dex::digest::StateDigest> for StateDigest {
+    fn from(digest: dex::digest::StateDigest) -> Self {
+        Self {
+            root: digest.root.into(),
+            pools: digest.pools.into(),
+            accounts: digest.accounts.into(),
+        }
+    }
+}
+
+/// Single read call in `batch_view`, mirrors one of the contract view methods
+#[derive(Serialize, Deserialize)]
+#[cfg_attr(not(target_arch = "wasm32"), derive(Debug))]
+#[serde(crate = "near_sdk::serde", rename_all = "snake_case")]
+#[serde(tag = "method", content = "args")]
+pub enum ViewCall {
+    Metadata,
+    GetVersion,
+    GetOwner,
+    GetVerifiedTokens,
+    GetDeposits {
+        account_id: AccountId,
+    },
+    GetDeposit {
+        account_id: AccountId,
+        token_id: TokenId,
+    },
+    GetUserTokens {
+        account_id: AccountId,
+    },
+    GetUserStorageState {
+        account_id: AccountId,
+    },
+    GetPoolInfo {
+        tokens: Pair<TokenId>,
+        extended: Option<bool>,
+    },
+    GetPoolStats {
+        tokens: Pair<TokenId>,
+    },
+    GetPositionInfo {
+        position_id: U64,
+    },
+}
+
+/// Result of single `ViewCall`, tagged with the name of the method
+#[derive(Serialize)]
+#[cfg_attr(not(target_arch = "wasm32"), derive(Deserialize, Debug))]
+#[serde(crate = "near_sdk::serde", rename_all = "snake_case")]
+#[serde(tag = "method", content = "result")]
+pub enum ViewResult {
+    Metadata(ContractMetadata),
+    GetVersion(dex::VersionInfo),
+    GetOwner(AccountId),
+    GetVerifiedTokens(Vec<TokenId>),
+    GetDeposits(HashMap<TokenId, U128>),
+    GetDeposit(U128),
+    GetUserTokens(Vec<TokenId>),
+    GetUserStorageState(Option<RefStorageState>),
+    GetPoolInfo(Option<PoolInfo>),
+    GetPoolStats(Option<PoolStats>),
+    GetPositionInfo(PositionInfo),
+}
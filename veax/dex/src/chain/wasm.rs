@@ -1,20 +1,37 @@
 //! Contract's WASM API
 //! Helper structures are re-exported through other means,
 //! to ensure they're not visible in case of WASM build
+use super::account::storage_operation_costs;
 use super::log::log_str;
 use super::{
-    Account, AccountId, AmountInOut, Contract, ContractMetadata, Error, Pair, PoolInfo,
-    PositionInfo, RefStorageState, State, StateExt, TokenId,
+    Account, AccountId, AdminLogEntryInfo, AmountInOut, CachedTokenMetadata, ClassicPoolInfo,
+    ClosePositionEstimate, ConditionalOrderInfo, Contract, ContractConfig, ContractConstants,
+    ContractMetadata, ContractSourceMetadata, DepositIntentInfo, Error, EventsSchema,
+    FeeAuctionInfo, IntegrationInfo, LevelUtilization, OpenPositionEstimate, OpenPositionResult,
+    Pair, PoolAliasInfo, PoolFeeAccumulatorsInfo, PoolInfo, PoolStats, PoolStorageDepositInfo,
+    PoolStorageReport, PositionInfo, PriceCurvePoint, RefStorageState, SessionKeyInfo,
+    SourceBuildInfo, StablePoolInfo, Standard, State, StateDigest, StateExt, TokenAmount, TokenId,
+    TokenMetadataInfo, TvlInfo, VaultInfo, ViewCall, ViewResult,
 };
-use crate::dex::latest::one_over_sqrt_one_minus_fee_rate;
+#[cfg(not(feature = "legacy-wasm-api"))]
+use super::{AdminsInfo, PendingApprovalInfo, ScheduledActionInfo, TokenWithdrawLimit};
+#[cfg(feature = "batch-auction")]
+use super::{BatchAuctionInfo, SettleBatchResult};
+use crate::dex::latest::liquidity_from_net_liquidity;
+#[cfg(not(feature = "legacy-wasm-api"))]
+use crate::dex::CleanupCursor;
+#[cfg(feature = "smart-routing")]
+use crate::dex::Path;
 use crate::dex::{
-    self, Action, BasisPoints, FeeLevel, ItemFactory, PositionInit, StateMembersMut, StateMut as _,
-    VersionInfo,
+    self, Action, AdminAction, BasisPoints, FeeAuctionConfig, FeeLevel, FeeLevelRecommendation,
+    ItemFactory, OrderTrigger, PositionInit, SeedPeriodConfig, Side, StateMembersMut,
+    StateMut as _, SwapGuardMode, SwapHook, VersionInfo, WithdrawLimitConfig,
 };
 #[cfg(feature = "smart-routing")]
-use crate::dex::{v0::NUM_TOP_POOLS, Path};
-use crate::{ensure_here, error_here, Liquidity};
+use crate::Liquidity;
+use crate::{ensure_here, error_here};
 use itertools::Itertools as _;
+use near_contract_standards::fungible_token::metadata::FungibleTokenMetadata;
 use near_contract_standards::fungible_token::receiver::FungibleTokenReceiver;
 use near_contract_standards::storage_management::{
     StorageBalance, StorageBalanceBounds, StorageManagement,
@@ -22,9 +39,11 @@ use near_contract_standards::storage_management::{
 
 /// Defines amount type used in WASM entrypoint APIs
 pub use near_sdk::json_types::U128 as WasmAmount;
-use near_sdk::json_types::{U128, U64};
-use near_sdk::{assert_one_yocto, env, near_bindgen, Promise, PromiseOrValue};
+use near_sdk::json_types::{Base58CryptoHash, U128, U64};
+use near_sdk::serde::{Deserialize, Serialize};
+use near_sdk::{assert_one_yocto, borsh, env, near_bindgen, Promise, PromiseOrValue, PublicKey};
 use std::collections::HashMap;
+use std::ptr::addr_of_mut;
 
 /// Extracts promise result and transforms it into normal `Result`
 ///
@@ -66,17 +85,77 @@ macro_rules! promise_result {
     };
 }
 
+/// Machine-readable error description, emitted as panic message in form `ERR_JSON:{...}`
+#[derive(Serialize)]
+#[serde(crate = "near_sdk::serde")]
+struct ErrorEnvelope<'a> {
+    /// Packed error code, see `describe_error_code`
+    code: i32,
+    /// Error kind name, stable across releases
+    kind: &'static str,
+    /// Source file and line where error was produced
+    location: String,
+    /// Human-readable error message
+    message: &'a str,
+}
+
+/// Converts any error which may surface in WASM API into `dex::Error`,
+/// so it can be reported with error code.
+/// If error doesn't carry its location, caller location is used
+pub(super) trait IntoDexError {
+    fn into_dex_error(self) -> dex::Error;
+}
+
+impl IntoDexError for dex::Error {
+    fn into_dex_error(self) -> dex::Error {
+        self
+    }
+}
+
+impl IntoDexError for Error {
+    #[track_caller]
+    fn into_dex_error(self) -> dex::Error {
+        error_here!(self)
+    }
+}
+
+impl IntoDexError for crate::fp::Error {
+    #[track_caller]
+    fn into_dex_error(self) -> dex::Error {
+        error_here!(self)
+    }
+}
+
+impl IntoDexError for std::num::TryFromIntError {
+    #[track_caller]
+    fn into_dex_error(self) -> dex::Error {
+        error_here!(dex::ErrorKind::ConvOverflow)
+    }
+}
+
 /// Extension trait which performs `Result` unwrapping through `near_sdk::env::panic_str`
 pub(super) trait NearUnwrap<T> {
     fn near_unwrap(self) -> T;
 }
 
-impl<T, E: std::fmt::Display> NearUnwrap<T> for std::result::Result<T, E> {
+impl<T, E: IntoDexError> NearUnwrap<T> for std::result::Result<T, E> {
     #[track_caller]
     fn near_unwrap(self) -> T {
         match self {
             Ok(value) => value,
-            Err(err) => near_sdk::env::panic_str(&format!("{err}")),
+            Err(err) => {
+                let err = err.into_dex_error();
+                let message = err.kind.to_string();
+                let envelope = ErrorEnvelope {
+                    code: err.error_code().integer(),
+                    kind: err.kind_name(),
+                    location: format!("{}:{}", err.file, err.line),
+                    message: &message,
+                };
+                // Serialization of plain struct cannot fail
+                let Ok(json) = near_sdk::serde_json::to_string(&envelope) else { unreachable!() };
+                near_sdk::env::panic_str(&format!("ERR_JSON:{json}"))
+            }
         }
     }
 }
@@ -96,7 +175,7 @@ impl State {
     ) -> dex::Result<StorageBalance> {
         let min_balance = self.storage_balance_bounds().min.0;
 
-        move |_, Account::V0(ref mut account), already_registered| {
+        move |_, Account::V1(ref mut account), already_registered| {
             let deposit = env::attached_deposit();
 
             ensure_here!(
@@ -130,6 +209,142 @@ impl State {
         }
     }
 
+    /// Records successful administrative call in on-chain admin log,
+    /// along with SHA-256 hash of its raw arguments
+    pub(super) fn record_admin_call(&mut self, method: &str) {
+        let args_hash = env::sha256(&env::input().unwrap_or_default())
+            .try_into()
+            .unwrap_or_else(|_| unreachable!());
+        self.as_dex_mut()
+            .record_admin_action(method.to_string(), args_hash);
+    }
+
+    /// Re-quote protection of swap calls: panics if spot price of route `tokens`
+    /// already deviates from client's `reference_sqrtprice`, if one is specified
+    fn ensure_reference_price(
+        &self,
+        tokens: &[AccountId],
+        reference_sqrtprice: Option<f64>,
+        max_deviation_bp: Option<dex::BasisPoints>,
+    ) {
+        if let Some(reference_sqrtprice) = reference_sqrtprice {
+            self.as_dex()
+                .ensure_route_price_near(
+                    tokens,
+                    crate::chain::Float::from(reference_sqrtprice),
+                    max_deviation_bp.unwrap_or(0),
+                )
+                .near_unwrap();
+        }
+    }
+
+    /// Process tokens received by `ft_on_transfer`, as directed by `msg`
+    ///
+    /// # Returns
+    /// Amount to refund, and promise which must complete before refund is returned, if any
+    fn on_tokens_received(
+        &mut self,
+        sender_id: AccountId,
+        token_in: AccountId,
+        amount: U128,
+        msg: String,
+    ) -> (U128, Option<Promise>) {
+        // Diverge based on message contents
+        if msg.is_empty() {
+            self.as_dex_mut()
+                .deposit(&sender_id, &token_in, amount.into())
+                .near_unwrap();
+
+            (U128(0), None)
+        } else if let Ok(DepositIntentMsg { intent_id }) = serde_json::from_str(&msg) {
+            let (refund, outcomes) = self
+                .as_dex_mut()
+                .deposit_to_intent(&sender_id, &token_in, amount.into(), intent_id.into())
+                .near_unwrap();
+
+            (U128(refund), Self::fold_promises(outcomes))
+        } else if let Ok(OpenPositionSingleSidedMsg {
+            open_position_single_sided,
+        }) = serde_json::from_str(&msg)
+        {
+            let (_position_id, refund) = self
+                .as_dex_mut()
+                .deposit_open_position_single_sided(
+                    &sender_id,
+                    &token_in,
+                    amount.into(),
+                    open_position_single_sided,
+                )
+                .near_unwrap();
+
+            (U128(refund), None)
+        } else {
+            #[cfg(feature = "gas-profile")]
+            super::gas_profile::start("ft_on_transfer");
+            let actions = parse_actions_msg(&msg).near_unwrap();
+            let outcomes = self
+                .as_dex_mut()
+                .deposit_execute_actions(
+                    &sender_id,
+                    token_in,
+                    amount.into(),
+                    |_, _, _| Err(error_here!(Error::RegisterAccountNotAllowedOnDeposit)),
+                    actions,
+                )
+                .near_unwrap();
+            #[cfg(feature = "gas-profile")]
+            super::gas_profile::finish();
+
+            (U128(0), Self::fold_promises(outcomes))
+        }
+    }
+
+    /// Credit tokens received by `ft_on_transfer` and process them as directed by `msg`
+    fn receive_tokens(
+        &mut self,
+        sender_id: AccountId,
+        token_in: AccountId,
+        amount: U128,
+        msg: String,
+    ) -> PromiseOrValue<U128> {
+        // Amount actually received from fee-on-transfer token is known after balance is fetched
+        if self.as_dex().is_token_reconciled(&token_in) {
+            self.as_dex_mut()
+                .begin_reconciled_deposit(&token_in)
+                .near_unwrap();
+            return PromiseOrValue::Promise(
+                self.reconcile_transfer(token_in, sender_id, amount, msg),
+            );
+        }
+        let (refund, promise) = self.on_tokens_received(sender_id, token_in, amount, msg);
+        self.return_refund(refund, promise)
+    }
+
+    /// Whether both calls made by `ping_token_interface` succeeded with results of expected types
+    fn token_interface_pinged() -> bool {
+        env::promise_results_count() == 2
+            && matches!(
+                env::promise_result(0),
+                near_sdk::PromiseResult::Successful(bytes)
+                    if serde_json::from_slice::<FungibleTokenMetadata>(&bytes).is_ok()
+            )
+            && matches!(
+                env::promise_result(1),
+                near_sdk::PromiseResult::Successful(bytes)
+                    if serde_json::from_slice::<U128>(&bytes).is_ok()
+            )
+    }
+
+    /// Complete `ft_on_transfer` with amount to refund, after `promise` if there's one
+    fn return_refund(&mut self, refund: U128, promise: Option<Promise>) -> PromiseOrValue<U128> {
+        match promise {
+            Some(p) => {
+                PromiseOrValue::Promise(p.then(self.delay_return_option_amount(Some(refund))))
+            }
+            None => PromiseOrValue::Value(refund),
+        }
+    }
+
     fn on_register_account_action(
         &self,
         registration_only: bool,
@@ -175,7 +390,7 @@ impl State {
         fee_rates: Option<dex::latest::RawFeeLevelsArray<BasisPoints>>,
     ) -> Self {
         Self(
-            unsafe { &mut super::ITEM_FACTORY }
+            unsafe { &mut *addr_of_mut!(super::ITEM_FACTORY) }
                 .new_contract(
                     owner_id.unwrap_or_else(env::predecessor_account_id),
                     protocol_fee_fraction.unwrap_or(1300),
@@ -184,6 +399,18 @@ impl State {
                 .near_unwrap(),
         )
     }
+
+    /// Migrate contract state after upgrade. Called by `upgrade` in the same batch
+    /// as deployment of new code, so that failed migration reverts deployment too.
+    ///
+    /// Root state stored by previous versions is converted right away, pools are converted
+    /// in batches by `migrate_pools`, while accounts and positions are converted
+    /// when they're accessed. Payable API is suspended until all pools are converted.
+    #[private]
+    #[init(ignore_state)]
+    pub fn migrate() -> Self {
+        Self(super::migration::read_state())
+    }
 }
 
 /// Various view methods into contract state
@@ -191,7 +418,7 @@ impl State {
 impl State {
     pub fn metadata(&self) -> ContractMetadata {
         let fee_rates = self.as_dex().fee_rates_ticks();
-        let Contract::V0(ref contract) = &self.0;
+        let Contract::V1(ref contract) = &self.0;
         ContractMetadata {
             owner: contract.owner_id.clone(),
             pool_count: contract.pool_count,
@@ -201,14 +428,96 @@ impl State {
         }
     }
 
+    /// Get source code metadata of contract, as defined by NEP-330, including Git commit,
+    /// digest of source files and toolchain versions, so that deployed code can be verified
+    /// against published source by rebuilding it.
+    pub fn contract_source_metadata(&self) -> ContractSourceMetadata {
+        ContractSourceMetadata {
+            version: Some(self.as_dex().get_version().version),
+            link: Some(env!("CARGO_PKG_REPOSITORY").to_string()).filter(|link| !link.is_empty()),
+            standards: vec![Standard {
+                standard: "nep330".to_string(),
+                version: "1.1.0".to_string(),
+            }],
+            build_info: SourceBuildInfo {
+                commit: env!("DEX_CORE_GIT_COMMIT").to_string(),
+                source_files_digest: dex::source_files_digest().to_string(),
+                source_files_count: dex::source_files_count() as u32,
+                rustc_version: env!("DEX_CORE_RUSTC_VERSION").to_string(),
+                cargo_version: env!("DEX_CORE_CARGO_VERSION").to_string(),
+            },
+        }
+    }
+
+    /// Get constants of contract math, so that clients don't need to hard-code them.
+    pub fn get_constants(&self) -> ContractConstants {
+        ContractConstants {
+            min_tick: crate::chain::MIN_TICK,
+            max_tick: crate::chain::MAX_TICK,
+            num_fee_levels: dex::latest::NUM_FEE_LEVELS,
+            basis_point_divisor: dex::BASIS_POINT_DIVISOR,
+            min_liquidity: dex::latest::MIN_LIQUIDITY.into(),
+            max_min_liquidity: dex::latest::MAX_MIN_LIQUIDITY.into(),
+            max_liquidity: dex::latest::MAX_LIQUIDITY.into(),
+            tick_base_bits: dex::Tick::BASE.to_bits().into(),
+        }
+    }
+
+    /// Get all governance-tunable parameters of contract at once.
+    pub fn get_config(&self) -> ContractConfig {
+        let dex = self.as_dex();
+        let (admins, admin_threshold) = dex.get_admins();
+        let Contract::V1(ref contract) = &self.0;
+        ContractConfig {
+            owner: contract.owner_id.clone(),
+            guards: contract.guards.iter().collect(),
+            admins,
+            admin_threshold,
+            suspended: contract.suspended,
+            withdraw_limits_disabled: dex.is_withdraw_limits_disabled(),
+            protocol_fee_fraction: contract.protocol_fee_fraction,
+            insurance_fee_fraction: dex.get_insurance_fee_fraction(),
+            fee_rates: dex.fee_rates_ticks(),
+            fee_divisor: dex::BASIS_POINT_DIVISOR,
+            timelock_delay: dex.get_timelock_delay().into(),
+            min_position_liquidity: dex.get_min_position_liquidity().into(),
+            disabled_events: dex.get_disabled_events(),
+            fee_auction_config: dex.get_fee_auction_config(),
+            swap_hook: dex.get_swap_hook(),
+            verified_tokens_count: contract.verified_tokens.len(),
+            gas_config: dex.get_gas_config(),
+            pool_price_init_open: dex.is_pool_price_init_open(),
+            seed_period: dex.get_seed_period(),
+            token_interface_check: dex.is_token_interface_check_enabled(),
+        }
+    }
+
+    /// Get number of pools stored by previous contract version which are yet to be converted
+    /// by `migrate_pools`. Such pools aren't listed by other methods until they're converted.
+    pub fn get_pools_to_migrate(&self) -> U64 {
+        self.pools_to_migrate().into()
+    }
+
+    /// Convert up to `limit` pools stored by previous contract version, so that payable API,
+    /// suspended by upgrade, can be used again. Should be called repeatedly until it returns 0;
+    /// `limit` should be chosen so that batch fits into attached gas.
+    /// Payable API is resumed by the last batch, unless it was suspended before upgrade.
+    /// Can be called by anyone, as it only completes upgrade.
+    ///
+    /// # Returns
+    /// Number of pools which are yet to be converted
+    pub fn migrate_pools(&mut self, limit: u32) -> U64 {
+        self.migrate_pools_batch(limit).into()
+    }
+
     /// Returns balances of the deposits for given user outside of any pools.
     /// Returns empty list if no tokens deposited.
     pub fn get_deposits(&self, account_id: &AccountId) -> HashMap<AccountId, U128> {
-        let Contract::V0(ref contract) = &self.0;
+        let Contract::V1(ref contract) = &self.0;
         contract
             .accounts
             .get(account_id)
-            .map(|Account::V0(ref account)| {
+            .map(|Account::V1(ref account)| {
                 account
                     .token_balances
                     .into_iter()
@@ -220,281 +529,1797 @@ impl State {
 
     /// Returns balance of the deposit for given user outside of any pools.
     pub fn get_deposit(&self, account_id: &AccountId, token_id: &AccountId) -> U128 {
-        let Contract::V0(ref contract) = &self.0;
+        let Contract::V1(ref contract) = &self.0;
         contract
             .accounts
             .get(account_id)
-            .and_then(|Account::V0(ref account)| account.token_balances.get(token_id))
+            .and_then(|Account::V1(ref account)| account.token_balances.get(token_id))
             .unwrap_or(0)
             .into()
     }
 
     /// Get ordered allowed tokens list.
     pub fn get_verified_tokens(&self) -> Vec<AccountId> {
-        let Contract::V0(ref contract) = &self.0;
+        let Contract::V1(ref contract) = &self.0;
         contract.verified_tokens.iter().collect()
     }
 
-    /// Get specific user tokens.
-    pub fn get_user_tokens(&self, account_id: &AccountId) -> Vec<AccountId> {
-        let Contract::V0(ref contract) = &self.0;
-        contract
-            .accounts
-            .get(account_id)
-            .map(|Account::V0(ref account)| {
-                account
-                    .token_balances
-                    .into_iter()
-                    .map(|(token_id, _)| token_id)
-                    .collect()
-            })
-            .unwrap_or_default()
+    /// Get cached metadata of tokens, ordered the same way as `tokens`;
+    /// `null` for tokens whose metadata isn't cached. See `refresh_tokens_metadata`.
+    pub fn get_tokens_metadata(&self, tokens: Vec<TokenId>) -> Vec<Option<TokenMetadataInfo>> {
+        let Contract::V1(ref contract) = &self.0;
+        tokens
+            .iter()
+            .map(|token_id| contract.extra.tokens_metadata.get(token_id).map(Into::into))
+            .collect()
     }
 
-    pub fn get_pool_info(&self, tokens: Pair<TokenId>) -> Option<PoolInfo> {
-        self.as_dex()
-            .get_pool_info(tokens.into())
-            .near_unwrap()
-            .map(TryInto::try_into)
-            .transpose()
-            .near_unwrap()
+    /// Fetch metadata of verified tokens from their contracts and cache it, so that it can be
+    /// retrieved with `get_tokens_metadata`. Up to 10 tokens can be refreshed at once.
+    /// Can be called by anyone, e.g. by keeper which periodically refreshes all verified tokens.
+    pub fn refresh_tokens_metadata(&mut self, tokens: Vec<TokenId>) -> Promise {
+        self.fetch_tokens_metadata(tokens).near_unwrap()
     }
 
-    /// Get user's storage deposit and needed in the account of current version
-    pub fn get_user_storage_state(&self, account_id: &AccountId) -> Option<RefStorageState> {
-        let Contract::V0(ref contract) = &self.0;
-        contract
-            .accounts
-            .get(account_id)
-            .map(|Account::V0(ref account)| RefStorageState {
-                deposit: account.extra.near_amount.into(),
-                usage: account.storage_usage().into(),
-            })
+    /// Get tokens banned by owner.
+    pub fn get_banned_tokens(&self) -> Vec<TokenId> {
+        self.as_dex().get_banned_tokens()
     }
 
-    pub fn get_version(&self) -> VersionInfo {
-        self.as_dex().get_version()
+    /// Check whether token is banned by owner.
+    pub fn is_token_banned(&self, token_id: &TokenId) -> bool {
+        self.as_dex().is_token_banned(token_id)
     }
-}
-/// Receive tokens from other contracts
-#[near_bindgen]
-impl FungibleTokenReceiver for State {
-    /// Callback on receiving tokens by this contract
-    ///
-    /// # Parameters
-    /// * `sender_id` - original owner of tokens
-    /// * `amount` - how many tokens to deposit
-    /// * `msg` - additional transfer payload; if empty, performs simple deposit;
-    ///     otherwise should contain vector of actions serialized as JSON;
-    ///     see `execute_actions` on required format
-    #[allow(unreachable_code)]
+
+    /// Enable or disable "reconcile on deposit" mode of fee-on-transfer token, which deducts
+    /// tax from transferred amount. In this mode, transfers of token are credited by delta
+    /// of contract's own balance, fetched with `ft_balance_of`, rather than by `amount`
+    /// reported to `ft_on_transfer`. Enabling mode fetches contract's current balance
+    /// of token, and transfers of token fail until it's fetched. wNEAR can't be reconciled.
+    /// Only can be called by owner.
     #[payable]
-    fn ft_on_transfer(
+    pub fn set_token_reconcile_on_deposit(
         &mut self,
-        sender_id: AccountId,
-        amount: U128,
-        msg: String,
-    ) -> PromiseOrValue<U128> {
-        // Token id is the caller here
-        let token_in = env::predecessor_account_id();
-        let remainder = U128(0);
-        // Diverge based on message contents
-        if msg.is_empty() {
-            self.as_dex_mut()
-                .deposit(&sender_id, &token_in, amount.into())
-                .near_unwrap();
-
-            PromiseOrValue::Value(remainder)
+        token_id: TokenId,
+        enabled: bool,
+    ) -> PromiseOrValue<()> {
+        assert_one_yocto();
+        self.as_dex_mut()
+            .set_token_reconcile_on_deposit(&token_id, enabled)
+            .near_unwrap();
+        self.record_admin_call("set_token_reconcile_on_deposit");
+        if enabled {
+            PromiseOrValue::Promise(self.fetch_reconciled_balance(token_id).near_unwrap())
         } else {
-            let actions: Vec<Action<()>> = serde_json::from_str(&msg)
-                .map_err(|e| error_here!(Error::WrongMsgFormat(e)))
-                .near_unwrap();
-            let outcomes = self
-                .as_dex_mut()
-                .deposit_execute_actions(
-                    &sender_id,
-                    token_in,
-                    amount.into(),
-                    |_, _, _| Err(error_here!(Error::RegisterAccountNotAllowedOnDeposit)),
-                    actions,
-                )
-                .near_unwrap();
-
-            match Self::fold_promises(outcomes) {
-                Some(p) => PromiseOrValue::Promise(
-                    p.then(Self::delay_return_option_amount(Some(remainder))),
-                ),
-                None => PromiseOrValue::Value(remainder),
-            }
+            PromiseOrValue::Value(())
         }
     }
-}
-/// Operations over accounts
-#[near_bindgen]
-impl State {
-    /// Registers given token in the user's account deposit.
-    /// Fails if not enough balance on this account to cover storage.
+
+    /// Fetch contract's balance of token in reconcile on deposit mode again, and replace
+    /// tracked balance with it, e.g. if tracked balance drifted because token takes fee
+    /// on outflows too. Can't be called while deposit of token is pending.
+    /// Only can be called by guard.
     #[payable]
-    pub fn register_tokens(&mut self, token_ids: &Vec<AccountId>) {
+    pub fn resync_reconciled_balance(&mut self, token_id: TokenId) -> Promise {
         assert_one_yocto();
-        let account_id = env::predecessor_account_id();
         self.as_dex_mut()
-            .register_tokens(&account_id, token_ids)
+            .resync_reconciled_balance(&token_id)
             .near_unwrap();
+        self.record_admin_call("resync_reconciled_balance");
+        self.fetch_reconciled_balance(token_id).near_unwrap()
     }
-    /// Unregister given token from user's account deposit.
-    /// Panics if the balance of any given token is non 0.
+
+    /// Check whether token is in reconcile on deposit mode.
+    pub fn is_token_reconciled(&self, token_id: &TokenId) -> bool {
+        self.as_dex().is_token_reconciled(token_id)
+    }
+
+    /// Get contract's balance of token in reconcile on deposit mode, as tracked by contract;
+    /// `null` if token isn't reconciled, or its balance isn't fetched yet.
+    pub fn get_reconciled_balance(&self, token_id: &TokenId) -> Option<U128> {
+        self.as_dex()
+            .get_reconciled_balance(token_id)
+            .map(Into::into)
+    }
+
+    /// Enable or disable interface check of token contracts. When enabled, token contract
+    /// is pinged with `ft_metadata` and `ft_balance_of` before its first deposit is credited,
+    /// and deposits of tokens which fail check are refunded. Pool can only be created
+    /// with tokens which passed check, see `check_token_interface`.
+    /// Only can be called by owner.
     #[payable]
-    pub fn unregister_tokens(&mut self, token_ids: &Vec<AccountId>) {
+    pub fn set_token_interface_check(&mut self, enabled: bool) {
         assert_one_yocto();
         self.as_dex_mut()
-            .unregister_tokens(&env::predecessor_account_id(), token_ids)
+            .set_token_interface_check(enabled)
             .near_unwrap();
+        self.record_admin_call("set_token_interface_check");
     }
 
-    pub fn token_register_of(&self, account_id: &AccountId, token_id: &AccountId) -> bool {
-        let Contract::V0(ref contract) = &self.0;
+    /// Check whether token contracts are checked before they are first used.
+    pub fn is_token_interface_check_enabled(&self) -> bool {
+        self.as_dex().is_token_interface_check_enabled()
+    }
+
+    /// Get outcome of interface check of token contract; `null` if token isn't checked yet.
+    pub fn get_token_interface_status(&self, token_id: &TokenId) -> Option<bool> {
+        self.as_dex().get_token_interface_status(token_id)
+    }
+
+    /// Check that token contract implements fungible token interface, and cache outcome,
+    /// e.g. before pool with token is created, or to check again token which failed check.
+    /// Can be called by any registered account while check is enabled; storage of outcome
+    /// of token which isn't checked yet is paid from caller's storage deposit,
+    /// see `get_user_storage_state`.
+    pub fn check_token_interface(&mut self, token_id: TokenId) -> Promise {
+        self.fetch_token_interface(token_id).near_unwrap()
+    }
+
+    /// Get specific user tokens.
+    pub fn get_user_tokens(&self, account_id: &AccountId) -> Vec<AccountId> {
+        let Contract::V1(ref contract) = &self.0;
         contract
             .accounts
             .get(account_id)
-            .map_or(false, |Account::V0(ref account)| {
-                account.token_balances.get(token_id).is_some()
+            .map(|Account::V1(ref account)| {
+                account
+                    .token_balances
+                    .into_iter()
+                    .map(|(token_id, _)| token_id)
+                    .collect()
             })
+            .unwrap_or_default()
     }
-    /// Withdraws given token from the deposits of given user.
-    /// a zero amount means to withdraw all in user's inner account.
-    ///
-    /// # Parameters
-    /// * `token_id` - token which should be withdrawn
-    /// * `amount` - amount to withdraw
-    /// * `unregister` - if `Ok(true)`, DEX will attempt to unregister token from account.
-    ///     Unregister will succeed only if named token has zero balance, but failure to do so will not
-    ///     interrupt operation as a whole
-    ///
-    /// # Returns
-    /// Promise which produces final operation result
-    #[payable]
-    #[allow(clippy::needless_pass_by_value)] // token_id is passed by value, dictated by existing API
-    pub fn withdraw(
-        &mut self,
-        token_id: AccountId,
-        amount: U128,
-        unregister: Option<bool>,
-    ) -> Option<Promise> {
-        assert_one_yocto();
-        self.as_dex_mut()
-            .withdraw(
-                &env::predecessor_account_id(),
-                &token_id,
-                amount.into(),
-                unregister.unwrap_or(false),
-                (),
-            )
+
+    /// Get state of concentrated pool of `tokens`. If `extended` is set,
+    /// internal pricing state of pool is included, which is intended for audit and debugging.
+    pub fn get_pool_info(&self, tokens: Pair<TokenId>, extended: Option<bool>) -> Option<PoolInfo> {
+        let dex = self.as_dex();
+        let tokens: (TokenId, TokenId) = tokens.into();
+        let mut info: PoolInfo = dex
+            .get_pool_info(tokens.clone())
             .near_unwrap()
+            .map(TryInto::try_into)
+            .transpose()
+            .near_unwrap()?;
+        if extended.unwrap_or(false) {
+            info.pricing_state = dex
+                .get_pool_pricing_state(tokens)
+                .near_unwrap()
+                .map(TryInto::try_into)
+                .transpose()
+                .near_unwrap();
+        }
+        Some(info)
     }
 
-    #[private]
-    pub fn exchange_callback_post_withdraw(
-        &mut self,
-        token_id: &AccountId,
-        sender_id: &AccountId,
-        amount: U128,
-        unregister: bool,
-    ) {
-        let succeeded = promise_result!().is_ok();
+    /// Get cumulative swap statistics of the pool: volumes, fees and number of swaps.
+    /// Token amounts are ordered the same way as `tokens`.
+    pub fn get_pool_stats(&self, tokens: Pair<TokenId>) -> Option<PoolStats> {
+        self.as_dex()
+            .get_pool_stats(tokens.into())
+            .near_unwrap()
+            .map(TryInto::try_into)
+            .transpose()
+            .near_unwrap()
+    }
 
-        let mut dex = self.as_dex_mut();
-        let StateMembersMut {
-            contract: Contract::V0(ref mut contract),
-            logger,
-            ..
-        } = dex.members_mut();
+    /// Get distribution of swapped volume among fee levels of the pool, accumulated since
+    /// it's tracked. Token amounts are ordered the same way as `tokens`.
+    pub fn get_level_utilization(&self, tokens: Pair<TokenId>) -> Option<LevelUtilization> {
+        self.as_dex()
+            .get_level_utilization(tokens.into())
+            .near_unwrap()
+            .map(TryInto::try_into)
+            .transpose()
+            .near_unwrap()
+    }
+
+    /// Get LP fee accumulators of concentrated pool, ordered the same way as `tokens`,
+    /// to verify position rewards off-chain.
+    pub fn get_pool_fee_accumulators(
+        &self,
+        tokens: Pair<TokenId>,
+    ) -> Option<PoolFeeAccumulatorsInfo> {
+        self.as_dex()
+            .get_pool_fee_accumulators(tokens.into())
+            .near_unwrap()
+            .map(Into::into)
+    }
+
+    /// Get kind of pool registered for the pair of tokens, if any.
+    pub fn get_pool_kind(&self, tokens: Pair<TokenId>) -> Option<dex::PoolKind> {
+        self.as_dex().get_pool_kind(tokens.into()).near_unwrap()
+    }
+
+    /// Get tokens of the pool registered under `alias`, if any.
+    pub fn resolve_pool(&self, alias: String) -> Option<Pair<TokenId>> {
+        self.as_dex().resolve_pool(&alias).map(Into::into)
+    }
+
+    /// Get alias of the pool, if it has one.
+    pub fn get_pool_alias(&self, tokens: Pair<TokenId>) -> Option<String> {
+        self.as_dex().get_pool_alias(tokens.into()).near_unwrap()
+    }
+
+    /// Get registered pool aliases, skipping first `from` of them.
+    pub fn get_pool_aliases(&self, from: u32, limit: u32) -> Vec<PoolAliasInfo> {
+        self.as_dex()
+            .get_pool_aliases(from as usize, limit as usize)
+            .into_iter()
+            .map(Into::into)
+            .collect()
+    }
+
+    /// Get recent administrative calls of owner, admins and guards, starting from sequence
+    /// number `from`. Only the most recent calls are kept, older ones are skipped.
+    pub fn get_admin_log(&self, from: U64, limit: u32) -> Vec<AdminLogEntryInfo> {
+        self.as_dex()
+            .get_admin_log(from.into(), limit as usize)
+            .into_iter()
+            .map(Into::into)
+            .collect()
+    }
+
+    /// Get NEAR paid for storage of the pool and its creator; `null` for pools created
+    /// before storage deposits were recorded.
+    pub fn get_pool_storage_deposit(
+        &self,
+        tokens: Pair<TokenId>,
+    ) -> Option<PoolStorageDepositInfo> {
+        self.as_dex()
+            .get_pool_storage_deposit(tokens.into())
+            .near_unwrap()
+            .map(Into::into)
+    }
+
+    /// Get total NEAR held by contract for storage of pools, compared to the storage cost
+    /// of those pools at current storage price.
+    pub fn get_pool_storage_report(&self) -> PoolStorageReport {
+        let (pools, total_deposits, storage_bytes) =
+            self.as_dex().get_pool_storage_deposits_total();
+        let required = u128::from(storage_bytes) * env::storage_byte_cost();
+        PoolStorageReport {
+            pools: pools.into(),
+            total_deposits: total_deposits.into(),
+            required: required.into(),
+            headroom: total_deposits.saturating_sub(required).into(),
+        }
+    }
+
+    /// Get state of constant product pool. Token amounts are ordered the same way as `tokens`.
+    pub fn get_classic_pool_info(&self, tokens: Pair<TokenId>) -> Option<ClassicPoolInfo> {
+        self.as_dex()
+            .get_classic_pool_info(tokens.into())
+            .near_unwrap()
+            .map(Into::into)
+    }
+
+    /// Get shares of constant product pool owned by account.
+    pub fn get_classic_pool_shares(&self, tokens: Pair<TokenId>, account_id: AccountId) -> U128 {
+        self.as_dex()
+            .get_classic_pool_shares(tokens.into(), &account_id)
+            .near_unwrap()
+            .into()
+    }
+
+    /// Get state of stable swap pool. Token amounts are ordered the same way as `tokens`.
+    pub fn get_stable_pool_info(&self, tokens: Pair<TokenId>) -> Option<StablePoolInfo> {
+        self.as_dex()
+            .get_stable_pool_info(tokens.into())
+            .near_unwrap()
+            .map(Into::into)
+    }
+
+    /// Get shares of stable swap pool owned by account.
+    pub fn get_stable_pool_shares(&self, tokens: Pair<TokenId>, account_id: AccountId) -> U128 {
+        self.as_dex()
+            .get_stable_pool_shares(tokens.into(), &account_id)
+            .near_unwrap()
+            .into()
+    }
+
+    /// Get conditional order by id, if it's neither executed nor cancelled yet.
+    pub fn get_order(&self, order_id: U64) -> Option<ConditionalOrderInfo> {
+        let order_id = order_id.into();
+        self.as_dex()
+            .get_order(order_id)
+            .map(|order| (order_id, order).into())
+    }
+
+    /// Get conditional orders placed by account.
+    pub fn get_orders(&self, account_id: AccountId) -> Vec<ConditionalOrderInfo> {
+        self.as_dex()
+            .get_orders(&account_id)
+            .into_iter()
+            .map(Into::into)
+            .collect()
+    }
+
+    /// Get deposit intent by id, if it's neither executed nor cancelled yet.
+    pub fn get_deposit_intent(&self, intent_id: U64) -> Option<DepositIntentInfo> {
+        let intent_id = intent_id.into();
+        self.as_dex()
+            .get_deposit_intent(intent_id)
+            .map(|intent| (intent_id, intent).into())
+    }
+
+    /// Get deposit intents prepared by account.
+    pub fn get_deposit_intents(&self, account_id: AccountId) -> Vec<DepositIntentInfo> {
+        self.as_dex()
+            .get_deposit_intents(&account_id)
+            .into_iter()
+            .map(Into::into)
+            .collect()
+    }
+
+    /// Get integration by id, if registered.
+    pub fn get_integration(&self, id: dex::IntegrationId) -> Option<IntegrationInfo> {
+        self.as_dex()
+            .get_integration(id)
+            .map(|integration| (id, integration).into())
+    }
+
+    /// Get registered integrations, skipping first `from` of them
+    /// and returning at most `limit` ones.
+    pub fn get_integrations(&self, from: u32, limit: u32) -> Vec<IntegrationInfo> {
+        self.as_dex()
+            .get_integrations(from as usize, limit as usize)
+            .into_iter()
+            .map(Into::into)
+            .collect()
+    }
+
+    /// Get current price of `token_in` in units of `token_out`, which is checked against
+    /// trigger prices of conditional orders.
+    pub fn get_order_price(&self, token_in: TokenId, token_out: TokenId) -> f64 {
+        self.as_dex()
+            .get_order_price(&token_in, &token_out)
+            .near_unwrap()
+            .into()
+    }
+
+    /// Recommend fee levels in the pool: the level where opening position is likely most profitable,
+    /// based on LP fees earned per unit of liquidity on each level, and the level where swap of `amount`
+    /// of `tokens.left` into `tokens.right` would mostly execute.
+    pub fn recommend_fee_level(
+        &self,
+        tokens: Pair<TokenId>,
+        amount: U128,
+    ) -> FeeLevelRecommendation {
+        self.as_dex()
+            .recommend_fee_level(tokens.into(), amount.into())
+            .near_unwrap()
+    }
+
+    /// Simulate exact-in swaps of each of `amounts` of token on `side` of `tokens` into the other one,
+    /// without changing the pool. Returns average price and price impact per amount,
+    /// or `null` for amounts which can't be swapped. At most 32 amounts may be passed.
+    pub fn sample_price_curve(
+        &self,
+        tokens: Pair<TokenId>,
+        side: Side,
+        amounts: Vec<U128>,
+    ) -> Vec<Option<PriceCurvePoint>> {
+        let amounts = amounts.into_iter().map(Into::into).collect_vec();
+        self.as_dex()
+            .sample_price_curve(tokens.into(), side, &amounts)
+            .near_unwrap()
+            .into_iter()
+            .map(|sample| sample.map(Into::into))
+            .collect()
+    }
+
+    /// Evaluate position which `open_position` with the same parameters would open,
+    /// without changing the pool. Returns exact amounts which would be charged,
+    /// accounted liquidity and position ticks. Positions with `auto_balance` aren't supported.
+    pub fn estimate_open_position(
+        &self,
+        token_a: TokenId,
+        token_b: TokenId,
+        fee_rate: dex::BasisPoints,
+        position: PositionInit,
+    ) -> OpenPositionEstimate {
+        let estimate = self
+            .as_dex()
+            .estimate_open_position(&token_a, &token_b, fee_rate, position)
+            .near_unwrap();
+        let liquidity = liquidity_from_net_liquidity(estimate.net_liquidity, estimate.fee_level);
+        OpenPositionEstimate {
+            amount_a: estimate.amounts.0.into(),
+            amount_b: estimate.amounts.1.into(),
+            liquidity: crate::chain::Float::from(liquidity).into(),
+            net_liquidity: estimate.net_liquidity.into(),
+            tick_low: estimate.ticks.0.index(),
+            tick_high: estimate.ticks.1.index(),
+        }
+    }
+
+    /// Evaluate amounts which `close_position` would deposit to position owner if called now,
+    /// i.e. position balance at current price and fees accumulated since their last withdrawal.
+    pub fn estimate_close_position(&self, position_id: U64) -> ClosePositionEstimate {
+        let estimate = self
+            .as_dex()
+            .estimate_close_position(position_id.into())
+            .near_unwrap();
+        ClosePositionEstimate {
+            tokens: estimate.tokens.into(),
+            amounts: estimate.amounts.into(),
+            fees: estimate.fees.into(),
+        }
+    }
 
+    /// Get withdraws of account which are still waiting for callback.
+    /// Account can't be unregistered until they're finished.
+    pub fn get_pending_withdrawals(&self, account_id: AccountId) -> Vec<TokenAmount> {
+        self.as_dex()
+            .get_pending_withdrawals(&account_id)
+            .near_unwrap()
+            .into_iter()
+            .map(|(token_id, amount)| TokenAmount {
+                token_id,
+                amount: amount.into(),
+            })
+            .collect()
+    }
+
+    /// Get user's storage deposit and needed in the account of current version,
+    /// with breakdown of used storage and storage costs of prospective operations
+    pub fn get_user_storage_state(&self, account_id: &AccountId) -> Option<RefStorageState> {
+        let Contract::V1(ref contract) = &self.0;
         contract
             .accounts
-            .try_update(sender_id, |dex::Account::V0(acc)| {
-                let amount = amount.into();
-                // Untrack in any case
-                acc.withdraw_tracker.untrack(token_id, &amount);
+            .get(account_id)
+            .map(|Account::V1(ref account)| RefStorageState {
+                deposit: account.extra.near_amount.into(),
+                usage: account.storage_usage().into(),
+                breakdown: account.storage_breakdown(),
+                operation_costs: storage_operation_costs(),
+            })
+    }
 
-                if succeeded {
-                    // And try unregister token - but don't fail if we can't do it
-                    if unregister {
-                        let _ignore = acc.unregister_tokens([token_id]);
-                    }
-                } else {
-                    // If we fail, return tokens back to balance
-                    let balance = acc.deposit(token_id, amount).map_err(|e| error_here!(e))?;
-                    logger.log_deposit_event(sender_id, token_id, &amount, &balance);
-                }
+    pub fn get_version(&self) -> VersionInfo {
+        self.as_dex().get_version()
+    }
 
-                Ok(())
+    /// Get digest of critical state: pools' reserves and positions, accounts' balances and positions.
+    /// Off-chain replicas may compare it against digest of their own state to verify they're in sync.
+    /// Reads whole contract state, so may run out of gas on large states.
+    pub fn get_state_digest(&self) -> StateDigest {
+        self.as_dex().get_state_digest().near_unwrap().into()
+    }
+
+    /// Verify cheap invariants of contract state, so monitoring can alert on state corruption.
+    /// Positions are checked only for a page of them: first `from` are skipped,
+    /// and at most `limit` are checked.
+    pub fn health_check(&self, from: u32, limit: u32) -> dex::HealthReport {
+        self.as_dex().health_check(from as usize, limit as usize)
+    }
+
+    /// Get value of pools' reserves in units of `reference_token`, for a page of pools:
+    /// first `from` pools are skipped, and at most `limit` are valued.
+    /// Each token is priced by its pool against `reference_token`; tokens without such pool
+    /// are valued at zero. Returned `next_from` should be passed to get the next page.
+    pub fn get_tvl(&self, reference_token: TokenId, from: u32, limit: u32) -> TvlInfo {
+        self.as_dex()
+            .get_tvl(&reference_token, from as usize, limit as usize)
+            .into()
+    }
+
+    /// Get events standard, version currently emitted and changelog of all supported versions.
+    /// Indexers may use it to find out which event payloads to expect after contract upgrade.
+    pub fn events_schema(&self) -> EventsSchema {
+        super::events::events_schema()
+    }
+
+    /// Perform several view calls in one round-trip.
+    /// Results are returned in the same order as calls; if any call fails, whole batch fails.
+    pub fn batch_view(&self, calls: Vec<ViewCall>) -> Vec<ViewResult> {
+        calls
+            .into_iter()
+            .map(|call| match call {
+                ViewCall::Metadata => ViewResult::Metadata(self.metadata()),
+                ViewCall::GetVersion => ViewResult::GetVersion(self.get_version()),
+                ViewCall::GetOwner => ViewResult::GetOwner(self.get_owner()),
+                ViewCall::GetVerifiedTokens => {
+                    ViewResult::GetVerifiedTokens(self.get_verified_tokens())
+                }
+                ViewCall::GetDeposits { account_id } => {
+                    ViewResult::GetDeposits(self.get_deposits(&account_id))
+                }
+                ViewCall::GetDeposit {
+                    account_id,
+                    token_id,
+                } => ViewResult::GetDeposit(self.get_deposit(&account_id, &token_id)),
+                ViewCall::GetUserTokens { account_id } => {
+                    ViewResult::GetUserTokens(self.get_user_tokens(&account_id))
+                }
+                ViewCall::GetUserStorageState { account_id } => {
+                    ViewResult::GetUserStorageState(self.get_user_storage_state(&account_id))
+                }
+                ViewCall::GetPoolInfo { tokens, extended } => {
+                    ViewResult::GetPoolInfo(self.get_pool_info(tokens, extended))
+                }
+                ViewCall::GetPoolStats { tokens } => {
+                    ViewResult::GetPoolStats(self.get_pool_stats(tokens))
+                }
+                ViewCall::GetPositionInfo { position_id } => {
+                    ViewResult::GetPositionInfo(self.get_position_info(position_id))
+                }
             })
-            .near_unwrap();
+            .collect()
     }
-    // Just return value passed in. Used to complete async withdrawals with value
-    #[private]
-    pub fn return_option_amount(&self, amount: Option<WasmAmount>) -> Option<WasmAmount> {
-        amount
+}
+/// Receive tokens from other contracts
+#[near_bindgen]
+impl FungibleTokenReceiver for State {
+    /// Callback on receiving tokens by this contract
+    ///
+    /// # Parameters
+    /// * `sender_id` - original owner of tokens
+    /// * `amount` - how many tokens to deposit; for tokens in reconcile on deposit mode,
+    ///     amount actually received is credited instead, see `set_token_reconcile_on_deposit`;
+    ///     if token interface check is enabled, first deposit of token is credited after check,
+    ///     see `set_token_interface_check`
+    /// * `msg` - additional transfer payload; if empty, performs simple deposit;
+    ///     if it's `{"intent_id":"<id>"}`, funds leg of deposit intent, see `prepare_deposit_intent`;
+    ///     if it's `{"open_position_single_sided":{...}}`, opens position with transferred
+    ///     token alone and refunds the part which position doesn't take;
+    ///     otherwise should contain versioned envelope of actions serialized as JSON,
+    ///     e.g. `{"v":2,"actions":[...]}`, or bare list of actions as in version 1;
+    ///     see `execute_actions` on format of actions
+    #[allow(unreachable_code)]
+    #[payable]
+    fn ft_on_transfer(
+        &mut self,
+        sender_id: AccountId,
+        amount: U128,
+        msg: String,
+    ) -> PromiseOrValue<U128> {
+        // Token id is the caller here
+        let token_in = env::predecessor_account_id();
+        // Banned tokens are refunded in full
+        if self.as_dex().is_token_banned(&token_in) {
+            return PromiseOrValue::Value(amount);
+        }
+        if self.as_dex().is_token_interface_check_enabled() {
+            match self.as_dex().get_token_interface_status(&token_in) {
+                // Token contract is checked before its first deposit is credited
+                None => {
+                    return PromiseOrValue::Promise(
+                        self.check_token_transfer(token_in, sender_id, amount, msg),
+                    );
+                }
+                Some(false) => return PromiseOrValue::Value(amount),
+                Some(true) => {}
+            }
+        }
+        self.receive_tokens(sender_id, token_in, amount, msg)
     }
 }
-/// Owner-only APIs
+/// The newest version of actions message format of `ft_on_transfer`
+const ACTIONS_MSG_VERSION: u64 = 2;
+
+/// Versioned envelope of actions in message of `ft_on_transfer`
+#[derive(Deserialize)]
+#[serde(crate = "near_sdk::serde")]
+struct ActionsMsg {
+    v: u64,
+    actions: Vec<serde_json::Value>,
+}
+
+/// Message of `ft_on_transfer` which funds leg of deposit intent
+#[derive(Deserialize)]
+#[serde(crate = "near_sdk::serde", deny_unknown_fields)]
+struct DepositIntentMsg {
+    intent_id: U64,
+}
+
+/// Message of `ft_on_transfer` which opens position funded by transferred token alone;
+/// part of transfer which position doesn't take is refunded
+#[derive(Deserialize)]
+#[serde(crate = "near_sdk::serde", deny_unknown_fields)]
+struct OpenPositionSingleSidedMsg {
+    open_position_single_sided: dex::OpenPositionSingleSided,
+}
+
+/// Parse actions from message of `ft_on_transfer`
+///
+/// Message is either versioned envelope, or bare list of actions, which is treated as version 1.
+/// Actions are parsed one by one, so that error points at the one which isn't understood,
+/// e.g. because it was introduced in a newer contract version.
+fn parse_actions_msg(msg: &str) -> dex::Result<Vec<Action<()>>> {
+    let actions =
+        match serde_json::from_str(msg).map_err(|e| error_here!(Error::WrongMsgFormat(e)))? {
+            serde_json::Value::Array(actions) => actions,
+            envelope => {
+                let ActionsMsg { v, actions } = serde_json::from_value(envelope)
+                    .map_err(|e| error_here!(Error::WrongMsgFormat(e)))?;
+                ensure_here!(
+                    (1..=ACTIONS_MSG_VERSION).contains(&v),
+                    Error::UnsupportedMsgVersion {
+                        version: v,
+                        supported: ACTIONS_MSG_VERSION
+                    }
+                );
+                actions
+            }
+        };
+    actions
+        .into_iter()
+        .enumerate()
+        .map(|(index, action)| {
+            serde_json::from_value(action)
+                .map_err(|e| error_here!(Error::WrongActionFormat(index, e)))
+        })
+        .collect()
+}
+
+/// Operations over accounts
 #[near_bindgen]
 impl State {
-    /// Get the owner of this account.
-    pub fn get_owner(&self) -> AccountId {
-        let Contract::V0(ref contract) = &self.0;
-        contract.owner_id.clone()
+    /// Registers given token in the user's account deposit.
+    /// Fails if not enough balance on this account to cover storage.
+    #[payable]
+    pub fn register_tokens(&mut self, token_ids: &Vec<AccountId>) {
+        assert_one_yocto();
+        let account_id = env::predecessor_account_id();
+        self.as_dex_mut()
+            .register_tokens(&account_id, token_ids)
+            .near_unwrap();
+    }
+    /// Unregister given token from user's account deposit.
+    /// Panics if the balance of any given token is non 0.
+    #[payable]
+    pub fn unregister_tokens(&mut self, token_ids: &Vec<AccountId>) {
+        assert_one_yocto();
+        self.as_dex_mut()
+            .unregister_tokens(&env::predecessor_account_id(), token_ids)
+            .near_unwrap();
     }
 
-    /// Extend verified tokens with new tokens. Only can be called by owner.
+    /// Grant `viewer_id` read-only access to caller's private views.
+    /// Storage of grant is paid from caller's storage deposit.
     #[payable]
-    pub fn extend_verified_tokens(&mut self, tokens: Vec<TokenId>) {
-        self.as_dex_mut().add_verified_tokens(tokens).near_unwrap();
+    pub fn grant_view_access(&mut self, viewer_id: AccountId) {
+        assert_one_yocto();
+        self.as_dex_mut().grant_view_access(viewer_id).near_unwrap();
     }
 
-    /// Remove verified token. Only can be called by owner.
+    /// Revoke read-only access of `viewer_id` to caller's private views.
     #[payable]
-    pub fn remove_verified_tokens(&mut self, tokens: Vec<TokenId>) {
+    pub fn revoke_view_access(&mut self, viewer_id: AccountId) {
+        assert_one_yocto();
         self.as_dex_mut()
-            .remove_verified_tokens(tokens)
+            .revoke_view_access(&viewer_id)
             .near_unwrap();
     }
 
-    /// Extend guard accounts with new accounts. Only can be called by owner.
-    #[payable]
-    pub fn extend_guard_accounts(&mut self, accounts: Vec<AccountId>) {
-        self.as_dex_mut().add_guard_accounts(accounts).near_unwrap();
+    /// Get accounts which `account_id` granted access to its private views.
+    pub fn get_view_grants(&self, account_id: AccountId) -> Vec<AccountId> {
+        self.as_dex().get_view_grants(&account_id)
     }
 
-    /// Remove guard accounts. Only can be called by owner.
+    /// Check whether `viewer_id` may access private views of `account_id`.
+    pub fn has_view_access(&self, account_id: AccountId, viewer_id: AccountId) -> bool {
+        self.as_dex().has_view_access(&account_id, &viewer_id)
+    }
+
+    /// Opt into vault mode: withdrawals of at least `thresholds` amounts are sent only after
+    /// `delay` seconds, and `guardian` may cancel them meanwhile. Internal transfers
+    /// of such amounts are rejected. Vault can be removed only by `guardian`.
+    /// Storage of vault is paid from caller's storage deposit.
     #[payable]
-    pub fn remove_guard_accounts(&mut self, accounts: Vec<AccountId>) {
+    pub fn set_vault(&mut self, guardian: AccountId, thresholds: Vec<TokenAmount>, delay: U64) {
+        assert_one_yocto();
+        let thresholds = thresholds
+            .into_iter()
+            .map(|threshold| (threshold.token_id, threshold.amount.into()))
+            .collect();
         self.as_dex_mut()
-            .remove_guard_accounts(accounts)
+            .set_vault(guardian, thresholds, delay.into())
             .near_unwrap();
     }
 
-    /// Suspend payable API calls. It can be done by owner or by guards.
+    /// Remove vault of `account_id`, returning tokens of delayed withdrawals to its deposits.
+    /// Can be called only by guardian of vault.
     #[payable]
-    pub fn suspend_payable_api(&mut self) {
-        self.as_dex_mut().suspend_payable_api().near_unwrap();
+    pub fn remove_vault(&mut self, account_id: AccountId) {
+        assert_one_yocto();
+        self.as_dex_mut().remove_vault(&account_id).near_unwrap();
     }
 
-    /// Resume payable API calls. It can be done by owner or by guards.
+    /// Cancel delayed withdrawal, returning its tokens to deposits of `account_id`.
+    /// Can be called only by guardian of vault.
+    #[payable]
+    pub fn cancel_delayed_withdrawal(&mut self, account_id: AccountId, withdrawal_id: U64) {
+        assert_one_yocto();
+        self.as_dex_mut()
+            .cancel_delayed_withdrawal(&account_id, withdrawal_id.into())
+            .near_unwrap();
+    }
+
+    /// Send tokens of delayed withdrawal of `account_id` once its delay has expired.
+    /// Can be called by any account.
+    #[payable]
+    pub fn execute_delayed_withdrawal(
+        &mut self,
+        account_id: AccountId,
+        withdrawal_id: U64,
+    ) -> Promise {
+        assert_one_yocto();
+        self.as_dex_mut()
+            .execute_delayed_withdrawal(&account_id, withdrawal_id.into())
+            .near_unwrap()
+    }
+
+    /// Get vault of `account_id`, including pending delayed withdrawals.
+    pub fn get_vault(&self, account_id: AccountId) -> Option<VaultInfo> {
+        self.as_dex().get_vault(&account_id).map(Into::into)
+    }
+
+    /// Register `public_key` of caller's function-call access key as session key.
+    /// Calls signed with session key may swap only tokens listed in `volume_caps`,
+    /// no more than specified amount of each per day, and can't withdraw or execute actions.
+    /// Storage of key is paid from caller's storage deposit.
+    #[payable]
+    pub fn register_session_key(&mut self, public_key: PublicKey, volume_caps: Vec<TokenAmount>) {
+        assert_one_yocto();
+        let volume_caps = volume_caps
+            .into_iter()
+            .map(|cap| (cap.token_id, cap.amount.into()))
+            .collect();
+        self.as_dex_mut()
+            .register_session_key(String::from(&public_key), volume_caps)
+            .near_unwrap();
+    }
+
+    /// Revoke caller's session key. Access key itself isn't deleted.
+    #[payable]
+    pub fn revoke_session_key(&mut self, public_key: PublicKey) {
+        assert_one_yocto();
+        self.as_dex_mut()
+            .revoke_session_key(&String::from(&public_key))
+            .near_unwrap();
+    }
+
+    /// Get session keys of `account_id`, with volumes swapped by them.
+    pub fn get_session_keys(&self, account_id: AccountId) -> Vec<SessionKeyInfo> {
+        self.as_dex()
+            .get_session_keys(&account_id)
+            .into_iter()
+            .map(Into::into)
+            .collect()
+    }
+
+    /// Opt into or out of automatic storage top-up: when caller's storage deposit is insufficient
+    /// for an operation, missing amount is taken from caller's wNEAR balance, which gets unwrapped.
+    #[payable]
+    pub fn set_storage_auto_top_up(&mut self, enabled: bool) {
+        assert_one_yocto();
+        let account_id = env::predecessor_account_id();
+        let mut dex = self.as_dex_mut();
+        dex.ensure_payable_api_resumed().near_unwrap();
+        let Contract::V1(ref mut contract) = dex.contract_mut();
+        contract
+            .accounts
+            .try_update(&account_id, |Account::V1(ref mut account)| {
+                account.extra.storage_auto_top_up = enabled;
+                Ok(())
+            })
+            .near_unwrap();
+    }
+
+    /// Allow or disallow guards' storage cleanup to unregister caller's tokens with zero balance.
+    #[payable]
+    pub fn set_registration_cleanup_allowed(&mut self, allowed: bool) {
+        assert_one_yocto();
+        self.as_dex_mut()
+            .set_registration_cleanup_allowed(allowed)
+            .near_unwrap();
+    }
+
+    pub fn token_register_of(&self, account_id: &AccountId, token_id: &AccountId) -> bool {
+        let Contract::V1(ref contract) = &self.0;
+        contract
+            .accounts
+            .get(account_id)
+            .map_or(false, |Account::V1(ref account)| {
+                account.token_balances.get(token_id).is_some()
+            })
+    }
+    /// Withdraws given token from the deposits of given user.
+    /// a zero amount means to withdraw all in user's inner account.
+    ///
+    /// # Parameters
+    /// * `token_id` - token which should be withdrawn
+    /// * `amount` - amount to withdraw
+    /// * `unregister` - if `Ok(true)`, DEX will attempt to unregister token from account.
+    ///     Unregister will succeed only if named token has zero balance, but failure to do so will not
+    ///     interrupt operation as a whole
+    ///
+    /// # Returns
+    /// Promise which produces final operation result
+    #[payable]
+    #[allow(clippy::needless_pass_by_value)] // token_id is passed by value, dictated by existing API
+    pub fn withdraw(
+        &mut self,
+        token_id: AccountId,
+        amount: U128,
+        unregister: Option<bool>,
+    ) -> Option<Promise> {
+        assert_one_yocto();
+        self.as_dex_mut()
+            .withdraw(
+                &env::predecessor_account_id(),
+                &token_id,
+                amount.into(),
+                unregister.unwrap_or(false),
+                (),
+            )
+            .near_unwrap()
+    }
+
+    /// Move deposited token from caller's account to another account of DEX, without on-chain transfer
+    ///
+    /// # Parameters
+    /// * `receiver_id` - registered account which receives tokens; must have `token_id` registered
+    /// * `token_id` - token which should be transferred
+    /// * `amount` - amount to transfer
+    #[payable]
+    pub fn transfer_internal(&mut self, receiver_id: AccountId, token_id: TokenId, amount: U128) {
+        assert_one_yocto();
+        self.as_dex_mut()
+            .transfer_internal(&receiver_id, &token_id, amount.into())
+            .near_unwrap();
+    }
+
+    /// Register integration, e.g. frontend, owned by caller. Swaps may carry returned id
+    /// as `integration_id`, which is echoed in `swap` events for attribution of volume.
+    /// Storage is paid from caller's storage deposit.
+    ///
+    /// # Parameters
+    /// * `name` - non-empty name of integration, up to 64 bytes
+    /// * `metadata` - free-form description, e.g. URL or JSON, up to 1024 bytes
+    #[payable]
+    pub fn register_integration(&mut self, name: String, metadata: String) -> dex::IntegrationId {
+        assert_one_yocto();
+        self.as_dex_mut()
+            .register_integration(name, metadata)
+            .near_unwrap()
+    }
+
+    #[private]
+    pub fn exchange_callback_post_withdraw(
+        &mut self,
+        token_id: &AccountId,
+        sender_id: &AccountId,
+        amount: U128,
+        unregister: bool,
+        callback_id: U64,
+    ) {
+        self.consume_callback(callback_id.into()).near_unwrap();
+        let succeeded = promise_result!().is_ok();
+        self.as_dex_mut()
+            .end_reconciled_outflow(token_id, amount.into(), succeeded);
+
+        let mut dex = self.as_dex_mut();
+        let StateMembersMut {
+            contract: Contract::V1(ref mut contract),
+            logger,
+            ..
+        } = dex.members_mut();
+
+        contract
+            .accounts
+            .try_update(sender_id, |dex::Account::V1(acc)| {
+                let amount = amount.into();
+                // Untrack in any case
+                acc.withdraw_tracker.untrack(token_id, &amount);
+
+                if succeeded {
+                    // And try unregister token - but don't fail if we can't do it
+                    if unregister {
+                        let _ignore = acc.unregister_tokens([token_id]);
+                    }
+                } else {
+                    // If we fail, return tokens back to balance
+                    let balance = acc.deposit(token_id, amount).map_err(|e| error_here!(e))?;
+                    logger.log_deposit_event(sender_id, token_id, &amount, &balance);
+                }
+
+                Ok(())
+            })
+            .near_unwrap();
+    }
+    // Just return value passed in. Used to complete async withdrawals with value
+    #[private]
+    pub fn return_option_amount(
+        &mut self,
+        amount: Option<WasmAmount>,
+        callback_id: U64,
+    ) -> Option<WasmAmount> {
+        self.consume_callback(callback_id.into()).near_unwrap();
+        amount
+    }
+
+    /// Cache metadata fetched by `refresh_tokens_metadata`.
+    /// If fetch failed, previously cached metadata is kept.
+    #[private]
+    pub fn cache_token_metadata(&mut self, token_id: AccountId, callback_id: U64) {
+        self.consume_callback(callback_id.into()).near_unwrap();
+        let metadata = match promise_result!(FungibleTokenMetadata) {
+            Ok(metadata) => metadata,
+            Err(e) => {
+                log_str(&format!("Failed to fetch metadata of {token_id}: {e}"));
+                return;
+            }
+        };
+        let icon_hash = metadata.icon.map(|icon| {
+            env::sha256(icon.as_bytes())
+                .try_into()
+                .unwrap_or_else(|_| unreachable!())
+        });
+        let Contract::V1(ref mut contract) = self.0;
+        contract.extra.tokens_metadata.insert(
+            &token_id,
+            &CachedTokenMetadata {
+                symbol: metadata.symbol,
+                decimals: metadata.decimals,
+                icon_hash,
+                updated_at: env::block_timestamp() / 1_000_000_000,
+            },
+        );
+    }
+
+    /// Cache contract's balance of token fetched by `set_token_reconcile_on_deposit`
+    /// or `resync_reconciled_balance`. If fetch failed, deposits of token fail
+    /// until balance is fetched again.
+    #[private]
+    pub fn cache_reconciled_balance(&mut self, token_id: AccountId, callback_id: U64) {
+        self.consume_callback(callback_id.into()).near_unwrap();
+        let balance = match promise_result!(U128) {
+            Ok(balance) => balance,
+            Err(e) => {
+                log_str(&format!("Failed to fetch balance of {token_id}: {e}"));
+                return;
+            }
+        };
+        self.as_dex_mut()
+            .set_reconciled_balance(&token_id, balance.into())
+            .near_unwrap();
+    }
+
+    /// Credit transfer of token in reconcile on deposit mode by delta of contract's balance,
+    /// fetched by `ft_on_transfer`, and process it as directed by `msg`.
+    /// If balance can't be fetched, or its delta exceeds `amount`, or withdrawals of token
+    /// are in flight, transfer is refunded in full.
+    #[private]
+    pub fn resolve_reconciled_deposit(
+        &mut self,
+        token_id: AccountId,
+        sender_id: AccountId,
+        amount: U128,
+        msg: String,
+        callback_id: U64,
+    ) -> PromiseOrValue<U128> {
+        self.consume_callback(callback_id.into()).near_unwrap();
+        let balance = promise_result!(U128).ok().map(Into::into);
+        let received = match self
+            .as_dex_mut()
+            .reconcile_deposit(&token_id, balance, amount.into())
+        {
+            Ok(received) => received,
+            Err(e) => {
+                // Not panicking, so that deposit is released for next transfers of token
+                log_str(&format!("Failed to reconcile deposit of {token_id}: {e}"));
+                return PromiseOrValue::Value(amount);
+            }
+        };
+        let (refund, promise) =
+            self.on_tokens_received(sender_id, token_id.clone(), U128(received), msg);
+        // Refund leaves contract's balance once token resolves transfer
+        self.as_dex_mut()
+            .track_reconciled_outflow(&token_id, refund.into());
+        self.return_refund(refund, promise)
+    }
+
+    /// Cache outcome of interface check requested with `check_token_interface`.
+    #[private]
+    pub fn cache_token_interface(&mut self, token_id: AccountId, callback_id: U64) {
+        self.consume_callback(callback_id.into()).near_unwrap();
+        let implemented = Self::token_interface_pinged();
+        if !implemented {
+            log_str(&format!("Token {token_id} failed interface check"));
+        }
+        self.as_dex_mut()
+            .set_token_interface_status(&token_id, implemented);
+    }
+
+    /// Cache outcome of interface check of token transferred for the first time;
+    /// if check passed, credit transfer as directed by `msg`, otherwise refund it in full.
+    #[private]
+    pub fn resolve_checked_deposit(
+        &mut self,
+        token_id: AccountId,
+        sender_id: AccountId,
+        amount: U128,
+        msg: String,
+        callback_id: U64,
+    ) -> PromiseOrValue<U128> {
+        self.consume_callback(callback_id.into()).near_unwrap();
+        let implemented = Self::token_interface_pinged();
+        self.as_dex_mut()
+            .set_token_interface_status(&token_id, implemented);
+        if !implemented {
+            log_str(&format!("Token {token_id} failed interface check"));
+            return PromiseOrValue::Value(amount);
+        }
+        self.receive_tokens(sender_id, token_id, amount, msg)
+    }
+}
+/// LP share tokens, NEP-141-like API where each token is addressed by `token_id`
+/// in form `<token_a>:<token_b>:<fee_rate>`
+#[near_bindgen]
+impl State {
+    /// List all LP tokens ever minted
+    #[cfg(feature = "lp-tokens")]
+    pub fn get_lp_token_ids(&self) -> Vec<String> {
+        self.as_dex()
+            .get_lp_token_ids()
+            .iter()
+            .map(ToString::to_string)
+            .collect()
+    }
+
+    #[cfg(feature = "lp-tokens")]
+    pub fn ft_total_supply(&self, token_id: String) -> U128 {
+        let token_id = parse_lp_token_id(&token_id);
+        self.as_dex().get_lp_token_supply(&token_id).into()
+    }
+
+    #[cfg(feature = "lp-tokens")]
+    pub fn ft_balance_of(&self, token_id: String, account_id: AccountId) -> U128 {
+        let token_id = parse_lp_token_id(&token_id);
+        self.as_dex()
+            .get_lp_token_balance(&token_id, &account_id)
+            .into()
+    }
+
+    /// Deposit liquidity into managed full-range position of pool and fee level,
+    /// and mint LP tokens for it
+    ///
+    /// # Parameters
+    /// * `tokens` - pair of tokens, in any order
+    /// * `fee_rate` - fee rate of managed position
+    /// * `max_amounts` - maximum amounts to take from caller's deposits, in order of `tokens`
+    ///
+    /// # Returns
+    /// Amounts taken, in order of `tokens`, and amount of LP tokens minted
+    #[cfg(feature = "lp-tokens")]
+    #[payable]
+    pub fn mint_lp_tokens(
+        &mut self,
+        tokens: Pair<TokenId>,
+        fee_rate: BasisPoints,
+        max_amounts: Pair<U128>,
+    ) -> (U128, U128, U128) {
+        assert_one_yocto();
+        let (amount_a, amount_b, minted) = self
+            .as_dex_mut()
+            .mint_lp_tokens(tokens.into(), fee_rate, max_amounts.into())
+            .near_unwrap();
+        (amount_a.into(), amount_b.into(), minted.into())
+    }
+
+    /// Burn LP tokens and credit share of managed position to caller's deposits
+    ///
+    /// # Returns
+    /// Amounts credited, in order of tokens in `token_id`
+    #[cfg(feature = "lp-tokens")]
+    #[payable]
+    pub fn burn_lp_tokens(&mut self, token_id: String, amount: U128) -> Pair<U128> {
+        assert_one_yocto();
+        let token_id = parse_lp_token_id(&token_id);
+        self.as_dex_mut()
+            .burn_lp_tokens(&token_id, amount.into())
+            .near_unwrap()
+            .into()
+    }
+
+    #[cfg(feature = "lp-tokens")]
+    #[payable]
+    pub fn ft_transfer(
+        &mut self,
+        token_id: String,
+        receiver_id: AccountId,
+        amount: U128,
+        memo: Option<String>,
+    ) {
+        assert_one_yocto();
+        let token_id = parse_lp_token_id(&token_id);
+        self.as_dex_mut()
+            .transfer_lp_tokens(&token_id, &receiver_id, amount.into(), memo.as_deref())
+            .near_unwrap();
+    }
+
+    /// Transfer LP tokens and notify receiver via `mft_on_transfer`;
+    /// unused amount returned by receiver is refunded to sender
+    #[cfg(feature = "lp-tokens")]
+    #[payable]
+    pub fn ft_transfer_call(
+        &mut self,
+        token_id: String,
+        receiver_id: AccountId,
+        amount: U128,
+        memo: Option<String>,
+        msg: String,
+    ) -> PromiseOrValue<U128> {
+        assert_one_yocto();
+        let lp_token_id = parse_lp_token_id(&token_id);
+        let sender_id = env::predecessor_account_id();
+        self.as_dex_mut()
+            .transfer_lp_tokens(&lp_token_id, &receiver_id, amount.into(), memo.as_deref())
+            .near_unwrap();
+
+        let gas_config = self.as_dex().get_gas_config();
+        super::ext_lp_token_receiver::ext(receiver_id.clone())
+            .with_attached_deposit(0u128)
+            .with_static_gas(super::GAS_FOR_LP_TOKEN_ON_TRANSFER)
+            .mft_on_transfer(token_id.clone(), sender_id.clone(), amount, msg)
+            .then(
+                super::ext_self::ext(env::current_account_id())
+                    .with_attached_deposit(0u128)
+                    .with_static_gas(super::tgas(gas_config.resolve_transfer))
+                    .lp_ft_resolve_transfer(token_id, sender_id, receiver_id, amount),
+            )
+            .into()
+    }
+
+    /// Refund unused LP tokens after `ft_transfer_call`
+    ///
+    /// # Returns
+    /// Amount actually kept by receiver
+    #[cfg(feature = "lp-tokens")]
+    #[private]
+    pub fn lp_ft_resolve_transfer(
+        &mut self,
+        token_id: String,
+        sender_id: AccountId,
+        receiver_id: AccountId,
+        amount: U128,
+    ) -> U128 {
+        let token_id = parse_lp_token_id(&token_id);
+        // Failed receiver call means nothing was used
+        let unused_amount = promise_result!(U128).unwrap_or(amount);
+        self.as_dex_mut()
+            .resolve_lp_token_transfer(
+                &token_id,
+                &sender_id,
+                &receiver_id,
+                amount.into(),
+                unused_amount.into(),
+            )
+            .near_unwrap()
+            .into()
+    }
+}
+
+#[cfg(feature = "lp-tokens")]
+fn parse_lp_token_id(token_id: &str) -> dex::LpTokenId {
+    token_id
+        .parse::<dex::LpTokenId>()
+        .map_err(|e| error_here!(e))
+        .near_unwrap()
+}
+
+/// Owner-only APIs
+#[near_bindgen]
+impl State {
+    /// Get the owner of this account.
+    pub fn get_owner(&self) -> AccountId {
+        let Contract::V1(ref contract) = &self.0;
+        contract.owner_id.clone()
+    }
+
+    /// Extend verified tokens with new tokens. Only can be called by owner.
+    #[payable]
+    pub fn extend_verified_tokens(&mut self, tokens: Vec<TokenId>) {
+        self.as_dex_mut().add_verified_tokens(tokens).near_unwrap();
+        self.record_admin_call("extend_verified_tokens");
+    }
+
+    /// Remove verified token. Only can be called by owner.
+    #[payable]
+    pub fn remove_verified_tokens(&mut self, tokens: Vec<TokenId>) {
+        self.as_dex_mut()
+            .remove_verified_tokens(tokens)
+            .near_unwrap();
+        self.record_admin_call("remove_verified_tokens");
+    }
+
+    /// Ban tokens, so that they can't be deposited, swapped or used to create new pools.
+    /// Only can be called by owner.
+    #[payable]
+    pub fn ban_tokens(&mut self, tokens: Vec<TokenId>) {
+        assert_one_yocto();
+        self.as_dex_mut().ban_tokens(tokens).near_unwrap();
+        self.record_admin_call("ban_tokens");
+    }
+
+    /// Remove tokens from banned list. Only can be called by owner.
+    #[payable]
+    pub fn unban_tokens(&mut self, tokens: Vec<TokenId>) {
+        assert_one_yocto();
+        self.as_dex_mut().unban_tokens(tokens).near_unwrap();
+        self.record_admin_call("unban_tokens");
+    }
+
+    /// Extend guard accounts with new accounts. Only can be called by owner.
+    #[payable]
+    pub fn extend_guard_accounts(&mut self, accounts: Vec<AccountId>) {
+        self.as_dex_mut().add_guard_accounts(accounts).near_unwrap();
+        self.record_admin_call("extend_guard_accounts");
+    }
+
+    /// Remove guard accounts. Only can be called by owner.
+    #[payable]
+    pub fn remove_guard_accounts(&mut self, accounts: Vec<AccountId>) {
+        self.as_dex_mut()
+            .remove_guard_accounts(accounts)
+            .near_unwrap();
+        self.record_admin_call("remove_guard_accounts");
+    }
+
+    /// Suspend payable API calls. It can be done by owner or by guards.
+    #[payable]
+    pub fn suspend_payable_api(&mut self) {
+        self.as_dex_mut().suspend_payable_api().near_unwrap();
+        self.record_admin_call("suspend_payable_api");
+    }
+
+    /// Resume payable API calls. It can be done by owner or by guards.
+    /// Can't be done until pools stored by previous contract version are converted.
     #[payable]
     pub fn resume_payable_api(&mut self) {
+        self.ensure_pools_migrated().near_unwrap();
         self.as_dex_mut().resume_payable_api().near_unwrap();
+        self.record_admin_call("resume_payable_api");
+    }
+
+    /// Stop tracking withdraw of account whose callback was lost, e.g. due to gas exhaustion,
+    /// so account can be unregistered again. Withdrawn amount isn't returned to account's balance.
+    /// It can be done by owner or by guards.
+    #[payable]
+    pub fn force_untrack(&mut self, account_id: AccountId, token_id: TokenId, amount: U128) {
+        assert_one_yocto();
+        self.as_dex_mut()
+            .force_untrack(&account_id, &token_id, amount.into())
+            .near_unwrap();
+        self.record_admin_call("force_untrack");
+    }
+
+    /// Fraction of the fee which goes to the DEX out of the total fee charged in swaps.
+    /// In units of 1/FEE_DIVISOR
+    #[payable]
+    pub fn set_protocol_fee_fraction(&mut self, protocol_fee_fraction: BasisPoints) {
+        assert_one_yocto();
+        self.as_dex_mut()
+            .set_protocol_fee_fraction(protocol_fee_fraction)
+            .near_unwrap();
+        self.record_admin_call("set_protocol_fee_fraction");
+    }
+
+    /// Set minimal accounted liquidity of newly opened position.
+    /// Must be between 2^-11 and 2^96. Only can be called by owner.
+    #[payable]
+    pub fn set_min_position_liquidity(&mut self, min_liquidity: f64) {
+        assert_one_yocto();
+        self.as_dex_mut()
+            .set_min_position_liquidity(crate::chain::Float::from(min_liquidity))
+            .near_unwrap();
+        self.record_admin_call("set_min_position_liquidity");
+    }
+
+    /// Get minimal accounted liquidity of newly opened position.
+    pub fn get_min_position_liquidity(&self) -> f64 {
+        self.as_dex().get_min_position_liquidity().into()
+    }
+
+    /// Set amount of details in trace records of pool math, written to logs
+    /// along with pool state events. Only can be called by owner.
+    #[cfg(feature = "tracing")]
+    #[payable]
+    pub fn set_trace_verbosity(&mut self, verbosity: dex::TraceVerbosity) {
+        assert_one_yocto();
+        self.as_dex_mut()
+            .set_trace_verbosity(verbosity)
+            .near_unwrap();
+        self.record_admin_call("set_trace_verbosity");
+    }
+
+    /// Get amount of details in trace records of pool math.
+    #[cfg(feature = "tracing")]
+    pub fn get_trace_verbosity(&self) -> dex::TraceVerbosity {
+        self.as_dex().get_trace_verbosity()
+    }
+
+    /// Enable or disable emission of events of specified kind, e.g. to save gas
+    /// on extremely hot pools. Only can be called by owner.
+    #[payable]
+    pub fn set_event_enabled(&mut self, event: dex::EventKind, enabled: bool) {
+        assert_one_yocto();
+        self.as_dex_mut()
+            .set_event_enabled(event, enabled)
+            .near_unwrap();
+        self.record_admin_call("set_event_enabled");
+    }
+
+    /// Get kinds of events which are currently not emitted.
+    pub fn get_disabled_events(&self) -> Vec<dex::EventKind> {
+        self.as_dex().get_disabled_events()
+    }
+
+    /// Set minimal amounts of tokens, in the order of `tokens`, deposited into newly opened
+    /// position in the pool, or remove them if `null` is passed. Only can be called by owner.
+    #[cfg(not(feature = "legacy-wasm-api"))]
+    #[payable]
+    pub fn set_min_deposit_amounts(
+        &mut self,
+        tokens: Pair<TokenId>,
+        min_amounts: Option<Pair<U128>>,
+    ) {
+        assert_one_yocto();
+        self.as_dex_mut()
+            .set_min_deposit_amounts(tokens.into(), min_amounts.map(Into::into))
+            .near_unwrap();
+        self.record_admin_call("set_min_deposit_amounts");
+    }
+
+    /// Get minimal amounts of tokens, in the order of `tokens`, deposited into newly opened
+    /// position in the pool. Zeros mean there's no limit.
+    #[cfg(not(feature = "legacy-wasm-api"))]
+    pub fn get_min_deposit_amounts(&self, tokens: Pair<TokenId>) -> Pair<U128> {
+        self.as_dex()
+            .get_min_deposit_amounts(tokens.into())
+            .near_unwrap()
+            .into()
+    }
+
+    /// Enable governance timelock by setting non-zero delay in seconds, or change delay while it's disabled.
+    /// Once timelock is enabled, delay can be changed only by scheduling `set_timelock_delay` action.
+    #[payable]
+    pub fn set_timelock_delay(&mut self, delay_sec: U64) {
+        assert_one_yocto();
+        self.as_dex_mut()
+            .set_timelock_delay(delay_sec.into())
+            .near_unwrap();
+        self.record_admin_call("set_timelock_delay");
+    }
+
+    /// Change amplification coefficient of stable swap pool. Only can be called by owner;
+    /// while timelock is enabled, should be scheduled as `set_stable_amplification` action instead.
+    #[payable]
+    pub fn set_stable_amplification(&mut self, tokens: Pair<TokenId>, amplification: u32) {
+        assert_one_yocto();
+        self.as_dex_mut()
+            .set_stable_amplification(tokens.into(), amplification)
+            .near_unwrap();
+        self.record_admin_call("set_stable_amplification");
+    }
+
+    /// Get parameters of protocol fee auctions; `null` means auctions are disabled.
+    pub fn get_fee_auction_config(&self) -> Option<FeeAuctionConfig> {
+        self.as_dex().get_fee_auction_config()
+    }
+
+    /// Enable Dutch auctions of protocol fees with specified parameters, or disable them with `null`.
+    /// Only can be called by owner; while timelock is enabled, should be scheduled
+    /// as `set_fee_auction_config` action instead.
+    #[payable]
+    pub fn set_fee_auction_config(&mut self, config: Option<FeeAuctionConfig>) {
+        assert_one_yocto();
+        self.as_dex_mut()
+            .set_fee_auction_config(config)
+            .near_unwrap();
+        self.record_admin_call("set_fee_auction_config");
+    }
+
+    /// Get running protocol fee auctions, with current prices.
+    pub fn get_fee_auctions(&self) -> Vec<FeeAuctionInfo> {
+        let now = self.get_block_timestamp();
+        self.as_dex()
+            .get_fee_auctions()
+            .into_iter()
+            .map(|(token, auction)| FeeAuctionInfo::new(token, auction, now))
+            .collect()
+    }
+
+    /// Move protocol fee accumulated by pool into Dutch auctions. Can be called by any account.
+    /// Fee in quote token is deposited onto owner's account; fee in other token is auctioned
+    /// for quote token, starting at configured premium over its pool price.
+    ///
+    /// Returns amounts of protocol fee taken from pool, ordered the same way as `tokens`.
+    #[payable]
+    pub fn start_fee_auction(&mut self, tokens: Pair<TokenId>) -> Pair<U128> {
+        assert_one_yocto();
+        self.as_dex_mut()
+            .start_fee_auction(tokens.into())
+            .near_unwrap()
+            .into()
+    }
+
+    /// Buy `amount` of `token` from protocol fee auction at current price, paying quote token
+    /// from caller's deposits, but no more than `max_quote_amount`.
+    ///
+    /// Returns amount of quote token paid.
+    #[payable]
+    pub fn take_fee_auction(
+        &mut self,
+        token: TokenId,
+        amount: U128,
+        max_quote_amount: U128,
+    ) -> U128 {
+        assert_one_yocto();
+        self.as_dex_mut()
+            .take_fee_auction(&token, amount.into(), max_quote_amount.into())
+            .near_unwrap()
+            .into()
+    }
+
+    /// Get governance timelock delay in seconds; zero means timelock is disabled.
+    pub fn get_timelock_delay(&self) -> U64 {
+        self.as_dex().get_timelock_delay().into()
+    }
+
+    /// Get hash of contract code which owner allowed to upgrade to, if any.
+    pub fn get_staged_upgrade(&self) -> Option<Base58CryptoHash> {
+        self.as_dex().get_staged_upgrade().map(Into::into)
+    }
+
+    /// Allow upgrade to contract code with specified SHA-256 hash, or cancel staged upgrade
+    /// with `null`. Only can be called by owner; while timelock is enabled, should be scheduled
+    /// as `stage_upgrade` action instead. Can be called while payable API is suspended.
+    #[payable]
+    pub fn stage_upgrade(&mut self, code_hash: Option<Base58CryptoHash>) {
+        assert_one_yocto();
+        self.as_dex_mut()
+            .stage_upgrade(code_hash.map(Into::into))
+            .near_unwrap();
+        self.record_admin_call("stage_upgrade");
+    }
+
+    /// Deploy staged contract code and migrate state in one batch. Only can be called by owner,
+    /// or by one of admins while multisig is enabled.
+    /// Code is passed as Borsh-serialized bytes, and its hash must match the staged one.
+    #[payable]
+    pub fn upgrade(&mut self, #[serializer(borsh)] code: Vec<u8>) -> Promise {
+        assert_one_yocto();
+        let code_hash: dex::CodeHash = env::sha256(&code)
+            .try_into()
+            .unwrap_or_else(|_| unreachable!());
+        self.as_dex_mut()
+            .take_staged_upgrade(&code_hash)
+            .near_unwrap();
+        self.record_admin_call("upgrade");
+        Promise::new(env::current_account_id())
+            .deploy_contract(code)
+            .function_call("migrate".to_string(), Vec::new(), 0, super::GAS_FOR_MIGRATE)
+    }
+
+    /// Schedule owner action, which can be executed after timelock delay. Returns action id.
+    #[payable]
+    pub fn schedule_admin_action(&mut self, action: AdminAction) -> U64 {
+        assert_one_yocto();
+        let id = self
+            .as_dex_mut()
+            .schedule_admin_action(action)
+            .near_unwrap();
+        self.record_admin_call("schedule_admin_action");
+        id.into()
+    }
+
+    /// Execute scheduled owner action once its timelock has expired.
+    #[payable]
+    pub fn execute_admin_action(&mut self, id: U64) {
+        assert_one_yocto();
+        self.as_dex_mut()
+            .execute_admin_action(id.into())
+            .near_unwrap();
+        self.record_admin_call("execute_admin_action");
+    }
+
+    /// Cancel scheduled owner action.
+    #[payable]
+    pub fn cancel_admin_action(&mut self, id: U64) {
+        assert_one_yocto();
+        self.as_dex_mut()
+            .cancel_admin_action(id.into())
+            .near_unwrap();
+        self.record_admin_call("cancel_admin_action");
+    }
+
+    /// Get owner actions waiting for execution, with their ids.
+    #[cfg(not(feature = "legacy-wasm-api"))]
+    pub fn get_scheduled_actions(&self) -> Vec<ScheduledActionInfo> {
+        self.as_dex()
+            .get_scheduled_actions()
+            .into_iter()
+            .map(|(id, scheduled)| ScheduledActionInfo {
+                id: id.into(),
+                scheduled,
+            })
+            .collect()
+    }
+
+    /// Enable multisig owner: owner actions will require `threshold` approvals from `admins`.
+    /// Once multisig is enabled, admins can be changed only through `set_admins` action.
+    #[payable]
+    pub fn set_admins(&mut self, admins: Vec<AccountId>, threshold: u16) {
+        assert_one_yocto();
+        self.as_dex_mut()
+            .set_admins(admins, threshold)
+            .near_unwrap();
+        self.record_admin_call("set_admins");
+    }
+
+    /// Get multisig admins and approval threshold; zero threshold means multisig is disabled.
+    #[cfg(not(feature = "legacy-wasm-api"))]
+    pub fn get_admins(&self) -> AdminsInfo {
+        let (admins, threshold) = self.as_dex().get_admins();
+        AdminsInfo { admins, threshold }
+    }
+
+    /// Propose owner action on behalf of multisig admins, counting as caller's approval.
+    /// Returns action hash, which other admins should approve.
+    #[payable]
+    pub fn propose_admin_action(&mut self, action: AdminAction) -> Base58CryptoHash {
+        assert_one_yocto();
+        let hash = self.as_dex_mut().propose_admin_action(action).near_unwrap();
+        self.record_admin_call("propose_admin_action");
+        hash.into()
+    }
+
+    /// Approve owner action proposed by multisig admin. Action is executed once approval
+    /// threshold is reached, or scheduled if governance timelock is enabled.
+    #[payable]
+    pub fn approve_admin_action(&mut self, action_hash: Base58CryptoHash) {
+        assert_one_yocto();
+        self.as_dex_mut()
+            .approve_admin_action(action_hash.into())
+            .near_unwrap();
+        self.record_admin_call("approve_admin_action");
+    }
+
+    /// Get owner actions waiting for admins' approvals, with their hashes.
+    #[cfg(not(feature = "legacy-wasm-api"))]
+    pub fn get_pending_approvals(&self) -> Vec<PendingApprovalInfo> {
+        self.as_dex()
+            .get_pending_approvals()
+            .into_iter()
+            .map(|(hash, pending)| PendingApprovalInfo {
+                action_hash: hash.into(),
+                pending,
+            })
+            .collect()
+    }
+
+    /// Set fraction of protocol fee, in basis points, which goes to insurance reserve
+    /// when protocol fee is withdrawn. Only can be called by owner.
+    #[payable]
+    pub fn set_insurance_fee_fraction(&mut self, insurance_fee_fraction: BasisPoints) {
+        assert_one_yocto();
+        self.as_dex_mut()
+            .set_insurance_fee_fraction(insurance_fee_fraction)
+            .near_unwrap();
+        self.record_admin_call("set_insurance_fee_fraction");
+    }
+
+    /// Get fraction of protocol fee, in basis points, which goes to insurance reserve.
+    pub fn get_insurance_fee_fraction(&self) -> BasisPoints {
+        self.as_dex().get_insurance_fee_fraction()
+    }
+
+    /// Register alias of the pool, replacing its previous alias, or remove alias
+    /// if `tokens` are omitted. Only can be called by owner.
+    #[payable]
+    pub fn set_pool_alias(&mut self, alias: String, tokens: Option<Pair<TokenId>>) {
+        assert_one_yocto();
+        self.as_dex_mut()
+            .set_pool_alias(alias, tokens.map(Into::into))
+            .near_unwrap();
+        self.record_admin_call("set_pool_alias");
+    }
+
+    /// Credit part of NEAR paid for storage of the pool, which exceeds its storage cost at current
+    /// storage price, back to the pool's creator. Only can be called by owner.
+    /// Returns credited amount.
+    #[payable]
+    pub fn credit_pool_storage_headroom(&mut self, tokens: Pair<TokenId>) -> U128 {
+        assert_one_yocto();
+        let credited = self
+            .as_dex_mut()
+            .credit_pool_storage_headroom(tokens.into(), env::storage_byte_cost())
+            .near_unwrap();
+        self.record_admin_call("credit_pool_storage_headroom");
+        credited.into()
+    }
+
+    /// Set gas attached to cross-contract calls, in Tgas. Only can be called by owner.
+    #[payable]
+    pub fn set_gas_config(&mut self, gas_config: dex::GasConfig) {
+        assert_one_yocto();
+        self.as_dex_mut().set_gas_config(gas_config).near_unwrap();
+        self.record_admin_call("set_gas_config");
+    }
+
+    /// Get gas attached to cross-contract calls, in Tgas.
+    pub fn get_gas_config(&self) -> dex::GasConfig {
+        self.as_dex().get_gas_config()
+    }
+
+    /// Get amounts of tokens kept in insurance reserve.
+    #[cfg(not(feature = "legacy-wasm-api"))]
+    pub fn get_insurance_reserve(&self) -> Vec<TokenAmount> {
+        self.as_dex()
+            .get_insurance_reserve()
+            .into_iter()
+            .map(|(token_id, amount)| TokenAmount {
+                token_id,
+                amount: amount.into(),
+            })
+            .collect()
+    }
+
+    /// Transfer tokens from insurance reserve to inner account of `receiver_id`; zero amount
+    /// transfers whole reserve of the token. Only can be called by owner while payable API is suspended.
+    #[payable]
+    #[allow(clippy::needless_pass_by_value)]
+    pub fn withdraw_insurance_reserve(
+        &mut self,
+        token_id: TokenId,
+        amount: U128,
+        receiver_id: AccountId,
+    ) -> U128 {
+        assert_one_yocto();
+        let withdrawn = self
+            .as_dex_mut()
+            .withdraw_insurance_reserve(&token_id, amount.into(), &receiver_id)
+            .near_unwrap();
+        self.record_admin_call("withdraw_insurance_reserve");
+        withdrawn.into()
+    }
+
+    /// Set withdraw rate limit for token, or remove it if `None` is passed. Only can be called by owner;
+    /// while timelock is enabled, only tightening limit can be called directly, while loosening
+    /// or removing it should be scheduled as `set_withdraw_limit` action instead.
+    #[payable]
+    #[allow(clippy::needless_pass_by_value)]
+    pub fn set_withdraw_limit(&mut self, token_id: TokenId, config: Option<WithdrawLimitConfig>) {
+        assert_one_yocto();
+        self.as_dex_mut()
+            .set_withdraw_limit(&token_id, config)
+            .near_unwrap();
+        self.record_admin_call("set_withdraw_limit");
+    }
+
+    /// Set sandwich guard of pool, or remove it if `None` is passed. Only can be called by owner.
+    ///
+    /// Guarded pool penalizes or rejects swap which reverses the previous swap
+    /// of the same account through the pool within the same block.
+    #[payable]
+    pub fn set_swap_guard(&mut self, tokens: Pair<TokenId>, mode: Option<SwapGuardMode>) {
+        assert_one_yocto();
+        self.as_dex_mut()
+            .set_swap_guard(tokens.into(), mode)
+            .near_unwrap();
+        self.record_admin_call("set_swap_guard");
+    }
+
+    /// Get sandwich guard mode of pool, if any.
+    pub fn get_swap_guard(&self, tokens: Pair<TokenId>) -> Option<SwapGuardMode> {
+        self.as_dex().get_swap_guard(tokens.into()).near_unwrap()
+    }
+
+    /// Switch batch clearing mode of concentrated pool. Only can be called by owner.
+    ///
+    /// Pool in batch mode doesn't accept direct swaps: swaps are submitted with
+    /// `submit_batch_swap` and cleared at single price with `settle_batch`.
+    /// Mode can be switched off only when pool has no pending swaps.
+    #[cfg(feature = "batch-auction")]
+    #[payable]
+    pub fn set_batch_auction_mode(&mut self, tokens: Pair<TokenId>, enabled: bool) {
+        assert_one_yocto();
+        self.as_dex_mut()
+            .set_batch_auction_mode(tokens.into(), enabled)
+            .near_unwrap();
+        self.record_admin_call("set_batch_auction_mode");
+    }
+
+    /// Get pending batch of pool, or `None` if pool isn't in batch clearing mode.
+    #[cfg(feature = "batch-auction")]
+    pub fn get_batch_auction(&self, tokens: Pair<TokenId>) -> Option<BatchAuctionInfo> {
+        self.as_dex()
+            .get_batch_auction(tokens.into())
+            .near_unwrap()
+            .map(Into::into)
+    }
+
+    /// Get withdraw rate limits with their current counters, per token.
+    #[cfg(not(feature = "legacy-wasm-api"))]
+    pub fn get_withdraw_limits(&self) -> Vec<TokenWithdrawLimit> {
+        self.as_dex()
+            .get_withdraw_limits()
+            .into_iter()
+            .map(|(token_id, limit)| TokenWithdrawLimit { token_id, limit })
+            .collect()
+    }
+
+    /// Stop or resume enforcement of withdraw rate limits. Only can be called by guard.
+    #[payable]
+    pub fn set_withdraw_limits_disabled(&mut self, disabled: bool) {
+        assert_one_yocto();
+        self.as_dex_mut()
+            .set_withdraw_limits_disabled(disabled)
+            .near_unwrap();
+        self.record_admin_call("set_withdraw_limits_disabled");
+    }
+
+    /// Check whether withdraw rate limits were disabled by guard.
+    pub fn is_withdraw_limits_disabled(&self) -> bool {
+        self.as_dex().is_withdraw_limits_disabled()
+    }
+
+    /// Remove pool's tick states which aren't referenced by any position, scanning at most
+    /// `max_items` of them. Only can be called by guard. Returns cursor where next call
+    /// continues from, or `null` if pool scan is complete.
+    #[cfg(not(feature = "legacy-wasm-api"))]
+    #[payable]
+    pub fn cleanup_pool_storage(
+        &mut self,
+        tokens: Pair<TokenId>,
+        max_items: u32,
+    ) -> Option<CleanupCursor> {
+        assert_one_yocto();
+        let cursor = self
+            .as_dex_mut()
+            .cleanup_pool_storage(tokens.into(), max_items as usize)
+            .near_unwrap();
+        self.record_admin_call("cleanup_pool_storage");
+        cursor
+    }
+
+    /// Unregister zero-balance tokens of listed accounts which allowed it,
+    /// removing at most `max_items` registrations. Only can be called by guard.
+    /// Returns number of removed registrations.
+    #[payable]
+    #[allow(clippy::ptr_arg)]
+    pub fn cleanup_token_registrations(
+        &mut self,
+        accounts: &Vec<AccountId>,
+        max_items: u32,
+    ) -> u32 {
+        assert_one_yocto();
+        let removed = self
+            .as_dex_mut()
+            .cleanup_token_registrations(accounts, max_items as usize)
+            .near_unwrap();
+        self.record_admin_call("cleanup_token_registrations");
+        u32::try_from(removed).unwrap_or(u32::MAX)
     }
 
-    /// Fraction of the fee which goes to the DEX out of the total fee charged in swaps.
-    /// In units of 1/FEE_DIVISOR
+    /// Set contract which is notified about swaps with input amounts above specified thresholds,
+    /// or remove it if `None` is passed. Only can be called by owner.
     #[payable]
-    pub fn set_protocol_fee_fraction(&mut self, protocol_fee_fraction: BasisPoints) {
+    pub fn set_swap_hook(&mut self, swap_hook: Option<SwapHook>) {
         assert_one_yocto();
-        self.as_dex_mut()
-            .set_protocol_fee_fraction(protocol_fee_fraction)
-            .near_unwrap();
+        self.as_dex_mut().set_swap_hook(swap_hook).near_unwrap();
+        self.record_admin_call("set_swap_hook");
+    }
+
+    /// Get swap hook configuration, if any.
+    pub fn get_swap_hook(&self) -> Option<SwapHook> {
+        self.as_dex().get_swap_hook()
     }
 
     /// Withdraw owner inner account token to owner wallet.
@@ -503,20 +2328,23 @@ impl State {
     #[allow(clippy::needless_pass_by_value)]
     pub fn withdraw_owner_token(&mut self, token_id: AccountId, amount: U128) -> Promise {
         assert_one_yocto();
-        self.as_dex_mut()
+        let promise = self
+            .as_dex_mut()
             .owner_withdraw(&token_id, amount.into(), ())
-            .near_unwrap()
+            .near_unwrap();
+        self.record_admin_call("withdraw_owner_token");
+        promise
     }
 
     /// Withdraw protocol fee onto the dex-owner account on the dex.
+    #[cfg(not(feature = "legacy-wasm-api"))]
     #[payable]
-    pub fn withdraw_protocol_fee(&mut self, pool_id: (TokenId, TokenId)) -> (U128, U128) {
+    pub fn withdraw_protocol_fee(&mut self, pool_id: Pair<TokenId>) -> Pair<U128> {
         assert_one_yocto();
-        let fee_amounts = self
-            .as_dex_mut()
-            .withdraw_protocol_fee(pool_id)
-            .near_unwrap();
-        (fee_amounts.0.into(), fee_amounts.1.into())
+        self.as_dex_mut()
+            .withdraw_protocol_fee(pool_id.into())
+            .near_unwrap()
+            .into()
     }
 }
 /// Storage APIs
@@ -543,10 +2371,10 @@ impl StorageManagement for State {
         let amount = amount.unwrap_or(U128(0)).0;
         let mut dex = self.as_dex_mut();
         dex.ensure_payable_api_resumed().near_unwrap();
-        let Contract::V0(ref mut contract) = dex.contract_mut();
+        let Contract::V1(ref mut contract) = dex.contract_mut();
         let (withdraw_amount, storage_balance) = contract
             .accounts
-            .try_update(&account_id, |Account::V0(ref mut account)| {
+            .try_update(&account_id, |Account::V1(ref mut account)| {
                 let available = account.storage_available();
                 ensure_here!(available > 0, Error::NoStorageCanWithdraw);
                 let withdraw_amount = if amount == 0 { available } else { amount };
@@ -565,7 +2393,7 @@ impl StorageManagement for State {
         assert_one_yocto();
 
         self.as_dex_mut()
-            .unregister_account_with_cb(None, |_, dex::Account::V0(acc)| Ok(acc.extra.near_amount))
+            .unregister_account_with_cb(None, |_, dex::Account::V1(acc)| Ok(acc.extra.near_amount))
             .near_unwrap()
             .map(|balance| {
                 Promise::new(env::predecessor_account_id()).transfer(balance);
@@ -581,11 +2409,11 @@ impl StorageManagement for State {
     }
 
     fn storage_balance_of(&self, account_id: AccountId) -> Option<StorageBalance> {
-        let Contract::V0(ref contract) = &self.0;
+        let Contract::V1(ref contract) = &self.0;
         contract
             .accounts
             .get(&account_id)
-            .map(|Account::V0(ref account)| account.storage_balance_of())
+            .map(|Account::V1(ref account)| account.storage_balance_of())
     }
 }
 /// Pools manipulation
@@ -598,6 +2426,8 @@ impl State {
         &mut self,
         actions: Vec<Action<()>>,
     ) -> PromiseOrValue<Option<WasmAmount>> {
+        #[cfg(feature = "gas-profile")]
+        super::gas_profile::start("execute_actions");
         let register_cb = self.on_register_account_action(false);
         let (outcomes, amount) = self
             .as_dex_mut()
@@ -606,23 +2436,79 @@ impl State {
 
         let amount = amount.map(Into::into);
 
-        match Self::fold_promises(outcomes) {
-            Some(p) => PromiseOrValue::Promise(p.then(Self::delay_return_option_amount(amount))),
+        let result = match Self::fold_promises(outcomes) {
+            Some(p) => PromiseOrValue::Promise(p.then(self.delay_return_option_amount(amount))),
             None => PromiseOrValue::Value(amount),
-        }
+        };
+        #[cfg(feature = "gas-profile")]
+        super::gas_profile::finish();
+        result
+    }
+
+    /// Get gas checkpoints of the last successful `execute_actions` call,
+    /// including ones performed through `ft_on_transfer`.
+    #[cfg(feature = "gas-profile")]
+    pub fn profile_last_call(&self) -> Option<super::gas_profile::GasProfile> {
+        super::gas_profile::last_call()
+    }
+    /// Swap caller's token balances below `min_threshold` into `token_out`, skipping tokens
+    /// which don't have direct pool with `token_out`. If `unregister` is set, swept tokens
+    /// and listed tokens with zero balance are unregistered. Returns total amount of `token_out` received.
+    #[payable]
+    #[allow(clippy::ptr_arg)]
+    pub fn sweep_dust(
+        &mut self,
+        tokens: &Vec<TokenId>,
+        min_threshold: U128,
+        token_out: &TokenId,
+        unregister: Option<bool>,
+    ) -> U128 {
+        assert_one_yocto();
+        let (_swept, amount_out) = self
+            .as_dex_mut()
+            .sweep_dust(
+                tokens,
+                min_threshold.into(),
+                token_out,
+                unregister.unwrap_or(false),
+            )
+            .near_unwrap();
+        amount_out.into()
     }
+
     /// Execute set of swap actions between pools.
+    /// If `min_amounts_per_hop` is specified, it must contain minimal output of each hop,
+    /// so that swap fails early when some intermediate pool prices badly.
+    /// If `reference_sqrtprice` is specified, swap is rejected when square root of pre-trade
+    /// spot price of the route deviates from it by more than `max_deviation_bp` basis points.
     #[payable]
     #[allow(clippy::ptr_arg)]
+    #[allow(clippy::too_many_arguments)]
     pub fn swap_exact_in(
         &mut self,
         tokens: &Vec<AccountId>,
         amount_in: U128,
         min_amount_out: U128,
+        integration_id: Option<dex::IntegrationId>,
+        min_amounts_per_hop: Option<Vec<U128>>,
+        reference_sqrtprice: Option<f64>,
+        max_deviation_bp: Option<dex::BasisPoints>,
     ) -> AmountInOut {
+        self.ensure_reference_price(tokens, reference_sqrtprice, max_deviation_bp);
+        let min_amounts_per_hop = min_amounts_per_hop
+            .unwrap_or_default()
+            .into_iter()
+            .map(Into::into)
+            .collect_vec();
         let (amount_in, amount_out) = self
             .as_dex_mut()
-            .swap_exact_in(tokens, amount_in.into(), min_amount_out.into())
+            .swap_exact_in_with_hop_limits(
+                tokens,
+                amount_in.into(),
+                min_amount_out.into(),
+                &min_amounts_per_hop,
+                integration_id,
+            )
             .near_unwrap();
         AmountInOut {
             amount_in: amount_in.into(),
@@ -630,6 +2516,7 @@ impl State {
         }
     }
 
+    /// Reference price parameters have the same meaning as in `swap_exact_in`
     #[payable]
     #[allow(clippy::ptr_arg)]
     pub fn swap_exact_out(
@@ -637,10 +2524,19 @@ impl State {
         tokens: &Vec<AccountId>,
         amount_out: U128,
         max_amount_in: U128,
+        integration_id: Option<dex::IntegrationId>,
+        reference_sqrtprice: Option<f64>,
+        max_deviation_bp: Option<dex::BasisPoints>,
     ) -> AmountInOut {
+        self.ensure_reference_price(tokens, reference_sqrtprice, max_deviation_bp);
         let (amount_in, amount_out) = self
             .as_dex_mut()
-            .swap_exact_out(tokens, amount_out.into(), max_amount_in.into())
+            .swap_exact_out(
+                tokens,
+                amount_out.into(),
+                max_amount_in.into(),
+                integration_id,
+            )
             .near_unwrap();
         AmountInOut {
             amount_in: amount_in.into(),
@@ -668,6 +2564,30 @@ impl State {
             .collect()
     }
 
+    /// Swap exact `amount_in` of `token_in` into `token_out` along the best route found
+    /// by `find_best_route`. Route is remembered, so that repeat swaps of the pair
+    /// with amount of same order of magnitude don't search it again.
+    /// Returns amount of `token_out` received.
+    #[cfg(feature = "smart-routing")]
+    #[payable]
+    pub fn swap_best_route_exact_in(
+        &mut self,
+        token_in: TokenId,
+        token_out: TokenId,
+        amount_in: U128,
+        min_amount_out: U128,
+    ) -> U128 {
+        self.as_dex_mut()
+            .swap_best_route_exact_in(
+                &token_in,
+                &token_out,
+                amount_in.into(),
+                min_amount_out.into(),
+            )
+            .near_unwrap()
+            .into()
+    }
+
     #[cfg(feature = "smart-routing")]
     #[payable]
     #[allow(clippy::ptr_arg)]
@@ -696,19 +2616,26 @@ impl State {
     ///  - fee: fee rate in units of 1/FEE_DIVISOR
     ///  - position: currently only FullRangePosition is suported. That is the ranges
     ///     of token amounts to be depsoted. TODO: provide example JSON.
+    ///     With `auto_balance: true`, position may be funded with single token: only one of
+    ///     the ranges should have non-zero `max`, and part of it is swapped in the pool
+    ///     into the other token at current price, charging normal fees.
+    ///     With `locked_until` set to a future timestamp in seconds, position cannot be
+    ///     closed or moved until then, e.g. to commit protocol-owned liquidity.
     ///
     /// Attached NEAR should be enough to cover the added storage.
     /// If pool doesn't exist, it is implicitly created. In such case more attached NEAR is required.
     /// As pool can never be deleted, the NEAR deposited for pool storage can not be withdrawn.
     ///
-    /// Returns:
+    /// Returns `OpenPositionResult` with:
     ///  - position_id
     ///  - amount of token a
     ///  - amount of token b
-    ///  - accounted liquidity
+    ///  - accounted liquidity, rounded to `f64`
+    ///  - accounted liquidity, exact, as hex-encoded `U192X64`
     ///
     /// Notice that actual accounted position liqudity may be smaller sqrt(amount_a, amount_b)
     ///
+    #[cfg(not(feature = "legacy-wasm-api"))]
     #[payable]
     pub fn open_position(
         &mut self,
@@ -716,39 +2643,253 @@ impl State {
         token_b: &AccountId,
         fee_rate: dex::BasisPoints,
         position: PositionInit,
-    ) -> (U64, U128, U128, f64) {
-        // TODO: returned liquidity is rounded. consider returning in full precision
+    ) -> OpenPositionResult {
+        self.open_position_impl(token_a, token_b, fee_rate, position)
+    }
 
+    /// Create concentrated pool of `token_a` and `token_b`, or take existing empty one,
+    /// and set its starting price, so that positions are opened at it rather than
+    /// at the price derived from amounts of the first position.
+    /// `sqrtprice` is square root of price in the same convention as position ticks,
+    /// i.e. `1.0001^(tick/2)`. Can be called only by owner, unless allowed to anyone.
+    ///
+    /// Pool storage is paid from caller's storage deposit.
+    #[payable]
+    pub fn create_pool_with_price(&mut self, token_a: TokenId, token_b: TokenId, sqrtprice: f64) {
         assert_one_yocto();
+        self.as_dex_mut()
+            .create_pool_with_price(&token_a, &token_b, crate::chain::Float::from(sqrtprice))
+            .near_unwrap();
+    }
 
-        let (position_id, amount_a, amount_b, net_liquidity) = self
+    /// Allow or disallow accounts other than owner to call `create_pool_with_price`.
+    /// Only can be called by owner.
+    #[payable]
+    pub fn set_pool_price_init_open(&mut self, open: bool) {
+        assert_one_yocto();
+        self.as_dex_mut()
+            .set_pool_price_init_open(open)
+            .near_unwrap();
+        self.record_admin_call("set_pool_price_init_open");
+    }
+
+    /// Check whether any account may call `create_pool_with_price`.
+    pub fn is_pool_price_init_open(&self) -> bool {
+        self.as_dex().is_pool_price_init_open()
+    }
+
+    /// Set seed period of concentrated pools created from now on, or disable it with `null`.
+    /// For `blocks` after creation, swaps in new pool are disabled, and positions may only
+    /// be opened with bounds within `max_width_ticks` from pool price.
+    /// Only can be called by owner.
+    #[payable]
+    pub fn set_seed_period(&mut self, config: Option<SeedPeriodConfig>) {
+        assert_one_yocto();
+        self.as_dex_mut().set_seed_period(config).near_unwrap();
+        self.record_admin_call("set_seed_period");
+    }
+
+    /// Get seed period of new concentrated pools; `null` if disabled.
+    pub fn get_seed_period(&self) -> Option<SeedPeriodConfig> {
+        self.as_dex().get_seed_period()
+    }
+
+    /// Deposit liquidity into constant product pool of `token_a` and `token_b`,
+    /// creating the pool with specified `fee_rate` if the pair has no pool yet.
+    /// Pair which already has concentrated pool cannot have constant product one.
+    ///
+    /// Returns actually deposited amounts of `token_a` and `token_b`, and minted shares.
+    #[payable]
+    pub fn add_classic_liquidity(
+        &mut self,
+        token_a: TokenId,
+        token_b: TokenId,
+        fee_rate: BasisPoints,
+        max_amounts: Pair<U128>,
+    ) -> (U128, U128, U128) {
+        assert_one_yocto();
+        let (amount_a, amount_b, shares) = self
             .as_dex_mut()
-            .open_position(token_a, token_b, fee_rate, position)
+            .add_classic_liquidity(&token_a, &token_b, fee_rate, max_amounts.into())
             .near_unwrap();
+        (amount_a.into(), amount_b.into(), shares.into())
+    }
 
-        let fee_level: FeeLevel = self
-            .as_dex()
-            .fee_rates_ticks()
-            .iter()
-            .find_position(|rate| **rate == fee_rate)
-            .ok_or(Error::InvalidArgument)
+    /// Burn `shares` of constant product pool, returning withdrawn amounts to caller's deposits.
+    /// Amounts are ordered the same way as `tokens`.
+    #[payable]
+    pub fn remove_classic_liquidity(&mut self, tokens: Pair<TokenId>, shares: U128) -> Pair<U128> {
+        assert_one_yocto();
+        self.as_dex_mut()
+            .remove_classic_liquidity(tokens.into(), shares.into())
             .near_unwrap()
-            .0
-            .try_into()
+            .into()
+    }
+
+    /// Deposit liquidity into stable swap pool of `token_a` and `token_b`,
+    /// creating the pool with specified `fee_rate` and `amplification` if the pair has no pool yet.
+    /// Pair which already has pool of another kind cannot have stable swap one.
+    ///
+    /// Returns actually deposited amounts of `token_a` and `token_b`, and minted shares.
+    #[payable]
+    pub fn add_stable_liquidity(
+        &mut self,
+        token_a: TokenId,
+        token_b: TokenId,
+        fee_rate: BasisPoints,
+        amplification: u32,
+        max_amounts: Pair<U128>,
+    ) -> (U128, U128, U128) {
+        assert_one_yocto();
+        let (amount_a, amount_b, shares) = self
+            .as_dex_mut()
+            .add_stable_liquidity(
+                &token_a,
+                &token_b,
+                fee_rate,
+                amplification,
+                max_amounts.into(),
+            )
             .near_unwrap();
+        (amount_a.into(), amount_b.into(), shares.into())
+    }
+
+    /// Burn `shares` of stable swap pool, returning withdrawn amounts to caller's deposits.
+    /// Amounts are ordered the same way as `tokens`.
+    #[payable]
+    pub fn remove_stable_liquidity(&mut self, tokens: Pair<TokenId>, shares: U128) -> Pair<U128> {
+        assert_one_yocto();
+        self.as_dex_mut()
+            .remove_stable_liquidity(tokens.into(), shares.into())
+            .near_unwrap()
+            .into()
+    }
+
+    /// Place conditional order, which any account can execute once price of `token_in`
+    /// in units of `token_out` drops to `trigger_price` (`stop_loss`) or rises to it (`take_profit`).
+    /// On execution, `amount_in` is swapped from caller's deposits, receiving at least `min_amount_out`,
+    /// and `keeper_fee` of `token_in` is paid to executing account. Amounts aren't reserved
+    /// until execution. Storage of order is paid from caller's storage deposit.
+    ///
+    /// Returns order id.
+    #[payable]
+    #[allow(clippy::too_many_arguments)]
+    pub fn place_order(
+        &mut self,
+        token_in: TokenId,
+        token_out: TokenId,
+        amount_in: U128,
+        min_amount_out: U128,
+        trigger: OrderTrigger,
+        trigger_price: f64,
+        expires_at: U64,
+        keeper_fee: U128,
+    ) -> U64 {
+        assert_one_yocto();
+        self.as_dex_mut()
+            .place_order(
+                (token_in, token_out),
+                (amount_in.into(), min_amount_out.into()),
+                trigger,
+                crate::chain::Float::from(trigger_price),
+                expires_at.into(),
+                keeper_fee.into(),
+            )
+            .near_unwrap()
+            .into()
+    }
 
-        let liquidity = net_liquidity
-            * Liquidity::try_from(one_over_sqrt_one_minus_fee_rate(fee_level)).near_unwrap();
+    /// Execute conditional order whose trigger condition holds. Can be called by any account;
+    /// caller must have `token_in` of order registered to receive keeper fee.
+    ///
+    /// Returns amount of `token_out` received by order owner.
+    #[payable]
+    pub fn execute_order(&mut self, order_id: U64) -> U128 {
+        assert_one_yocto();
+        self.as_dex_mut()
+            .execute_order(order_id.into())
+            .near_unwrap()
+            .into()
+    }
 
-        // TODO: Review output type:
-        let liquidity: f64 = crate::chain::Float::from(liquidity).into();
+    /// Cancel conditional order, releasing its storage. Can be called by order owner,
+    /// or by any account once order has expired.
+    #[payable]
+    pub fn cancel_order(&mut self, order_id: U64) {
+        assert_one_yocto();
+        self.as_dex_mut()
+            .cancel_order(order_id.into())
+            .near_unwrap();
+    }
 
-        (
-            position_id.into(),
-            amount_a.into(),
-            amount_b.into(),
-            liquidity,
-        )
+    /// Prepare deposit intent: `actions` are executed on behalf of caller once each token
+    /// of `tokens_and_amounts` is transferred via `ft_transfer_call` with message
+    /// `{"intent_id":"<id>"}`, in exact amount, possibly split into several transfers.
+    /// Each transfer is credited to caller's deposits on arrival, so tokens must be registered.
+    /// Transfers arriving after intent expires are refunded. Actions can't contain
+    /// `register_account` or `deposit`. Storage of intent is paid from caller's storage deposit.
+    ///
+    /// Returns intent id.
+    #[payable]
+    pub fn prepare_deposit_intent(
+        &mut self,
+        tokens_and_amounts: Vec<TokenAmount>,
+        actions: Vec<Action<()>>,
+    ) -> U64 {
+        assert_one_yocto();
+        let legs = tokens_and_amounts
+            .into_iter()
+            .map(|leg| (leg.token_id, leg.amount.into()))
+            .collect();
+        self.as_dex_mut()
+            .prepare_deposit_intent(legs, actions)
+            .near_unwrap()
+            .into()
+    }
+
+    /// Cancel deposit intent, releasing its storage; tokens received so far stay
+    /// in owner's deposits. Can be called by intent owner, or by any account once intent has expired.
+    #[payable]
+    pub fn cancel_deposit_intent(&mut self, intent_id: U64) {
+        assert_one_yocto();
+        self.as_dex_mut()
+            .cancel_deposit_intent(intent_id.into())
+            .near_unwrap();
+    }
+
+    /// Submit exact-in swap to batch of pool in batch clearing mode. `amount_in` is taken
+    /// from caller's deposit right away; both tokens must be registered by caller.
+    /// Storage of swap is paid from caller's storage deposit until settlement.
+    #[cfg(feature = "batch-auction")]
+    #[payable]
+    pub fn submit_batch_swap(
+        &mut self,
+        token_in: TokenId,
+        token_out: TokenId,
+        amount_in: U128,
+        min_amount_out: U128,
+    ) {
+        assert_one_yocto();
+        self.as_dex_mut()
+            .submit_batch_swap(
+                (token_in, token_out),
+                amount_in.into(),
+                min_amount_out.into(),
+            )
+            .near_unwrap();
+    }
+
+    /// Clear batch of pool at single price, once block of its swaps has passed.
+    /// Can be called by any account. Swaps whose `min_amount_out` isn't met are refunded.
+    #[cfg(feature = "batch-auction")]
+    #[payable]
+    pub fn settle_batch(&mut self, tokens: Pair<TokenId>) -> SettleBatchResult {
+        assert_one_yocto();
+        let (executed, refunded) = self.as_dex_mut().settle_batch(tokens.into()).near_unwrap();
+        SettleBatchResult {
+            executed: executed as u32,
+            refunded: refunded as u32,
+        }
     }
 
     #[payable]
@@ -760,6 +2901,46 @@ impl State {
             .near_unwrap();
     }
 
+    /// Move position to new range of ticks in one call: position is closed, its fees are harvested,
+    /// and it's reopened on the same fee level under the same id. Withdrawn amounts are used
+    /// as maximal deposit; amounts not taken by position in new range remain on caller's deposits.
+    ///
+    /// Returns amounts deposited into new range, ordered as pool tokens.
+    #[payable]
+    pub fn move_position(
+        &mut self,
+        position_id: U64,
+        range_ticks: (Option<i32>, Option<i32>),
+    ) -> Pair<U128> {
+        assert_one_yocto();
+        let (amount_a, amount_b, _) = self
+            .as_dex_mut()
+            .move_position(position_id.into(), range_ticks)
+            .near_unwrap();
+        (amount_a, amount_b).into()
+    }
+
+    /// Lock position on behalf of `locker_id`, e.g. lending contract using it as collateral.
+    /// Locked position cannot be closed until unlocked by `locker_id`.
+    #[payable]
+    pub fn lock_position(&mut self, position_id: U64, locker_id: AccountId) {
+        assert_one_yocto();
+
+        self.as_dex_mut()
+            .lock_position(position_id.into(), locker_id)
+            .near_unwrap();
+    }
+
+    /// Unlock position. Must be called by the account which locked it.
+    #[payable]
+    pub fn unlock_position(&mut self, position_id: U64) {
+        assert_one_yocto();
+
+        self.as_dex_mut()
+            .unlock_position(position_id.into())
+            .near_unwrap();
+    }
+
     #[payable]
     pub fn withdraw_fee(&mut self, position_id: U64) -> Pair<U128> {
         assert_one_yocto();
@@ -769,6 +2950,21 @@ impl State {
             .into()
     }
 
+    /// Harvest fees of all caller's positions onto caller's deposits
+    ///
+    /// # Returns
+    /// Position ids with harvested amounts, in order of position's tokens
+    #[payable]
+    pub fn withdraw_all_fees(&mut self) -> Vec<(U64, Pair<U128>)> {
+        assert_one_yocto();
+        self.as_dex_mut()
+            .withdraw_all_fees()
+            .near_unwrap()
+            .into_iter()
+            .map(|(position_id, amounts)| (position_id.into(), amounts.into()))
+            .collect()
+    }
+
     pub fn get_position_info(&self, position_id: U64) -> PositionInfo {
         self.as_dex()
             .get_position_info(position_id.into())
@@ -776,16 +2972,102 @@ impl State {
             .into()
     }
 
-    #[cfg(feature = "smart-routing")]
-    pub fn update_top_pools(&mut self) -> HashMap<TokenId, [TokenId; NUM_TOP_POOLS]> {
-        self.as_dex_mut().update_top_pools().unwrap()
+    /// Get account which owns position, or `null` if position is held by LP token.
+    /// Fails if position doesn't exist
+    pub fn get_position_owner(&self, position_id: U64) -> Option<AccountId> {
+        self.as_dex()
+            .get_position_owner(position_id.into())
+            .near_unwrap()
+    }
+
+    /// Get number of currently open positions
+    pub fn positions_total_supply(&self) -> U64 {
+        self.as_dex().positions_total_supply().into()
+    }
+
+    /// Get positions of the pool, among `limit` of them skipping first `from`, whose fees
+    /// accrued since last withdrawal reach either of `min_amounts`, e.g. for auto-compounding
+    /// keepers. Fewer than `limit` positions may be returned even if pool has more of them.
+    /// Token amounts are ordered the same way as `tokens`.
+    pub fn get_positions_with_fees(
+        &self,
+        tokens: Pair<TokenId>,
+        min_amounts: Pair<U128>,
+        from: u32,
+        limit: u32,
+    ) -> Vec<(U64, Pair<U128>)> {
+        self.as_dex()
+            .get_positions_with_fees(
+                tokens.into(),
+                min_amounts.into(),
+                from as usize,
+                limit as usize,
+            )
+            .near_unwrap()
+            .into_iter()
+            .map(|(position_id, fees)| (position_id.into(), fees.into()))
+            .collect()
+    }
+
+    /// Set minimal accrued fees of caller's position, in order of position's tokens,
+    /// upon reaching either of which fees are credited to caller's deposits by swaps through pool,
+    /// without explicit `withdraw_fee`; `null` removes rule. Storage of rule is paid
+    /// from caller's storage deposit.
+    #[payable]
+    pub fn set_auto_harvest(&mut self, position_id: U64, min_fees: Option<Pair<U128>>) {
+        assert_one_yocto();
+        self.as_dex_mut()
+            .set_auto_harvest(position_id.into(), min_fees.map(Into::into))
+            .near_unwrap();
+    }
+
+    /// Get minimal accrued fees upon which fees of position are harvested automatically,
+    /// in order of position's tokens, or `null` if auto-harvest isn't set.
+    pub fn get_auto_harvest(&self, position_id: U64) -> Option<Pair<U128>> {
+        self.as_dex()
+            .get_auto_harvest(position_id.into())
+            .near_unwrap()
+            .map(Into::into)
     }
 
+    /// Get tokens of top pools of `token`, ordered by descending liquidity.
+    /// Rankings are kept up to date as pools' liquidity changes.
     #[cfg(feature = "smart-routing")]
-    pub fn get_token_top_pools(&self, token: &TokenId) -> [TokenId; NUM_TOP_POOLS] {
+    pub fn get_token_top_pools(&self, token: &TokenId) -> Vec<TokenId> {
         self.as_dex().get_token_top_pools(token).unwrap()
     }
 
+    /// Get tokens which share a pool with `token`, skipping first `from` of them
+    /// and returning at most `limit` ones. Lets off-chain routers mirror the token graph.
+    #[cfg(feature = "smart-routing")]
+    pub fn get_token_connections(&self, token: TokenId, from: u32, limit: u32) -> Vec<TokenId> {
+        self.as_dex()
+            .get_token_connections(&token, from as usize, limit as usize)
+    }
+
+    /// Get tokens which participate in at least one pool, skipping first `from` of them
+    /// and returning at most `limit` ones.
+    #[cfg(feature = "smart-routing")]
+    pub fn get_all_tokens(&self, from: u32, limit: u32) -> Vec<TokenId> {
+        self.as_dex().get_all_tokens(from as usize, limit as usize)
+    }
+
+    /// Get best route to swap `amount` of `token_in` into `token_out`: tokens along it,
+    /// starting with `token_in` and ending with `token_out`. Considers direct pool
+    /// and routes through one of top pools of `token_in`; recently swapped routes
+    /// are returned from cache.
+    #[cfg(feature = "smart-routing")]
+    pub fn find_best_route(
+        &self,
+        token_in: TokenId,
+        token_out: TokenId,
+        amount: U128,
+    ) -> Vec<TokenId> {
+        self.as_dex()
+            .find_best_route(&token_in, &token_out, amount.into())
+            .near_unwrap()
+    }
+
     #[cfg(feature = "smart-routing")]
     pub fn calculate_path_liquidity(&self, token_id_vec: &Vec<TokenId>) -> Liquidity {
         self.as_dex()
@@ -793,3 +3075,41 @@ impl State {
             .unwrap()
     }
 }
+
+impl State {
+    pub(super) fn open_position_impl(
+        &mut self,
+        token_a: &AccountId,
+        token_b: &AccountId,
+        fee_rate: dex::BasisPoints,
+        position: PositionInit,
+    ) -> OpenPositionResult {
+        assert_one_yocto();
+
+        let (position_id, amount_a, amount_b, net_liquidity) = self
+            .as_dex_mut()
+            .open_position(token_a, token_b, fee_rate, position)
+            .near_unwrap();
+
+        let fee_level: FeeLevel = self
+            .as_dex()
+            .fee_rates_ticks()
+            .iter()
+            .find_position(|rate| **rate == fee_rate)
+            .ok_or(Error::InvalidArgument)
+            .near_unwrap()
+            .0
+            .try_into()
+            .near_unwrap();
+
+        let liquidity = liquidity_from_net_liquidity(net_liquidity, fee_level);
+
+        OpenPositionResult {
+            position_id: position_id.into(),
+            amount_a: amount_a.into(),
+            amount_b: amount_b.into(),
+            liquidity: crate::chain::Float::from(liquidity).into(),
+            liquidity_exact: liquidity.into(),
+        }
+    }
+}
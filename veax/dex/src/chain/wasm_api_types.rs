@@ -0,0 +1,613 @@
+//! Explicit request and response types of WASM entrypoints, which previously used tuples.
+//!
+//! Named fields keep JSON representation self-describing and stable when entrypoints evolve.
+//! Tuple-based signatures are still available with `legacy-wasm-api` feature, see `wasm_legacy`.
+use super::{AccountId, CachedTokenMetadata, LiquidityHex, Pair, TokenId};
+use crate::dex::latest::RawFeeLevelsArray;
+#[cfg(feature = "batch-auction")]
+use crate::dex::BatchAuction;
+use crate::dex::{
+    Action, AdminLogEntry, BasisPoints, ConditionalOrder, DepositIntent, EventKind, FeeAuction,
+    FeeAuctionConfig, FeeLevel, GasConfig, Integration, IntegrationId, OrderTrigger,
+    PendingApproval, PoolStorageDeposit, ScheduledAction, SeedPeriodConfig, SessionKey, SwapHook,
+    Vault, WithdrawLimit,
+};
+use near_sdk::json_types::{Base58CryptoHash, U128, U64};
+use near_sdk::serde::{Deserialize, Serialize};
+
+/// Result of `open_position`
+#[derive(Serialize, Deserialize)]
+#[cfg_attr(not(target_arch = "wasm32"), derive(Debug))]
+#[serde(crate = "near_sdk::serde")]
+pub struct OpenPositionResult {
+    pub position_id: U64,
+
+    /// Amount of `token_a` deposited into the position.
+    pub amount_a: U128,
+
+    /// Amount of `token_b` deposited into the position.
+    pub amount_b: U128,
+
+    /// Accounted liquidity of the position, rounded.
+    pub liquidity: f64,
+
+    /// Accounted liquidity of the position, exact.
+    pub liquidity_exact: LiquidityHex,
+}
+
+/// Result of `estimate_open_position`
+#[derive(Serialize, Deserialize)]
+#[cfg_attr(not(target_arch = "wasm32"), derive(Debug))]
+#[serde(crate = "near_sdk::serde")]
+pub struct OpenPositionEstimate {
+    /// Amount of `token_a` which would be deposited into the position.
+    pub amount_a: U128,
+
+    /// Amount of `token_b` which would be deposited into the position.
+    pub amount_b: U128,
+
+    /// Accounted liquidity of the position, rounded.
+    pub liquidity: f64,
+
+    /// Accounted net liquidity of the position, exact.
+    pub net_liquidity: LiquidityHex,
+
+    /// Lower tick of the position, with respect to order of `token_a` and `token_b`.
+    pub tick_low: i32,
+
+    /// Upper tick of the position, with respect to order of `token_a` and `token_b`.
+    pub tick_high: i32,
+}
+
+/// Result of `estimate_close_position`. Amounts are ordered the same way as `tokens`
+#[derive(Serialize, Deserialize)]
+#[cfg_attr(not(target_arch = "wasm32"), derive(Debug))]
+#[serde(crate = "near_sdk::serde")]
+pub struct ClosePositionEstimate {
+    /// Tokens of position's pool.
+    pub tokens: Pair<TokenId>,
+
+    /// Position balance at current price.
+    pub amounts: Pair<U128>,
+
+    /// LP fees accumulated since last withdrawal of fees.
+    pub fees: Pair<U128>,
+}
+
+/// Owner action waiting for execution, see `get_scheduled_actions`
+#[derive(Serialize, Deserialize)]
+#[cfg_attr(not(target_arch = "wasm32"), derive(Debug))]
+#[serde(crate = "near_sdk::serde")]
+pub struct ScheduledActionInfo {
+    pub id: U64,
+    pub scheduled: ScheduledAction,
+}
+
+/// Owner action waiting for admins' approvals, see `get_pending_approvals`
+#[derive(Serialize, Deserialize)]
+#[cfg_attr(not(target_arch = "wasm32"), derive(Debug))]
+#[serde(crate = "near_sdk::serde")]
+pub struct PendingApprovalInfo {
+    pub action_hash: Base58CryptoHash,
+    pub pending: PendingApproval,
+}
+
+/// Multisig owner configuration, see `get_admins`
+#[derive(Serialize, Deserialize)]
+#[cfg_attr(not(target_arch = "wasm32"), derive(Debug))]
+#[serde(crate = "near_sdk::serde")]
+pub struct AdminsInfo {
+    pub admins: Vec<AccountId>,
+
+    /// Number of approvals required to execute owner action; zero means multisig is disabled.
+    pub threshold: u16,
+}
+
+/// Governance-tunable parameters of contract, see `get_config`
+#[derive(Serialize, Deserialize)]
+#[cfg_attr(not(target_arch = "wasm32"), derive(Debug))]
+#[serde(crate = "near_sdk::serde")]
+pub struct ContractConfig {
+    pub owner: AccountId,
+
+    /// Accounts which may suspend payable API.
+    pub guards: Vec<AccountId>,
+
+    /// Multisig admins, see `get_admins`.
+    pub admins: Vec<AccountId>,
+    pub admin_threshold: u16,
+
+    /// Whether payable API is suspended.
+    pub suspended: bool,
+
+    /// Whether withdraw rate limits were disabled by guard.
+    pub withdraw_limits_disabled: bool,
+
+    /// Fraction of fee which goes to DEX, scaled up by `fee_divisor`.
+    pub protocol_fee_fraction: BasisPoints,
+
+    /// Fraction of protocol fee which goes to insurance reserve, scaled up by `fee_divisor`.
+    pub insurance_fee_fraction: BasisPoints,
+
+    /// Fee rates of levels, scaled up by `fee_divisor`.
+    pub fee_rates: RawFeeLevelsArray<BasisPoints>,
+    pub fee_divisor: BasisPoints,
+
+    /// Governance timelock delay in seconds; zero means timelock is disabled.
+    pub timelock_delay: U64,
+
+    /// Minimal accounted liquidity of newly opened position.
+    pub min_position_liquidity: f64,
+
+    /// Kinds of events which are currently not emitted.
+    pub disabled_events: Vec<EventKind>,
+
+    /// Parameters of protocol fee auctions; `null` if auctions are disabled.
+    pub fee_auction_config: Option<FeeAuctionConfig>,
+    pub swap_hook: Option<SwapHook>,
+    pub verified_tokens_count: u64,
+
+    /// Gas attached to cross-contract calls, in Tgas.
+    pub gas_config: GasConfig,
+
+    /// Whether any account, not only owner, may create pool at explicit starting price.
+    #[serde(default)]
+    pub pool_price_init_open: bool,
+
+    /// Restrictions applied to new concentrated pools; `null` if disabled.
+    #[serde(default)]
+    pub seed_period: Option<SeedPeriodConfig>,
+
+    /// Whether token contracts are checked to implement fungible token interface
+    /// before they are first used.
+    #[serde(default)]
+    pub token_interface_check: bool,
+}
+
+#[derive(Serialize, Deserialize)]
+#[cfg_attr(not(target_arch = "wasm32"), derive(Debug))]
+#[serde(crate = "near_sdk::serde")]
+pub struct TokenAmount {
+    pub token_id: TokenId,
+    pub amount: U128,
+}
+
+/// Withdraw rate limit of token, see `get_withdraw_limits`
+#[derive(Serialize, Deserialize)]
+#[cfg_attr(not(target_arch = "wasm32"), derive(Debug))]
+#[serde(crate = "near_sdk::serde")]
+pub struct TokenWithdrawLimit {
+    pub token_id: TokenId,
+    pub limit: WithdrawLimit,
+}
+
+/// Conditional order waiting for keeper, see `get_order` and `get_orders`
+#[derive(Serialize, Deserialize)]
+#[cfg_attr(not(target_arch = "wasm32"), derive(Debug))]
+#[serde(crate = "near_sdk::serde")]
+pub struct ConditionalOrderInfo {
+    pub id: U64,
+    pub owner: AccountId,
+    pub token_in: TokenId,
+    pub token_out: TokenId,
+    pub amount_in: U128,
+    pub min_amount_out: U128,
+    pub trigger: OrderTrigger,
+
+    /// Price of `token_in` in units of `token_out`.
+    pub trigger_price: f64,
+
+    /// Moment after which order can't be executed, in seconds.
+    pub expires_at: U64,
+
+    /// Amount of `token_in` paid to keeper, in addition to `amount_in`.
+    pub keeper_fee: U128,
+}
+
+impl From<(u64, ConditionalOrder)> for ConditionalOrderInfo {
+    fn from((id, order): (u64, ConditionalOrder)) -> Self {
+        Self {
+            id: id.into(),
+            owner: order.owner,
+            token_in: order.token_in,
+            token_out: order.token_out,
+            amount_in: order.amount_in.into(),
+            min_amount_out: order.min_amount_out.into(),
+            trigger: order.trigger,
+            trigger_price: order.trigger_price.into(),
+            expires_at: order.expires_at.into(),
+            keeper_fee: order.keeper_fee.into(),
+        }
+    }
+}
+
+/// Pending batch of pool in batch clearing mode, see `get_batch_auction`
+#[cfg(feature = "batch-auction")]
+#[derive(Serialize, Deserialize)]
+#[cfg_attr(not(target_arch = "wasm32"), derive(Debug))]
+#[serde(crate = "near_sdk::serde")]
+pub struct BatchAuctionInfo {
+    pub swaps: Vec<BatchSwapInfo>,
+
+    /// Height of block in which swaps were submitted.
+    pub block_height: U64,
+}
+
+/// Swap submitted to batch, see `submit_batch_swap`
+#[cfg(feature = "batch-auction")]
+#[derive(Serialize, Deserialize)]
+#[cfg_attr(not(target_arch = "wasm32"), derive(Debug))]
+#[serde(crate = "near_sdk::serde")]
+pub struct BatchSwapInfo {
+    pub owner: AccountId,
+
+    /// Input token; output token is the other token of pool.
+    pub token_in: TokenId,
+    pub amount_in: U128,
+    pub min_amount_out: U128,
+}
+
+#[cfg(feature = "batch-auction")]
+impl From<BatchAuction> for BatchAuctionInfo {
+    fn from(batch: BatchAuction) -> Self {
+        Self {
+            swaps: batch
+                .orders
+                .into_iter()
+                .map(|order| BatchSwapInfo {
+                    owner: order.owner,
+                    token_in: order.token_in,
+                    amount_in: order.amount_in.into(),
+                    min_amount_out: order.min_amount_out.into(),
+                })
+                .collect(),
+            block_height: batch.block_height.into(),
+        }
+    }
+}
+
+/// Result of `settle_batch`
+#[cfg(feature = "batch-auction")]
+#[derive(Serialize, Deserialize)]
+#[cfg_attr(not(target_arch = "wasm32"), derive(Debug))]
+#[serde(crate = "near_sdk::serde")]
+pub struct SettleBatchResult {
+    /// Number of swaps executed at clearing price.
+    pub executed: u32,
+
+    /// Number of swaps refunded, as their minimal output wasn't met.
+    pub refunded: u32,
+}
+
+/// Deposit intent waiting for its legs, see `get_deposit_intent` and `get_deposit_intents`
+#[derive(Serialize, Deserialize)]
+#[cfg_attr(not(target_arch = "wasm32"), derive(Debug))]
+#[serde(crate = "near_sdk::serde")]
+pub struct DepositIntentInfo {
+    pub id: U64,
+    pub owner: AccountId,
+    pub legs: Vec<DepositLegInfo>,
+
+    /// Actions executed once all legs are received.
+    pub actions: Vec<Action<()>>,
+
+    /// Moment after which deposits to intent are refunded, in seconds.
+    pub expires_at: U64,
+}
+
+/// Single token deposit expected by deposit intent
+#[derive(Serialize, Deserialize)]
+#[cfg_attr(not(target_arch = "wasm32"), derive(Debug))]
+#[serde(crate = "near_sdk::serde")]
+pub struct DepositLegInfo {
+    pub token_id: TokenId,
+    pub amount: U128,
+
+    /// Amount received so far, already credited to owner's deposits.
+    pub received: U128,
+}
+
+impl From<(u64, DepositIntent)> for DepositIntentInfo {
+    fn from((id, intent): (u64, DepositIntent)) -> Self {
+        Self {
+            id: id.into(),
+            owner: intent.owner,
+            legs: intent
+                .legs
+                .into_iter()
+                .map(|leg| DepositLegInfo {
+                    token_id: leg.token_id,
+                    amount: leg.amount.into(),
+                    received: leg.received.into(),
+                })
+                .collect(),
+            actions: intent.actions,
+            expires_at: intent.expires_at.into(),
+        }
+    }
+}
+
+/// Registered integration, see `get_integration` and `get_integrations`
+#[derive(Serialize, Deserialize)]
+#[cfg_attr(not(target_arch = "wasm32"), derive(Debug))]
+#[serde(crate = "near_sdk::serde")]
+pub struct IntegrationInfo {
+    /// Id which swaps are attributed with, echoed in `swap` events.
+    pub id: IntegrationId,
+    pub owner: AccountId,
+    pub name: String,
+    pub metadata: String,
+}
+
+impl From<(IntegrationId, Integration)> for IntegrationInfo {
+    fn from((id, integration): (IntegrationId, Integration)) -> Self {
+        Self {
+            id,
+            owner: integration.owner,
+            name: integration.name,
+            metadata: integration.metadata,
+        }
+    }
+}
+
+/// Running protocol fee auction, see `get_fee_auctions`
+#[derive(Serialize, Deserialize)]
+#[cfg_attr(not(target_arch = "wasm32"), derive(Debug))]
+#[serde(crate = "near_sdk::serde")]
+pub struct FeeAuctionInfo {
+    /// Sold token.
+    pub token: TokenId,
+    pub quote_token: TokenId,
+
+    /// Amount of `token` left for sale.
+    pub lot: U128,
+
+    /// Current price of `token` in units of `quote_token`.
+    pub price: f64,
+
+    /// Price of `token` after the end of auction.
+    pub end_price: f64,
+
+    /// Moment when price stops decaying, in seconds.
+    pub ends_at: U64,
+}
+
+impl FeeAuctionInfo {
+    pub fn new(token: TokenId, auction: FeeAuction, now: u64) -> Self {
+        Self {
+            token,
+            price: auction.price_at(now).into(),
+            quote_token: auction.quote_token,
+            lot: auction.lot.into(),
+            end_price: auction.end_price.into(),
+            ends_at: auction.ends_at.into(),
+        }
+    }
+}
+
+/// Registered pool alias, see `get_pool_aliases`
+#[derive(Serialize, Deserialize)]
+#[cfg_attr(not(target_arch = "wasm32"), derive(Debug))]
+#[serde(crate = "near_sdk::serde")]
+pub struct PoolAliasInfo {
+    pub alias: String,
+    pub tokens: Pair<TokenId>,
+}
+
+impl From<(String, (TokenId, TokenId))> for PoolAliasInfo {
+    fn from((alias, tokens): (String, (TokenId, TokenId))) -> Self {
+        Self {
+            alias,
+            tokens: tokens.into(),
+        }
+    }
+}
+
+/// NEAR paid by account for storage of pool it created, see `get_pool_storage_deposit`
+#[derive(Serialize, Deserialize)]
+#[cfg_attr(not(target_arch = "wasm32"), derive(Debug))]
+#[serde(crate = "near_sdk::serde")]
+pub struct PoolStorageDepositInfo {
+    pub creator: AccountId,
+    pub amount: U128,
+    pub storage_bytes: U64,
+}
+
+impl From<PoolStorageDeposit> for PoolStorageDepositInfo {
+    fn from(
+        PoolStorageDeposit {
+            creator,
+            amount,
+            storage_bytes,
+        }: PoolStorageDeposit,
+    ) -> Self {
+        Self {
+            creator,
+            amount: amount.into(),
+            storage_bytes: storage_bytes.into(),
+        }
+    }
+}
+
+/// Summary of NEAR held by contract for storage of pools, see `get_pool_storage_report`
+#[derive(Serialize, Deserialize)]
+#[cfg_attr(not(target_arch = "wasm32"), derive(Debug))]
+#[serde(crate = "near_sdk::serde")]
+pub struct PoolStorageReport {
+    /// Number of pools with recorded storage deposits
+    pub pools: U64,
+    /// Total of recorded deposits
+    pub total_deposits: U128,
+    /// Storage cost of those pools at current storage price
+    pub required: U128,
+    /// Part of deposits exceeding storage cost, which may be credited back to creators
+    pub headroom: U128,
+}
+
+/// Administrative call kept in on-chain admin log, see `get_admin_log`
+#[derive(Serialize, Deserialize)]
+#[cfg_attr(not(target_arch = "wasm32"), derive(Debug))]
+#[serde(crate = "near_sdk::serde")]
+pub struct AdminLogEntryInfo {
+    /// Sequence number of the call
+    pub id: U64,
+    pub caller: AccountId,
+    /// Name of called method
+    pub action: String,
+    /// SHA-256 hash of call arguments
+    pub args_hash: Base58CryptoHash,
+    pub block_height: U64,
+}
+
+impl From<(u64, AdminLogEntry)> for AdminLogEntryInfo {
+    fn from(
+        (
+            id,
+            AdminLogEntry {
+                caller,
+                action,
+                args_hash,
+                block_height,
+            },
+        ): (u64, AdminLogEntry),
+    ) -> Self {
+        Self {
+            id: id.into(),
+            caller,
+            action,
+            args_hash: args_hash.into(),
+            block_height: block_height.into(),
+        }
+    }
+}
+
+/// Cached metadata of token, see `get_tokens_metadata`
+#[derive(Serialize, Deserialize)]
+#[cfg_attr(not(target_arch = "wasm32"), derive(Debug))]
+#[serde(crate = "near_sdk::serde")]
+pub struct TokenMetadataInfo {
+    pub symbol: String,
+    pub decimals: u8,
+    /// SHA-256 hash of token icon, so that frontend can tell if its copy of icon is stale
+    pub icon_hash: Option<Base58CryptoHash>,
+    /// Moment when metadata was fetched from token contract, in seconds
+    pub updated_at: U64,
+}
+
+impl From<CachedTokenMetadata> for TokenMetadataInfo {
+    fn from(metadata: CachedTokenMetadata) -> Self {
+        Self {
+            symbol: metadata.symbol,
+            decimals: metadata.decimals,
+            icon_hash: metadata.icon_hash.map(Into::into),
+            updated_at: metadata.updated_at.into(),
+        }
+    }
+}
+
+/// Withdraw protection of account, see `set_vault`
+#[derive(Serialize, Deserialize)]
+#[cfg_attr(not(target_arch = "wasm32"), derive(Debug))]
+#[serde(crate = "near_sdk::serde")]
+pub struct VaultInfo {
+    /// Account which may cancel delayed withdrawals and remove vault
+    pub guardian: AccountId,
+    /// Withdrawals of at least these amounts are delayed
+    pub thresholds: Vec<TokenAmount>,
+    /// Delay of large withdrawals, in seconds
+    pub delay: U64,
+    pub delayed_withdrawals: Vec<DelayedWithdrawalInfo>,
+}
+
+/// Withdrawal waiting for delay of vault to expire
+#[derive(Serialize, Deserialize)]
+#[cfg_attr(not(target_arch = "wasm32"), derive(Debug))]
+#[serde(crate = "near_sdk::serde")]
+pub struct DelayedWithdrawalInfo {
+    pub id: U64,
+    pub token_id: TokenId,
+    pub amount: U128,
+    /// Moment since which withdrawal can be executed, in seconds
+    pub execute_after: U64,
+}
+
+impl From<Vault> for VaultInfo {
+    fn from(vault: Vault) -> Self {
+        Self {
+            guardian: vault.guardian,
+            thresholds: vault
+                .thresholds
+                .into_iter()
+                .map(|(token_id, amount)| TokenAmount {
+                    token_id,
+                    amount: amount.into(),
+                })
+                .collect(),
+            delay: vault.delay.into(),
+            delayed_withdrawals: vault
+                .delayed_withdrawals
+                .into_iter()
+                .map(|withdrawal| DelayedWithdrawalInfo {
+                    id: withdrawal.id.into(),
+                    token_id: withdrawal.token_id,
+                    amount: withdrawal.amount.into(),
+                    execute_after: withdrawal.execute_after.into(),
+                })
+                .collect(),
+        }
+    }
+}
+
+/// Session key of account, see `register_session_key`
+#[derive(Serialize, Deserialize)]
+#[cfg_attr(not(target_arch = "wasm32"), derive(Debug))]
+#[serde(crate = "near_sdk::serde")]
+pub struct SessionKeyInfo {
+    pub public_key: String,
+    /// Tokens which key may swap from, with maximal amount swapped per day
+    pub volume_caps: Vec<TokenAmount>,
+    /// Amounts swapped during `day`, in the same order as `volume_caps`
+    pub volumes: Vec<U128>,
+    /// Day which `volumes` are counted for, as number of days since epoch
+    pub day: U64,
+}
+
+impl From<SessionKey> for SessionKeyInfo {
+    fn from(key: SessionKey) -> Self {
+        Self {
+            public_key: key.public_key,
+            volume_caps: key
+                .volume_caps
+                .into_iter()
+                .map(|(token_id, amount)| TokenAmount {
+                    token_id,
+                    amount: amount.into(),
+                })
+                .collect(),
+            volumes: key.volumes.into_iter().map(Into::into).collect(),
+            day: key.day.into(),
+        }
+    }
+}
+
+/// Constants of contract math, see `get_constants`
+#[derive(Serialize, Deserialize)]
+#[cfg_attr(not(target_arch = "wasm32"), derive(Debug))]
+#[serde(crate = "near_sdk::serde")]
+pub struct ContractConstants {
+    /// Lowest tick of position range
+    pub min_tick: i32,
+    /// Highest tick of position range
+    pub max_tick: i32,
+    pub num_fee_levels: FeeLevel,
+    /// Scale factor of fee rates and other fractions in basis points
+    pub basis_point_divisor: BasisPoints,
+    /// Lower bound of minimal position liquidity which owner may configure
+    pub min_liquidity: f64,
+    /// Upper bound of minimal position liquidity which owner may configure
+    pub max_min_liquidity: f64,
+    /// Maximal liquidity of single position
+    pub max_liquidity: f64,
+    /// Bits of `f64` spot sqrtprice of tick 1, i.e. ratio of sqrtprices of adjacent ticks
+    pub tick_base_bits: U64,
+}
@@ -0,0 +1,118 @@
+//! Deprecated tuple-based signatures of WASM entrypoints, replaced with `wasm_api_types`.
+//! Kept for clients which aren't migrated yet; will be removed in future versions.
+use super::wasm::NearUnwrap;
+use super::{AccountId, OpenPositionResult, State, StateExt, TokenId};
+use crate::dex::{
+    self, CleanupCursor, PendingApproval, PositionInit, ScheduledAction, WithdrawLimit,
+};
+use near_sdk::json_types::{Base58CryptoHash, U128, U64};
+use near_sdk::{assert_one_yocto, near_bindgen};
+
+#[near_bindgen]
+impl State {
+    /// Deprecated: returns `(position_id, amount_a, amount_b, liquidity)` tuple
+    #[payable]
+    pub fn open_position(
+        &mut self,
+        token_a: &AccountId,
+        token_b: &AccountId,
+        fee_rate: dex::BasisPoints,
+        position: PositionInit,
+    ) -> (U64, U128, U128, f64) {
+        let OpenPositionResult {
+            position_id,
+            amount_a,
+            amount_b,
+            liquidity,
+            ..
+        } = self.open_position_impl(token_a, token_b, fee_rate, position);
+        (position_id, amount_a, amount_b, liquidity)
+    }
+
+    /// Deprecated: takes tokens and amounts as tuples
+    #[payable]
+    #[allow(clippy::needless_pass_by_value)]
+    pub fn set_min_deposit_amounts(
+        &mut self,
+        tokens: (TokenId, TokenId),
+        min_amounts: Option<(U128, U128)>,
+    ) {
+        assert_one_yocto();
+        self.as_dex_mut()
+            .set_min_deposit_amounts(tokens, min_amounts.map(|(a, b)| (a.into(), b.into())))
+            .near_unwrap();
+        self.record_admin_call("set_min_deposit_amounts");
+    }
+
+    /// Deprecated: takes tokens and returns amounts as tuples
+    #[allow(clippy::needless_pass_by_value)]
+    pub fn get_min_deposit_amounts(&self, tokens: (TokenId, TokenId)) -> (U128, U128) {
+        let (min_a, min_b) = self.as_dex().get_min_deposit_amounts(tokens).near_unwrap();
+        (min_a.into(), min_b.into())
+    }
+
+    /// Deprecated: returns `(id, scheduled)` tuples
+    pub fn get_scheduled_actions(&self) -> Vec<(U64, ScheduledAction)> {
+        self.as_dex()
+            .get_scheduled_actions()
+            .into_iter()
+            .map(|(id, scheduled)| (id.into(), scheduled))
+            .collect()
+    }
+
+    /// Deprecated: returns `(admins, threshold)` tuple
+    pub fn get_admins(&self) -> (Vec<AccountId>, u16) {
+        self.as_dex().get_admins()
+    }
+
+    /// Deprecated: returns `(action_hash, pending)` tuples
+    pub fn get_pending_approvals(&self) -> Vec<(Base58CryptoHash, PendingApproval)> {
+        self.as_dex()
+            .get_pending_approvals()
+            .into_iter()
+            .map(|(hash, pending)| (hash.into(), pending))
+            .collect()
+    }
+
+    /// Deprecated: returns `(token_id, amount)` tuples
+    pub fn get_insurance_reserve(&self) -> Vec<(TokenId, U128)> {
+        self.as_dex()
+            .get_insurance_reserve()
+            .into_iter()
+            .map(|(token_id, amount)| (token_id, amount.into()))
+            .collect()
+    }
+
+    /// Deprecated: returns `(token_id, limit)` tuples
+    pub fn get_withdraw_limits(&self) -> Vec<(TokenId, WithdrawLimit)> {
+        self.as_dex().get_withdraw_limits()
+    }
+
+    /// Deprecated: takes tokens as tuple
+    #[payable]
+    #[allow(clippy::needless_pass_by_value)]
+    pub fn cleanup_pool_storage(
+        &mut self,
+        tokens: (TokenId, TokenId),
+        max_items: u32,
+    ) -> Option<CleanupCursor> {
+        assert_one_yocto();
+        let cursor = self
+            .as_dex_mut()
+            .cleanup_pool_storage(tokens, max_items as usize)
+            .near_unwrap();
+        self.record_admin_call("cleanup_pool_storage");
+        cursor
+    }
+
+    /// Deprecated: takes tokens and returns amounts as tuples
+    #[payable]
+    pub fn withdraw_protocol_fee(&mut self, pool_id: (TokenId, TokenId)) -> (U128, U128) {
+        assert_one_yocto();
+        let fee_amounts = self
+            .as_dex_mut()
+            .withdraw_protocol_fee(pool_id)
+            .near_unwrap();
+        (fee_amounts.0.into(), fee_amounts.1.into())
+    }
+}
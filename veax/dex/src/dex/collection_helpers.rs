@@ -94,3 +94,55 @@ impl<'a, K: 'a, V: 'a, I: Iterator<Item = (K, V)> + 'a> Iterator
             .map(|(k, v)| (StorageRef::new(k), StorageRef::new(v)))
     }
 }
+/// Wraps map iterator and yields only entries starting from `skip_to` key (inclusive)
+/// and ending before `end` key, in map's iteration order
+///
+/// Entries before `skip_to` are still read from inner iterator, so maps which can
+/// start iteration at arbitrary key should position inner iterator themselves
+/// and leave `skip_to` empty.
+pub struct MapRange<K, I> {
+    inner: I,
+    skip_to: Option<K>,
+    end: Option<K>,
+    done: bool,
+}
+
+impl<K, I> MapRange<K, I> {
+    pub fn new(inner: I, skip_to: Option<K>, end: Option<K>) -> Self {
+        Self {
+            inner,
+            skip_to,
+            end,
+            done: false,
+        }
+    }
+}
+
+impl<K, KR, VR, I> Iterator for MapRange<K, I>
+where
+    K: PartialEq,
+    KR: Deref<Target = K>,
+    I: Iterator<Item = (KR, VR)>,
+{
+    type Item = (KR, VR);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.done {
+            return None;
+        }
+        for (key, value) in self.inner.by_ref() {
+            if let Some(ref start) = self.skip_to {
+                if *key != *start {
+                    continue;
+                }
+                self.skip_to = None;
+            }
+            if self.end.as_ref() == Some(&*key) {
+                break;
+            }
+            return Some((key, value));
+        }
+        self.done = true;
+        None
+    }
+}
@@ -0,0 +1,53 @@
+//! Bounded on-chain log of administrative calls made by owner, admins and guards
+//!
+//! Complements events for clients which can't access historical receipts.
+//! Only the most recent `ADMIN_LOG_CAPACITY` calls are kept.
+use super::Dex;
+use crate::dex::{ActionHash, AdminLogEntry, Contract, Map, MapRemoveKey, State, StateMut, Types};
+use std::borrow::{Borrow, BorrowMut};
+
+/// Maximal number of entries kept in admin log
+const ADMIN_LOG_CAPACITY: u64 = 512;
+
+impl<T: Types, S: State<T>, SS: Borrow<S>> Dex<T, S, SS> {
+    /// Entries of admin log with their sequence numbers, starting from sequence number `from`;
+    /// entries which are already evicted are skipped
+    pub fn get_admin_log(&self, from: u64, limit: usize) -> Vec<(u64, AdminLogEntry)> {
+        let Contract::V1(ref contract) = self.contract();
+        let oldest = contract
+            .next_admin_log_id
+            .saturating_sub(ADMIN_LOG_CAPACITY);
+        (from.max(oldest)..contract.next_admin_log_id)
+            .take(limit)
+            .filter_map(|id| {
+                contract
+                    .admin_log
+                    .inspect(&id, |entry| (id, AdminLogEntry::clone(entry)))
+            })
+            .collect()
+    }
+}
+
+impl<T: Types, S: StateMut<T>, SS: BorrowMut<S>> Dex<T, S, SS> {
+    /// Append administrative call made by caller to admin log, evicting the oldest entry
+    /// if log is full. Should be called once call succeeded, with hash of its arguments
+    ///
+    /// # Parameters
+    /// * `action` - name of called method
+    /// * `args_hash` - SHA-256 hash of call arguments
+    pub fn record_admin_action(&mut self, action: String, args_hash: ActionHash) {
+        let entry = AdminLogEntry {
+            caller: self.get_caller_id(),
+            action,
+            args_hash,
+            block_height: self.get_block_height(),
+        };
+        let Contract::V1(ref mut contract) = self.contract_mut();
+        let id = contract.next_admin_log_id;
+        contract.admin_log.insert(id, entry);
+        if let Some(evicted) = id.checked_sub(ADMIN_LOG_CAPACITY) {
+            contract.admin_log.remove(&evicted);
+        }
+        contract.next_admin_log_id += 1;
+    }
+}
@@ -0,0 +1,228 @@
+//! Automatic harvest of position fees
+//!
+//! Position owner may set minimal accrued fees, upon reaching which fees are credited
+//! to owner's deposits without explicit `withdraw_fee`. Rules are kept per pool and settled
+//! lazily: each swap through pool checks single rule, in round-robin order, so extra gas
+//! paid by swapper is small and bounded. Rule is dropped once its position is closed,
+//! or its owner can't receive fees anymore, e.g. after unregistering pool's token.
+use super::Dex;
+use crate::chain::Amount;
+use crate::dex::errors::{ErrorKind, Result};
+use crate::dex::traits::AccountExtra;
+use crate::dex::{
+    state_types, Account, AutoHarvestQueue, AutoHarvestRule, Contract, ContractLatest, Map,
+    MapRemoveKey, Pool, PoolId, Position, PositionId, Set, State, StateMut, SwapNotification,
+    Types,
+};
+use crate::{ensure_here, error_here};
+use num_traits::Zero;
+use std::borrow::{Borrow, BorrowMut};
+
+/// Maximal number of positions with auto-harvest rules in single pool
+const MAX_AUTO_HARVEST_RULES: usize = 64;
+
+impl<T: Types, S: State<T>, SS: Borrow<S>> Dex<T, S, SS> {
+    /// Minimal accrued fees of position, in order of pool tokens, upon reaching which
+    /// fees are harvested automatically; `None` if position has no auto-harvest rule
+    pub fn get_auto_harvest(&self, position_id: PositionId) -> Result<Option<(Amount, Amount)>> {
+        let Contract::V1(ref contract) = self.contract();
+        let pool_id = contract
+            .position_to_pool_id
+            .try_inspect(&position_id, Clone::clone)?;
+        Ok(contract
+            .auto_harvest
+            .inspect(&pool_id, |queue| {
+                queue
+                    .rules
+                    .iter()
+                    .find(|rule| rule.position_id == position_id)
+                    .map(|rule| rule.min_fees)
+            })
+            .flatten())
+    }
+}
+
+impl<T: Types, S: StateMut<T>, SS: BorrowMut<S>> Dex<T, S, SS> {
+    /// Set or remove auto-harvest rule of caller's position;
+    /// storage of rule is paid from caller's storage deposit
+    ///
+    /// # Parameters
+    /// * `min_fees` - minimal accrued fees, in order of pool tokens, upon reaching either of which
+    ///     fees are harvested; both must be non-zero. `None` removes rule
+    pub fn set_auto_harvest(
+        &mut self,
+        position_id: PositionId,
+        min_fees: Option<(Amount, Amount)>,
+    ) -> Result<()> {
+        self.ensure_payable_api_resumed()?;
+        if let Some(min_fees) = min_fees {
+            ensure_here!(
+                !min_fees.0.is_zero() && !min_fees.1.is_zero(),
+                ErrorKind::InvalidParams
+            );
+        }
+        let owner = self.get_caller_id();
+        let pool_id = self.with_account_mut(&owner, |account_view| {
+            ensure_here!(
+                account_view.account.positions.contains_item(&position_id),
+                ErrorKind::NotYourPosition
+            );
+            account_view
+                .position_to_pool_id
+                .try_inspect(&position_id, Clone::clone)
+        })?;
+        let mut queue = {
+            let Contract::V1(ref contract) = self.contract();
+            contract
+                .auto_harvest
+                .inspect(&pool_id, AutoHarvestQueue::clone)
+                .unwrap_or_default()
+        };
+
+        let rule_index = queue
+            .rules
+            .iter()
+            .position(|rule| rule.position_id == position_id);
+        match (rule_index, min_fees) {
+            (Some(index), Some(min_fees)) => queue.rules[index].min_fees = min_fees,
+            (Some(index), None) => {
+                queue.rules.remove(index);
+                self.with_account_mut(&owner, |account_view| {
+                    account_view.account.extra.on_auto_harvest_removed();
+                    Ok(())
+                })?;
+            }
+            (None, Some(min_fees)) => {
+                ensure_here!(
+                    queue.rules.len() < MAX_AUTO_HARVEST_RULES,
+                    ErrorKind::AutoHarvestQueueFull
+                );
+                self.with_account_mut(&owner, |account_view| {
+                    account_view.account.extra.on_auto_harvest_set()
+                })?;
+                queue.rules.push(AutoHarvestRule {
+                    position_id,
+                    owner,
+                    min_fees,
+                });
+            }
+            (None, None) => return Ok(()),
+        }
+        let Contract::V1(ref mut contract) = self.contract_mut();
+        store_queue::<T>(&mut contract.auto_harvest, &pool_id, queue);
+        Ok(())
+    }
+
+    /// Check the next auto-harvest rule of pool, and harvest fees of its position
+    /// if they've reached minimum
+    pub(super) fn auto_harvest(&mut self, pool_id: &PoolId) -> Result<()> {
+        let Contract::V1(ref mut contract) = self.contract_mut();
+        // Stored queues are never empty
+        let rule = contract
+            .auto_harvest
+            .update(pool_id, |queue| {
+                let index = queue.cursor as usize % queue.rules.len();
+                queue.cursor = ((index + 1) % queue.rules.len()) as u32;
+                Ok(queue.rules[index].clone())
+            })
+            .transpose()?;
+        let Some(rule) = rule else {
+            return Ok(());
+        };
+
+        let can_receive = contract
+            .accounts
+            .inspect(&rule.owner, |Account::V1(ref account)| {
+                account.token_balances.contains_key(&pool_id.0)
+                    && account.token_balances.contains_key(&pool_id.1)
+            })
+            .unwrap_or(false);
+        let fees = contract.pools.try_inspect(pool_id, |Pool::V1(ref pool)| {
+            pool.positions
+                .inspect(&rule.position_id, |Position::V1(ref pos)| {
+                    pool.position_reward(pos, false)
+                })
+                .transpose()
+        })??;
+        let fees = match fees {
+            Some(fees) if can_receive => fees,
+            _ => {
+                drop_rule::<T>(contract, pool_id, &rule);
+                return Ok(());
+            }
+        };
+        if fees.0 < rule.min_fees.0 && fees.1 < rule.min_fees.1 {
+            return Ok(());
+        }
+
+        let fees = contract
+            .pools
+            .try_update(pool_id, |Pool::V1(ref mut pool)| {
+                pool.withdraw_fee(rule.position_id)
+            })?;
+        self.with_account_mut(&rule.owner, |account_view| {
+            account_view
+                .account
+                .deposit(&pool_id.0, fees.0)
+                .map_err(|e| error_here!(e))?;
+            account_view
+                .account
+                .deposit(&pool_id.1, fees.1)
+                .map_err(|e| error_here!(e))?;
+            account_view.logger.log_harvest_fee_event(
+                account_view.account_id,
+                rule.position_id,
+                fees,
+            );
+            Ok(())
+        })
+    }
+
+    /// Check auto-harvest rules of pools which were swapped through
+    pub(super) fn auto_harvest_swapped_pools(
+        &mut self,
+        notifications: &[SwapNotification],
+    ) -> Result<()> {
+        for notification in notifications {
+            let (pool_id, _) = PoolId::try_from_pair((
+                notification.token_in.clone(),
+                notification.token_out.clone(),
+            ))
+            .map_err(|e| error_here!(e))?;
+            self.auto_harvest(&pool_id)?;
+        }
+        Ok(())
+    }
+}
+
+/// Store auto-harvest queue of pool, or remove it if it's empty
+fn store_queue<T: Types>(
+    auto_harvest: &mut state_types::AutoHarvestMap<T>,
+    pool_id: &PoolId,
+    queue: AutoHarvestQueue,
+) {
+    if queue.rules.is_empty() {
+        auto_harvest.remove(pool_id);
+    } else {
+        auto_harvest.insert(pool_id.clone(), queue);
+    }
+}
+
+/// Remove rule which can't be settled anymore, releasing its storage if owner is still registered
+fn drop_rule<T: Types>(contract: &mut ContractLatest<T>, pool_id: &PoolId, rule: &AutoHarvestRule) {
+    if let Some(mut queue) = contract
+        .auto_harvest
+        .inspect(pool_id, AutoHarvestQueue::clone)
+    {
+        queue
+            .rules
+            .retain(|other| other.position_id != rule.position_id);
+        store_queue::<T>(&mut contract.auto_harvest, pool_id, queue);
+    }
+    contract
+        .accounts
+        .update(&rule.owner, |Account::V1(ref mut account)| {
+            account.extra.on_auto_harvest_removed();
+            Ok(())
+        });
+}
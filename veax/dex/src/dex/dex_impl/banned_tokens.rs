@@ -0,0 +1,77 @@
+//! Token deny-list: owner-managed set of known malicious tokens, e.g. honeypots impersonating
+//! legit ones, which can't be deposited, swapped or used to create new pools
+use super::Dex;
+use crate::chain::TokenId;
+use crate::dex::errors::{ErrorKind, Result};
+use crate::dex::{Contract, Set, State, StateMut, Types};
+use crate::ensure_here;
+use std::borrow::{Borrow, BorrowMut};
+
+impl<T: Types, S: State<T>, SS: Borrow<S>> Dex<T, S, SS> {
+    pub fn get_banned_tokens(&self) -> Vec<TokenId> {
+        let Contract::V1(ref contract) = self.contract();
+        contract
+            .banned_tokens
+            .iter()
+            .map(|token| TokenId::clone(&token))
+            .collect()
+    }
+
+    pub fn is_token_banned(&self, token_id: &TokenId) -> bool {
+        let Contract::V1(ref contract) = self.contract();
+        contract.banned_tokens.contains_item(token_id)
+    }
+
+    pub(super) fn ensure_tokens_not_banned<'a>(
+        &self,
+        tokens: impl IntoIterator<Item = &'a TokenId>,
+    ) -> Result<()> {
+        let Contract::V1(ref contract) = self.contract();
+        ensure_not_banned::<T>(&contract.banned_tokens, tokens)
+    }
+}
+
+impl<T: Types, S: StateMut<T>, SS: BorrowMut<S>> Dex<T, S, SS> {
+    pub fn ban_tokens(&mut self, tokens: impl IntoIterator<Item = TokenId>) -> Result<()> {
+        self.ensure_payable_api_resumed()?;
+        self.ensure_caller_is_owner()?;
+        let Contract::V1(ref mut contract) = self.contract_mut();
+        let mut banned_tokens = Vec::new();
+        for token in tokens {
+            if !contract.banned_tokens.contains_item(&token) {
+                contract.banned_tokens.add_item(token.clone());
+                banned_tokens.push(token);
+            }
+        }
+
+        self.logger_mut().log_ban_tokens_event(&banned_tokens);
+        Ok(())
+    }
+
+    pub fn unban_tokens(&mut self, tokens: impl IntoIterator<Item = TokenId>) -> Result<()> {
+        self.ensure_payable_api_resumed()?;
+        self.ensure_caller_is_owner()?;
+        let Contract::V1(ref mut contract) = self.contract_mut();
+        let mut unbanned_tokens = Vec::new();
+        for token in tokens {
+            if contract.banned_tokens.contains_item(&token) {
+                contract.banned_tokens.remove_item(&token);
+                unbanned_tokens.push(token);
+            }
+        }
+
+        self.logger_mut().log_unban_tokens_event(&unbanned_tokens);
+        Ok(())
+    }
+}
+
+/// Fails with `TokenBanned` if any of `tokens` is banned
+pub(super) fn ensure_not_banned<'a, T: Types>(
+    banned_tokens: &T::BannedTokensSet,
+    tokens: impl IntoIterator<Item = &'a TokenId>,
+) -> Result<()> {
+    for token in tokens {
+        ensure_here!(!banned_tokens.contains_item(token), ErrorKind::TokenBanned);
+    }
+    Ok(())
+}
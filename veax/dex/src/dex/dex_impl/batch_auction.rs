@@ -0,0 +1,377 @@
+//! Frequent batch clearing mode of concentrated pools (experimental)
+//!
+//! Pool in batch mode doesn't accept direct swaps. Instead, swaps submitted within the same block
+//! are accumulated, with their input amounts taken from deposits, and cleared together at single
+//! uniform price by a keeper once the block has passed. Opposite swaps are matched against each
+//! other, and only net imbalance is swapped through the pool, so order of swaps within block
+//! gives no advantage.
+use super::{Dex, PoolStateBefore};
+use crate::chain::{AccountId, Amount, TokenId, UIntBig};
+use crate::dex::errors::{ErrorKind, Result};
+use crate::dex::traits::AccountExtra;
+use crate::dex::util_types::{PoolId, Side};
+use crate::dex::{
+    state_types, Account, BasisPoints, BatchAuction, BatchOrder, ConfigChange, Contract, Exact,
+    Float, Map, Pool, PoolUpdateReason, State, StateMembersMut, StateMut, Types,
+};
+use crate::{ensure_here, error_here};
+use near_sdk::borsh::{BorshDeserialize, BorshSerialize};
+use num_traits::Zero;
+use std::borrow::{Borrow, BorrowMut};
+
+/// Maximal number of swaps in single batch, limits gas consumption of settlement
+const MAX_BATCH_ORDERS: usize = 32;
+/// Maximal number of bisection steps when searching for net amount swapped through pool;
+/// each step simulates swap, so precision is traded for gas
+const MAX_CLEARING_ITERATIONS: usize = 64;
+
+impl<T: Types, S: State<T>, SS: Borrow<S>> Dex<T, S, SS> {
+    /// Pending batch of pool; `None` if pool isn't in batch clearing mode
+    pub fn get_batch_auction(&self, tokens: (TokenId, TokenId)) -> Result<Option<BatchAuction>> {
+        let (pool_id, _) = PoolId::try_from_pair(tokens).map_err(|e| error_here!(e))?;
+        let Contract::V1(ref contract) = self.contract();
+        contract
+            .pools
+            .try_inspect(&pool_id, |Pool::V1(ref pool)| pool.batch_auction.clone())
+    }
+}
+
+impl<T: Types, S: StateMut<T>, SS: BorrowMut<S>> Dex<T, S, SS> {
+    /// Switch batch clearing mode of concentrated pool; it can be switched off
+    /// only when pool has no pending swaps
+    pub fn set_batch_auction_mode(
+        &mut self,
+        tokens: (TokenId, TokenId),
+        enabled: bool,
+    ) -> Result<()> {
+        self.ensure_payable_api_resumed()?;
+        self.ensure_caller_is_owner()?;
+        let (pool_id, _) = PoolId::try_from_pair(tokens).map_err(|e| error_here!(e))?;
+        let Contract::V1(ref mut contract) = self.contract_mut();
+        let previous = contract
+            .pools
+            .try_update(&pool_id, |Pool::V1(ref mut pool)| {
+                let previous = pool.batch_auction.is_some();
+                if !enabled {
+                    let pending = pool
+                        .batch_auction
+                        .as_ref()
+                        .map_or(false, |batch| !batch.orders.is_empty());
+                    ensure_here!(!pending, ErrorKind::BatchNotSettled);
+                    pool.batch_auction = None;
+                } else if !previous {
+                    pool.batch_auction = Some(BatchAuction::default());
+                }
+                Ok(previous)
+            })?;
+        let caller_id = self.get_caller_id();
+        self.logger_mut().log_config_change_event(
+            &caller_id,
+            &ConfigChange::BatchAuctionMode {
+                pool: (pool_id.0.clone(), pool_id.1.clone()),
+                previous,
+                new: enabled,
+            },
+        );
+        Ok(())
+    }
+
+    /// Submit exact-in swap of caller to batch of the current block
+    ///
+    /// # Parameters
+    /// * `tokens` - input and output tokens; both must be registered by caller
+    /// * `amount_in` - amount of input token, taken from caller's deposit right away
+    /// * `min_amount_out` - minimal amount of output token to receive at clearing price
+    pub fn submit_batch_swap(
+        &mut self,
+        tokens: (TokenId, TokenId),
+        amount_in: Amount,
+        min_amount_out: Amount,
+    ) -> Result<()> {
+        self.ensure_payable_api_resumed()?;
+        self.ensure_tokens_not_banned([&tokens.0, &tokens.1])?;
+        ensure_here!(!amount_in.is_zero(), ErrorKind::InvalidParams);
+        let (pool_id, _) = PoolId::try_from_pair(tokens.clone()).map_err(|e| error_here!(e))?;
+        let owner = self.get_caller_id();
+        let block_height = self.get_block_height();
+        self.with_account_mut(&owner, |account_view| {
+            ensure_here!(
+                account_view.account.token_balances.contains_key(&tokens.1),
+                ErrorKind::TokenNotRegistered
+            );
+            account_view
+                .account
+                .withdraw(&tokens.0, amount_in)
+                .map_err(|e| error_here!(e))?;
+            account_view.account.extra.on_order_placed()
+        })?;
+
+        let Contract::V1(ref mut contract) = self.contract_mut();
+        contract
+            .pools
+            .try_update(&pool_id, |Pool::V1(ref mut pool)| {
+                let batch = pool
+                    .batch_auction
+                    .as_mut()
+                    .ok_or_else(|| error_here!(ErrorKind::PoolNotInBatchMode))?;
+                if batch.orders.is_empty() {
+                    batch.block_height = block_height;
+                }
+                ensure_here!(
+                    batch.block_height == block_height,
+                    ErrorKind::BatchNotSettled
+                );
+                ensure_here!(batch.orders.len() < MAX_BATCH_ORDERS, ErrorKind::BatchFull);
+                batch.orders.push(BatchOrder {
+                    owner,
+                    token_in: tokens.0,
+                    amount_in,
+                    min_amount_out,
+                });
+                Ok(())
+            })
+    }
+
+    /// Clear batch of pool at single price, once block of its swaps has passed;
+    /// may be called by any account
+    ///
+    /// Swaps whose minimal output isn't met at clearing price are refunded,
+    /// and clearing price is evaluated again without them.
+    ///
+    /// # Returns
+    /// Numbers of executed and refunded swaps
+    pub fn settle_batch(&mut self, tokens: (TokenId, TokenId)) -> Result<(usize, usize)> {
+        self.ensure_payable_api_resumed()?;
+        let (pool_id, _) = PoolId::try_from_pair(tokens).map_err(|e| error_here!(e))?;
+        let block_height = self.get_block_height();
+        let protocol_fee_fraction = self.protocol_fee_fraction();
+
+        let Contract::V1(ref mut contract) = self.contract_mut();
+        let (mut orders, pool_bytes) = contract.pools.try_update(&pool_id, |pool| {
+            let Pool::V1(ref mut pool_v0) = pool;
+            let batch = pool_v0
+                .batch_auction
+                .as_mut()
+                .ok_or_else(|| error_here!(ErrorKind::PoolNotInBatchMode))?;
+            ensure_here!(
+                !batch.orders.is_empty() && batch.block_height < block_height,
+                ErrorKind::BatchNotReady
+            );
+            let orders = std::mem::take(&mut batch.orders);
+            // Clearing is searched on copies of pool state before net swap
+            let pool_bytes = pool
+                .try_to_vec()
+                .map_err(|_| error_here!(ErrorKind::InternalLogicError))?;
+            Ok((orders, pool_bytes))
+        })?;
+
+        let mut refunded = Vec::new();
+        let clearing = loop {
+            let clearing =
+                find_clearing::<T>(&pool_id, &pool_bytes, &orders, protocol_fee_fraction)?;
+            let (filled, unfilled): (Vec<_>, Vec<_>) = orders.into_iter().partition(|order| {
+                let amount_out = clearing.amount_out(&pool_id, order);
+                !amount_out.is_zero() && amount_out >= order.min_amount_out
+            });
+            orders = filled;
+            if unfilled.is_empty() {
+                break clearing;
+            }
+            refunded.extend(unfilled);
+        };
+
+        if let Some((side, amount_in, expected_amount_out)) = clearing.pool_swap {
+            let Contract::V1(ref mut contract) = self.contract_mut();
+            let before = contract
+                .pools
+                .try_update(&pool_id, |Pool::V1(ref mut pool)| {
+                    let before = PoolStateBefore::new(pool);
+                    let amount_out =
+                        pool.swap(side, Exact::In, amount_in, protocol_fee_fraction)?;
+                    // Swap runs on the same pool state as simulation, so it can't give less
+                    ensure_here!(
+                        amount_out >= expected_amount_out,
+                        ErrorKind::InternalLogicError
+                    );
+                    Ok(before)
+                })?;
+            self.log_pool_state(&pool_id, Some(&before), PoolUpdateReason::Swap)?;
+            #[cfg(feature = "smart-routing")]
+            self.update_top_pools([pool_id.clone()])?;
+            self.auto_harvest(&pool_id)?;
+        }
+
+        let StateMembersMut {
+            contract: Contract::V1(ref mut contract),
+            logger,
+            ..
+        } = self.members_mut();
+        for order in &orders {
+            let token_out = other_token(&pool_id, &order.token_in);
+            let amount_out = clearing.amount_out(&pool_id, order);
+            pay_out::<T>(
+                &mut contract.accounts,
+                &mut contract.insurance_reserve,
+                &order.owner,
+                token_out,
+                amount_out,
+            )?;
+            logger.log_swap_event(
+                &order.owner,
+                (&order.token_in, token_out),
+                (&order.amount_in, &amount_out),
+                &[],
+                None,
+            );
+        }
+        for order in &refunded {
+            pay_out::<T>(
+                &mut contract.accounts,
+                &mut contract.insurance_reserve,
+                &order.owner,
+                &order.token_in,
+                order.amount_in,
+            )?;
+        }
+        Ok((orders.len(), refunded.len()))
+    }
+}
+
+/// Uniform clearing of batch
+struct Clearing {
+    /// Total input amounts of swaps of each side
+    amounts_in: (Amount, Amount),
+    /// Total output amounts paid out to swaps of each side
+    amounts_out: (Amount, Amount),
+    /// Side, input and output amounts of net swap through pool, if any
+    pool_swap: Option<(Side, Amount, Amount)>,
+}
+
+impl Clearing {
+    /// Output amount of swap: its pro-rata share of total output of its side
+    fn amount_out(&self, pool_id: &PoolId, order: &BatchOrder) -> Amount {
+        let side = side_of(pool_id, &order.token_in);
+        if self.amounts_in[side].is_zero() {
+            return Amount::zero();
+        }
+        (UIntBig::from(order.amount_in) * UIntBig::from(self.amounts_out[side])
+            / UIntBig::from(self.amounts_in[side]))
+        .low_u128()
+    }
+}
+
+/// Find clearing of swaps at single price
+///
+/// Swaps of side which outweighs the opposite one at pool's marginal price swap net amount `x`
+/// through the pool, for output `y(x)`, and the rest is matched with opposite swaps at the
+/// same price: `y(x) / x = opposite_total / (total - x)`. Left-hand side decreases with `x`,
+/// while right-hand side increases, so `x` is found by bisection over simulated swaps.
+/// When neither side outweighs, swaps are matched against each other entirely,
+/// at price which is within pool's bid-ask spread.
+fn find_clearing<T: Types>(
+    pool_id: &PoolId,
+    pool_bytes: &[u8],
+    orders: &[BatchOrder],
+    protocol_fee_fraction: BasisPoints,
+) -> Result<Clearing> {
+    let mut amounts_in = (Amount::zero(), Amount::zero());
+    for order in orders {
+        amounts_in[side_of(pool_id, &order.token_in)] += order.amount_in;
+    }
+    let matched = Clearing {
+        amounts_in,
+        amounts_out: (amounts_in.1, amounts_in.0),
+        pool_swap: None,
+    };
+
+    let Pool::V1(pool) = Pool::<T>::try_from_slice(pool_bytes)
+        .map_err(|_| error_here!(ErrorKind::InternalLogicError))?;
+    if !pool.is_spot_price_set() {
+        return Ok(matched);
+    }
+    let outweighs = |side: Side| {
+        let eff_sqrtprice = pool.eff_sqrtprice(side, 0);
+        Float::from(amounts_in[side])
+            > Float::from(amounts_in[side.opposite()]) * eff_sqrtprice * eff_sqrtprice
+    };
+    let Some(side) = [Side::Left, Side::Right].into_iter().find(|&side| outweighs(side)) else {
+        return Ok(matched);
+    };
+
+    let (total, opposite_total) = (amounts_in[side], amounts_in[side.opposite()]);
+    // Net swap which is known to satisfy clearing condition, and upper bound of the rest
+    let mut best = (Amount::zero(), Amount::zero());
+    let mut high = total;
+    for _ in 0..MAX_CLEARING_ITERATIONS {
+        if best.0 >= high {
+            break;
+        }
+        let mid = best.0 + (high - best.0).div_ceil(2);
+        // Each swap is simulated on a fresh copy, as simulation leaves pool state modified
+        let Pool::V1(mut pool_copy) = Pool::<T>::try_from_slice(pool_bytes)
+            .map_err(|_| error_here!(ErrorKind::InternalLogicError))?;
+        match pool_copy.simulate_swap_exact_in(side, mid, protocol_fee_fraction) {
+            Ok((amount_in, amount_out))
+                if amount_in == mid
+                    && UIntBig::from(amount_out) * UIntBig::from(total - mid)
+                        >= UIntBig::from(opposite_total) * UIntBig::from(mid) =>
+            {
+                best = (mid, amount_out);
+            }
+            _ => high = mid - 1,
+        }
+    }
+
+    let (amount_in, amount_out) = best;
+    let mut amounts_out = (Amount::zero(), Amount::zero());
+    amounts_out[side] = opposite_total + amount_out;
+    amounts_out[side.opposite()] = total - amount_in;
+    Ok(Clearing {
+        amounts_in,
+        amounts_out,
+        pool_swap: (!amount_in.is_zero()).then_some((side, amount_in, amount_out)),
+    })
+}
+
+fn side_of(pool_id: &PoolId, token_in: &TokenId) -> Side {
+    Side::from_swapped(*token_in != pool_id.0)
+}
+
+fn other_token<'a>(pool_id: &'a PoolId, token_in: &TokenId) -> &'a TokenId {
+    if *token_in == pool_id.0 {
+        &pool_id.1
+    } else {
+        &pool_id.0
+    }
+}
+
+/// Credit amount to owner of batch swap and release storage of swap
+///
+/// Amount which can't be credited, as owner has unregistered account or token meanwhile,
+/// goes to insurance reserve.
+fn pay_out<T: Types>(
+    accounts: &mut state_types::AccountsMap<T>,
+    insurance_reserve: &mut state_types::InsuranceReserveMap<T>,
+    owner: &AccountId,
+    token_id: &TokenId,
+    amount: Amount,
+) -> Result<()> {
+    let credited = accounts
+        .update(owner, |Account::V1(ref mut account)| {
+            account.extra.on_order_removed();
+            Ok(account.deposit(token_id, amount).is_ok())
+        })
+        .transpose()?
+        .unwrap_or(false);
+    if credited || amount.is_zero() {
+        return Ok(());
+    }
+    insurance_reserve.update_or_insert(
+        token_id,
+        || Ok(Amount::zero()),
+        |reserve, _| {
+            *reserve += amount;
+            Ok(())
+        },
+    )
+}
@@ -0,0 +1,263 @@
+//! Constant product pools, see `PoolKind::ClassicXY`
+//!
+//! Pair of tokens may have only one pool, of any kind;
+//! kind of pool is selected by the first liquidity provider.
+use super::{fee_rates_ticks, pool_kind, AccountViewMut, Dex};
+use crate::chain::{AccountId, Amount, TokenId};
+use crate::dex::errors::{ErrorKind, Result};
+use crate::dex::latest::{RawFeeLevelsArray, NUM_FEE_LEVELS};
+use crate::dex::util_types::{PoolId, Side};
+use crate::dex::utils::swap_if;
+use crate::dex::{
+    state_types, BasisPoints, ClassicPool, ClassicPoolInfo, ClassicPoolV0, Contract, EventKind,
+    Exact, Float, Logger, Map, PairExt, PoolKind, PoolUpdateReason, State, StateMut, Types,
+    BASIS_POINT_DIVISOR,
+};
+use crate::{ensure_here, error_here};
+use std::borrow::{Borrow, BorrowMut};
+
+impl<T: Types, S: State<T>, SS: Borrow<S>> Dex<T, S, SS> {
+    /// Kind of pool registered for pair of tokens, if any
+    pub fn get_pool_kind(&self, tokens: (TokenId, TokenId)) -> Result<Option<PoolKind>> {
+        let (pool_id, _) = PoolId::try_from_pair(tokens).map_err(|e| error_here!(e))?;
+        let Contract::V1(ref contract) = self.contract();
+        Ok(pool_kind::<T>(
+            &contract.pools,
+            &contract.classic_pools,
+            &contract.stable_pools,
+            &pool_id,
+        ))
+    }
+
+    /// State of constant product pool; reserves are ordered the same way as `tokens`
+    pub fn get_classic_pool_info(
+        &self,
+        tokens: (TokenId, TokenId),
+    ) -> Result<Option<ClassicPoolInfo>> {
+        let (pool_id, swapped) = PoolId::try_from_pair(tokens).map_err(|e| error_here!(e))?;
+        let Contract::V1(ref contract) = self.contract();
+        Ok(contract
+            .classic_pools
+            .inspect(&pool_id, |ClassicPool::V0(ref pool)| ClassicPoolInfo {
+                reserves: swap_if(swapped, pool.reserves),
+                fee_rate: pool.fee_rate,
+                total_shares: pool.total_shares,
+                fee_divisor: BASIS_POINT_DIVISOR,
+            }))
+    }
+
+    /// Shares of constant product pool owned by account
+    pub fn get_classic_pool_shares(
+        &self,
+        tokens: (TokenId, TokenId),
+        account_id: &AccountId,
+    ) -> Result<Amount> {
+        let (pool_id, _) = PoolId::try_from_pair(tokens).map_err(|e| error_here!(e))?;
+        let Contract::V1(ref contract) = self.contract();
+        contract
+            .classic_pools
+            .try_inspect(&pool_id, |ClassicPool::V0(ref pool)| {
+                pool.shares_of(account_id)
+            })
+    }
+}
+
+impl<T: Types, S: StateMut<T>, SS: BorrowMut<S>> Dex<T, S, SS> {
+    /// Deposit liquidity into constant product pool, creating the pool if pair has none
+    ///
+    /// # Parameters
+    /// * `token_a`, `token_b` - pair of tokens
+    /// * `fee_rate` - fee rate of pool, one of `fee_rates_ticks`; must match existing pool
+    /// * `max_amounts` - maximal amounts of `token_a` and `token_b` to deposit;
+    ///     first deposit defines initial price
+    ///
+    /// # Returns
+    /// Actually deposited amounts of `token_a` and `token_b`, and minted shares
+    pub fn add_classic_liquidity(
+        &mut self,
+        token_a: &TokenId,
+        token_b: &TokenId,
+        fee_rate: BasisPoints,
+        max_amounts: (Amount, Amount),
+    ) -> Result<(Amount, Amount, Amount)> {
+        self.ensure_payable_api_resumed()?;
+        self.with_caller_account_mut(|mut account_view| {
+            Self::add_classic_liquidity_impl(
+                token_a,
+                token_b,
+                fee_rate,
+                max_amounts,
+                &mut account_view,
+            )
+        })
+    }
+
+    fn add_classic_liquidity_impl(
+        token_a: &TokenId,
+        token_b: &TokenId,
+        fee_rate: BasisPoints,
+        max_amounts: (Amount, Amount),
+        account_view: &mut AccountViewMut<'_, T>,
+    ) -> Result<(Amount, Amount, Amount)> {
+        let (pool_id, transposed) = PoolId::try_from_pair((token_a.clone(), token_b.clone()))
+            .map_err(|e| error_here!(e))?;
+        account_view.ensure_pool_kind(&pool_id, PoolKind::ClassicXY)?;
+        ensure_here!(fee_rates_ticks().contains(&fee_rate), ErrorKind::IllegalFee);
+        let max_amounts = swap_if(transposed, max_amounts);
+
+        if !account_view.classic_pools.contains_key(&pool_id) {
+            account_view.create_pool(&pool_id, token_a, token_b, |account_view| {
+                let pool = account_view.item_factory.new_classic_pool(fee_rate)?;
+                account_view.classic_pools.insert(pool_id.clone(), pool);
+                Ok(())
+            })?;
+        }
+
+        let account_id = account_view.account_id;
+        let (amounts, shares) =
+            account_view
+                .classic_pools
+                .try_update(&pool_id, |ClassicPool::V0(ref mut pool)| {
+                    ensure_here!(pool.fee_rate == fee_rate, ErrorKind::IllegalFee);
+                    let (amounts, shares) = pool.add_liquidity(account_id, max_amounts)?;
+                    account_view
+                        .account
+                        .withdraw(&pool_id.0, amounts.0)
+                        .map_err(|e| error_here!(e))?;
+                    account_view
+                        .account
+                        .withdraw(&pool_id.1, amounts.1)
+                        .map_err(|e| error_here!(e))?;
+                    Self::log_classic_pool_state(
+                        &pool_id,
+                        pool,
+                        account_view.logger,
+                        PoolUpdateReason::AddLiquidity,
+                    );
+                    Ok((amounts, shares))
+                })?;
+
+        let amounts = swap_if(transposed, amounts);
+        Ok((amounts.0, amounts.1, shares))
+    }
+
+    /// Burn caller's shares of constant product pool, returning withdrawn amounts to caller's deposits
+    ///
+    /// # Returns
+    /// Withdrawn amounts, ordered the same way as `tokens`
+    pub fn remove_classic_liquidity(
+        &mut self,
+        tokens: (TokenId, TokenId),
+        shares: Amount,
+    ) -> Result<(Amount, Amount)> {
+        self.ensure_payable_api_resumed()?;
+        let (pool_id, swapped) = PoolId::try_from_pair(tokens).map_err(|e| error_here!(e))?;
+        self.with_caller_account_mut(|account_view| {
+            let account_id = account_view.account_id;
+            let amounts = account_view.classic_pools.try_update(
+                &pool_id,
+                |ClassicPool::V0(ref mut pool)| {
+                    let amounts = pool.remove_liquidity(account_id, shares)?;
+                    account_view
+                        .account
+                        .deposit(&pool_id.0, amounts.0)
+                        .map_err(|e| error_here!(e))?;
+                    account_view
+                        .account
+                        .deposit(&pool_id.1, amounts.1)
+                        .map_err(|e| error_here!(e))?;
+                    Self::log_classic_pool_state(
+                        &pool_id,
+                        pool,
+                        account_view.logger,
+                        PoolUpdateReason::RemoveLiquidity,
+                    );
+                    Ok(amounts)
+                },
+            )?;
+            Ok(swap_if(swapped, amounts))
+        })
+    }
+
+    /// Swap tokens in constant product pool and log its updated state
+    ///
+    /// # Returns
+    /// Input and output amounts
+    pub(super) fn swap_classic(
+        classic_pools: &mut state_types::ClassicPoolsMap<T>,
+        pool_id: &PoolId,
+        side: Side,
+        exact: Exact,
+        amount: Amount,
+        protocol_fee_fraction: BasisPoints,
+        logger: &mut dyn Logger,
+    ) -> Result<(Amount, Amount)> {
+        classic_pools.try_update(pool_id, |ClassicPool::V0(ref mut pool)| {
+            let amounts = pool.swap(side, exact, amount, protocol_fee_fraction)?;
+            Self::log_classic_pool_state(pool_id, pool, logger, PoolUpdateReason::Swap);
+            Ok(amounts)
+        })
+    }
+
+    /// Logs state of constant product pool
+    fn log_classic_pool_state(
+        pool_id: &PoolId,
+        pool: &ClassicPoolV0<T>,
+        logger: &mut dyn Logger,
+        reason: PoolUpdateReason,
+    ) {
+        if !logger.is_event_enabled(EventKind::UpdatePoolState) {
+            return;
+        }
+        let reserves = pool.reserves.map(Float::from);
+        let (sqrt_price, liquidity) = if reserves.0.is_zero() {
+            (Float::zero(), Float::zero())
+        } else {
+            (
+                (reserves.1 / reserves.0).sqrt(),
+                (reserves.0 * reserves.1).sqrt(),
+            )
+        };
+        Self::log_single_level_pool_state(
+            pool_id,
+            pool.reserves,
+            sqrt_price,
+            liquidity,
+            logger,
+            reason,
+        );
+    }
+
+    /// Logs state of pool without fee levels, e.g. constant product or stable swap one,
+    /// in the same `update_pool_state` event as concentrated pools:
+    /// whole pool is reported as the lowest fee level
+    pub(super) fn log_single_level_pool_state(
+        pool_id: &PoolId,
+        reserves: (Amount, Amount),
+        sqrt_price: Float,
+        liquidity: Float,
+        logger: &mut dyn Logger,
+        reason: PoolUpdateReason,
+    ) {
+        let no_fees = [Amount::default(); NUM_FEE_LEVELS as usize];
+        logger.log_update_pool_state_event(
+            reason,
+            (&pool_id.0, &pool_id.1),
+            &lowest_level(reserves.0),
+            &lowest_level(reserves.1),
+            &lowest_level(sqrt_price),
+            &lowest_level(liquidity),
+            (&no_fees, &no_fees),
+            0,
+            Side::Left,
+            None,
+        );
+    }
+}
+
+/// Per-level array, with `value` at the lowest level and defaults elsewhere
+fn lowest_level<V: Default>(value: V) -> RawFeeLevelsArray<V> {
+    let mut values: RawFeeLevelsArray<V> = std::array::from_fn(|_| V::default());
+    values[0] = value;
+    values
+}
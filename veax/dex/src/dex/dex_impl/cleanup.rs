@@ -0,0 +1,116 @@
+//! Storage cleanup: removes leftover tick states and, for accounts which allowed it,
+//! token registrations with zero balance
+use super::Dex;
+use crate::chain::{AccountId, TokenId};
+use crate::dex::errors::Result;
+use crate::dex::{
+    Account, AccountWithdrawTracker, CleanupCursor, Contract, Map, Pool, PoolId, StateMembersMut,
+    StateMut, Types,
+};
+use crate::error_here;
+use num_traits::Zero;
+use std::borrow::BorrowMut;
+
+impl<T: Types, S: StateMut<T>, SS: BorrowMut<S>> Dex<T, S, SS> {
+    /// Remove pool's tick states which aren't referenced by any position.
+    /// Scan is resumable: each call continues from where previous one stopped,
+    /// so repeated calls eventually cover the whole pool and then start over.
+    ///
+    /// # Parameters
+    /// * `tokens` - pool's tokens
+    /// * `max_items` - maximal number of tick states to scan during this call
+    ///
+    /// # Returns
+    /// Cursor where next call will continue from, or `None` if scan is complete
+    pub fn cleanup_pool_storage(
+        &mut self,
+        tokens: (TokenId, TokenId),
+        max_items: usize,
+    ) -> Result<Option<CleanupCursor>> {
+        self.ensure_payable_api_resumed()?;
+        self.ensure_caller_is_guard()?;
+
+        let (pool_id, _) = PoolId::try_from_pair(tokens).map_err(|e| error_here!(e))?;
+        let Contract::V1(ref mut contract) = self.contract_mut();
+        let (removed, next_cursor) =
+            contract
+                .pools
+                .try_update(&pool_id, |Pool::V1(ref mut pool)| {
+                    let (removed, next_cursor) =
+                        pool.remove_orphaned_ticks(pool.cleanup_cursor, max_items)?;
+                    pool.cleanup_cursor = next_cursor;
+                    Ok((removed, next_cursor))
+                })?;
+
+        self.logger_mut()
+            .log_pool_storage_cleanup_event(&pool_id, &removed, next_cursor.as_ref());
+        Ok(next_cursor)
+    }
+
+    /// Unregister zero-balance tokens of accounts which allowed storage cleanup
+    ///
+    /// # Parameters
+    /// * `accounts` - accounts to clean up; ones which haven't allowed cleanup are skipped
+    /// * `max_items` - maximal number of token registrations to remove during this call
+    ///
+    /// # Returns
+    /// Number of removed token registrations
+    pub fn cleanup_token_registrations(
+        &mut self,
+        accounts: &[AccountId],
+        max_items: usize,
+    ) -> Result<usize> {
+        self.ensure_payable_api_resumed()?;
+        self.ensure_caller_is_guard()?;
+
+        let mut total_removed = 0;
+        for account_id in accounts {
+            if total_removed == max_items {
+                break;
+            }
+            let StateMembersMut {
+                contract: Contract::V1(ref mut contract),
+                logger,
+                ..
+            } = self.members_mut();
+            let Some(removed) = contract.accounts.update(account_id, |Account::V1(ref mut account)| {
+                if !account.registration_cleanup_allowed {
+                    return Ok(Vec::new());
+                }
+                let zero_balance_tokens = account
+                    .token_balances
+                    .iter()
+                    .filter(|(token_id, balance)| {
+                        balance.is_zero()
+                            && !account.withdraw_tracker.is_token_withdraw_in_progress(token_id)
+                    })
+                    .map(|(token_id, _)| TokenId::clone(&token_id))
+                    .take(max_items - total_removed)
+                    .collect::<Vec<_>>();
+                account.unregister_tokens(&zero_balance_tokens)?;
+                Ok(zero_balance_tokens)
+            }) else {
+                continue;
+            };
+            let removed = removed?;
+            if !removed.is_empty() {
+                logger.log_token_registrations_cleanup_event(account_id, &removed);
+            }
+            total_removed += removed.len();
+        }
+        Ok(total_removed)
+    }
+
+    /// Allow or disallow storage cleanup to unregister caller's tokens with zero balance
+    pub fn set_registration_cleanup_allowed(&mut self, allowed: bool) -> Result<()> {
+        self.ensure_payable_api_resumed()?;
+        let caller_id = self.get_caller_id();
+        let Contract::V1(ref mut contract) = self.contract_mut();
+        contract
+            .accounts
+            .try_update(&caller_id, |Account::V1(ref mut account)| {
+                account.registration_cleanup_allowed = allowed;
+                Ok(())
+            })
+    }
+}
@@ -0,0 +1,222 @@
+//! Deposit intents: batches of actions funded by several token transfers
+//!
+//! Wallet can't attach several tokens to single call, so user prepares intent which lists
+//! expected deposits and actions, then transfers each token with intent id in `ft_on_transfer`
+//! message. Each leg is credited to owner's deposits on arrival, and actions are executed
+//! on arrival of the last one. Legs which arrive after intent has expired are refunded,
+//! while ones received before stay in owner's deposits.
+use super::{ActionResult, Dex};
+use crate::chain::{AccountId, Amount, TokenId};
+use crate::dex::errors::{ErrorKind, Result};
+use crate::dex::traits::AccountExtra;
+use crate::dex::{
+    Account, Action, Contract, DepositIntent, DepositLeg, Map, MapRemoveKey, State,
+    StateMembersMut, StateMut, Types,
+};
+use crate::{ensure_here, error_here};
+use itertools::Itertools;
+use num_traits::Zero;
+use std::borrow::{Borrow, BorrowMut};
+
+/// Maximal number of deposit legs of single intent
+const MAX_INTENT_LEGS: usize = 4;
+/// Maximal number of actions of single intent
+const MAX_INTENT_ACTIONS: usize = 16;
+/// Time during which intent accepts deposits, in seconds
+const INTENT_LIFETIME: u64 = 60 * 60;
+
+impl<T: Types, S: State<T>, SS: Borrow<S>> Dex<T, S, SS> {
+    /// Deposit intent by id, if it's neither executed nor cancelled yet
+    pub fn get_deposit_intent(&self, intent_id: u64) -> Option<DepositIntent> {
+        let Contract::V1(ref contract) = self.contract();
+        contract
+            .deposit_intents
+            .inspect(&intent_id, DepositIntent::clone)
+    }
+
+    /// Deposit intents of account, with their ids
+    pub fn get_deposit_intents(&self, account_id: &AccountId) -> Vec<(u64, DepositIntent)> {
+        let Contract::V1(ref contract) = self.contract();
+        contract
+            .deposit_intents
+            .iter()
+            .filter(|(_, intent)| intent.owner == *account_id)
+            .map(|(id, intent)| (*id, DepositIntent::clone(&intent)))
+            .collect()
+    }
+}
+
+impl<T: Types, S: StateMut<T, SendTokensExtraParam = ()>, SS: BorrowMut<S>> Dex<T, S, SS> {
+    /// Prepare deposit intent of caller, to be funded by `ft_on_transfer` calls
+    ///
+    /// # Parameters
+    /// * `legs` - distinct tokens and amounts which must be deposited, up to `MAX_INTENT_LEGS`;
+    ///     all tokens must be registered by caller
+    /// * `actions` - actions executed once all legs arrive, up to `MAX_INTENT_ACTIONS`;
+    ///     can't contain `RegisterAccount` or `Deposit`
+    ///
+    /// # Returns
+    /// Id of prepared intent
+    pub fn prepare_deposit_intent(
+        &mut self,
+        legs: Vec<(TokenId, Amount)>,
+        actions: Vec<Action<()>>,
+    ) -> Result<u64> {
+        self.ensure_payable_api_resumed()?;
+        ensure_here!(
+            !legs.is_empty() && legs.len() <= MAX_INTENT_LEGS,
+            ErrorKind::InvalidParams
+        );
+        ensure_here!(
+            !actions.is_empty() && actions.len() <= MAX_INTENT_ACTIONS,
+            ErrorKind::InvalidParams
+        );
+        ensure_here!(
+            legs.iter().all(|(_, amount)| !amount.is_zero()),
+            ErrorKind::InvalidParams
+        );
+        ensure_here!(
+            legs.iter().map(|(token_id, _)| token_id).all_unique(),
+            ErrorKind::TokenDuplicates
+        );
+        ensure_here!(
+            !actions
+                .iter()
+                .any(|action| matches!(action, Action::RegisterAccount | Action::Deposit)),
+            ErrorKind::InvalidParams
+        );
+        self.ensure_tokens_not_banned(legs.iter().map(|(token_id, _)| token_id))?;
+        let owner = self.get_caller_id();
+        self.with_account_mut(&owner, |account_view| {
+            ensure_here!(
+                legs.iter().all(|(token_id, _)| account_view
+                    .account
+                    .token_balances
+                    .contains_key(token_id)),
+                ErrorKind::TokenNotRegistered
+            );
+            account_view.account.extra.on_deposit_intent_prepared()
+        })?;
+
+        let intent = DepositIntent {
+            owner,
+            legs: legs
+                .into_iter()
+                .map(|(token_id, amount)| DepositLeg {
+                    token_id,
+                    amount,
+                    received: Amount::zero(),
+                })
+                .collect(),
+            actions,
+            expires_at: self.get_block_timestamp() + INTENT_LIFETIME,
+        };
+        let Contract::V1(ref mut contract) = self.contract_mut();
+        let intent_id = contract.next_deposit_intent_id;
+        contract.next_deposit_intent_id += 1;
+        contract.deposit_intents.insert(intent_id, intent);
+        Ok(intent_id)
+    }
+
+    /// Cancel deposit intent; legs received so far stay in owner's deposits.
+    /// Can be called by owner, or by anyone once intent has expired
+    pub fn cancel_deposit_intent(&mut self, intent_id: u64) -> Result<()> {
+        self.ensure_payable_api_resumed()?;
+        let caller_id = self.get_caller_id();
+        let intent = {
+            let Contract::V1(ref contract) = self.contract();
+            contract
+                .deposit_intents
+                .try_inspect(&intent_id, DepositIntent::clone)?
+        };
+        ensure_here!(
+            intent.owner == caller_id || self.get_block_timestamp() > intent.expires_at,
+            ErrorKind::NotYourDepositIntent
+        );
+        self.remove_deposit_intent(intent_id, &intent.owner)
+    }
+
+    /// Credit tokens received by `ft_on_transfer` to leg of deposit intent,
+    /// and execute intent's actions if all legs are received
+    ///
+    /// # Parameters
+    /// * `sender_id` - sender of tokens, must be intent owner
+    ///
+    /// # Returns
+    /// Amount which should be refunded, i.e. the whole amount if intent has expired, or zero;
+    /// and results of withdrawals among executed actions
+    pub fn deposit_to_intent(
+        &mut self,
+        sender_id: &AccountId,
+        token_id: &TokenId,
+        amount: Amount,
+        intent_id: u64,
+    ) -> Result<(Amount, Vec<S::SendTokensResult>)> {
+        self.ensure_payable_api_resumed()?;
+        self.ensure_tokens_not_banned([token_id])?;
+        let mut intent = {
+            let Contract::V1(ref contract) = self.contract();
+            contract
+                .deposit_intents
+                .try_inspect(&intent_id, DepositIntent::clone)?
+        };
+        ensure_here!(intent.owner == *sender_id, ErrorKind::NotYourDepositIntent);
+        if self.get_block_timestamp() > intent.expires_at {
+            self.remove_deposit_intent(intent_id, sender_id)?;
+            return Ok((amount, Vec::new()));
+        }
+
+        let leg = intent
+            .legs
+            .iter_mut()
+            .find(|leg| leg.token_id == *token_id)
+            .ok_or_else(|| error_here!(ErrorKind::UnexpectedIntentDeposit))?;
+        ensure_here!(
+            amount <= leg.amount - leg.received,
+            ErrorKind::UnexpectedIntentDeposit
+        );
+        leg.received += amount;
+        let StateMembersMut {
+            contract: Contract::V1(ref mut contract),
+            logger,
+            ..
+        } = self.members_mut();
+        contract
+            .accounts
+            .try_update(sender_id, |Account::V1(ref mut account)| {
+                Self::deposit_impl(sender_id, account, token_id, amount, logger)
+            })?;
+        if !intent.is_complete() {
+            contract.deposit_intents.insert(intent_id, intent);
+            return Ok((Amount::zero(), Vec::new()));
+        }
+
+        self.remove_deposit_intent(intent_id, sender_id)?;
+        let results = self
+            .execute_actions_impl(
+                sender_id,
+                None,
+                |_, _, _| Err(error_here!(ErrorKind::UnexpectedRegisterAccount)),
+                intent.actions,
+            )?
+            .into_iter()
+            .filter_map(|r| {
+                if let ActionResult::Withdraw(Some(r)) = r {
+                    Some(r)
+                } else {
+                    None
+                }
+            })
+            .collect();
+        Ok((Amount::zero(), results))
+    }
+
+    fn remove_deposit_intent(&mut self, intent_id: u64, owner: &AccountId) -> Result<()> {
+        let Contract::V1(ref mut contract) = self.contract_mut();
+        contract.deposit_intents.remove(&intent_id);
+        self.with_account_mut(owner, |account_view| {
+            account_view.account.extra.on_deposit_intent_removed();
+            Ok(())
+        })
+    }
+}
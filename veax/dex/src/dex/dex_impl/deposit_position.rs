@@ -0,0 +1,104 @@
+//! Opening position directly from tokens received by `ft_on_transfer`
+//!
+//! Transferred token alone funds the position, optionally after swapping part of it
+//! into the other token of the pool. Part of the transfer which position doesn't take
+//! is refunded to sender, so that LPing needs just a single token transfer.
+use super::{ensure_not_banned, Dex};
+use crate::chain::{AccountId, Amount, TokenId};
+use crate::dex::errors::{ErrorKind, Result};
+#[cfg(feature = "near")]
+#[cfg(feature = "smart-routing")]
+use crate::dex::PoolId;
+use crate::dex::{Map, OpenPositionSingleSided, PositionId, PositionInit, Range, StateMut, Types};
+use crate::{ensure_here, error_here};
+use num_traits::Zero;
+use std::borrow::BorrowMut;
+
+impl<T: Types, S: StateMut<T>, SS: BorrowMut<S>> Dex<T, S, SS> {
+    /// Deposit tokens received by `ft_on_transfer` and open position with them
+    ///
+    /// # Parameters
+    /// * `sender_id` - sender of tokens, becomes position owner
+    /// * `token_id` - transferred token, one of pool tokens
+    /// * `amount` - transferred amount, which is the maximal amount position can take
+    /// * `directive` - pool, price range and slippage limits of position
+    ///
+    /// # Returns
+    /// Id of opened position and amount of transferred token which should be refunded.
+    /// Swap output which position doesn't take remains on sender's deposit.
+    pub fn deposit_open_position_single_sided(
+        &mut self,
+        sender_id: &AccountId,
+        token_id: &TokenId,
+        amount: Amount,
+        directive: OpenPositionSingleSided,
+    ) -> Result<(PositionId, Amount)> {
+        self.ensure_payable_api_resumed()?;
+        ensure_here!(!amount.is_zero(), ErrorKind::InvalidParams);
+        let OpenPositionSingleSided {
+            token_id: other_token_id,
+            fee_rate,
+            ticks_range,
+            min_amounts,
+            auto_balance,
+        } = directive;
+
+        let position = PositionInit {
+            amount_ranges: (
+                Range {
+                    min: min_amounts.0,
+                    max: amount.into(),
+                },
+                Range {
+                    min: min_amounts.1,
+                    max: Amount::zero().into(),
+                },
+            ),
+            ticks_range,
+            auto_balance,
+            locked_until: None,
+        };
+
+        let result = self.with_account_mut(sender_id, |mut account_view| {
+            ensure_not_banned::<T>(account_view.banned_tokens, [token_id, &other_token_id])?;
+            let balance = Self::deposit_impl(
+                sender_id,
+                account_view.account,
+                token_id,
+                amount,
+                account_view.logger,
+            )?;
+            let (position_id, ..) = Self::open_position_impl(
+                token_id,
+                &other_token_id,
+                fee_rate,
+                position,
+                &mut account_view,
+            )?;
+            let remaining = account_view
+                .account
+                .token_balances
+                .inspect(token_id, |balance| *balance)
+                .ok_or(error_here!(ErrorKind::InternalLogicError))?;
+            // Swap and position spend no more than was transferred,
+            // so whatever they didn't spend is refunded
+            let refund = amount - (balance - remaining);
+            if !refund.is_zero() {
+                let balance = account_view
+                    .account
+                    .withdraw(token_id, refund)
+                    .map_err(|e| error_here!(e))?;
+                account_view
+                    .logger
+                    .log_withdraw_event(sender_id, token_id, &refund, &balance);
+            }
+            Ok((position_id, refund))
+        })?;
+        #[cfg(feature = "near")]
+        #[cfg(feature = "smart-routing")]
+        self.update_top_pools([PoolId::try_from_pair((token_id.clone(), other_token_id))
+            .map_err(|e| error_here!(e))?
+            .0])?;
+        Ok(result)
+    }
+}
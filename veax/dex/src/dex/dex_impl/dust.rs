@@ -0,0 +1,73 @@
+//! Dust sweep: swaps tiny token balances into single token, so that dust tokens can be unregistered
+use super::Dex;
+use crate::chain::{Amount, TokenId};
+use crate::dex::errors::Result;
+use crate::dex::{Account, Contract, Map, PoolId, StateMut, Types};
+use crate::error_here;
+use itertools::Itertools;
+use num_traits::Zero;
+use std::borrow::BorrowMut;
+
+impl<T: Types, S: StateMut<T>, SS: BorrowMut<S>> Dex<T, S, SS> {
+    /// Swap caller's balances below `min_threshold` into `token_out`, through direct pools.
+    /// Tokens which aren't registered, or don't have pool with `token_out`, are skipped
+    ///
+    /// # Parameters
+    /// * `tokens` - tokens to sweep
+    /// * `min_threshold` - balances below this amount are considered dust
+    /// * `token_out` - token to receive
+    /// * `unregister` - whether to unregister swept tokens, as well as listed tokens with zero balance
+    ///
+    /// # Returns
+    /// Swept tokens with swapped amounts, and total amount of `token_out` received
+    pub fn sweep_dust(
+        &mut self,
+        tokens: &[TokenId],
+        min_threshold: Amount,
+        token_out: &TokenId,
+        unregister: bool,
+    ) -> Result<(Vec<(TokenId, Amount)>, Amount)> {
+        self.ensure_payable_api_resumed()?;
+        let caller_id = self.get_caller_id();
+
+        let mut swept = Vec::new();
+        let mut dust_tokens = Vec::new();
+        let mut amount_out = Amount::zero();
+        for token_id in tokens.iter().unique() {
+            if token_id == token_out {
+                continue;
+            }
+            let Contract::V1(ref contract) = self.contract();
+            let balance =
+                contract
+                    .accounts
+                    .try_inspect(&caller_id, |Account::V1(ref account)| {
+                        account.token_balances.inspect(token_id, |balance| *balance)
+                    })?;
+            let Some(balance) = balance.filter(|balance| *balance < min_threshold) else {
+                continue;
+            };
+            if !balance.is_zero() {
+                let (pool_id, _) = PoolId::try_from_pair((token_id.clone(), token_out.clone()))
+                    .map_err(|e| error_here!(e))?;
+                if !contract.pools.contains_key(&pool_id) {
+                    continue;
+                }
+                let (_, swap_amount_out) = self.swap_exact_in(
+                    &[token_id.clone(), token_out.clone()],
+                    balance,
+                    Amount::zero(),
+                    None,
+                )?;
+                amount_out += swap_amount_out;
+                swept.push((token_id.clone(), balance));
+            }
+            dust_tokens.push(token_id);
+        }
+
+        if unregister {
+            self.unregister_tokens(&caller_id, dust_tokens)?;
+        }
+        Ok((swept, amount_out))
+    }
+}
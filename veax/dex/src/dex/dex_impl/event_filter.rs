@@ -0,0 +1,36 @@
+//! Owner control over kinds of emitted events
+use super::Dex;
+use crate::dex::errors::Result;
+use crate::dex::{ConfigChange, Contract, EventKind, State, StateMut, Types};
+use std::borrow::{Borrow, BorrowMut};
+
+impl<T: Types, S: State<T>, SS: Borrow<S>> Dex<T, S, SS> {
+    pub fn is_event_enabled(&self, kind: EventKind) -> bool {
+        let Contract::V1(ref contract) = self.contract();
+        contract.event_filter.is_enabled(kind)
+    }
+
+    pub fn get_disabled_events(&self) -> Vec<EventKind> {
+        let Contract::V1(ref contract) = self.contract();
+        contract.event_filter.disabled_kinds()
+    }
+}
+
+impl<T: Types, S: StateMut<T>, SS: BorrowMut<S>> Dex<T, S, SS> {
+    /// Enable or disable events of specified kind, effective immediately
+    pub fn set_event_enabled(&mut self, kind: EventKind, enabled: bool) -> Result<()> {
+        self.ensure_caller_is_owner()?;
+        let Contract::V1(ref mut contract) = self.contract_mut();
+        let previous = contract.event_filter.set_enabled(kind, enabled);
+        let caller_id = self.get_caller_id();
+        self.logger_mut().log_config_change_event(
+            &caller_id,
+            &ConfigChange::EventEnabled {
+                event: kind,
+                previous,
+                new: enabled,
+            },
+        );
+        Ok(())
+    }
+}
@@ -0,0 +1,204 @@
+//! Dutch auctions which convert protocol fees into single quote token
+//!
+//! Anyone may move protocol fees of a pool into auctions, one per sold token.
+//! Price of sold token starts above its pool price and decays linearly towards end price;
+//! anyone may buy part or whole of the lot at current price, paying quote token from own deposits.
+//! Quote token goes to owner's deposits, as well as protocol fees which are already in quote token.
+use super::Dex;
+use crate::chain::{Amount, TokenId};
+use crate::dex::errors::{ErrorKind, Result};
+use crate::dex::util_types::PoolId;
+use crate::dex::utils::swap_if;
+use crate::dex::{
+    Account, AdminAction, ConfigChange, Contract, FeeAuction, FeeAuctionConfig, Float, Map,
+    MapRemoveKey, PairExt, State, StateMut, Types, BASIS_POINT_DIVISOR,
+};
+use crate::{ensure_here, error_here};
+use num_traits::Zero;
+use std::borrow::{Borrow, BorrowMut};
+
+impl<T: Types, S: State<T>, SS: Borrow<S>> Dex<T, S, SS> {
+    /// Parameters of protocol fee auctions; `None` if auctions are disabled
+    pub fn get_fee_auction_config(&self) -> Option<FeeAuctionConfig> {
+        let Contract::V1(ref contract) = self.contract();
+        contract.fee_auction_config.clone()
+    }
+
+    /// Running protocol fee auctions, with sold tokens
+    pub fn get_fee_auctions(&self) -> Vec<(TokenId, FeeAuction)> {
+        let Contract::V1(ref contract) = self.contract();
+        contract
+            .fee_auctions
+            .iter()
+            .map(|(token, auction)| (token.clone(), FeeAuction::clone(&auction)))
+            .collect()
+    }
+}
+
+impl<T: Types, S: StateMut<T>, SS: BorrowMut<S>> Dex<T, S, SS> {
+    /// Change parameters of protocol fee auctions, or disable them with `None`;
+    /// already running auctions keep their parameters
+    pub fn set_fee_auction_config(&mut self, config: Option<FeeAuctionConfig>) -> Result<()> {
+        self.ensure_payable_api_resumed()?;
+        self.ensure_caller_is_owner()?;
+        self.ensure_multisig_disabled()?;
+        self.ensure_timelock_disabled()?;
+        self.apply_admin_action(AdminAction::SetFeeAuctionConfig(config))
+    }
+
+    /// Change parameters of protocol fee auctions, without any permission checks
+    pub(super) fn set_fee_auction_config_unchecked(&mut self, config: Option<FeeAuctionConfig>) {
+        let Contract::V1(ref mut contract) = self.contract_mut();
+        let previous = std::mem::replace(&mut contract.fee_auction_config, config.clone());
+        let caller_id = self.get_caller_id();
+        self.logger_mut().log_config_change_event(
+            &caller_id,
+            &ConfigChange::FeeAuctionConfig {
+                previous,
+                new: config,
+            },
+        );
+    }
+
+    /// Move protocol fee accumulated by pool into auctions; may be called by anyone
+    ///
+    /// Fee in quote token is deposited onto owner's account. Fee in other token is added
+    /// to lot of its auction; if token has no auction or its auction has ended, auction
+    /// is (re)started at prices relative to current pool price of token in quote token.
+    ///
+    /// # Returns
+    /// Amounts of protocol fee taken from pool, ordered the same way as `tokens`
+    pub fn start_fee_auction(&mut self, tokens: (TokenId, TokenId)) -> Result<(Amount, Amount)> {
+        self.ensure_payable_api_resumed()?;
+        let Contract::V1(ref contract) = self.contract();
+        let config = contract
+            .fee_auction_config
+            .clone()
+            .ok_or(error_here!(ErrorKind::FeeAuctionsDisabled))?;
+        let (pool_id, swapped) = PoolId::try_from_pair(tokens).map_err(|e| error_here!(e))?;
+        let protocol_fees = self.take_protocol_fee(&pool_id)?;
+        self.logger_mut()
+            .log_withdraw_protocol_fee_event(pool_id.as_refs(), protocol_fees.as_refs());
+
+        let now = self.get_block_timestamp();
+        for (token, amount) in [(&pool_id.0, protocol_fees.0), (&pool_id.1, protocol_fees.1)] {
+            if amount.is_zero() {
+                continue;
+            }
+            if *token == config.quote_token {
+                let Contract::V1(ref mut contract) = self.contract_mut();
+                contract.accounts.try_update(
+                    &contract.owner_id,
+                    |Account::V1(ref mut account)| {
+                        account.deposit(token, amount).map_err(|e| error_here!(e))
+                    },
+                )?;
+                continue;
+            }
+            let Contract::V1(ref contract) = self.contract();
+            let auction = match contract.fee_auctions.inspect(token, FeeAuction::clone) {
+                Some(mut auction) if auction.ends_at > now => {
+                    auction.lot += amount;
+                    auction
+                }
+                Some(auction) => self.new_fee_auction(token, auction.lot + amount, &config, now)?,
+                None => self.new_fee_auction(token, amount, &config, now)?,
+            };
+            self.logger_mut()
+                .log_fee_auction_update_event(token, &auction);
+            let Contract::V1(ref mut contract) = self.contract_mut();
+            contract.fee_auctions.insert(token.clone(), auction);
+        }
+        Ok(swap_if(swapped, protocol_fees))
+    }
+
+    /// Buy `amount` of token sold in protocol fee auction at current price,
+    /// paying quote token from caller's deposits
+    ///
+    /// # Parameters
+    /// * `token` - sold token, which must be registered by caller
+    /// * `amount` - amount of sold token to buy, at most auction's lot
+    /// * `max_quote_amount` - maximal amount of quote token caller agrees to pay
+    ///
+    /// # Returns
+    /// Amount of quote token paid
+    pub fn take_fee_auction(
+        &mut self,
+        token: &TokenId,
+        amount: Amount,
+        max_quote_amount: Amount,
+    ) -> Result<Amount> {
+        self.ensure_payable_api_resumed()?;
+        let now = self.get_block_timestamp();
+        let Contract::V1(ref mut contract) = self.contract_mut();
+        let auction = contract.fee_auctions.try_update(token, |auction| {
+            ensure_here!(
+                !amount.is_zero() && amount <= auction.lot,
+                ErrorKind::InvalidParams
+            );
+            auction.lot -= amount;
+            Ok(auction.clone())
+        })?;
+        let quote_amount = Amount::try_from((Float::from(amount) * auction.price_at(now)).ceil())
+            .map_err(|e| error_here!(e))?;
+        ensure_here!(quote_amount <= max_quote_amount, ErrorKind::Slippage);
+        if auction.lot.is_zero() {
+            contract.fee_auctions.remove(token);
+        }
+
+        let user = self.get_caller_id();
+        self.with_account_mut(&user, |account_view| {
+            account_view
+                .account
+                .withdraw(&auction.quote_token, quote_amount)
+                .map_err(|e| error_here!(e))?;
+            account_view
+                .account
+                .deposit(token, amount)
+                .map_err(|e| error_here!(e))?;
+            Ok(())
+        })?;
+        let Contract::V1(ref mut contract) = self.contract_mut();
+        contract
+            .accounts
+            .try_update(&contract.owner_id, |Account::V1(ref mut account)| {
+                account
+                    .deposit(&auction.quote_token, quote_amount)
+                    .map_err(|e| error_here!(e))
+            })?;
+        self.logger_mut()
+            .log_fee_auction_take_event(&user, token, &auction, amount, quote_amount);
+        Ok(quote_amount)
+    }
+
+    /// Auction of `lot` of `token`, starting at `now`, with prices relative to current pool price
+    fn new_fee_auction(
+        &self,
+        token: &TokenId,
+        lot: Amount,
+        config: &FeeAuctionConfig,
+        now: u64,
+    ) -> Result<FeeAuction> {
+        let price = self.get_order_price(token, &config.quote_token)?;
+        let divisor = Float::from(BASIS_POINT_DIVISOR);
+        Ok(FeeAuction {
+            lot,
+            quote_token: config.quote_token.clone(),
+            start_price: price * Float::from(config.start_price_bps) / divisor,
+            end_price: price * Float::from(config.end_price_bps) / divisor,
+            started_at: now,
+            ends_at: now + config.duration_sec,
+        })
+    }
+}
+
+pub(super) fn validate_fee_auction_config(config: &Option<FeeAuctionConfig>) -> Result<()> {
+    if let Some(config) = config {
+        ensure_here!(config.duration_sec > 0, ErrorKind::InvalidParams);
+        ensure_here!(
+            config.end_price_bps > 0 && config.end_price_bps <= config.start_price_bps,
+            ErrorKind::InvalidParams
+        );
+    }
+    Ok(())
+}
@@ -0,0 +1,31 @@
+//! Fee level recommendation for liquidity providers' and traders' UIs
+use super::Dex;
+use crate::chain::{Amount, TokenId};
+use crate::dex::errors::{ErrorKind, Result};
+use crate::dex::{Contract, FeeLevelRecommendation, Pool, PoolId, State, Types};
+use crate::{ensure_here, error_here};
+use std::borrow::Borrow;
+
+impl<T: Types, S: State<T>, SS: Borrow<S>> Dex<T, S, SS> {
+    /// Recommend fee levels in the pool, based on per-level liquidity and LP fees earned so far
+    ///
+    /// # Parameters
+    /// * `tokens` - pool's tokens, as input and output tokens of the swap
+    /// * `amount` - amount of input token to swap
+    pub fn recommend_fee_level(
+        &self,
+        tokens: (TokenId, TokenId),
+        amount: Amount,
+    ) -> Result<FeeLevelRecommendation> {
+        let (pool_id, _) = PoolId::try_from_pair(tokens.clone()).map_err(|e| error_here!(e))?;
+        let side = pool_id.side(&tokens.0);
+        let Contract::V1(ref contract) = self.contract();
+        contract.pools.try_inspect(&pool_id, |Pool::V1(ref pool)| {
+            ensure_here!(pool.is_spot_price_set(), ErrorKind::InsufficientLiquidity);
+            Ok(FeeLevelRecommendation {
+                position_fee_level: pool.most_profitable_fee_level(),
+                swap_fee_level: pool.estimate_swap_fee_level(side, amount),
+            })
+        })?
+    }
+}
@@ -0,0 +1,54 @@
+//! Gas attached to cross-contract calls, tunable by owner
+//!
+//! Token contracts may change their gas consumption, e.g. on upgrade, so gas amounts
+//! are kept in contract state instead of being compiled in. Each amount is bounded,
+//! so misconfiguration can't make calls unable to complete or exceed gas limit of transaction.
+use super::Dex;
+use crate::dex::errors::{ErrorKind, Result};
+use crate::dex::{ConfigChange, Contract, GasConfig, State, StateMut, Types};
+use crate::ensure_here;
+use std::borrow::{Borrow, BorrowMut};
+
+/// Minimal gas attached to any cross-contract call, in Tgas
+const MIN_CALL_GAS: u64 = 5;
+/// Maximal gas attached to any cross-contract call, in Tgas
+const MAX_CALL_GAS: u64 = 100;
+
+impl<T: Types, S: State<T>, SS: Borrow<S>> Dex<T, S, SS> {
+    /// Gas attached to cross-contract calls, in Tgas
+    pub fn get_gas_config(&self) -> GasConfig {
+        let Contract::V1(ref contract) = self.contract();
+        contract.gas_config
+    }
+}
+
+impl<T: Types, S: StateMut<T>, SS: BorrowMut<S>> Dex<T, S, SS> {
+    /// Set gas attached to cross-contract calls; each amount must be within
+    /// `MIN_CALL_GAS..=MAX_CALL_GAS` Tgas
+    pub fn set_gas_config(&mut self, gas_config: GasConfig) -> Result<()> {
+        self.ensure_payable_api_resumed()?;
+        self.ensure_caller_is_owner()?;
+        let GasConfig {
+            ft_transfer,
+            resolve_transfer,
+            swap_hook,
+        } = gas_config;
+        for gas in [ft_transfer, resolve_transfer, swap_hook] {
+            ensure_here!(
+                (MIN_CALL_GAS..=MAX_CALL_GAS).contains(&gas),
+                ErrorKind::IllegalGasAmount
+            );
+        }
+        let Contract::V1(ref mut contract) = self.contract_mut();
+        let previous = std::mem::replace(&mut contract.gas_config, gas_config);
+        let caller_id = self.get_caller_id();
+        self.logger_mut().log_config_change_event(
+            &caller_id,
+            &ConfigChange::GasConfig {
+                previous,
+                new: gas_config,
+            },
+        );
+        Ok(())
+    }
+}
@@ -0,0 +1,68 @@
+//! Self-diagnostics of contract state for monitoring
+//!
+//! Only invariants which are cheap to verify are checked, so that the view fits into gas limit
+//! regardless of state size. Positions are checked page by page, to be sampled across calls.
+use super::Dex;
+use crate::dex::{Contract, HealthIssue, HealthReport, Map, Pool, Set, State, Types};
+use std::borrow::Borrow;
+
+impl<T: Types, S: State<T>, SS: Borrow<S>> Dex<T, S, SS> {
+    /// Verify cheap invariants of contract state
+    ///
+    /// # Parameters
+    /// * `from` - number of positions to skip before the checked page
+    /// * `limit` - maximal number of positions to check
+    pub fn health_check(&self, from: usize, limit: usize) -> HealthReport {
+        let Contract::V1(ref contract) = self.contract();
+        let mut issues = Vec::new();
+
+        let actual_pool_count = (contract.pools.len()
+            + contract.classic_pools.len()
+            + contract.stable_pools.len()) as u64;
+        if contract.pool_count != actual_pool_count {
+            issues.push(HealthIssue::PoolCountMismatch {
+                recorded: contract.pool_count,
+                actual: actual_pool_count,
+            });
+        }
+
+        let mut positions_checked = 0;
+        for (position_id, pool_id) in contract.position_to_pool_id.iter().skip(from).take(limit) {
+            positions_checked += 1;
+            let position_id = *position_id;
+            if position_id >= contract.next_free_position_id {
+                issues.push(HealthIssue::PositionIdNotIssued { position_id });
+            }
+            let contains_position = contract.pools.inspect(&*pool_id, |Pool::V1(ref pool)| {
+                pool.positions.contains_key(&position_id)
+            });
+            let pool = (pool_id.0.clone(), pool_id.1.clone());
+            match contains_position {
+                None => issues.push(HealthIssue::PositionPoolMissing { position_id, pool }),
+                Some(false) => issues.push(HealthIssue::PositionNotInPool { position_id, pool }),
+                Some(true) => {}
+            }
+        }
+
+        if contract.fee_auction_config.is_none() && !contract.fee_auctions.is_empty() {
+            issues.push(HealthIssue::FeeAuctionsWithoutConfig {
+                auctions: contract.fee_auctions.len() as u64,
+            });
+        }
+
+        let admins = contract.admins.len() as u64;
+        if u64::from(contract.admin_threshold) > admins {
+            issues.push(HealthIssue::AdminThresholdUnreachable {
+                threshold: contract.admin_threshold,
+                admins,
+            });
+        }
+
+        HealthReport {
+            suspended: contract.suspended,
+            withdraw_limits_disabled: contract.withdraw_limits_disabled,
+            positions_checked,
+            issues,
+        }
+    }
+}
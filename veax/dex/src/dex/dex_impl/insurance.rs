@@ -0,0 +1,107 @@
+//! Insurance reserve: part of protocol fees is set aside per token,
+//! to reimburse users after accounting bugs
+use super::Dex;
+use crate::chain::{AccountId, Amount, TokenId, UIntBig};
+use crate::dex::errors::{ErrorKind, Result};
+use crate::dex::{
+    Account, BasisPoints, ConfigChange, Contract, Map, State, StateMembersMut, StateMut, Types,
+    BASIS_POINT_DIVISOR,
+};
+use crate::ensure_here;
+use num_traits::Zero;
+use std::borrow::{Borrow, BorrowMut};
+
+impl<T: Types, S: State<T>, SS: Borrow<S>> Dex<T, S, SS> {
+    /// Fraction of protocol fee which goes to insurance reserve, in basis points
+    pub fn get_insurance_fee_fraction(&self) -> BasisPoints {
+        let Contract::V1(ref contract) = self.contract();
+        contract.insurance_fee_fraction
+    }
+
+    /// Amounts of tokens kept in insurance reserve
+    pub fn get_insurance_reserve(&self) -> Vec<(TokenId, Amount)> {
+        let Contract::V1(ref contract) = self.contract();
+        contract
+            .insurance_reserve
+            .iter()
+            .map(|(token_id, amount)| (TokenId::clone(&token_id), *amount))
+            .collect()
+    }
+}
+
+impl<T: Types, S: StateMut<T>, SS: BorrowMut<S>> Dex<T, S, SS> {
+    /// Set fraction of protocol fee which goes to insurance reserve when protocol fee is withdrawn
+    pub fn set_insurance_fee_fraction(
+        &mut self,
+        insurance_fee_fraction: BasisPoints,
+    ) -> Result<()> {
+        self.ensure_payable_api_resumed()?;
+        self.ensure_caller_is_owner()?;
+        ensure_here!(
+            insurance_fee_fraction <= BASIS_POINT_DIVISOR,
+            ErrorKind::IllegalFee
+        );
+        let Contract::V1(ref mut contract) = self.contract_mut();
+        let previous =
+            std::mem::replace(&mut contract.insurance_fee_fraction, insurance_fee_fraction);
+        let caller_id = self.get_caller_id();
+        self.logger_mut().log_config_change_event(
+            &caller_id,
+            &ConfigChange::InsuranceFeeFraction {
+                previous,
+                new: insurance_fee_fraction,
+            },
+        );
+        Ok(())
+    }
+
+    /// Transfer tokens from insurance reserve to internal balance of specified account,
+    /// e.g. to reimburse user affected by accounting bug.
+    /// Allowed only while payable API is suspended, i.e. during incident handling.
+    /// Credited amount is reported as regular deposit of `receiver_id`
+    ///
+    /// # Parameters
+    /// * `token_id` - token to withdraw
+    /// * `amount` - amount to withdraw; zero withdraws whole reserve of the token
+    /// * `receiver_id` - account which receives tokens; token must be registered for it
+    ///
+    /// # Returns
+    /// Actually withdrawn amount
+    pub fn withdraw_insurance_reserve(
+        &mut self,
+        token_id: &TokenId,
+        amount: Amount,
+        receiver_id: &AccountId,
+    ) -> Result<Amount> {
+        self.ensure_caller_is_owner()?;
+        let StateMembersMut {
+            contract: Contract::V1(ref mut contract),
+            logger,
+            ..
+        } = self.members_mut();
+        ensure_here!(contract.suspended, ErrorKind::PayableAPINotSuspended);
+
+        let amount = contract.insurance_reserve.try_update(token_id, |reserve| {
+            let amount = if amount.is_zero() { *reserve } else { amount };
+            ensure_here!(
+                !amount.is_zero() && amount <= *reserve,
+                ErrorKind::InsufficientInsuranceReserve
+            );
+            *reserve -= amount;
+            Ok(amount)
+        })?;
+        contract
+            .accounts
+            .try_update(receiver_id, |Account::V1(ref mut account)| {
+                Self::deposit_impl(receiver_id, account, token_id, amount, logger)
+            })?;
+        Ok(amount)
+    }
+}
+
+/// Part of protocol fee `amount` which goes to insurance reserve
+pub(super) fn insurance_share(amount: Amount, insurance_fee_fraction: BasisPoints) -> Amount {
+    let share = UIntBig::from(amount) * UIntBig::from(insurance_fee_fraction)
+        / UIntBig::from(BASIS_POINT_DIVISOR);
+    share.low_u128()
+}
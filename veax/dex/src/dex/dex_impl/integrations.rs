@@ -0,0 +1,100 @@
+//! Registry of integrations, e.g. frontends, which swaps may be attributed to
+//!
+//! Integrators self-register and get an id. Swap may carry id of integration which routed it,
+//! and the id is echoed in swap event, so volume can be attributed using on-chain data only.
+use super::Dex;
+use crate::dex::errors::{ErrorKind, Result};
+use crate::dex::traits::AccountExtra;
+use crate::dex::{state_types, Contract, Integration, IntegrationId, Map, State, StateMut, Types};
+use crate::ensure_here;
+use std::borrow::{Borrow, BorrowMut};
+
+/// Maximal length of integration name, in bytes
+const MAX_INTEGRATION_NAME_LEN: usize = 64;
+/// Maximal length of integration metadata, in bytes
+const MAX_INTEGRATION_METADATA_LEN: usize = 1024;
+
+impl<T: Types, S: State<T>, SS: Borrow<S>> Dex<T, S, SS> {
+    /// Integration by id, if registered
+    pub fn get_integration(&self, id: IntegrationId) -> Option<Integration> {
+        let Contract::V1(ref contract) = self.contract();
+        contract.integrations.inspect(&id, Integration::clone)
+    }
+
+    /// Page of registered integrations with their ids
+    pub fn get_integrations(&self, from: usize, limit: usize) -> Vec<(IntegrationId, Integration)> {
+        let Contract::V1(ref contract) = self.contract();
+        contract
+            .integrations
+            .iter()
+            .skip(from)
+            .take(limit)
+            .map(|(id, integration)| (*id, Integration::clone(&integration)))
+            .collect()
+    }
+
+    /// Fails with `IntegrationNotFound` if swap is attributed to unregistered integration
+    pub(super) fn ensure_integration_registered(
+        &self,
+        integration_id: Option<IntegrationId>,
+    ) -> Result<()> {
+        let Contract::V1(ref contract) = self.contract();
+        ensure_integration_registered::<T>(&contract.integrations, integration_id)
+    }
+}
+
+impl<T: Types, S: StateMut<T>, SS: BorrowMut<S>> Dex<T, S, SS> {
+    /// Register integration owned by caller; its storage is paid from caller's storage deposit
+    ///
+    /// # Parameters
+    /// * `name` - non-empty name, up to `MAX_INTEGRATION_NAME_LEN` bytes
+    /// * `metadata` - free-form description, up to `MAX_INTEGRATION_METADATA_LEN` bytes
+    ///
+    /// # Returns
+    /// Id of registered integration
+    pub fn register_integration(
+        &mut self,
+        name: String,
+        metadata: String,
+    ) -> Result<IntegrationId> {
+        self.ensure_payable_api_resumed()?;
+        ensure_here!(
+            !name.is_empty() && name.len() <= MAX_INTEGRATION_NAME_LEN,
+            ErrorKind::InvalidParams
+        );
+        ensure_here!(
+            metadata.len() <= MAX_INTEGRATION_METADATA_LEN,
+            ErrorKind::InvalidParams
+        );
+        let owner = self.get_caller_id();
+        self.with_account_mut(&owner, |account_view| {
+            account_view.account.extra.on_integration_registered()
+        })?;
+        let Contract::V1(ref mut contract) = self.contract_mut();
+        let id = contract.next_integration_id;
+        contract.next_integration_id += 1;
+        contract.integrations.insert(
+            id,
+            Integration {
+                owner,
+                name,
+                metadata,
+            },
+        );
+        Ok(id)
+    }
+}
+
+/// Fails with `IntegrationNotFound` if `integration_id` is set, but isn't registered
+pub(super) fn ensure_integration_registered<T: Types>(
+    integrations: &state_types::IntegrationsMap<T>,
+    integration_id: Option<IntegrationId>,
+) -> Result<()> {
+    if let Some(id) = integration_id {
+        ensure_here!(
+            integrations.contains_key(&id),
+            ErrorKind::IntegrationNotFound
+        );
+    }
+    Ok(())
+}
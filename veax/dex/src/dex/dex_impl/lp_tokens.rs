@@ -0,0 +1,368 @@
+//! LP share tokens of managed full-range positions, see `LpTokenId`
+//!
+//! Each fee level of concentrated pool may have single full-range position owned by DEX itself,
+//! which liquidity is represented by fungible LP token. Position is closed and reopened
+//! on every mint and burn, so its fees are compounded. It's never reported by position events:
+//! LP token events and pool state updates describe its changes instead.
+use super::{ensure_not_banned, fee_rates_ticks, Dex};
+use crate::chain::{AccountId, Amount, TokenId};
+use crate::dex::errors::{ErrorKind, Result};
+use crate::dex::util_types::PoolId;
+use crate::dex::utils::swap_if;
+use crate::dex::{
+    state_types, Account, BasisPoints, Contract, FeeLevel, Float, ItemFactory, LpToken, LpTokenId,
+    LpTokenV0, Map, MapRemoveKey, Pool, PoolUpdateReason, PoolV1, PositionInit, State,
+    StateMembersMut, StateMut, Types,
+};
+use crate::{ensure_here, error_here};
+use num_traits::Zero;
+use std::borrow::{Borrow, BorrowMut};
+
+impl<T: Types, S: State<T>, SS: Borrow<S>> Dex<T, S, SS> {
+    /// Identifiers of all LP tokens which were ever minted
+    pub fn get_lp_token_ids(&self) -> Vec<LpTokenId> {
+        let Contract::V1(ref contract) = self.contract();
+        contract
+            .lp_tokens
+            .iter()
+            .map(|(token_id, _)| token_id.clone())
+            .collect()
+    }
+
+    /// Total supply of LP token, including locked part; zero if token was never minted
+    pub fn get_lp_token_supply(&self, token_id: &LpTokenId) -> Amount {
+        let Contract::V1(ref contract) = self.contract();
+        contract
+            .lp_tokens
+            .inspect(token_id, |LpToken::V0(ref lp_token)| lp_token.total_supply)
+            .unwrap_or_default()
+    }
+
+    /// LP tokens owned by account
+    pub fn get_lp_token_balance(&self, token_id: &LpTokenId, account_id: &AccountId) -> Amount {
+        let Contract::V1(ref contract) = self.contract();
+        contract
+            .lp_tokens
+            .inspect(token_id, |LpToken::V0(ref lp_token)| {
+                lp_token.balance_of(account_id)
+            })
+            .unwrap_or_default()
+    }
+}
+
+impl<T: Types, S: StateMut<T>, SS: BorrowMut<S>> Dex<T, S, SS> {
+    /// Deposit liquidity into managed full-range position of pool's fee level,
+    /// minting LP tokens to caller
+    ///
+    /// # Parameters
+    /// * `tokens` - pair of tokens of existing concentrated pool
+    /// * `fee_rate` - fee rate of position's level, one of `fee_rates_ticks`
+    /// * `max_amounts` - maximal amounts to deposit, ordered the same way as `tokens`;
+    ///     first deposit is taken in ratio of current pool price
+    ///
+    /// # Returns
+    /// Actually deposited amounts, ordered the same way as `tokens`, and minted LP tokens
+    pub fn mint_lp_tokens(
+        &mut self,
+        tokens: (TokenId, TokenId),
+        fee_rate: BasisPoints,
+        max_amounts: (Amount, Amount),
+    ) -> Result<(Amount, Amount, Amount)> {
+        self.ensure_payable_api_resumed()?;
+        let (pool_id, swapped) = PoolId::try_from_pair(tokens).map_err(|e| error_here!(e))?;
+        let token_id = LpTokenId {
+            pool_id,
+            fee_level: fee_level_of(fee_rate)?,
+        };
+        let max_amounts = swap_if(swapped, max_amounts);
+        let user = self.get_caller_id();
+        let StateMembersMut {
+            contract: Contract::V1(ref mut contract),
+            item_factory,
+            logger,
+        } = self.members_mut();
+        ensure_not_banned::<T>(
+            &contract.banned_tokens,
+            [&token_id.pool_id.0, &token_id.pool_id.1],
+        )?;
+        if !contract.lp_tokens.contains_key(&token_id) {
+            let lp_token = item_factory.new_lp_token();
+            contract.lp_tokens.insert(token_id.clone(), lp_token);
+        }
+
+        let min_liquidity = contract.min_position_liquidity;
+        let (amounts, minted) =
+            contract
+                .lp_tokens
+                .try_update(&token_id, |LpToken::V0(ref mut lp_token)| {
+                    contract
+                        .pools
+                        .try_update(&token_id.pool_id, |Pool::V1(ref mut pool)| {
+                            Self::unwind_lp_position(pool, lp_token)?;
+                            let (amounts, minted) = if lp_token.total_supply.is_zero() {
+                                // Position is opened with whole deposit first,
+                                // to find out amounts it takes at current price
+                                lp_token.idle_amounts = max_amounts;
+                                Self::rewind_lp_position(
+                                    pool,
+                                    lp_token,
+                                    &token_id,
+                                    &mut contract.next_free_position_id,
+                                    &mut contract.position_to_pool_id,
+                                    min_liquidity,
+                                    item_factory,
+                                )?;
+                                let untaken = std::mem::take(&mut lp_token.idle_amounts);
+                                let deposited =
+                                    (max_amounts.0 - untaken.0, max_amounts.1 - untaken.1);
+                                lp_token.mint(&user, (Amount::zero(), Amount::zero()), deposited)?
+                            } else {
+                                let assets = lp_token.idle_amounts;
+                                let (amounts, minted) =
+                                    lp_token.mint(&user, assets, max_amounts)?;
+                                lp_token.idle_amounts =
+                                    (assets.0 + amounts.0, assets.1 + amounts.1);
+                                Self::rewind_lp_position(
+                                    pool,
+                                    lp_token,
+                                    &token_id,
+                                    &mut contract.next_free_position_id,
+                                    &mut contract.position_to_pool_id,
+                                    min_liquidity,
+                                    item_factory,
+                                )?;
+                                (amounts, minted)
+                            };
+                            Self::log_pool_v0_state(
+                                &token_id.pool_id,
+                                pool,
+                                None,
+                                logger,
+                                PoolUpdateReason::AddLiquidity,
+                            );
+                            Ok((amounts, minted))
+                        })
+                })?;
+
+        contract
+            .accounts
+            .try_update(&user, |Account::V1(ref mut account)| {
+                account
+                    .withdraw(&token_id.pool_id.0, amounts.0)
+                    .map_err(|e| error_here!(e))?;
+                account
+                    .withdraw(&token_id.pool_id.1, amounts.1)
+                    .map_err(|e| error_here!(e))?;
+                Ok(())
+            })?;
+        logger.log_lp_token_mint_event(&user, &token_id, amounts, minted);
+        #[cfg(feature = "near")]
+        #[cfg(feature = "smart-routing")]
+        self.update_top_pools([token_id.pool_id])?;
+
+        let amounts = swap_if(swapped, amounts);
+        Ok((amounts.0, amounts.1, minted))
+    }
+
+    /// Burn caller's LP tokens, withdrawing proportional part of managed position,
+    /// with its compounded fees, onto caller's deposits
+    ///
+    /// # Returns
+    /// Withdrawn amounts, ordered as pool tokens
+    pub fn burn_lp_tokens(
+        &mut self,
+        token_id: &LpTokenId,
+        amount: Amount,
+    ) -> Result<(Amount, Amount)> {
+        self.ensure_payable_api_resumed()?;
+        let user = self.get_caller_id();
+        let StateMembersMut {
+            contract: Contract::V1(ref mut contract),
+            item_factory,
+            logger,
+        } = self.members_mut();
+
+        let min_liquidity = contract.min_position_liquidity;
+        let amounts =
+            contract
+                .lp_tokens
+                .try_update(token_id, |LpToken::V0(ref mut lp_token)| {
+                    contract.pools.try_update_or(
+                        &token_id.pool_id,
+                        // Inconsistent state: LP token exists, but its pool doesn't
+                        ErrorKind::InternalLogicError,
+                        |Pool::V1(ref mut pool)| {
+                            Self::unwind_lp_position(pool, lp_token)?;
+                            let assets = lp_token.idle_amounts;
+                            let amounts = lp_token.burn(&user, assets, amount)?;
+                            lp_token.idle_amounts = (assets.0 - amounts.0, assets.1 - amounts.1);
+                            if lp_token.has_holders() {
+                                Self::rewind_lp_position(
+                                    pool,
+                                    lp_token,
+                                    token_id,
+                                    &mut contract.next_free_position_id,
+                                    &mut contract.position_to_pool_id,
+                                    min_liquidity,
+                                    item_factory,
+                                )?;
+                            } else if let Some(position_id) = lp_token.position_id.take() {
+                                // Only locked supply is left, so it keeps remaining assets idle
+                                contract.position_to_pool_id.remove(&position_id);
+                            }
+                            Self::log_pool_v0_state(
+                                &token_id.pool_id,
+                                pool,
+                                None,
+                                logger,
+                                PoolUpdateReason::RemoveLiquidity,
+                            );
+                            Ok(amounts)
+                        },
+                    )
+                })?;
+
+        contract
+            .accounts
+            .try_update(&user, |Account::V1(ref mut account)| {
+                account
+                    .deposit(&token_id.pool_id.0, amounts.0)
+                    .map_err(|e| error_here!(e))?;
+                account
+                    .deposit(&token_id.pool_id.1, amounts.1)
+                    .map_err(|e| error_here!(e))?;
+                Ok(())
+            })?;
+        logger.log_lp_token_burn_event(&user, token_id, amounts, amount);
+        #[cfg(feature = "near")]
+        #[cfg(feature = "smart-routing")]
+        self.update_top_pools([token_id.pool_id.clone()])?;
+        Ok(amounts)
+    }
+
+    /// Transfer caller's LP tokens to another registered account
+    pub fn transfer_lp_tokens(
+        &mut self,
+        token_id: &LpTokenId,
+        receiver_id: &AccountId,
+        amount: Amount,
+        memo: Option<&str>,
+    ) -> Result<()> {
+        self.ensure_payable_api_resumed()?;
+        let sender_id = self.get_caller_id();
+        self.transfer_lp_tokens_unchecked(token_id, &sender_id, receiver_id, amount, memo)
+    }
+
+    /// Return part of LP tokens transferred with notification, which receiver reported
+    /// as unused, back to sender, as long as receiver still holds them and sender is registered
+    ///
+    /// # Returns
+    /// Amount of LP tokens which remain transferred
+    pub fn resolve_lp_token_transfer(
+        &mut self,
+        token_id: &LpTokenId,
+        sender_id: &AccountId,
+        receiver_id: &AccountId,
+        amount: Amount,
+        unused_amount: Amount,
+    ) -> Result<Amount> {
+        let Contract::V1(ref contract) = self.contract();
+        if !contract.accounts.contains_key(sender_id) {
+            return Ok(amount);
+        }
+        let refund = unused_amount
+            .min(amount)
+            .min(self.get_lp_token_balance(token_id, receiver_id));
+        if !refund.is_zero() {
+            self.transfer_lp_tokens_unchecked(
+                token_id,
+                receiver_id,
+                sender_id,
+                refund,
+                Some("refund"),
+            )?;
+        }
+        Ok(amount - refund)
+    }
+
+    /// Transfer LP tokens between accounts, without checking caller
+    fn transfer_lp_tokens_unchecked(
+        &mut self,
+        token_id: &LpTokenId,
+        sender_id: &AccountId,
+        receiver_id: &AccountId,
+        amount: Amount,
+        memo: Option<&str>,
+    ) -> Result<()> {
+        ensure_here!(sender_id != receiver_id, ErrorKind::InvalidParams);
+        let StateMembersMut {
+            contract: Contract::V1(ref mut contract),
+            logger,
+            ..
+        } = self.members_mut();
+        ensure_here!(
+            contract.accounts.contains_key(receiver_id),
+            ErrorKind::AccountNotRegistered
+        );
+        contract
+            .lp_tokens
+            .try_update(token_id, |LpToken::V0(ref mut lp_token)| {
+                lp_token.transfer(sender_id, receiver_id, amount)
+            })?;
+        logger.log_lp_token_transfer_event(sender_id, receiver_id, token_id, amount, memo);
+        Ok(())
+    }
+
+    /// Close managed position of LP token, if it's open,
+    /// adding its balance and harvested fees to idle amounts
+    fn unwind_lp_position(pool: &mut PoolV1<T>, lp_token: &mut LpTokenV0<T>) -> Result<()> {
+        if let Some(position_id) = lp_token.position_id {
+            let (fees, amounts) = pool.withdraw_fee_and_close_position(position_id)?;
+            lp_token.idle_amounts.0 += fees.0 + amounts.0;
+            lp_token.idle_amounts.1 += fees.1 + amounts.1;
+        }
+        Ok(())
+    }
+
+    /// Open managed position of LP token with its idle amounts;
+    /// amounts which position doesn't take at current price remain idle
+    fn rewind_lp_position(
+        pool: &mut PoolV1<T>,
+        lp_token: &mut LpTokenV0<T>,
+        token_id: &LpTokenId,
+        next_free_position_id: &mut u64,
+        position_to_pool_id: &mut state_types::PositionToPoolMap<T>,
+        min_liquidity: Float,
+        factory: &mut dyn ItemFactory<T>,
+    ) -> Result<()> {
+        let position_id = if let Some(position_id) = lp_token.position_id {
+            position_id
+        } else {
+            let position_id = *next_free_position_id;
+            *next_free_position_id += 1;
+            position_to_pool_id.insert(position_id, token_id.pool_id.clone());
+            lp_token.position_id = Some(position_id);
+            position_id
+        };
+        let idle = lp_token.idle_amounts;
+        let (deposited, _) = pool.open_position(
+            PositionInit::new_full_range(Amount::zero(), idle.0, Amount::zero(), idle.1),
+            token_id.fee_level,
+            position_id,
+            min_liquidity,
+            factory,
+        )?;
+        lp_token.idle_amounts = (idle.0 - deposited.0, idle.1 - deposited.1);
+        Ok(())
+    }
+}
+
+/// Fee level of fee rate, which must be one of `fee_rates_ticks`
+fn fee_level_of(fee_rate: BasisPoints) -> Result<FeeLevel> {
+    fee_rates_ticks()
+        .iter()
+        .position(|rate| *rate == fee_rate)
+        .ok_or(error_here!(ErrorKind::IllegalFee))?
+        .try_into()
+        .map_err(|_| error_here!(ErrorKind::ConvOverflow))
+}
@@ -1,36 +1,97 @@
 use super::errors::{ErrorKind, Result};
+use super::tick::Tick;
 use super::traits::AccountExtra;
-use super::util_types::{PoolId, Side};
+use super::util_types::{PoolId, PoolStorageDeposit, Side};
 use super::utils::swap_if;
 use super::{
-    state_types, Account, AccountLatest, AccountV0, AccountWithdrawTracker, Action, BasisPoints,
-    Contract, Exact, FeeLevel, ItemFactory, Logger, Map, MapRemoveKey, Pool, PoolInfo, PoolV0,
-    PositionId, PositionInfo, PositionInit, Range, Set, State, StateMembersMut, StateMut,
-    SwapAction, Types, VersionInfo, BASIS_POINT_DIVISOR,
+    state_types, Account, AccountLatest, AccountV1, AccountWithdrawTracker, Action, AdminAction,
+    BasisPoints, ClassicPool, Contract, EventKind, Exact, FeeLevel, IntegrationId, ItemFactory,
+    Logger, Map, MapRemoveKey, Pool, PoolFeeAccumulators, PoolInfo, PoolKind, PoolPricingState,
+    PoolV1, PositionId, PositionInfo, PositionInit, Range, SeedPeriodConfig, Set, StablePool,
+    State, StateMembersMut, StateMut, SwapAction, SwapHook, SwapNotification, Types, VersionInfo,
+};
+use crate::chain::{AccountId, Amount, AmountUFP, Liquidity, TokenId};
+use crate::dex::latest::{
+    liquidity_from_net_liquidity, PoolStats, RawFeeLevelsArray, NUM_FEE_LEVELS,
 };
-use crate::chain::{AccountId, Amount, Liquidity, TokenId};
-use crate::dex::latest::{RawFeeLevelsArray, NUM_FEE_LEVELS};
 use crate::dex::{PairExt, PoolUpdateReason};
-use crate::{ensure_here, error_here};
+use crate::{ensure_here, error_here, gas_checkpoint};
 use itertools::Itertools;
 #[allow(unused)] // Some impls use it, some don't
 use num_traits::{One, Zero};
 use std::borrow::{Borrow, BorrowMut};
-use std::cell::RefCell;
 use std::marker::PhantomData;
 use std::ops::{Deref, DerefMut};
 
-#[cfg(feature = "smart-routing")]
-use super::latest::NUM_TOP_POOLS;
-#[cfg(feature = "test-utils")]
+#[cfg(feature = "near")]
+use super::digest::{DigestBuilder, StateDigest};
 use super::Float;
 #[cfg(feature = "near")]
 use super::Path;
 #[cfg(feature = "near")]
+use super::Position;
+#[cfg(feature = "near")]
 #[cfg(feature = "smart-routing")]
 use crate::chain::FixedPointBig;
+
+mod admin_log;
+mod auto_harvest;
+mod banned_tokens;
+#[cfg(feature = "batch-auction")]
+mod batch_auction;
+mod classic_pool;
+mod cleanup;
+mod deposit_intents;
+mod deposit_position;
+mod dust;
+mod event_filter;
+mod fee_auction;
+mod fee_recommendation;
+mod gas_config;
+mod health_check;
+mod insurance;
+mod integrations;
+#[cfg(feature = "lp-tokens")]
+mod lp_tokens;
+mod multisig;
+mod orders;
+mod pending_withdrawals;
+mod pool_aliases;
+mod pool_price_init;
+mod pool_storage;
+mod position_estimate;
+mod position_limits;
+#[cfg(feature = "near")]
+mod price_curve;
+mod price_reference;
+mod reconcile;
+#[cfg(feature = "near")]
+#[cfg(feature = "smart-routing")]
+mod route_cache;
+mod seed_period;
+mod session_keys;
+mod stable_pool;
+mod swap_guard;
+mod timelock;
+mod token_interface;
+#[cfg(feature = "near")]
 #[cfg(feature = "smart-routing")]
-use std::collections::{BTreeSet, HashMap};
+mod top_pools;
+#[cfg(feature = "tracing")]
+mod trace_verbosity;
+mod transfer;
+mod tvl;
+mod upgrade;
+mod vault;
+mod view_access;
+mod withdraw_limits;
+
+use banned_tokens::ensure_not_banned;
+use insurance::insurance_share;
+use integrations::ensure_integration_registered;
+use swap_guard::{charge_extra_fee, check_swap_guard};
+pub use timelock::MAX_TIMELOCK_DELAY;
+use token_interface::ensure_interfaces_implemented;
 
 pub const fn fee_levels() -> RawFeeLevelsArray<FeeLevel> {
     // TODO: any way to tell compiler that is's a 0..7 range, to facilitate optimizations?
@@ -57,6 +118,9 @@ enum ActionResult<S> {
     OpenPosition,
     ClosePosition,
     WithdrawFee,
+    WithdrawAllFees,
+    TransferInternal,
+    SwapAndWithdraw(Amount, Option<S>),
 }
 
 pub struct Dex<T, S, SS> {
@@ -81,6 +145,12 @@ impl<T: Types, S: State<T>, SS: BorrowMut<S>> DerefMut for Dex<T, S, SS> {
 
 impl<T: Types, S: State<T>, SS: Borrow<S>> Dex<T, S, SS> {
     pub fn new(state: SS) -> Self {
+        #[cfg(feature = "tracing")]
+        {
+            let state_ref: &S = state.borrow();
+            let Contract::V1(ref contract) = state_ref.contract();
+            super::tracing::set_verbosity(contract.trace_verbosity);
+        }
         Self {
             state,
             _phantom_s: PhantomData,
@@ -88,14 +158,77 @@ impl<T: Types, S: State<T>, SS: Borrow<S>> Dex<T, S, SS> {
         }
     }
 }
+/// Per-level pool state, in the form reported by `update_pool_state` event
+struct PoolLevelsReport {
+    amounts_a: RawFeeLevelsArray<Amount>,
+    amounts_b: RawFeeLevelsArray<Amount>,
+    spot_sqrtprices: RawFeeLevelsArray<Float>,
+    liquidities: RawFeeLevelsArray<Float>,
+}
+
+impl PoolLevelsReport {
+    fn new<T: Types>(pool: &PoolV1<T>) -> Self {
+        let position_reserves = pool.position_reserves();
+        Self {
+            amounts_a: position_reserves.map(|(left, _right)| Amount::try_from(left).unwrap()),
+            amounts_b: position_reserves.map(|(_left, right)| Amount::try_from(right).unwrap()),
+            spot_sqrtprices: pool.spot_sqrtprices(Side::Right),
+            liquidities: pool
+                .liquidities()
+                .map(|liq| liq.try_into().unwrap_or_default()),
+        }
+    }
+
+    fn differs_at(&self, other: &Self, level: usize) -> bool {
+        self.amounts_a[level] != other.amounts_a[level]
+            || self.amounts_b[level] != other.amounts_b[level]
+            || self.spot_sqrtprices[level] != other.spot_sqrtprices[level]
+            || self.liquidities[level] != other.liquidities[level]
+    }
+}
+
+/// Pool state captured before update which can produce LP fees
+struct PoolStateBefore {
+    /// Per-level accumulated LP fees
+    lp_fees: RawFeeLevelsArray<(AmountUFP, AmountUFP)>,
+    levels: PoolLevelsReport,
+}
+
+impl PoolStateBefore {
+    fn new<T: Types>(pool: &PoolV1<T>) -> Self {
+        Self {
+            lp_fees: pool.acc_lp_fees_per_level(),
+            levels: PoolLevelsReport::new(pool),
+        }
+    }
+}
+
 /// Mutable view into contract's state, but for specific account
 struct AccountViewMut<'a, T: Types> {
     account_id: &'a AccountId,
     account: &'a mut AccountLatest<T>,
     pools: &'a mut state_types::PoolsMap<T>,
+    classic_pools: &'a mut state_types::ClassicPoolsMap<T>,
+    stable_pools: &'a mut state_types::StablePoolsMap<T>,
     pool_count: &'a mut u64,
     next_free_position_id: &'a mut u64,
     position_to_pool_id: &'a mut state_types::PositionToPoolMap<T>,
+    position_owners: &'a mut state_types::PositionOwnersMap<T>,
+    banned_tokens: &'a T::BannedTokensSet,
+    token_interface_check: bool,
+    token_interfaces: &'a state_types::TokenInterfacesMap<T>,
+    min_position_liquidity: Float,
+    min_deposit_amounts: &'a state_types::MinDepositAmountsMap<T>,
+    protocol_fee_fraction: BasisPoints,
+    swap_guards: &'a mut state_types::SwapGuardsMap<T>,
+    block_height: u64,
+    block_timestamp: u64,
+    integrations: &'a state_types::IntegrationsMap<T>,
+    pool_storage_deposits: &'a mut state_types::PoolStorageDepositsMap<T>,
+    pool_storage_deposits_total: &'a mut Amount,
+    pool_storage_bytes_total: &'a mut u64,
+    vaults: &'a state_types::VaultsMap<T>,
+    seed_period: Option<SeedPeriodConfig>,
     #[cfg(feature = "near")]
     #[cfg(feature = "smart-routing")]
     token_connections: &'a mut state_types::TokenConnectionsMap<T>,
@@ -104,12 +237,105 @@ struct AccountViewMut<'a, T: Types> {
     logger: &'a mut dyn Logger,
 }
 
+impl<'a, T: Types> AccountViewMut<'a, T> {
+    /// Create pool of any kind on behalf of the account: checks tokens, inserts pool with `insert`,
+    /// connects tokens for smart routing, and charges account for storage taken by all of that
+    fn create_pool(
+        &mut self,
+        pool_id: &PoolId,
+        token_a: &TokenId,
+        token_b: &TokenId,
+        insert: impl FnOnce(&mut Self) -> Result<()>,
+    ) -> Result<()> {
+        ensure_not_banned::<T>(self.banned_tokens, [token_a, token_b])?;
+        if self.token_interface_check {
+            ensure_interfaces_implemented::<T>(self.token_interfaces, [token_a, token_b])?;
+        }
+        let storage_before = T::storage_usage();
+        insert(self)?;
+        *self.pool_count += 1;
+        #[cfg(feature = "near")]
+        #[cfg(feature = "smart-routing")]
+        for (token, other) in [(token_a, token_b), (token_b, token_a)] {
+            let factory = &mut *self.item_factory;
+            let Ok(_) = self.token_connections.update_or_insert(
+                token,
+                || Ok(factory.new_tokens_set()),
+                |set, _| {
+                    set.add_item(other.clone());
+                    Ok(())
+                }
+            ) else { unreachable!() };
+        }
+        let storage_bytes = T::storage_usage().saturating_sub(storage_before);
+
+        // Charge account, remembering paid amount so that it may be credited back
+        let amount = self.account.extra.on_pool_created(storage_bytes)?;
+        self.pool_storage_deposits.insert(
+            pool_id.clone(),
+            PoolStorageDeposit {
+                creator: self.account_id.clone(),
+                amount,
+                storage_bytes,
+            },
+        );
+        *self.pool_storage_deposits_total += amount;
+        *self.pool_storage_bytes_total += storage_bytes;
+        Ok(())
+    }
+
+    /// Create concentrated pool on behalf of the account, starting its seed period if enabled
+    fn create_concentrated_pool(
+        &mut self,
+        pool_id: &PoolId,
+        token_a: &TokenId,
+        token_b: &TokenId,
+    ) -> Result<()> {
+        self.create_pool(pool_id, token_a, token_b, |account_view| {
+            let Pool::V1(mut pool) = account_view.item_factory.new_pool()?;
+            if let Some(config) = account_view.seed_period {
+                pool.seed_until = account_view.block_height.saturating_add(config.blocks);
+            }
+            account_view.pools.insert(pool_id.clone(), Pool::V1(pool));
+            Ok(())
+        })
+    }
+
+    /// Ensure pair doesn't have pool of kind other than `kind`
+    fn ensure_pool_kind(&self, pool_id: &PoolId, kind: PoolKind) -> Result<()> {
+        let existing = pool_kind::<T>(self.pools, self.classic_pools, self.stable_pools, pool_id);
+        ensure_here!(
+            existing.map_or(true, |existing| existing == kind),
+            ErrorKind::PoolKindMismatch
+        );
+        Ok(())
+    }
+}
+
+/// Kind of pool registered for pair, if any
+fn pool_kind<T: Types>(
+    pools: &state_types::PoolsMap<T>,
+    classic_pools: &state_types::ClassicPoolsMap<T>,
+    stable_pools: &state_types::StablePoolsMap<T>,
+    pool_id: &PoolId,
+) -> Option<PoolKind> {
+    if pools.contains_key(pool_id) {
+        Some(PoolKind::Concentrated)
+    } else if classic_pools.contains_key(pool_id) {
+        Some(PoolKind::ClassicXY)
+    } else if stable_pools.contains_key(pool_id) {
+        Some(PoolKind::Stable)
+    } else {
+        None
+    }
+}
+
 impl<T: Types, S: State<T>, SS: Borrow<S>> Dex<T, S, SS> {
     pub fn get_deposit(&self, account: &AccountId, token: &TokenId) -> Result<Amount> {
-        let Contract::V0(ref contract) = self.contract();
+        let Contract::V1(ref contract) = self.contract();
         contract
             .accounts
-            .try_inspect(account, |Account::V0(ref acc)| {
+            .try_inspect(account, |Account::V1(ref acc)| {
                 acc.token_balances.try_inspect(token, |balance| *balance)
             })?
     }
@@ -117,35 +343,191 @@ impl<T: Types, S: State<T>, SS: Borrow<S>> Dex<T, S, SS> {
     pub fn get_pool_info(&self, tokens: (TokenId, TokenId)) -> Result<Option<PoolInfo>> {
         let (pool_id, swapped) = PoolId::try_from_pair(tokens).map_err(|e| error_here!(e))?;
         let side = if swapped { Side::Right } else { Side::Left };
-        let Contract::V0(ref contract) = self.contract();
+        let Contract::V1(ref contract) = self.contract();
         let result = contract
             .pools
-            .inspect(&pool_id, |Pool::V0(ref pool)| pool.pool_info(side))
+            .inspect(&pool_id, |Pool::V1(ref pool)| pool.pool_info(side))
             .transpose()?;
         Ok(result)
     }
 
+    /// Internal pricing state of pool of `tokens`, in pool's own order of tokens
+    pub fn get_pool_pricing_state(
+        &self,
+        tokens: (TokenId, TokenId),
+    ) -> Result<Option<PoolPricingState>> {
+        let (pool_id, _) = PoolId::try_from_pair(tokens).map_err(|e| error_here!(e))?;
+        let Contract::V1(ref contract) = self.contract();
+        Ok(contract
+            .pools
+            .inspect(&pool_id, |Pool::V1(ref pool)| pool.pricing_state()))
+    }
+
+    pub fn get_pool_stats(&self, tokens: (TokenId, TokenId)) -> Result<Option<PoolStats>> {
+        let (pool_id, swapped) = PoolId::try_from_pair(tokens).map_err(|e| error_here!(e))?;
+        let side = if swapped { Side::Right } else { Side::Left };
+        let Contract::V1(ref contract) = self.contract();
+        Ok(contract
+            .pools
+            .inspect(&pool_id, |Pool::V1(ref pool)| pool.pool_stats(side)))
+    }
+
+    /// Cumulative amounts of tokens paid in by traders, per fee level of concentrated pool
+    /// which absorbed them, ordered the same way as `tokens`
+    pub fn get_level_utilization(
+        &self,
+        tokens: (TokenId, TokenId),
+    ) -> Result<Option<RawFeeLevelsArray<(AmountUFP, AmountUFP)>>> {
+        let (pool_id, swapped) = PoolId::try_from_pair(tokens).map_err(|e| error_here!(e))?;
+        let side = if swapped { Side::Right } else { Side::Left };
+        let Contract::V1(ref contract) = self.contract();
+        Ok(contract
+            .pools
+            .inspect(&pool_id, |Pool::V1(ref pool)| pool.level_volumes_in(side)))
+    }
+
+    /// LP fee accumulators of concentrated pool, ordered the same way as `tokens`
+    pub fn get_pool_fee_accumulators(
+        &self,
+        tokens: (TokenId, TokenId),
+    ) -> Result<Option<PoolFeeAccumulators>> {
+        let (pool_id, swapped) = PoolId::try_from_pair(tokens).map_err(|e| error_here!(e))?;
+        let side = if swapped { Side::Right } else { Side::Left };
+        let Contract::V1(ref contract) = self.contract();
+        Ok(contract
+            .pools
+            .inspect(&pool_id, |Pool::V1(ref pool)| pool.fee_accumulators(side)))
+    }
+
     pub fn get_pool_infos(&self) -> Result<Vec<(PoolId, PoolInfo)>> {
-        let Contract::V0(ref contract) = self.contract();
+        let Contract::V1(ref contract) = self.contract();
         let mut infos = Vec::new();
         for (pool_id, pool) in contract.pools.iter() {
-            let Pool::V0(ref pool) = &*pool;
+            let Pool::V1(ref pool) = &*pool;
             infos.push((pool_id.clone(), pool.pool_info(Side::Left)?));
         }
         Ok(infos)
     }
 
+    /// Compute digest of critical state: pools' reserves and positions,
+    /// accounts' balances and owned positions
+    ///
+    /// Reads whole state, so is intended for off-chain consumers
+    /// to verify their replicas are in sync with contract.
+    #[cfg(feature = "near")]
+    pub fn get_state_digest(&self) -> Result<StateDigest> {
+        let Contract::V1(ref contract) = self.contract();
+
+        let mut pools = DigestBuilder::default();
+        for (pool_id, pool) in contract.pools.iter() {
+            let Pool::V1(ref pool) = &*pool;
+            let mut positions = DigestBuilder::default();
+            for (position_id, position) in pool.positions.iter() {
+                let Position::V1(ref position) = &*position;
+                positions.add(
+                    &*position_id,
+                    &(
+                        position.fee_level,
+                        position.net_liquidity,
+                        position.tick_bounds,
+                        &position.locked_by,
+                        position.locked_until,
+                    ),
+                );
+            }
+            pools.add(&*pool_id, &(pool.total_reserves, positions.finish()));
+        }
+
+        let mut accounts = DigestBuilder::default();
+        for (account_id, account) in contract.accounts.iter() {
+            let Account::V1(ref account) = &*account;
+            let mut balances = DigestBuilder::default();
+            for (token_id, balance) in account.token_balances.iter() {
+                balances.add(&*token_id, &*balance);
+            }
+            let mut positions = DigestBuilder::default();
+            for position_id in account.positions.iter() {
+                positions.add(&*position_id, &());
+            }
+            accounts.add(&*account_id, &(balances.finish(), positions.finish()));
+        }
+
+        Ok(StateDigest::new(pools.finish(), accounts.finish()))
+    }
+
     pub fn get_position_info(&self, position_id: u64) -> Result<PositionInfo> {
-        let Contract::V0(ref contract) = self.contract();
+        let Contract::V1(ref contract) = self.contract();
         contract
             .position_to_pool_id
             .try_inspect(&position_id, |pool_id| {
-                contract.pools.try_inspect(pool_id, |Pool::V0(ref pool)| {
+                contract.pools.try_inspect(pool_id, |Pool::V1(ref pool)| {
                     pool.get_position_info(pool_id, position_id)
                 })
             })??
     }
 
+    /// Account which owns position, or `None` if position is held by LP token
+    pub fn get_position_owner(&self, position_id: PositionId) -> Result<Option<AccountId>> {
+        let Contract::V1(ref contract) = self.contract();
+        ensure_here!(
+            contract.position_to_pool_id.contains_key(&position_id),
+            ErrorKind::PositionDoesNotExist
+        );
+        if let Some(owner_id) = contract.position_owners.inspect(&position_id, Clone::clone) {
+            return Ok(Some(owner_id));
+        }
+        // Positions opened before owners were recorded are found in account sets
+        for (account_id, account) in contract.accounts.iter() {
+            let Account::V1(ref account) = &*account;
+            if account.positions.contains_item(&position_id) {
+                #[allow(clippy::clone_on_copy)] // not all account ids are copyable
+                let owner_id = (*account_id).clone();
+                return Ok(Some(owner_id));
+            }
+        }
+        Ok(None)
+    }
+
+    /// Number of currently open positions
+    pub fn positions_total_supply(&self) -> u64 {
+        let Contract::V1(ref contract) = self.contract();
+        contract.position_to_pool_id.len() as u64
+    }
+
+    /// Positions of concentrated pool, among `limit` of them skipping first `from`,
+    /// whose fees accrued since last withdrawal reach either of `min_amounts`.
+    /// Fewer than `limit` positions may be returned even if pool has more of them.
+    ///
+    /// # Returns
+    /// Position ids with accrued fees; amounts are ordered the same way as `tokens`
+    pub fn get_positions_with_fees(
+        &self,
+        tokens: (TokenId, TokenId),
+        min_amounts: (Amount, Amount),
+        from: usize,
+        limit: usize,
+    ) -> Result<Vec<(PositionId, (Amount, Amount))>> {
+        let (pool_id, swapped) = PoolId::try_from_pair(tokens).map_err(|e| error_here!(e))?;
+        let min_amounts = swap_if(swapped, min_amounts);
+        let Contract::V1(ref contract) = self.contract();
+        contract.pools.try_inspect(&pool_id, |Pool::V1(ref pool)| {
+            let mut positions = Vec::new();
+            for (position_id, position) in pool.positions.iter().skip(from).take(limit) {
+                let Position::V1(ref position) = &*position;
+                let fees = pool.position_reward(position, false)?;
+                if fees.0 >= min_amounts.0 || fees.1 >= min_amounts.1 {
+                    positions.push((*position_id, swap_if(swapped, fees)));
+                }
+            }
+            Ok(positions)
+        })?
+    }
+
+    pub fn get_swap_hook(&self) -> Option<SwapHook> {
+        let Contract::V1(ref contract) = self.contract();
+        contract.swap_hook.clone()
+    }
+
     pub fn get_version(&self) -> VersionInfo {
         VersionInfo {
             version: env!("DEX_CORE_VERSION").to_string(),
@@ -161,7 +543,7 @@ impl<T: Types, S: State<T>, SS: Borrow<S>> Dex<T, S, SS> {
     }
 
     pub fn protocol_fee_fraction(&self) -> BasisPoints {
-        let Contract::V0(ref contract) = self.contract();
+        let Contract::V1(ref contract) = self.contract();
         contract.protocol_fee_fraction
     }
 
@@ -173,21 +555,47 @@ impl<T: Types, S: State<T>, SS: Borrow<S>> Dex<T, S, SS> {
     ) -> Result<RawFeeLevelsArray<Float>> {
         let (pool_id, swapped) = PoolId::try_from_pair(tokens).map_err(|e| error_here!(e))?;
         let side = direction.opposite_if(swapped);
-        let Contract::V0(ref contract) = self.contract();
-        contract.pools.try_inspect(&pool_id, |Pool::V0(ref pool)| {
+        let Contract::V1(ref contract) = self.contract();
+        contract.pools.try_inspect(&pool_id, |Pool::V1(ref pool)| {
             fee_levels().map(|level| pool.eff_sqrtprice(side, level))
         })
     }
 
+    /// Tokens which share a pool with `token`, skipping first `from` of them
     #[cfg(feature = "near")]
     #[cfg(feature = "smart-routing")]
-    pub fn get_token_top_pools(&self, token: &TokenId) -> Result<[TokenId; NUM_TOP_POOLS]> {
-        let Contract::V0(ref contract) = self.contract();
-        let top_pools_vec: Vec<_> = contract
-            .top_pools
-            .inspect(token, |tokens| tokens.iter().map(|t| t.clone()).collect())
-            .ok_or(error_here!(ErrorKind::TokenNotRegistered))?;
-        Ok(top_pools_vec.try_into().unwrap())
+    pub fn get_token_connections(
+        &self,
+        token: &TokenId,
+        from: usize,
+        limit: usize,
+    ) -> Vec<TokenId> {
+        let Contract::V1(ref contract) = self.contract();
+        contract
+            .token_connections
+            .inspect(token, |tokens| {
+                tokens
+                    .iter()
+                    .skip(from)
+                    .take(limit)
+                    .map(|t| t.clone())
+                    .collect()
+            })
+            .unwrap_or_default()
+    }
+
+    /// Tokens which participate in at least one pool, skipping first `from` of them
+    #[cfg(feature = "near")]
+    #[cfg(feature = "smart-routing")]
+    pub fn get_all_tokens(&self, from: usize, limit: usize) -> Vec<TokenId> {
+        let Contract::V1(ref contract) = self.contract();
+        contract
+            .token_connections
+            .iter()
+            .skip(from)
+            .take(limit)
+            .map(|(token, _)| token.clone())
+            .collect()
     }
 
     #[cfg(feature = "near")]
@@ -246,11 +654,11 @@ impl<T: Types, S: State<T>, SS: Borrow<S>> Dex<T, S, SS> {
     #[cfg(feature = "near")]
     #[cfg(feature = "smart-routing")]
     fn price_of_pair(&self, token_a: TokenId, token_b: TokenId) -> Result<Liquidity, ErrorKind> {
-        let Contract::V0(ref contract) = self.contract();
+        let Contract::V1(ref contract) = self.contract();
         let (pool_id, swapped) = PoolId::try_from_pair((token_a, token_b))?;
         let price = contract
             .pools
-            .try_inspect(&pool_id, |Pool::V0(ref pool)| pool.primitive_price())
+            .try_inspect(&pool_id, |Pool::V1(ref pool)| pool.primitive_price())
             .map_err(|e| e.kind)?;
         if swapped {
             Ok(price.recip())
@@ -266,11 +674,11 @@ impl<T: Types, S: State<T>, SS: Borrow<S>> Dex<T, S, SS> {
         token_a: TokenId,
         token_b: TokenId,
     ) -> Result<Liquidity, ErrorKind> {
-        let Contract::V0(ref contract) = self.contract();
+        let Contract::V1(ref contract) = self.contract();
         let (pool_id, _) = PoolId::try_from_pair((token_a, token_b))?;
         contract
             .pools
-            .try_inspect(&pool_id, |Pool::V0(ref pool)| pool.total_liquidity())
+            .try_inspect(&pool_id, |Pool::V1(ref pool)| pool.total_liquidity())
             .map_err(|e| e.kind)
     }
 }
@@ -281,22 +689,42 @@ impl<T: Types, S: StateMut<T>, SS: BorrowMut<S>> Dex<T, S, SS> {
         account_id: &AccountId,
         cb: impl FnOnce(AccountViewMut<'_, T>) -> Result<R>,
     ) -> Result<R> {
+        let block_height = self.get_block_height();
+        let block_timestamp = self.get_block_timestamp();
         let StateMembersMut {
-            contract: Contract::V0(ref mut contract),
+            contract: Contract::V1(ref mut contract),
             item_factory,
             logger,
         } = self.members_mut();
 
         contract
             .accounts
-            .try_update(account_id, |Account::V0(ref mut account)| {
+            .try_update(account_id, |Account::V1(ref mut account)| {
                 cb(AccountViewMut {
                     account_id,
                     account,
                     pools: &mut contract.pools,
+                    classic_pools: &mut contract.classic_pools,
+                    stable_pools: &mut contract.stable_pools,
                     pool_count: &mut contract.pool_count,
                     next_free_position_id: &mut contract.next_free_position_id,
                     position_to_pool_id: &mut contract.position_to_pool_id,
+                    position_owners: &mut contract.position_owners,
+                    banned_tokens: &contract.banned_tokens,
+                    token_interface_check: contract.token_interface_check,
+                    token_interfaces: &contract.token_interfaces,
+                    min_position_liquidity: contract.min_position_liquidity,
+                    min_deposit_amounts: &contract.min_deposit_amounts,
+                    protocol_fee_fraction: contract.protocol_fee_fraction,
+                    swap_guards: &mut contract.swap_guards,
+                    block_height,
+                    block_timestamp,
+                    integrations: &contract.integrations,
+                    pool_storage_deposits: &mut contract.pool_storage_deposits,
+                    pool_storage_deposits_total: &mut contract.pool_storage_deposits_total,
+                    pool_storage_bytes_total: &mut contract.pool_storage_bytes_total,
+                    vaults: &contract.vaults,
+                    seed_period: contract.seed_period,
                     #[cfg(feature = "near")]
                     #[cfg(feature = "smart-routing")]
                     token_connections: &mut contract.token_connections,
@@ -343,7 +771,7 @@ impl<T: Types, S: StateMut<T>, SS: BorrowMut<S>> Dex<T, S, SS> {
         let account_id: Option<AccountId> = account_id.into();
         let account_id = account_id.unwrap_or_else(|| self.get_caller_id());
         let StateMembersMut {
-            contract: Contract::V0(contract),
+            contract: Contract::V1(contract),
             item_factory,
             ..
         } = self.members_mut();
@@ -388,14 +816,18 @@ impl<T: Types, S: StateMut<T>, SS: BorrowMut<S>> Dex<T, S, SS> {
         let account_id = account_id.unwrap_or_else(|| self.get_caller_id());
 
         let StateMembersMut {
-            contract: Contract::V0(contract),
+            contract: Contract::V1(contract),
             ..
         } = self.members_mut();
 
+        ensure_here!(
+            !contract.vaults.contains_key(&account_id),
+            ErrorKind::VaultEnabled
+        );
         contract
             .accounts
             .inspect(&account_id, |account| {
-                let Account::V0(ref acc) = account;
+                let Account::V1(ref acc) = account;
                 ensure_here!(
                     acc.token_balances.is_empty(),
                     ErrorKind::TokensStorageNotEmpty
@@ -410,6 +842,8 @@ impl<T: Types, S: StateMut<T>, SS: BorrowMut<S>> Dex<T, S, SS> {
             .map(|r| {
                 if r.is_ok() {
                     contract.accounts.remove(&account_id);
+                    contract.view_grants.remove(&account_id);
+                    contract.session_keys.remove(&account_id);
                 }
                 r
             })
@@ -417,7 +851,7 @@ impl<T: Types, S: StateMut<T>, SS: BorrowMut<S>> Dex<T, S, SS> {
     }
 
     fn ensure_caller_is_owner(&self) -> Result<()> {
-        let Contract::V0(ref contract) = self.contract();
+        let Contract::V1(ref contract) = self.contract();
         ensure_here!(
             contract.owner_id == self.get_caller_id(),
             ErrorKind::PermissionDenied
@@ -426,19 +860,19 @@ impl<T: Types, S: StateMut<T>, SS: BorrowMut<S>> Dex<T, S, SS> {
     }
 
     fn ensure_suspended(&self) -> Result<()> {
-        let Contract::V0(ref contract) = self.contract();
+        let Contract::V1(ref contract) = self.contract();
         ensure_here!(contract.suspended, ErrorKind::GuardChangeStateDenied);
         Ok(())
     }
 
     fn ensure_resumed(&self) -> Result<()> {
-        let Contract::V0(ref contract) = self.contract();
+        let Contract::V1(ref contract) = self.contract();
         ensure_here!(!contract.suspended, ErrorKind::GuardChangeStateDenied);
         Ok(())
     }
 
     fn ensure_caller_is_guard(&self) -> Result<()> {
-        let Contract::V0(ref contract) = self.contract();
+        let Contract::V1(ref contract) = self.contract();
         ensure_here!(
             contract.owner_id == self.get_caller_id()
                 || contract.guards.contains_item(&self.get_caller_id()),
@@ -448,7 +882,7 @@ impl<T: Types, S: StateMut<T>, SS: BorrowMut<S>> Dex<T, S, SS> {
     }
 
     pub(crate) fn ensure_payable_api_resumed(&self) -> Result<()> {
-        let Contract::V0(ref contract) = self.contract();
+        let Contract::V1(ref contract) = self.contract();
         ensure_here!(!contract.suspended, ErrorKind::PayableAPISuspended);
         Ok(())
     }
@@ -456,7 +890,7 @@ impl<T: Types, S: StateMut<T>, SS: BorrowMut<S>> Dex<T, S, SS> {
     pub fn add_verified_tokens(&mut self, tokens: impl IntoIterator<Item = TokenId>) -> Result<()> {
         self.ensure_payable_api_resumed()?;
         self.ensure_caller_is_owner()?;
-        let Contract::V0(ref mut contract) = self.contract_mut();
+        let Contract::V1(ref mut contract) = self.contract_mut();
         let verified_tokens = &mut contract.verified_tokens;
         let mut new_tokens = Vec::new();
 
@@ -478,7 +912,15 @@ impl<T: Types, S: StateMut<T>, SS: BorrowMut<S>> Dex<T, S, SS> {
     ) -> Result<()> {
         self.ensure_payable_api_resumed()?;
         self.ensure_caller_is_owner()?;
-        let Contract::V0(ref mut contract) = self.contract_mut();
+        self.ensure_multisig_disabled()?;
+        self.ensure_timelock_disabled()?;
+        self.apply_admin_action(AdminAction::RemoveVerifiedTokens(
+            tokens.into_iter().collect(),
+        ))
+    }
+
+    fn remove_verified_tokens_unchecked(&mut self, tokens: Vec<TokenId>) {
+        let Contract::V1(ref mut contract) = self.contract_mut();
         let verified_tokens = &mut contract.verified_tokens;
         let mut removed_tokens = Vec::new();
         for token in tokens {
@@ -490,12 +932,10 @@ impl<T: Types, S: StateMut<T>, SS: BorrowMut<S>> Dex<T, S, SS> {
 
         self.logger_mut()
             .log_remove_verified_tokens_event(&removed_tokens);
-
-        Ok(())
     }
 
     pub fn get_verified_tokens(&self) -> Vec<TokenId> {
-        let Contract::V0(contract) = self.contract();
+        let Contract::V1(contract) = self.contract();
         contract
             .verified_tokens
             .iter()
@@ -510,7 +950,7 @@ impl<T: Types, S: StateMut<T>, SS: BorrowMut<S>> Dex<T, S, SS> {
     ) -> Result<()> {
         self.ensure_payable_api_resumed()?;
         self.ensure_caller_is_owner()?;
-        let Contract::V0(ref mut contract) = self.contract_mut();
+        let Contract::V1(ref mut contract) = self.contract_mut();
         let guards = &mut contract.guards;
         let mut new_guards = Vec::new();
 
@@ -521,7 +961,9 @@ impl<T: Types, S: StateMut<T>, SS: BorrowMut<S>> Dex<T, S, SS> {
             }
         }
 
-        self.logger_mut().log_add_guard_accounts_event(&new_guards);
+        let caller_id = self.get_caller_id();
+        self.logger_mut()
+            .log_add_guard_accounts_event(&caller_id, &new_guards);
 
         Ok(())
     }
@@ -532,7 +974,7 @@ impl<T: Types, S: StateMut<T>, SS: BorrowMut<S>> Dex<T, S, SS> {
     ) -> Result<()> {
         self.ensure_payable_api_resumed()?;
         self.ensure_caller_is_owner()?;
-        let Contract::V0(ref mut contract) = self.contract_mut();
+        let Contract::V1(ref mut contract) = self.contract_mut();
         let guards = &mut contract.guards;
         let mut removed_guards = Vec::new();
 
@@ -543,8 +985,9 @@ impl<T: Types, S: StateMut<T>, SS: BorrowMut<S>> Dex<T, S, SS> {
             }
         }
 
+        let caller_id = self.get_caller_id();
         self.logger_mut()
-            .log_remove_guard_accounts_event(&removed_guards);
+            .log_remove_guard_accounts_event(&caller_id, &removed_guards);
 
         Ok(())
     }
@@ -553,7 +996,7 @@ impl<T: Types, S: StateMut<T>, SS: BorrowMut<S>> Dex<T, S, SS> {
         self.ensure_caller_is_guard()?;
         self.ensure_resumed()?;
 
-        let Contract::V0(ref mut contract) = self.contract_mut();
+        let Contract::V1(ref mut contract) = self.contract_mut();
         contract.suspended = true;
 
         let caller_id = self.get_caller_id();
@@ -566,7 +1009,7 @@ impl<T: Types, S: StateMut<T>, SS: BorrowMut<S>> Dex<T, S, SS> {
         self.ensure_caller_is_guard()?;
         self.ensure_suspended()?;
 
-        let Contract::V0(ref mut contract) = self.contract_mut();
+        let Contract::V1(ref mut contract) = self.contract_mut();
         contract.suspended = false;
 
         let caller_id = self.get_caller_id();
@@ -578,16 +1021,33 @@ impl<T: Types, S: StateMut<T>, SS: BorrowMut<S>> Dex<T, S, SS> {
     pub fn set_protocol_fee_fraction(&mut self, protocol_fee_fraction: BasisPoints) -> Result<()> {
         self.ensure_payable_api_resumed()?;
         self.ensure_caller_is_owner()?;
-        ensure_here!(protocol_fee_fraction > 0, ErrorKind::IllegalFee);
-        ensure_here!(
-            protocol_fee_fraction <= BASIS_POINT_DIVISOR / 2,
-            ErrorKind::IllegalFee
-        );
-        let Contract::V0(ref mut contract) = self.contract_mut();
-        contract.protocol_fee_fraction = protocol_fee_fraction;
+        self.ensure_multisig_disabled()?;
+        self.ensure_timelock_disabled()?;
+        self.apply_admin_action(AdminAction::SetProtocolFeeFraction(protocol_fee_fraction))
+    }
+
+    /// Set or reset contract which is notified about swaps exceeding configured thresholds
+    pub fn set_swap_hook(&mut self, swap_hook: Option<SwapHook>) -> Result<()> {
+        self.ensure_payable_api_resumed()?;
+        self.ensure_caller_is_owner()?;
+        let Contract::V1(ref mut contract) = self.contract_mut();
+        contract.swap_hook = swap_hook;
         Ok(())
     }
 
+    /// Notifies swap hook, if it's configured and swap exceeds threshold
+    fn notify_swap_hook_if_triggered(&mut self, notification: SwapNotification) {
+        let Contract::V1(ref contract) = self.contract();
+        let Some(hook_id) = contract
+            .swap_hook
+            .as_ref()
+            .filter(|hook| hook.is_triggered(&notification.token_in, notification.amount_in.into()))
+            .map(|hook| hook.hook_id.clone()) else {
+            return;
+        };
+        self.notify_swap_hook(&hook_id, notification);
+    }
+
     pub fn owner_withdraw(
         &mut self,
         token_id: &TokenId,
@@ -597,10 +1057,11 @@ impl<T: Types, S: StateMut<T>, SS: BorrowMut<S>> Dex<T, S, SS> {
         self.ensure_payable_api_resumed()?;
         self.ensure_caller_is_owner()?;
         ensure_here!(amount > Amount::zero(), ErrorKind::IllegalWithdrawAmount);
-        let Contract::V0(ref mut contract) = self.contract_mut();
+        self.consume_withdraw_limits([(token_id, amount)], true)?;
+        let Contract::V1(ref mut contract) = self.contract_mut();
         contract
             .accounts
-            .try_update(&contract.owner_id, |Account::V0(ref mut account)| {
+            .try_update(&contract.owner_id, |Account::V1(ref mut account)| {
                 // Note: subtraction and deregistration will be reverted if the promise fails.
                 account
                     .withdraw(token_id, amount)
@@ -609,6 +1070,9 @@ impl<T: Types, S: StateMut<T>, SS: BorrowMut<S>> Dex<T, S, SS> {
         #[allow(clippy::clone_on_copy)] // Some blockchains have address copyable, some don't
         let owner_id = contract.owner_id.clone();
 
+        self.logger_mut()
+            .log_owner_withdraw_event(token_id, &amount);
+
         Ok(self.send_tokens(&owner_id, token_id, amount, false, extra))
     }
 
@@ -618,10 +1082,10 @@ impl<T: Types, S: StateMut<T>, SS: BorrowMut<S>> Dex<T, S, SS> {
         tokens: impl IntoIterator<Item = &'a TokenId>,
     ) -> Result<()> {
         self.ensure_payable_api_resumed()?;
-        let Contract::V0(ref mut contract) = self.contract_mut();
+        let Contract::V1(ref mut contract) = self.contract_mut();
         contract
             .accounts
-            .try_update(account_id, |Account::V0(ref mut account)| {
+            .try_update(account_id, |Account::V1(ref mut account)| {
                 account.register_tokens(tokens);
                 Ok(())
             })
@@ -633,10 +1097,10 @@ impl<T: Types, S: StateMut<T>, SS: BorrowMut<S>> Dex<T, S, SS> {
         tokens: impl IntoIterator<Item = &'a TokenId>,
     ) -> Result<()> {
         self.ensure_payable_api_resumed()?;
-        let Contract::V0(ref mut contract) = self.contract_mut();
+        let Contract::V1(ref mut contract) = self.contract_mut();
         contract
             .accounts
-            .try_update(account_id, |Account::V0(ref mut account)| {
+            .try_update(account_id, |Account::V1(ref mut account)| {
                 account.unregister_tokens(tokens)
             })
     }
@@ -648,21 +1112,22 @@ impl<T: Types, S: StateMut<T>, SS: BorrowMut<S>> Dex<T, S, SS> {
         amount: Amount,
     ) -> Result<Amount> {
         self.ensure_payable_api_resumed()?;
+        self.ensure_tokens_not_banned([token_id])?;
         let StateMembersMut {
-            contract: Contract::V0(ref mut contract),
+            contract: Contract::V1(ref mut contract),
             logger,
             ..
         } = self.members_mut();
         contract
             .accounts
-            .try_update(account_id, |Account::V0(ref mut account)| {
+            .try_update(account_id, |Account::V1(ref mut account)| {
                 Self::deposit_impl(account_id, account, token_id, amount, logger)
             })
     }
 
     fn deposit_impl(
         account_id: &AccountId,
-        account: &mut AccountV0<T>,
+        account: &mut AccountV1<T>,
         token_id: &TokenId,
         amount: Amount,
         logger: &mut dyn Logger,
@@ -684,20 +1149,24 @@ impl<T: Types, S: StateMut<T>, SS: BorrowMut<S>> Dex<T, S, SS> {
     ) -> Result<Option<S::SendTokensResult>> {
         self.ensure_payable_api_resumed()?;
         let StateMembersMut {
-            contract: Contract::V0(ref mut contract),
+            contract: Contract::V1(ref mut contract),
             logger,
             ..
         } = self.members_mut();
 
         let sender = contract
             .accounts
-            .try_update(account_id, |Account::V0(ref mut account)| {
+            .try_update(account_id, |Account::V1(ref mut account)| {
                 Self::withdraw_impl(
                     account_id, account, token_id, amount, unregister, extra, logger,
                 )
             })?;
 
-        Ok(sender.map(|func| func(self)))
+        let Some((amount, func)) = sender else {
+            return Ok(None);
+        };
+        self.consume_withdraw_limits([(token_id, amount)], false)?;
+        func(self)
     }
     /// Internal implementation of token withdrawal, including event logging
     /// and sending tokens to new owner
@@ -713,9 +1182,10 @@ impl<T: Types, S: StateMut<T>, SS: BorrowMut<S>> Dex<T, S, SS> {
     ///
     /// # Returns
     /// * `Ok(None)` - if requested amount was zero, and balance was zero too or token wasn't registered,
-    /// * `Ok(Some(closure))` - if nonzero amount was withdrawn. `closure` will perform
-    ///     actual tokens send and return that send result. So function callers should call it like
-    ///     `Self::withdraw_impl(...)?.map(|func| func(self))`
+    /// * `Ok(Some((amount, closure)))` - if nonzero `amount` was withdrawn. `closure` will perform
+    ///     actual tokens send and return that send result, or `None` if withdrawal was delayed
+    ///     by account's vault. So function callers should call it like
+    ///     `Self::withdraw_impl(...)?.map(|(_amount, func)| func(self)).transpose()`
     /// * `Err(_)` if any error happens on the way
     fn withdraw_impl(
         account_id: &AccountId,
@@ -725,7 +1195,12 @@ impl<T: Types, S: StateMut<T>, SS: BorrowMut<S>> Dex<T, S, SS> {
         unregister: bool,
         extra: S::SendTokensExtraParam,
         logger: &mut dyn Logger,
-    ) -> Result<Option<impl FnOnce(&mut Self) -> S::SendTokensResult>> {
+    ) -> Result<
+        Option<(
+            Amount,
+            impl FnOnce(&mut Self) -> Result<Option<S::SendTokensResult>>,
+        )>,
+    > {
         // If amount is zero, we try withdraw all what remains
         let amount = if amount.is_zero() {
             // First, fetch balance
@@ -763,9 +1238,18 @@ impl<T: Types, S: StateMut<T>, SS: BorrowMut<S>> Dex<T, S, SS> {
         let account_id = account_id.clone();
         let token_id = token_id.clone();
         let sender = move |dex: &mut Self| {
-            dex.send_tokens(&account_id, &token_id, amount, unregister, extra)
+            if dex.delay_withdrawal_if_vaulted(&account_id, &token_id, amount)? {
+                return Ok(None);
+            }
+            Ok(Some(dex.send_tokens(
+                &account_id,
+                &token_id,
+                amount,
+                unregister,
+                extra,
+            )))
         };
-        Ok(Some(sender))
+        Ok(Some((amount, sender)))
     }
 
     /// Returns:
@@ -781,9 +1265,16 @@ impl<T: Types, S: StateMut<T>, SS: BorrowMut<S>> Dex<T, S, SS> {
         position: PositionInit,
     ) -> Result<(PositionId, Amount, Amount, Liquidity)> {
         self.ensure_payable_api_resumed()?;
-        self.with_caller_account_mut(|mut account_view| {
+        self.ensure_not_session_key()?;
+        let result = self.with_caller_account_mut(|mut account_view| {
             Self::open_position_impl(token_a, token_b, fee_rate, position, &mut account_view)
-        })
+        })?;
+        #[cfg(feature = "near")]
+        #[cfg(feature = "smart-routing")]
+        self.update_top_pools([PoolId::try_from_pair((token_a.clone(), token_b.clone()))
+            .map_err(|e| error_here!(e))?
+            .0])?;
+        Ok(result)
     }
 
     #[allow(clippy::too_many_lines)] // FIXME: refactor
@@ -798,19 +1289,22 @@ impl<T: Types, S: StateMut<T>, SS: BorrowMut<S>> Dex<T, S, SS> {
     ) -> Result<(PositionId, Amount, Amount, Liquidity)> {
         let (pool_id, transposed) = PoolId::try_from_pair((token_a.clone(), token_b.clone()))
             .map_err(|e| error_here!(e))?;
+        account_view.ensure_pool_kind(&pool_id, PoolKind::Concentrated)?;
 
         if !account_view.pools.contains_key(&pool_id) {
-            account_view.account.extra.on_pool_created()?;
+            account_view.create_concentrated_pool(&pool_id, token_a, token_b)?;
         }
 
         let position = position.transpose_if(transposed);
+        let locked_until = position.locked_until;
+        if let Some(locked_until) = locked_until {
+            ensure_here!(
+                locked_until > account_view.block_timestamp,
+                ErrorKind::InvalidParams
+            );
+        }
         let fee_rates = fee_rates_ticks();
 
-        let position_id = *account_view.next_free_position_id;
-        *account_view.next_free_position_id += 1;
-
-        let factory = RefCell::new(&mut *account_view.item_factory);
-
         let fee_level: FeeLevel = fee_rates
             .iter()
             .find_position(|r| **r == fee_rate)
@@ -819,36 +1313,54 @@ impl<T: Types, S: StateMut<T>, SS: BorrowMut<S>> Dex<T, S, SS> {
             .try_into()
             .map_err(|_| error_here!(ErrorKind::ConvOverflow))?;
 
-        let (deposited_amounts, accounted_net_liquidity) = account_view.pools.update_or_insert(
+        let position = if position.auto_balance {
+            Self::auto_balance_position(&pool_id, position, fee_level, account_view)?
+        } else {
+            position
+        };
+
+        let position_id = *account_view.next_free_position_id;
+        *account_view.next_free_position_id += 1;
+
+        let ticks_range = Tick::unwrap_range(position.ticks_range).map_err(|e| error_here!(e))?;
+        let storage_before = T::storage_usage();
+        let (deposited_amounts, accounted_net_liquidity) = account_view.pools.try_update_or(
             &pool_id,
-            || {
-                *account_view.pool_count += 1;
-                let pool = factory.borrow_mut().new_pool()?;
-                #[cfg(feature = "near")]
-                #[cfg(feature = "smart-routing")]
-                {
-                    let Ok(_) = account_view.token_connections.update_or_insert(
-                        token_a,
-                        || Ok(factory.borrow_mut().new_tokens_set()),
-                        |set, _| {
-                            set.add_item(token_b.clone());
-                            Ok(())
-                        }
-                    ) else { unreachable!() };
-                    let Ok(_) = account_view.token_connections.update_or_insert(
-                        token_b,
-                        || Ok(factory.borrow_mut().new_tokens_set()),
-                        |set, _| {
-                            set.add_item(token_a.clone());
+            // Pool is created above if it's missing
+            ErrorKind::InternalLogicError,
+            |Pool::V1(ref mut pool)| {
+                let (deposited_amounts, accounted_net_liquidity) = pool.open_position(
+                    position,
+                    fee_level,
+                    position_id,
+                    account_view.min_position_liquidity,
+                    &mut *account_view.item_factory,
+                )?;
+                // Checked after opening, as the first position sets pool price
+                seed_period::ensure_position_in_seed_range(
+                    pool,
+                    ticks_range,
+                    account_view.seed_period,
+                    account_view.block_height,
+                )?;
+                if locked_until.is_some() {
+                    pool.positions
+                        .try_update(&position_id, |Position::V1(ref mut pos)| {
+                            pos.locked_until = locked_until;
                             Ok(())
-                        }
-                    ) else { unreachable!() };
+                        })?;
+                }
+
+                if let Some(min_amounts) = account_view
+                    .min_deposit_amounts
+                    .inspect(&pool_id, |min_amounts| *min_amounts)
+                {
+                    ensure_here!(
+                        deposited_amounts.0 >= min_amounts.0
+                            && deposited_amounts.1 >= min_amounts.1,
+                        ErrorKind::DepositTooSmall
+                    );
                 }
-                Ok(pool)
-            },
-            |Pool::V0(ref mut pool), _| {
-                let (deposited_amounts, accounted_net_liquidity) =
-                    pool.open_position(position, fee_level, position_id, *factory.borrow_mut())?;
 
                 ensure_here!(
                     !account_view.account.positions.contains_item(&position_id),
@@ -871,6 +1383,16 @@ impl<T: Types, S: StateMut<T>, SS: BorrowMut<S>> Dex<T, S, SS> {
                 account_view
                     .position_to_pool_id
                     .insert(position_id, pool_id.clone());
+                #[allow(clippy::clone_on_copy)] // not all account ids are copyable
+                let owner_id = account_view.account_id.clone();
+                account_view.position_owners.insert(position_id, owner_id);
+
+                let (tick_bounds, init_sqrtprice) = pool
+                    .positions
+                    .inspect(&position_id, |Position::V1(pos)| {
+                        (pos.tick_bounds, pos.init_sqrtprice)
+                    })
+                    .ok_or(error_here!(ErrorKind::InternalLogicError))?;
 
                 // Event is emitted here because method is also called by add_simple_pool directly
                 account_view.logger.log_open_position_event(
@@ -879,18 +1401,37 @@ impl<T: Types, S: StateMut<T>, SS: BorrowMut<S>> Dex<T, S, SS> {
                     deposited_amounts.as_refs(),
                     fee_rate,
                     position_id,
+                    &liquidity_from_net_liquidity(accounted_net_liquidity, fee_level),
+                    fee_level,
+                    tick_bounds,
+                    &accounted_net_liquidity,
+                    init_sqrtprice,
                 );
+                if let Some(locked_until) = locked_until {
+                    account_view.logger.log_lock_position_until_event(
+                        account_view.account_id,
+                        position_id,
+                        locked_until,
+                    );
+                }
 
                 Self::log_pool_v0_state(
                     &pool_id,
                     pool,
+                    None,
                     account_view.logger,
                     PoolUpdateReason::AddLiquidity,
                 );
+                // Storage taken by position is measured right after pool is written
+                pool.levels.flush();
 
                 Ok((deposited_amounts, accounted_net_liquidity))
             },
         )?;
+        account_view
+            .account
+            .extra
+            .on_position_opened(T::storage_usage().saturating_sub(storage_before));
 
         let deposited_amounts_in_user_order = swap_if(transposed, deposited_amounts);
         Ok((
@@ -901,6 +1442,94 @@ impl<T: Types, S: StateMut<T>, SS: BorrowMut<S>> Dex<T, S, SS> {
         ))
     }
 
+    /// Swap part of the single token provided for position into the other one,
+    /// so that position takes both tokens in the ratio required at current price.
+    /// Swap output which position doesn't take remains on account's deposit.
+    ///
+    /// # Returns
+    /// Position with `max` amounts replaced by amounts available after swap
+    fn auto_balance_position(
+        pool_id: &PoolId,
+        position: PositionInit,
+        fee_level: FeeLevel,
+        account_view: &mut AccountViewMut<'_, T>,
+    ) -> Result<PositionInit> {
+        let (left, right) = position.amount_ranges;
+        let max_amounts: (Amount, Amount) = (left.max.into(), right.max.into());
+        let side = match (max_amounts.0.is_zero(), max_amounts.1.is_zero()) {
+            (false, true) => Side::Left,
+            (true, false) => Side::Right,
+            _ => return Err(error_here!(ErrorKind::InvalidParams)),
+        };
+        let amount = max_amounts[side];
+        let ticks_range = Tick::unwrap_range(position.ticks_range).map_err(|e| error_here!(e))?;
+        let (token_in, token_out) = swap_if(side == Side::Right, (&pool_id.0, &pool_id.1));
+
+        let (amount_in, amount_out) =
+            account_view
+                .pools
+                .try_update(pool_id, |Pool::V1(ref mut pool)| {
+                    #[cfg(feature = "batch-auction")]
+                    ensure_here!(pool.batch_auction.is_none(), ErrorKind::PoolInBatchMode);
+                    seed_period::ensure_not_seeding(pool, account_view.block_height)?;
+                    let amount_in =
+                        pool.eval_auto_balance_swap_amount(side, amount, ticks_range, fee_level)?;
+                    if amount_in.is_zero() {
+                        return Ok((Amount::zero(), Amount::zero()));
+                    }
+                    let before = PoolStateBefore::new(pool);
+                    let amount_out = pool.swap(
+                        side,
+                        Exact::In,
+                        amount_in,
+                        account_view.protocol_fee_fraction,
+                    )?;
+                    account_view
+                        .account
+                        .withdraw(token_in, amount_in)
+                        .map_err(|e| error_here!(e))?;
+                    account_view
+                        .account
+                        .deposit(token_out, amount_out)
+                        .map_err(|e| error_here!(e))?;
+
+                    account_view.logger.log_swap_event(
+                        account_view.account_id,
+                        (token_in, token_out),
+                        (&amount_in, &amount_out),
+                        &[],
+                        None,
+                    );
+                    Self::log_pool_v0_state(
+                        pool_id,
+                        pool,
+                        Some(&before),
+                        account_view.logger,
+                        PoolUpdateReason::Swap,
+                    );
+                    Ok((amount_in, amount_out))
+                })?;
+
+        let mut available = (Amount::zero(), Amount::zero());
+        available[side] = amount - amount_in;
+        available[side.opposite()] = amount_out;
+        Ok(PositionInit {
+            amount_ranges: (
+                Range {
+                    min: left.min,
+                    max: available.0.into(),
+                },
+                Range {
+                    min: right.min,
+                    max: available.1.into(),
+                },
+            ),
+            ticks_range: position.ticks_range,
+            auto_balance: false,
+            locked_until: position.locked_until,
+        })
+    }
+
     /// Returns:
     ///  - `position_id`
     ///  - actually deposited amount of first token
@@ -930,21 +1559,37 @@ impl<T: Types, S: StateMut<T>, SS: BorrowMut<S>> Dex<T, S, SS> {
                     },
                 ),
                 ticks_range: (None, None),
+                auto_balance: false,
+                locked_until: None,
             },
         )
     }
 
     pub fn close_position(&mut self, position_id: PositionId) -> Result<()> {
         self.ensure_payable_api_resumed()?;
+        #[cfg(feature = "near")]
+        #[cfg(feature = "smart-routing")]
+        let pool_id = {
+            let Contract::V1(ref contract) = self.contract();
+            contract
+                .position_to_pool_id
+                .try_inspect(&position_id, Clone::clone)?
+        };
         self.with_caller_account_mut(|mut account_view| {
             Self::close_position_impl(position_id, &mut account_view)
-        })
+        })?;
+        #[cfg(feature = "near")]
+        #[cfg(feature = "smart-routing")]
+        self.update_top_pools([pool_id])?;
+        Ok(())
     }
 
     fn close_position_impl(
         position_id: PositionId,
         account_view: &mut AccountViewMut<'_, T>,
     ) -> Result<()> {
+        let storage_before = T::storage_usage();
+        let now = account_view.block_timestamp;
         // Get pool_id and at the same time check if position exists
         let (pool_id, fees, amounts) =
             account_view
@@ -966,9 +1611,23 @@ impl<T: Types, S: StateMut<T>, SS: BorrowMut<S>> Dex<T, S, SS> {
                         // Inconsistent state: position is present in `position_to_pool_id`,
                         // but the pool doesn't exist
                         ErrorKind::InternalLogicError,
-                        |Pool::V0(ref mut pool)| {
+                        |Pool::V1(ref mut pool)| {
+                            pool.positions
+                                .try_inspect(&position_id, |Position::V1(pos)| {
+                                    ensure_here!(
+                                        pos.locked_by.is_none(),
+                                        ErrorKind::PositionLocked
+                                    );
+                                    ensure_here!(
+                                        pos.locked_until.map_or(true, |until| until <= now),
+                                        ErrorKind::PositionTimeLocked
+                                    );
+                                    Ok(())
+                                })??;
                             let (fees, amounts) =
                                 pool.withdraw_fee_and_close_position(position_id)?;
+                            // Storage released by position is measured right after pool is written
+                            pool.levels.flush();
                             account_view
                                 .account
                                 .deposit(&pool_id.0, amounts.0 + fees.0)
@@ -983,17 +1642,25 @@ impl<T: Types, S: StateMut<T>, SS: BorrowMut<S>> Dex<T, S, SS> {
                 })??;
 
         account_view.position_to_pool_id.remove(&position_id);
+        account_view.position_owners.remove(&position_id);
+        account_view
+            .account
+            .extra
+            .on_position_closed(storage_before.saturating_sub(T::storage_usage()));
 
-        account_view.logger.log_harvest_fee_event(position_id, fees);
+        account_view
+            .logger
+            .log_harvest_fee_event(account_view.account_id, position_id, fees);
 
         account_view
             .logger
-            .log_close_position_event(position_id, amounts);
+            .log_close_position_event(account_view.account_id, position_id, amounts);
 
-        account_view.pools.inspect(&pool_id, |Pool::V0(ref pool)| {
+        account_view.pools.inspect(&pool_id, |Pool::V1(ref pool)| {
             Self::log_pool_v0_state(
                 &pool_id,
                 pool,
+                None,
                 account_view.logger,
                 PoolUpdateReason::RemoveLiquidity,
             );
@@ -1002,6 +1669,220 @@ impl<T: Types, S: StateMut<T>, SS: BorrowMut<S>> Dex<T, S, SS> {
         Ok(())
     }
 
+    /// Move caller's position to new ticks range in one call: position is closed, its fees
+    /// are harvested, and it's reopened on the same fee level under the same id, with withdrawn
+    /// amounts as maximal deposit. Amounts not taken by position in new range remain on deposits.
+    ///
+    /// Returns (amounts are ordered as pool tokens):
+    ///  - actually deposited amount of first token
+    ///  - actually deposited amount of second token
+    ///  - accounted net liquidity
+    pub fn move_position(
+        &mut self,
+        position_id: PositionId,
+        new_ticks_range: (Option<i32>, Option<i32>),
+    ) -> Result<(Amount, Amount, Liquidity)> {
+        self.ensure_payable_api_resumed()?;
+        let result = self.with_caller_account_mut(|mut account_view| {
+            Self::move_position_impl(position_id, new_ticks_range, &mut account_view)
+        })?;
+        #[cfg(feature = "near")]
+        #[cfg(feature = "smart-routing")]
+        {
+            let Contract::V1(ref contract) = self.contract();
+            let pool_id = contract
+                .position_to_pool_id
+                .try_inspect(&position_id, Clone::clone)?;
+            self.update_top_pools([pool_id])?;
+        }
+        Ok(result)
+    }
+
+    fn move_position_impl(
+        position_id: PositionId,
+        new_ticks_range: (Option<i32>, Option<i32>),
+        account_view: &mut AccountViewMut<'_, T>,
+    ) -> Result<(Amount, Amount, Liquidity)> {
+        ensure_here!(
+            account_view.account.positions.contains_item(&position_id),
+            ErrorKind::NotYourPosition
+        );
+        let pool_id = account_view
+            .position_to_pool_id
+            .try_inspect(&position_id, Clone::clone)?;
+        let factory = &mut *account_view.item_factory;
+        let now = account_view.block_timestamp;
+
+        let (fees, withdrawn, deposited, net_liquidity, tick_bounds) =
+            account_view.pools.try_update_or(
+                &pool_id,
+                // Inconsistent state: position is present in `position_to_pool_id`,
+                // but the pool doesn't exist
+                ErrorKind::InternalLogicError,
+                |Pool::V1(ref mut pool)| {
+                    let fee_level =
+                        pool.positions
+                            .try_inspect(&position_id, |Position::V1(pos)| {
+                                ensure_here!(pos.locked_by.is_none(), ErrorKind::PositionLocked);
+                                ensure_here!(
+                                    pos.locked_until.map_or(true, |until| until <= now),
+                                    ErrorKind::PositionTimeLocked
+                                );
+                                Ok(pos.fee_level)
+                            })??;
+                    let (fees, withdrawn) = pool.withdraw_fee_and_close_position(position_id)?;
+
+                    let position = PositionInit {
+                        amount_ranges: (
+                            Range {
+                                min: Amount::zero().into(),
+                                max: withdrawn.0.into(),
+                            },
+                            Range {
+                                min: Amount::zero().into(),
+                                max: withdrawn.1.into(),
+                            },
+                        ),
+                        ticks_range: new_ticks_range,
+                        auto_balance: false,
+                        locked_until: None,
+                    };
+                    let (deposited, net_liquidity) = pool.open_position(
+                        position,
+                        fee_level,
+                        position_id,
+                        account_view.min_position_liquidity,
+                        factory,
+                    )?;
+
+                    if let Some(min_amounts) = account_view
+                        .min_deposit_amounts
+                        .inspect(&pool_id, |min_amounts| *min_amounts)
+                    {
+                        ensure_here!(
+                            deposited.0 >= min_amounts.0 && deposited.1 >= min_amounts.1,
+                            ErrorKind::DepositTooSmall
+                        );
+                    }
+
+                    // Deposited amounts never exceed withdrawn ones,
+                    // so only the rest and fees are credited to account
+                    account_view
+                        .account
+                        .deposit(&pool_id.0, withdrawn.0 - deposited.0 + fees.0)
+                        .map_err(|e| error_here!(e))?;
+                    account_view
+                        .account
+                        .deposit(&pool_id.1, withdrawn.1 - deposited.1 + fees.1)
+                        .map_err(|e| error_here!(e))?;
+
+                    let tick_bounds = pool
+                        .positions
+                        .inspect(&position_id, |Position::V1(pos)| pos.tick_bounds)
+                        .ok_or(error_here!(ErrorKind::InternalLogicError))?;
+                    Ok((fees, withdrawn, deposited, net_liquidity, tick_bounds))
+                },
+            )?;
+
+        account_view
+            .logger
+            .log_harvest_fee_event(account_view.account_id, position_id, fees);
+
+        account_view.logger.log_move_position_event(
+            account_view.account_id,
+            position_id,
+            pool_id.as_refs(),
+            tick_bounds,
+            withdrawn,
+            deposited,
+            &net_liquidity,
+        );
+
+        account_view.pools.inspect(&pool_id, |Pool::V1(ref pool)| {
+            Self::log_pool_v0_state(
+                &pool_id,
+                pool,
+                None,
+                account_view.logger,
+                PoolUpdateReason::AddLiquidity,
+            );
+        });
+
+        Ok((deposited.0, deposited.1, net_liquidity))
+    }
+
+    /// Lock caller's position on behalf of `locker_id`, e.g. lending contract
+    /// which uses position as collateral. Locked position cannot be closed
+    /// until `locker_id` unlocks it.
+    pub fn lock_position(&mut self, position_id: PositionId, locker_id: AccountId) -> Result<()> {
+        self.ensure_payable_api_resumed()?;
+        self.with_caller_account_mut(|account_view| {
+            ensure_here!(
+                account_view.account.positions.contains_item(&position_id),
+                ErrorKind::NotYourPosition
+            );
+            let pool_id = account_view
+                .position_to_pool_id
+                .try_inspect(&position_id, Clone::clone)?;
+            #[allow(clippy::clone_on_copy)] // not all account ids are copyable
+            let locked_by = Some(locker_id.clone());
+            account_view.pools.try_update_or(
+                &pool_id,
+                // Inconsistent state: position is present in `position_to_pool_id`,
+                // but the pool doesn't exist
+                ErrorKind::InternalLogicError,
+                |Pool::V1(ref mut pool)| {
+                    pool.positions
+                        .try_update(&position_id, |Position::V1(ref mut pos)| {
+                            ensure_here!(pos.locked_by.is_none(), ErrorKind::PositionLocked);
+                            pos.locked_by = locked_by;
+                            Ok(())
+                        })
+                },
+            )?;
+            account_view
+                .logger
+                .log_lock_position_event(position_id, &locker_id);
+            Ok(())
+        })
+    }
+
+    /// Unlock position previously locked by caller
+    pub fn unlock_position(&mut self, position_id: PositionId) -> Result<()> {
+        self.ensure_payable_api_resumed()?;
+        let caller_id = self.get_caller_id();
+        let StateMembersMut {
+            contract: Contract::V1(ref mut contract),
+            logger,
+            ..
+        } = self.members_mut();
+
+        let pool_id = contract
+            .position_to_pool_id
+            .try_inspect(&position_id, Clone::clone)?;
+        contract.pools.try_update_or(
+            &pool_id,
+            ErrorKind::InternalLogicError,
+            |Pool::V1(ref mut pool)| {
+                pool.positions
+                    .try_update(&position_id, |Position::V1(ref mut pos)| {
+                        match pos.locked_by {
+                            None => Err(error_here!(ErrorKind::PositionNotLocked)),
+                            Some(ref locker_id) if *locker_id != caller_id => {
+                                Err(error_here!(ErrorKind::NotPositionLocker))
+                            }
+                            Some(_) => {
+                                pos.locked_by = None;
+                                Ok(())
+                            }
+                        }
+                    })
+            },
+        )?;
+        logger.log_unlock_position_event(position_id, &caller_id);
+        Ok(())
+    }
+
     pub fn withdraw_fee(&mut self, position_id: PositionId) -> Result<(Amount, Amount)> {
         self.ensure_payable_api_resumed()?;
         self.with_caller_account_mut(|mut account_view| {
@@ -1009,9 +1890,55 @@ impl<T: Types, S: StateMut<T>, SS: BorrowMut<S>> Dex<T, S, SS> {
         })
     }
 
+    /// Harvest fees of all caller's positions onto caller's deposits
+    ///
+    /// # Returns
+    /// Harvested amounts per position, in order of position's tokens
+    pub fn withdraw_all_fees(&mut self) -> Result<Vec<(PositionId, (Amount, Amount))>> {
+        self.ensure_payable_api_resumed()?;
+        self.with_caller_account_mut(|mut account_view| {
+            Self::withdraw_all_fees_impl(&mut account_view)
+        })
+    }
+
     fn withdraw_fee_impl(
         position_id: PositionId,
         account_view: &mut AccountViewMut<'_, T>,
+    ) -> Result<(Amount, Amount)> {
+        let amounts = Self::harvest_fee(position_id, account_view)?;
+
+        account_view
+            .logger
+            .log_harvest_fee_event(account_view.account_id, position_id, amounts);
+
+        Ok(amounts)
+    }
+
+    fn withdraw_all_fees_impl(
+        account_view: &mut AccountViewMut<'_, T>,
+    ) -> Result<Vec<(PositionId, (Amount, Amount))>> {
+        let position_ids = account_view
+            .account
+            .positions
+            .iter()
+            .map(|position_id| *position_id)
+            .collect_vec();
+        let harvests = position_ids
+            .into_iter()
+            .map(|position_id| Ok((position_id, Self::harvest_fee(position_id, account_view)?)))
+            .collect::<Result<Vec<_>>>()?;
+
+        account_view
+            .logger
+            .log_harvest_all_fees_event(account_view.account_id, &harvests);
+
+        Ok(harvests)
+    }
+
+    /// Withdraw fees of position onto owner's deposits, without logging
+    fn harvest_fee(
+        position_id: PositionId,
+        account_view: &mut AccountViewMut<'_, T>,
     ) -> Result<(Amount, Amount)> {
         // Get pool_id and at the same time check if position exists:
         let amounts = account_view
@@ -1029,7 +1956,7 @@ impl<T: Types, S: StateMut<T>, SS: BorrowMut<S>> Dex<T, S, SS> {
                     // Inconsistent state: position is present in `position_to_pool_id`,
                     // but the pool doesn't exist
                     ErrorKind::InternalLogicError,
-                    |Pool::V0(ref mut pool)| {
+                    |Pool::V1(ref mut pool)| {
                         let fees = pool.withdraw_fee(position_id)?;
                         account_view
                             .account
@@ -1044,45 +1971,98 @@ impl<T: Types, S: StateMut<T>, SS: BorrowMut<S>> Dex<T, S, SS> {
                 )
             })??;
 
-        account_view
-            .logger
-            .log_harvest_fee_event(position_id, amounts);
-
         Ok(amounts)
     }
 
+    /// Withdraw protocol fee accumulated by pool onto owner's account,
+    /// except for the part which goes to insurance reserve
+    ///
+    /// # Returns
+    /// Amounts deposited onto owner's account
     pub fn withdraw_protocol_fee(
         &mut self,
         pool_id: (TokenId, TokenId),
     ) -> Result<(Amount, Amount)> {
         self.ensure_payable_api_resumed()?;
+        self.ensure_caller_is_owner()?;
         let sender_id = self.get_caller_id();
-        let Contract::V0(ref mut contract) = self.contract_mut();
-        ensure_here!(contract.owner_id == sender_id, ErrorKind::PermissionDenied);
 
         let (pool_id, swapped) = PoolId::try_from_pair(pool_id).map_err(|e| error_here!(e))?;
-        let protocol_fees = contract
-            .pools
-            .try_update(&pool_id, |Pool::V0(ref mut pool)| {
-                let protocol_fees = pool.withdraw_protocol_fee()?;
+        let protocol_fees = self.take_protocol_fee(&pool_id)?;
 
-                contract
-                    .accounts
-                    .try_update(&sender_id, |Account::V0(ref mut account)| {
-                        account
-                            .deposit(&pool_id.0, protocol_fees.0)
-                            .map_err(|e| error_here!(e))?;
-                        account
-                            .deposit(&pool_id.1, protocol_fees.1)
-                            .map_err(|e| error_here!(e))?;
-
-                        Ok(())
-                    })?;
+        let Contract::V1(ref mut contract) = self.contract_mut();
+        contract
+            .accounts
+            .try_update(&sender_id, |Account::V1(ref mut account)| {
+                account
+                    .deposit(&pool_id.0, protocol_fees.0)
+                    .map_err(|e| error_here!(e))?;
+                account
+                    .deposit(&pool_id.1, protocol_fees.1)
+                    .map_err(|e| error_here!(e))?;
 
-                Ok(protocol_fees)
+                Ok(())
             })?;
+        self.logger_mut()
+            .log_withdraw_protocol_fee_event(pool_id.as_refs(), protocol_fees.as_refs());
         Ok(swap_if(swapped, protocol_fees))
     }
+
+    /// Take protocol fee accumulated by pool of any kind, moving its insurance share
+    /// to insurance reserve
+    ///
+    /// # Returns
+    /// Remaining amounts, ordered as pool tokens
+    fn take_protocol_fee(&mut self, pool_id: &PoolId) -> Result<(Amount, Amount)> {
+        let Contract::V1(ref mut contract) = self.contract_mut();
+        let insurance_fee_fraction = contract.insurance_fee_fraction;
+        let protocol_fees = match pool_kind::<T>(
+            &contract.pools,
+            &contract.classic_pools,
+            &contract.stable_pools,
+            pool_id,
+        ) {
+            Some(PoolKind::ClassicXY) => contract
+                .classic_pools
+                .try_update(pool_id, |ClassicPool::V0(ref mut pool)| {
+                    Ok(pool.withdraw_protocol_fee())
+                })?,
+            Some(PoolKind::Stable) => contract
+                .stable_pools
+                .try_update(pool_id, |StablePool::V0(ref mut pool)| {
+                    Ok(pool.withdraw_protocol_fee())
+                })?,
+            Some(PoolKind::Concentrated) | None => contract
+                .pools
+                .try_update(pool_id, |Pool::V1(ref mut pool)| {
+                    pool.withdraw_protocol_fee()
+                })?,
+        };
+        let insurance_shares = (
+            insurance_share(protocol_fees.0, insurance_fee_fraction),
+            insurance_share(protocol_fees.1, insurance_fee_fraction),
+        );
+        for (token_id, share) in [
+            (&pool_id.0, insurance_shares.0),
+            (&pool_id.1, insurance_shares.1),
+        ] {
+            if !share.is_zero() {
+                contract.insurance_reserve.update_or_insert(
+                    token_id,
+                    || Ok(Amount::zero()),
+                    |reserve, _| {
+                        *reserve += share;
+                        Ok(())
+                    },
+                )?;
+            }
+        }
+        Ok((
+            protocol_fees.0 - insurance_shares.0,
+            protocol_fees.1 - insurance_shares.1,
+        ))
+    }
+
     /// Common implementation of `execute_actions` and `deposit_execute_actions`, handles all actions
     /// with respect to execution context
     #[allow(clippy::too_many_lines)] // Because of lengthy worker functions invocations. Relatively simple otherwise
@@ -1111,8 +2091,21 @@ impl<T: Types, S: StateMut<T>, SS: BorrowMut<S>> Dex<T, S, SS> {
             #[allow(clippy::clone_on_copy)] // not all account ids are copyable
             self.register_account_and_then(account_id.clone(), register_account_cb)?;
             results.push(ActionResult::RegisterAccount);
+            gas_checkpoint!("register_account");
         }
         let protocol_fee_fraction = self.protocol_fee_fraction();
+        // Swaps which should be reported to swap hook, if any
+        let mut swap_notifications = Vec::new();
+        // Withdrawals which should be counted against withdraw rate limits
+        let mut withdrawals = Vec::new();
+        // Internal transfers which are debited from account, but not credited to receivers yet
+        let mut transfers = Vec::new();
+        // Extra fees charged by sandwich guards, to be put into insurance reserve
+        let mut extra_fees = Vec::new();
+        // Pools which liquidity was changed by positions, to re-rank them among top pools
+        #[cfg(feature = "near")]
+        #[cfg(feature = "smart-routing")]
+        let mut ranked_pools = Vec::new();
         // Process rest of actions
         self.with_account_mut(account_id, |mut account_view| {
             for action in actions {
@@ -1125,30 +2118,58 @@ impl<T: Types, S: StateMut<T>, SS: BorrowMut<S>> Dex<T, S, SS> {
                         ActionResult::RegisterTokens
                     }
                     Action::SwapExactIn(action) => {
+                        ensure_not_banned::<T>(
+                            account_view.banned_tokens,
+                            [&action.token_in, &action.token_out],
+                        )?;
+                        ensure_integration_registered::<T>(
+                            account_view.integrations,
+                            action.integration_id,
+                        )?;
                         let swap_result = Self::execute_swap_action(
                             account_id,
                             account_view.account,
                             account_view.pools,
+                            account_view.classic_pools,
+                            account_view.stable_pools,
                             account_view.logger,
                             &prev_swap_action,
                             Exact::In,
                             action,
                             protocol_fee_fraction,
+                            account_view.swap_guards,
+                            account_view.block_height,
+                            &mut extra_fees,
+                            &mut swap_notifications,
                         )?;
                         let swap_amount = swap_result.2;
                         prev_swap_action = Some(swap_result);
                         ActionResult::SwapExactIn(swap_amount)
                     }
                     Action::SwapExactOut(action) => {
+                        ensure_not_banned::<T>(
+                            account_view.banned_tokens,
+                            [&action.token_in, &action.token_out],
+                        )?;
+                        ensure_integration_registered::<T>(
+                            account_view.integrations,
+                            action.integration_id,
+                        )?;
                         let swap_result = Self::execute_swap_action(
                             account_id,
                             account_view.account,
                             account_view.pools,
+                            account_view.classic_pools,
+                            account_view.stable_pools,
                             account_view.logger,
                             &prev_swap_action,
                             Exact::Out,
                             action,
                             protocol_fee_fraction,
+                            account_view.swap_guards,
+                            account_view.block_height,
+                            &mut extra_fees,
+                            &mut swap_notifications,
                         )?;
                         let swap_amount = swap_result.2;
                         prev_swap_action = Some(swap_result);
@@ -1187,7 +2208,10 @@ impl<T: Types, S: StateMut<T>, SS: BorrowMut<S>> Dex<T, S, SS> {
                             extra,
                             account_view.logger,
                         )?;
-                        ActionResult::Withdraw(do_send.map(Box::new))
+                        ActionResult::Withdraw(do_send.map(|(amount, send)| {
+                            withdrawals.push((token_id, amount));
+                            Box::new(send)
+                        }))
                     }
                     Action::OpenPosition {
                         tokens: (token_a, token_b),
@@ -1201,9 +2225,23 @@ impl<T: Types, S: StateMut<T>, SS: BorrowMut<S>> Dex<T, S, SS> {
                             position,
                             &mut account_view,
                         )?;
+                        #[cfg(feature = "near")]
+                        #[cfg(feature = "smart-routing")]
+                        ranked_pools.push(
+                            PoolId::try_from_pair((token_a, token_b))
+                                .map_err(|e| error_here!(e))?
+                                .0,
+                        );
                         ActionResult::OpenPosition
                     }
                     Action::ClosePosition(position_id) => {
+                        #[cfg(feature = "near")]
+                        #[cfg(feature = "smart-routing")]
+                        ranked_pools.push(
+                            account_view
+                                .position_to_pool_id
+                                .try_inspect(&position_id, Clone::clone)?,
+                        );
                         Self::close_position_impl(position_id, &mut account_view)?;
                         ActionResult::ClosePosition
                     }
@@ -1211,8 +2249,80 @@ impl<T: Types, S: StateMut<T>, SS: BorrowMut<S>> Dex<T, S, SS> {
                         Self::withdraw_fee_impl(position_id, &mut account_view)?;
                         ActionResult::WithdrawFee
                     }
+                    Action::WithdrawAllFees => {
+                        Self::withdraw_all_fees_impl(&mut account_view)?;
+                        ActionResult::WithdrawAllFees
+                    }
+                    Action::TransferInternal {
+                        receiver_id,
+                        token_id,
+                        amount,
+                    } => {
+                        transfers.push(Self::debit_internal_transfer(
+                            &mut account_view,
+                            &receiver_id,
+                            &token_id,
+                            amount.into(),
+                        )?);
+                        ActionResult::TransferInternal
+                    }
+                    Action::SwapAndWithdraw {
+                        token_in,
+                        token_out,
+                        amount,
+                        amount_limit,
+                        receiver,
+                    } => {
+                        ensure_not_banned::<T>(
+                            account_view.banned_tokens,
+                            [&token_in, &token_out],
+                        )?;
+                        let (_, _, amount_out) = Self::execute_swap_action(
+                            account_id,
+                            account_view.account,
+                            account_view.pools,
+                            account_view.classic_pools,
+                            account_view.stable_pools,
+                            account_view.logger,
+                            &prev_swap_action,
+                            Exact::In,
+                            SwapAction {
+                                token_in,
+                                token_out: token_out.clone(),
+                                amount,
+                                amount_limit,
+                                integration_id: None,
+                            },
+                            protocol_fee_fraction,
+                            account_view.swap_guards,
+                            account_view.block_height,
+                            &mut extra_fees,
+                            &mut swap_notifications,
+                        )?;
+                        // Output is withdrawn, so it can't be chained into next swap
+                        prev_swap_action = None;
+                        // Zero output would be treated as request to withdraw whole balance
+                        ensure_here!(!amount_out.is_zero(), ErrorKind::Slippage);
+                        let do_send = Self::withdraw_impl(
+                            account_id,
+                            account_view.account,
+                            &token_out,
+                            amount_out,
+                            false,
+                            receiver,
+                            account_view.logger,
+                        )?;
+                        ActionResult::SwapAndWithdraw(
+                            amount_out,
+                            do_send.map(|(amount, send)| {
+                                withdrawals.push((token_out, amount));
+                                Box::new(send)
+                            }),
+                        )
+                    }
                 };
                 results.push(result);
+                gas_checkpoint!("action #{}", results.len() - 1);
             }
             Ok(())
         })?;
@@ -1220,23 +2330,68 @@ impl<T: Types, S: StateMut<T>, SS: BorrowMut<S>> Dex<T, S, SS> {
         if let Some(Some(_)) = deposit_data {
             ensure_here!(false, ErrorKind::DepositNotHandled);
         }
+        for transfer in transfers {
+            self.credit_internal_transfer(account_id, transfer)?;
+        }
+        gas_checkpoint!("transfers");
+        self.credit_extra_fees(extra_fees)?;
+        self.auto_harvest_swapped_pools(&swap_notifications)?;
+        gas_checkpoint!("auto_harvest");
+        self.consume_withdraw_limits(
+            withdrawals
+                .iter()
+                .map(|(token_id, amount)| (token_id, *amount)),
+            false,
+        )?;
+        gas_checkpoint!("withdraw_limits");
+        #[cfg(feature = "near")]
+        #[cfg(feature = "smart-routing")]
+        {
+            for notification in &swap_notifications {
+                ranked_pools.push(
+                    PoolId::try_from_pair((
+                        notification.token_in.clone(),
+                        notification.token_out.clone(),
+                    ))
+                    .map_err(|e| error_here!(e))?
+                    .0,
+                );
+            }
+            self.update_top_pools(ranked_pools)?;
+            gas_checkpoint!("top_pools");
+        }
+        for notification in swap_notifications {
+            self.notify_swap_hook_if_triggered(notification);
+        }
+        gas_checkpoint!("swap_hook");
         // Transform inner result into outer one
         let results = results
             .into_iter()
-            .map(|r| match r {
-                // Only withdrawal needs actual transformation
-                ActionResult::Withdraw(r) => ActionResult::Withdraw(r.map(|func| func(self))),
-                // Rest is just transformed as-is
-                ActionResult::RegisterAccount => ActionResult::RegisterAccount,
-                ActionResult::RegisterTokens => ActionResult::RegisterTokens,
-                ActionResult::SwapExactIn(amount) => ActionResult::SwapExactIn(amount),
-                ActionResult::SwapExactOut(amount) => ActionResult::SwapExactOut(amount),
-                ActionResult::Deposit => ActionResult::Deposit,
-                ActionResult::OpenPosition => ActionResult::OpenPosition,
-                ActionResult::ClosePosition => ActionResult::ClosePosition,
-                ActionResult::WithdrawFee => ActionResult::WithdrawFee,
+            .map(|r| {
+                Ok(match r {
+                    // Only withdrawal needs actual transformation
+                    ActionResult::Withdraw(r) => {
+                        ActionResult::Withdraw(r.map(|func| func(self)).transpose()?.flatten())
+                    }
+                    ActionResult::SwapAndWithdraw(amount, r) => ActionResult::SwapAndWithdraw(
+                        amount,
+                        r.map(|func| func(self)).transpose()?.flatten(),
+                    ),
+                    // Rest is just transformed as-is
+                    ActionResult::RegisterAccount => ActionResult::RegisterAccount,
+                    ActionResult::RegisterTokens => ActionResult::RegisterTokens,
+                    ActionResult::SwapExactIn(amount) => ActionResult::SwapExactIn(amount),
+                    ActionResult::SwapExactOut(amount) => ActionResult::SwapExactOut(amount),
+                    ActionResult::Deposit => ActionResult::Deposit,
+                    ActionResult::OpenPosition => ActionResult::OpenPosition,
+                    ActionResult::ClosePosition => ActionResult::ClosePosition,
+                    ActionResult::WithdrawFee => ActionResult::WithdrawFee,
+                    ActionResult::WithdrawAllFees => ActionResult::WithdrawAllFees,
+                    ActionResult::TransferInternal => ActionResult::TransferInternal,
+                })
             })
-            .collect();
+            .collect::<Result<_>>()?;
+        gas_checkpoint!("send_tokens");
 
         Ok(results)
     }
@@ -1268,6 +2423,7 @@ impl<T: Types, S: StateMut<T>, SS: BorrowMut<S>> Dex<T, S, SS> {
         actions: impl IntoIterator<Item = Action<S::SendTokensExtraParam>>,
     ) -> Result<Vec<S::SendTokensResult>> {
         self.ensure_payable_api_resumed()?;
+        self.ensure_tokens_not_banned([&deposit_token_id])?;
 
         ensure_here!(
             account_id == &self.get_initiator_id(),
@@ -1283,7 +2439,9 @@ impl<T: Types, S: StateMut<T>, SS: BorrowMut<S>> Dex<T, S, SS> {
             )?
             .into_iter()
             .filter_map(|r| {
-                if let ActionResult::Withdraw(Some(r)) = r {
+                if let ActionResult::Withdraw(Some(r)) | ActionResult::SwapAndWithdraw(_, Some(r)) =
+                    r
+                {
                     Some(r)
                 } else {
                     None
@@ -1300,6 +2458,8 @@ impl<T: Types, S: StateMut<T>, SS: BorrowMut<S>> Dex<T, S, SS> {
         actions: impl IntoIterator<Item = Action<S::SendTokensExtraParam>>,
     ) -> Result<(Vec<S::SendTokensResult>, Option<Amount>)> {
         self.ensure_payable_api_resumed()?;
+        // Actions may withdraw, so they're never allowed to session keys
+        self.ensure_not_session_key()?;
         let account_id = self.get_caller_id();
 
         let mut out_amount = None;
@@ -1313,6 +2473,10 @@ impl<T: Types, S: StateMut<T>, SS: BorrowMut<S>> Dex<T, S, SS> {
                     out_amount = Some(amount);
                     None
                 }
+                ActionResult::SwapAndWithdraw(amount, r) => {
+                    out_amount = Some(amount);
+                    r
+                }
                 _ => None,
             })
             .collect();
@@ -1325,12 +2489,34 @@ impl<T: Types, S: StateMut<T>, SS: BorrowMut<S>> Dex<T, S, SS> {
         tokens: &[TokenId],
         amount_in: Amount,
         min_amount_out: Amount,
+        integration_id: Option<IntegrationId>,
+    ) -> Result<(Amount, Amount)> {
+        self.swap_exact_in_with_hop_limits(tokens, amount_in, min_amount_out, &[], integration_id)
+    }
+
+    /// Same as `swap_exact_in`, but also fails as soon as output of any hop is below
+    /// respective `min_amounts_per_hop`, which is either empty or has one amount per hop
+    pub fn swap_exact_in_with_hop_limits(
+        &mut self,
+        tokens: &[TokenId],
+        amount_in: Amount,
+        min_amount_out: Amount,
+        min_amounts_per_hop: &[Amount],
+        integration_id: Option<IntegrationId>,
     ) -> Result<(Amount, Amount)> {
         ensure_here!(tokens.len() >= 2, ErrorKind::AtLeastOneSwap);
+        ensure_here!(
+            min_amounts_per_hop.is_empty() || min_amounts_per_hop.len() == tokens.len() - 1,
+            ErrorKind::InvalidParams
+        );
+        self.ensure_integration_registered(integration_id)?;
 
         let mut amount_out = amount_in;
-        for (token_in, token_out) in tokens.iter().tuple_windows() {
+        for (hop, (token_in, token_out)) in tokens.iter().tuple_windows().enumerate() {
             amount_out = self.swap(token_in, token_out, Exact::In, amount_out)?;
+            if let Some(min_hop_amount_out) = min_amounts_per_hop.get(hop) {
+                ensure_here!(amount_out >= *min_hop_amount_out, ErrorKind::Slippage);
+            }
         }
 
         ensure_here!(amount_out >= min_amount_out, ErrorKind::Slippage);
@@ -1339,12 +2525,13 @@ impl<T: Types, S: StateMut<T>, SS: BorrowMut<S>> Dex<T, S, SS> {
             // Should never fail - function requires at least 2 input tokens
             unreachable!()
         };
+        self.consume_session_volume(first_token, amount_in)?;
 
         let caller_id = &self.get_caller_id();
-        let Contract::V0(ref mut contract) = self.contract_mut();
+        let Contract::V1(ref mut contract) = self.contract_mut();
         contract
             .accounts
-            .try_update(caller_id, |Account::V0(ref mut account)| {
+            .try_update(caller_id, |Account::V1(ref mut account)| {
                 account
                     .withdraw(first_token, amount_in)
                     .map_err(|e| error_here!(e))?;
@@ -1358,8 +2545,18 @@ impl<T: Types, S: StateMut<T>, SS: BorrowMut<S>> Dex<T, S, SS> {
             (first_token, last_token),
             (&amount_in, &amount_out),
             &[], // TODO: add fees into swap event
+            integration_id,
         );
 
+        #[allow(clippy::clone_on_copy)] // not all account ids are copyable
+        self.notify_swap_hook_if_triggered(SwapNotification {
+            account_id: caller_id.clone(),
+            token_in: first_token.clone(),
+            token_out: last_token.clone(),
+            amount_in: amount_in.into(),
+            amount_out: amount_out.into(),
+        });
+
         Ok((amount_in, amount_out))
     }
 
@@ -1368,8 +2565,10 @@ impl<T: Types, S: StateMut<T>, SS: BorrowMut<S>> Dex<T, S, SS> {
         tokens: &[TokenId],
         amount_out: Amount,
         max_amount_in: Amount,
+        integration_id: Option<IntegrationId>,
     ) -> Result<(Amount, Amount)> {
         ensure_here!(tokens.len() >= 2, ErrorKind::AtLeastOneSwap);
+        self.ensure_integration_registered(integration_id)?;
 
         let mut amount_in = amount_out;
         for (token_in, token_out) in tokens.iter().tuple_windows() {
@@ -1382,12 +2581,13 @@ impl<T: Types, S: StateMut<T>, SS: BorrowMut<S>> Dex<T, S, SS> {
             // Should never fail - function requires at least 2 input tokens
             unreachable!()
         };
+        self.consume_session_volume(first_token, amount_in)?;
 
         let caller_id = &self.get_caller_id();
-        let Contract::V0(ref mut contract) = self.contract_mut();
+        let Contract::V1(ref mut contract) = self.contract_mut();
         contract
             .accounts
-            .try_update(caller_id, |Account::V0(ref mut account)| {
+            .try_update(caller_id, |Account::V1(ref mut account)| {
                 account
                     .withdraw(first_token, amount_in)
                     .map_err(|e| error_here!(e))?;
@@ -1401,8 +2601,18 @@ impl<T: Types, S: StateMut<T>, SS: BorrowMut<S>> Dex<T, S, SS> {
             (first_token, last_token),
             (&amount_in, &amount_out),
             &[], // TODO: add fees into swap event
+            integration_id,
         );
 
+        #[allow(clippy::clone_on_copy)] // not all account ids are copyable
+        self.notify_swap_hook_if_triggered(SwapNotification {
+            account_id: caller_id.clone(),
+            token_in: first_token.clone(),
+            token_out: last_token.clone(),
+            amount_in: amount_in.into(),
+            amount_out: amount_out.into(),
+        });
+
         Ok((amount_in, amount_out))
     }
 
@@ -1414,25 +2624,81 @@ impl<T: Types, S: StateMut<T>, SS: BorrowMut<S>> Dex<T, S, SS> {
         amount: Amount,
     ) -> Result<Amount> {
         self.ensure_payable_api_resumed()?;
+        self.ensure_tokens_not_banned([token_in, token_out])?;
         let (pool_id, swapped) = PoolId::try_from_pair((token_in.clone(), token_out.clone()))
             .map_err(|e| error_here!(e))?; // todo: avoid .clone()
         let direction = if swapped { Side::Right } else { Side::Left };
+        let block_height = self.get_block_height();
 
-        let Contract::V0(ref mut contract) = self.contract_mut();
-        let amount = contract
-            .pools
-            .try_update(&pool_id, |Pool::V0(ref mut pool)| {
-                pool.swap(
-                    direction,
-                    exact_in_or_out,
-                    amount,
-                    contract.protocol_fee_fraction,
-                )
-            })?;
+        let StateMembersMut {
+            contract: Contract::V1(ref mut contract),
+            logger,
+            ..
+        } = self.members_mut();
+        let swapped_amounts = match pool_kind::<T>(
+            &contract.pools,
+            &contract.classic_pools,
+            &contract.stable_pools,
+            &pool_id,
+        ) {
+            Some(PoolKind::ClassicXY) => Some(Self::swap_classic(
+                &mut contract.classic_pools,
+                &pool_id,
+                direction,
+                exact_in_or_out,
+                amount,
+                contract.protocol_fee_fraction,
+                logger,
+            )?),
+            Some(PoolKind::Stable) => Some(Self::swap_stable(
+                &mut contract.stable_pools,
+                &pool_id,
+                direction,
+                exact_in_or_out,
+                amount,
+                contract.protocol_fee_fraction,
+                logger,
+            )?),
+            Some(PoolKind::Concentrated) | None => None,
+        };
+        let amounts = if let Some(amounts) = swapped_amounts {
+            amounts
+        } else {
+            let (amounts, before) =
+                contract
+                    .pools
+                    .try_update(&pool_id, |Pool::V1(ref mut pool)| {
+                        #[cfg(feature = "batch-auction")]
+                        ensure_here!(pool.batch_auction.is_none(), ErrorKind::PoolInBatchMode);
+                        seed_period::ensure_not_seeding(pool, block_height)?;
+                        let before = PoolStateBefore::new(pool);
+                        let swapped = pool.swap(
+                            direction,
+                            exact_in_or_out,
+                            amount,
+                            contract.protocol_fee_fraction,
+                        )?;
+                        let amounts = match exact_in_or_out {
+                            Exact::In => (amount, swapped),
+                            Exact::Out => (swapped, amount),
+                        };
+                        Ok((amounts, before))
+                    })?;
 
-        self.log_pool_state(&pool_id, PoolUpdateReason::Swap)?;
+            self.log_pool_state(&pool_id, Some(&before), PoolUpdateReason::Swap)?;
+            #[cfg(feature = "near")]
+            #[cfg(feature = "smart-routing")]
+            self.update_top_pools([pool_id.clone()])?;
+            self.auto_harvest(&pool_id)?;
+            amounts
+        };
+        let (amount_in, amount_out) =
+            self.apply_swap_guard(&pool_id, (token_in, token_out), exact_in_or_out, amounts)?;
 
-        Ok(amount)
+        Ok(match exact_in_or_out {
+            Exact::In => amount_out,
+            Exact::Out => amount_in,
+        })
     }
 
     #[cfg(feature = "near")]
@@ -1444,6 +2710,9 @@ impl<T: Types, S: StateMut<T>, SS: BorrowMut<S>> Dex<T, S, SS> {
         self.ensure_payable_api_resumed()?;
 
         let amount_pairs = self.multiple_path_swap(paths, Exact::In)?;
+        for (path, (amount_in, _)) in paths.iter().zip(&amount_pairs) {
+            self.consume_session_volume(&path.tokens[0], *amount_in)?;
+        }
 
         ensure_here!(
             amount_pairs
@@ -1455,14 +2724,14 @@ impl<T: Types, S: StateMut<T>, SS: BorrowMut<S>> Dex<T, S, SS> {
         );
 
         let caller_id = &self.get_caller_id();
-        let Contract::V0(ref mut contract) = self.contract_mut();
+        let Contract::V1(ref mut contract) = self.contract_mut();
 
         for (i, path) in paths.iter().enumerate() {
             //unfallible unwrap as the length of `amount_pairs` is same as the length of `paths`
             let (amount_in, amount_out) = amount_pairs.get(i).unwrap();
             contract
                 .accounts
-                .try_update(caller_id, |Account::V0(ref mut account)| {
+                .try_update(caller_id, |Account::V1(ref mut account)| {
                     account
                         .withdraw(&path.tokens[0], *amount_in)
                         .map_err(|e| error_here!(e))?;
@@ -1484,6 +2753,9 @@ impl<T: Types, S: StateMut<T>, SS: BorrowMut<S>> Dex<T, S, SS> {
         self.ensure_payable_api_resumed()?;
 
         let amount_pairs = self.multiple_path_swap(paths, Exact::Out)?;
+        for (path, (amount_in, _)) in paths.iter().zip(&amount_pairs) {
+            self.consume_session_volume(&path.tokens[0], *amount_in)?;
+        }
 
         ensure_here!(
             amount_pairs
@@ -1495,14 +2767,14 @@ impl<T: Types, S: StateMut<T>, SS: BorrowMut<S>> Dex<T, S, SS> {
         );
 
         let caller_id = &self.get_caller_id();
-        let Contract::V0(ref mut contract) = self.contract_mut();
+        let Contract::V1(ref mut contract) = self.contract_mut();
 
         for (i, path) in paths.iter().enumerate() {
             //unfallible unwrap as the length of `amount_pairs` is same as the length of `paths`
             let (amount_in, amount_out) = amount_pairs.get(i).unwrap();
             contract
                 .accounts
-                .try_update(caller_id, |Account::V0(ref mut account)| {
+                .try_update(caller_id, |Account::V1(ref mut account)| {
                     account
                         .withdraw(path.tokens.first().unwrap(), *amount_in)
                         .map_err(|e| error_here!(e))?;
@@ -1540,22 +2812,32 @@ impl<T: Types, S: StateMut<T>, SS: BorrowMut<S>> Dex<T, S, SS> {
     ///
     /// NB: returns `Option` with swap result just for convenience,
     /// to simplify assignment to `prev_swap_result`
+    ///
+    /// Performed swap is appended to `swap_notifications`, to be reported to swap hook
+    /// after whole batch succeeds
     #[allow(clippy::too_many_arguments)]
     fn execute_swap_action(
         account_id: &AccountId,
-        account: &mut AccountV0<T>,
+        account: &mut AccountV1<T>,
         pools: &mut state_types::PoolsMap<T>,
+        classic_pools: &mut state_types::ClassicPoolsMap<T>,
+        stable_pools: &mut state_types::StablePoolsMap<T>,
         logger: &mut dyn Logger,
         prev_swap_result: &Option<(TokenId, Exact, Amount)>,
         exact: Exact,
         action: SwapAction,
         protocol_fee_fraction: BasisPoints,
+        swap_guards: &mut state_types::SwapGuardsMap<T>,
+        block_height: u64,
+        extra_fees: &mut Vec<(TokenId, Amount)>,
+        swap_notifications: &mut Vec<SwapNotification>,
     ) -> Result<(TokenId, Exact, Amount)> {
         let SwapAction {
             token_in,
             token_out,
             amount,
             amount_limit,
+            integration_id,
         } = action;
         let amount: Option<Amount> = amount.map(Into::into);
         let amount_limit: Amount = amount_limit.into();
@@ -1594,127 +2876,195 @@ impl<T: Types, S: StateMut<T>, SS: BorrowMut<S>> Dex<T, S, SS> {
         )?;
         let (pool_id, swapped) = PoolId::try_from_pair((token_in.clone(), token_out.clone()))
             .map_err(|e| error_here!(e))?;
+        let extra_fee_rate =
+            check_swap_guard::<T>(swap_guards, &pool_id, account_id, &token_in, block_height)?;
+        let extra_fee_token = match exact {
+            Exact::In => &token_out,
+            Exact::Out => &token_in,
+        };
 
-        let (amount_in, amount_out) = pools.try_update(&pool_id, |Pool::V0(ref mut pool)| {
-            let side = if swapped { Side::Right } else { Side::Left };
-
-            let (amount_in, amount_out) = match exact {
-                Exact::In => {
-                    let amount_in = amount;
-                    let amount_out =
-                        pool.swap(side, Exact::In, amount_in, protocol_fee_fraction)?;
-                    ensure_here!(amount_out >= amount_limit, ErrorKind::Slippage);
-                    (amount_in, amount_out)
-                }
-                Exact::Out => {
-                    let amount_out = amount;
-                    let amount_in =
-                        pool.swap(side, Exact::Out, amount_out, protocol_fee_fraction)?;
-                    ensure_here!(amount_in <= amount_limit, ErrorKind::Slippage);
-                    (amount_in, amount_out)
-                }
+        let side = if swapped { Side::Right } else { Side::Left };
+        let swapped_amounts = match pool_kind::<T>(pools, classic_pools, stable_pools, &pool_id) {
+            Some(PoolKind::ClassicXY) => Some(Self::swap_classic(
+                classic_pools,
+                &pool_id,
+                side,
+                exact,
+                amount,
+                protocol_fee_fraction,
+                logger,
+            )?),
+            Some(PoolKind::Stable) => Some(Self::swap_stable(
+                stable_pools,
+                &pool_id,
+                side,
+                exact,
+                amount,
+                protocol_fee_fraction,
+                logger,
+            )?),
+            Some(PoolKind::Concentrated) | None => None,
+        };
+        let (amount_in, amount_out) = if let Some(amounts) = swapped_amounts {
+            let ((amount_in, amount_out), extra_fee) =
+                charge_extra_fee(exact, amounts, extra_fee_rate);
+            extra_fees.push((extra_fee_token.clone(), extra_fee));
+            let within_limit = match exact {
+                Exact::In => amount_out >= amount_limit,
+                Exact::Out => amount_in <= amount_limit,
             };
+            ensure_here!(within_limit, ErrorKind::Slippage);
             account
                 .withdraw(&token_in, amount_in)
                 .map_err(|e| error_here!(e))?;
             account
                 .deposit(&token_out, amount_out)
                 .map_err(|e| error_here!(e))?;
-
-            // Log swap event and pool state
             logger.log_swap_event(
                 account_id,
                 (&token_in, &token_out),
                 (&amount_in, &amount_out),
-                &[], // TODO: add fees into swap event
+                &[],
+                integration_id,
             );
-            Self::log_pool_v0_state(&pool_id, pool, logger, PoolUpdateReason::Swap);
+            (amount_in, amount_out)
+        } else {
+            pools.try_update(&pool_id, |Pool::V1(ref mut pool)| {
+                #[cfg(feature = "batch-auction")]
+                ensure_here!(pool.batch_auction.is_none(), ErrorKind::PoolInBatchMode);
+                seed_period::ensure_not_seeding(pool, block_height)?;
+                let before = PoolStateBefore::new(pool);
+
+                let amounts = match exact {
+                    Exact::In => (
+                        amount,
+                        pool.swap(side, Exact::In, amount, protocol_fee_fraction)?,
+                    ),
+                    Exact::Out => (
+                        pool.swap(side, Exact::Out, amount, protocol_fee_fraction)?,
+                        amount,
+                    ),
+                };
+                let ((amount_in, amount_out), extra_fee) =
+                    charge_extra_fee(exact, amounts, extra_fee_rate);
+                extra_fees.push((extra_fee_token.clone(), extra_fee));
+                let within_limit = match exact {
+                    Exact::In => amount_out >= amount_limit,
+                    Exact::Out => amount_in <= amount_limit,
+                };
+                ensure_here!(within_limit, ErrorKind::Slippage);
+                account
+                    .withdraw(&token_in, amount_in)
+                    .map_err(|e| error_here!(e))?;
+                account
+                    .deposit(&token_out, amount_out)
+                    .map_err(|e| error_here!(e))?;
 
-            Ok((amount_in, amount_out))
-        })?;
+                // Log swap event and pool state
+                logger.log_swap_event(
+                    account_id,
+                    (&token_in, &token_out),
+                    (&amount_in, &amount_out),
+                    &[], // TODO: add fees into swap event
+                    integration_id,
+                );
+                Self::log_pool_v0_state(
+                    &pool_id,
+                    pool,
+                    Some(&before),
+                    logger,
+                    PoolUpdateReason::Swap,
+                );
+
+                Ok((amount_in, amount_out))
+            })?
+        };
+        #[allow(clippy::clone_on_copy)] // not all account ids are copyable
+        swap_notifications.push(SwapNotification {
+            account_id: account_id.clone(),
+            token_in: token_in.clone(),
+            token_out: token_out.clone(),
+            amount_in: amount_in.into(),
+            amount_out: amount_out.into(),
+        });
         Ok(match exact {
             Exact::In => (token_out, exact, amount_out),
             Exact::Out => (token_in, exact, amount_in),
         })
     }
 
-    fn log_pool_state(&mut self, pool_id: &PoolId, reason: PoolUpdateReason) -> Result<()> {
+    fn log_pool_state(
+        &mut self,
+        pool_id: &PoolId,
+        before: Option<&PoolStateBefore>,
+        reason: PoolUpdateReason,
+    ) -> Result<()> {
         let StateMembersMut {
-            contract: Contract::V0(ref mut contract),
+            contract: Contract::V1(ref mut contract),
             logger,
             ..
         } = self.members_mut();
 
-        contract.pools.try_inspect(pool_id, |Pool::V0(ref pool)| {
-            Self::log_pool_v0_state(pool_id, pool, logger, reason);
+        contract.pools.try_inspect(pool_id, |Pool::V1(ref pool)| {
+            Self::log_pool_v0_state(pool_id, pool, before, logger, reason);
         })
     }
 
+    /// Logs pool state after update
+    ///
+    /// # Parameters
+    /// * `before` - pool state captured before update, used to evaluate LP fees earned
+    ///     during update and to report only levels changed by it; `None` if update
+    ///     cannot produce any fees
     fn log_pool_v0_state(
         pool_id: &PoolId,
-        pool: &PoolV0<T>,
+        pool: &PoolV1<T>,
+        before: Option<&PoolStateBefore>,
         logger: &mut dyn Logger,
         reason: PoolUpdateReason,
     ) {
-        let position_reserves = pool.position_reserves();
-        let amounts_a = position_reserves.map(|(left, _right)| Amount::try_from(left).unwrap());
-        let amounts_b = position_reserves.map(|(_left, right)| Amount::try_from(right).unwrap());
-        let spot_sqrtprices = pool.spot_sqrtprices(Side::Right);
-        let liquidities = pool
-            .liquidities()
-            .map(|liq| liq.try_into().unwrap_or_default());
+        #[cfg(feature = "tracing")]
+        for record in super::tracing::take_records() {
+            logger.log(format_args!("TRACE: {record}"));
+        }
+
+        if !logger.is_event_enabled(EventKind::UpdatePoolState) {
+            return;
+        }
+
+        let levels = PoolLevelsReport::new(pool);
+
+        let lp_fees_after = pool.acc_lp_fees_per_level();
+        let lp_fee_delta = |level: usize, side: Side| {
+            before.map_or(0, |before| {
+                let earned = lp_fees_after[level][side] - before.lp_fees[level][side];
+                Amount::try_from(earned.floor()).unwrap_or_default()
+            })
+        };
+        let lp_fees_a: RawFeeLevelsArray<Amount> =
+            std::array::from_fn(|level| lp_fee_delta(level, Side::Left));
+        let lp_fees_b: RawFeeLevelsArray<Amount> =
+            std::array::from_fn(|level| lp_fee_delta(level, Side::Right));
+
+        let changed_levels: Option<RawFeeLevelsArray<bool>> = before.map(|before| {
+            std::array::from_fn(|level| {
+                levels.differs_at(&before.levels, level)
+                    || lp_fees_a[level] != 0
+                    || lp_fees_b[level] != 0
+            })
+        });
 
         logger.log_update_pool_state_event(
             reason,
             (&pool_id.0, &pool_id.1),
-            &amounts_a,
-            &amounts_b,
-            &spot_sqrtprices,
-            &liquidities,
+            &levels.amounts_a,
+            &levels.amounts_b,
+            &levels.spot_sqrtprices,
+            &levels.liquidities,
+            (&lp_fees_a, &lp_fees_b),
+            pool.top_active_level,
+            pool.active_side,
+            changed_levels.as_ref(),
         );
     }
-
-    #[cfg(feature = "smart-routing")]
-    pub fn update_top_pools(&mut self) -> Result<HashMap<TokenId, [TokenId; NUM_TOP_POOLS]>> {
-        let StateMembersMut {
-            contract: Contract::V0(ref mut contract),
-            item_factory,
-            ..
-        } = self.members_mut();
-        let factory = RefCell::new(item_factory);
-
-        let mut top_pools: HashMap<TokenId, [TokenId; NUM_TOP_POOLS]> = HashMap::new();
-        for (key, value) in contract.token_connections.iter() {
-            let mut local_btreeset: BTreeSet<(TokenId, Liquidity)> = BTreeSet::new();
-            let mut top_pools_set = factory.borrow_mut().new_tokens_array_set();
-
-            for item in value.iter() {
-                let (pool_id, _) = PoolId::try_from_pair((key.clone(), item.clone()))
-                    .map_err(|e| error_here!(e))?;
-                let total_liquidity = contract
-                    .pools
-                    .try_inspect(&pool_id, |Pool::V0(ref pool)| pool.total_liquidity())?;
-                local_btreeset.insert((item.clone(), total_liquidity));
-            }
-
-            let res_vec: Vec<TokenId> = local_btreeset
-                .into_iter()
-                .rev()
-                .take(NUM_TOP_POOLS)
-                .map(|(token_id, _)| token_id)
-                .collect();
-            let res_array: [TokenId; NUM_TOP_POOLS] = res_vec
-                .try_into()
-                .map_err(|_| error_here!(ErrorKind::InternalTopPoolsNumberMismatch))?;
-
-            for item in &res_array {
-                top_pools_set.add_item(item.clone());
-            }
-
-            top_pools.insert(key.clone(), res_array);
-            contract.top_pools.insert(key.clone(), top_pools_set);
-        }
-
-        Ok(top_pools)
-    }
 }
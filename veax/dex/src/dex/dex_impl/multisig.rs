@@ -0,0 +1,183 @@
+//! Multisig owner: owner actions are performed by a set of admins, once enough of them approve,
+//! so contract doesn't have to rely on external multisig contract as its owner
+use super::timelock::validate_admin_action;
+use super::Dex;
+use crate::chain::AccountId;
+use crate::dex::errors::{ErrorKind, Result};
+use crate::dex::{
+    ActionHash, AdminAction, Contract, Map, MapRemoveKey, PendingApproval, Set, State, StateMut,
+    Types,
+};
+use crate::ensure_here;
+use std::borrow::{Borrow, BorrowMut};
+
+impl<T: Types, S: State<T>, SS: Borrow<S>> Dex<T, S, SS> {
+    /// Accounts which collectively act as owner, and number of approvals needed to perform
+    /// owner action; zero threshold means multisig is disabled
+    pub fn get_admins(&self) -> (Vec<AccountId>, u16) {
+        let Contract::V1(ref contract) = self.contract();
+        let admins = contract
+            .admins
+            .iter()
+            .map(|admin| AccountId::clone(&admin))
+            .collect();
+        (admins, contract.admin_threshold)
+    }
+
+    /// Owner actions proposed by admins and waiting for approvals, with their hashes
+    pub fn get_pending_approvals(&self) -> Vec<(ActionHash, PendingApproval)> {
+        let Contract::V1(ref contract) = self.contract();
+        contract
+            .pending_approvals
+            .iter()
+            .map(|(hash, pending)| (*hash, PendingApproval::clone(&pending)))
+            .collect()
+    }
+}
+
+impl<T: Types, S: StateMut<T>, SS: BorrowMut<S>> Dex<T, S, SS> {
+    /// Enable multisig by setting admins and approval threshold.
+    /// Once multisig is enabled, admins can be changed only through `AdminAction::SetAdmins`
+    ///
+    /// # Parameters
+    /// * `admins` - accounts which collectively act as owner
+    /// * `threshold` - number of admins' approvals needed to perform owner action;
+    ///     must be between 1 and number of admins
+    pub fn set_admins(&mut self, admins: Vec<AccountId>, threshold: u16) -> Result<()> {
+        self.ensure_payable_api_resumed()?;
+        self.ensure_caller_is_owner()?;
+        self.ensure_multisig_disabled()?;
+        self.ensure_timelock_disabled()?;
+        self.apply_admin_action(AdminAction::SetAdmins { admins, threshold })
+    }
+
+    /// Propose owner action, counting as caller's approval
+    ///
+    /// # Parameters
+    /// * `action` - action to propose; it's validated right away
+    ///
+    /// # Returns
+    /// Hash of proposed action, which other admins should pass to `approve_admin_action`
+    #[cfg(feature = "near")]
+    pub fn propose_admin_action(&mut self, action: AdminAction) -> Result<ActionHash> {
+        use near_sdk::borsh::BorshSerialize;
+        use sha2::{Digest as _, Sha256};
+
+        self.ensure_payable_api_resumed()?;
+        self.ensure_caller_is_admin()?;
+        validate_admin_action(&action)?;
+
+        let hash: ActionHash = Sha256::digest(action.try_to_vec().unwrap()).into();
+        let Contract::V1(ref mut contract) = self.contract_mut();
+        if !contract.pending_approvals.contains_key(&hash) {
+            contract.pending_approvals.insert(
+                hash,
+                PendingApproval {
+                    action,
+                    approvals: Vec::new(),
+                },
+            );
+        }
+        self.approve_admin_action(hash)?;
+        Ok(hash)
+    }
+
+    /// Approve owner action proposed by some admin. Once number of approvals reaches threshold,
+    /// action is executed, or scheduled if timelock is enabled
+    ///
+    /// # Parameters
+    /// * `hash` - hash of proposed action
+    pub fn approve_admin_action(&mut self, hash: ActionHash) -> Result<()> {
+        self.ensure_payable_api_resumed()?;
+        self.ensure_caller_is_admin()?;
+
+        let approver = self.get_caller_id();
+        let Contract::V1(ref mut contract) = self.contract_mut();
+        let threshold = contract.admin_threshold;
+        let pending = contract.pending_approvals.try_update(&hash, |pending| {
+            ensure_here!(
+                !pending.approvals.contains(&approver),
+                ErrorKind::AlreadyApproved
+            );
+            pending.approvals.push(approver.clone());
+            Ok(PendingApproval::clone(pending))
+        })?;
+        let approvals = u16::try_from(pending.approvals.len()).unwrap_or(u16::MAX);
+
+        self.logger_mut()
+            .log_action_approved_event(&hash, &pending.action, &approver, approvals);
+
+        if approvals < threshold {
+            return Ok(());
+        }
+
+        let Contract::V1(ref mut contract) = self.contract_mut();
+        contract.pending_approvals.remove(&hash);
+        if contract.timelock_delay > 0 {
+            self.schedule_admin_action_unchecked(pending.action);
+            Ok(())
+        } else {
+            self.apply_admin_action(pending.action)
+        }
+    }
+
+    /// Owner actions which have `AdminAction` counterpart can be performed by owner alone
+    /// only while multisig is disabled; other owner actions are not subject to multisig
+    pub(super) fn ensure_multisig_disabled(&self) -> Result<()> {
+        let Contract::V1(ref contract) = self.contract();
+        ensure_here!(contract.admin_threshold == 0, ErrorKind::MultisigEnabled);
+        Ok(())
+    }
+
+    /// Scheduled owner actions and staged upgrades are managed by owner while multisig
+    /// is disabled, and by admins otherwise
+    pub(super) fn ensure_caller_is_owner_or_admin(&self) -> Result<()> {
+        let Contract::V1(ref contract) = self.contract();
+        if contract.admin_threshold == 0 {
+            self.ensure_caller_is_owner()
+        } else {
+            self.ensure_caller_is_admin()
+        }
+    }
+
+    /// Replace admins and threshold, without any permission checks.
+    /// Pending approvals are dropped, since they were collected from previous admins
+    pub(super) fn set_admins_unchecked(&mut self, admins: Vec<AccountId>, threshold: u16) {
+        let Contract::V1(ref mut contract) = self.contract_mut();
+        contract.admins.clear();
+        for admin in admins {
+            contract.admins.add_item(admin);
+        }
+        contract.admin_threshold = threshold;
+        contract.pending_approvals.clear();
+    }
+
+    fn ensure_caller_is_admin(&self) -> Result<()> {
+        let Contract::V1(ref contract) = self.contract();
+        ensure_here!(
+            contract.admin_threshold > 0 && contract.admins.contains_item(&self.get_caller_id()),
+            ErrorKind::PermissionDenied
+        );
+        Ok(())
+    }
+}
+
+/// Threshold must be reachable by listed admins; empty list with zero threshold disables multisig
+pub(super) fn validate_admins(admins: &[AccountId], threshold: u16) -> Result<()> {
+    let mut unique_admins = admins.to_vec();
+    unique_admins.sort();
+    unique_admins.dedup();
+    ensure_here!(
+        unique_admins.len() == admins.len(),
+        ErrorKind::InvalidParams
+    );
+    ensure_here!(
+        usize::from(threshold) <= admins.len(),
+        ErrorKind::InvalidParams
+    );
+    ensure_here!(
+        (threshold == 0) == admins.is_empty(),
+        ErrorKind::InvalidParams
+    );
+    Ok(())
+}
@@ -0,0 +1,275 @@
+//! Conditional orders (stop-loss / take-profit), executed by keepers
+//!
+//! User posts an order, which any account may execute once pool price reaches trigger price.
+//! Input amount isn't reserved: it's taken from owner's deposits at execution,
+//! together with keeper fee, which is credited to executing account.
+use super::{ensure_not_banned, fee_levels, pool_kind, Dex};
+use crate::chain::{AccountId, Amount, TokenId};
+use crate::dex::errors::{ErrorKind, Result};
+use crate::dex::traits::AccountExtra;
+use crate::dex::util_types::{PoolId, Side};
+use crate::dex::{
+    ClassicPool, ConditionalOrder, Contract, Exact, Float, Map, MapRemoveKey, OrderTrigger, Pool,
+    PoolKind, StablePool, State, StateMut, SwapAction, Types,
+};
+use crate::{ensure_here, error_here};
+use num_traits::Zero;
+use std::borrow::{Borrow, BorrowMut};
+
+impl<T: Types, S: State<T>, SS: Borrow<S>> Dex<T, S, SS> {
+    /// Conditional order by id, if it's neither executed nor cancelled yet
+    pub fn get_order(&self, order_id: u64) -> Option<ConditionalOrder> {
+        let Contract::V1(ref contract) = self.contract();
+        contract.orders.inspect(&order_id, ConditionalOrder::clone)
+    }
+
+    /// Conditional orders of account, with their ids
+    pub fn get_orders(&self, account_id: &AccountId) -> Vec<(u64, ConditionalOrder)> {
+        let Contract::V1(ref contract) = self.contract();
+        contract
+            .orders
+            .iter()
+            .filter(|(_, order)| order.owner == *account_id)
+            .map(|(id, order)| (*id, ConditionalOrder::clone(&order)))
+            .collect()
+    }
+
+    /// Current price of `token_in` in units of `token_out`, as checked against order triggers
+    ///
+    /// Concentrated pools report spot price of the lowest fee level which has one
+    pub fn get_order_price(&self, token_in: &TokenId, token_out: &TokenId) -> Result<Float> {
+        let (pool_id, swapped) = PoolId::try_from_pair((token_in.clone(), token_out.clone()))
+            .map_err(|e| error_here!(e))?;
+        let side = Side::from_swapped(swapped);
+        let Contract::V1(ref contract) = self.contract();
+        let kind = pool_kind::<T>(
+            &contract.pools,
+            &contract.classic_pools,
+            &contract.stable_pools,
+            &pool_id,
+        );
+        let price = match kind {
+            Some(PoolKind::Concentrated) => {
+                contract.pools.try_inspect(&pool_id, |Pool::V1(ref pool)| {
+                    fee_levels()
+                        .into_iter()
+                        .map(|level| pool.spot_price(side.opposite(), level))
+                        .find(|price| !price.is_zero())
+                        .unwrap_or_else(Float::zero)
+                })?
+            }
+            Some(PoolKind::ClassicXY) => {
+                contract
+                    .classic_pools
+                    .try_inspect(&pool_id, |ClassicPool::V0(ref pool)| {
+                        if pool.reserves[side].is_zero() {
+                            Float::zero()
+                        } else {
+                            Float::from(pool.reserves[side.opposite()])
+                                / Float::from(pool.reserves[side])
+                        }
+                    })?
+            }
+            Some(PoolKind::Stable) => {
+                contract
+                    .stable_pools
+                    .try_inspect(&pool_id, |StablePool::V0(ref pool)| {
+                        let price = pool.spot_price()?;
+                        Ok(match side {
+                            Side::Right if !price.is_zero() => price.recip(),
+                            _ => price,
+                        })
+                    })??
+            }
+            None => return Err(error_here!(ErrorKind::PoolNotRegistered)),
+        };
+        ensure_here!(!price.is_zero(), ErrorKind::InsufficientLiquidity);
+        Ok(price)
+    }
+}
+
+impl<T: Types, S: StateMut<T>, SS: BorrowMut<S>> Dex<T, S, SS> {
+    /// Post conditional order of caller, to be executed by keeper once trigger condition holds
+    ///
+    /// # Parameters
+    /// * `tokens` - input and output tokens; both must be registered by caller
+    /// * `amounts` - exact input amount and minimal output amount
+    /// * `trigger` - whether order executes when price drops to `trigger_price` or rises to it
+    /// * `trigger_price` - price of input token in units of output token
+    /// * `expires_at` - moment after which order can't be executed, in seconds
+    /// * `keeper_fee` - amount of input token paid to keeper on execution
+    ///
+    /// # Returns
+    /// Id of placed order
+    pub fn place_order(
+        &mut self,
+        tokens: (TokenId, TokenId),
+        amounts: (Amount, Amount),
+        trigger: OrderTrigger,
+        trigger_price: Float,
+        expires_at: u64,
+        keeper_fee: Amount,
+    ) -> Result<u64> {
+        self.ensure_payable_api_resumed()?;
+        ensure_here!(!amounts.0.is_zero(), ErrorKind::InvalidParams);
+        ensure_here!(!trigger_price.is_zero(), ErrorKind::InvalidParams);
+        ensure_here!(
+            expires_at > self.get_block_timestamp(),
+            ErrorKind::OrderExpired
+        );
+        let (pool_id, _) = PoolId::try_from_pair(tokens.clone()).map_err(|e| error_here!(e))?;
+        let owner = self.get_caller_id();
+        self.with_account_mut(&owner, |account_view| {
+            ensure_not_banned::<T>(account_view.banned_tokens, [&tokens.0, &tokens.1])?;
+            ensure_here!(
+                pool_kind::<T>(
+                    account_view.pools,
+                    account_view.classic_pools,
+                    account_view.stable_pools,
+                    &pool_id,
+                )
+                .is_some(),
+                ErrorKind::PoolNotRegistered
+            );
+            ensure_here!(
+                account_view.account.token_balances.contains_key(&tokens.0)
+                    && account_view.account.token_balances.contains_key(&tokens.1),
+                ErrorKind::TokenNotRegistered
+            );
+            account_view.account.extra.on_order_placed()
+        })?;
+
+        let order = ConditionalOrder {
+            owner,
+            token_in: tokens.0,
+            token_out: tokens.1,
+            amount_in: amounts.0,
+            min_amount_out: amounts.1,
+            trigger,
+            trigger_price,
+            expires_at,
+            keeper_fee,
+        };
+        let Contract::V1(ref mut contract) = self.contract_mut();
+        let order_id = contract.next_order_id;
+        contract.next_order_id += 1;
+        self.logger_mut().log_order_placed_event(order_id, &order);
+        let Contract::V1(ref mut contract) = self.contract_mut();
+        contract.orders.insert(order_id, order);
+        Ok(order_id)
+    }
+
+    /// Execute conditional order on behalf of its owner; may be called by any account,
+    /// which receives keeper fee into its deposits
+    ///
+    /// # Returns
+    /// Amount of output token received by order's owner
+    pub fn execute_order(&mut self, order_id: u64) -> Result<Amount> {
+        self.ensure_payable_api_resumed()?;
+        let order = {
+            let Contract::V1(ref contract) = self.contract();
+            contract
+                .orders
+                .try_inspect(&order_id, ConditionalOrder::clone)?
+        };
+        ensure_here!(
+            self.get_block_timestamp() <= order.expires_at,
+            ErrorKind::OrderExpired
+        );
+        let price = self.get_order_price(&order.token_in, &order.token_out)?;
+        ensure_here!(
+            order.trigger.is_triggered(price, order.trigger_price),
+            ErrorKind::OrderNotTriggered
+        );
+
+        let Contract::V1(ref mut contract) = self.contract_mut();
+        contract.orders.remove(&order_id);
+        let protocol_fee_fraction = self.protocol_fee_fraction();
+        let mut swap_notifications = Vec::new();
+        let mut extra_fees = Vec::new();
+        let amount_out = self.with_account_mut(&order.owner, |account_view| {
+            ensure_not_banned::<T>(
+                account_view.banned_tokens,
+                [&order.token_in, &order.token_out],
+            )?;
+            account_view
+                .account
+                .withdraw(&order.token_in, order.keeper_fee)
+                .map_err(|e| error_here!(e))?;
+            let (_, _, amount_out) = Self::execute_swap_action(
+                &order.owner,
+                account_view.account,
+                account_view.pools,
+                account_view.classic_pools,
+                account_view.stable_pools,
+                account_view.logger,
+                &None,
+                Exact::In,
+                SwapAction {
+                    token_in: order.token_in.clone(),
+                    token_out: order.token_out.clone(),
+                    amount: Some(order.amount_in.into()),
+                    amount_limit: order.min_amount_out.into(),
+                    integration_id: None,
+                },
+                protocol_fee_fraction,
+                account_view.swap_guards,
+                account_view.block_height,
+                &mut extra_fees,
+                &mut swap_notifications,
+            )?;
+            account_view.account.extra.on_order_removed();
+            Ok(amount_out)
+        })?;
+        self.credit_extra_fees(extra_fees)?;
+        self.auto_harvest_swapped_pools(&swap_notifications)?;
+        #[cfg(feature = "near")]
+        #[cfg(feature = "smart-routing")]
+        self.update_top_pools([PoolId::try_from_pair((
+            order.token_in.clone(),
+            order.token_out.clone(),
+        ))
+        .map_err(|e| error_here!(e))?
+        .0])?;
+
+        let keeper_id = self.get_caller_id();
+        self.with_account_mut(&keeper_id, |account_view| {
+            account_view
+                .account
+                .deposit(&order.token_in, order.keeper_fee)
+                .map_err(|e| error_here!(e))
+        })?;
+        self.logger_mut()
+            .log_order_executed_event(order_id, &keeper_id, &order, amount_out);
+        for notification in swap_notifications {
+            self.notify_swap_hook_if_triggered(notification);
+        }
+        Ok(amount_out)
+    }
+
+    /// Cancel conditional order; allowed to its owner, or to anyone once order has expired,
+    /// so storage of stale orders can be released
+    pub fn cancel_order(&mut self, order_id: u64) -> Result<()> {
+        self.ensure_payable_api_resumed()?;
+        let caller_id = self.get_caller_id();
+        let order = {
+            let Contract::V1(ref contract) = self.contract();
+            contract
+                .orders
+                .try_inspect(&order_id, ConditionalOrder::clone)?
+        };
+        ensure_here!(
+            order.owner == caller_id || self.get_block_timestamp() > order.expires_at,
+            ErrorKind::NotYourOrder
+        );
+        let Contract::V1(ref mut contract) = self.contract_mut();
+        contract.orders.remove(&order_id);
+        self.with_account_mut(&order.owner, |account_view| {
+            account_view.account.extra.on_order_removed();
+            Ok(())
+        })?;
+        self.logger_mut()
+            .log_order_cancelled_event(order_id, &caller_id, &order);
+        Ok(())
+    }
+}
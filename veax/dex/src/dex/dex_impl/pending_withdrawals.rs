@@ -0,0 +1,55 @@
+//! Inspection of unfinished withdraws, and recovery from withdraws whose callback was lost
+//!
+//! Withdraw is tracked until its callback arrives, and account can't be unregistered meanwhile.
+//! If callback is lost, e.g. due to gas exhaustion, guard may stop tracking it.
+use super::Dex;
+use crate::chain::{AccountId, Amount, TokenId};
+use crate::dex::errors::{ErrorKind, Result};
+use crate::dex::{
+    Account, AccountWithdrawTracker, Contract, State, StateMembersMut, StateMut, Types,
+};
+use crate::ensure_here;
+use std::borrow::{Borrow, BorrowMut};
+
+impl<T: Types, S: State<T>, SS: Borrow<S>> Dex<T, S, SS> {
+    /// Unfinished withdraws of account, as pairs of token id and amount
+    pub fn get_pending_withdrawals(
+        &self,
+        account_id: &AccountId,
+    ) -> Result<Vec<(TokenId, Amount)>> {
+        let Contract::V1(ref contract) = self.contract();
+        contract
+            .accounts
+            .try_inspect(account_id, |Account::V1(ref account)| {
+                account.withdraw_tracker.pending_withdrawals()
+            })
+    }
+}
+
+impl<T: Types, S: StateMut<T>, SS: BorrowMut<S>> Dex<T, S, SS> {
+    /// Stop tracking withdraw whose callback was lost, so account can be unregistered again.
+    /// Withdrawn amount isn't returned to account's balance. Only can be called by guard
+    pub fn force_untrack(
+        &mut self,
+        account_id: &AccountId,
+        token_id: &TokenId,
+        amount: Amount,
+    ) -> Result<()> {
+        self.ensure_caller_is_guard()?;
+        let caller_id = self.get_caller_id();
+        let StateMembersMut {
+            contract: Contract::V1(ref mut contract),
+            logger,
+            ..
+        } = self.members_mut();
+        contract
+            .accounts
+            .try_update(account_id, |Account::V1(ref mut account)| {
+                let tracked = account.withdraw_tracker.force_untrack(token_id, &amount);
+                ensure_here!(tracked, ErrorKind::WithdrawNotTracked);
+                Ok(())
+            })?;
+        logger.log_force_untrack_withdraw_event(&caller_id, account_id, token_id, amount);
+        Ok(())
+    }
+}
@@ -0,0 +1,130 @@
+//! Registry of human-readable pool aliases, e.g. "wNEAR/USDC", managed by owner
+//!
+//! Alias lets CLIs and governance proposals reference pool unambiguously
+//! without spelling out both token ids. Each pool has at most one alias.
+use super::{pool_kind, Dex};
+use crate::chain::TokenId;
+use crate::dex::errors::{ErrorKind, Result};
+use crate::dex::{ConfigChange, Contract, Map, MapRemoveKey, PoolId, State, StateMut, Types};
+use crate::{ensure_here, error_here};
+use std::borrow::{Borrow, BorrowMut};
+
+/// Maximal length of pool alias, in bytes
+const MAX_POOL_ALIAS_LEN: usize = 64;
+
+impl<T: Types, S: State<T>, SS: Borrow<S>> Dex<T, S, SS> {
+    /// Tokens of pool which alias refers to, if alias is registered
+    pub fn resolve_pool(&self, alias: &String) -> Option<(TokenId, TokenId)> {
+        let Contract::V1(ref contract) = self.contract();
+        contract
+            .pool_aliases
+            .inspect(alias, |pool_id| (pool_id.0.clone(), pool_id.1.clone()))
+    }
+
+    /// Alias of pool, if it has one
+    pub fn get_pool_alias(&self, tokens: (TokenId, TokenId)) -> Result<Option<String>> {
+        let (pool_id, _) = PoolId::try_from_pair(tokens).map_err(|e| error_here!(e))?;
+        let Contract::V1(ref contract) = self.contract();
+        Ok(contract.aliases_by_pool.inspect(&pool_id, String::clone))
+    }
+
+    /// Page of registered aliases with tokens of their pools
+    pub fn get_pool_aliases(&self, from: usize, limit: usize) -> Vec<(String, (TokenId, TokenId))> {
+        let Contract::V1(ref contract) = self.contract();
+        contract
+            .pool_aliases
+            .iter()
+            .skip(from)
+            .take(limit)
+            .map(|(alias, pool_id)| (alias.clone(), (pool_id.0.clone(), pool_id.1.clone())))
+            .collect()
+    }
+}
+
+impl<T: Types, S: StateMut<T>, SS: BorrowMut<S>> Dex<T, S, SS> {
+    /// Register alias of pool, replacing previous alias of the pool and previous pool of the alias,
+    /// or remove alias if `None` is passed
+    ///
+    /// # Parameters
+    /// * `alias` - non-empty label, up to `MAX_POOL_ALIAS_LEN` bytes
+    /// * `tokens` - tokens of existing pool, in any order
+    pub fn set_pool_alias(
+        &mut self,
+        alias: String,
+        tokens: Option<(TokenId, TokenId)>,
+    ) -> Result<()> {
+        self.ensure_payable_api_resumed()?;
+        self.ensure_caller_is_owner()?;
+        ensure_here!(
+            !alias.is_empty() && alias.len() <= MAX_POOL_ALIAS_LEN,
+            ErrorKind::InvalidParams
+        );
+        let pool_id = match tokens {
+            Some(tokens) => Some(PoolId::try_from_pair(tokens).map_err(|e| error_here!(e))?.0),
+            None => None,
+        };
+        let caller_id = self.get_caller_id();
+        let Contract::V1(ref mut contract) = self.contract_mut();
+        if let Some(ref pool_id) = pool_id {
+            ensure_here!(
+                pool_kind::<T>(
+                    &contract.pools,
+                    &contract.classic_pools,
+                    &contract.stable_pools,
+                    pool_id
+                )
+                .is_some(),
+                ErrorKind::PoolNotRegistered
+            );
+        }
+
+        let previous = contract.pool_aliases.inspect(&alias, PoolId::clone);
+        ensure_here!(
+            previous.is_some() || pool_id.is_some(),
+            ErrorKind::PoolAliasNotFound
+        );
+        if let Some(ref previous) = previous {
+            contract.aliases_by_pool.remove(previous);
+        }
+        // Alias which pool had before is dropped, so that pool has single alias
+        let replaced = match pool_id {
+            Some(ref pool_id) => {
+                let replaced = contract.aliases_by_pool.inspect(pool_id, String::clone);
+                if let Some(ref replaced) = replaced {
+                    contract.pool_aliases.remove(replaced);
+                }
+                contract.pool_aliases.insert(alias.clone(), pool_id.clone());
+                contract
+                    .aliases_by_pool
+                    .insert(pool_id.clone(), alias.clone());
+                replaced.filter(|replaced| *replaced != alias)
+            }
+            None => {
+                contract.pool_aliases.remove(&alias);
+                None
+            }
+        };
+
+        let as_pair = |pool_id: &PoolId| (pool_id.0.clone(), pool_id.1.clone());
+        let logger = self.logger_mut();
+        if let (Some(replaced), Some(ref pool_id)) = (replaced, &pool_id) {
+            logger.log_config_change_event(
+                &caller_id,
+                &ConfigChange::PoolAlias {
+                    alias: replaced,
+                    previous: Some(as_pair(pool_id)),
+                    new: None,
+                },
+            );
+        }
+        logger.log_config_change_event(
+            &caller_id,
+            &ConfigChange::PoolAlias {
+                alias,
+                previous: previous.as_ref().map(as_pair),
+                new: pool_id.as_ref().map(as_pair),
+            },
+        );
+        Ok(())
+    }
+}
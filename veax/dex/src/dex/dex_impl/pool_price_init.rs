@@ -0,0 +1,80 @@
+//! Creation of concentrated pool at explicit starting price
+//!
+//! Normally pool price is derived from amounts of the first position, so a position opened
+//! at a wrong ratio sets a wrong price. Market creators may instead set precise opening price
+//! before any liquidity is added. Owner decides whether this is open to any account.
+use super::Dex;
+use crate::chain::TokenId;
+use crate::dex::errors::{ErrorKind, Result};
+use crate::dex::{
+    ConfigChange, Contract, Float, Map, Pool, PoolId, PoolKind, State, StateMut, Types,
+};
+use crate::{ensure_here, error_here};
+use std::borrow::{Borrow, BorrowMut};
+
+impl<T: Types, S: State<T>, SS: Borrow<S>> Dex<T, S, SS> {
+    /// Whether any account, not only owner, may call `create_pool_with_price`
+    pub fn is_pool_price_init_open(&self) -> bool {
+        let Contract::V1(ref contract) = self.contract();
+        contract.pool_price_init_open
+    }
+}
+
+impl<T: Types, S: StateMut<T>, SS: BorrowMut<S>> Dex<T, S, SS> {
+    /// Allow or disallow accounts other than owner to create pools at explicit price
+    pub fn set_pool_price_init_open(&mut self, open: bool) -> Result<()> {
+        self.ensure_payable_api_resumed()?;
+        self.ensure_caller_is_owner()?;
+        let Contract::V1(ref mut contract) = self.contract_mut();
+        let previous = std::mem::replace(&mut contract.pool_price_init_open, open);
+        let caller_id = self.get_caller_id();
+        self.logger_mut().log_config_change_event(
+            &caller_id,
+            &ConfigChange::PoolPriceInitOpen {
+                previous,
+                new: open,
+            },
+        );
+        Ok(())
+    }
+
+    /// Create concentrated pool of `token_a` and `token_b`, or take existing empty one,
+    /// and set its price, so that positions are opened at this price
+    ///
+    /// # Parameters
+    /// * `sqrtprice` - square root of spot price, in the same convention as position ticks
+    ///   passed with `token_a` and `token_b` in this order, i.e. `1.0001^(tick/2)`
+    pub fn create_pool_with_price(
+        &mut self,
+        token_a: &TokenId,
+        token_b: &TokenId,
+        sqrtprice: Float,
+    ) -> Result<()> {
+        self.ensure_payable_api_resumed()?;
+        if !self.is_pool_price_init_open() {
+            self.ensure_caller_is_owner()?;
+        }
+        let (pool_id, transposed) = PoolId::try_from_pair((token_a.clone(), token_b.clone()))
+            .map_err(|e| error_here!(e))?;
+        // Ticks of transposed pair are opposite, so are their prices
+        let sqrtprice = if transposed {
+            sqrtprice.recip()
+        } else {
+            sqrtprice
+        };
+        ensure_here!(sqrtprice.is_normal(), ErrorKind::InvalidParams);
+
+        self.with_caller_account_mut(|mut account_view| {
+            account_view.ensure_pool_kind(&pool_id, PoolKind::Concentrated)?;
+            if !account_view.pools.contains_key(&pool_id) {
+                account_view.create_concentrated_pool(&pool_id, token_a, token_b)?;
+            }
+            account_view
+                .pools
+                .try_update(&pool_id, |Pool::V1(ref mut pool)| {
+                    pool.init_pool_from_spot_sqrtprice(sqrtprice)
+                })?;
+            Ok(())
+        })
+    }
+}
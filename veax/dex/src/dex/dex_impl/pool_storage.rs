@@ -0,0 +1,88 @@
+//! Accounting of native tokens paid by accounts for storage of pools they created
+//!
+//! Pool is never removed, so its storage deposit stays locked in contract.
+//! When storage gets cheaper, owner may credit the difference between
+//! recorded deposit and current storage cost back to pool's creator.
+use super::Dex;
+use crate::chain::{Amount, TokenId};
+use crate::dex::errors::{ErrorKind, Result};
+use crate::dex::traits::AccountExtra;
+use crate::dex::{Account, Contract, Map, PoolId, PoolStorageDeposit, State, StateMut, Types};
+use crate::error_here;
+use std::borrow::{Borrow, BorrowMut};
+
+impl<T: Types, S: State<T>, SS: Borrow<S>> Dex<T, S, SS> {
+    /// Native tokens paid for storage of pool, if payment was recorded
+    pub fn get_pool_storage_deposit(
+        &self,
+        tokens: (TokenId, TokenId),
+    ) -> Result<Option<PoolStorageDeposit>> {
+        let (pool_id, _) = PoolId::try_from_pair(tokens).map_err(|e| error_here!(e))?;
+        let Contract::V1(ref contract) = self.contract();
+        Ok(contract
+            .pool_storage_deposits
+            .inspect(&pool_id, PoolStorageDeposit::clone))
+    }
+
+    /// Number of pools with recorded storage deposits, total of those deposits,
+    /// and total storage taken by those pools, in bytes
+    pub fn get_pool_storage_deposits_total(&self) -> (u64, Amount, u64) {
+        let Contract::V1(ref contract) = self.contract();
+        (
+            contract.pool_storage_deposits.len() as u64,
+            contract.pool_storage_deposits_total,
+            contract.pool_storage_bytes_total,
+        )
+    }
+}
+
+impl<T: Types, S: StateMut<T>, SS: BorrowMut<S>> Dex<T, S, SS> {
+    /// Credit part of pool's storage deposit which exceeds current cost of pool's storage
+    /// back to pool's creator; recorded deposit is lowered to that cost
+    ///
+    /// # Parameters
+    /// * `tokens` - tokens of pool, in any order
+    /// * `storage_byte_cost` - current price of storage byte, in native tokens
+    ///
+    /// # Returns
+    /// Amount credited to creator; zero if deposit doesn't exceed current cost
+    pub fn credit_pool_storage_headroom(
+        &mut self,
+        tokens: (TokenId, TokenId),
+        storage_byte_cost: Amount,
+    ) -> Result<Amount> {
+        self.ensure_payable_api_resumed()?;
+        self.ensure_caller_is_owner()?;
+        let (pool_id, _) = PoolId::try_from_pair(tokens).map_err(|e| error_here!(e))?;
+        let Contract::V1(ref mut contract) = self.contract_mut();
+        let PoolStorageDeposit {
+            creator,
+            amount,
+            storage_bytes,
+        } = contract
+            .pool_storage_deposits
+            .inspect(&pool_id, PoolStorageDeposit::clone)
+            .ok_or(error_here!(ErrorKind::PoolStorageDepositNotFound))?;
+        let storage_cost = Amount::from(storage_bytes) * storage_byte_cost;
+        let Some(headroom) = amount.checked_sub(storage_cost).filter(|h| *h > 0) else {
+            return Ok(0);
+        };
+
+        contract
+            .accounts
+            .try_update(&creator, |Account::V1(ref mut account)| {
+                account.extra.on_pool_storage_refunded(headroom);
+                Ok(())
+            })?;
+        contract.pool_storage_deposits.insert(
+            pool_id,
+            PoolStorageDeposit {
+                creator,
+                amount: storage_cost,
+                storage_bytes,
+            },
+        );
+        contract.pool_storage_deposits_total -= headroom;
+        Ok(headroom)
+    }
+}
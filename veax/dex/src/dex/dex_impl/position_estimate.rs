@@ -0,0 +1,99 @@
+//! Read-only simulations of `open_position` and `close_position`, to let UIs display
+//! exact charges and proceeds using contract math before transaction
+use super::{fee_rates_ticks, Dex};
+use crate::chain::TokenId;
+use crate::dex::errors::{ErrorKind, Result};
+use crate::dex::utils::swap_if;
+use crate::dex::{
+    BasisPoints, Contract, FeeLevel, Map, PairExt, Pool, PoolId, PositionCloseEstimate,
+    PositionEstimate, PositionId, PositionInit, State, Types,
+};
+use crate::{ensure_here, error_here};
+use itertools::Itertools;
+use near_sdk::borsh::{BorshDeserialize, BorshSerialize};
+use std::borrow::Borrow;
+
+impl<T: Types, S: State<T>, SS: Borrow<S>> Dex<T, S, SS> {
+    /// Evaluate position which `open_position` with the same parameters would open
+    /// on the current state of existing concentrated pool, without mutating it
+    ///
+    /// Positions with `auto_balance` aren't supported, as their amounts depend on the swap
+    pub fn estimate_open_position(
+        &self,
+        token_a: &TokenId,
+        token_b: &TokenId,
+        fee_rate: BasisPoints,
+        position: PositionInit,
+    ) -> Result<PositionEstimate> {
+        ensure_here!(!position.auto_balance, ErrorKind::InvalidParams);
+        let (pool_id, transposed) = PoolId::try_from_pair((token_a.clone(), token_b.clone()))
+            .map_err(|e| error_here!(e))?;
+        let position = position.transpose_if(transposed);
+        let fee_level: FeeLevel = fee_rates_ticks()
+            .iter()
+            .find_position(|r| **r == fee_rate)
+            .ok_or(error_here!(ErrorKind::IllegalFee))?
+            .0
+            .try_into()
+            .map_err(|_| error_here!(ErrorKind::ConvOverflow))?;
+
+        let Contract::V1(ref contract) = self.contract();
+        let (amounts, net_liquidity, (tick_low, tick_high)) =
+            contract.pools.try_inspect(&pool_id, |pool| {
+                let pool_bytes = pool
+                    .try_to_vec()
+                    .map_err(|_| error_here!(ErrorKind::InternalLogicError))?;
+                // Estimation leaves pool state modified, so it's done on a copy
+                let Pool::V1(mut pool_copy) = Pool::<T>::try_from_slice(&pool_bytes)
+                    .map_err(|_| error_here!(ErrorKind::InternalLogicError))?;
+                pool_copy.estimate_open_position(
+                    position,
+                    fee_level,
+                    contract.min_position_liquidity,
+                )
+            })??;
+
+        if let Some(min_amounts) = contract
+            .min_deposit_amounts
+            .inspect(&pool_id, |min_amounts| *min_amounts)
+        {
+            ensure_here!(
+                amounts.0 >= min_amounts.0 && amounts.1 >= min_amounts.1,
+                ErrorKind::DepositTooSmall
+            );
+        }
+
+        let ticks = if transposed {
+            (tick_high.opposite(), tick_low.opposite())
+        } else {
+            (tick_low, tick_high)
+        };
+        Ok(PositionEstimate {
+            amounts: swap_if(transposed, amounts),
+            net_liquidity,
+            fee_level,
+            ticks,
+        })
+    }
+
+    /// Evaluate amounts which `close_position` would deposit to owner if called now:
+    /// position balance at current price and LP fees accumulated since last withdrawal
+    pub fn estimate_close_position(
+        &self,
+        position_id: PositionId,
+    ) -> Result<PositionCloseEstimate> {
+        let Contract::V1(ref contract) = self.contract();
+        contract
+            .position_to_pool_id
+            .try_inspect(&position_id, |pool_id| {
+                contract.pools.try_inspect(pool_id, |Pool::V1(ref pool)| {
+                    let (fees, amounts) = pool.estimate_close_position(position_id)?;
+                    Ok(PositionCloseEstimate {
+                        tokens: pool_id.as_refs().map(Clone::clone),
+                        amounts,
+                        fees,
+                    })
+                })
+            })??
+    }
+}
@@ -0,0 +1,75 @@
+//! Position size limits: minimal liquidity and deposit amounts of newly opened positions,
+//! tuned by owner according to tokens' decimals and economics
+use super::Dex;
+use crate::chain::{Amount, TokenId};
+use crate::dex::errors::{ErrorKind, Result};
+use crate::dex::latest::{MAX_MIN_LIQUIDITY, MIN_LIQUIDITY};
+use crate::dex::utils::swap_if;
+use crate::dex::{Contract, Float, Map, MapRemoveKey, PoolId, State, StateMut, Types};
+use crate::{ensure_here, error_here};
+use num_traits::Zero;
+use std::borrow::{Borrow, BorrowMut};
+
+impl<T: Types, S: State<T>, SS: Borrow<S>> Dex<T, S, SS> {
+    /// Minimal accounted liquidity of newly opened position
+    pub fn get_min_position_liquidity(&self) -> Float {
+        let Contract::V1(ref contract) = self.contract();
+        contract.min_position_liquidity
+    }
+
+    /// Minimal amounts of tokens deposited into newly opened position in the pool,
+    /// in the order of `tokens`; zeros if not set
+    pub fn get_min_deposit_amounts(&self, tokens: (TokenId, TokenId)) -> Result<(Amount, Amount)> {
+        let (pool_id, transposed) = PoolId::try_from_pair(tokens).map_err(|e| error_here!(e))?;
+        let Contract::V1(ref contract) = self.contract();
+        let min_amounts = contract
+            .min_deposit_amounts
+            .inspect(&pool_id, |min_amounts| *min_amounts)
+            .unwrap_or((Amount::zero(), Amount::zero()));
+        Ok(swap_if(transposed, min_amounts))
+    }
+}
+
+impl<T: Types, S: StateMut<T>, SS: BorrowMut<S>> Dex<T, S, SS> {
+    /// Set minimal accounted liquidity of newly opened position.
+    /// Must be between `MIN_LIQUIDITY` and `MAX_MIN_LIQUIDITY`
+    pub fn set_min_position_liquidity(&mut self, min_liquidity: Float) -> Result<()> {
+        self.ensure_payable_api_resumed()?;
+        self.ensure_caller_is_owner()?;
+        ensure_here!(
+            min_liquidity >= MIN_LIQUIDITY && min_liquidity <= MAX_MIN_LIQUIDITY,
+            ErrorKind::InvalidParams
+        );
+        let Contract::V1(ref mut contract) = self.contract_mut();
+        contract.min_position_liquidity = min_liquidity;
+        Ok(())
+    }
+
+    /// Set minimal amounts of tokens deposited into newly opened position in the pool,
+    /// or remove them if `None` is passed
+    ///
+    /// # Parameters
+    /// * `tokens` - pool's tokens
+    /// * `min_amounts` - minimal amounts, in the order of `tokens`
+    pub fn set_min_deposit_amounts(
+        &mut self,
+        tokens: (TokenId, TokenId),
+        min_amounts: Option<(Amount, Amount)>,
+    ) -> Result<()> {
+        self.ensure_payable_api_resumed()?;
+        self.ensure_caller_is_owner()?;
+        let (pool_id, transposed) = PoolId::try_from_pair(tokens).map_err(|e| error_here!(e))?;
+        let Contract::V1(ref mut contract) = self.contract_mut();
+        match min_amounts {
+            Some(min_amounts) => {
+                contract
+                    .min_deposit_amounts
+                    .insert(pool_id, swap_if(transposed, min_amounts));
+            }
+            None => {
+                contract.min_deposit_amounts.remove(&pool_id);
+            }
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,71 @@
+//! Effective price curve sampling, to let UIs plot price impact without many separate estimates
+use super::Dex;
+use crate::chain::{Amount, TokenId};
+use crate::dex::errors::{ErrorKind, Result};
+use crate::dex::{Contract, Float, Pool, PoolId, PriceCurveSample, Side, State, Types};
+use crate::{ensure_here, error_here};
+use near_sdk::borsh::{BorshDeserialize, BorshSerialize};
+use std::borrow::Borrow;
+
+/// Maximal number of amounts sampled in single call, limits gas consumption
+const MAX_PRICE_CURVE_SAMPLES: usize = 32;
+
+impl<T: Types, S: State<T>, SS: Borrow<S>> Dex<T, S, SS> {
+    /// Simulate exact-in swaps of each of `amounts` on the current pool state, without mutating it
+    ///
+    /// # Parameters
+    /// * `tokens` - pool's tokens
+    /// * `side` - which of `tokens` is input token of the swaps
+    /// * `amounts` - amounts of input token to swap, each simulated independently
+    ///
+    /// # Returns
+    /// Sample per each of `amounts`, in the same order; `None` if swap of the amount would fail
+    pub fn sample_price_curve(
+        &self,
+        tokens: (TokenId, TokenId),
+        side: Side,
+        amounts: &[Amount],
+    ) -> Result<Vec<Option<PriceCurveSample>>> {
+        ensure_here!(
+            amounts.len() <= MAX_PRICE_CURVE_SAMPLES,
+            ErrorKind::InvalidParams
+        );
+        let (pool_id, transposed) = PoolId::try_from_pair(tokens).map_err(|e| error_here!(e))?;
+        let side = side.opposite_if(transposed);
+        let Contract::V1(ref contract) = self.contract();
+        contract.pools.try_inspect(&pool_id, |pool| {
+            let Pool::V1(ref pool_v0) = pool;
+            ensure_here!(
+                pool_v0.is_spot_price_set(),
+                ErrorKind::InsufficientLiquidity
+            );
+            let marginal_eff_sqrtprice = pool_v0.eff_sqrtprice(side, 0);
+            let marginal_price = marginal_eff_sqrtprice * marginal_eff_sqrtprice;
+            let pool_bytes = pool
+                .try_to_vec()
+                .map_err(|_| error_here!(ErrorKind::InternalLogicError))?;
+
+            amounts
+                .iter()
+                .map(|&amount_in| {
+                    // Each swap is simulated on a fresh copy, as simulation leaves pool state modified
+                    let Pool::V1(mut pool_copy) = Pool::<T>::try_from_slice(&pool_bytes)
+                        .map_err(|_| error_here!(ErrorKind::InternalLogicError))?;
+                    let sample = pool_copy
+                        .simulate_swap_exact_in(side, amount_in, contract.protocol_fee_fraction)
+                        .ok()
+                        .map(|(amount_in, amount_out)| {
+                            let average_price = Float::from(amount_in) / Float::from(amount_out);
+                            PriceCurveSample {
+                                amount_in,
+                                amount_out,
+                                average_price,
+                                price_impact: average_price / marginal_price - Float::one(),
+                            }
+                        });
+                    Ok(sample)
+                })
+                .collect()
+        })?
+    }
+}
@@ -0,0 +1,48 @@
+//! Re-quote protection: swap may carry square root of price which client quoted it at,
+//! and is rejected if pre-trade spot price of the route already deviates from it too much,
+//! so aggregators get deterministic failure instead of execution at a stale quote
+use super::Dex;
+use crate::chain::TokenId;
+use crate::dex::errors::{ErrorKind, Result};
+use crate::dex::{BasisPoints, Float, State, Types, BASIS_POINT_DIVISOR};
+use crate::ensure_here;
+use itertools::Itertools;
+use std::borrow::Borrow;
+
+impl<T: Types, S: State<T>, SS: Borrow<S>> Dex<T, S, SS> {
+    /// Square root of current spot price of first of `tokens` in units of the last one,
+    /// composed of spot prices of each hop of the route
+    pub fn get_route_spot_sqrtprice(&self, tokens: &[TokenId]) -> Result<Float> {
+        ensure_here!(tokens.len() >= 2, ErrorKind::AtLeastOneSwap);
+        let price = tokens
+            .iter()
+            .tuple_windows()
+            .try_fold(Float::one(), |price, (token_in, token_out)| {
+                Ok(price * self.get_order_price(token_in, token_out)?)
+            })?;
+        Ok(price.sqrt())
+    }
+
+    /// Fails with `PriceDeviationExceeded` if spot sqrtprice of route `tokens` deviates from
+    /// `reference_sqrtprice` by more than `max_deviation_bp` basis points of the latter
+    pub fn ensure_route_price_near(
+        &self,
+        tokens: &[TokenId],
+        reference_sqrtprice: Float,
+        max_deviation_bp: BasisPoints,
+    ) -> Result<()> {
+        ensure_here!(
+            !reference_sqrtprice.is_zero() && max_deviation_bp <= BASIS_POINT_DIVISOR,
+            ErrorKind::InvalidParams
+        );
+        let spot_sqrtprice = self.get_route_spot_sqrtprice(tokens)?;
+        let divisor = Float::from(BASIS_POINT_DIVISOR);
+        let max_deviation = reference_sqrtprice * Float::from(max_deviation_bp) / divisor;
+        ensure_here!(
+            spot_sqrtprice <= reference_sqrtprice + max_deviation
+                && spot_sqrtprice >= reference_sqrtprice - max_deviation,
+            ErrorKind::PriceDeviationExceeded
+        );
+        Ok(())
+    }
+}
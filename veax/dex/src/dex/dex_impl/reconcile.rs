@@ -0,0 +1,213 @@
+//! Compatibility with fee-on-transfer tokens
+//!
+//! Such tokens deduct tax from transferred amount, so contract receives less than `amount`
+//! reported to `ft_on_transfer`, and crediting it in full lets accounting drift from actual
+//! balances. In "reconcile on deposit" mode, contract tracks its own balance of token,
+//! and credits deposit by exactly the delta between balance fetched from token and tracked one.
+//! Deposits of token are processed one at a time, and aren't credited while transfers
+//! sent by contract are in flight, as it's unknown whether they left fetched balance.
+//! If tracked balance drifts, e.g. because token also takes fee on outflows, it's replaced
+//! with fetched one on the next mismatching deposit, or on request of guard.
+use super::Dex;
+use crate::chain::{Amount, TokenId};
+use crate::dex::errors::{ErrorKind, Result};
+use crate::dex::{
+    ConfigChange, Contract, Map, MapRemoveKey, ReconciledBalance, State, StateMut, Types,
+};
+use crate::{ensure_here, error_here};
+use std::borrow::{Borrow, BorrowMut};
+
+impl<T: Types, S: State<T>, SS: Borrow<S>> Dex<T, S, SS> {
+    /// Whether deposits of token are credited by contract's balance delta
+    pub fn is_token_reconciled(&self, token_id: &TokenId) -> bool {
+        let Contract::V1(ref contract) = self.contract();
+        contract.reconciled_tokens.contains_key(token_id)
+    }
+
+    /// Contract's balance of reconciled token, as tracked by contract
+    ///
+    /// # Returns
+    /// * `None` - if token isn't reconciled, or its balance isn't fetched yet
+    pub fn get_reconciled_balance(&self, token_id: &TokenId) -> Option<Amount> {
+        let Contract::V1(ref contract) = self.contract();
+        contract
+            .reconciled_tokens
+            .inspect(token_id, |tracked| tracked.balance)
+            .flatten()
+    }
+}
+
+impl<T: Types, S: StateMut<T>, SS: BorrowMut<S>> Dex<T, S, SS> {
+    /// Enable or disable "reconcile on deposit" mode of token
+    ///
+    /// When mode is enabled, tracked balance is reset, and must be set
+    /// with `set_reconciled_balance` before deposits of token are accepted.
+    pub fn set_token_reconcile_on_deposit(
+        &mut self,
+        token_id: &TokenId,
+        enabled: bool,
+    ) -> Result<()> {
+        self.ensure_payable_api_resumed()?;
+        self.ensure_caller_is_owner()?;
+        let Contract::V1(ref mut contract) = self.contract_mut();
+        let previous = contract.reconciled_tokens.contains_key(token_id);
+        if enabled {
+            contract
+                .reconciled_tokens
+                .insert(token_id.clone(), ReconciledBalance::default());
+        } else {
+            contract.reconciled_tokens.remove(token_id);
+        }
+        let caller_id = self.get_caller_id();
+        self.logger_mut().log_config_change_event(
+            &caller_id,
+            &ConfigChange::ReconcileOnDeposit {
+                token: token_id.clone(),
+                previous,
+                new: enabled,
+            },
+        );
+        Ok(())
+    }
+
+    /// Check that tracked balance of reconciled token may be replaced with balance
+    /// fetched from token contract, see `set_reconciled_balance`. Only can be called by guard
+    pub fn resync_reconciled_balance(&mut self, token_id: &TokenId) -> Result<()> {
+        self.ensure_caller_is_guard()?;
+        let Contract::V1(ref contract) = self.contract();
+        let tracked = contract
+            .reconciled_tokens
+            .inspect(token_id, |tracked| *tracked)
+            .ok_or(error_here!(ErrorKind::TokenNotReconciled))?;
+        ensure_here!(
+            !tracked.deposit_pending,
+            ErrorKind::ReconciledDepositPending
+        );
+        Ok(())
+    }
+
+    /// Set contract's balance of reconciled token, as fetched from token contract.
+    /// Transfers sent by contract which are in flight are considered to have left
+    /// fetched balance; if they haven't, tracked balance is fixed on next deposit.
+    /// Fails if deposit of token is pending, as its delta would be lost
+    pub(crate) fn set_reconciled_balance(
+        &mut self,
+        token_id: &TokenId,
+        balance: Amount,
+    ) -> Result<()> {
+        let Contract::V1(ref mut contract) = self.contract_mut();
+        contract.reconciled_tokens.try_update(token_id, |tracked| {
+            ensure_here!(
+                !tracked.deposit_pending,
+                ErrorKind::ReconciledDepositPending
+            );
+            tracked.balance = Some(balance.saturating_sub(tracked.outflow_in_flight));
+            Ok(())
+        })
+    }
+
+    /// Start deposit of reconciled token. Fails if another deposit of token
+    /// isn't resolved yet with `reconcile_deposit`
+    pub fn begin_reconciled_deposit(&mut self, token_id: &TokenId) -> Result<()> {
+        let Contract::V1(ref mut contract) = self.contract_mut();
+        contract.reconciled_tokens.try_update(token_id, |tracked| {
+            ensure_here!(
+                tracked.balance.is_some(),
+                ErrorKind::ReconciledBalanceUnknown
+            );
+            ensure_here!(
+                !tracked.deposit_pending,
+                ErrorKind::ReconciledDepositPending
+            );
+            tracked.deposit_pending = true;
+            Ok(())
+        })
+    }
+
+    /// Resolve deposit of reconciled token started with `begin_reconciled_deposit`,
+    /// given contract's balance fetched after transfer, and account it in tracked balance.
+    /// Deposit is released even if it fails, so that next deposits of token are accepted.
+    /// Failed deposit is refunded in full; if it failed because balance delta doesn't match,
+    /// tracked balance is replaced with fetched one, less refund
+    ///
+    /// # Parameters
+    /// * `balance` - contract's balance of token; `None` if it couldn't be fetched
+    /// * `amount` - amount reported by token as transferred
+    ///
+    /// # Returns
+    /// Amount to credit: exact delta between fetched and tracked balance,
+    /// which can't exceed `amount`
+    pub fn reconcile_deposit(
+        &mut self,
+        token_id: &TokenId,
+        balance: Option<Amount>,
+        amount: Amount,
+    ) -> Result<Amount> {
+        let Contract::V1(ref mut contract) = self.contract_mut();
+        let tracked = contract.reconciled_tokens.try_update(token_id, |tracked| {
+            ensure_here!(tracked.deposit_pending, ErrorKind::InternalLogicError);
+            tracked.deposit_pending = false;
+            Ok(*tracked)
+        })?;
+        let tracked_balance = tracked
+            .balance
+            .ok_or(error_here!(ErrorKind::ReconciledBalanceUnknown))?;
+        let balance = balance.ok_or(error_here!(ErrorKind::ReconciledBalanceUnknown))?;
+        ensure_here!(
+            tracked.outflow_in_flight == 0,
+            ErrorKind::ReconciledOutflowPending
+        );
+        let received = balance
+            .checked_sub(tracked_balance)
+            .filter(|received| *received <= amount);
+        contract.reconciled_tokens.try_update(token_id, |tracked| {
+            tracked.balance = Some(match received {
+                Some(received) => tracked_balance + received,
+                None => balance.saturating_sub(amount),
+            });
+            Ok(())
+        })?;
+        received.ok_or(error_here!(ErrorKind::ReconciledBalanceMismatch))
+    }
+
+    /// Account `amount` of token which leaves contract without callback, e.g. refund
+    /// of transfer; does nothing if token isn't reconciled
+    pub fn track_reconciled_outflow(&mut self, token_id: &TokenId, amount: Amount) {
+        let Contract::V1(ref mut contract) = self.contract_mut();
+        contract.reconciled_tokens.update(token_id, |tracked| {
+            tracked.balance = tracked
+                .balance
+                .map(|balance| balance.saturating_sub(amount));
+            Ok(())
+        });
+    }
+
+    /// Account `amount` of token sent by contract, e.g. on withdrawal, which transfer
+    /// is resolved later with `end_reconciled_outflow`; does nothing if token isn't reconciled
+    pub fn begin_reconciled_outflow(&mut self, token_id: &TokenId, amount: Amount) {
+        let Contract::V1(ref mut contract) = self.contract_mut();
+        contract.reconciled_tokens.update(token_id, |tracked| {
+            tracked.balance = tracked
+                .balance
+                .map(|balance| balance.saturating_sub(amount));
+            tracked.outflow_in_flight = tracked.outflow_in_flight.saturating_add(amount);
+            Ok(())
+        });
+    }
+
+    /// Resolve transfer of `amount` started with `begin_reconciled_outflow`;
+    /// if it failed, tokens are still on contract's balance.
+    /// Does nothing if token isn't reconciled
+    pub fn end_reconciled_outflow(&mut self, token_id: &TokenId, amount: Amount, succeeded: bool) {
+        let Contract::V1(ref mut contract) = self.contract_mut();
+        contract.reconciled_tokens.update(token_id, |tracked| {
+            tracked.outflow_in_flight = tracked.outflow_in_flight.saturating_sub(amount);
+            if !succeeded {
+                tracked.balance = tracked
+                    .balance
+                    .map(|balance| balance.saturating_add(amount));
+            }
+            Ok(())
+        });
+    }
+}
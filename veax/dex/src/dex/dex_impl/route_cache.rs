@@ -0,0 +1,127 @@
+//! Best routes between pairs of tokens, with cache of recently swapped ones
+//!
+//! Cache lives in contract state record, which every call reads anyway, so repeat swaps
+//! on popular pairs skip route search without any extra storage reads. It holds up to
+//! `ROUTE_CACHE_SIZE` most recently swapped routes. Route of a pair is dropped on every update
+//! of state of any pool along it, or of any pool of either of its tokens, be it swap,
+//! open, close or move of position, or change of LP tokens supply, as route search could
+//! pick a different route after that. Whole cache is dropped on change of protocol fee.
+use super::Dex;
+use crate::chain::{Amount, TokenId};
+use crate::dex::errors::{ErrorKind, Result};
+use crate::dex::latest::ROUTE_CACHE_SIZE;
+use crate::dex::util_types::PoolId;
+use crate::dex::{CachedRoute, Contract, Path, State, StateMut, Types};
+use crate::{ensure_here, error_here};
+use num_traits::Zero;
+use std::borrow::{Borrow, BorrowMut};
+
+impl<T: Types, S: State<T>, SS: Borrow<S>> Dex<T, S, SS> {
+    /// Best route to swap `amount` of `token_in` into `token_out`, as tokens along it,
+    /// starting with `token_in` and ending with `token_out`
+    ///
+    /// Candidates are direct pool of the pair, and routes through tokens of top pools
+    /// of `token_in`; one with the highest path liquidity wins. If route of the pair was
+    /// swapped recently with amount of same order of magnitude, it's returned as is.
+    pub fn find_best_route(
+        &self,
+        token_in: &TokenId,
+        token_out: &TokenId,
+        amount: Amount,
+    ) -> Result<Vec<TokenId>> {
+        ensure_here!(token_in != token_out, ErrorKind::TokenDuplicates);
+        let Contract::V1(ref contract) = self.contract();
+        let amount_bucket = amount_bucket(amount);
+        if let Some(route) = contract.route_cache.iter().find(|route| {
+            route.token_in == *token_in
+                && route.token_out == *token_out
+                && route.amount_bucket == amount_bucket
+        }) {
+            return Ok(route.tokens.clone());
+        }
+
+        let mut candidates = vec![vec![token_in.clone(), token_out.clone()]];
+        for via in self.get_token_top_pools(token_in)? {
+            if via != *token_out {
+                candidates.push(vec![token_in.clone(), via, token_out.clone()]);
+            }
+        }
+        candidates
+            .into_iter()
+            // Candidates without pools along them, or with empty ones, are skipped
+            .filter_map(|tokens| {
+                let liquidity = self.calculate_path_liquidity(&tokens).ok()?;
+                (!liquidity.is_zero()).then_some((tokens, liquidity))
+            })
+            .max_by_key(|(_, liquidity)| *liquidity)
+            .map(|(tokens, _)| tokens)
+            .ok_or(error_here!(ErrorKind::RouteNotFound))
+    }
+}
+
+impl<T: Types, S: StateMut<T>, SS: BorrowMut<S>> Dex<T, S, SS> {
+    /// Swap exact `amount_in` of `token_in` into `token_out` along the best route,
+    /// see `find_best_route`, and remember the route for subsequent swaps of the pair
+    ///
+    /// Returns amount of `token_out` received
+    pub fn swap_best_route_exact_in(
+        &mut self,
+        token_in: &TokenId,
+        token_out: &TokenId,
+        amount_in: Amount,
+        min_amount_out: Amount,
+    ) -> Result<Amount> {
+        let tokens = self.find_best_route(token_in, token_out, amount_in)?;
+        let path = Path {
+            tokens: tokens.clone(),
+            token_out: token_out.clone(),
+            amount: amount_in,
+        };
+        let amount_out = self
+            .multiple_path_swap_exact_in(&[path], min_amount_out)?
+            .into_iter()
+            .map(|(_, amount_out)| amount_out)
+            .sum();
+
+        // Swap has updated pools of the route, which drops it from cache,
+        // but the route was the best one just before the swap, so it's remembered anyway
+        let Contract::V1(ref mut contract) = self.contract_mut();
+        let amount_bucket = amount_bucket(amount_in);
+        contract.route_cache.retain(|route| {
+            route.token_in != *token_in
+                || route.token_out != *token_out
+                || route.amount_bucket != amount_bucket
+        });
+        contract.route_cache.push(CachedRoute {
+            token_in: token_in.clone(),
+            token_out: token_out.clone(),
+            amount_bucket,
+            tokens,
+        });
+        if contract.route_cache.len() > ROUTE_CACHE_SIZE {
+            contract.route_cache.remove(0);
+        }
+
+        Ok(amount_out)
+    }
+}
+
+/// Drop cached routes which route search could pick differently after state of `pool_id`
+/// was updated, i.e. routes of pairs with either token of the pool, and routes through the pool
+pub(super) fn invalidate_routes(route_cache: &mut Vec<CachedRoute>, pool_id: &PoolId) {
+    let (token_a, token_b) = pool_id.as_refs();
+    let is_pool_token = |token: &TokenId| token == token_a || token == token_b;
+    route_cache.retain(|route| {
+        !is_pool_token(&route.token_in)
+            && !is_pool_token(&route.token_out)
+            && !route
+                .tokens
+                .windows(2)
+                .any(|hop| is_pool_token(&hop[0]) && is_pool_token(&hop[1]))
+    });
+}
+
+/// Number of significant bits of amount, so that swaps of same order of magnitude share routes
+fn amount_bucket(amount: Amount) -> u8 {
+    (Amount::BITS - amount.leading_zeros()) as u8
+}
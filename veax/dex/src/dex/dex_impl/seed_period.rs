@@ -0,0 +1,79 @@
+//! Seed period of new concentrated pools
+//!
+//! Whoever opens the first position sets pool price, and may snipe liquidity which others add
+//! at that price right after. While pool is in seed period, swaps in it are disabled,
+//! and positions may only be opened within configured distance from the initial price.
+use super::Dex;
+use crate::chain::MAX_TICK;
+use crate::dex::errors::{ErrorKind, Result};
+use crate::dex::tick::Tick;
+use crate::dex::{ConfigChange, Contract, PoolV1, SeedPeriodConfig, Side, State, StateMut, Types};
+use crate::{ensure_here, error_here};
+use std::borrow::{Borrow, BorrowMut};
+
+impl<T: Types, S: State<T>, SS: Borrow<S>> Dex<T, S, SS> {
+    /// Restrictions applied to new concentrated pools; `None` if disabled
+    pub fn get_seed_period(&self) -> Option<SeedPeriodConfig> {
+        let Contract::V1(ref contract) = self.contract();
+        contract.seed_period
+    }
+}
+
+impl<T: Types, S: StateMut<T>, SS: BorrowMut<S>> Dex<T, S, SS> {
+    /// Set restrictions applied to concentrated pools created from now on,
+    /// or disable them with `None`. Pools which are already in seed period keep it.
+    pub fn set_seed_period(&mut self, config: Option<SeedPeriodConfig>) -> Result<()> {
+        self.ensure_payable_api_resumed()?;
+        self.ensure_caller_is_owner()?;
+        if let Some(config) = config {
+            ensure_here!(
+                config.blocks > 0
+                    && config.max_width_ticks > 0
+                    && config.max_width_ticks <= MAX_TICK.unsigned_abs(),
+                ErrorKind::InvalidParams
+            );
+        }
+        let Contract::V1(ref mut contract) = self.contract_mut();
+        let previous = std::mem::replace(&mut contract.seed_period, config);
+        let caller_id = self.get_caller_id();
+        self.logger_mut().log_config_change_event(
+            &caller_id,
+            &ConfigChange::SeedPeriod {
+                previous,
+                new: config,
+            },
+        );
+        Ok(())
+    }
+}
+
+/// Fails with `PoolInSeedPeriod` if pool is still in its seed period at `block_height`
+pub(super) fn ensure_not_seeding<T: Types>(pool: &PoolV1<T>, block_height: u64) -> Result<()> {
+    ensure_here!(block_height >= pool.seed_until, ErrorKind::PoolInSeedPeriod);
+    Ok(())
+}
+
+/// Fails with `PositionOutsideSeedRange` if pool is in seed period and any bound
+/// of position `ticks` is farther from pool price than allowed by `config`
+pub(super) fn ensure_position_in_seed_range<T: Types>(
+    pool: &PoolV1<T>,
+    (tick_low, tick_high): (Tick, Tick),
+    config: Option<SeedPeriodConfig>,
+    block_height: u64,
+) -> Result<()> {
+    let Some(config) = config.filter(|_| block_height < pool.seed_until) else {
+        return Ok(());
+    };
+    let spot_sqrtprice = pool.spot_sqrtprice(Side::Left, 0);
+    let max_width =
+        i32::try_from(config.max_width_ticks).map_err(|_| error_here!(ErrorKind::ConvOverflow))?;
+    let max_ratio = Tick::new(max_width)
+        .map_err(|e| error_here!(e))?
+        .spot_sqrtprice();
+    ensure_here!(
+        tick_low.spot_sqrtprice() * max_ratio >= spot_sqrtprice
+            && tick_high.spot_sqrtprice() <= spot_sqrtprice * max_ratio,
+        ErrorKind::PositionOutsideSeedRange
+    );
+    Ok(())
+}
@@ -0,0 +1,156 @@
+//! Session keys: limited access keys of trading bots
+//!
+//! Account adds function-call access key for the contract and registers its public key here.
+//! Such key can't attach deposit, so it can't call methods which require one yocto, e.g. withdrawals.
+//! Methods which don't require deposit are either rejected for session keys,
+//! or, for swaps, checked against per-day volume caps of swapped tokens.
+use super::Dex;
+use crate::chain::{AccountId, Amount, TokenId};
+use crate::dex::errors::{ErrorKind, Result};
+use crate::dex::traits::AccountExtra;
+use crate::dex::{Contract, Map, MapRemoveKey, SessionKey, State, StateMut, Types};
+use crate::{ensure_here, error_here};
+use itertools::Itertools;
+use num_traits::Zero;
+use std::borrow::{Borrow, BorrowMut};
+
+/// Maximal number of session keys of single account
+const MAX_SESSION_KEYS: usize = 4;
+/// Maximal number of tokens with volume caps in single session key
+const MAX_SESSION_VOLUME_CAPS: usize = 8;
+/// Length of period which volume caps apply to, in seconds
+const SESSION_VOLUME_PERIOD: u64 = 24 * 60 * 60;
+
+impl<T: Types, S: State<T>, SS: Borrow<S>> Dex<T, S, SS> {
+    /// Session keys registered by `account_id`
+    pub fn get_session_keys(&self, account_id: &AccountId) -> Vec<SessionKey> {
+        let Contract::V1(ref contract) = self.contract();
+        contract
+            .session_keys
+            .inspect(account_id, Vec::clone)
+            .unwrap_or_default()
+    }
+}
+
+impl<T: Types, S: StateMut<T>, SS: BorrowMut<S>> Dex<T, S, SS> {
+    /// Index of session key which current call is signed with among keys of caller, if any.
+    /// Keys of caller are checked only if caller signed transaction itself
+    fn signing_session_key(&self, keys: &[SessionKey]) -> Option<usize> {
+        if keys.is_empty() || self.get_initiator_id() != self.get_caller_id() {
+            return None;
+        }
+        // State types without access keys never sign calls with session key
+        let public_key = self.get_signer_public_key()?;
+        keys.iter().position(|key| key.public_key == public_key)
+    }
+
+    /// Fails with `SessionKeyNotAllowed` if call is signed with caller's session key
+    pub(super) fn ensure_not_session_key(&self) -> Result<()> {
+        let keys = self.get_session_keys(&self.get_caller_id());
+        ensure_here!(
+            self.signing_session_key(&keys).is_none(),
+            ErrorKind::SessionKeyNotAllowed
+        );
+        Ok(())
+    }
+
+    /// Register session key of caller; storage of key is paid from caller's storage deposit
+    ///
+    /// # Parameters
+    /// * `public_key` - public key of function-call access key, in its string representation
+    /// * `volume_caps` - tokens which key may swap from, with maximal amount swapped per day
+    pub fn register_session_key(
+        &mut self,
+        public_key: String,
+        volume_caps: Vec<(TokenId, Amount)>,
+    ) -> Result<()> {
+        self.ensure_payable_api_resumed()?;
+        let account_id = self.get_caller_id();
+        ensure_here!(
+            !volume_caps.is_empty() && volume_caps.len() <= MAX_SESSION_VOLUME_CAPS,
+            ErrorKind::InvalidParams
+        );
+        ensure_here!(
+            volume_caps
+                .iter()
+                .map(|(token_id, _)| token_id)
+                .all_unique(),
+            ErrorKind::InvalidParams
+        );
+        let mut keys = self.get_session_keys(&account_id);
+        ensure_here!(
+            keys.iter().all(|key| key.public_key != public_key),
+            ErrorKind::InvalidParams
+        );
+        ensure_here!(keys.len() < MAX_SESSION_KEYS, ErrorKind::TooManySessionKeys);
+        self.with_account_mut(&account_id, |account_view| {
+            account_view.account.extra.on_session_key_registered()
+        })?;
+        keys.push(SessionKey {
+            public_key,
+            volumes: vec![Amount::zero(); volume_caps.len()],
+            volume_caps,
+            day: 0,
+        });
+        let Contract::V1(ref mut contract) = self.contract_mut();
+        contract.session_keys.insert(account_id, keys);
+        Ok(())
+    }
+
+    /// Revoke session key of caller, releasing its storage.
+    /// Access key itself should be deleted by account separately
+    pub fn revoke_session_key(&mut self, public_key: &str) -> Result<()> {
+        self.ensure_payable_api_resumed()?;
+        let account_id = self.get_caller_id();
+        let mut keys = self.get_session_keys(&account_id);
+        let index = keys
+            .iter()
+            .position(|key| key.public_key == public_key)
+            .ok_or_else(|| error_here!(ErrorKind::SessionKeyNotFound))?;
+        keys.remove(index);
+        self.with_account_mut(&account_id, |account_view| {
+            account_view.account.extra.on_session_key_revoked();
+            Ok(())
+        })?;
+        let Contract::V1(ref mut contract) = self.contract_mut();
+        if keys.is_empty() {
+            contract.session_keys.remove(&account_id);
+        } else {
+            contract.session_keys.insert(account_id, keys);
+        }
+        Ok(())
+    }
+
+    /// If call is signed with caller's session key, counts swap of `amount_in` of `token_in`
+    /// against its daily volume cap
+    pub(super) fn consume_session_volume(
+        &mut self,
+        token_in: &TokenId,
+        amount_in: Amount,
+    ) -> Result<()> {
+        let account_id = self.get_caller_id();
+        let mut keys = self.get_session_keys(&account_id);
+        let Some(index) = self.signing_session_key(&keys) else {
+            return Ok(());
+        };
+        let day = self.get_block_timestamp() / SESSION_VOLUME_PERIOD;
+        let key = &mut keys[index];
+        if key.day != day {
+            key.day = day;
+            key.volumes.fill(Amount::zero());
+        }
+        let cap_index = key
+            .volume_caps
+            .iter()
+            .position(|(token_id, _)| token_id == token_in)
+            .ok_or_else(|| error_here!(ErrorKind::SessionKeyNotAllowed))?;
+        let volume = key.volumes[cap_index]
+            .checked_add(amount_in)
+            .filter(|volume| *volume <= key.volume_caps[cap_index].1)
+            .ok_or_else(|| error_here!(ErrorKind::SessionVolumeCapExceeded))?;
+        key.volumes[cap_index] = volume;
+        let Contract::V1(ref mut contract) = self.contract_mut();
+        contract.session_keys.insert(account_id, keys);
+        Ok(())
+    }
+}
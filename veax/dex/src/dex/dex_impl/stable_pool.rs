@@ -0,0 +1,269 @@
+//! Stable swap pools for pegged tokens, see `PoolKind::Stable`
+//!
+//! Amplification coefficient is chosen by the first liquidity provider,
+//! and afterwards can be changed only by owner, through `AdminAction::SetStableAmplification`.
+use super::{fee_rates_ticks, AccountViewMut, Dex};
+use crate::chain::{AccountId, Amount, TokenId};
+use crate::dex::errors::{ErrorKind, Result};
+use crate::dex::latest::{MAX_AMPLIFICATION, MIN_AMPLIFICATION};
+use crate::dex::util_types::{PoolId, Side};
+use crate::dex::utils::swap_if;
+use crate::dex::{
+    state_types, AdminAction, BasisPoints, ConfigChange, Contract, EventKind, Exact, Float, Logger,
+    Map, PoolKind, PoolUpdateReason, StablePool, StablePoolInfo, StablePoolV0, State, StateMut,
+    Types, BASIS_POINT_DIVISOR,
+};
+use crate::{ensure_here, error_here};
+use std::borrow::{Borrow, BorrowMut};
+
+impl<T: Types, S: State<T>, SS: Borrow<S>> Dex<T, S, SS> {
+    /// State of stable swap pool; reserves are ordered the same way as `tokens`
+    pub fn get_stable_pool_info(
+        &self,
+        tokens: (TokenId, TokenId),
+    ) -> Result<Option<StablePoolInfo>> {
+        let (pool_id, swapped) = PoolId::try_from_pair(tokens).map_err(|e| error_here!(e))?;
+        let Contract::V1(ref contract) = self.contract();
+        Ok(contract
+            .stable_pools
+            .inspect(&pool_id, |StablePool::V0(ref pool)| StablePoolInfo {
+                reserves: swap_if(swapped, pool.reserves),
+                amplification: pool.amplification,
+                fee_rate: pool.fee_rate,
+                total_shares: pool.total_shares,
+                fee_divisor: BASIS_POINT_DIVISOR,
+            }))
+    }
+
+    /// Shares of stable swap pool owned by account
+    pub fn get_stable_pool_shares(
+        &self,
+        tokens: (TokenId, TokenId),
+        account_id: &AccountId,
+    ) -> Result<Amount> {
+        let (pool_id, _) = PoolId::try_from_pair(tokens).map_err(|e| error_here!(e))?;
+        let Contract::V1(ref contract) = self.contract();
+        contract
+            .stable_pools
+            .try_inspect(&pool_id, |StablePool::V0(ref pool)| {
+                pool.shares_of(account_id)
+            })
+    }
+}
+
+impl<T: Types, S: StateMut<T>, SS: BorrowMut<S>> Dex<T, S, SS> {
+    /// Deposit liquidity into stable swap pool, creating the pool if pair has none
+    ///
+    /// # Parameters
+    /// * `token_a`, `token_b` - pair of tokens
+    /// * `fee_rate` - fee rate of pool, one of `fee_rates_ticks`; must match existing pool
+    /// * `amplification` - amplification coefficient of new pool; ignored if pool exists
+    /// * `max_amounts` - maximal amounts of `token_a` and `token_b` to deposit
+    ///
+    /// # Returns
+    /// Actually deposited amounts of `token_a` and `token_b`, and minted shares
+    pub fn add_stable_liquidity(
+        &mut self,
+        token_a: &TokenId,
+        token_b: &TokenId,
+        fee_rate: BasisPoints,
+        amplification: u32,
+        max_amounts: (Amount, Amount),
+    ) -> Result<(Amount, Amount, Amount)> {
+        self.ensure_payable_api_resumed()?;
+        self.with_caller_account_mut(|mut account_view| {
+            Self::add_stable_liquidity_impl(
+                token_a,
+                token_b,
+                fee_rate,
+                amplification,
+                max_amounts,
+                &mut account_view,
+            )
+        })
+    }
+
+    fn add_stable_liquidity_impl(
+        token_a: &TokenId,
+        token_b: &TokenId,
+        fee_rate: BasisPoints,
+        amplification: u32,
+        max_amounts: (Amount, Amount),
+        account_view: &mut AccountViewMut<'_, T>,
+    ) -> Result<(Amount, Amount, Amount)> {
+        let (pool_id, transposed) = PoolId::try_from_pair((token_a.clone(), token_b.clone()))
+            .map_err(|e| error_here!(e))?;
+        account_view.ensure_pool_kind(&pool_id, PoolKind::Stable)?;
+        ensure_here!(fee_rates_ticks().contains(&fee_rate), ErrorKind::IllegalFee);
+        let max_amounts = swap_if(transposed, max_amounts);
+
+        if !account_view.stable_pools.contains_key(&pool_id) {
+            validate_amplification(amplification)?;
+            account_view.create_pool(&pool_id, token_a, token_b, |account_view| {
+                let pool = account_view
+                    .item_factory
+                    .new_stable_pool(fee_rate, amplification)?;
+                account_view.stable_pools.insert(pool_id.clone(), pool);
+                Ok(())
+            })?;
+        }
+
+        let account_id = account_view.account_id;
+        let (amounts, shares) =
+            account_view
+                .stable_pools
+                .try_update(&pool_id, |StablePool::V0(ref mut pool)| {
+                    ensure_here!(pool.fee_rate == fee_rate, ErrorKind::IllegalFee);
+                    let (amounts, shares) = pool.add_liquidity(account_id, max_amounts)?;
+                    account_view
+                        .account
+                        .withdraw(&pool_id.0, amounts.0)
+                        .map_err(|e| error_here!(e))?;
+                    account_view
+                        .account
+                        .withdraw(&pool_id.1, amounts.1)
+                        .map_err(|e| error_here!(e))?;
+                    Self::log_stable_pool_state(
+                        &pool_id,
+                        pool,
+                        account_view.logger,
+                        PoolUpdateReason::AddLiquidity,
+                    )?;
+                    Ok((amounts, shares))
+                })?;
+
+        let amounts = swap_if(transposed, amounts);
+        Ok((amounts.0, amounts.1, shares))
+    }
+
+    /// Burn caller's shares of stable swap pool, returning withdrawn amounts to caller's deposits
+    ///
+    /// # Returns
+    /// Withdrawn amounts, ordered the same way as `tokens`
+    pub fn remove_stable_liquidity(
+        &mut self,
+        tokens: (TokenId, TokenId),
+        shares: Amount,
+    ) -> Result<(Amount, Amount)> {
+        self.ensure_payable_api_resumed()?;
+        let (pool_id, swapped) = PoolId::try_from_pair(tokens).map_err(|e| error_here!(e))?;
+        self.with_caller_account_mut(|account_view| {
+            let account_id = account_view.account_id;
+            let amounts = account_view.stable_pools.try_update(
+                &pool_id,
+                |StablePool::V0(ref mut pool)| {
+                    let amounts = pool.remove_liquidity(account_id, shares)?;
+                    account_view
+                        .account
+                        .deposit(&pool_id.0, amounts.0)
+                        .map_err(|e| error_here!(e))?;
+                    account_view
+                        .account
+                        .deposit(&pool_id.1, amounts.1)
+                        .map_err(|e| error_here!(e))?;
+                    Self::log_stable_pool_state(
+                        &pool_id,
+                        pool,
+                        account_view.logger,
+                        PoolUpdateReason::RemoveLiquidity,
+                    )?;
+                    Ok(amounts)
+                },
+            )?;
+            Ok(swap_if(swapped, amounts))
+        })
+    }
+
+    /// Change amplification coefficient of stable swap pool
+    pub fn set_stable_amplification(
+        &mut self,
+        tokens: (TokenId, TokenId),
+        amplification: u32,
+    ) -> Result<()> {
+        self.ensure_payable_api_resumed()?;
+        self.ensure_caller_is_owner()?;
+        self.ensure_multisig_disabled()?;
+        self.ensure_timelock_disabled()?;
+        self.apply_admin_action(AdminAction::SetStableAmplification {
+            tokens,
+            amplification,
+        })
+    }
+
+    /// Change amplification coefficient of stable swap pool, without any permission checks
+    pub(super) fn set_stable_amplification_unchecked(
+        &mut self,
+        tokens: (TokenId, TokenId),
+        amplification: u32,
+    ) -> Result<()> {
+        let (pool_id, _) = PoolId::try_from_pair(tokens).map_err(|e| error_here!(e))?;
+        let Contract::V1(ref mut contract) = self.contract_mut();
+        let previous =
+            contract
+                .stable_pools
+                .try_update(&pool_id, |StablePool::V0(ref mut pool)| {
+                    Ok(std::mem::replace(&mut pool.amplification, amplification))
+                })?;
+        let caller_id = self.get_caller_id();
+        self.logger_mut().log_config_change_event(
+            &caller_id,
+            &ConfigChange::StableAmplification {
+                pool: (pool_id.0.clone(), pool_id.1.clone()),
+                previous,
+                new: amplification,
+            },
+        );
+        Ok(())
+    }
+
+    /// Swap tokens in stable swap pool and log its updated state
+    ///
+    /// # Returns
+    /// Input and output amounts
+    pub(super) fn swap_stable(
+        stable_pools: &mut state_types::StablePoolsMap<T>,
+        pool_id: &PoolId,
+        side: Side,
+        exact: Exact,
+        amount: Amount,
+        protocol_fee_fraction: BasisPoints,
+        logger: &mut dyn Logger,
+    ) -> Result<(Amount, Amount)> {
+        stable_pools.try_update(pool_id, |StablePool::V0(ref mut pool)| {
+            let amounts = pool.swap(side, exact, amount, protocol_fee_fraction)?;
+            Self::log_stable_pool_state(pool_id, pool, logger, PoolUpdateReason::Swap)?;
+            Ok(amounts)
+        })
+    }
+
+    /// Logs state of stable swap pool, reporting marginal price and invariant `D` as liquidity
+    fn log_stable_pool_state(
+        pool_id: &PoolId,
+        pool: &StablePoolV0<T>,
+        logger: &mut dyn Logger,
+        reason: PoolUpdateReason,
+    ) -> Result<()> {
+        if !logger.is_event_enabled(EventKind::UpdatePoolState) {
+            return Ok(());
+        }
+        let invariant =
+            u128::try_from(pool.invariant()?).map_err(|_| error_here!(ErrorKind::ConvOverflow))?;
+        Self::log_single_level_pool_state(
+            pool_id,
+            pool.reserves,
+            pool.spot_price()?.sqrt(),
+            Float::from(invariant),
+            logger,
+            reason,
+        );
+        Ok(())
+    }
+}
+
+pub(super) fn validate_amplification(amplification: u32) -> Result<()> {
+    ensure_here!(
+        (MIN_AMPLIFICATION..=MAX_AMPLIFICATION).contains(&amplification),
+        ErrorKind::IllegalAmplification
+    );
+    Ok(())
+}
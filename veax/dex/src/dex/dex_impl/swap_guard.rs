@@ -0,0 +1,193 @@
+//! Sandwich guard: pools may be configured by owner to penalize or reject swap
+//! which reverses the previous swap of the same account through the same pool
+//! within the same block, as MEV mitigation
+use super::{pool_kind, Dex};
+use crate::chain::{AccountId, Amount, TokenId, UIntBig};
+use crate::dex::errors::{ErrorKind, Result};
+use crate::dex::util_types::PoolId;
+use crate::dex::{
+    state_types, BasisPoints, ConfigChange, Contract, Exact, Map, MapRemoveKey, State, StateMut,
+    SwapGuard, SwapGuardMode, Types, BASIS_POINT_DIVISOR,
+};
+use crate::{ensure_here, error_here};
+use num_traits::Zero;
+use std::borrow::{Borrow, BorrowMut};
+
+impl<T: Types, S: State<T>, SS: Borrow<S>> Dex<T, S, SS> {
+    /// Sandwich guard mode of pool, if any
+    pub fn get_swap_guard(&self, tokens: (TokenId, TokenId)) -> Result<Option<SwapGuardMode>> {
+        let (pool_id, _) = PoolId::try_from_pair(tokens).map_err(|e| error_here!(e))?;
+        let Contract::V1(ref contract) = self.contract();
+        Ok(contract.swap_guards.inspect(&pool_id, |guard| guard.mode))
+    }
+}
+
+impl<T: Types, S: StateMut<T>, SS: BorrowMut<S>> Dex<T, S, SS> {
+    /// Set sandwich guard mode of pool, or remove guard if `None` is passed
+    pub fn set_swap_guard(
+        &mut self,
+        tokens: (TokenId, TokenId),
+        mode: Option<SwapGuardMode>,
+    ) -> Result<()> {
+        self.ensure_payable_api_resumed()?;
+        self.ensure_caller_is_owner()?;
+        if let Some(SwapGuardMode::ExtraFee(fee_rate)) = mode {
+            ensure_here!(fee_rate <= BASIS_POINT_DIVISOR, ErrorKind::IllegalFee);
+        }
+        let (pool_id, _) = PoolId::try_from_pair(tokens).map_err(|e| error_here!(e))?;
+        let Contract::V1(ref mut contract) = self.contract_mut();
+        ensure_here!(
+            pool_kind::<T>(
+                &contract.pools,
+                &contract.classic_pools,
+                &contract.stable_pools,
+                &pool_id
+            )
+            .is_some(),
+            ErrorKind::PoolNotRegistered
+        );
+        let previous = contract.swap_guards.inspect(&pool_id, |guard| guard.mode);
+        match mode {
+            Some(mode) => contract.swap_guards.insert(
+                pool_id.clone(),
+                SwapGuard {
+                    mode,
+                    last_swap: None,
+                },
+            ),
+            None => contract.swap_guards.remove(&pool_id),
+        }
+        let caller_id = self.get_caller_id();
+        self.logger_mut().log_config_change_event(
+            &caller_id,
+            &ConfigChange::SwapGuard {
+                pool: (pool_id.0.clone(), pool_id.1.clone()),
+                previous,
+                new: mode,
+            },
+        );
+        Ok(())
+    }
+
+    /// Check swap of caller against sandwich guard of its pool, and charge extra fee if needed
+    ///
+    /// # Parameters
+    /// * `amounts` - input and output amounts of swap, as evaluated by pool
+    ///
+    /// # Returns
+    /// Input and output amounts with extra fee applied
+    pub(super) fn apply_swap_guard(
+        &mut self,
+        pool_id: &PoolId,
+        (token_in, token_out): (&TokenId, &TokenId),
+        exact: Exact,
+        amounts: (Amount, Amount),
+    ) -> Result<(Amount, Amount)> {
+        let account_id = self.get_caller_id();
+        let block_height = self.get_block_height();
+        let Contract::V1(ref mut contract) = self.contract_mut();
+        let fee_rate = check_swap_guard::<T>(
+            &mut contract.swap_guards,
+            pool_id,
+            &account_id,
+            token_in,
+            block_height,
+        )?;
+        let (amounts, extra_fee) = charge_extra_fee(exact, amounts, fee_rate);
+        let fee_token = match exact {
+            Exact::In => token_out,
+            Exact::Out => token_in,
+        };
+        credit_extra_fee::<T>(&mut contract.insurance_reserve, fee_token, extra_fee)?;
+        Ok(amounts)
+    }
+
+    /// Put extra fees charged by sandwich guards into insurance reserve
+    pub(super) fn credit_extra_fees(
+        &mut self,
+        extra_fees: impl IntoIterator<Item = (TokenId, Amount)>,
+    ) -> Result<()> {
+        let Contract::V1(ref mut contract) = self.contract_mut();
+        for (token_id, amount) in extra_fees {
+            credit_extra_fee::<T>(&mut contract.insurance_reserve, &token_id, amount)?;
+        }
+        Ok(())
+    }
+}
+
+/// Check swap against sandwich guard of its pool, and record it as the last swap through pool
+///
+/// # Returns
+/// Extra fee rate which should be charged for swap; zero if swap isn't penalized
+pub(super) fn check_swap_guard<T: Types>(
+    swap_guards: &mut state_types::SwapGuardsMap<T>,
+    pool_id: &PoolId,
+    account_id: &AccountId,
+    token_in: &TokenId,
+    block_height: u64,
+) -> Result<BasisPoints> {
+    swap_guards
+        .update(pool_id, |guard| {
+            let reverses = matches!(
+                &guard.last_swap,
+                Some((last_account, last_height, last_token_in))
+                    if last_account == account_id
+                        && *last_height == block_height
+                        && last_token_in != token_in
+            );
+            #[allow(clippy::clone_on_copy)] // not all account ids are copyable
+            let last_swap = (account_id.clone(), block_height, token_in.clone());
+            guard.last_swap = Some(last_swap);
+            match guard.mode {
+                _ if !reverses => Ok(0),
+                SwapGuardMode::ExtraFee(fee_rate) => Ok(fee_rate),
+                SwapGuardMode::Reject => Err(error_here!(ErrorKind::SandwichSwap)),
+            }
+        })
+        .unwrap_or(Ok(0))
+}
+
+/// Apply extra fee to swap amounts: it's taken from output of exact-in swap,
+/// and added to input of exact-out swap
+///
+/// # Returns
+/// Adjusted input and output amounts, and amount of extra fee
+pub(super) fn charge_extra_fee(
+    exact: Exact,
+    (amount_in, amount_out): (Amount, Amount),
+    fee_rate: BasisPoints,
+) -> ((Amount, Amount), Amount) {
+    let fee_of = |amount: Amount| {
+        (UIntBig::from(amount) * UIntBig::from(fee_rate) / UIntBig::from(BASIS_POINT_DIVISOR))
+            .low_u128()
+    };
+    match exact {
+        Exact::In => {
+            let fee = fee_of(amount_out);
+            ((amount_in, amount_out - fee), fee)
+        }
+        Exact::Out => {
+            let fee = fee_of(amount_in);
+            ((amount_in + fee, amount_out), fee)
+        }
+    }
+}
+
+/// Put extra fee charged by sandwich guard into insurance reserve
+fn credit_extra_fee<T: Types>(
+    insurance_reserve: &mut state_types::InsuranceReserveMap<T>,
+    token_id: &TokenId,
+    amount: Amount,
+) -> Result<()> {
+    if amount.is_zero() {
+        return Ok(());
+    }
+    insurance_reserve.update_or_insert(
+        token_id,
+        || Ok(Amount::zero()),
+        |reserve, _| {
+            *reserve += amount;
+            Ok(())
+        },
+    )
+}
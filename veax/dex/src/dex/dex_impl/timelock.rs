@@ -0,0 +1,217 @@
+//! Governance timelock: sensitive owner actions take effect only after configured delay,
+//! so users get advance notice of parameter changes
+use super::fee_auction::validate_fee_auction_config;
+use super::multisig::validate_admins;
+use super::stable_pool::validate_amplification;
+use super::Dex;
+use crate::dex::errors::{ErrorKind, Result};
+use crate::dex::{
+    AdminAction, ConfigChange, Contract, Map, MapRemoveKey, ScheduledAction, State, StateMut,
+    Types, BASIS_POINT_DIVISOR,
+};
+use crate::ensure_here;
+use std::borrow::{Borrow, BorrowMut};
+
+/// Maximal timelock delay, in seconds; prevents owner from locking out parameter changes forever
+pub const MAX_TIMELOCK_DELAY: u64 = 30 * 24 * 60 * 60;
+
+impl<T: Types, S: State<T>, SS: Borrow<S>> Dex<T, S, SS> {
+    /// Delay between scheduling and execution of owner actions, in seconds; zero if timelock is disabled
+    pub fn get_timelock_delay(&self) -> u64 {
+        let Contract::V1(ref contract) = self.contract();
+        contract.timelock_delay
+    }
+
+    /// Owner actions waiting for execution, with their ids
+    pub fn get_scheduled_actions(&self) -> Vec<(u64, ScheduledAction)> {
+        let Contract::V1(ref contract) = self.contract();
+        contract
+            .scheduled_actions
+            .iter()
+            .map(|(id, scheduled)| (*id, ScheduledAction::clone(&scheduled)))
+            .collect()
+    }
+}
+
+impl<T: Types, S: StateMut<T>, SS: BorrowMut<S>> Dex<T, S, SS> {
+    /// Schedule owner action for execution after timelock delay
+    ///
+    /// # Parameters
+    /// * `action` - action to schedule; it's validated right away, to not wait for delay
+    ///     only to find out action can't be executed
+    ///
+    /// # Returns
+    /// Id of scheduled action, which should be passed to `execute_admin_action` or `cancel_admin_action`
+    pub fn schedule_admin_action(&mut self, action: AdminAction) -> Result<u64> {
+        self.ensure_payable_api_resumed()?;
+        self.ensure_caller_is_owner()?;
+        self.ensure_multisig_disabled()?;
+        validate_admin_action(&action)?;
+        Ok(self.schedule_admin_action_unchecked(action))
+    }
+
+    /// Execute previously scheduled owner action, if its timelock has expired
+    ///
+    /// # Parameters
+    /// * `id` - id of scheduled action
+    pub fn execute_admin_action(&mut self, id: u64) -> Result<()> {
+        self.ensure_payable_api_resumed()?;
+        self.ensure_caller_is_owner_or_admin()?;
+
+        let now = self.get_block_timestamp();
+
+        let Contract::V1(ref mut contract) = self.contract_mut();
+        let scheduled = contract
+            .scheduled_actions
+            .try_inspect(&id, ScheduledAction::clone)?;
+        ensure_here!(
+            now >= scheduled.execute_after,
+            ErrorKind::TimelockNotExpired
+        );
+        contract.scheduled_actions.remove(&id);
+
+        self.apply_admin_action(scheduled.action.clone())?;
+
+        self.logger_mut()
+            .log_action_executed_event(id, &scheduled.action);
+        Ok(())
+    }
+
+    /// Cancel previously scheduled owner action
+    pub fn cancel_admin_action(&mut self, id: u64) -> Result<()> {
+        self.ensure_caller_is_owner_or_admin()?;
+
+        let Contract::V1(ref mut contract) = self.contract_mut();
+        let scheduled = contract
+            .scheduled_actions
+            .try_inspect(&id, ScheduledAction::clone)?;
+        contract.scheduled_actions.remove(&id);
+
+        self.logger_mut()
+            .log_action_cancelled_event(id, &scheduled.action);
+        Ok(())
+    }
+
+    /// Enable timelock by setting non-zero delay, or change delay while timelock is disabled.
+    /// Once timelock is enabled, delay can be changed only through `AdminAction::SetTimelockDelay`
+    pub fn set_timelock_delay(&mut self, delay: u64) -> Result<()> {
+        self.ensure_payable_api_resumed()?;
+        self.ensure_caller_is_owner()?;
+        self.ensure_multisig_disabled()?;
+        self.ensure_timelock_disabled()?;
+        self.apply_admin_action(AdminAction::SetTimelockDelay(delay))
+    }
+
+    /// Owner actions subject to timelock can be executed directly only while timelock is disabled
+    pub(super) fn ensure_timelock_disabled(&self) -> Result<()> {
+        let Contract::V1(ref contract) = self.contract();
+        ensure_here!(contract.timelock_delay == 0, ErrorKind::TimelockEnabled);
+        Ok(())
+    }
+
+    /// Schedule already validated owner action, without any permission checks
+    pub(super) fn schedule_admin_action_unchecked(&mut self, action: AdminAction) -> u64 {
+        let now = self.get_block_timestamp();
+        let Contract::V1(ref mut contract) = self.contract_mut();
+        let id = contract.next_scheduled_action_id;
+        contract.next_scheduled_action_id += 1;
+        let execute_after = now.saturating_add(contract.timelock_delay);
+        contract.scheduled_actions.insert(
+            id,
+            ScheduledAction {
+                action: action.clone(),
+                execute_after,
+            },
+        );
+
+        self.logger_mut()
+            .log_action_scheduled_event(id, &action, execute_after);
+        id
+    }
+
+    /// Apply owner action, without any permission or timelock checks
+    pub(super) fn apply_admin_action(&mut self, action: AdminAction) -> Result<()> {
+        validate_admin_action(&action)?;
+        match action {
+            AdminAction::SetProtocolFeeFraction(protocol_fee_fraction) => {
+                let Contract::V1(ref mut contract) = self.contract_mut();
+                let previous =
+                    std::mem::replace(&mut contract.protocol_fee_fraction, protocol_fee_fraction);
+                // Fee is charged on every hop, so swaps along all cached routes are repriced
+                #[cfg(feature = "near")]
+                #[cfg(feature = "smart-routing")]
+                contract.route_cache.clear();
+                let caller_id = self.get_caller_id();
+                self.logger_mut().log_set_protocol_fee_fraction_event(
+                    &caller_id,
+                    previous,
+                    protocol_fee_fraction,
+                );
+            }
+            AdminAction::RemoveVerifiedTokens(tokens) => {
+                self.remove_verified_tokens_unchecked(tokens);
+            }
+            AdminAction::SetTimelockDelay(delay) => {
+                let Contract::V1(ref mut contract) = self.contract_mut();
+                let previous = std::mem::replace(&mut contract.timelock_delay, delay);
+                let caller_id = self.get_caller_id();
+                self.logger_mut().log_config_change_event(
+                    &caller_id,
+                    &ConfigChange::TimelockDelay {
+                        previous,
+                        new: delay,
+                    },
+                );
+            }
+            AdminAction::SetAdmins { admins, threshold } => {
+                self.set_admins_unchecked(admins, threshold);
+            }
+            AdminAction::SetStableAmplification {
+                tokens,
+                amplification,
+            } => {
+                self.set_stable_amplification_unchecked(tokens, amplification)?;
+            }
+            AdminAction::SetFeeAuctionConfig(config) => {
+                self.set_fee_auction_config_unchecked(config);
+            }
+            AdminAction::StageUpgrade(code_hash) => {
+                self.stage_upgrade_unchecked(code_hash);
+            }
+            AdminAction::SetWithdrawLimit { token, config } => {
+                self.set_withdraw_limit_unchecked(&token, config);
+            }
+        }
+        Ok(())
+    }
+}
+
+pub(super) fn validate_admin_action(action: &AdminAction) -> Result<()> {
+    match *action {
+        AdminAction::SetProtocolFeeFraction(protocol_fee_fraction) => {
+            ensure_here!(protocol_fee_fraction > 0, ErrorKind::IllegalFee);
+            ensure_here!(
+                protocol_fee_fraction <= BASIS_POINT_DIVISOR / 2,
+                ErrorKind::IllegalFee
+            );
+        }
+        AdminAction::RemoveVerifiedTokens(_) => {}
+        AdminAction::SetTimelockDelay(delay) => {
+            ensure_here!(delay <= MAX_TIMELOCK_DELAY, ErrorKind::InvalidParams);
+        }
+        AdminAction::SetAdmins {
+            ref admins,
+            threshold,
+        } => {
+            validate_admins(admins, threshold)?;
+        }
+        AdminAction::SetStableAmplification { amplification, .. } => {
+            validate_amplification(amplification)?;
+        }
+        AdminAction::SetFeeAuctionConfig(ref config) => {
+            validate_fee_auction_config(config)?;
+        }
+        AdminAction::StageUpgrade(_) | AdminAction::SetWithdrawLimit { .. } => {}
+    }
+    Ok(())
+}
@@ -0,0 +1,93 @@
+//! Check of token contracts before they are first used
+//!
+//! Contract which doesn't implement fungible token interface, e.g. mistyped account
+//! or custom contract merely calling `ft_on_transfer`, may leave deposited funds or pools
+//! which can't be withdrawn from. When check is enabled, token contract is pinged
+//! before its first deposit is credited or pool with it is created, and outcome is cached.
+use super::Dex;
+use crate::chain::TokenId;
+use crate::dex::errors::{ErrorKind, Result};
+use crate::dex::traits::AccountExtra;
+use crate::dex::{state_types, ConfigChange, Contract, Map, State, StateMut, Types};
+use crate::ensure_here;
+use std::borrow::{Borrow, BorrowMut};
+
+impl<T: Types, S: State<T>, SS: Borrow<S>> Dex<T, S, SS> {
+    /// Whether token contracts must pass interface check before they are used
+    pub fn is_token_interface_check_enabled(&self) -> bool {
+        let Contract::V1(ref contract) = self.contract();
+        contract.token_interface_check
+    }
+
+    /// Outcome of interface check of token contract
+    ///
+    /// # Returns
+    /// * `None` - if token contract isn't checked yet
+    pub fn get_token_interface_status(&self, token_id: &TokenId) -> Option<bool> {
+        let Contract::V1(ref contract) = self.contract();
+        contract
+            .token_interfaces
+            .inspect(token_id, |implemented| *implemented)
+    }
+}
+
+impl<T: Types, S: StateMut<T>, SS: BorrowMut<S>> Dex<T, S, SS> {
+    /// Enable or disable interface check of token contracts
+    ///
+    /// Tokens already in use are checked on their next deposit
+    pub fn set_token_interface_check(&mut self, enabled: bool) -> Result<()> {
+        self.ensure_payable_api_resumed()?;
+        self.ensure_caller_is_owner()?;
+        let Contract::V1(ref mut contract) = self.contract_mut();
+        let previous = std::mem::replace(&mut contract.token_interface_check, enabled);
+        let caller_id = self.get_caller_id();
+        self.logger_mut().log_config_change_event(
+            &caller_id,
+            &ConfigChange::TokenInterfaceCheck {
+                previous,
+                new: enabled,
+            },
+        );
+        Ok(())
+    }
+
+    /// Charge caller for storage of interface check outcome, if token isn't checked yet;
+    /// repeated checks of already checked token overwrite its outcome and are free
+    pub fn request_token_interface_check(&mut self, token_id: &TokenId) -> Result<()> {
+        ensure_here!(
+            self.is_token_interface_check_enabled(),
+            ErrorKind::InvalidParams
+        );
+        if self.get_token_interface_status(token_id).is_some() {
+            return Ok(());
+        }
+        let account_id = self.get_caller_id();
+        self.with_account_mut(&account_id, |account_view| {
+            account_view
+                .account
+                .extra
+                .on_token_interface_check_requested()
+        })
+    }
+
+    /// Cache outcome of interface check of token contract
+    pub fn set_token_interface_status(&mut self, token_id: &TokenId, implemented: bool) {
+        let Contract::V1(ref mut contract) = self.contract_mut();
+        contract
+            .token_interfaces
+            .insert(token_id.clone(), implemented);
+    }
+}
+
+/// Fails with `TokenInterfaceNotChecked` if any of `tokens` isn't checked yet,
+/// and with `TokenInterfaceCheckFailed` if any of them failed check
+pub(super) fn ensure_interfaces_implemented<'a, T: Types>(
+    token_interfaces: &state_types::TokenInterfacesMap<T>,
+    tokens: impl IntoIterator<Item = &'a TokenId>,
+) -> Result<()> {
+    for token in tokens {
+        let implemented = token_interfaces.try_inspect(token, |implemented| *implemented)?;
+        ensure_here!(implemented, ErrorKind::TokenInterfaceCheckFailed);
+    }
+    Ok(())
+}
@@ -0,0 +1,161 @@
+//! Top pools of each token for smart routing, ranked by total liquidity
+//!
+//! Ranking is maintained incrementally: when total liquidity of a pool drifts by more than
+//! `1 / TOP_POOLS_HYSTERESIS_DIVISOR` from the value it was last ranked with, only top pools
+//! of its two tokens are revisited. Small changes, e.g. most swaps, don't touch rankings at all.
+use super::Dex;
+use crate::chain::{Liquidity, TokenId};
+use crate::dex::errors::{ErrorKind, Result};
+use crate::dex::latest::NUM_TOP_POOLS;
+use crate::dex::util_types::PoolId;
+use crate::dex::{state_types, Contract, Map, Pool, Set, State, StateMembersMut, StateMut, Types};
+use crate::error_here;
+use num_traits::Zero;
+use std::borrow::{Borrow, BorrowMut};
+use std::cmp::Reverse;
+
+/// Pool is re-ranked once its total liquidity changes by more than `1 / N` of ranked value
+const TOP_POOLS_HYSTERESIS_DIVISOR: u128 = 10;
+
+impl<T: Types, S: State<T>, SS: Borrow<S>> Dex<T, S, SS> {
+    /// Tokens of top pools of `token`, ordered by descending liquidity
+    pub fn get_token_top_pools(&self, token: &TokenId) -> Result<Vec<TokenId>> {
+        let Contract::V1(ref contract) = self.contract();
+        let mut top_pools: Vec<TokenId> = contract
+            .top_pools
+            .inspect(token, |tokens| tokens.iter().map(|t| t.clone()).collect())
+            .ok_or(error_here!(ErrorKind::TokenNotRegistered))?;
+        top_pools.sort_by_cached_key(|other| {
+            Reverse(ranked_liquidity::<T>(&contract.pools, token, other))
+        });
+        Ok(top_pools)
+    }
+}
+
+impl<T: Types, S: StateMut<T>, SS: BorrowMut<S>> Dex<T, S, SS> {
+    /// Drop cached routes affected by each of specified pools, which state was just updated,
+    /// and re-rank top pools of both its tokens, if its total liquidity drifted past
+    /// hysteresis threshold
+    ///
+    /// Pools of kinds other than concentrated are skipped.
+    pub(super) fn update_top_pools(
+        &mut self,
+        pool_ids: impl IntoIterator<Item = PoolId>,
+    ) -> Result<()> {
+        let StateMembersMut {
+            contract: Contract::V1(ref mut contract),
+            item_factory,
+            ..
+        } = self.members_mut();
+
+        for pool_id in pool_ids {
+            let Some(update) = contract.pools.update(&pool_id, |Pool::V1(ref mut pool)| {
+                let before = pool.ranked_liquidity;
+                let after = pool.total_liquidity();
+                if !exceeds_hysteresis(before, after) {
+                    return Ok(None);
+                }
+                pool.ranked_liquidity = after;
+                Ok(Some((before, after)))
+            }) else {
+                continue;
+            };
+            // Any update may change prices or liquidity along cached routes, not only re-ranking
+            super::route_cache::invalidate_routes(&mut contract.route_cache, &pool_id);
+            let Some((before, after)) = update? else {
+                continue;
+            };
+
+            for (token, other) in [(&pool_id.0, &pool_id.1), (&pool_id.1, &pool_id.0)] {
+                let top_pools: Vec<TokenId> = contract
+                    .top_pools
+                    .inspect(token, |tokens| tokens.iter().map(|t| t.clone()).collect())
+                    .unwrap_or_default();
+
+                // Pair of tokens to remove from and to add to top pools
+                let (removed, added) = if top_pools.contains(other) {
+                    if after >= before {
+                        continue;
+                    }
+                    // Pool went down, so it may be outranked by the best of the rest
+                    let best_rest = contract
+                        .token_connections
+                        .inspect(token, |connections| {
+                            connections
+                                .iter()
+                                .filter(|t| !top_pools.contains(t))
+                                .map(|t| {
+                                    let liquidity =
+                                        ranked_liquidity::<T>(&contract.pools, token, &t);
+                                    (t.clone(), liquidity)
+                                })
+                                .max_by_key(|(_, liquidity)| *liquidity)
+                        })
+                        .flatten();
+                    match best_rest {
+                        Some((best, liquidity)) if liquidity > after => {
+                            (Some(other.clone()), Some(best))
+                        }
+                        _ => continue,
+                    }
+                } else if top_pools.len() < NUM_TOP_POOLS {
+                    if after.is_zero() {
+                        continue;
+                    }
+                    (None, Some(other.clone()))
+                } else {
+                    // Pool may outrank the worst of current top pools
+                    let worst = top_pools
+                        .iter()
+                        .map(|t| (t, ranked_liquidity::<T>(&contract.pools, token, t)))
+                        .min_by_key(|(_, liquidity)| *liquidity);
+                    match worst {
+                        Some((worst, liquidity)) if liquidity < after => {
+                            (Some(worst.clone()), Some(other.clone()))
+                        }
+                        _ => continue,
+                    }
+                };
+
+                let factory = &mut *item_factory;
+                contract.top_pools.update_or_insert(
+                    token,
+                    || Ok(factory.new_tokens_array_set()),
+                    |tokens, _| {
+                        if let Some(removed) = removed {
+                            tokens.remove_item(&removed);
+                        }
+                        if let Some(added) = added {
+                            tokens.add_item(added);
+                        }
+                        Ok(())
+                    },
+                )?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Liquidity which pool of pair was last ranked with; zero for pools of other kinds
+fn ranked_liquidity<T: Types>(
+    pools: &state_types::PoolsMap<T>,
+    token: &TokenId,
+    other: &TokenId,
+) -> Liquidity {
+    PoolId::try_from_pair((token.clone(), other.clone()))
+        .ok()
+        .and_then(|(pool_id, _)| {
+            pools.inspect(&pool_id, |Pool::V1(ref pool)| pool.ranked_liquidity)
+        })
+        .unwrap_or_else(Liquidity::zero)
+}
+
+fn exceeds_hysteresis(before: Liquidity, after: Liquidity) -> bool {
+    let change = if after > before {
+        after - before
+    } else {
+        before - after
+    };
+    change > before / Liquidity::from(TOP_POOLS_HYSTERESIS_DIVISOR)
+}
@@ -0,0 +1,23 @@
+//! Runtime control of trace records of pool math, see `dex::tracing`
+use super::Dex;
+use crate::dex::errors::Result;
+use crate::dex::{tracing, Contract, State, StateMut, TraceVerbosity, Types};
+use std::borrow::{Borrow, BorrowMut};
+
+impl<T: Types, S: State<T>, SS: Borrow<S>> Dex<T, S, SS> {
+    pub fn get_trace_verbosity(&self) -> TraceVerbosity {
+        let Contract::V1(ref contract) = self.contract();
+        contract.trace_verbosity
+    }
+}
+
+impl<T: Types, S: StateMut<T>, SS: BorrowMut<S>> Dex<T, S, SS> {
+    /// Set amount of details in trace records, effective immediately
+    pub fn set_trace_verbosity(&mut self, verbosity: TraceVerbosity) -> Result<()> {
+        self.ensure_caller_is_owner()?;
+        let Contract::V1(ref mut contract) = self.contract_mut();
+        contract.trace_verbosity = verbosity;
+        tracing::set_verbosity(verbosity);
+        Ok(())
+    }
+}
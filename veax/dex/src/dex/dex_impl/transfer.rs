@@ -0,0 +1,97 @@
+//! Transfers of deposited balances between accounts of DEX, without sending tokens on chain
+//!
+//! Sender's balance is debited first and receiver's one is credited afterwards,
+//! so transfer can be a part of action batch, which keeps sender's account borrowed.
+use super::{ensure_not_banned, AccountViewMut, Dex};
+use crate::chain::{AccountId, Amount, TokenId};
+use crate::dex::errors::{ErrorKind, Result};
+use crate::dex::{Map, StateMut, Types};
+use crate::{ensure_here, error_here};
+use num_traits::Zero;
+use std::borrow::BorrowMut;
+
+/// Debited part of internal transfer, which is yet to be credited to receiver
+pub(super) struct PendingTransfer {
+    pub receiver_id: AccountId,
+    pub token_id: TokenId,
+    pub amount: Amount,
+    /// Balance of sender after transfer
+    pub sender_balance: Amount,
+}
+
+impl<T: Types, S: StateMut<T>, SS: BorrowMut<S>> Dex<T, S, SS> {
+    /// Move `amount` of deposited `token_id` from caller's account to account of `receiver_id`
+    ///
+    /// Receiver must be registered and have `token_id` registered.
+    pub fn transfer_internal(
+        &mut self,
+        receiver_id: &AccountId,
+        token_id: &TokenId,
+        amount: Amount,
+    ) -> Result<()> {
+        self.ensure_payable_api_resumed()?;
+        let sender_id = self.get_caller_id();
+        let transfer = self.with_account_mut(&sender_id, |mut account_view| {
+            Self::debit_internal_transfer(&mut account_view, receiver_id, token_id, amount)
+        })?;
+        self.credit_internal_transfer(&sender_id, transfer)
+    }
+
+    /// Withdraw transferred amount from sender's account
+    pub(super) fn debit_internal_transfer(
+        account_view: &mut AccountViewMut<'_, T>,
+        receiver_id: &AccountId,
+        token_id: &TokenId,
+        amount: Amount,
+    ) -> Result<PendingTransfer> {
+        ensure_here!(!amount.is_zero(), ErrorKind::InvalidParams);
+        ensure_here!(
+            receiver_id != account_view.account_id,
+            ErrorKind::InvalidParams
+        );
+        ensure_not_banned::<T>(account_view.banned_tokens, [token_id])?;
+        // Large transfers would bypass withdrawal delay of vault
+        ensure_here!(
+            !account_view
+                .vaults
+                .inspect(account_view.account_id, |vault| vault
+                    .is_triggered(token_id, amount))
+                .unwrap_or(false),
+            ErrorKind::VaultEnabled
+        );
+        let sender_balance = account_view
+            .account
+            .withdraw(token_id, amount)
+            .map_err(|e| error_here!(e))?;
+        #[allow(clippy::clone_on_copy)] // not all account ids are copyable
+        let receiver_id = receiver_id.clone();
+        Ok(PendingTransfer {
+            receiver_id,
+            token_id: token_id.clone(),
+            amount,
+            sender_balance,
+        })
+    }
+
+    /// Deposit transferred amount onto receiver's account and log transfer
+    pub(super) fn credit_internal_transfer(
+        &mut self,
+        sender_id: &AccountId,
+        transfer: PendingTransfer,
+    ) -> Result<()> {
+        let receiver_balance = self.with_account_mut(&transfer.receiver_id, |account_view| {
+            account_view
+                .account
+                .deposit(&transfer.token_id, transfer.amount)
+                .map_err(|e| error_here!(e))
+        })?;
+        self.logger_mut().log_transfer_internal_event(
+            sender_id,
+            &transfer.receiver_id,
+            &transfer.token_id,
+            transfer.amount,
+            (transfer.sender_balance, receiver_balance),
+        );
+        Ok(())
+    }
+}
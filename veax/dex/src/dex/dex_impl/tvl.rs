@@ -0,0 +1,75 @@
+//! Canonical on-chain TVL, valued in a reference token
+//!
+//! Each token is priced by spot price of its pool against the reference token, as orders do.
+//! Tokens which have no such pool, or whose pool is empty, are valued at zero.
+use super::Dex;
+use crate::chain::{Amount, TokenId};
+use crate::dex::{
+    ClassicPool, Contract, Float, Map, Pool, PoolId, PoolTvl, StablePool, State, TvlReport, Types,
+};
+use std::borrow::Borrow;
+
+impl<T: Types, S: State<T>, SS: Borrow<S>> Dex<T, S, SS> {
+    /// Value of pools' reserves in units of `reference_token`
+    ///
+    /// # Parameters
+    /// * `from` - number of pools to skip, i.e. `next_from` returned by previous page
+    /// * `limit` - maximal number of pools to value
+    ///
+    /// # Returns
+    /// TVL of each valued pool and their total; `next_from` is set if more pools remain,
+    /// so that overall TVL is sum of totals of all pages
+    pub fn get_tvl(&self, reference_token: &TokenId, from: usize, limit: usize) -> TvlReport {
+        let Contract::V1(ref contract) = self.contract();
+        let concentrated = contract.pools.iter().map(|(pool_id, pool)| {
+            let Pool::V1(ref pool) = &*pool;
+            (PoolId::clone(&pool_id), pool.total_reserves)
+        });
+        let classic = contract.classic_pools.iter().map(|(pool_id, pool)| {
+            let ClassicPool::V0(ref pool) = &*pool;
+            (PoolId::clone(&pool_id), pool.reserves)
+        });
+        let stable = contract.stable_pools.iter().map(|(pool_id, pool)| {
+            let StablePool::V0(ref pool) = &*pool;
+            (PoolId::clone(&pool_id), pool.reserves)
+        });
+
+        let page = concentrated
+            .chain(classic)
+            .chain(stable)
+            .skip(from)
+            .take(limit);
+
+        let mut pools = Vec::new();
+        let mut total = Float::zero();
+        for (pool_id, reserves) in page {
+            let tvl = self.value_in(&pool_id.0, reserves.0, reference_token)
+                + self.value_in(&pool_id.1, reserves.1, reference_token);
+            total = total + tvl;
+            pools.push(PoolTvl {
+                tokens: (pool_id.0.clone(), pool_id.1.clone()),
+                tvl,
+            });
+        }
+
+        let pool_count =
+            contract.pools.len() + contract.classic_pools.len() + contract.stable_pools.len();
+        let next_from = from.saturating_add(pools.len());
+        TvlReport {
+            total,
+            next_from: (pools.len() == limit && next_from < pool_count).then_some(next_from as u64),
+            pools,
+        }
+    }
+
+    /// Value of `amount` of `token_id` in units of `reference_token`
+    fn value_in(&self, token_id: &TokenId, amount: Amount, reference_token: &TokenId) -> Float {
+        let price = if token_id == reference_token {
+            Float::one()
+        } else {
+            self.get_order_price(token_id, reference_token)
+                .unwrap_or_else(|_| Float::zero())
+        };
+        Float::from(amount) * price
+    }
+}
@@ -0,0 +1,55 @@
+//! Staged upgrades of contract code
+//!
+//! Owner, normally governance contract, stages hash of new code, which is subject to timelock
+//! and admins' approvals like other owner actions. Then owner deploys code blob with matching hash,
+//! so upgrades don't require full-access keys of contract account.
+use super::Dex;
+use crate::dex::errors::{ErrorKind, Result};
+use crate::dex::{AdminAction, CodeHash, Contract, State, StateMut, Types};
+use crate::ensure_here;
+use std::borrow::{Borrow, BorrowMut};
+
+impl<T: Types, S: State<T>, SS: Borrow<S>> Dex<T, S, SS> {
+    /// Hash of contract code which owner allowed to upgrade to, if any
+    pub fn get_staged_upgrade(&self) -> Option<CodeHash> {
+        let Contract::V1(ref contract) = self.contract();
+        contract.staged_upgrade
+    }
+}
+
+impl<T: Types, S: StateMut<T>, SS: BorrowMut<S>> Dex<T, S, SS> {
+    /// Allow upgrade to contract code with specified hash, replacing previously staged one,
+    /// or cancel staged upgrade if `None` is passed. Unlike other owner actions,
+    /// can be performed while payable API is suspended, as upgrade may be the fix
+    pub fn stage_upgrade(&mut self, code_hash: Option<CodeHash>) -> Result<()> {
+        self.ensure_caller_is_owner()?;
+        self.ensure_multisig_disabled()?;
+        self.ensure_timelock_disabled()?;
+        self.apply_admin_action(AdminAction::StageUpgrade(code_hash))
+    }
+
+    /// Stage upgrade, without any permission checks
+    pub(super) fn stage_upgrade_unchecked(&mut self, code_hash: Option<CodeHash>) {
+        let Contract::V1(ref mut contract) = self.contract_mut();
+        contract.staged_upgrade = code_hash;
+        let caller_id = self.get_caller_id();
+        self.logger_mut()
+            .log_upgrade_staged_event(&caller_id, code_hash.as_ref());
+    }
+
+    /// Consume staged upgrade before deployment of code with specified hash;
+    /// deployment itself and state migration are up to chain layer.
+    /// Staging is already subject to timelock, so only multisig applies here
+    pub fn take_staged_upgrade(&mut self, code_hash: &CodeHash) -> Result<()> {
+        self.ensure_caller_is_owner_or_admin()?;
+        let Contract::V1(ref mut contract) = self.contract_mut();
+        ensure_here!(
+            contract.staged_upgrade.as_ref() == Some(code_hash),
+            ErrorKind::UpgradeNotStaged
+        );
+        contract.staged_upgrade = None;
+        let caller_id = self.get_caller_id();
+        self.logger_mut().log_upgrade_event(&caller_id, code_hash);
+        Ok(())
+    }
+}
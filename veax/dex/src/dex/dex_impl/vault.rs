@@ -0,0 +1,228 @@
+//! Vault mode: protection of account's deposits against compromised keys
+//!
+//! Account may opt into vault mode, designating a guardian account. Withdrawals of at least
+//! configured thresholds are then taken from deposits, but sent only after configured delay,
+//! and guardian may cancel them meanwhile, returning tokens to account's deposits.
+//! Vault can be removed only by guardian, so compromised account key can't just disable it.
+use super::Dex;
+use crate::chain::{AccountId, Amount, TokenId};
+use crate::dex::errors::{ErrorKind, Result};
+use crate::dex::traits::AccountExtra;
+use crate::dex::{
+    Account, Contract, DelayedWithdrawal, Map, MapRemoveKey, State, StateMembersMut, StateMut,
+    Types, Vault,
+};
+use crate::{ensure_here, error_here};
+use itertools::Itertools;
+use num_traits::Zero;
+use std::borrow::{Borrow, BorrowMut};
+
+/// Maximal number of tokens with withdrawal thresholds in single vault
+const MAX_VAULT_THRESHOLDS: usize = 8;
+/// Maximal number of pending delayed withdrawals of single vault
+const MAX_DELAYED_WITHDRAWALS: usize = 16;
+/// Maximal withdrawal delay, in seconds
+const MAX_VAULT_DELAY: u64 = 30 * 24 * 60 * 60;
+
+impl<T: Types, S: State<T>, SS: Borrow<S>> Dex<T, S, SS> {
+    /// Vault of `account_id`, if account opted into vault mode
+    pub fn get_vault(&self, account_id: &AccountId) -> Option<Vault> {
+        let Contract::V1(ref contract) = self.contract();
+        contract.vaults.inspect(account_id, Vault::clone)
+    }
+}
+
+impl<T: Types, S: StateMut<T>, SS: BorrowMut<S>> Dex<T, S, SS> {
+    /// Opt caller into vault mode; storage of vault is paid from caller's storage deposit
+    ///
+    /// # Parameters
+    /// * `guardian` - account which may cancel delayed withdrawals and remove vault
+    /// * `thresholds` - withdrawals of at least specified amounts of tokens are delayed
+    /// * `delay` - delay of large withdrawals, in seconds
+    pub fn set_vault(
+        &mut self,
+        guardian: AccountId,
+        thresholds: Vec<(TokenId, Amount)>,
+        delay: u64,
+    ) -> Result<()> {
+        self.ensure_payable_api_resumed()?;
+        let account_id = self.get_caller_id();
+        ensure_here!(guardian != account_id, ErrorKind::InvalidParams);
+        ensure_here!(
+            delay > 0 && delay <= MAX_VAULT_DELAY,
+            ErrorKind::InvalidParams
+        );
+        ensure_here!(
+            !thresholds.is_empty() && thresholds.len() <= MAX_VAULT_THRESHOLDS,
+            ErrorKind::InvalidParams
+        );
+        ensure_here!(
+            thresholds.iter().map(|(token_id, _)| token_id).all_unique(),
+            ErrorKind::InvalidParams
+        );
+        ensure_here!(
+            thresholds.iter().all(|(_, threshold)| !threshold.is_zero()),
+            ErrorKind::InvalidParams
+        );
+        ensure_here!(
+            self.get_vault(&account_id).is_none(),
+            ErrorKind::VaultAlreadySet
+        );
+        self.with_account_mut(&account_id, |account_view| {
+            account_view.account.extra.on_vault_set()
+        })?;
+        let Contract::V1(ref mut contract) = self.contract_mut();
+        contract.vaults.insert(
+            account_id,
+            Vault {
+                guardian,
+                thresholds,
+                delay,
+                delayed_withdrawals: Vec::new(),
+                next_withdrawal_id: 0,
+            },
+        );
+        Ok(())
+    }
+
+    /// Remove vault of `account_id`; pending delayed withdrawals are cancelled.
+    /// Can be called only by guardian of vault
+    pub fn remove_vault(&mut self, account_id: &AccountId) -> Result<()> {
+        self.ensure_payable_api_resumed()?;
+        let vault = self.guarded_vault(account_id)?;
+        for withdrawal in vault.delayed_withdrawals {
+            self.return_delayed_withdrawal(account_id, &withdrawal)?;
+        }
+        self.with_account_mut(account_id, |account_view| {
+            account_view.account.extra.on_vault_removed();
+            Ok(())
+        })?;
+        let Contract::V1(ref mut contract) = self.contract_mut();
+        contract.vaults.remove(account_id);
+        Ok(())
+    }
+
+    /// Cancel delayed withdrawal, returning tokens to deposits of `account_id`.
+    /// Can be called only by guardian of vault
+    pub fn cancel_delayed_withdrawal(&mut self, account_id: &AccountId, id: u64) -> Result<()> {
+        self.ensure_payable_api_resumed()?;
+        let mut vault = self.guarded_vault(account_id)?;
+        let withdrawal = Self::take_delayed_withdrawal(&mut vault, id)?;
+        self.return_delayed_withdrawal(account_id, &withdrawal)?;
+        let Contract::V1(ref mut contract) = self.contract_mut();
+        #[allow(clippy::clone_on_copy)] // not all account ids are copyable
+        contract.vaults.insert(account_id.clone(), vault);
+        Ok(())
+    }
+
+    /// Delays withdrawal of `amount` of `token_id`, already taken from deposits of `account_id`,
+    /// if account's vault requires that
+    ///
+    /// # Returns
+    /// Whether withdrawal was delayed, otherwise tokens should be sent right away
+    pub(super) fn delay_withdrawal_if_vaulted(
+        &mut self,
+        account_id: &AccountId,
+        token_id: &TokenId,
+        amount: Amount,
+    ) -> Result<bool> {
+        let Some(mut vault) = self
+            .get_vault(account_id)
+            .filter(|vault| vault.is_triggered(token_id, amount)) else {
+            return Ok(false);
+        };
+        ensure_here!(
+            vault.delayed_withdrawals.len() < MAX_DELAYED_WITHDRAWALS,
+            ErrorKind::TooManyDelayedWithdrawals
+        );
+        let execute_after = self.get_block_timestamp().saturating_add(vault.delay);
+        vault.delayed_withdrawals.push(DelayedWithdrawal {
+            id: vault.next_withdrawal_id,
+            token_id: token_id.clone(),
+            amount,
+            execute_after,
+        });
+        vault.next_withdrawal_id += 1;
+        let Contract::V1(ref mut contract) = self.contract_mut();
+        #[allow(clippy::clone_on_copy)] // not all account ids are copyable
+        contract.vaults.insert(account_id.clone(), vault);
+        Ok(true)
+    }
+
+    /// Vault of `account_id`, if caller is its guardian
+    fn guarded_vault(&self, account_id: &AccountId) -> Result<Vault> {
+        let vault = self
+            .get_vault(account_id)
+            .ok_or_else(|| error_here!(ErrorKind::VaultNotFound))?;
+        ensure_here!(
+            vault.guardian == self.get_caller_id(),
+            ErrorKind::NotVaultGuardian
+        );
+        Ok(vault)
+    }
+
+    fn take_delayed_withdrawal(vault: &mut Vault, id: u64) -> Result<DelayedWithdrawal> {
+        let index = vault
+            .delayed_withdrawals
+            .iter()
+            .position(|withdrawal| withdrawal.id == id)
+            .ok_or_else(|| error_here!(ErrorKind::DelayedWithdrawalNotFound))?;
+        Ok(vault.delayed_withdrawals.remove(index))
+    }
+
+    /// Credit tokens of cancelled withdrawal back to deposits of `account_id`
+    fn return_delayed_withdrawal(
+        &mut self,
+        account_id: &AccountId,
+        withdrawal: &DelayedWithdrawal,
+    ) -> Result<()> {
+        let StateMembersMut {
+            contract: Contract::V1(ref mut contract),
+            logger,
+            ..
+        } = self.members_mut();
+        contract
+            .accounts
+            .try_update(account_id, |Account::V1(ref mut account)| {
+                // Token may be unregistered while withdrawal is pending
+                account.register_tokens([&withdrawal.token_id]);
+                Self::deposit_impl(
+                    account_id,
+                    account,
+                    &withdrawal.token_id,
+                    withdrawal.amount,
+                    logger,
+                )
+            })?;
+        Ok(())
+    }
+}
+
+impl<T: Types, S: StateMut<T, SendTokensExtraParam = ()>, SS: BorrowMut<S>> Dex<T, S, SS> {
+    /// Send tokens of delayed withdrawal once its delay has expired. Can be called by anyone
+    pub fn execute_delayed_withdrawal(
+        &mut self,
+        account_id: &AccountId,
+        id: u64,
+    ) -> Result<S::SendTokensResult> {
+        self.ensure_payable_api_resumed()?;
+        let mut vault = self
+            .get_vault(account_id)
+            .ok_or_else(|| error_here!(ErrorKind::VaultNotFound))?;
+        let withdrawal = Self::take_delayed_withdrawal(&mut vault, id)?;
+        ensure_here!(
+            self.get_block_timestamp() >= withdrawal.execute_after,
+            ErrorKind::WithdrawalDelayNotExpired
+        );
+        let Contract::V1(ref mut contract) = self.contract_mut();
+        #[allow(clippy::clone_on_copy)] // not all account ids are copyable
+        contract.vaults.insert(account_id.clone(), vault);
+        Ok(self.send_tokens(
+            account_id,
+            &withdrawal.token_id,
+            withdrawal.amount,
+            false,
+            (),
+        ))
+    }
+}
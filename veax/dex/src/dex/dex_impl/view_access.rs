@@ -0,0 +1,92 @@
+//! Read-only access delegation for private views
+//!
+//! Account may grant other accounts, e.g. portfolio trackers of integrators, access to views
+//! of its data. Grants are only recorded here; views which are restricted use `ensure_view_access`.
+use super::Dex;
+use crate::chain::AccountId;
+use crate::dex::errors::{ErrorKind, Result};
+use crate::dex::traits::AccountExtra;
+use crate::dex::{Contract, Map, MapRemoveKey, State, StateMut, Types};
+use crate::{ensure_here, error_here};
+use std::borrow::{Borrow, BorrowMut};
+
+/// Maximal number of accounts which single account may grant view access to
+const MAX_VIEW_GRANTS: usize = 16;
+
+impl<T: Types, S: State<T>, SS: Borrow<S>> Dex<T, S, SS> {
+    /// Accounts which `account_id` granted view access to
+    pub fn get_view_grants(&self, account_id: &AccountId) -> Vec<AccountId> {
+        let Contract::V1(ref contract) = self.contract();
+        contract
+            .view_grants
+            .inspect(account_id, Vec::clone)
+            .unwrap_or_default()
+    }
+
+    /// Whether `viewer_id` may view private data of `account_id`; account may always view its own
+    pub fn has_view_access(&self, account_id: &AccountId, viewer_id: &AccountId) -> bool {
+        let Contract::V1(ref contract) = self.contract();
+        account_id == viewer_id
+            || contract
+                .view_grants
+                .inspect(account_id, |viewers| viewers.contains(viewer_id))
+                .unwrap_or(false)
+    }
+
+    /// Fails with `ViewAccessNotGranted` if `viewer_id` may not view private data of `account_id`
+    pub fn ensure_view_access(&self, account_id: &AccountId, viewer_id: &AccountId) -> Result<()> {
+        ensure_here!(
+            self.has_view_access(account_id, viewer_id),
+            ErrorKind::ViewAccessNotGranted
+        );
+        Ok(())
+    }
+}
+
+impl<T: Types, S: StateMut<T>, SS: BorrowMut<S>> Dex<T, S, SS> {
+    /// Grant view access to caller's private data; storage of grant is paid
+    /// from caller's storage deposit. Granting access twice has no effect
+    pub fn grant_view_access(&mut self, viewer_id: AccountId) -> Result<()> {
+        self.ensure_payable_api_resumed()?;
+        let account_id = self.get_caller_id();
+        ensure_here!(viewer_id != account_id, ErrorKind::InvalidParams);
+        let mut viewers = self.get_view_grants(&account_id);
+        if viewers.contains(&viewer_id) {
+            return Ok(());
+        }
+        ensure_here!(
+            viewers.len() < MAX_VIEW_GRANTS,
+            ErrorKind::TooManyViewGrants
+        );
+        self.with_account_mut(&account_id, |account_view| {
+            account_view.account.extra.on_view_access_granted()
+        })?;
+        viewers.push(viewer_id);
+        let Contract::V1(ref mut contract) = self.contract_mut();
+        contract.view_grants.insert(account_id, viewers);
+        Ok(())
+    }
+
+    /// Revoke view access to caller's private data, releasing storage of grant
+    pub fn revoke_view_access(&mut self, viewer_id: &AccountId) -> Result<()> {
+        self.ensure_payable_api_resumed()?;
+        let account_id = self.get_caller_id();
+        let mut viewers = self.get_view_grants(&account_id);
+        let index = viewers
+            .iter()
+            .position(|viewer| viewer == viewer_id)
+            .ok_or_else(|| error_here!(ErrorKind::ViewAccessNotGranted))?;
+        viewers.remove(index);
+        self.with_account_mut(&account_id, |account_view| {
+            account_view.account.extra.on_view_access_revoked();
+            Ok(())
+        })?;
+        let Contract::V1(ref mut contract) = self.contract_mut();
+        if viewers.is_empty() {
+            contract.view_grants.remove(&account_id);
+        } else {
+            contract.view_grants.insert(account_id, viewers);
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,141 @@
+//! Withdraw rate limiting: caps amount of each token which may leave contract within time window,
+//! through both owner and user withdrawals, in case owner key gets compromised
+use super::Dex;
+use crate::chain::{Amount, TokenId};
+use crate::dex::errors::Result;
+use crate::dex::{
+    AdminAction, ConfigChange, Contract, Map, MapRemoveKey, State, StateMut, Types, WithdrawLimit,
+    WithdrawLimitConfig,
+};
+use crate::error_here;
+use std::borrow::{Borrow, BorrowMut};
+
+impl<T: Types, S: State<T>, SS: Borrow<S>> Dex<T, S, SS> {
+    /// Withdraw rate limits with their current counters, per token
+    pub fn get_withdraw_limits(&self) -> Vec<(TokenId, WithdrawLimit)> {
+        let Contract::V1(ref contract) = self.contract();
+        contract
+            .withdraw_limits
+            .iter()
+            .map(|(token_id, limit)| (TokenId::clone(&token_id), WithdrawLimit::clone(&limit)))
+            .collect()
+    }
+
+    /// Whether withdraw rate limits were disabled by guard
+    pub fn is_withdraw_limits_disabled(&self) -> bool {
+        let Contract::V1(ref contract) = self.contract();
+        contract.withdraw_limits_disabled
+    }
+}
+
+impl<T: Types, S: StateMut<T>, SS: BorrowMut<S>> Dex<T, S, SS> {
+    /// Set withdraw rate limit for token, or remove it if `None` is passed.
+    /// Counter of already withdrawn amount is preserved, unless window length changes.
+    /// Owner can tighten limit directly even while timelock is enabled; loosening
+    /// or removing it is subject to timelock like other owner actions
+    pub fn set_withdraw_limit(
+        &mut self,
+        token_id: &TokenId,
+        config: Option<WithdrawLimitConfig>,
+    ) -> Result<()> {
+        self.ensure_payable_api_resumed()?;
+        let Contract::V1(ref contract) = self.contract();
+        let previous = contract
+            .withdraw_limits
+            .inspect(token_id, |limit| limit.config.clone());
+        let tightened = match (&config, &previous) {
+            (Some(config), Some(previous)) => config.is_tighter_than(previous),
+            (Some(_), None) => true,
+            (None, _) => false,
+        };
+        self.ensure_caller_is_owner()?;
+        self.ensure_multisig_disabled()?;
+        if !tightened {
+            self.ensure_timelock_disabled()?;
+        }
+        self.apply_admin_action(AdminAction::SetWithdrawLimit {
+            token: token_id.clone(),
+            config,
+        })
+    }
+
+    /// Set or remove withdraw rate limit, without any permission checks
+    pub(super) fn set_withdraw_limit_unchecked(
+        &mut self,
+        token_id: &TokenId,
+        config: Option<WithdrawLimitConfig>,
+    ) {
+        let Contract::V1(ref mut contract) = self.contract_mut();
+        let previous = contract
+            .withdraw_limits
+            .inspect(token_id, WithdrawLimit::clone);
+        if let Some(ref config) = config {
+            let limit = match previous {
+                Some(ref limit) if limit.config.window_sec == config.window_sec => WithdrawLimit {
+                    config: config.clone(),
+                    ..limit.clone()
+                },
+                _ => WithdrawLimit::new(config.clone()),
+            };
+            contract.withdraw_limits.insert(token_id.clone(), limit);
+        } else {
+            contract.withdraw_limits.remove(token_id);
+        }
+        let caller_id = self.get_caller_id();
+        self.logger_mut().log_config_change_event(
+            &caller_id,
+            &ConfigChange::WithdrawLimit {
+                token: token_id.clone(),
+                previous: previous.map(|limit| limit.config),
+                new: config,
+            },
+        );
+    }
+
+    /// Guard override, which stops or resumes enforcement of withdraw rate limits
+    pub fn set_withdraw_limits_disabled(&mut self, disabled: bool) -> Result<()> {
+        self.ensure_caller_is_guard()?;
+        let Contract::V1(ref mut contract) = self.contract_mut();
+        let previous = std::mem::replace(&mut contract.withdraw_limits_disabled, disabled);
+        let caller_id = self.get_caller_id();
+        self.logger_mut().log_config_change_event(
+            &caller_id,
+            &ConfigChange::WithdrawLimitsDisabled {
+                previous,
+                new: disabled,
+            },
+        );
+        Ok(())
+    }
+
+    /// Count withdrawals against configured rate limits. Every withdrawal is counted,
+    /// so splitting large withdrawal into small ones doesn't bypass the limit
+    ///
+    /// # Parameters
+    /// * `withdrawals` - tokens and amounts being withdrawn
+    /// * `by_owner` - whether withdrawals are performed by owner, which must fit into limit
+    ///     regardless of amount
+    pub(super) fn consume_withdraw_limits<'a>(
+        &mut self,
+        withdrawals: impl IntoIterator<Item = (&'a TokenId, Amount)>,
+        by_owner: bool,
+    ) -> Result<()> {
+        let now = self.get_block_timestamp();
+        let Contract::V1(ref mut contract) = self.contract_mut();
+        if contract.withdraw_limits_disabled {
+            return Ok(());
+        }
+        for (token_id, amount) in withdrawals {
+            contract
+                .withdraw_limits
+                .update(token_id, |limit| {
+                    let strict = by_owner || amount >= limit.config.large_withdraw_threshold.0;
+                    limit
+                        .consume(amount, now, strict)
+                        .map_err(|e| error_here!(e))
+                })
+                .transpose()?;
+        }
+        Ok(())
+    }
+}
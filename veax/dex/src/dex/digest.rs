@@ -0,0 +1,65 @@
+//! Digest of critical contract state
+//!
+//! Digest is computed on demand, as a two-level hash tree: each state entry (pool, account)
+//! is hashed separately, then entry hashes are combined in the order of their serialized keys.
+//! This makes digest independent of collections' iteration order, so contract deployed
+//! on-chain and simulation environment produce the same digest for the same state.
+use near_sdk::borsh::BorshSerialize;
+use sha2::{Digest as _, Sha256};
+
+/// SHA-256 hash
+pub type Hash = [u8; 32];
+
+/// Hashes over critical contract state
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct StateDigest {
+    /// Hash over all sections below
+    pub root: Hash,
+    /// Hash over pools' reserves and positions
+    pub pools: Hash,
+    /// Hash over accounts' token balances and owned positions
+    pub accounts: Hash,
+}
+
+impl StateDigest {
+    pub(crate) fn new(pools: Hash, accounts: Hash) -> Self {
+        let mut hasher = Sha256::new();
+        hasher.update(pools);
+        hasher.update(accounts);
+        Self {
+            root: hasher.finalize().into(),
+            pools,
+            accounts,
+        }
+    }
+}
+
+/// Collects hashes of separate entries and combines them into single hash,
+/// ordered by entries' serialized keys
+#[derive(Default)]
+pub(crate) struct DigestBuilder {
+    leaves: Vec<(Vec<u8>, Hash)>,
+}
+
+impl DigestBuilder {
+    /// Adds entry with specified key; entry's hash covers both key and value
+    pub fn add(&mut self, key: &impl BorshSerialize, value: &impl BorshSerialize) {
+        let key = key.try_to_vec().unwrap();
+        let mut hasher = Sha256::new();
+        hasher.update(&key);
+        hasher.update(value.try_to_vec().unwrap());
+        self.leaves.push((key, hasher.finalize().into()));
+    }
+
+    /// Combines hashes of all added entries
+    pub fn finish(mut self) -> Hash {
+        self.leaves
+            .sort_unstable_by(|(lhs, _), (rhs, _)| lhs.cmp(rhs));
+        let mut hasher = Sha256::new();
+        hasher.update((self.leaves.len() as u64).to_le_bytes());
+        for (_key, leaf) in &self.leaves {
+            hasher.update(leaf);
+        }
+        hasher.finalize().into()
+    }
+}
@@ -17,6 +17,18 @@ static_assertions::const_assert!(ErrorKindDiscriminants::COUNT <= (CODE_MASK as
 // so `SOURCE_FILES_COUNT` should be strictly less than `FILE_MASK`, which is maximum
 // possible file index
 static_assertions::const_assert!(SOURCE_FILES_COUNT < (FILE_MASK as usize));
+
+/// Number of crate's source files, which are listed in error locations
+/// and covered by source files digest
+pub fn source_files_count() -> usize {
+    SOURCE_FILES_COUNT
+}
+
+/// Hex-encoded SHA-256 digest of crate's source files, see `gen_source_files_list_ignoring`
+pub fn source_files_digest() -> &'static str {
+    SOURCE_FILES_DIGEST
+}
+
 /// Creates error object with location info filled from macro invocation location
 ///
 /// # Arguments
@@ -103,6 +115,14 @@ impl Error {
     pub fn error_code(&self) -> ErrorCode {
         ErrorDesc::from(self).into()
     }
+
+    /// Name of error kind, without any details; stable across releases
+    pub fn kind_name(&self) -> &'static str {
+        match self.kind {
+            ErrorKind::Custom(ref kind) => chain::ErrorDiscriminants::from(kind).into(),
+            ref other => ErrorKindDiscriminants::from(other).into(),
+        }
+    }
 }
 
 impl std::fmt::Display for Error {
@@ -320,6 +340,8 @@ pub enum ErrorKind {
     SwapAmountTooSmall,
     #[error("Swap amount too large")]
     SwapAmountTooLarge,
+    #[error("No route between tokens")]
+    RouteNotFound,
     #[error("Invalid params")]
     InvalidParams,
     // pool manage
@@ -349,6 +371,12 @@ pub enum ErrorKind {
     UserHasPositions,
     #[error("Not your position")]
     NotYourPosition,
+    #[error("Position is locked")]
+    PositionLocked,
+    #[error("Position is not locked")]
+    PositionNotLocked,
+    #[error("Position can be unlocked only by account which locked it")]
+    NotPositionLocker,
     // Math errors
     #[error("Numeric conversion error: overflow - source number cannot fit into destination")]
     ConvOverflow,
@@ -370,12 +398,172 @@ pub enum ErrorKind {
     InternalTickNotDeleted,
     #[error("Evaluated deposited amount is larger than specified max limit.")]
     InternalDepositMoreThanMax,
-    #[error("Logic error: number of picked pools doesnt' match number of top pools")]
-    InternalTopPoolsNumberMismatch,
     #[error("Internal logic error")]
     InternalLogicError,
+    #[error("Pool invariant violated")]
+    InvariantViolation,
     #[error("Tick value is either too large or too small")]
     PriceTickOutOfBounds,
+    // Governance timelock
+    #[error("Owner action is not scheduled")]
+    AdminActionNotScheduled,
+    #[error("Owner action timelock has not expired yet")]
+    TimelockNotExpired,
+    #[error("Timelock is enabled, owner action must be scheduled")]
+    TimelockEnabled,
+    // Multisig owner
+    #[error("Owner action is not proposed")]
+    AdminActionNotProposed,
+    #[error("Multisig is enabled, owner action must be approved by admins")]
+    MultisigEnabled,
+    #[error("Owner action is already approved by this admin")]
+    AlreadyApproved,
+    // Withdraw rate limiting
+    #[error("Withdraw rate limit is not set for token")]
+    WithdrawLimitNotSet,
+    #[error("Withdraw rate limit exceeded, try again later")]
+    WithdrawRateLimitExceeded,
+    // Insurance reserve
+    #[error("Insufficient insurance reserve")]
+    InsufficientInsuranceReserve,
+    #[error("Insurance reserve can be withdrawn only while payable API is suspended")]
+    PayableAPINotSuspended,
+    // Banned tokens
+    #[error("Token is banned")]
+    TokenBanned,
+    // Position size limits
+    #[error("Deposited amount is less than minimal for the pool")]
+    DepositTooSmall,
+    // Pool kinds
+    #[error("Pool of another kind already exists for this pair")]
+    PoolKindMismatch,
+    #[error("Not enough pool shares")]
+    InsufficientShares,
+    #[error("Amplification coefficient is out of allowed range")]
+    IllegalAmplification,
+    // Conditional orders
+    #[error("Conditional order not found")]
+    OrderNotFound,
+    #[error("Conditional order has expired")]
+    OrderExpired,
+    #[error("Pool price hasn't reached order's trigger price")]
+    OrderNotTriggered,
+    #[error("Conditional order belongs to another account")]
+    NotYourOrder,
+    // Protocol fee auctions
+    #[error("Protocol fee auctions are disabled")]
+    FeeAuctionsDisabled,
+    #[error("Protocol fee auction not found")]
+    FeeAuctionNotFound,
+    // LP share tokens
+    #[error("LP token not found")]
+    LpTokenNotFound,
+    // Sandwich guard
+    #[error(
+        "Swap reverses previous swap of the same account through guarded pool in the same block"
+    )]
+    SandwichSwap,
+    // Batch auctions
+    #[error("Pool is in batch clearing mode, swaps must be submitted to batch")]
+    PoolInBatchMode,
+    #[error("Pool isn't in batch clearing mode")]
+    PoolNotInBatchMode,
+    #[error("Batch of previous block isn't settled yet")]
+    BatchNotSettled,
+    #[error("Batch is empty or its block hasn't passed yet")]
+    BatchNotReady,
+    #[error("Too many swaps in batch")]
+    BatchFull,
+    // Integrations
+    #[error("Integration not found")]
+    IntegrationNotFound,
+    // Pending withdrawals
+    #[error("Withdraw of such token and amount isn't in progress for account")]
+    WithdrawNotTracked,
+    // Gas configuration
+    #[error("Gas amount is out of allowed bounds")]
+    IllegalGasAmount,
+    // Deposit intents
+    #[error("Deposit intent not found")]
+    DepositIntentNotFound,
+    #[error("Deposit intent belongs to another account")]
+    NotYourDepositIntent,
+    #[error("Token isn't expected by deposit intent, or its amount exceeds the remaining one")]
+    UnexpectedIntentDeposit,
+    // Auto-harvest
+    #[error("Too many positions with auto-harvest in pool")]
+    AutoHarvestQueueFull,
+    // View access
+    #[error("View access isn't granted to this account")]
+    ViewAccessNotGranted,
+    #[error("Too many accounts are granted view access")]
+    TooManyViewGrants,
+    // Pool aliases
+    #[error("Pool alias not found")]
+    PoolAliasNotFound,
+    // Upgrades
+    #[error("Upgrade to this code isn't staged")]
+    UpgradeNotStaged,
+    // Pool storage
+    #[error("No record of native tokens paid for pool's storage")]
+    PoolStorageDepositNotFound,
+    // Admin log
+    #[error("Admin log entry not found")]
+    AdminLogEntryNotFound,
+    // Vaults
+    #[error("Account has no vault")]
+    VaultNotFound,
+    #[error("Account already has vault, it can be changed only after guardian removes it")]
+    VaultAlreadySet,
+    #[error("Operation is allowed only to guardian of vault")]
+    NotVaultGuardian,
+    #[error("Operation isn't allowed while account has vault")]
+    VaultEnabled,
+    #[error("Delayed withdrawal not found")]
+    DelayedWithdrawalNotFound,
+    #[error("Delay of withdrawal has not expired yet")]
+    WithdrawalDelayNotExpired,
+    #[error("Too many delayed withdrawals")]
+    TooManyDelayedWithdrawals,
+    // Session keys
+    #[error("Session key not found")]
+    SessionKeyNotFound,
+    #[error("Too many session keys")]
+    TooManySessionKeys,
+    #[error("Operation isn't allowed to session key")]
+    SessionKeyNotAllowed,
+    #[error("Daily volume cap of session key exceeded")]
+    SessionVolumeCapExceeded,
+    // Re-quote protection
+    #[error("Spot price deviates from reference price beyond allowed bound")]
+    PriceDeviationExceeded,
+    // Pool price initialization
+    #[error("Pool already has price set")]
+    PoolAlreadyInitialized,
+    // Seed period
+    #[error("Swaps in pool are disabled during its seed period")]
+    PoolInSeedPeriod,
+    #[error("Position is too far from pool price during its seed period")]
+    PositionOutsideSeedRange,
+    // Vesting locks
+    #[error("Position is locked until its unlock time")]
+    PositionTimeLocked,
+    // Fee-on-transfer tokens
+    #[error("Token isn't in reconcile on deposit mode")]
+    TokenNotReconciled,
+    #[error("Contract balance of token isn't fetched yet")]
+    ReconciledBalanceUnknown,
+    #[error("Another deposit of token waits for contract balance of token")]
+    ReconciledDepositPending,
+    #[error("Contract balance delta doesn't match deposit of token")]
+    ReconciledBalanceMismatch,
+    #[error("Transfers of token sent by contract aren't resolved yet")]
+    ReconciledOutflowPending,
+    // Token interface check
+    #[error("Token contract isn't checked to implement fungible token interface")]
+    TokenInterfaceNotChecked,
+    #[error("Token contract doesn't implement fungible token interface")]
+    TokenInterfaceCheckFailed,
 }
 
 // Custom debug implementation to not use `derive`, because it blows up binary size
@@ -1,4 +1,4 @@
-pub use dex_impl::Dex;
+pub use dex_impl::{Dex, MAX_TIMELOCK_DELAY};
 pub use errors::*;
 pub use primitives::*;
 pub use state_types::*;
@@ -10,6 +10,9 @@ pub use traits::{
 pub use util_types::*;
 pub use utils::PairExt;
 
+#[cfg(feature = "tracing")]
+pub use tracing::TraceVerbosity;
+
 mod dex_impl;
 mod errors;
 mod primitives;
@@ -22,10 +25,21 @@ pub mod state_types;
 pub mod tick;
 
 pub mod collection_helpers;
+#[cfg(feature = "near")]
+pub mod digest;
 pub mod tick_state_ex;
 pub mod v0;
 pub mod withdraw_trackers;
 
+#[cfg(feature = "test-utils")]
+pub mod sim;
+
+#[cfg(feature = "reference-model")]
+pub mod reference_model;
+
+#[cfg(feature = "tracing")]
+pub mod tracing;
+
 pub use v0 as latest;
 
 pub type BasisPoints = u16;
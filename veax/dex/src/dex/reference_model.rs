@@ -0,0 +1,213 @@
+//! Reference model of pool math in exact rational arithmetic
+//!
+//! Models single position which covers whole price range on single fee level,
+//! i.e. classic constant-product pool where LP fee is accumulated outside of the curve.
+//! Used to check divergence of production fixed-point and `Float` math from exact values.
+use super::dex_impl::fee_rate_ticks;
+use super::sim::SimState;
+use super::{FeeLevel, PositionInit, Side};
+use crate::chain::{AccountId, Amount, TokenId};
+use num_bigint::BigInt;
+use num_rational::BigRational;
+use num_traits::{Signed, Zero};
+
+/// Pool with exact rational reserves
+#[derive(Clone, Debug)]
+pub struct ReferencePool {
+    reserves: (BigRational, BigRational),
+    /// `1 - fee_rate`; equals to `(10000/10001)^fee_rate_ticks`, same as spot price of tick
+    fee_factor: BigRational,
+}
+
+fn rational(amount: Amount) -> BigRational {
+    BigRational::from_integer(BigInt::from(amount))
+}
+
+impl ReferencePool {
+    pub fn new(reserves: (Amount, Amount), fee_level: FeeLevel) -> Self {
+        let tick_ratio = BigRational::new(BigInt::from(10_000), BigInt::from(10_001));
+        let fee_factor = (0..fee_rate_ticks(fee_level))
+            .fold(BigRational::from_integer(BigInt::from(1)), |acc, _| {
+                acc * &tick_ratio
+            });
+        Self {
+            reserves: (rational(reserves.0), rational(reserves.1)),
+            fee_factor,
+        }
+    }
+
+    pub fn reserves(&self) -> &(BigRational, BigRational) {
+        &self.reserves
+    }
+
+    /// Price of left token in terms of right one
+    pub fn spot_price(&self) -> BigRational {
+        &self.reserves.1 / &self.reserves.0
+    }
+
+    /// Swap exact amount of token on `side`, returns exact amount of opposite token paid out
+    pub fn swap_exact_in(&mut self, side: Side, amount_in: Amount) -> BigRational {
+        let amount_in = rational(amount_in) * &self.fee_factor;
+        let reserve_in = &self.reserves[side];
+        let reserve_out = &self.reserves[side.opposite()];
+        let amount_out = reserve_out * &amount_in / (reserve_in + &amount_in);
+
+        self.reserves[side] += amount_in;
+        self.reserves[side.opposite()] -= &amount_out;
+        amount_out
+    }
+}
+
+/// Precision of production values which are of magnitude of pool reserves, e.g. liquidity;
+/// `Float` keeps 52 bits of mantissa, and few of them are lost over operations of a swap
+const RESERVE_PRECISION_BITS: u32 = 48;
+
+/// Allowed divergence of production results from reference ones
+#[derive(Clone, Debug)]
+pub struct Tolerance {
+    /// Relative to exact value
+    pub relative: BigRational,
+    /// Relative to reserve of output token before swap; covers rounding of values
+    /// of magnitude of reserves, which doesn't shrink with swapped amount
+    pub reserve_relative: BigRational,
+    /// In token units, covers rounding of exact value to integer amount
+    pub absolute: Amount,
+}
+
+impl Tolerance {
+    /// Relative tolerance of `2^-bits`, plus tolerance of `2^-RESERVE_PRECISION_BITS`
+    /// relative to reserve, plus one token unit to cover rounding
+    pub fn with_precision_bits(bits: u32) -> Self {
+        let power_of_two = |bits| BigRational::new(BigInt::from(1), BigInt::from(1) << bits);
+        Self {
+            relative: power_of_two(bits),
+            reserve_relative: power_of_two(RESERVE_PRECISION_BITS),
+            absolute: 1,
+        }
+    }
+}
+
+impl Default for Tolerance {
+    fn default() -> Self {
+        Self::with_precision_bits(40)
+    }
+}
+
+/// Describes first swap which diverged from reference model beyond tolerance
+#[derive(Debug)]
+pub enum Divergence {
+    /// Production code failed where reference model succeeded
+    Failed { swap: usize, error: super::Error },
+    /// Production and reference results differ too much
+    AmountOut {
+        swap: usize,
+        expected: BigRational,
+        actual: Amount,
+    },
+}
+
+/// Runs same sequence of swaps over production pool in `SimState` and over `ReferencePool`,
+/// then checks that amounts paid out don't diverge beyond `tolerance`
+///
+/// # Parameters
+/// * `amounts` - amounts of tokens deposited into full range position, which initializes pool
+/// * `fee_level` - fee level of the position
+/// * `swaps` - sequence of swaps, as side of input token and exact input amount
+/// * `tolerance` - allowed divergence
+pub fn compare_swaps(
+    amounts: (Amount, Amount),
+    fee_level: FeeLevel,
+    swaps: &[(Side, Amount)],
+    tolerance: &Tolerance,
+) -> Result<(), Divergence> {
+    let account = |name: &str| -> AccountId {
+        // Names are hardcoded and valid
+        let Ok(account_id) = name.parse() else { unreachable!() };
+        account_id
+    };
+    let (owner, provider, trader) = (account("owner"), account("provider"), account("trader"));
+    let tokens: (TokenId, TokenId) = (account("token-a"), account("token-b"));
+
+    let setup_failed = |error| Divergence::Failed { swap: 0, error };
+    let mut state = SimState::new(owner, 0).map_err(setup_failed)?;
+
+    let total_in = swaps
+        .iter()
+        .fold((0, 0), |mut total: (Amount, Amount), (side, amount)| {
+            total[*side] = total[*side].saturating_add(*amount);
+            total
+        });
+    for (account_id, deposit) in [(&provider, amounts), (&trader, total_in)] {
+        for (token_id, amount) in [(&tokens.0, deposit.0), (&tokens.1, deposit.1)] {
+            state
+                .deposit(account_id, token_id, amount)
+                .map_err(setup_failed)?;
+        }
+    }
+
+    let (_, deposited_a, deposited_b, _) = state
+        .call(&provider, |dex| {
+            dex.open_position(
+                &tokens.0,
+                &tokens.1,
+                fee_rate_ticks(fee_level),
+                PositionInit::new_full_range(Amount::zero(), amounts.0, Amount::zero(), amounts.1),
+            )
+        })
+        .map_err(setup_failed)?;
+    let mut reference = ReferencePool::new((deposited_a, deposited_b), fee_level);
+
+    for (swap, (side, amount_in)) in swaps.iter().copied().enumerate() {
+        let path = match side {
+            Side::Left => [tokens.0.clone(), tokens.1.clone()],
+            Side::Right => [tokens.1.clone(), tokens.0.clone()],
+        };
+        let (_, actual) = state
+            .call(&trader, |dex| dex.swap_exact_in(&path, amount_in, 0, None))
+            .map_err(|error| Divergence::Failed { swap, error })?;
+        let reserve_out = reference.reserves()[side.opposite()].clone();
+        let expected = reference.swap_exact_in(side, amount_in);
+
+        let allowed = &expected * &tolerance.relative
+            + reserve_out * &tolerance.reserve_relative
+            + rational(tolerance.absolute);
+        if (rational(actual) - &expected).abs() > allowed {
+            return Err(Divergence::AmountOut {
+                swap,
+                expected,
+                actual,
+            });
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{compare_swaps, Tolerance};
+    use crate::dex::latest::NUM_FEE_LEVELS;
+    use crate::dex::Side;
+    use proptest::prelude::*;
+
+    fn side() -> impl Strategy<Value = Side> {
+        prop_oneof![Just(Side::Left), Just(Side::Right)]
+    }
+
+    proptest! {
+        #![proptest_config(ProptestConfig::with_cases(32))]
+
+        /// Amounts paid out by production swaps stay within default tolerance of exact ones
+        #[test]
+        fn swaps_match_reference_model(
+            amounts in (
+                1_000_000_000_000u128..1_000_000_000_000_000_000,
+                1_000_000_000_000u128..1_000_000_000_000_000_000,
+            ),
+            fee_level in 0..NUM_FEE_LEVELS,
+            swaps in prop::collection::vec((side(), 1u128..1_000_000_000_000), 1..16),
+        ) {
+            let result = compare_swaps(amounts, fee_level, &swaps, &Tolerance::default());
+            prop_assert!(result.is_ok(), "{:?}", result.err());
+        }
+    }
+}
@@ -0,0 +1,204 @@
+//! In-memory collections which implement `dex::Map`, `dex::OrderedMap` and `dex::Set`
+//!
+//! Collections are ordered, so iteration order doesn't depend on hashing seed
+//! and simulation runs are reproducible.
+use crate::dex::{KeyAt, Map, MapRemoveKey, OrderedMap, Result, Set};
+use near_sdk::borsh::{BorshDeserialize, BorshSerialize};
+use std::collections::{btree_map, btree_set, BTreeMap, BTreeSet};
+use std::hash::Hash;
+use std::io::{self, Write};
+use std::ops::Bound;
+
+/// Ordered in-memory map
+pub struct SimMap<K: Ord + Hash, V>(BTreeMap<K, V>);
+
+// Derives don't bound generic parameters, so serialization is implemented manually
+impl<K: Ord + Hash + BorshSerialize, V: BorshSerialize> BorshSerialize for SimMap<K, V> {
+    fn serialize<W: Write>(&self, writer: &mut W) -> io::Result<()> {
+        self.0.serialize(writer)
+    }
+}
+
+impl<K: Ord + Hash + BorshDeserialize, V: BorshDeserialize> BorshDeserialize for SimMap<K, V> {
+    fn deserialize(buf: &mut &[u8]) -> io::Result<Self> {
+        BTreeMap::deserialize(buf).map(Self)
+    }
+}
+
+impl<K: Ord + Hash, V> Default for SimMap<K, V> {
+    fn default() -> Self {
+        Self(BTreeMap::new())
+    }
+}
+
+impl<K: Ord + Hash + Clone, V: BorshSerialize + BorshDeserialize> SimMap<K, V> {
+    /// Produces detached copy of stored value, mimicking persistent collections
+    /// which always deserialize values from storage
+    fn load(&self, key: &K) -> Option<V> {
+        self.0.get(key).map(|value| {
+            // Serialization into memory buffer and deserialization of just serialized value
+            // never fail
+            let Ok(bytes) = value.try_to_vec() else { unreachable!() };
+            let Ok(value) = V::try_from_slice(&bytes) else { unreachable!() };
+            value
+        })
+    }
+
+    fn find_key_at(&self, at: KeyAt<&K>) -> Option<K> {
+        match at {
+            KeyAt::Min => self.0.keys().next(),
+            KeyAt::Max => self.0.keys().next_back(),
+            KeyAt::Above(key) => self
+                .0
+                .range((Bound::Excluded(key), Bound::Unbounded))
+                .map(|(key, _)| key)
+                .next(),
+            KeyAt::Below(key) => self
+                .0
+                .range((Bound::Unbounded, Bound::Excluded(key)))
+                .map(|(key, _)| key)
+                .next_back(),
+        }
+        .cloned()
+    }
+}
+
+impl<K: Ord + Hash + Clone, V: BorshSerialize + BorshDeserialize> Map for SimMap<K, V> {
+    type Key = K;
+    type Value = V;
+    type KeyRef<'a> = &'a K where Self: 'a;
+    type ValueRef<'a> = &'a V where Self: 'a;
+    type Iter<'a> = btree_map::Iter<'a, K, V> where Self: 'a;
+
+    fn iter(&self) -> Self::Iter<'_> {
+        self.0.iter()
+    }
+
+    fn clear(&mut self) {
+        self.0.clear();
+    }
+
+    fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    fn len(&self) -> usize {
+        self.0.len()
+    }
+
+    fn contains_key(&self, key: &K) -> bool {
+        self.0.contains_key(key)
+    }
+
+    fn inspect<R, F: FnOnce(&V) -> R>(&self, key: &K, inspect_fn: F) -> Option<R> {
+        self.0.get(key).map(inspect_fn)
+    }
+
+    fn update<R, F: FnOnce(&mut V) -> Result<R>>(
+        &mut self,
+        key: &K,
+        update_fn: F,
+    ) -> Option<Result<R>> {
+        self.load(key).map(|mut value| {
+            update_fn(&mut value).map(|result| {
+                self.0.insert(key.clone(), value);
+                result
+            })
+        })
+    }
+
+    fn update_or_insert<R, F, U>(&mut self, key: &K, factory_fn: F, update_fn: U) -> Result<R>
+    where
+        F: FnOnce() -> Result<V>,
+        U: FnOnce(&mut V, /* exists */ bool) -> Result<R>,
+    {
+        let (value, exists) = self
+            .load(key)
+            .map_or_else(|| (factory_fn(), false), |value| (Ok(value), true));
+        let mut value = value?;
+        let result = update_fn(&mut value, exists)?;
+        self.0.insert(key.clone(), value);
+        Ok(result)
+    }
+
+    fn insert(&mut self, key: K, value: V) {
+        self.0.insert(key, value);
+    }
+}
+
+impl<K: Ord + Hash + Clone, V: BorshSerialize + BorshDeserialize> MapRemoveKey for SimMap<K, V> {
+    fn remove(&mut self, key: &K) {
+        self.0.remove(key);
+    }
+}
+
+impl<K: Ord + Hash + Clone, V: BorshSerialize + BorshDeserialize> OrderedMap for SimMap<K, V> {
+    fn inspect_at<R, F: FnOnce(&K, &V) -> R>(&self, at: KeyAt<&K>, inspect_fn: F) -> Option<R> {
+        self.find_key_at(at)
+            .and_then(|key| self.inspect(&key, |value| inspect_fn(&key, value)))
+    }
+
+    fn update_at<R, F: FnOnce(&K, &mut V) -> Result<R>>(
+        &mut self,
+        at: KeyAt<&K>,
+        update_fn: F,
+    ) -> Option<Result<R>> {
+        self.find_key_at(at)
+            .and_then(|key| self.update(&key, |value| update_fn(&key, value)))
+    }
+}
+
+/// Ordered in-memory set
+pub struct SimSet<I: Ord + Hash>(BTreeSet<I>);
+
+impl<I: Ord + Hash + BorshSerialize> BorshSerialize for SimSet<I> {
+    fn serialize<W: Write>(&self, writer: &mut W) -> io::Result<()> {
+        self.0.serialize(writer)
+    }
+}
+
+impl<I: Ord + Hash + BorshDeserialize> BorshDeserialize for SimSet<I> {
+    fn deserialize(buf: &mut &[u8]) -> io::Result<Self> {
+        BTreeSet::deserialize(buf).map(Self)
+    }
+}
+
+impl<I: Ord + Hash> Default for SimSet<I> {
+    fn default() -> Self {
+        Self(BTreeSet::new())
+    }
+}
+
+impl<I: Ord + Hash> Set for SimSet<I> {
+    type Item = I;
+    type Ref<'a> = &'a I where Self: 'a;
+    type Iter<'a> = btree_set::Iter<'a, I> where Self: 'a;
+
+    fn iter(&self) -> Self::Iter<'_> {
+        self.0.iter()
+    }
+
+    fn clear(&mut self) {
+        self.0.clear();
+    }
+
+    fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    fn len(&self) -> usize {
+        self.0.len()
+    }
+
+    fn contains_item(&self, item: &I) -> bool {
+        self.0.contains(item)
+    }
+
+    fn add_item(&mut self, item: I) {
+        self.0.insert(item);
+    }
+
+    fn remove_item(&mut self, item: &I) {
+        self.0.remove(item);
+    }
+}
@@ -0,0 +1,124 @@
+//! Invariant checkers for simulated contract state
+use super::SimState;
+use crate::chain::{Amount, AmountUFP, TokenId};
+use crate::dex::latest::RawFeeLevelsArray;
+use crate::dex::{Account, Contract, FeeLevel, Map, Pool, PoolId, Side, State};
+use std::collections::BTreeMap;
+
+/// Describes broken invariant
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum InvariantViolation {
+    /// Tokens held by accounts and pools don't sum up to amount transferred into contract
+    ReserveMismatch {
+        token_id: TokenId,
+        expected: Amount,
+        actual: Amount,
+    },
+    /// Pool's cumulative fee counter decreased
+    FeeDecreased {
+        pool_id: PoolId,
+        counter: &'static str,
+        side: Side,
+        fee_level: Option<FeeLevel>,
+    },
+}
+
+/// Cumulative fee counters of single pool, which must never decrease
+#[derive(Clone)]
+pub struct FeeSnapshot {
+    lp_fees: (AmountUFP, AmountUFP),
+    protocol_fees: (AmountUFP, AmountUFP),
+    acc_lp_fees_per_level: RawFeeLevelsArray<(AmountUFP, AmountUFP)>,
+}
+
+/// Checks that for every token, sum of account deposits and pool reserves
+/// equals total amount transferred into contract minus total amount transferred out
+pub fn check_reserve_conservation(state: &SimState) -> Result<(), InvariantViolation> {
+    let Contract::V1(ref contract) = state.contract();
+
+    let mut actual = BTreeMap::<TokenId, Amount>::new();
+    for (_, account) in contract.accounts.iter() {
+        let Account::V1(ref account) = account;
+        for (token_id, balance) in account.token_balances.iter() {
+            *actual.entry(token_id.clone()).or_default() += *balance;
+        }
+    }
+    for (pool_id, pool) in contract.pools.iter() {
+        let Pool::V1(ref pool) = pool;
+        *actual.entry(pool_id.0.clone()).or_default() += pool.total_reserves.0;
+        *actual.entry(pool_id.1.clone()).or_default() += pool.total_reserves.1;
+    }
+    for (token_id, reserve) in contract.insurance_reserve.iter() {
+        *actual.entry(token_id.clone()).or_default() += *reserve;
+    }
+
+    let tokens = state.supply.keys().chain(actual.keys());
+    for token_id in tokens {
+        let expected = state.supply(token_id);
+        let actual = actual.get(token_id).copied().unwrap_or_default();
+        if expected != actual {
+            return Err(InvariantViolation::ReserveMismatch {
+                token_id: token_id.clone(),
+                expected,
+                actual,
+            });
+        }
+    }
+    Ok(())
+}
+
+/// Captures cumulative fee counters of all pools
+pub fn fee_snapshot(state: &SimState) -> BTreeMap<PoolId, FeeSnapshot> {
+    let Contract::V1(ref contract) = state.contract();
+    contract
+        .pools
+        .iter()
+        .map(|(pool_id, pool)| {
+            let Pool::V1(ref pool) = pool;
+            let snapshot = FeeSnapshot {
+                lp_fees: pool.stats.lp_fees,
+                protocol_fees: pool.stats.protocol_fees,
+                acc_lp_fees_per_level: pool.acc_lp_fees_per_level(),
+            };
+            (pool_id.clone(), snapshot)
+        })
+        .collect()
+}
+
+/// Checks that no cumulative fee counter decreased between two snapshots
+///
+/// Pools missing from `after` are skipped; pools cannot be removed,
+/// so it may happen only if snapshots were taken from different simulations
+pub fn check_fee_monotonicity(
+    before: &BTreeMap<PoolId, FeeSnapshot>,
+    after: &BTreeMap<PoolId, FeeSnapshot>,
+) -> Result<(), InvariantViolation> {
+    for (pool_id, before) in before {
+        let Some(after) = after.get(pool_id) else { continue };
+        let violation = |counter, side, fee_level| InvariantViolation::FeeDecreased {
+            pool_id: pool_id.clone(),
+            counter,
+            side,
+            fee_level,
+        };
+
+        for side in [Side::Left, Side::Right] {
+            if after.lp_fees[side] < before.lp_fees[side] {
+                return Err(violation("lp_fees", side, None));
+            }
+            if after.protocol_fees[side] < before.protocol_fees[side] {
+                return Err(violation("protocol_fees", side, None));
+            }
+            let levels = before
+                .acc_lp_fees_per_level
+                .iter()
+                .zip(after.acc_lp_fees_per_level.iter());
+            for (fee_level, (before, after)) in (0..).zip(levels) {
+                if after[side] < before[side] {
+                    return Err(violation("acc_lp_fees_per_level", side, Some(fee_level)));
+                }
+            }
+        }
+    }
+    Ok(())
+}
@@ -0,0 +1,907 @@
+//! Deterministic in-memory simulation of DEX contract
+//!
+//! Runs exactly the same contract math as on-chain `State`, but keeps all the data
+//! in ordered in-memory collections and performs token transfers synchronously.
+//! Intended for strategy developers who need to fuzz or backtest against the contract off-chain.
+//!
+//! Every call performed through `SimState::call` is transactional: if it fails,
+//! whole contract state is rolled back, the same way blockchain reverts failed transaction.
+use super::dex_impl::fee_rates_ticks;
+use super::withdraw_trackers::NoopTracker;
+use super::{
+    Account, AccountExtra, ActionHash, AdminAction, AdminLogEntry, AutoHarvestQueue, BasisPoints,
+    ClassicPool, CleanupCursor, CodeHash, ConditionalOrder, ConfigChange, Contract, DepositIntent,
+    Dex, FeeAuction, FeeLevel, Float, Integration, IntegrationId, ItemFactory, LevelState, Logger,
+    LpTokenId, PendingApproval, Pool, PoolId, PoolStorageDeposit, PoolUpdateReason, Position,
+    PositionId, ReconciledBalance, Result, ScheduledAction, SessionKey, Side, StablePool, State,
+    StateMembersMut, StateMut, SwapGuard, SwapNotification, TickState, Vault, WithdrawLimit,
+};
+use crate::chain::{AccountId, Amount, Liquidity, TokenId};
+use crate::dex::latest::RawFeeLevelsArray;
+use crate::dex::tick::Tick;
+use near_sdk::borsh::{self, BorshDeserialize, BorshSerialize};
+use std::collections::BTreeMap;
+use std::fmt::Arguments;
+
+mod collections;
+pub mod invariants;
+pub mod replay;
+pub mod scenario;
+pub mod strategies;
+
+pub use collections::{SimMap, SimSet};
+pub use invariants::{FeeSnapshot, InvariantViolation};
+pub use replay::{Replay, ReplayError, ReplayErrorKind, ReplayReport};
+pub use scenario::{Scenario, ScenarioError, Step, StepOutcome};
+
+/// Set of types which parametrize simulated contract state
+pub struct SimTypes;
+
+pub type SimContract = Contract<SimTypes>;
+
+/// Account extra data; simulation doesn't track storage usage
+#[derive(Default, BorshSerialize, BorshDeserialize)]
+pub struct SimAccountExtra;
+
+impl AccountExtra for SimAccountExtra {}
+
+impl super::Types for SimTypes {
+    type Bound = ();
+    type ContractExtra = ();
+    type AccountsMap = SimMap<AccountId, Account<Self>>;
+    type TickStatesMap = SimMap<Tick, TickState<Self>>;
+    type AccountTokenBalancesMap = SimMap<TokenId, Amount>;
+    type AccountWithdrawTracker = NoopTracker;
+    type AccountExtra = SimAccountExtra;
+    type AccountExtraV0 = SimAccountExtra;
+    type PoolsMap = SimMap<PoolId, Pool<Self>>;
+    type PoolPositionsMap = SimMap<PositionId, Position<Self>>;
+    type PoolLevelsMap = SimMap<FeeLevel, LevelState<Self>>;
+    type AccountPositionsSet = SimSet<PositionId>;
+    type VerifiedTokensSet = SimSet<TokenId>;
+    type PositionToPoolMap = SimMap<PositionId, PoolId>;
+    type PositionOwnersMap = SimMap<PositionId, AccountId>;
+    type ScheduledActionsMap = SimMap<u64, ScheduledAction>;
+    type PendingApprovalsMap = SimMap<ActionHash, PendingApproval>;
+    type WithdrawLimitsMap = SimMap<TokenId, WithdrawLimit>;
+    type SwapGuardsMap = SimMap<PoolId, SwapGuard>;
+    type InsuranceReserveMap = SimMap<TokenId, Amount>;
+    type BannedTokensSet = SimSet<TokenId>;
+    type MinDepositAmountsMap = SimMap<PoolId, (Amount, Amount)>;
+    type ClassicPoolsMap = SimMap<PoolId, ClassicPool<Self>>;
+    type StablePoolsMap = SimMap<PoolId, StablePool<Self>>;
+    type PoolSharesMap = SimMap<AccountId, Amount>;
+    type OrdersMap = SimMap<u64, ConditionalOrder>;
+    type FeeAuctionsMap = SimMap<TokenId, FeeAuction>;
+    type IntegrationsMap = SimMap<IntegrationId, Integration>;
+    type DepositIntentsMap = SimMap<u64, DepositIntent>;
+    type AutoHarvestMap = SimMap<PoolId, AutoHarvestQueue>;
+    type ViewGrantsMap = SimMap<AccountId, Vec<AccountId>>;
+    type PoolAliasesMap = SimMap<String, PoolId>;
+    type AliasesByPoolMap = SimMap<PoolId, String>;
+    type PoolStorageDepositsMap = SimMap<PoolId, PoolStorageDeposit>;
+    type AdminLogMap = SimMap<u64, AdminLogEntry>;
+    type VaultsMap = SimMap<AccountId, Vault>;
+    type SessionKeysMap = SimMap<AccountId, Vec<SessionKey>>;
+    type ReconciledTokensMap = SimMap<TokenId, ReconciledBalance>;
+    type TokenInterfacesMap = SimMap<TokenId, bool>;
+    #[cfg(feature = "lp-tokens")]
+    type LpTokensMap = SimMap<LpTokenId, super::LpToken<Self>>;
+    type AccountIdSet = SimSet<AccountId>;
+    #[cfg(feature = "smart-routing")]
+    type TokenConnectionsMap = SimMap<TokenId, Self::TokensSet>;
+    #[cfg(feature = "smart-routing")]
+    type TokensSet = SimSet<TokenId>;
+    #[cfg(feature = "smart-routing")]
+    type TokensArraySet = SimSet<TokenId>;
+    #[cfg(feature = "smart-routing")]
+    type TopPoolsMap = SimMap<TokenId, Self::TokensArraySet>;
+}
+
+/// Creates empty in-memory collections
+#[derive(Default)]
+pub struct SimItemFactory;
+
+impl ItemFactory<SimTypes> for SimItemFactory {
+    fn new_accounts_map(&mut self) -> SimMap<AccountId, Account<SimTypes>> {
+        SimMap::default()
+    }
+
+    fn new_tick_states_map(&mut self) -> SimMap<Tick, TickState<SimTypes>> {
+        SimMap::default()
+    }
+
+    fn new_account_token_balances_map(&mut self) -> SimMap<TokenId, Amount> {
+        SimMap::default()
+    }
+
+    fn new_account_withdraw_tracker(&mut self) -> NoopTracker {
+        NoopTracker
+    }
+
+    fn new_pools_map(&mut self) -> SimMap<PoolId, Pool<SimTypes>> {
+        SimMap::default()
+    }
+
+    fn new_pool_positions_map(&mut self) -> SimMap<PositionId, Position<SimTypes>> {
+        SimMap::default()
+    }
+
+    fn new_pool_levels_map(&mut self) -> SimMap<FeeLevel, LevelState<SimTypes>> {
+        SimMap::default()
+    }
+
+    fn new_account_positions_set(&mut self) -> SimSet<PositionId> {
+        SimSet::default()
+    }
+
+    fn new_verified_tokens_set(&mut self) -> SimSet<TokenId> {
+        SimSet::default()
+    }
+
+    fn new_position_to_pool_map(&mut self) -> SimMap<PositionId, PoolId> {
+        SimMap::default()
+    }
+
+    fn new_position_owners_map(&mut self) -> SimMap<PositionId, AccountId> {
+        SimMap::default()
+    }
+
+    fn new_guards(&mut self) -> SimSet<AccountId> {
+        SimSet::default()
+    }
+
+    fn new_scheduled_actions_map(&mut self) -> SimMap<u64, ScheduledAction> {
+        SimMap::default()
+    }
+
+    fn new_admins(&mut self) -> SimSet<AccountId> {
+        SimSet::default()
+    }
+
+    fn new_pending_approvals_map(&mut self) -> SimMap<ActionHash, PendingApproval> {
+        SimMap::default()
+    }
+
+    fn new_withdraw_limits_map(&mut self) -> SimMap<TokenId, WithdrawLimit> {
+        SimMap::default()
+    }
+
+    fn new_swap_guards_map(&mut self) -> SimMap<PoolId, SwapGuard> {
+        SimMap::default()
+    }
+
+    fn new_insurance_reserve_map(&mut self) -> SimMap<TokenId, Amount> {
+        SimMap::default()
+    }
+
+    fn new_banned_tokens_set(&mut self) -> SimSet<TokenId> {
+        SimSet::default()
+    }
+
+    fn new_min_deposit_amounts_map(&mut self) -> SimMap<PoolId, (Amount, Amount)> {
+        SimMap::default()
+    }
+
+    fn new_classic_pools_map(&mut self) -> SimMap<PoolId, ClassicPool<SimTypes>> {
+        SimMap::default()
+    }
+
+    fn new_stable_pools_map(&mut self) -> SimMap<PoolId, StablePool<SimTypes>> {
+        SimMap::default()
+    }
+
+    fn new_pool_shares_map(&mut self) -> SimMap<AccountId, Amount> {
+        SimMap::default()
+    }
+
+    fn new_orders_map(&mut self) -> SimMap<u64, ConditionalOrder> {
+        SimMap::default()
+    }
+
+    fn new_fee_auctions_map(&mut self) -> SimMap<TokenId, FeeAuction> {
+        SimMap::default()
+    }
+
+    fn new_integrations_map(&mut self) -> SimMap<IntegrationId, Integration> {
+        SimMap::default()
+    }
+
+    fn new_deposit_intents_map(&mut self) -> SimMap<u64, DepositIntent> {
+        SimMap::default()
+    }
+
+    fn new_auto_harvest_map(&mut self) -> SimMap<PoolId, AutoHarvestQueue> {
+        SimMap::default()
+    }
+
+    fn new_view_grants_map(&mut self) -> SimMap<AccountId, Vec<AccountId>> {
+        SimMap::default()
+    }
+
+    fn new_pool_aliases_map(&mut self) -> SimMap<String, PoolId> {
+        SimMap::default()
+    }
+
+    fn new_aliases_by_pool_map(&mut self) -> SimMap<PoolId, String> {
+        SimMap::default()
+    }
+
+    fn new_pool_storage_deposits_map(&mut self) -> SimMap<PoolId, PoolStorageDeposit> {
+        SimMap::default()
+    }
+
+    fn new_admin_log_map(&mut self) -> SimMap<u64, AdminLogEntry> {
+        SimMap::default()
+    }
+
+    fn new_vaults_map(&mut self) -> SimMap<AccountId, Vault> {
+        SimMap::default()
+    }
+
+    fn new_session_keys_map(&mut self) -> SimMap<AccountId, Vec<SessionKey>> {
+        SimMap::default()
+    }
+
+    fn new_reconciled_tokens_map(&mut self) -> SimMap<TokenId, ReconciledBalance> {
+        SimMap::default()
+    }
+
+    fn new_token_interfaces_map(&mut self) -> SimMap<TokenId, bool> {
+        SimMap::default()
+    }
+
+    #[cfg(feature = "lp-tokens")]
+    fn new_lp_tokens_map(&mut self) -> SimMap<LpTokenId, super::LpToken<SimTypes>> {
+        SimMap::default()
+    }
+
+    #[cfg(feature = "smart-routing")]
+    fn new_token_connections_map(&mut self) -> SimMap<TokenId, SimSet<TokenId>> {
+        SimMap::default()
+    }
+
+    #[cfg(feature = "smart-routing")]
+    fn new_tokens_set(&mut self) -> SimSet<TokenId> {
+        SimSet::default()
+    }
+
+    #[cfg(feature = "smart-routing")]
+    fn new_top_pools_map(&mut self) -> SimMap<TokenId, SimSet<TokenId>> {
+        SimMap::default()
+    }
+
+    #[cfg(feature = "smart-routing")]
+    fn new_tokens_array_set(&mut self) -> SimSet<TokenId> {
+        SimSet::default()
+    }
+}
+
+/// Collects all log messages and events in human-readable form
+#[derive(Default)]
+pub struct SimLogger {
+    pub records: Vec<String>,
+}
+
+impl SimLogger {
+    fn record(&mut self, args: Arguments<'_>) {
+        self.records.push(args.to_string());
+    }
+}
+
+impl Logger for SimLogger {
+    fn log(&mut self, args: Arguments<'_>) {
+        self.record(args);
+    }
+
+    fn log_deposit_event(
+        &mut self,
+        user: &AccountId,
+        token: &TokenId,
+        amount: &Amount,
+        balance: &Amount,
+    ) {
+        self.record(format_args!(
+            "deposit: user={user} token={token} amount={amount} balance={balance}"
+        ));
+    }
+
+    fn log_withdraw_event(
+        &mut self,
+        user: &AccountId,
+        token: &TokenId,
+        amount: &Amount,
+        balance: &Amount,
+    ) {
+        self.record(format_args!(
+            "withdraw: user={user} token={token} amount={amount} balance={balance}"
+        ));
+    }
+
+    fn log_open_position_event(
+        &mut self,
+        user: &AccountId,
+        pool: (&TokenId, &TokenId),
+        amounts: (&Amount, &Amount),
+        fee_rate: BasisPoints,
+        position_id: PositionId,
+        liquidity: &Liquidity,
+        fee_level: FeeLevel,
+        tick_bounds: (Tick, Tick),
+        net_liquidity: &Liquidity,
+        init_sqrtprice: Float,
+    ) {
+        self.record(format_args!(
+            "open_position: user={user} pool={pool:?} amounts={amounts:?} \
+            fee_rate={fee_rate} position_id={position_id} liquidity={liquidity:?} \
+            fee_level={fee_level} tick_bounds={tick_bounds:?} net_liquidity={net_liquidity:?} \
+            init_sqrtprice={init_sqrtprice:?}"
+        ));
+    }
+
+    fn log_harvest_fee_event(
+        &mut self,
+        user: &AccountId,
+        position_id: PositionId,
+        fee_amounts: (Amount, Amount),
+    ) {
+        self.record(format_args!(
+            "harvest_fee: user={user} position_id={position_id} fee_amounts={fee_amounts:?}"
+        ));
+    }
+
+    fn log_harvest_all_fees_event(
+        &mut self,
+        user: &AccountId,
+        harvests: &[(PositionId, (Amount, Amount))],
+    ) {
+        self.record(format_args!(
+            "harvest_all_fees: user={user} harvests={harvests:?}"
+        ));
+    }
+
+    fn log_close_position_event(
+        &mut self,
+        user: &AccountId,
+        position_id: PositionId,
+        amounts: (Amount, Amount),
+    ) {
+        self.record(format_args!(
+            "close_position: user={user} position_id={position_id} amounts={amounts:?}"
+        ));
+    }
+
+    fn log_move_position_event(
+        &mut self,
+        user: &AccountId,
+        position_id: PositionId,
+        pool: (&TokenId, &TokenId),
+        tick_bounds: (Tick, Tick),
+        withdrawn: (Amount, Amount),
+        deposited: (Amount, Amount),
+        _net_liquidity: &Liquidity,
+    ) {
+        self.record(format_args!(
+            "move_position: user={user} position_id={position_id} pool={pool:?} \
+            tick_bounds={tick_bounds:?} withdrawn={withdrawn:?} deposited={deposited:?}"
+        ));
+    }
+
+    fn log_order_placed_event(&mut self, id: u64, order: &ConditionalOrder) {
+        self.record(format_args!("order_placed: id={id} order={order:?}"));
+    }
+
+    fn log_order_executed_event(
+        &mut self,
+        id: u64,
+        keeper: &AccountId,
+        order: &ConditionalOrder,
+        amount_out: Amount,
+    ) {
+        self.record(format_args!(
+            "order_executed: id={id} keeper={keeper} user={} amount_in={} amount_out={amount_out} \
+            keeper_fee={}",
+            order.owner, order.amount_in, order.keeper_fee
+        ));
+    }
+
+    fn log_fee_auction_update_event(&mut self, token: &TokenId, auction: &FeeAuction) {
+        self.record(format_args!(
+            "fee_auction_update: token={token} auction={auction:?}"
+        ));
+    }
+
+    fn log_fee_auction_take_event(
+        &mut self,
+        user: &AccountId,
+        token: &TokenId,
+        auction: &FeeAuction,
+        amount: Amount,
+        quote_amount: Amount,
+    ) {
+        self.record(format_args!(
+            "fee_auction_take: user={user} token={token} amount={amount} \
+            quote_token={} quote_amount={quote_amount} lot={}",
+            auction.quote_token, auction.lot
+        ));
+    }
+
+    fn log_transfer_internal_event(
+        &mut self,
+        sender: &AccountId,
+        receiver: &AccountId,
+        token: &TokenId,
+        amount: Amount,
+        balances: (Amount, Amount),
+    ) {
+        self.record(format_args!(
+            "transfer_internal: sender={sender} receiver={receiver} token={token} \
+            amount={amount} balances={balances:?}"
+        ));
+    }
+
+    fn log_force_untrack_withdraw_event(
+        &mut self,
+        caller: &AccountId,
+        user: &AccountId,
+        token: &TokenId,
+        amount: Amount,
+    ) {
+        self.record(format_args!(
+            "force_untrack_withdraw: caller={caller} user={user} token={token} amount={amount}"
+        ));
+    }
+
+    fn log_upgrade_staged_event(&mut self, caller: &AccountId, code_hash: Option<&CodeHash>) {
+        self.record(format_args!(
+            "upgrade_staged: caller={caller} code_hash={code_hash:?}"
+        ));
+    }
+
+    fn log_upgrade_event(&mut self, caller: &AccountId, code_hash: &CodeHash) {
+        self.record(format_args!(
+            "upgrade: caller={caller} code_hash={code_hash:?}"
+        ));
+    }
+
+    fn log_lp_token_mint_event(
+        &mut self,
+        user: &AccountId,
+        token: &LpTokenId,
+        amounts: (Amount, Amount),
+        amount: Amount,
+    ) {
+        self.record(format_args!(
+            "lp_token_mint: user={user} token={token} amounts={amounts:?} amount={amount}"
+        ));
+    }
+
+    fn log_lp_token_burn_event(
+        &mut self,
+        user: &AccountId,
+        token: &LpTokenId,
+        amounts: (Amount, Amount),
+        amount: Amount,
+    ) {
+        self.record(format_args!(
+            "lp_token_burn: user={user} token={token} amounts={amounts:?} amount={amount}"
+        ));
+    }
+
+    fn log_lp_token_transfer_event(
+        &mut self,
+        sender: &AccountId,
+        receiver: &AccountId,
+        token: &LpTokenId,
+        amount: Amount,
+        memo: Option<&str>,
+    ) {
+        self.record(format_args!(
+            "lp_token_transfer: sender={sender} receiver={receiver} token={token} \
+            amount={amount} memo={memo:?}"
+        ));
+    }
+
+    fn log_order_cancelled_event(&mut self, id: u64, caller: &AccountId, order: &ConditionalOrder) {
+        self.record(format_args!(
+            "order_cancelled: id={id} caller={caller} user={}",
+            order.owner
+        ));
+    }
+
+    fn log_swap_event(
+        &mut self,
+        user: &AccountId,
+        tokens: (&TokenId, &TokenId),
+        amounts: (&Amount, &Amount),
+        _fees: &[(&TokenId, &BasisPoints)],
+        _integration_id: Option<IntegrationId>,
+    ) {
+        self.record(format_args!(
+            "swap: user={user} tokens={tokens:?} amounts={amounts:?}"
+        ));
+    }
+
+    fn log_update_pool_state_event(
+        &mut self,
+        reason: PoolUpdateReason,
+        pool: (&TokenId, &TokenId),
+        amounts_a: &RawFeeLevelsArray<Amount>,
+        amounts_b: &RawFeeLevelsArray<Amount>,
+        _sqrt_prices: &RawFeeLevelsArray<Float>,
+        _liquidities: &RawFeeLevelsArray<Float>,
+        _lp_fees: (&RawFeeLevelsArray<Amount>, &RawFeeLevelsArray<Amount>),
+        top_active_level: FeeLevel,
+        active_side: Side,
+        _changed_levels: Option<&RawFeeLevelsArray<bool>>,
+    ) {
+        self.record(format_args!(
+            "update_pool_state: reason={reason:?} pool={pool:?} amounts_a={amounts_a:?} \
+            amounts_b={amounts_b:?} top_active_level={top_active_level} active_side={active_side:?}"
+        ));
+    }
+
+    fn log_add_verified_tokens_event(&mut self, tokens: &[TokenId]) {
+        self.record(format_args!("add_verified_tokens: tokens={tokens:?}"));
+    }
+
+    fn log_remove_verified_tokens_event(&mut self, tokens: &[TokenId]) {
+        self.record(format_args!("remove_verified_tokens: tokens={tokens:?}"));
+    }
+
+    fn log_ban_tokens_event(&mut self, tokens: &[TokenId]) {
+        self.record(format_args!("ban_tokens: tokens={tokens:?}"));
+    }
+
+    fn log_unban_tokens_event(&mut self, tokens: &[TokenId]) {
+        self.record(format_args!("unban_tokens: tokens={tokens:?}"));
+    }
+
+    fn log_add_guard_accounts_event(&mut self, caller: &AccountId, accounts: &[AccountId]) {
+        self.record(format_args!(
+            "add_guard_accounts: caller={caller} accounts={accounts:?}"
+        ));
+    }
+
+    fn log_remove_guard_accounts_event(&mut self, caller: &AccountId, accounts: &[AccountId]) {
+        self.record(format_args!(
+            "remove_guard_accounts: caller={caller} accounts={accounts:?}"
+        ));
+    }
+
+    fn log_lock_position_event(&mut self, position_id: PositionId, locker: &AccountId) {
+        self.record(format_args!(
+            "lock_position: position_id={position_id} locker={locker}"
+        ));
+    }
+
+    fn log_unlock_position_event(&mut self, position_id: PositionId, locker: &AccountId) {
+        self.record(format_args!(
+            "unlock_position: position_id={position_id} locker={locker}"
+        ));
+    }
+
+    fn log_lock_position_until_event(
+        &mut self,
+        user: &AccountId,
+        position_id: PositionId,
+        locked_until: u64,
+    ) {
+        self.record(format_args!(
+            "lock_position_until: user={user} position_id={position_id} locked_until={locked_until}"
+        ));
+    }
+
+    fn log_suspend_payable_api_event(&mut self, account: &AccountId) {
+        self.record(format_args!("suspend_payable_api: account={account}"));
+    }
+
+    fn log_resume_payable_api_event(&mut self, account: &AccountId) {
+        self.record(format_args!("resume_payable_api: account={account}"));
+    }
+
+    fn log_action_scheduled_event(&mut self, id: u64, action: &AdminAction, execute_after: u64) {
+        self.record(format_args!(
+            "action_scheduled: id={id} action={action:?} execute_after={execute_after}"
+        ));
+    }
+
+    fn log_action_executed_event(&mut self, id: u64, action: &AdminAction) {
+        self.record(format_args!("action_executed: id={id} action={action:?}"));
+    }
+
+    fn log_action_cancelled_event(&mut self, id: u64, action: &AdminAction) {
+        self.record(format_args!("action_cancelled: id={id} action={action:?}"));
+    }
+
+    fn log_action_approved_event(
+        &mut self,
+        hash: &ActionHash,
+        action: &AdminAction,
+        approver: &AccountId,
+        approvals: u16,
+    ) {
+        self.record(format_args!(
+            "action_approved: hash={hash:?} action={action:?} approver={approver} approvals={approvals}"
+        ));
+    }
+
+    fn log_pool_storage_cleanup_event(
+        &mut self,
+        pool_id: &PoolId,
+        removed_ticks: &[(FeeLevel, Tick)],
+        next_cursor: Option<&CleanupCursor>,
+    ) {
+        self.record(format_args!(
+            "pool_storage_cleanup: pool={pool_id:?} removed_ticks={removed_ticks:?} next_cursor={next_cursor:?}"
+        ));
+    }
+
+    fn log_token_registrations_cleanup_event(&mut self, user: &AccountId, tokens: &[TokenId]) {
+        self.record(format_args!(
+            "token_registrations_cleanup: user={user} tokens={tokens:?}"
+        ));
+    }
+
+    fn log_withdraw_protocol_fee_event(
+        &mut self,
+        pool: (&TokenId, &TokenId),
+        amounts: (&Amount, &Amount),
+    ) {
+        self.record(format_args!(
+            "withdraw_protocol_fee: pool={pool:?} amounts={amounts:?}"
+        ));
+    }
+
+    fn log_owner_withdraw_event(&mut self, token: &TokenId, amount: &Amount) {
+        self.record(format_args!(
+            "owner_withdraw: token={token} amount={amount}"
+        ));
+    }
+
+    fn log_set_protocol_fee_fraction_event(
+        &mut self,
+        caller: &AccountId,
+        previous: BasisPoints,
+        new: BasisPoints,
+    ) {
+        self.record(format_args!(
+            "set_protocol_fee_fraction: caller={caller} previous={previous} new={new}"
+        ));
+    }
+
+    fn log_config_change_event(&mut self, caller: &AccountId, change: &ConfigChange) {
+        self.record(format_args!(
+            "config_change: caller={caller} change={change:?}"
+        ));
+    }
+}
+
+/// Token transfer performed by contract to some account
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct SimTransfer {
+    pub account_id: AccountId,
+    pub token_id: TokenId,
+    pub amount: Amount,
+}
+
+/// In-memory contract state with synchronous token transfers and simulated clock
+pub struct SimState {
+    contract: SimContract,
+    item_factory: SimItemFactory,
+    pub logger: SimLogger,
+    /// Account which performs current call
+    caller_id: AccountId,
+    /// Simulated time, in seconds since simulation start
+    now: u64,
+    /// Simulated block height
+    block_height: u64,
+    /// Simulated block random seed
+    random_seed: [u8; 32],
+    /// Total amounts of tokens ever transferred into contract minus amounts transferred out
+    supply: BTreeMap<TokenId, Amount>,
+    /// Token transfers performed by contract, in order
+    pub transfers: Vec<SimTransfer>,
+    /// Notifications sent to swap hook, in order
+    pub swap_notifications: Vec<(AccountId, SwapNotification)>,
+}
+
+impl State<SimTypes> for SimState {
+    fn contract(&self) -> &SimContract {
+        &self.contract
+    }
+}
+
+impl StateMut<SimTypes> for SimState {
+    type SendTokensResult = ();
+    type SendTokensExtraParam = ();
+
+    fn members_mut(&mut self) -> StateMembersMut<'_, SimTypes> {
+        StateMembersMut {
+            contract: &mut self.contract,
+            item_factory: &mut self.item_factory,
+            logger: &mut self.logger,
+        }
+    }
+
+    fn send_tokens(
+        &mut self,
+        account_id: &AccountId,
+        token_id: &TokenId,
+        amount: Amount,
+        unregister_token: bool,
+        _extra: (),
+    ) {
+        if let Some(supply) = self.supply.get_mut(token_id) {
+            *supply -= amount;
+        }
+        self.transfers.push(SimTransfer {
+            account_id: account_id.clone(),
+            token_id: token_id.clone(),
+            amount,
+        });
+        if unregister_token {
+            // Transfer always succeeds, so token can be unregistered right away.
+            // Unregistration is best-effort, its failure doesn't affect transfer
+            let account_id = account_id.clone();
+            let _ = self.as_dex_mut().unregister_tokens(&account_id, [token_id]);
+        }
+    }
+
+    fn notify_swap_hook(&mut self, hook_id: &AccountId, notification: SwapNotification) {
+        self.swap_notifications
+            .push((hook_id.clone(), notification));
+    }
+
+    fn get_initiator_id(&self) -> AccountId {
+        self.caller_id.clone()
+    }
+
+    fn get_caller_id(&self) -> AccountId {
+        self.caller_id.clone()
+    }
+
+    fn get_block_timestamp(&self) -> u64 {
+        self.now
+    }
+
+    fn get_block_height(&self) -> u64 {
+        self.block_height
+    }
+
+    fn get_random_seed(&self) -> [u8; 32] {
+        self.random_seed
+    }
+
+    fn get_signer_public_key(&self) -> Option<String> {
+        None
+    }
+}
+
+impl SimState {
+    /// Create new simulated contract, owned by `owner_id`
+    pub fn new(owner_id: AccountId, protocol_fee_fraction: BasisPoints) -> Result<Self> {
+        let mut item_factory = SimItemFactory;
+        let contract = item_factory.new_contract(
+            owner_id.clone(),
+            protocol_fee_fraction,
+            fee_rates_ticks(),
+        )?;
+        Ok(Self {
+            contract,
+            item_factory,
+            logger: SimLogger::default(),
+            caller_id: owner_id,
+            now: 0,
+            block_height: 0,
+            random_seed: [0; 32],
+            supply: BTreeMap::new(),
+            transfers: Vec::new(),
+            swap_notifications: Vec::new(),
+        })
+    }
+
+    /// Make independent copy of simulation, e.g. to run several scenarios
+    /// from the same starting point
+    pub fn snapshot(&self) -> Self {
+        // Serialization into memory buffer and deserialization of just serialized state
+        // never fail
+        let Ok(bytes) = self.contract.try_to_vec() else { unreachable!() };
+        let Ok(contract) = SimContract::try_from_slice(&bytes) else { unreachable!() };
+        Self {
+            contract,
+            item_factory: SimItemFactory,
+            logger: SimLogger {
+                records: self.logger.records.clone(),
+            },
+            caller_id: self.caller_id.clone(),
+            now: self.now,
+            block_height: self.block_height,
+            random_seed: self.random_seed,
+            supply: self.supply.clone(),
+            transfers: self.transfers.clone(),
+            swap_notifications: self.swap_notifications.clone(),
+        }
+    }
+
+    /// Current simulated time, in seconds
+    pub fn now(&self) -> u64 {
+        self.now
+    }
+
+    /// Move simulated clock forward
+    pub fn advance_time(&mut self, seconds: u64) {
+        self.now += seconds;
+    }
+
+    /// Current simulated block height
+    pub fn block_height(&self) -> u64 {
+        self.block_height
+    }
+
+    /// Move simulated block height forward
+    pub fn advance_blocks(&mut self, blocks: u64) {
+        self.block_height += blocks;
+    }
+
+    /// Set random seed reported for subsequent calls
+    pub fn set_random_seed(&mut self, random_seed: [u8; 32]) {
+        self.random_seed = random_seed;
+    }
+
+    /// Total amount of token held by contract, according to transfers in and out
+    pub fn supply(&self, token_id: &TokenId) -> Amount {
+        self.supply.get(token_id).copied().unwrap_or_default()
+    }
+
+    /// Perform call on behalf of `caller_id`
+    ///
+    /// If call fails, contract state, logs, transfers and notifications are rolled back
+    pub fn call<R>(
+        &mut self,
+        caller_id: &AccountId,
+        call_fn: impl FnOnce(&mut Dex<SimTypes, Self, &mut Self>) -> Result<R>,
+    ) -> Result<R> {
+        // Serialization into memory buffer never fails
+        let Ok(snapshot) = self.contract.try_to_vec() else { unreachable!() };
+        let supply = self.supply.clone();
+        let (records, transfers, notifications) = (
+            self.logger.records.len(),
+            self.transfers.len(),
+            self.swap_notifications.len(),
+        );
+
+        self.caller_id = caller_id.clone();
+        let result = call_fn(&mut self.as_dex_mut());
+
+        if result.is_err() {
+            // Deserialization of just serialized state never fails
+            let Ok(contract) = SimContract::try_from_slice(&snapshot) else { unreachable!() };
+            self.contract = contract;
+            self.supply = supply;
+            self.logger.records.truncate(records);
+            self.transfers.truncate(transfers);
+            self.swap_notifications.truncate(notifications);
+        }
+        result
+    }
+
+    /// Transfer tokens from outside into account's deposit,
+    /// registering account and token if needed
+    pub fn deposit(
+        &mut self,
+        account_id: &AccountId,
+        token_id: &TokenId,
+        amount: Amount,
+    ) -> Result<Amount> {
+        let balance = self.call(account_id, |dex| {
+            dex.register_account_and_then(account_id.clone(), |_, Account::V1(account), _| {
+                account.register_tokens([token_id]);
+                Ok(())
+            })?;
+            dex.deposit(account_id, token_id, amount)
+        })?;
+        *self.supply.entry(token_id.clone()).or_default() += amount;
+        Ok(balance)
+    }
+}
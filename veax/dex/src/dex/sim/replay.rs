@@ -0,0 +1,558 @@
+//! Deterministic replay of contract event log over simulated state
+//!
+//! Takes log lines emitted by on-chain contract, applies user operations they describe
+//! to `SimState` and compares every reported pool state with the simulated one.
+//! First divergence pinpoints the operation after which simulation and chain disagree,
+//! which is the primary tool for reproducing incidents and validating upgrades.
+//!
+//! Only events which change balances or pools are replayed; the rest are counted as ignored.
+//! Replay is exact only if log starts from contract deployment and simulation is created
+//! with the same owner and protocol fee fraction.
+//!
+//! Known sources of inexactness, which can be covered by `Replay::with_tolerance`:
+//! * positions are reopened using actually deposited amounts as maximums, which may produce
+//!   slightly different liquidity;
+//! * swap events don't tell exact-in from exact-out swaps, and don't contain intermediate
+//!   tokens of multi-hop swaps, so all swaps are replayed as single-hop exact-in swaps.
+use super::SimState;
+use crate::chain::{AccountId, Amount, AmountUFP, Liquidity, TokenId};
+use crate::dex::{
+    Account, BasisPoints, Contract, Error, FeeLevel, Float, Map, Pool, PoolId, PositionId,
+    PositionInit, Range, Side, State as _,
+};
+use near_sdk::json_types::{U128, U64};
+use serde::Deserialize;
+use serde_json::Value;
+
+const EVENT_PREFIX: &str = "EVENT_JSON:";
+
+/// Why replay was stopped
+#[derive(Debug)]
+pub enum ReplayErrorKind {
+    /// Line is not valid event JSON, or event data doesn't match its schema
+    Parse(String),
+    /// Event can't be replayed, e.g. it was emitted by too old contract version
+    Unsupported(String),
+    /// Replayed operation failed over simulated state
+    CallFailed(Error),
+    /// Simulated state diverged from the one reported by event
+    Mismatch {
+        field: String,
+        expected: String,
+        actual: String,
+    },
+}
+
+/// Replay error, with 1-based number of log line which caused it
+#[derive(Debug)]
+pub struct ReplayError {
+    pub line: usize,
+    pub kind: ReplayErrorKind,
+}
+
+/// Counters of processed log lines
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct ReplayReport {
+    /// Events which were applied to simulated state
+    pub applied: usize,
+    /// Pool state events which were compared with simulated state
+    pub verified: usize,
+    /// Events which don't affect balances or pools
+    pub ignored: usize,
+    /// Lines which are not events, e.g. plain logs and trace records
+    pub skipped: usize,
+}
+
+#[derive(Deserialize)]
+struct EventEnvelope {
+    event: String,
+    #[serde(default)]
+    data: Value,
+}
+
+#[derive(Deserialize)]
+struct BalanceChange {
+    user: AccountId,
+    token_id: TokenId,
+    amount: U128,
+    balance: U128,
+}
+
+#[derive(Deserialize)]
+struct OpenPosition {
+    user: AccountId,
+    pool: (TokenId, TokenId),
+    amounts: (U128, U128),
+    fee_rate: U64,
+    position_id: U64,
+    /// Missing before 1.9.0, which means full range
+    #[serde(default)]
+    range_ticks: Option<(Option<i32>, Option<i32>)>,
+}
+
+#[derive(Deserialize)]
+struct PositionChange {
+    /// Missing before 1.1.0
+    #[serde(default)]
+    user: Option<AccountId>,
+    position_id: U64,
+    amounts: (U128, U128),
+}
+
+#[derive(Deserialize)]
+struct MovePosition {
+    user: AccountId,
+    position_id: U64,
+    range_ticks: (Option<i32>, Option<i32>),
+    deposited: (U128, U128),
+}
+
+#[derive(Deserialize)]
+struct Swap {
+    user: AccountId,
+    tokens: (TokenId, TokenId),
+    amounts: (U128, U128),
+}
+
+#[derive(Deserialize)]
+struct UpdatePoolState {
+    pool: (TokenId, TokenId),
+    amounts_a: Vec<U128>,
+    amounts_b: Vec<U128>,
+    sqrt_prices: Vec<f64>,
+    liquidities: Vec<f64>,
+}
+
+#[derive(Deserialize)]
+struct UpdatePoolStateDelta {
+    pool: (TokenId, TokenId),
+    levels: Vec<LevelStateDelta>,
+}
+
+#[derive(Deserialize)]
+struct LevelStateDelta {
+    level: FeeLevel,
+    amount_a: U128,
+    amount_b: U128,
+    sqrt_price: f64,
+    liquidity: f64,
+}
+
+/// Pool state reported by either full or delta event, for levels it covers
+struct ReportedPoolState {
+    pool: (TokenId, TokenId),
+    levels: Vec<ReportedLevel>,
+}
+
+struct ReportedLevel {
+    level: usize,
+    amount_a: f64,
+    amount_b: f64,
+    sqrt_price: f64,
+    liquidity: f64,
+}
+
+impl From<UpdatePoolState> for ReportedPoolState {
+    fn from(event: UpdatePoolState) -> Self {
+        let levels = (event.amounts_a.iter())
+            .zip(&event.amounts_b)
+            .zip(&event.sqrt_prices)
+            .zip(&event.liquidities)
+            .enumerate()
+            .map(
+                |(level, (((amount_a, amount_b), sqrt_price), liquidity))| ReportedLevel {
+                    level,
+                    amount_a: amount_a.0 as f64,
+                    amount_b: amount_b.0 as f64,
+                    sqrt_price: *sqrt_price,
+                    liquidity: *liquidity,
+                },
+            )
+            .collect();
+        Self {
+            pool: event.pool,
+            levels,
+        }
+    }
+}
+
+impl From<UpdatePoolStateDelta> for ReportedPoolState {
+    fn from(event: UpdatePoolStateDelta) -> Self {
+        let levels = event
+            .levels
+            .into_iter()
+            .map(|level| ReportedLevel {
+                level: level.level.into(),
+                amount_a: level.amount_a.0 as f64,
+                amount_b: level.amount_b.0 as f64,
+                sqrt_price: level.sqrt_price,
+                liquidity: level.liquidity,
+            })
+            .collect();
+        Self {
+            pool: event.pool,
+            levels,
+        }
+    }
+}
+
+#[derive(Deserialize)]
+struct SetProtocolFeeFraction {
+    caller: AccountId,
+    new: BasisPoints,
+}
+
+/// Applies event log to simulated state, line by line
+pub struct Replay<'a> {
+    state: &'a mut SimState,
+    tolerance: f64,
+    report: ReplayReport,
+    /// Pool states which didn't match simulation yet, with their line numbers
+    pending: Vec<(usize, ReportedPoolState)>,
+}
+
+impl<'a> Replay<'a> {
+    /// Create replay over `state`, which requires exact match of all the values
+    pub fn new(state: &'a mut SimState) -> Self {
+        Self {
+            state,
+            tolerance: 0.0,
+            report: ReplayReport::default(),
+            pending: Vec::new(),
+        }
+    }
+
+    /// Allow relative difference between reported and simulated values
+    #[must_use]
+    pub fn with_tolerance(mut self, tolerance: f64) -> Self {
+        self.tolerance = tolerance;
+        self
+    }
+
+    /// Apply all lines of log, stopping at first error
+    ///
+    /// # Returns
+    /// * `Ok(report)` - all lines were applied, and simulated state matches reported one
+    /// * `Err(error)` - replay was stopped; state contains all changes applied before failed line
+    pub fn run<'l>(
+        mut self,
+        lines: impl IntoIterator<Item = &'l str>,
+    ) -> Result<ReplayReport, ReplayError> {
+        for (index, line) in lines.into_iter().enumerate() {
+            self.apply_line(index + 1, line)?;
+        }
+        self.verify_pending()?;
+        Ok(self.report)
+    }
+
+    fn apply_line(&mut self, line: usize, text: &str) -> Result<(), ReplayError> {
+        let error = |kind| ReplayError { line, kind };
+
+        let Some(json) = text.trim().strip_prefix(EVENT_PREFIX) else {
+            self.report.skipped += 1;
+            return Ok(());
+        };
+        let envelope: EventEnvelope = parse(serde_json::from_str(json)).map_err(error)?;
+        let data = envelope.data;
+
+        match envelope.event.as_str() {
+            "deposit" => parse(serde_json::from_value(data)).and_then(|event| self.deposit(event)),
+            "withdraw" => {
+                parse(serde_json::from_value(data)).and_then(|event| self.withdraw(event))
+            }
+            "open_position" => {
+                parse(serde_json::from_value(data)).and_then(|event| self.open_position(event))
+            }
+            "close_position" => {
+                parse(serde_json::from_value(data)).and_then(|event| self.close_position(event))
+            }
+            "harvest_fee" => {
+                parse(serde_json::from_value(data)).and_then(|event| self.harvest_fee(event))
+            }
+            "move_position" => {
+                parse(serde_json::from_value(data)).and_then(|event| self.move_position(event))
+            }
+            "swap" => parse(serde_json::from_value(data)).and_then(|event| self.swap(event)),
+            "set_protocol_fee_fraction" => parse(serde_json::from_value(data))
+                .and_then(|event| self.set_protocol_fee_fraction(event)),
+            "update_pool_state" => {
+                let event: UpdatePoolState = parse(serde_json::from_value(data)).map_err(error)?;
+                self.check_pool_state(line, event.into());
+                return Ok(());
+            }
+            "update_pool_state_delta" => {
+                let event: UpdatePoolStateDelta =
+                    parse(serde_json::from_value(data)).map_err(error)?;
+                self.check_pool_state(line, event.into());
+                return Ok(());
+            }
+            _ => {
+                self.report.ignored += 1;
+                return Ok(());
+            }
+        }
+        .map_err(error)?;
+        self.report.applied += 1;
+        self.verify_pending()
+    }
+
+    fn check_pool_state(&mut self, line: usize, state: ReportedPoolState) {
+        // Depending on operation, pool state is emitted either before or after
+        // operation's own event, so state which doesn't match yet is re-checked
+        // after next applied operation
+        if self.verify_pool_state(&state).is_ok() {
+            self.report.verified += 1;
+        } else {
+            self.pending.push((line, state));
+        }
+    }
+
+    fn verify_pending(&mut self) -> Result<(), ReplayError> {
+        for (line, event) in std::mem::take(&mut self.pending) {
+            self.verify_pool_state(&event)
+                .map_err(|kind| ReplayError { line, kind })?;
+            self.report.verified += 1;
+        }
+        Ok(())
+    }
+
+    fn deposit(&mut self, event: BalanceChange) -> Result<(), ReplayErrorKind> {
+        let balance = self
+            .state
+            .deposit(&event.user, &event.token_id, event.amount.0)
+            .map_err(ReplayErrorKind::CallFailed)?;
+        self.check_amount("deposit.balance", event.balance.0, balance)
+    }
+
+    fn withdraw(&mut self, event: BalanceChange) -> Result<(), ReplayErrorKind> {
+        self.state
+            .call(&event.user, |dex| {
+                dex.withdraw(&event.user, &event.token_id, event.amount.0, false, ())
+            })
+            .map_err(ReplayErrorKind::CallFailed)?;
+        let Contract::V1(ref contract) = self.state.contract();
+        let balance = contract
+            .accounts
+            .inspect(&event.user, |Account::V1(account)| {
+                account
+                    .token_balances
+                    .inspect(&event.token_id, |balance| *balance)
+            })
+            .flatten()
+            .unwrap_or_default();
+        self.check_amount("withdraw.balance", event.balance.0, balance)
+    }
+
+    fn open_position(&mut self, event: OpenPosition) -> Result<(), ReplayErrorKind> {
+        let fee_rate = BasisPoints::try_from(event.fee_rate.0).map_err(|_| {
+            ReplayErrorKind::Parse(format!("invalid fee rate {}", event.fee_rate.0))
+        })?;
+        let position = PositionInit {
+            amount_ranges: (
+                Range {
+                    min: 0.into(),
+                    max: event.amounts.0,
+                },
+                Range {
+                    min: 0.into(),
+                    max: event.amounts.1,
+                },
+            ),
+            ticks_range: event.range_ticks.unwrap_or((None, None)),
+            auto_balance: false,
+            locked_until: None,
+        };
+        let (position_id, amount_a, amount_b, _) = self
+            .state
+            .call(&event.user, |dex| {
+                dex.open_position(&event.pool.0, &event.pool.1, fee_rate, position)
+            })
+            .map_err(ReplayErrorKind::CallFailed)?;
+
+        if position_id != event.position_id.0 {
+            return Err(mismatch(
+                "open_position.position_id",
+                event.position_id.0,
+                position_id,
+            ));
+        }
+        self.check_amount("open_position.amounts.0", event.amounts.0 .0, amount_a)?;
+        self.check_amount("open_position.amounts.1", event.amounts.1 .0, amount_b)
+    }
+
+    fn close_position(&mut self, event: PositionChange) -> Result<(), ReplayErrorKind> {
+        let user = required_user(event.user, "close_position")?;
+        let position_id: PositionId = event.position_id.0;
+        self.state
+            .call(&user, |dex| dex.close_position(position_id))
+            .map_err(ReplayErrorKind::CallFailed)
+    }
+
+    fn harvest_fee(&mut self, event: PositionChange) -> Result<(), ReplayErrorKind> {
+        let user = required_user(event.user, "harvest_fee")?;
+        let position_id: PositionId = event.position_id.0;
+        let (amount_a, amount_b) = self
+            .state
+            .call(&user, |dex| dex.withdraw_fee(position_id))
+            .map_err(ReplayErrorKind::CallFailed)?;
+        self.check_amount("harvest_fee.amounts.0", event.amounts.0 .0, amount_a)?;
+        self.check_amount("harvest_fee.amounts.1", event.amounts.1 .0, amount_b)
+    }
+
+    fn move_position(&mut self, event: MovePosition) -> Result<(), ReplayErrorKind> {
+        let position_id: PositionId = event.position_id.0;
+        let (amount_a, amount_b, _) = self
+            .state
+            .call(&event.user, |dex| {
+                dex.move_position(position_id, event.range_ticks)
+            })
+            .map_err(ReplayErrorKind::CallFailed)?;
+        self.check_amount("move_position.deposited.0", event.deposited.0 .0, amount_a)?;
+        self.check_amount("move_position.deposited.1", event.deposited.1 .0, amount_b)
+    }
+
+    fn swap(&mut self, event: Swap) -> Result<(), ReplayErrorKind> {
+        let tokens = [event.tokens.0, event.tokens.1];
+        let (_, amount_out) = self
+            .state
+            .call(&event.user, |dex| {
+                dex.swap_exact_in(&tokens, event.amounts.0 .0, 0, None)
+            })
+            .map_err(ReplayErrorKind::CallFailed)?;
+        self.check_amount("swap.amounts.1", event.amounts.1 .0, amount_out)
+    }
+
+    fn set_protocol_fee_fraction(
+        &mut self,
+        event: SetProtocolFeeFraction,
+    ) -> Result<(), ReplayErrorKind> {
+        self.state
+            .call(&event.caller, |dex| {
+                dex.set_protocol_fee_fraction(event.new)
+            })
+            .map_err(ReplayErrorKind::CallFailed)
+    }
+
+    fn verify_pool_state(&self, state: &ReportedPoolState) -> Result<(), ReplayErrorKind> {
+        let (pool_id, swapped) = PoolId::try_from_pair(state.pool.clone())
+            .map_err(|e| ReplayErrorKind::Parse(format!("invalid pool: {e:?}")))?;
+        if swapped {
+            return Err(ReplayErrorKind::Parse(format!(
+                "pool tokens {:?} are not in pool order",
+                state.pool
+            )));
+        }
+
+        let Contract::V1(ref contract) = self.state.contract();
+        let (reserves, sqrt_prices, liquidities) = contract
+            .pools
+            .inspect(&pool_id, |Pool::V1(pool)| {
+                (
+                    pool.position_reserves(),
+                    pool.spot_sqrtprices(Side::Right),
+                    pool.liquidities(),
+                )
+            })
+            .ok_or_else(|| {
+                ReplayErrorKind::Unsupported(format!(
+                    "pool {pool_id:?} doesn't exist in simulation"
+                ))
+            })?;
+
+        let amounts_a = reserves.map(|(left, _right)| amount_to_f64(left));
+        let amounts_b = reserves.map(|(_left, right)| amount_to_f64(right));
+        let sqrt_prices = sqrt_prices.map(f64::from);
+        let liquidities = liquidities.map(liquidity_to_f64);
+
+        for reported in &state.levels {
+            let level = reported.level;
+            if level >= liquidities.len() {
+                return Err(mismatch(
+                    "update_pool_state.level",
+                    level,
+                    liquidities.len(),
+                ));
+            }
+            self.check_level("amounts_a", level, reported.amount_a, amounts_a[level])?;
+            self.check_level("amounts_b", level, reported.amount_b, amounts_b[level])?;
+            self.check_level(
+                "sqrt_prices",
+                level,
+                reported.sqrt_price,
+                sqrt_prices[level],
+            )?;
+            self.check_level("liquidities", level, reported.liquidity, liquidities[level])?;
+        }
+        Ok(())
+    }
+
+    fn check_level(
+        &self,
+        field: &str,
+        level: usize,
+        expected: f64,
+        actual: f64,
+    ) -> Result<(), ReplayErrorKind> {
+        if self.is_close(expected, actual) {
+            Ok(())
+        } else {
+            Err(mismatch(
+                &format!("update_pool_state.{field}[{level}]"),
+                expected,
+                actual,
+            ))
+        }
+    }
+
+    fn check_amount(
+        &self,
+        field: &str,
+        expected: Amount,
+        actual: Amount,
+    ) -> Result<(), ReplayErrorKind> {
+        if expected == actual || self.is_close(expected as f64, actual as f64) {
+            Ok(())
+        } else {
+            Err(mismatch(field, expected, actual))
+        }
+    }
+
+    #[allow(clippy::float_cmp)] // exact match is required with zero tolerance
+    fn is_close(&self, expected: f64, actual: f64) -> bool {
+        expected == actual
+            || (expected - actual).abs() <= self.tolerance * expected.abs().max(actual.abs())
+    }
+}
+
+fn parse<T>(result: serde_json::Result<T>) -> Result<T, ReplayErrorKind> {
+    result.map_err(|e| ReplayErrorKind::Parse(e.to_string()))
+}
+
+fn mismatch(
+    field: &str,
+    expected: impl std::fmt::Debug,
+    actual: impl std::fmt::Debug,
+) -> ReplayErrorKind {
+    ReplayErrorKind::Mismatch {
+        field: field.to_string(),
+        expected: format!("{expected:?}"),
+        actual: format!("{actual:?}"),
+    }
+}
+
+fn required_user(user: Option<AccountId>, event: &str) -> Result<AccountId, ReplayErrorKind> {
+    user.ok_or_else(|| {
+        ReplayErrorKind::Unsupported(format!(
+            "`{event}` event without `user`, emitted before 1.1.0"
+        ))
+    })
+}
+
+/// Same conversions as used for `update_pool_state` event
+fn amount_to_f64(amount: AmountUFP) -> f64 {
+    Amount::try_from(amount).unwrap_or_default() as f64
+}
+
+fn liquidity_to_f64(liquidity: Liquidity) -> f64 {
+    f64::from(Float::try_from(liquidity).unwrap_or_default())
+}
@@ -0,0 +1,184 @@
+//! Scripted scenarios over simulated contract
+use super::invariants::{check_fee_monotonicity, check_reserve_conservation, fee_snapshot};
+use super::{InvariantViolation, SimState};
+use crate::chain::{AccountId, Amount, Liquidity, TokenId};
+use crate::dex::{BasisPoints, Error, PositionId, PositionInit};
+
+/// Single scenario step, performed on behalf of some account
+#[derive(Clone, Debug)]
+pub enum Step {
+    /// Transfer tokens into account's deposit, registering account and token if needed
+    Deposit {
+        account_id: AccountId,
+        token_id: TokenId,
+        amount: Amount,
+    },
+    /// Withdraw tokens from account's deposit; zero amount withdraws whole balance
+    Withdraw {
+        account_id: AccountId,
+        token_id: TokenId,
+        amount: Amount,
+    },
+    /// Swap exact amount of first token along the path
+    Swap {
+        account_id: AccountId,
+        tokens: Vec<TokenId>,
+        amount_in: Amount,
+        min_amount_out: Amount,
+    },
+    OpenPosition {
+        account_id: AccountId,
+        tokens: (TokenId, TokenId),
+        fee_rate: BasisPoints,
+        position: PositionInit,
+    },
+    ClosePosition {
+        account_id: AccountId,
+        position_id: PositionId,
+    },
+    WithdrawFee {
+        account_id: AccountId,
+        position_id: PositionId,
+    },
+    /// Move simulated clock forward
+    AdvanceTime { seconds: u64 },
+}
+
+/// Result of single scenario step
+#[derive(Debug)]
+pub enum StepOutcome {
+    Deposited {
+        balance: Amount,
+    },
+    Withdrawn,
+    Swapped {
+        amount_in: Amount,
+        amount_out: Amount,
+    },
+    PositionOpened {
+        position_id: PositionId,
+        amounts: (Amount, Amount),
+        net_liquidity: Liquidity,
+    },
+    PositionClosed,
+    FeeWithdrawn {
+        amounts: (Amount, Amount),
+    },
+    TimeAdvanced {
+        now: u64,
+    },
+    /// Step failed and was rolled back; this is a normal outcome for fuzzed steps
+    Failed(Error),
+}
+
+/// Invariant violation detected after some step
+#[derive(Debug)]
+pub struct ScenarioError {
+    /// Index of step after which invariant was broken
+    pub step: usize,
+    pub violation: InvariantViolation,
+}
+
+/// Sequence of steps, performed one after another with invariants checked after each step
+#[derive(Clone, Debug, Default)]
+pub struct Scenario {
+    pub steps: Vec<Step>,
+}
+
+impl Scenario {
+    pub fn new(steps: impl IntoIterator<Item = Step>) -> Self {
+        Self {
+            steps: steps.into_iter().collect(),
+        }
+    }
+
+    /// Run scenario over provided state
+    ///
+    /// # Returns
+    /// * `Ok(outcomes)` - outcomes of all the steps, in order
+    /// * `Err(error)` - if any invariant was broken; scenario is stopped at that step
+    pub fn run(&self, state: &mut SimState) -> Result<Vec<StepOutcome>, ScenarioError> {
+        let mut outcomes = Vec::with_capacity(self.steps.len());
+        let mut fees_before = fee_snapshot(state);
+
+        for (index, step) in self.steps.iter().enumerate() {
+            outcomes.push(Self::run_step(state, step).unwrap_or_else(StepOutcome::Failed));
+
+            let fees_after = fee_snapshot(state);
+            check_reserve_conservation(state)
+                .and_then(|()| check_fee_monotonicity(&fees_before, &fees_after))
+                .map_err(|violation| ScenarioError {
+                    step: index,
+                    violation,
+                })?;
+            fees_before = fees_after;
+        }
+        Ok(outcomes)
+    }
+
+    fn run_step(state: &mut SimState, step: &Step) -> Result<StepOutcome, Error> {
+        match step {
+            Step::Deposit {
+                account_id,
+                token_id,
+                amount,
+            } => state
+                .deposit(account_id, token_id, *amount)
+                .map(|balance| StepOutcome::Deposited { balance }),
+            Step::Withdraw {
+                account_id,
+                token_id,
+                amount,
+            } => state
+                .call(account_id, |dex| {
+                    dex.withdraw(account_id, token_id, *amount, false, ())
+                })
+                .map(|_| StepOutcome::Withdrawn),
+            Step::Swap {
+                account_id,
+                tokens,
+                amount_in,
+                min_amount_out,
+            } => state
+                .call(account_id, |dex| {
+                    dex.swap_exact_in(tokens, *amount_in, *min_amount_out, None)
+                })
+                .map(|(amount_in, amount_out)| StepOutcome::Swapped {
+                    amount_in,
+                    amount_out,
+                }),
+            Step::OpenPosition {
+                account_id,
+                tokens,
+                fee_rate,
+                position,
+            } => state
+                .call(account_id, |dex| {
+                    dex.open_position(&tokens.0, &tokens.1, *fee_rate, position.clone())
+                })
+                .map(|(position_id, amount_a, amount_b, net_liquidity)| {
+                    StepOutcome::PositionOpened {
+                        position_id,
+                        amounts: (amount_a, amount_b),
+                        net_liquidity,
+                    }
+                }),
+            Step::ClosePosition {
+                account_id,
+                position_id,
+            } => state
+                .call(account_id, |dex| dex.close_position(*position_id))
+                .map(|()| StepOutcome::PositionClosed),
+            Step::WithdrawFee {
+                account_id,
+                position_id,
+            } => state
+                .call(account_id, |dex| dex.withdraw_fee(*position_id))
+                .map(|amounts| StepOutcome::FeeWithdrawn { amounts }),
+            Step::AdvanceTime { seconds } => {
+                state.advance_time(*seconds);
+                Ok(StepOutcome::TimeAdvanced { now: state.now() })
+            }
+        }
+    }
+}
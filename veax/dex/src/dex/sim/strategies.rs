@@ -0,0 +1,137 @@
+//! `proptest` generators for positions and scenario steps
+use super::Step;
+use crate::chain::wasm::WasmAmount;
+use crate::chain::{AccountId, Amount, TokenId, MAX_TICK, MIN_TICK};
+use crate::dex::dex_impl::fee_rates_ticks;
+use crate::dex::{BasisPoints, PositionInit, Range};
+use proptest::prelude::*;
+use proptest::sample::select;
+
+/// Any of supported fee rates
+pub fn fee_rate() -> impl Strategy<Value = BasisPoints> {
+    select(fee_rates_ticks().to_vec())
+}
+
+/// Either full range or arbitrary non-empty range of ticks
+pub fn ticks_range() -> impl Strategy<Value = (Option<i32>, Option<i32>)> {
+    prop_oneof![
+        Just((None, None)),
+        (MIN_TICK..MAX_TICK)
+            .prop_flat_map(|low| (Just(low), low + 1..=MAX_TICK))
+            .prop_map(|(low, high)| (Some(low), Some(high))),
+    ]
+}
+
+/// Position which deposits up to `max_amount` of each token, without lower bounds on deposit
+pub fn position_init(max_amount: Amount) -> impl Strategy<Value = PositionInit> {
+    (1..=max_amount, 1..=max_amount, ticks_range()).prop_map(|(max_a, max_b, ticks_range)| {
+        let range = |max| Range {
+            min: WasmAmount::from(0),
+            max: WasmAmount::from(max),
+        };
+        PositionInit {
+            amount_ranges: (range(max_a), range(max_b)),
+            ticks_range,
+            auto_balance: false,
+            locked_until: None,
+        }
+    })
+}
+
+/// Opening position by one of `accounts` in pool of `tokens`
+pub fn open_position_step(
+    accounts: Vec<AccountId>,
+    tokens: (TokenId, TokenId),
+    max_amount: Amount,
+) -> impl Strategy<Value = Step> {
+    (select(accounts), fee_rate(), position_init(max_amount)).prop_map(
+        move |(account_id, fee_rate, position)| Step::OpenPosition {
+            account_id,
+            tokens: tokens.clone(),
+            fee_rate,
+            position,
+        },
+    )
+}
+
+/// Swap of up to `max_amount` by one of `accounts` in either direction, without slippage limit
+pub fn swap_step(
+    accounts: Vec<AccountId>,
+    tokens: (TokenId, TokenId),
+    max_amount: Amount,
+) -> impl Strategy<Value = Step> {
+    (select(accounts), any::<bool>(), 1..=max_amount).prop_map(
+        move |(account_id, reversed, amount_in)| {
+            let (token_in, token_out) = if reversed {
+                (tokens.1.clone(), tokens.0.clone())
+            } else {
+                tokens.clone()
+            };
+            Step::Swap {
+                account_id,
+                tokens: vec![token_in, token_out],
+                amount_in,
+                min_amount_out: 0,
+            }
+        },
+    )
+}
+
+/// Scenario steps over single pool: every account deposits `max_amount` of both tokens,
+/// then up to `max_steps` positions openings and swaps are performed in random order
+pub fn pool_steps(
+    accounts: Vec<AccountId>,
+    tokens: (TokenId, TokenId),
+    max_amount: Amount,
+    max_steps: usize,
+) -> impl Strategy<Value = Vec<Step>> {
+    let deposits: Vec<_> = accounts
+        .iter()
+        .flat_map(|account_id| {
+            [&tokens.0, &tokens.1].map(|token_id| Step::Deposit {
+                account_id: account_id.clone(),
+                token_id: token_id.clone(),
+                amount: max_amount,
+            })
+        })
+        .collect();
+    // Single position or swap shouldn't be able to exhaust deposits at once
+    let max_step_amount = (max_amount / 4).max(1);
+    let step = prop_oneof![
+        open_position_step(accounts.clone(), tokens.clone(), max_step_amount),
+        swap_step(accounts, tokens, max_step_amount),
+    ];
+    proptest::collection::vec(step, 1..=max_steps)
+        .prop_map(move |steps| deposits.iter().cloned().chain(steps).collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::pool_steps;
+    use crate::chain::AccountId;
+    use crate::dex::sim::{Scenario, SimState};
+    use proptest::prelude::*;
+
+    fn account(name: &str) -> AccountId {
+        name.parse().unwrap()
+    }
+
+    proptest! {
+        #![proptest_config(ProptestConfig::with_cases(32))]
+
+        /// Reserves stay conserved and fees never decrease, whichever positions and swaps are made
+        #[test]
+        fn pool_invariants_hold(
+            steps in pool_steps(
+                vec![account("alice"), account("bob")],
+                (account("token-a"), account("token-b")),
+                1_000_000_000_000,
+                16,
+            )
+        ) {
+            let mut state = SimState::new(account("owner"), 1300).unwrap();
+            let result = Scenario::new(steps).run(&mut state);
+            prop_assert!(result.is_ok(), "{:?}", result.err());
+        }
+    }
+}
@@ -1,5 +1,14 @@
 use super::map_with_context::{MapContext, MapWithContext};
-use super::{v0, BasisPoints, ErrorKind, FeeLevel, Float, Side, Types};
+#[cfg(feature = "batch-auction")]
+use super::BatchAuction;
+#[cfg(feature = "smart-routing")]
+use super::CachedRoute;
+#[cfg(feature = "tracing")]
+use super::TraceVerbosity;
+use super::{
+    v0, BasisPoints, CleanupCursor, CodeHash, ErrorKind, EventFilter, FeeAuctionConfig, FeeLevel,
+    Float, GasConfig, IntegrationId, PositionId, SeedPeriodConfig, Side, SwapHook, Types,
+};
 use crate::chain::{AccountId, Amount, AmountUFP, LPFeePerFeeLiquidity, Liquidity, LiquiditySFP};
 use crate::dex::tick::{EffTick, Tick};
 use paste::paste;
@@ -21,8 +30,14 @@ fn serialize_ver<U: BorshSerialize, W: std::io::Write>(
     value.serialize(writer)
 }
 
+/// Declares versioned state type, as enum of all its current layouts, with version number
+/// written before each record.
+///
+/// Layouts marked `legacy` are converted into enum right away on deserialization,
+/// with `From` implementation which must be provided separately.
 macro_rules! versioned {
     ($pub:vis $enum_name:ident {
+        $(legacy $old_ver_num:literal => { $($old_struct_body:tt)* },)*
         $($ver_num:literal => { $($struct_body:tt)* }),+
     }) => {
         paste! {
@@ -47,6 +62,11 @@ macro_rules! versioned {
             impl<T: Types + ?Sized> BorshDeserialize for $enum_name<T> {
                 fn deserialize(buf: &mut &[u8]) -> std::io::Result<Self> {
                     match VersionNumber::deserialize(buf)? {
+                        $(
+                            $old_ver_num => Ok(
+                                [<$enum_name V $old_ver_num>]::<T>::deserialize(buf)?.into()
+                            ),
+                        )*
                         $(
                             $ver_num => Ok($enum_name::[<V $ver_num>](
                                 [<$enum_name V $ver_num>]::deserialize(buf)?
@@ -60,6 +80,13 @@ macro_rules! versioned {
                 }
             }
 
+            $(
+                #[cfg_attr(feature = "near", derive(BorshSerialize, BorshDeserialize))]
+                $pub struct [<$enum_name V $old_ver_num>]<T: Types> {
+                    $($old_struct_body)*
+                }
+            )*
+
             $(
                 #[cfg_attr(feature = "near", derive(BorshSerialize, BorshDeserialize))]
                 $pub struct [<$enum_name V $ver_num>]<T: Types> {
@@ -102,6 +129,35 @@ macro_rules! map_with_ctxt {
 map_with_ctxt!(PoolsMap, ErrorKind::PoolNotRegistered);
 map_with_ctxt!(AccountsMap, ErrorKind::AccountNotRegistered);
 map_with_ctxt!(PositionToPoolMap, ErrorKind::PositionDoesNotExist);
+map_with_ctxt!(PositionOwnersMap, ErrorKind::PositionDoesNotExist);
+map_with_ctxt!(ScheduledActionsMap, ErrorKind::AdminActionNotScheduled);
+map_with_ctxt!(PendingApprovalsMap, ErrorKind::AdminActionNotProposed);
+map_with_ctxt!(WithdrawLimitsMap, ErrorKind::WithdrawLimitNotSet);
+map_with_ctxt!(SwapGuardsMap, ErrorKind::PoolNotRegistered);
+map_with_ctxt!(InsuranceReserveMap, ErrorKind::InsufficientInsuranceReserve);
+map_with_ctxt!(MinDepositAmountsMap, ErrorKind::PoolNotRegistered);
+map_with_ctxt!(ClassicPoolsMap, ErrorKind::PoolNotRegistered);
+map_with_ctxt!(StablePoolsMap, ErrorKind::PoolNotRegistered);
+map_with_ctxt!(PoolSharesMap, ErrorKind::InsufficientShares);
+map_with_ctxt!(OrdersMap, ErrorKind::OrderNotFound);
+map_with_ctxt!(FeeAuctionsMap, ErrorKind::FeeAuctionNotFound);
+map_with_ctxt!(IntegrationsMap, ErrorKind::IntegrationNotFound);
+map_with_ctxt!(DepositIntentsMap, ErrorKind::DepositIntentNotFound);
+map_with_ctxt!(AutoHarvestMap, ErrorKind::PoolNotRegistered);
+map_with_ctxt!(ViewGrantsMap, ErrorKind::ViewAccessNotGranted);
+map_with_ctxt!(PoolAliasesMap, ErrorKind::PoolAliasNotFound);
+map_with_ctxt!(AliasesByPoolMap, ErrorKind::PoolAliasNotFound);
+map_with_ctxt!(
+    PoolStorageDepositsMap,
+    ErrorKind::PoolStorageDepositNotFound
+);
+map_with_ctxt!(AdminLogMap, ErrorKind::AdminLogEntryNotFound);
+map_with_ctxt!(VaultsMap, ErrorKind::VaultNotFound);
+map_with_ctxt!(SessionKeysMap, ErrorKind::SessionKeyNotFound);
+map_with_ctxt!(ReconciledTokensMap, ErrorKind::TokenNotReconciled);
+map_with_ctxt!(TokenInterfacesMap, ErrorKind::TokenInterfaceNotChecked);
+#[cfg(feature = "lp-tokens")]
+map_with_ctxt!(LpTokensMap, ErrorKind::LpTokenNotFound);
 #[cfg(feature = "smart-routing")]
 map_with_ctxt!(TokenConnectionsMap, ErrorKind::PoolNotRegistered);
 #[cfg(feature = "smart-routing")]
@@ -109,7 +165,7 @@ map_with_ctxt!(TopPoolsMap, ErrorKind::PoolNotRegistered);
 
 versioned! {
     pub Contract {
-        0 => {
+        1 => {
             /// Account of the owner.
             pub owner_id: AccountId,
             /// Accounts that are allowed to set permitions for payable methods.
@@ -128,6 +184,9 @@ versioned! {
             pub next_free_position_id: u64,
             /// Map of position to token_pair, in pool of which it exists
             pub position_to_pool_id: PositionToPoolMap<T>,
+            /// Map of position to account which owns it; positions opened before
+            /// this record was introduced, and ones held by LP tokens, aren't listed
+            pub position_owners: PositionOwnersMap<T>,
             /// Fraction of the total fee, that will go to the DEX.
             /// The rest of the fee will be distributed among the liquidity providers.
             /// Specified in units of 1/FEE_DIVISOR. For example, if FEE_DIVISOR
@@ -138,11 +197,113 @@ versioned! {
             /// out of which 260 tokens will go to the DEX, and the rest 1740 tokens
             /// will be distributed among the LPs.
             pub protocol_fee_fraction: BasisPoints,
+            /// Optional contract notified about large swaps
+            pub swap_hook: Option<SwapHook>,
+            /// Gas attached to cross-contract calls
+            pub gas_config: GasConfig,
+            /// Delay between scheduling and execution of owner actions, in seconds.
+            /// If zero, timelock is disabled and owner actions are executed immediately
+            pub timelock_delay: u64,
+            /// Owner actions waiting for timelock to expire, by id
+            pub scheduled_actions: ScheduledActionsMap<T>,
+            /// Counter for scheduled owner actions
+            pub next_scheduled_action_id: u64,
+            /// Accounts which collectively act as owner, if `admin_threshold` is non-zero
+            pub admins: T::AccountIdSet,
+            /// Number of admins' approvals needed to perform owner action.
+            /// If zero, multisig is disabled and owner actions are performed by `owner_id` alone
+            pub admin_threshold: u16,
+            /// Owner actions proposed by admins and waiting for approvals, by action hash
+            pub pending_approvals: PendingApprovalsMap<T>,
+            /// Hash of contract code which owner allowed to upgrade to, if any
+            pub staged_upgrade: Option<CodeHash>,
+            /// Withdraw rate limits per token
+            pub withdraw_limits: WithdrawLimitsMap<T>,
+            /// Set by guard to stop enforcing withdraw rate limits
+            pub withdraw_limits_disabled: bool,
+            /// Sandwich guards of pools which have them configured
+            pub swap_guards: SwapGuardsMap<T>,
+            /// Fraction of protocol fee which goes to insurance reserve, in basis points
+            pub insurance_fee_fraction: BasisPoints,
+            /// Amounts of tokens set aside from protocol fees to reimburse users after accounting bugs
+            pub insurance_reserve: InsuranceReserveMap<T>,
+            /// Set of tokens banned by "owner", e.g. honeypots impersonating legit tokens.
+            /// Banned tokens can't be deposited, swapped or used to create new pools
+            pub banned_tokens: T::BannedTokensSet,
+            /// Minimal accounted liquidity of newly opened position
+            pub min_position_liquidity: Float,
+            /// Minimal amounts of pool tokens deposited into newly opened position, per pool
+            pub min_deposit_amounts: MinDepositAmountsMap<T>,
+            /// Amount of details in trace records of pool math, set by owner
+            #[cfg(feature = "tracing")]
+            pub trace_verbosity: TraceVerbosity,
+            /// Kinds of events disabled by owner
+            pub event_filter: EventFilter,
+            /// Constant product pools, for pairs which don't have concentrated pool
+            pub classic_pools: ClassicPoolsMap<T>,
+            /// Stable swap pools, for pairs of pegged tokens which don't have pool of other kind
+            pub stable_pools: StablePoolsMap<T>,
+            /// Conditional orders waiting for keepers, by id
+            pub orders: OrdersMap<T>,
+            /// Counter for conditional orders
+            pub next_order_id: u64,
+            /// Parameters of protocol fee auctions; `None` if auctions are disabled
+            pub fee_auction_config: Option<FeeAuctionConfig>,
+            /// Running Dutch auctions of protocol fees, by sold token
+            pub fee_auctions: FeeAuctionsMap<T>,
+            /// Integrations which swaps may be attributed to, by id
+            pub integrations: IntegrationsMap<T>,
+            /// Counter for integrations
+            pub next_integration_id: IntegrationId,
+            /// Deposit intents waiting for their legs, by id
+            pub deposit_intents: DepositIntentsMap<T>,
+            /// Counter for deposit intents
+            pub next_deposit_intent_id: u64,
+            /// Auto-harvest rules of positions, by pool
+            pub auto_harvest: AutoHarvestMap<T>,
+            /// Accounts granted read-only access to account's private views, by account
+            pub view_grants: ViewGrantsMap<T>,
+            /// Human-readable labels of pools set by owner, e.g. "wNEAR/USDC"
+            pub pool_aliases: PoolAliasesMap<T>,
+            /// Reverse index of `pool_aliases`
+            pub aliases_by_pool: AliasesByPoolMap<T>,
+            /// Native tokens paid for storage of pools by their creators;
+            /// pools created before this record was introduced aren't listed
+            pub pool_storage_deposits: PoolStorageDepositsMap<T>,
+            /// Sum of amounts in `pool_storage_deposits`
+            pub pool_storage_deposits_total: Amount,
+            /// Sum of storage bytes in `pool_storage_deposits`
+            pub pool_storage_bytes_total: u64,
+            /// Recent administrative calls, by sequence number; older ones are evicted
+            pub admin_log: AdminLogMap<T>,
+            /// Sequence number of the next administrative call
+            pub next_admin_log_id: u64,
+            /// Withdraw protections of accounts which opted into vault mode
+            pub vaults: VaultsMap<T>,
+            /// Session keys of accounts, which may only swap within daily volume caps
+            pub session_keys: SessionKeysMap<T>,
+            /// Fee-on-transfer tokens credited on deposit by balance delta, with tracked balance
+            pub reconciled_tokens: ReconciledTokensMap<T>,
+            /// Whether any account, not only owner, may create pool at explicit starting price
+            pub pool_price_init_open: bool,
+            /// Restrictions applied to concentrated pools after creation; `None` if disabled
+            pub seed_period: Option<SeedPeriodConfig>,
+            /// Whether token contracts must pass interface check before their first deposit
+            /// or pool creation
+            pub token_interface_check: bool,
+            /// Outcomes of checks whether token contracts implement fungible token interface
+            pub token_interfaces: TokenInterfacesMap<T>,
+            /// LP share tokens of managed full-range positions
+            #[cfg(feature = "lp-tokens")]
+            pub lp_tokens: LpTokensMap<T>,
 
             #[cfg(feature = "smart-routing")]
             pub token_connections: TokenConnectionsMap<T>,
             #[cfg(feature = "smart-routing")]
             pub top_pools: TopPoolsMap<T>,
+            /// Most recently swapped best routes, ordered from least to most recent
+            #[cfg(feature = "smart-routing")]
+            pub route_cache: Vec<CachedRoute>,
 
             pub extra: T::ContractExtra,
         }
@@ -153,7 +314,14 @@ map_with_ctxt!(AccountTokenBalancesMap, ErrorKind::TokenNotRegistered);
 
 versioned! {
     pub Account {
-        0 => {
+        // Before registration cleanup consent, with previous layout of blockchain-specific extra
+        legacy 0 => {
+            pub token_balances: AccountTokenBalancesMap<T>,
+            pub positions: T::AccountPositionsSet,
+            pub withdraw_tracker: T::AccountWithdrawTracker,
+            pub extra: T::AccountExtraV0,
+        },
+        1 => {
             /// Amounts of various tokens deposited to this account
             pub token_balances: AccountTokenBalancesMap<T>,
             /// Positions which belong to current account
@@ -162,6 +330,8 @@ versioned! {
             pub withdraw_tracker: T::AccountWithdrawTracker,
             /// Blockchain-specific extra information, may be `()`
             pub extra: T::AccountExtra,
+            /// Account allows storage cleanup to unregister its tokens with zero balance
+            pub registration_cleanup_allowed: bool,
         }
     }
 }
@@ -171,30 +341,21 @@ map_with_ctxt!(TickStatesMap, ErrorKind::InternalTickNotFound);
 
 versioned! {
     pub Pool {
-        0 => {
+        1 => {
             /// Liquidity positions of this pool
             pub positions: PoolPositionsMap<T>,
             /// Tick states per fee level
             pub tick_states: v0::FeeLevelsArray<TickStatesMap<T>>,
             /// Total amounts of tokens, including the positions and collected fees (LP and protocol)
             pub total_reserves: (Amount, Amount),
-            /// Amounts of tokens locked in positions.
-            pub position_reserves: v0::FeeLevelsArray<(AmountUFP, AmountUFP)>,
             /// Total amount of LP fee reward to be paid out to all LPs (in case all pasitions are closed)
             pub acc_lp_fee: (AmountUFP, AmountUFP),
-            /// Global sqrtprice shift accumulators per top-active-level and for each swap direction.
-            /// These are sums of price shifts, performed in swaps with top active level equal to
-            /// the index of the array. Hence, to get the total price shift on level `k`
-            /// one has to sum up the values from index k to NUM_FEE_LEVELS.
-            pub acc_lp_fees_per_fee_liquidity: v0::FeeLevelsArray<(LPFeePerFeeLiquidity, LPFeePerFeeLiquidity)>,
-            /// Effective price on each of the levels
-            pub eff_sqrtprices: v0::FeeLevelsArray<v0::EffectiveSqrtPrice>,
-            /// next active ticks for swaps in left direction
-            pub next_active_ticks_left: v0::FeeLevelsArray<Option<Tick>>,
-            /// next active ticks for swaps in right direction
-            pub next_active_ticks_right: v0::FeeLevelsArray<Option<Tick>>,
-            /// Current effective net liquidity. Equal to: liquidity * sqrt(1-fee_rate)
-            pub net_liquidities: v0::FeeLevelsArray<Liquidity>,
+            /// Cumulative swap statistics
+            pub stats: v0::PoolStats,
+            /// Cumulative amounts of tokens paid in by traders, per fee level which absorbed them
+            pub level_volumes_in: v0::FeeLevelsArray<(AmountUFP, AmountUFP)>,
+            /// State of each of the fee levels, stored as separate records
+            pub levels: v0::LevelStates<T>,
             /// Current top active level
             pub top_active_level: FeeLevel,
             pub active_side: Side,
@@ -203,13 +364,134 @@ versioned! {
             /// effective sqrtprice in the opposite direction.
             /// See `eff_sqrtprice_opposite_side` for details.
             pub pivot: EffTick,
+            /// Where storage cleanup scan should continue from, if it's in progress
+            pub cleanup_cursor: Option<CleanupCursor>,
+            /// Total liquidity which pool was last ranked with among top pools of its tokens
+            #[cfg(feature = "smart-routing")]
+            pub ranked_liquidity: Liquidity,
+            /// Pending batch of swaps; `None` unless pool is in batch clearing mode
+            #[cfg(feature = "batch-auction")]
+            pub batch_auction: Option<BatchAuction>,
+            /// Block height until which pool is in seed period: swaps are disabled,
+            /// and positions may only be opened close to the pool price
+            pub seed_until: u64,
         }
     }
 }
 
+/// Layout of pool stored before version 1, with fee level states kept in-place.
+/// Unlike accounts and positions, such pools can't be converted on read,
+/// as separate records must be created for the levels; see `ItemFactory::migrate_pool`
+#[cfg_attr(feature = "near", derive(BorshSerialize, BorshDeserialize))]
+pub struct PoolV0<T: Types> {
+    pub positions: PoolPositionsMap<T>,
+    pub tick_states: v0::FeeLevelsArray<TickStatesMap<T>>,
+    pub total_reserves: (Amount, Amount),
+    pub position_reserves: v0::FeeLevelsArray<(AmountUFP, AmountUFP)>,
+    pub acc_lp_fee: (AmountUFP, AmountUFP),
+    pub acc_lp_fees_per_fee_liquidity:
+        v0::FeeLevelsArray<(LPFeePerFeeLiquidity, LPFeePerFeeLiquidity)>,
+    pub eff_sqrtprices: v0::FeeLevelsArray<v0::EffectiveSqrtPrice>,
+    pub next_active_ticks_left: v0::FeeLevelsArray<Option<Tick>>,
+    pub next_active_ticks_right: v0::FeeLevelsArray<Option<Tick>>,
+    pub net_liquidities: v0::FeeLevelsArray<Liquidity>,
+    pub top_active_level: FeeLevel,
+    pub active_side: Side,
+    pub pivot: EffTick,
+}
+
 versioned! {
-    pub Position {
+    pub ClassicPool {
         0 => {
+            /// Amounts of tokens available for swaps, excluding protocol fees
+            pub reserves: (Amount, Amount),
+            /// Total fee rate charged from input amount, in basis points
+            pub fee_rate: BasisPoints,
+            /// Protocol fees accumulated since last withdrawal
+            pub protocol_fees: (Amount, Amount),
+            /// Sum of all liquidity providers' shares
+            pub total_shares: Amount,
+            /// Shares of liquidity providers, by account
+            pub shares: PoolSharesMap<T>,
+        }
+    }
+}
+
+versioned! {
+    pub StablePool {
+        0 => {
+            /// Amounts of tokens available for swaps, excluding protocol fees
+            pub reserves: (Amount, Amount),
+            /// Amplification coefficient `A` of stable swap invariant; the higher it is,
+            /// the closer pool behaves to constant sum around balanced reserves
+            pub amplification: u32,
+            /// Total fee rate charged from input amount, in basis points
+            pub fee_rate: BasisPoints,
+            /// Protocol fees accumulated since last withdrawal
+            pub protocol_fees: (Amount, Amount),
+            /// Sum of all liquidity providers' shares
+            pub total_shares: Amount,
+            /// Shares of liquidity providers, by account
+            pub shares: PoolSharesMap<T>,
+        }
+    }
+}
+
+versioned! {
+    pub LpToken {
+        0 => {
+            /// Managed full-range position which holds token's liquidity;
+            /// `None` while token has no holders except locked supply
+            pub position_id: Option<PositionId>,
+            /// Amounts which position couldn't take at current price, kept until next reopening
+            pub idle_amounts: (Amount, Amount),
+            /// Sum of all holders' balances, including locked supply
+            pub total_supply: Amount,
+            /// Balances of holders, by account
+            pub balances: PoolSharesMap<T>,
+        }
+    }
+}
+
+versioned! {
+    pub LevelState {
+        0 => {
+            /// Amounts of tokens locked in positions on this level
+            pub position_reserves: (AmountUFP, AmountUFP),
+            /// Total amount of LP fee earned on this level since pool creation.
+            /// Unlike pool's `acc_lp_fee`, never decreases; used to report per-level fee deltas.
+            pub acc_lp_fees: (AmountUFP, AmountUFP),
+            /// Global sqrtprice shift accumulators for swaps with top active level equal to this level,
+            /// for each swap direction. To get the total price shift on level `k`
+            /// one has to sum up the values from level k to NUM_FEE_LEVELS.
+            pub acc_lp_fees_per_fee_liquidity: (LPFeePerFeeLiquidity, LPFeePerFeeLiquidity),
+            /// Effective price on this level
+            pub eff_sqrtprice: v0::EffectiveSqrtPrice,
+            /// Next active tick for swaps in left direction
+            pub next_active_tick_left: Option<Tick>,
+            /// Next active tick for swaps in right direction
+            pub next_active_tick_right: Option<Tick>,
+            /// Current effective net liquidity. Equal to: liquidity * sqrt(1-fee_rate)
+            pub net_liquidity: Liquidity,
+            /// Phantom data, to bind T and unify all state types declarations
+            pub phantom_t: PhantomData<T>,
+        }
+    }
+}
+
+versioned! {
+    pub Position {
+        // Before position locks
+        legacy 0 => {
+            pub fee_level: FeeLevel,
+            pub net_liquidity: Liquidity,
+            pub init_acc_lp_fees_per_fee_liquidity: (LPFeePerFeeLiquidity, LPFeePerFeeLiquidity),
+            pub unwithdrawn_acc_lp_fees_per_fee_liquidity: (LPFeePerFeeLiquidity, LPFeePerFeeLiquidity),
+            pub init_sqrtprice: Float,
+            pub tick_bounds: (Tick, Tick),
+            pub phantom_t: PhantomData<T>,
+        },
+        1 => {
             /// Fee level index where the position is open
             pub fee_level: FeeLevel,
             /// Liquidity of the position
@@ -222,6 +504,11 @@ versioned! {
             pub init_sqrtprice: Float,
             /// Concentrated liquidity bounds
             pub tick_bounds: (Tick, Tick),
+            /// Account which locked position, e.g. lending contract which uses it as collateral.
+            /// Locked position cannot be closed.
+            pub locked_by: Option<AccountId>,
+            /// Moment until which position cannot be closed or moved, in seconds
+            pub locked_until: Option<u64>,
             /// Phantom data, to bind T and unify all state types declarations
             pub phantom_t: PhantomData<T>,
         }
@@ -0,0 +1,60 @@
+//! Structured trace records of pool math, to debug pricing anomalies on live networks
+//! without redeploying contract.
+//!
+//! Records are collected while pool is updated and flushed through `Logger::log`
+//! right before pool state event. Verbosity is stored in contract state and set by owner
+//! at runtime, see `Dex::set_trace_verbosity`.
+//!
+//! Exists only with `tracing` feature; without it, `trace_here!` expands to nothing.
+use std::cell::{Cell, RefCell};
+use std::fmt::Arguments;
+
+#[cfg(feature = "near")]
+use near_sdk::{
+    borsh::{self, BorshDeserialize, BorshSerialize},
+    serde::{Deserialize, Serialize},
+};
+
+/// Amount of details written into trace, each level includes all previous ones
+#[cfg_attr(
+    feature = "near",
+    derive(BorshDeserialize, BorshSerialize, Serialize, Deserialize)
+)]
+#[cfg_attr(
+    feature = "near",
+    serde(crate = "near_sdk::serde", rename_all = "snake_case")
+)]
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, PartialOrd, Ord)]
+pub enum TraceVerbosity {
+    /// Nothing is traced
+    #[default]
+    Off,
+    /// Tick crossings and fee level activations
+    Crossings,
+    /// Every step of swap loop
+    Steps,
+}
+
+thread_local! {
+    static VERBOSITY: Cell<TraceVerbosity> = const { Cell::new(TraceVerbosity::Off) };
+    static RECORDS: RefCell<Vec<String>> = const { RefCell::new(Vec::new()) };
+}
+
+/// Set verbosity of subsequent records, normally loaded from contract state on each call
+pub fn set_verbosity(verbosity: TraceVerbosity) {
+    VERBOSITY.with(|cell| cell.set(verbosity));
+}
+
+pub fn is_enabled(verbosity: TraceVerbosity) -> bool {
+    verbosity != TraceVerbosity::Off && VERBOSITY.with(Cell::get) >= verbosity
+}
+
+/// Append record to pending trace; use `trace_here!` instead of calling directly
+pub fn record(args: Arguments<'_>) {
+    RECORDS.with(|records| records.borrow_mut().push(std::fmt::format(args)));
+}
+
+/// Take all records collected since previous call
+pub fn take_records() -> Vec<String> {
+    RECORDS.with(|records| std::mem::take(&mut *records.borrow_mut()))
+}
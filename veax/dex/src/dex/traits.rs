@@ -1,14 +1,21 @@
 //! Traits and types which must be implemented and/or used
 //! to use Dex shared implementstion
 use std::fmt::Arguments;
+use std::iter::Take;
 use std::marker::PhantomData;
 use std::ops::Deref;
 
+use super::collection_helpers::MapRange;
 use super::errors::Result;
 use super::{
-    latest, Account, AccountLatest, BasisPoints, Contract, ContractLatest, FeeLevel, Float, Pool,
-    PoolId, PoolLatest, PoolUpdateReason, Position, PositionId, PositionLatest, Side, TickState,
-    TickStateV0,
+    latest, Account, AccountLatest, ActionHash, AdminAction, AdminLogEntry, AutoHarvestQueue,
+    BasisPoints, ClassicPool, ClassicPoolLatest, CleanupCursor, CodeHash, ConditionalOrder,
+    ConfigChange, Contract, ContractLatest, DepositIntent, EventFilter, EventKind, FeeAuction,
+    FeeLevel, Float, GasConfig, Integration, IntegrationId, LevelState, LevelStateLatest, LpToken,
+    LpTokenId, LpTokenLatest, PendingApproval, Pool, PoolId, PoolLatest, PoolStorageDeposit,
+    PoolUpdateReason, PoolV0, Position, PositionId, PositionLatest, ReconciledBalance,
+    ScheduledAction, SessionKey, Side, StablePool, StablePoolLatest, SwapGuard, SwapNotification,
+    TickState, TickStateV0, Vault, WithdrawLimit,
 };
 use crate::chain::{AccountId, Amount, LPFeePerFeeLiquidity, Liquidity, LiquiditySFP, TokenId};
 use crate::dex::tick::{EffTick, Tick};
@@ -73,9 +80,16 @@ pub trait Types: Sized {
     /// Extra data stored in each account entry, blockchain-specific
     type AccountExtra: PersistentCollection<Self::Bound> + Default + AccountExtra;
 
+    /// Extra data of accounts stored before version 1, converted on read
+    type AccountExtraV0: PersistentCollection<Self::Bound> + Into<Self::AccountExtra>;
+
     /// Map of liquidity pools indexed by pool identifier
     type PoolsMap: PersistentCollection<Self::Bound> + Map<Key = PoolId, Value = super::Pool<Self>>;
 
+    /// Per-pool map of fee level states indexed by fee level
+    type PoolLevelsMap: PersistentCollection<Self::Bound>
+        + Map<Key = FeeLevel, Value = super::LevelState<Self>>;
+
     /// Per-pool map of position records indexed by position ids
     type PoolPositionsMap: PersistentCollection<Self::Bound>
         + MapRemoveKey<Key = PositionId, Value = super::Position<Self>>;
@@ -90,6 +104,110 @@ pub trait Types: Sized {
     type PositionToPoolMap: PersistentCollection<Self::Bound>
         + MapRemoveKey<Key = PositionId, Value = PoolId>;
 
+    /// Mapping from position id to account which owns it
+    type PositionOwnersMap: PersistentCollection<Self::Bound>
+        + MapRemoveKey<Key = PositionId, Value = AccountId>;
+
+    /// Owner actions waiting for governance timelock to expire
+    type ScheduledActionsMap: PersistentCollection<Self::Bound>
+        + MapRemoveKey<Key = u64, Value = ScheduledAction>;
+
+    /// Owner actions proposed by admins, waiting for enough approvals
+    type PendingApprovalsMap: PersistentCollection<Self::Bound>
+        + MapRemoveKey<Key = ActionHash, Value = PendingApproval>;
+
+    /// Withdraw rate limits per token
+    type WithdrawLimitsMap: PersistentCollection<Self::Bound>
+        + MapRemoveKey<Key = TokenId, Value = WithdrawLimit>;
+
+    /// Sandwich guards per pool
+    type SwapGuardsMap: PersistentCollection<Self::Bound>
+        + MapRemoveKey<Key = PoolId, Value = SwapGuard>;
+
+    /// Insurance reserve amounts per token
+    type InsuranceReserveMap: PersistentCollection<Self::Bound>
+        + MapRemoveKey<Key = TokenId, Value = Amount>;
+
+    /// Minimal deposit amounts of newly opened positions, per pool
+    type MinDepositAmountsMap: PersistentCollection<Self::Bound>
+        + MapRemoveKey<Key = PoolId, Value = (Amount, Amount)>;
+
+    /// Constant product pools, indexed by pool identifier
+    type ClassicPoolsMap: PersistentCollection<Self::Bound>
+        + MapRemoveKey<Key = PoolId, Value = super::ClassicPool<Self>>;
+
+    /// Stable swap pools, indexed by pool identifier
+    type StablePoolsMap: PersistentCollection<Self::Bound>
+        + MapRemoveKey<Key = PoolId, Value = super::StablePool<Self>>;
+
+    /// Per-pool map of liquidity providers' shares, indexed by account ids
+    type PoolSharesMap: PersistentCollection<Self::Bound>
+        + MapRemoveKey<Key = AccountId, Value = Amount>;
+
+    /// Conditional orders waiting for keepers, by id
+    type OrdersMap: PersistentCollection<Self::Bound>
+        + MapRemoveKey<Key = u64, Value = ConditionalOrder>;
+
+    /// Dutch auctions of protocol fees, by sold token
+    type FeeAuctionsMap: PersistentCollection<Self::Bound>
+        + MapRemoveKey<Key = TokenId, Value = FeeAuction>;
+
+    /// Self-registered integrations, by id
+    type IntegrationsMap: PersistentCollection<Self::Bound>
+        + MapRemoveKey<Key = IntegrationId, Value = Integration>;
+
+    /// Deposit intents waiting for their legs, by id
+    type DepositIntentsMap: PersistentCollection<Self::Bound>
+        + MapRemoveKey<Key = u64, Value = DepositIntent>;
+
+    /// Auto-harvest rules of positions, by pool
+    type AutoHarvestMap: PersistentCollection<Self::Bound>
+        + MapRemoveKey<Key = PoolId, Value = AutoHarvestQueue>;
+
+    /// Accounts granted view access, by granting account
+    type ViewGrantsMap: PersistentCollection<Self::Bound>
+        + MapRemoveKey<Key = AccountId, Value = Vec<AccountId>>;
+
+    /// Pools by their aliases
+    type PoolAliasesMap: PersistentCollection<Self::Bound>
+        + MapRemoveKey<Key = String, Value = PoolId>;
+
+    /// Aliases by pools
+    type AliasesByPoolMap: PersistentCollection<Self::Bound>
+        + MapRemoveKey<Key = PoolId, Value = String>;
+
+    /// Recent administrative calls, by sequence number
+    type AdminLogMap: PersistentCollection<Self::Bound>
+        + MapRemoveKey<Key = u64, Value = AdminLogEntry>;
+
+    /// Withdraw protections of accounts which opted into vault mode
+    type VaultsMap: PersistentCollection<Self::Bound> + MapRemoveKey<Key = AccountId, Value = Vault>;
+
+    /// Session keys of accounts, which may only swap within daily volume caps
+    type SessionKeysMap: PersistentCollection<Self::Bound>
+        + MapRemoveKey<Key = AccountId, Value = Vec<SessionKey>>;
+
+    /// Fee-on-transfer tokens whose deposits are credited by contract's own balance delta,
+    /// with contract's balance tracked for each of them
+    type ReconciledTokensMap: PersistentCollection<Self::Bound>
+        + MapRemoveKey<Key = TokenId, Value = ReconciledBalance>;
+
+    /// Outcomes of checks whether token contracts implement fungible token interface
+    type TokenInterfacesMap: PersistentCollection<Self::Bound>
+        + MapRemoveKey<Key = TokenId, Value = bool>;
+
+    /// Native tokens paid for storage of pools by their creators
+    type PoolStorageDepositsMap: PersistentCollection<Self::Bound>
+        + MapRemoveKey<Key = PoolId, Value = PoolStorageDeposit>;
+
+    /// LP share tokens of managed full-range positions
+    #[cfg(feature = "lp-tokens")]
+    type LpTokensMap: PersistentCollection<Self::Bound>
+        + MapRemoveKey<Key = super::LpTokenId, Value = super::LpToken<Self>>;
+
+    /// Set of banned tokens
+    type BannedTokensSet: PersistentCollection<Self::Bound> + Set<Item = TokenId>;
+
     /// Set of accounts
     type AccountIdSet: PersistentCollection<Self::Bound> + Set<Item = AccountId>;
 
@@ -111,6 +229,14 @@ pub trait Types: Sized {
     #[cfg(feature = "smart-routing")]
     type TopPoolsMap: PersistentCollection<Self::Bound>
         + MapRemoveKey<Key = TokenId, Value = Self::TokensArraySet>;
+
+    /// Storage occupied by contract, in bytes; used to measure storage taken by operations.
+    /// Collections must write their records through to storage, and pool level states
+    /// must be flushed, before usage is measured after operation.
+    /// Blockchains which don't charge for storage may keep it zero
+    fn storage_usage() -> u64 {
+        0
+    }
 }
 
 /// Tracks withdrawals for each account, which may imply different confirmation strategies,
@@ -120,11 +246,90 @@ pub trait AccountWithdrawTracker {
     fn is_any_withdraw_in_progress(&self) -> bool;
     /// Check if specific token can't be unregistered due to unfinished withdraws
     fn is_token_withdraw_in_progress(&self, token_id: &TokenId) -> bool;
+    /// Unfinished withdraws as pairs of token id and amount, if tracker keeps them
+    fn pending_withdrawals(&self) -> Vec<(TokenId, Amount)> {
+        Vec::new()
+    }
+    /// Stop tracking withdraw which will never be finished, e.g. as its callback was lost
+    ///
+    /// # Returns
+    /// `false` if such withdraw isn't tracked
+    fn force_untrack(&mut self, _token_id: &TokenId, _amount: &Amount) -> bool {
+        false
+    }
 }
 /// Additional actions may need to be performed with `AccountExtra` data
 pub trait AccountExtra {
     /// Actions during pool creation
-    fn on_pool_created(&mut self) -> Result<()> {
+    ///
+    /// # Arguments
+    /// * `storage_bytes` - storage taken by pool, as measured with `Types::storage_usage`
+    ///
+    /// # Returns
+    /// Amount of native tokens charged for pool's storage
+    fn on_pool_created(&mut self, _storage_bytes: u64) -> Result<Amount> {
+        Ok(0)
+    }
+    /// Actions when account opens position
+    ///
+    /// # Arguments
+    /// * `storage_bytes` - storage taken by position, as measured with `Types::storage_usage`
+    fn on_position_opened(&mut self, _storage_bytes: u64) {}
+    /// Actions when account closes position
+    ///
+    /// # Arguments
+    /// * `storage_bytes` - storage released by position, as measured with `Types::storage_usage`
+    fn on_position_closed(&mut self, _storage_bytes: u64) {}
+    /// Actions when account stored before version 1 is converted to current layout
+    ///
+    /// # Arguments
+    /// * `position_count` - number of positions account had open, which storage wasn't measured
+    fn on_migrated(&mut self, _position_count: usize) {}
+    /// Actions when part of native tokens paid for pool's storage is returned to account
+    fn on_pool_storage_refunded(&mut self, _amount: Amount) {}
+    /// Actions when account places conditional order
+    fn on_order_placed(&mut self) -> Result<()> {
+        Ok(())
+    }
+    /// Actions when account's conditional order is executed or cancelled
+    fn on_order_removed(&mut self) {}
+    /// Actions when account registers integration
+    fn on_integration_registered(&mut self) -> Result<()> {
+        Ok(())
+    }
+    /// Actions when account prepares deposit intent
+    fn on_deposit_intent_prepared(&mut self) -> Result<()> {
+        Ok(())
+    }
+    /// Actions when account's deposit intent is executed or cancelled
+    fn on_deposit_intent_removed(&mut self) {}
+    /// Actions when account sets auto-harvest rule of position
+    fn on_auto_harvest_set(&mut self) -> Result<()> {
+        Ok(())
+    }
+    /// Actions when auto-harvest rule of account's position is removed
+    fn on_auto_harvest_removed(&mut self) {}
+    /// Actions when account grants view access to another account
+    fn on_view_access_granted(&mut self) -> Result<()> {
+        Ok(())
+    }
+    /// Actions when account revokes view access
+    fn on_view_access_revoked(&mut self) {}
+    /// Actions when account opts into vault mode
+    fn on_vault_set(&mut self) -> Result<()> {
+        Ok(())
+    }
+    /// Actions when account's vault is removed
+    fn on_vault_removed(&mut self) {}
+    /// Actions when account registers session key
+    fn on_session_key_registered(&mut self) -> Result<()> {
+        Ok(())
+    }
+    /// Actions when account revokes session key
+    fn on_session_key_revoked(&mut self) {}
+    /// Actions when account requests interface check of token which isn't checked yet,
+    /// as outcome of check is stored permanently
+    fn on_token_interface_check_requested(&mut self) -> Result<()> {
         Ok(())
     }
 }
@@ -204,11 +409,24 @@ pub trait StateMut<T: Types + ?Sized>: State<T> {
         unregister_token: bool,
         extra: Self::SendTokensExtraParam,
     ) -> Self::SendTokensResult;
+    /// Notifies swap hook contract about swap which exceeded configured threshold.
+    /// Notification is fire-and-forget: its outcome never affects the swap
+    fn notify_swap_hook(&mut self, hook_id: &AccountId, notification: SwapNotification);
     /// Retrieve account identifier which initiated whole chain of calls
     /// which constitutes transactions.
     fn get_initiator_id(&self) -> AccountId;
     /// Retrieve identifier of entity which called smart contract's method
     fn get_caller_id(&self) -> AccountId;
+    /// Retrieve timestamp of current block, in seconds
+    fn get_block_timestamp(&self) -> u64;
+    /// Retrieve height of current block
+    fn get_block_height(&self) -> u64;
+    /// Retrieve random seed of current block; it's the same for all calls within block,
+    /// and is predictable by block producer, so must not be used to protect any value
+    fn get_random_seed(&self) -> [u8; 32];
+    /// Retrieve public key which transaction was signed with, in its string representation;
+    /// `None` for state types which have no access keys
+    fn get_signer_public_key(&self) -> Option<String>;
     /// Make temporary mutable `Dex` instance out of `&mut self`
     fn as_dex_mut(&mut self) -> super::Dex<T, Self, &mut Self>
     where
@@ -225,10 +443,39 @@ pub trait ItemFactory<T: Types + ?Sized> {
     fn new_account_withdraw_tracker(&mut self) -> T::AccountWithdrawTracker;
     fn new_pools_map(&mut self) -> T::PoolsMap;
     fn new_pool_positions_map(&mut self) -> T::PoolPositionsMap;
+    fn new_pool_levels_map(&mut self) -> T::PoolLevelsMap;
     fn new_account_positions_set(&mut self) -> T::AccountPositionsSet;
     fn new_verified_tokens_set(&mut self) -> T::VerifiedTokensSet;
     fn new_position_to_pool_map(&mut self) -> T::PositionToPoolMap;
+    fn new_position_owners_map(&mut self) -> T::PositionOwnersMap;
     fn new_guards(&mut self) -> T::AccountIdSet;
+    fn new_scheduled_actions_map(&mut self) -> T::ScheduledActionsMap;
+    fn new_admins(&mut self) -> T::AccountIdSet;
+    fn new_pending_approvals_map(&mut self) -> T::PendingApprovalsMap;
+    fn new_withdraw_limits_map(&mut self) -> T::WithdrawLimitsMap;
+    fn new_swap_guards_map(&mut self) -> T::SwapGuardsMap;
+    fn new_insurance_reserve_map(&mut self) -> T::InsuranceReserveMap;
+    fn new_banned_tokens_set(&mut self) -> T::BannedTokensSet;
+    fn new_min_deposit_amounts_map(&mut self) -> T::MinDepositAmountsMap;
+    fn new_classic_pools_map(&mut self) -> T::ClassicPoolsMap;
+    fn new_stable_pools_map(&mut self) -> T::StablePoolsMap;
+    fn new_pool_shares_map(&mut self) -> T::PoolSharesMap;
+    fn new_orders_map(&mut self) -> T::OrdersMap;
+    fn new_fee_auctions_map(&mut self) -> T::FeeAuctionsMap;
+    fn new_integrations_map(&mut self) -> T::IntegrationsMap;
+    fn new_deposit_intents_map(&mut self) -> T::DepositIntentsMap;
+    fn new_auto_harvest_map(&mut self) -> T::AutoHarvestMap;
+    fn new_view_grants_map(&mut self) -> T::ViewGrantsMap;
+    fn new_pool_aliases_map(&mut self) -> T::PoolAliasesMap;
+    fn new_aliases_by_pool_map(&mut self) -> T::AliasesByPoolMap;
+    fn new_pool_storage_deposits_map(&mut self) -> T::PoolStorageDepositsMap;
+    fn new_admin_log_map(&mut self) -> T::AdminLogMap;
+    fn new_vaults_map(&mut self) -> T::VaultsMap;
+    fn new_session_keys_map(&mut self) -> T::SessionKeysMap;
+    fn new_reconciled_tokens_map(&mut self) -> T::ReconciledTokensMap;
+    fn new_token_interfaces_map(&mut self) -> T::TokenInterfacesMap;
+    #[cfg(feature = "lp-tokens")]
+    fn new_lp_tokens_map(&mut self) -> T::LpTokensMap;
     #[cfg(feature = "smart-routing")]
     fn new_token_connections_map(&mut self) -> T::TokenConnectionsMap;
     #[cfg(feature = "smart-routing")]
@@ -248,7 +495,7 @@ pub trait ItemFactory<T: Types + ?Sized> {
             fee_rates == [1, 2, 4, 8, 16, 32, 64, 128],
             ErrorKind::InvalidParams
         );
-        Ok(Contract::V0(ContractLatest {
+        Ok(Contract::V1(ContractLatest {
             owner_id,
             guards: self.new_guards(),
             suspended: false,
@@ -258,45 +505,194 @@ pub trait ItemFactory<T: Types + ?Sized> {
             pool_count: 0,
             next_free_position_id: 0,
             position_to_pool_id: self.new_position_to_pool_map().into(),
+            position_owners: self.new_position_owners_map().into(),
             protocol_fee_fraction,
+            swap_hook: None,
+            gas_config: GasConfig::default(),
+            timelock_delay: 0,
+            scheduled_actions: self.new_scheduled_actions_map().into(),
+            next_scheduled_action_id: 0,
+            admins: self.new_admins(),
+            admin_threshold: 0,
+            pending_approvals: self.new_pending_approvals_map().into(),
+            staged_upgrade: None,
+            withdraw_limits: self.new_withdraw_limits_map().into(),
+            swap_guards: self.new_swap_guards_map().into(),
+            withdraw_limits_disabled: false,
+            insurance_fee_fraction: 0,
+            insurance_reserve: self.new_insurance_reserve_map().into(),
+            banned_tokens: self.new_banned_tokens_set(),
+            min_position_liquidity: latest::MIN_LIQUIDITY,
+            min_deposit_amounts: self.new_min_deposit_amounts_map().into(),
+            #[cfg(feature = "tracing")]
+            trace_verbosity: super::TraceVerbosity::Off,
+            event_filter: EventFilter::default(),
+            classic_pools: self.new_classic_pools_map().into(),
+            stable_pools: self.new_stable_pools_map().into(),
+            orders: self.new_orders_map().into(),
+            next_order_id: 0,
+            fee_auction_config: None,
+            fee_auctions: self.new_fee_auctions_map().into(),
+            integrations: self.new_integrations_map().into(),
+            next_integration_id: 0,
+            deposit_intents: self.new_deposit_intents_map().into(),
+            next_deposit_intent_id: 0,
+            auto_harvest: self.new_auto_harvest_map().into(),
+            view_grants: self.new_view_grants_map().into(),
+            pool_aliases: self.new_pool_aliases_map().into(),
+            aliases_by_pool: self.new_aliases_by_pool_map().into(),
+            pool_storage_deposits: self.new_pool_storage_deposits_map().into(),
+            pool_storage_deposits_total: 0,
+            pool_storage_bytes_total: 0,
+            admin_log: self.new_admin_log_map().into(),
+            next_admin_log_id: 0,
+            vaults: self.new_vaults_map().into(),
+            session_keys: self.new_session_keys_map().into(),
+            reconciled_tokens: self.new_reconciled_tokens_map().into(),
+            pool_price_init_open: false,
+            seed_period: None,
+            token_interface_check: false,
+            token_interfaces: self.new_token_interfaces_map().into(),
+            #[cfg(feature = "lp-tokens")]
+            lp_tokens: self.new_lp_tokens_map().into(),
             #[cfg(feature = "smart-routing")]
             token_connections: self.new_token_connections_map().into(),
             #[cfg(feature = "smart-routing")]
             top_pools: self.new_top_pools_map().into(),
+            #[cfg(feature = "smart-routing")]
+            route_cache: Vec::new(),
             extra: T::ContractExtra::default(),
         }))
     }
 
     fn new_account(&mut self) -> Result<Account<T>> {
-        Ok(Account::V0(AccountLatest {
+        Ok(Account::V1(AccountLatest {
             token_balances: self.new_account_token_balances_map().into(),
             positions: self.new_account_positions_set(),
             withdraw_tracker: self.new_account_withdraw_tracker(),
             extra: Default::default(),
+            registration_cleanup_allowed: false,
         }))
     }
 
     fn new_pool(&mut self) -> Result<Pool<T>> {
-        Ok(Pool::V0(PoolLatest {
+        let mut levels = self.new_pool_levels_map();
+        let level_states = (0..latest::NUM_FEE_LEVELS)
+            .map(|fee_level| Ok((fee_level, self.new_level_state()?)))
+            .collect::<Result<Vec<_>>>()?;
+        levels.insert_many(level_states);
+        Ok(Pool::V1(PoolLatest {
             total_reserves: (Amount::zero(), Amount::zero()),
             positions: self.new_pool_positions_map().into(),
-            eff_sqrtprices: latest::FeeLevelsArray::default(),
-            acc_lp_fees_per_fee_liquidity: latest::FeeLevelsArray::from_value((
-                LPFeePerFeeLiquidity::zero(),
-                LPFeePerFeeLiquidity::zero(),
-            )),
             acc_lp_fee: (AmountUFP::zero(), AmountUFP::zero()),
+            stats: latest::PoolStats::default(),
+            level_volumes_in: latest::FeeLevelsArray::default(),
             tick_states: latest::FeeLevelsArray::from_fn(|_| self.new_tick_states_map().into()),
-            net_liquidities: latest::FeeLevelsArray::default(),
-            position_reserves: latest::FeeLevelsArray::from_value((
-                AmountUFP::zero(),
-                AmountUFP::zero(),
-            )),
-            next_active_ticks_left: latest::FeeLevelsArray::default(),
-            next_active_ticks_right: latest::FeeLevelsArray::default(),
+            levels: latest::LevelStates::new(levels),
             top_active_level: 0,
             active_side: Side::Left,
             pivot: EffTick::default(),
+            cleanup_cursor: None,
+            #[cfg(feature = "smart-routing")]
+            ranked_liquidity: Liquidity::zero(),
+            #[cfg(feature = "batch-auction")]
+            batch_auction: None,
+            seed_until: 0,
+        }))
+    }
+
+    /// Converts pool stored before version 1, moving its fee level states to separate records
+    fn migrate_pool(&mut self, legacy: PoolV0<T>) -> Result<Pool<T>> {
+        let position_reserves = RawFeeLevelsArray::from(legacy.position_reserves);
+        let acc_lp_fees_per_fee_liquidity =
+            RawFeeLevelsArray::from(legacy.acc_lp_fees_per_fee_liquidity);
+        let eff_sqrtprices = RawFeeLevelsArray::from(legacy.eff_sqrtprices);
+        let next_active_ticks_left = RawFeeLevelsArray::from(legacy.next_active_ticks_left);
+        let next_active_ticks_right = RawFeeLevelsArray::from(legacy.next_active_ticks_right);
+        let net_liquidities = RawFeeLevelsArray::from(legacy.net_liquidities);
+        let mut levels = self.new_pool_levels_map();
+        levels.insert_many((0..latest::NUM_FEE_LEVELS).map(|fee_level| {
+            let index = usize::from(fee_level);
+            let level_state = LevelState::V0(LevelStateLatest {
+                position_reserves: position_reserves[index],
+                // Per-level fee totals weren't tracked, so they're counted from migration
+                acc_lp_fees: (AmountUFP::zero(), AmountUFP::zero()),
+                acc_lp_fees_per_fee_liquidity: acc_lp_fees_per_fee_liquidity[index],
+                eff_sqrtprice: eff_sqrtprices[index],
+                next_active_tick_left: next_active_ticks_left[index],
+                next_active_tick_right: next_active_ticks_right[index],
+                net_liquidity: net_liquidities[index],
+                phantom_t: PhantomData,
+            });
+            (fee_level, level_state)
+        }));
+        Ok(Pool::V1(PoolLatest {
+            positions: legacy.positions,
+            tick_states: legacy.tick_states,
+            total_reserves: legacy.total_reserves,
+            acc_lp_fee: legacy.acc_lp_fee,
+            stats: latest::PoolStats::default(),
+            level_volumes_in: latest::FeeLevelsArray::default(),
+            levels: latest::LevelStates::new(levels),
+            top_active_level: legacy.top_active_level,
+            active_side: legacy.active_side,
+            pivot: legacy.pivot,
+            cleanup_cursor: None,
+            #[cfg(feature = "smart-routing")]
+            ranked_liquidity: Liquidity::zero(),
+            #[cfg(feature = "batch-auction")]
+            batch_auction: None,
+            seed_until: 0,
+        }))
+    }
+
+    fn new_classic_pool(&mut self, fee_rate: BasisPoints) -> Result<ClassicPool<T>> {
+        Ok(ClassicPool::V0(ClassicPoolLatest {
+            reserves: (Amount::zero(), Amount::zero()),
+            fee_rate,
+            protocol_fees: (Amount::zero(), Amount::zero()),
+            total_shares: Amount::zero(),
+            shares: self.new_pool_shares_map().into(),
+        }))
+    }
+
+    fn new_stable_pool(
+        &mut self,
+        fee_rate: BasisPoints,
+        amplification: u32,
+    ) -> Result<StablePool<T>> {
+        Ok(StablePool::V0(StablePoolLatest {
+            reserves: (Amount::zero(), Amount::zero()),
+            amplification,
+            fee_rate,
+            protocol_fees: (Amount::zero(), Amount::zero()),
+            total_shares: Amount::zero(),
+            shares: self.new_pool_shares_map().into(),
+        }))
+    }
+
+    fn new_lp_token(&mut self) -> LpToken<T> {
+        LpToken::V0(LpTokenLatest {
+            position_id: None,
+            idle_amounts: (Amount::zero(), Amount::zero()),
+            total_supply: Amount::zero(),
+            balances: self.new_pool_shares_map().into(),
+        })
+    }
+
+    fn new_level_state(&mut self) -> Result<LevelState<T>> {
+        Ok(LevelState::V0(LevelStateLatest {
+            position_reserves: (AmountUFP::zero(), AmountUFP::zero()),
+            acc_lp_fees: (AmountUFP::zero(), AmountUFP::zero()),
+            acc_lp_fees_per_fee_liquidity: (
+                LPFeePerFeeLiquidity::zero(),
+                LPFeePerFeeLiquidity::zero(),
+            ),
+            eff_sqrtprice: latest::EffectiveSqrtPrice::default(),
+            next_active_tick_left: None,
+            next_active_tick_right: None,
+            net_liquidity: Liquidity::zero(),
+            phantom_t: PhantomData,
         }))
     }
 
@@ -308,13 +704,15 @@ pub trait ItemFactory<T: Types + ?Sized> {
         ticks_range: (Tick, Tick),
         init_sqrtprice: Float,
     ) -> Result<Position<T>> {
-        Ok(Position::V0(PositionLatest {
+        Ok(Position::V1(PositionLatest {
             fee_level,
             net_liquidity,
             init_sqrtprice,
             init_acc_lp_fees_per_fee_liquidity,
             tick_bounds: ticks_range,
             unwithdrawn_acc_lp_fees_per_fee_liquidity: init_acc_lp_fees_per_fee_liquidity,
+            locked_by: None,
+            locked_until: None,
             phantom_t: PhantomData,
         }))
     }
@@ -451,6 +849,45 @@ pub trait Map {
         U: FnOnce(&mut Self::Value, /* exists */ bool) -> Result<R>;
     /// Insert new value into map container
     fn insert(&mut self, key: Self::Key, value: Self::Value);
+    /// Insert all key-value pairs into map container
+    ///
+    /// Default implementation inserts pairs one by one; maps which can insert
+    /// in bulk more efficiently should override it.
+    fn insert_many<I: IntoIterator<Item = (Self::Key, Self::Value)>>(&mut self, entries: I) {
+        for (key, value) in entries {
+            self.insert(key, value);
+        }
+    }
+    /// Iterate over map's key-value pairs in iteration order, starting from `from` key (inclusive)
+    /// and stopping before `to` key; missing bound means start or end of map respectively
+    ///
+    /// NB: bounds are positions in iteration order, so for maps which aren't ordered by keys,
+    /// e.g. linked lists on chain, range isn't the set of keys between the bounds.
+    ///
+    /// Default implementation walks the map from its beginning; maps which can start
+    /// iteration at arbitrary key should override it.
+    /// Iterator is empty if `from` key isn't present in map.
+    fn range<'a>(
+        &'a self,
+        from: Option<&Self::Key>,
+        to: Option<&Self::Key>,
+    ) -> MapRange<Self::Key, Self::Iter<'a>>
+    where
+        Self::Key: Clone + PartialEq,
+    {
+        MapRange::new(self.iter(), from.cloned(), to.cloned())
+    }
+    /// Iterate over map's key-value pairs in iteration order, starting from specified key (inclusive)
+    fn iter_from<'a>(&'a self, key: &Self::Key) -> MapRange<Self::Key, Self::Iter<'a>>
+    where
+        Self::Key: Clone + PartialEq,
+    {
+        self.range(Some(key), None)
+    }
+    /// Iterate over at most `n` first key-value pairs of map
+    fn take_n(&self, n: usize) -> Take<Self::Iter<'_>> {
+        self.iter().take(n)
+    }
 }
 
 pub trait MapRemoveKey: Map {
@@ -606,6 +1043,12 @@ pub trait OrderedMap: MapRemoveKey {
 
 /// `EventEmitter` hides platform-specific event API and/or custom event formatting.
 pub trait Logger {
+    /// Checks whether events of specified kind are emitted, so callers can skip
+    /// preparing payloads of disabled events
+    fn is_event_enabled(&self, _kind: EventKind) -> bool {
+        true
+    }
+
     fn log(&mut self, args: Arguments<'_>);
     fn log_deposit_event(
         &mut self,
@@ -621,6 +1064,7 @@ pub trait Logger {
         amount: &Amount,
         balance: &Amount,
     );
+    #[allow(clippy::too_many_arguments)]
     fn log_open_position_event(
         &mut self,
         user: &AccountId,
@@ -628,16 +1072,115 @@ pub trait Logger {
         amounts: (&Amount, &Amount),
         fee_rate: BasisPoints,
         position_id: PositionId,
+        liquidity: &Liquidity,
+        fee_level: FeeLevel,
+        tick_bounds: (Tick, Tick),
+        net_liquidity: &Liquidity,
+        init_sqrtprice: Float,
+    );
+    fn log_harvest_fee_event(
+        &mut self,
+        user: &AccountId,
+        position_id: PositionId,
+        fee_amounts: (Amount, Amount),
+    );
+    /// `harvests` are fee amounts per position, in order of position's tokens
+    fn log_harvest_all_fees_event(
+        &mut self,
+        user: &AccountId,
+        harvests: &[(PositionId, (Amount, Amount))],
+    );
+    fn log_close_position_event(
+        &mut self,
+        user: &AccountId,
+        position_id: PositionId,
+        amounts: (Amount, Amount),
+    );
+    #[allow(clippy::too_many_arguments)]
+    fn log_move_position_event(
+        &mut self,
+        user: &AccountId,
+        position_id: PositionId,
+        pool: (&TokenId, &TokenId),
+        tick_bounds: (Tick, Tick),
+        withdrawn: (Amount, Amount),
+        deposited: (Amount, Amount),
+        net_liquidity: &Liquidity,
+    );
+    fn log_order_placed_event(&mut self, id: u64, order: &ConditionalOrder);
+    fn log_order_executed_event(
+        &mut self,
+        id: u64,
+        keeper: &AccountId,
+        order: &ConditionalOrder,
+        amount_out: Amount,
+    );
+    fn log_order_cancelled_event(&mut self, id: u64, caller: &AccountId, order: &ConditionalOrder);
+    fn log_fee_auction_update_event(&mut self, token: &TokenId, auction: &FeeAuction);
+    fn log_fee_auction_take_event(
+        &mut self,
+        user: &AccountId,
+        token: &TokenId,
+        auction: &FeeAuction,
+        amount: Amount,
+        quote_amount: Amount,
+    );
+    /// `balances` are balances of sender and receiver after transfer
+    fn log_transfer_internal_event(
+        &mut self,
+        sender: &AccountId,
+        receiver: &AccountId,
+        token: &TokenId,
+        amount: Amount,
+        balances: (Amount, Amount),
+    );
+    fn log_force_untrack_withdraw_event(
+        &mut self,
+        caller: &AccountId,
+        user: &AccountId,
+        token: &TokenId,
+        amount: Amount,
+    );
+    /// `code_hash` is `None` if staged upgrade is cancelled
+    fn log_upgrade_staged_event(&mut self, caller: &AccountId, code_hash: Option<&CodeHash>);
+    fn log_upgrade_event(&mut self, caller: &AccountId, code_hash: &CodeHash);
+    /// `amounts` are ordered as pool tokens
+    fn log_lp_token_mint_event(
+        &mut self,
+        user: &AccountId,
+        token: &LpTokenId,
+        amounts: (Amount, Amount),
+        amount: Amount,
+    );
+    /// `amounts` are ordered as pool tokens
+    fn log_lp_token_burn_event(
+        &mut self,
+        user: &AccountId,
+        token: &LpTokenId,
+        amounts: (Amount, Amount),
+        amount: Amount,
+    );
+    fn log_lp_token_transfer_event(
+        &mut self,
+        sender: &AccountId,
+        receiver: &AccountId,
+        token: &LpTokenId,
+        amount: Amount,
+        memo: Option<&str>,
     );
-    fn log_harvest_fee_event(&mut self, position_id: PositionId, fee_amounts: (Amount, Amount));
-    fn log_close_position_event(&mut self, position_id: PositionId, amounts: (Amount, Amount));
     fn log_swap_event(
         &mut self,
         user: &AccountId,
         tokens: (&TokenId, &TokenId),
         amounts: (&Amount, &Amount),
         fees: &[(&TokenId, &BasisPoints)],
+        integration_id: Option<IntegrationId>,
     );
+    /// Logs per-level pool state after update
+    ///
+    /// `changed_levels` marks levels changed by update, so logger may report only them;
+    /// `None` if previous state is unknown and all levels should be reported
+    #[allow(clippy::too_many_arguments)]
     fn log_update_pool_state_event(
         &mut self,
         reason: PoolUpdateReason,
@@ -646,14 +1189,63 @@ pub trait Logger {
         amounts_b: &RawFeeLevelsArray<Amount>,
         sqrt_prices: &RawFeeLevelsArray<Float>,
         liquidities: &RawFeeLevelsArray<Float>,
+        lp_fees: (&RawFeeLevelsArray<Amount>, &RawFeeLevelsArray<Amount>),
+        top_active_level: FeeLevel,
+        active_side: Side,
+        changed_levels: Option<&RawFeeLevelsArray<bool>>,
     );
 
     fn log_add_verified_tokens_event(&mut self, tokens: &[TokenId]);
     fn log_remove_verified_tokens_event(&mut self, tokens: &[TokenId]);
+    fn log_ban_tokens_event(&mut self, tokens: &[TokenId]);
+    fn log_unban_tokens_event(&mut self, tokens: &[TokenId]);
+
+    fn log_add_guard_accounts_event(&mut self, caller: &AccountId, accounts: &[AccountId]);
+    fn log_remove_guard_accounts_event(&mut self, caller: &AccountId, accounts: &[AccountId]);
 
-    fn log_add_guard_accounts_event(&mut self, tokens: &[AccountId]);
-    fn log_remove_guard_accounts_event(&mut self, tokens: &[AccountId]);
+    fn log_lock_position_event(&mut self, position_id: PositionId, locker: &AccountId);
+    fn log_unlock_position_event(&mut self, position_id: PositionId, locker: &AccountId);
+    fn log_lock_position_until_event(
+        &mut self,
+        user: &AccountId,
+        position_id: PositionId,
+        locked_until: u64,
+    );
 
     fn log_suspend_payable_api_event(&mut self, account: &AccountId);
     fn log_resume_payable_api_event(&mut self, account: &AccountId);
+
+    fn log_action_scheduled_event(&mut self, id: u64, action: &AdminAction, execute_after: u64);
+    fn log_action_executed_event(&mut self, id: u64, action: &AdminAction);
+    fn log_action_cancelled_event(&mut self, id: u64, action: &AdminAction);
+    fn log_action_approved_event(
+        &mut self,
+        hash: &ActionHash,
+        action: &AdminAction,
+        approver: &AccountId,
+        approvals: u16,
+    );
+
+    fn log_pool_storage_cleanup_event(
+        &mut self,
+        pool_id: &PoolId,
+        removed_ticks: &[(FeeLevel, Tick)],
+        next_cursor: Option<&CleanupCursor>,
+    );
+    fn log_token_registrations_cleanup_event(&mut self, user: &AccountId, tokens: &[TokenId]);
+
+    fn log_withdraw_protocol_fee_event(
+        &mut self,
+        pool: (&TokenId, &TokenId),
+        amounts: (&Amount, &Amount),
+    );
+    fn log_owner_withdraw_event(&mut self, token: &TokenId, amount: &Amount);
+
+    fn log_set_protocol_fee_fraction_event(
+        &mut self,
+        caller: &AccountId,
+        previous: BasisPoints,
+        new: BasisPoints,
+    );
+    fn log_config_change_event(&mut self, caller: &AccountId, change: &ConfigChange);
 }
@@ -1,9 +1,10 @@
 use super::utils::swap_if;
-use super::{latest, BasisPoints, ErrorKind as DexErrorKind, Float, PositionId, WasmApi};
+use super::{latest, BasisPoints, ErrorKind as DexErrorKind, FeeLevel, Float, PositionId, WasmApi};
 use crate::chain::wasm::WasmAmount;
-use crate::chain::{Amount, Liquidity, TokenId};
-use crate::dex::tick::Tick;
+use crate::chain::{AccountId, Amount, AmountUFP, LPFeePerFeeLiquidity, Liquidity, TokenId};
+use crate::dex::tick::{EffTick, Tick};
 use crate::ensure;
+use num_traits::Zero;
 use std::ops::{Deref, Index, IndexMut};
 
 #[cfg(feature = "near")]
@@ -58,12 +59,44 @@ impl From<PoolId> for (TokenId, TokenId) {
     }
 }
 
-#[cfg_attr(feature = "near", derive(Serialize, Deserialize))]
+#[cfg_attr(
+    feature = "near",
+    derive(BorshDeserialize, BorshSerialize, Serialize, Deserialize)
+)]
 #[cfg_attr(feature = "near", serde(crate = "near_sdk::serde"))]
 #[derive(Clone, Debug)]
 pub struct PositionInit {
     pub amount_ranges: (Range<WasmAmount>, Range<WasmAmount>),
     pub ticks_range: (Option<i32>, Option<i32>),
+    /// Position is funded with single token: exactly one of `amount_ranges` must have non-zero `max`.
+    /// Part of it is swapped in the pool into the other token at current price, charging normal fees,
+    /// and `min` amounts are checked against amounts actually deposited into position
+    #[cfg_attr(feature = "near", serde(default))]
+    pub auto_balance: bool,
+    /// Moment until which position cannot be closed or moved, in seconds, e.g. to commit
+    /// protocol-owned liquidity or liquidity of launch partner; `None` if position isn't locked
+    #[cfg_attr(feature = "near", serde(default))]
+    pub locked_until: Option<u64>,
+}
+
+/// Position funded by single token received by `ft_on_transfer`
+#[cfg_attr(
+    feature = "near",
+    derive(BorshDeserialize, BorshSerialize, Serialize, Deserialize)
+)]
+#[cfg_attr(feature = "near", serde(crate = "near_sdk::serde"))]
+#[derive(Clone, Debug)]
+pub struct OpenPositionSingleSided {
+    /// Other token of the pool
+    pub token_id: TokenId,
+    pub fee_rate: BasisPoints,
+    pub ticks_range: (Option<i32>, Option<i32>),
+    /// Minimal amounts of transferred and other token actually put into position
+    pub min_amounts: (WasmAmount, WasmAmount),
+    /// Whether to swap part of transferred token into the other one,
+    /// see `PositionInit::auto_balance`
+    #[cfg_attr(feature = "near", serde(default))]
+    pub auto_balance: bool,
 }
 
 impl PositionInit {
@@ -85,6 +118,8 @@ impl PositionInit {
                 },
             ),
             ticks_range: (None, None),
+            auto_balance: false,
+            locked_until: None,
         }
     }
 
@@ -100,11 +135,16 @@ impl PositionInit {
             } else {
                 self.ticks_range
             },
+            auto_balance: self.auto_balance,
+            locked_until: self.locked_until,
         }
     }
 }
 
-#[cfg_attr(feature = "near", derive(Serialize, Deserialize))]
+#[cfg_attr(
+    feature = "near",
+    derive(BorshDeserialize, BorshSerialize, Serialize, Deserialize)
+)]
 #[cfg_attr(
     feature = "near",
     serde(
@@ -119,9 +159,13 @@ pub struct Range<T: std::fmt::Debug + WasmApi> {
 }
 
 #[derive(Copy, Clone, PartialEq, Eq, Default)]
-#[cfg_attr(not(target_arch = "wasm32"), derive(Debug))]
+#[cfg_attr(any(not(target_arch = "wasm32"), feature = "tracing"), derive(Debug))]
 #[cfg_attr(feature = "near", derive(BorshDeserialize, BorshSerialize))]
-#[cfg_attr(feature = "test-utils", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "near", derive(Serialize, Deserialize))]
+#[cfg_attr(
+    feature = "near",
+    serde(crate = "near_sdk::serde", rename_all = "snake_case")
+)]
 pub enum Side {
     #[default]
     Left,
@@ -194,7 +238,7 @@ impl Exact {
 /// This type of actions can be passed only as message payload during `deposit`.
 #[cfg_attr(
     feature = "near",
-    derive(Serialize, Deserialize),
+    derive(BorshDeserialize, BorshSerialize, Serialize, Deserialize),
     serde(
         crate = "near_sdk::serde",
         bound(deserialize = "E: for<'d> Deserialize<'d>")
@@ -225,9 +269,32 @@ pub enum Action<E: 'static + Sized + super::WasmApi> {
     ClosePosition(PositionId),
     /// Withdraw fees collected on specific position. User must own it
     WithdrawFee(PositionId),
+    /// Move deposited token to another account, which must have the token registered
+    TransferInternal {
+        receiver_id: AccountId,
+        token_id: TokenId,
+        amount: WasmAmount,
+    },
+    /// Perform swap-in exchange of tokens and immediately withdraw whole output amount
+    /// to account's wallet, so tokens never stay on account balance
+    SwapAndWithdraw {
+        token_in: TokenId,
+        token_out: TokenId,
+        /// Amount to exchange; if `None`, output of previous swap-in step is used
+        amount: Option<WasmAmount>,
+        /// Min out amount
+        amount_limit: WasmAmount,
+        /// Blockchain-specific parameter of withdrawal, same as in `Withdraw`
+        receiver: E,
+    },
+    /// Withdraw fees collected on all positions of user
+    WithdrawAllFees,
 }
 
-#[cfg_attr(feature = "near", derive(Serialize, Deserialize))]
+#[cfg_attr(
+    feature = "near",
+    derive(BorshDeserialize, BorshSerialize, Serialize, Deserialize)
+)]
 #[cfg_attr(feature = "near", serde(crate = "near_sdk::serde"))]
 #[derive(Clone, Debug)]
 pub struct SwapAction {
@@ -243,6 +310,888 @@ pub struct SwapAction {
     /// For exact-in swap this is min out amount.
     /// For exact-out swap this is max in amount.
     pub amount_limit: WasmAmount,
+    /// Integration which routed the swap, echoed in swap event; see `register_integration`
+    #[cfg_attr(
+        feature = "near",
+        serde(default, skip_serializing_if = "Option::is_none")
+    )]
+    pub integration_id: Option<IntegrationId>,
+}
+
+/// External contract which is notified about swaps above configured size,
+/// e.g. for large-trade surveillance required in some jurisdictions
+#[cfg_attr(
+    feature = "near",
+    derive(BorshDeserialize, BorshSerialize, Serialize, Deserialize)
+)]
+#[cfg_attr(feature = "near", serde(crate = "near_sdk::serde"))]
+#[derive(Clone, Debug)]
+pub struct SwapHook {
+    /// Contract which receives notifications
+    pub hook_id: AccountId,
+    /// Minimal amounts of input tokens which trigger notification.
+    /// Swaps of tokens not listed here never trigger notification.
+    pub thresholds: Vec<(TokenId, WasmAmount)>,
+}
+
+impl SwapHook {
+    /// Checks whether swap of `amount_in` of `token_in` should be reported to hook
+    pub fn is_triggered(&self, token_in: &TokenId, amount_in: Amount) -> bool {
+        self.thresholds
+            .iter()
+            .any(|(token, threshold)| token == token_in && amount_in >= threshold.0)
+    }
+}
+
+/// Gas attached to cross-contract calls, in Tgas; tuned by owner when token contracts
+/// change their behavior, without redeploying contract
+#[cfg_attr(
+    feature = "near",
+    derive(BorshDeserialize, BorshSerialize, Serialize, Deserialize)
+)]
+#[cfg_attr(feature = "near", serde(crate = "near_sdk::serde"))]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct GasConfig {
+    /// Gas for `ft_transfer` of withdrawn tokens
+    pub ft_transfer: u64,
+    /// Gas for callbacks which resolve transfers
+    pub resolve_transfer: u64,
+    /// Gas for notification of swap hook
+    pub swap_hook: u64,
+}
+
+impl Default for GasConfig {
+    fn default() -> Self {
+        Self {
+            // Increased to 20T to support AS token contracts
+            ft_transfer: 20,
+            resolve_transfer: 20,
+            swap_hook: 10,
+        }
+    }
+}
+
+/// Owner action which is subject to governance timelock
+#[cfg_attr(
+    feature = "near",
+    derive(BorshDeserialize, BorshSerialize, Serialize, Deserialize)
+)]
+#[cfg_attr(
+    feature = "near",
+    serde(crate = "near_sdk::serde", rename_all = "snake_case")
+)]
+#[derive(Clone, Debug)]
+pub enum AdminAction {
+    /// Change fraction of the total fee which goes to DEX
+    SetProtocolFeeFraction(BasisPoints),
+    /// Remove tokens from verified tokens list
+    RemoveVerifiedTokens(Vec<TokenId>),
+    /// Change timelock delay, in seconds
+    SetTimelockDelay(u64),
+    /// Replace accounts which collectively act as owner, and number of approvals they need
+    /// to perform owner action; zero threshold disables multisig
+    SetAdmins {
+        admins: Vec<AccountId>,
+        threshold: u16,
+    },
+    /// Change amplification coefficient of stable swap pool
+    SetStableAmplification {
+        tokens: (TokenId, TokenId),
+        amplification: u32,
+    },
+    /// Enable Dutch auctions of protocol fees with specified parameters, or disable them
+    SetFeeAuctionConfig(Option<FeeAuctionConfig>),
+    /// Allow upgrade to contract code with specified hash, or cancel staged upgrade
+    StageUpgrade(Option<CodeHash>),
+    /// Set withdraw rate limit for token, or remove it
+    SetWithdrawLimit {
+        token: TokenId,
+        config: Option<WithdrawLimitConfig>,
+    },
+}
+
+/// Kind of liquidity pool, selected at pool creation
+#[cfg_attr(feature = "near", derive(Serialize, Deserialize))]
+#[cfg_attr(
+    feature = "near",
+    serde(crate = "near_sdk::serde", rename_all = "snake_case")
+)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum PoolKind {
+    /// Concentrated liquidity over multiple fee levels, with positions bounded by ticks
+    Concentrated,
+    /// Constant product `x*y=k` over whole price range, with liquidity tracked as shares;
+    /// simpler and cheaper for long-tail tokens where ticks are overkill
+    ClassicXY,
+    /// Curve-style stable swap invariant with amplification coefficient, liquidity tracked as shares;
+    /// concentrates liquidity around 1:1 price for pegged tokens, e.g. USDC/USDT
+    Stable,
+}
+
+/// Change of contract configuration parameter, reported in events for audit purposes
+#[cfg_attr(feature = "near", derive(Serialize))]
+#[cfg_attr(
+    feature = "near",
+    serde(
+        crate = "near_sdk::serde",
+        rename_all = "snake_case",
+        tag = "parameter"
+    )
+)]
+#[derive(Clone, Debug)]
+pub enum ConfigChange {
+    /// Fraction of protocol fee which goes to insurance reserve
+    InsuranceFeeFraction {
+        previous: BasisPoints,
+        new: BasisPoints,
+    },
+    /// Timelock delay of owner actions, in seconds
+    TimelockDelay { previous: u64, new: u64 },
+    /// Guard override of withdraw rate limits
+    WithdrawLimitsDisabled { previous: bool, new: bool },
+    /// Withdraw rate limit of token; `None` if token isn't limited
+    WithdrawLimit {
+        token: TokenId,
+        previous: Option<WithdrawLimitConfig>,
+        new: Option<WithdrawLimitConfig>,
+    },
+    /// Whether events of specific kind are emitted
+    EventEnabled {
+        event: EventKind,
+        previous: bool,
+        new: bool,
+    },
+    /// Amplification coefficient of stable swap pool
+    StableAmplification {
+        pool: (TokenId, TokenId),
+        previous: u32,
+        new: u32,
+    },
+    /// Parameters of protocol fee auctions; `None` if auctions are disabled
+    FeeAuctionConfig {
+        previous: Option<FeeAuctionConfig>,
+        new: Option<FeeAuctionConfig>,
+    },
+    /// Sandwich guard of pool; `None` if pool isn't guarded
+    SwapGuard {
+        pool: (TokenId, TokenId),
+        previous: Option<SwapGuardMode>,
+        new: Option<SwapGuardMode>,
+    },
+    /// Gas attached to cross-contract calls
+    GasConfig { previous: GasConfig, new: GasConfig },
+    /// Pool which alias refers to; `None` if alias isn't registered
+    PoolAlias {
+        alias: String,
+        previous: Option<(TokenId, TokenId)>,
+        new: Option<(TokenId, TokenId)>,
+    },
+    /// Whether any account may create pool at explicit starting price
+    PoolPriceInitOpen { previous: bool, new: bool },
+    /// Seed period of new concentrated pools; `None` if new pools aren't restricted
+    SeedPeriod {
+        previous: Option<SeedPeriodConfig>,
+        new: Option<SeedPeriodConfig>,
+    },
+    /// Whether deposits of fee-on-transfer token are credited by contract's balance delta
+    ReconcileOnDeposit {
+        token: TokenId,
+        previous: bool,
+        new: bool,
+    },
+    /// Whether token contracts are checked to implement fungible token interface
+    /// before their first deposit or pool creation
+    TokenInterfaceCheck { previous: bool, new: bool },
+    /// Batch clearing mode of pool
+    #[cfg(feature = "batch-auction")]
+    BatchAuctionMode {
+        pool: (TokenId, TokenId),
+        previous: bool,
+        new: bool,
+    },
+}
+
+/// Kind of event which can be disabled by owner, e.g. to save gas on extremely hot pools
+///
+/// `config_change` events can't be disabled, so changes of event filter are always reported.
+#[cfg_attr(feature = "near", derive(Serialize, Deserialize))]
+#[cfg_attr(
+    feature = "near",
+    serde(crate = "near_sdk::serde", rename_all = "snake_case")
+)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum EventKind {
+    Deposit,
+    Withdraw,
+    OpenPosition,
+    ClosePosition,
+    HarvestFee,
+    Swap,
+    UpdatePoolState,
+    StorageBalance,
+    AddVerifiedTokens,
+    RemoveVerifiedTokens,
+    BanTokens,
+    UnbanTokens,
+    AddGuardAccounts,
+    RemoveGuardAccounts,
+    LockPosition,
+    UnlockPosition,
+    SuspendPayableApi,
+    ResumePayableApi,
+    ActionScheduled,
+    ActionExecuted,
+    ActionCancelled,
+    ActionApproved,
+    PoolStorageCleanup,
+    TokenRegistrationsCleanup,
+    WithdrawProtocolFee,
+    OwnerWithdraw,
+    SetProtocolFeeFraction,
+    MovePosition,
+    OrderPlaced,
+    OrderExecuted,
+    OrderCancelled,
+    FeeAuctionUpdate,
+    FeeAuctionTake,
+    TransferInternal,
+    LpTokenMint,
+    LpTokenBurn,
+    LpTokenTransfer,
+    ForceUntrackWithdraw,
+    UpgradeStaged,
+    Upgrade,
+    HarvestAllFees,
+    LockPositionUntil,
+}
+
+impl EventKind {
+    pub const ALL: [EventKind; 42] = [
+        EventKind::Deposit,
+        EventKind::Withdraw,
+        EventKind::OpenPosition,
+        EventKind::ClosePosition,
+        EventKind::HarvestFee,
+        EventKind::Swap,
+        EventKind::UpdatePoolState,
+        EventKind::StorageBalance,
+        EventKind::AddVerifiedTokens,
+        EventKind::RemoveVerifiedTokens,
+        EventKind::BanTokens,
+        EventKind::UnbanTokens,
+        EventKind::AddGuardAccounts,
+        EventKind::RemoveGuardAccounts,
+        EventKind::LockPosition,
+        EventKind::UnlockPosition,
+        EventKind::SuspendPayableApi,
+        EventKind::ResumePayableApi,
+        EventKind::ActionScheduled,
+        EventKind::ActionExecuted,
+        EventKind::ActionCancelled,
+        EventKind::ActionApproved,
+        EventKind::PoolStorageCleanup,
+        EventKind::TokenRegistrationsCleanup,
+        EventKind::WithdrawProtocolFee,
+        EventKind::OwnerWithdraw,
+        EventKind::SetProtocolFeeFraction,
+        EventKind::MovePosition,
+        EventKind::OrderPlaced,
+        EventKind::OrderExecuted,
+        EventKind::OrderCancelled,
+        EventKind::FeeAuctionUpdate,
+        EventKind::FeeAuctionTake,
+        EventKind::TransferInternal,
+        EventKind::LpTokenMint,
+        EventKind::LpTokenBurn,
+        EventKind::LpTokenTransfer,
+        EventKind::ForceUntrackWithdraw,
+        EventKind::UpgradeStaged,
+        EventKind::Upgrade,
+        EventKind::HarvestAllFees,
+        EventKind::LockPositionUntil,
+    ];
+
+    const fn mask(self) -> u64 {
+        1 << self as u8
+    }
+}
+
+/// Per-kind event enable flags, stored in contract state; all events are enabled by default
+#[cfg_attr(feature = "near", derive(BorshDeserialize, BorshSerialize))]
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct EventFilter {
+    /// Bit set of disabled kinds, indexed by `EventKind` discriminant
+    disabled: u64,
+}
+
+impl EventFilter {
+    /// Filter which passes all events
+    pub const fn new() -> Self {
+        Self { disabled: 0 }
+    }
+
+    pub const fn is_enabled(self, kind: EventKind) -> bool {
+        self.disabled & kind.mask() == 0
+    }
+
+    /// Enable or disable events of specified kind, returning previous state
+    pub fn set_enabled(&mut self, kind: EventKind, enabled: bool) -> bool {
+        let previous = self.is_enabled(kind);
+        if enabled {
+            self.disabled &= !kind.mask();
+        } else {
+            self.disabled |= kind.mask();
+        }
+        previous
+    }
+
+    pub fn disabled_kinds(self) -> Vec<EventKind> {
+        EventKind::ALL
+            .into_iter()
+            .filter(|kind| !self.is_enabled(*kind))
+            .collect()
+    }
+}
+
+/// Hash which identifies owner action proposed by admins
+pub type ActionHash = [u8; 32];
+
+/// SHA-256 hash of contract code
+pub type CodeHash = [u8; 32];
+
+/// Owner action proposed by one of admins, waiting for enough approvals
+#[cfg_attr(
+    feature = "near",
+    derive(BorshDeserialize, BorshSerialize, Serialize, Deserialize)
+)]
+#[cfg_attr(feature = "near", serde(crate = "near_sdk::serde"))]
+#[derive(Clone, Debug)]
+pub struct PendingApproval {
+    pub action: AdminAction,
+    /// Admins which approved action, including the one which proposed it
+    pub approvals: Vec<AccountId>,
+}
+
+/// Owner action waiting for timelock to expire
+#[cfg_attr(
+    feature = "near",
+    derive(BorshDeserialize, BorshSerialize, Serialize, Deserialize)
+)]
+#[cfg_attr(feature = "near", serde(crate = "near_sdk::serde"))]
+#[derive(Clone, Debug)]
+pub struct ScheduledAction {
+    pub action: AdminAction,
+    /// Moment since which action can be executed, in seconds
+    pub execute_after: u64,
+}
+
+/// Administrative call performed by owner, admin or guard, kept in on-chain admin log
+#[cfg_attr(feature = "near", derive(BorshDeserialize, BorshSerialize))]
+#[derive(Clone, Debug)]
+pub struct AdminLogEntry {
+    pub caller: AccountId,
+    /// Name of called method, e.g. `set_protocol_fee_fraction`
+    pub action: String,
+    /// SHA-256 hash of call arguments, as they were passed to the method
+    pub args_hash: ActionHash,
+    pub block_height: u64,
+}
+
+/// Contract's balance of fee-on-transfer token in reconcile on deposit mode
+#[cfg_attr(feature = "near", derive(BorshDeserialize, BorshSerialize))]
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct ReconciledBalance {
+    /// Balance tracked by contract; `None` until it's fetched from token
+    pub balance: Option<Amount>,
+    /// Whether deposit of token waits for balance fetch; other deposits of token
+    /// are rejected meanwhile, so that balance delta is attributed to single deposit
+    pub deposit_pending: bool,
+    /// Amount sent by contract which transfers aren't resolved yet; it may have left
+    /// contract's balance or not, so balance fetched meanwhile is ambiguous
+    pub outflow_in_flight: Amount,
+}
+
+/// What happens to swap which reverses the previous swap of the same account
+/// through the same pool within the same block, i.e. sandwich or self-arbitrage pattern
+#[cfg_attr(
+    feature = "near",
+    derive(BorshDeserialize, BorshSerialize, Serialize, Deserialize)
+)]
+#[cfg_attr(
+    feature = "near",
+    serde(crate = "near_sdk::serde", rename_all = "snake_case")
+)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SwapGuardMode {
+    /// Charge extra fee, in basis points of swap output (or input, for exact-out swaps),
+    /// which goes to insurance reserve
+    ExtraFee(BasisPoints),
+    /// Reject swap
+    Reject,
+}
+
+/// Sandwich guard of single pool, together with record of the last swap through it
+#[cfg_attr(feature = "near", derive(BorshDeserialize, BorshSerialize))]
+#[derive(Clone, Debug)]
+pub struct SwapGuard {
+    pub mode: SwapGuardMode,
+    /// Account, block height and input token of the last swap through pool
+    pub last_swap: Option<(AccountId, u64, TokenId)>,
+}
+
+/// Swap submitted to batch of pool in batch clearing mode; input amount is taken
+/// from owner's deposits at submission
+#[cfg(feature = "batch-auction")]
+#[cfg_attr(feature = "near", derive(BorshDeserialize, BorshSerialize))]
+#[derive(Clone, Debug)]
+pub struct BatchOrder {
+    pub owner: AccountId,
+    /// Input token, one of pool's tokens; output token is the other one
+    pub token_in: TokenId,
+    pub amount_in: Amount,
+    /// Minimal amount of output token to receive at clearing price;
+    /// order is refunded at settlement if it isn't met
+    pub min_amount_out: Amount,
+}
+
+/// Swaps accumulated by pool in batch clearing mode, to be cleared at single price
+#[cfg(feature = "batch-auction")]
+#[cfg_attr(feature = "near", derive(BorshDeserialize, BorshSerialize))]
+#[derive(Clone, Debug, Default)]
+pub struct BatchAuction {
+    pub orders: Vec<BatchOrder>,
+    /// Height of block in which orders were submitted
+    pub block_height: u64,
+}
+
+/// Parameters of withdraw rate limit for single token
+#[cfg_attr(
+    feature = "near",
+    derive(BorshDeserialize, BorshSerialize, Serialize, Deserialize)
+)]
+#[cfg_attr(feature = "near", serde(crate = "near_sdk::serde"))]
+#[derive(Clone, Debug)]
+pub struct WithdrawLimitConfig {
+    /// Maximal amount which may leave contract within any `window_sec` long period
+    pub max_amount: WasmAmount,
+    /// Length of rolling window, in seconds
+    pub window_sec: u64,
+    /// User withdrawals below this amount are counted, but are let through
+    /// until limit is exhausted, even if they slightly exceed it; larger ones
+    /// must fit into the limit entirely. Owner withdrawals are always limited
+    pub large_withdraw_threshold: WasmAmount,
+}
+
+impl WithdrawLimitConfig {
+    /// Whether limit with this config lets through no more than limit with `other` config.
+    /// Change of window length resets counter, so it never tightens limit
+    pub fn is_tighter_than(&self, other: &Self) -> bool {
+        self.window_sec == other.window_sec
+            && self.max_amount.0 <= other.max_amount.0
+            && self.large_withdraw_threshold.0 <= other.large_withdraw_threshold.0
+    }
+}
+
+/// Withdraw rate limit for single token, together with rolling counter of withdrawn amount
+///
+/// Rolling counter is approximated with two adjacent fixed windows: amount withdrawn
+/// during previous window is accounted proportionally to its overlap with rolling window
+#[cfg_attr(
+    feature = "near",
+    derive(BorshDeserialize, BorshSerialize, Serialize, Deserialize)
+)]
+#[cfg_attr(feature = "near", serde(crate = "near_sdk::serde"))]
+#[derive(Clone, Debug)]
+pub struct WithdrawLimit {
+    pub config: WithdrawLimitConfig,
+    /// Index of current fixed window, i.e. `now / window_sec`
+    pub current_window: u64,
+    /// Amount withdrawn during current fixed window
+    pub current_amount: WasmAmount,
+    /// Amount withdrawn during previous fixed window
+    pub previous_amount: WasmAmount,
+}
+
+impl WithdrawLimit {
+    pub fn new(config: WithdrawLimitConfig) -> Self {
+        Self {
+            config,
+            current_window: 0,
+            current_amount: Amount::zero().into(),
+            previous_amount: Amount::zero().into(),
+        }
+    }
+
+    /// Amount withdrawn within rolling window which ends at `now`
+    pub fn withdrawn_amount(&self, now: u64) -> Amount {
+        let window_sec = self.config.window_sec.max(1);
+        let window = now / window_sec;
+        let (current, previous) = if window == self.current_window {
+            (self.current_amount.into(), self.previous_amount.into())
+        } else if window == self.current_window + 1 {
+            (Amount::zero(), self.current_amount.into())
+        } else {
+            (Amount::zero(), Amount::zero())
+        };
+        // Part of previous fixed window which is still covered by rolling window
+        let overlap = Amount::from(window_sec - now % window_sec);
+        let window_sec = Amount::from(window_sec);
+        let previous: Amount = previous.checked_mul(overlap).map_or_else(
+            || previous / window_sec * overlap,
+            |previous| previous / window_sec,
+        );
+        current.saturating_add(previous)
+    }
+
+    /// Count withdrawal of `amount` made at `now`
+    ///
+    /// # Parameters
+    /// * `strict` - whether withdrawal must fit into the limit entirely; otherwise it's only
+    ///     required that limit isn't exhausted yet
+    ///
+    /// # Returns
+    /// * `Err(ErrorKind::WithdrawRateLimitExceeded)` if withdrawal would exceed the limit;
+    ///     counter is left unchanged in such case
+    pub fn consume(&mut self, amount: Amount, now: u64, strict: bool) -> Result<(), DexErrorKind> {
+        let withdrawn = self.withdrawn_amount(now);
+        let allowed = if strict {
+            withdrawn.saturating_add(amount) <= self.config.max_amount.0
+        } else {
+            withdrawn < self.config.max_amount.0
+        };
+        ensure!(allowed, DexErrorKind::WithdrawRateLimitExceeded);
+        let window = now / self.config.window_sec.max(1);
+        if window != self.current_window {
+            self.previous_amount = if window == self.current_window + 1 {
+                self.current_amount
+            } else {
+                Amount::zero().into()
+            };
+            self.current_amount = Amount::zero().into();
+            self.current_window = window;
+        }
+        self.current_amount = Amount::from(self.current_amount)
+            .saturating_add(amount)
+            .into();
+        Ok(())
+    }
+}
+
+/// Position of storage cleanup scan over pool's tick states
+#[cfg_attr(
+    feature = "near",
+    derive(BorshDeserialize, BorshSerialize, Serialize, Deserialize)
+)]
+#[cfg_attr(feature = "near", serde(crate = "near_sdk::serde"))]
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct CleanupCursor {
+    /// Fee level being scanned
+    pub fee_level: FeeLevel,
+    /// Last scanned tick on the level; `None` if level scan hasn't started yet
+    pub after_tick: Option<Tick>,
+}
+
+/// Condition on pool price which makes conditional order executable
+#[cfg_attr(
+    feature = "near",
+    derive(BorshDeserialize, BorshSerialize, Serialize, Deserialize)
+)]
+#[cfg_attr(
+    feature = "near",
+    serde(crate = "near_sdk::serde", rename_all = "snake_case")
+)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum OrderTrigger {
+    /// Price of input token dropped to trigger price or below
+    StopLoss,
+    /// Price of input token rose to trigger price or above
+    TakeProfit,
+}
+
+impl OrderTrigger {
+    /// Whether order with this trigger is executable at `price`
+    pub fn is_triggered(self, price: Float, trigger_price: Float) -> bool {
+        match self {
+            OrderTrigger::StopLoss => price <= trigger_price,
+            OrderTrigger::TakeProfit => price >= trigger_price,
+        }
+    }
+}
+
+/// Swap posted by user, to be executed by any keeper once pool price reaches trigger price
+#[cfg_attr(feature = "near", derive(BorshDeserialize, BorshSerialize))]
+#[derive(Clone)]
+#[cfg_attr(not(target_arch = "wasm32"), derive(Debug))]
+pub struct ConditionalOrder {
+    pub owner: AccountId,
+    pub token_in: TokenId,
+    pub token_out: TokenId,
+    /// Amount of `token_in` to swap, excluding keeper fee
+    pub amount_in: Amount,
+    /// Minimal amount of `token_out` to receive, protects against slippage at execution
+    pub min_amount_out: Amount,
+    pub trigger: OrderTrigger,
+    /// Price of `token_in` in units of `token_out`
+    pub trigger_price: Float,
+    /// Moment after which order can't be executed, in seconds
+    pub expires_at: u64,
+    /// Amount of `token_in` paid to keeper which executes order
+    pub keeper_fee: Amount,
+}
+
+/// Rule of automatic fee harvest of position, see `set_auto_harvest`
+#[cfg_attr(feature = "near", derive(BorshDeserialize, BorshSerialize))]
+#[derive(Clone, Debug)]
+pub struct AutoHarvestRule {
+    pub position_id: PositionId,
+    pub owner: AccountId,
+    /// Minimal accrued fees, in order of pool tokens; fees are harvested once either is reached
+    pub min_fees: (Amount, Amount),
+}
+
+/// Auto-harvest rules of pool's positions, which are checked one per swap through pool
+#[cfg_attr(feature = "near", derive(BorshDeserialize, BorshSerialize))]
+#[derive(Clone, Debug, Default)]
+pub struct AutoHarvestQueue {
+    pub rules: Vec<AutoHarvestRule>,
+    /// Index of rule which is checked on the next swap
+    pub cursor: u32,
+}
+
+/// Identifier of integration, see `Integration`
+pub type IntegrationId = u32;
+
+/// Frontend or other integrator which self-registered on chain,
+/// so that volume of swaps it routes can be attributed to it
+#[cfg_attr(
+    feature = "near",
+    derive(BorshDeserialize, BorshSerialize, Serialize, Deserialize)
+)]
+#[cfg_attr(feature = "near", serde(crate = "near_sdk::serde"))]
+#[derive(Clone, Debug)]
+pub struct Integration {
+    /// Account which registered integration
+    pub owner: AccountId,
+    pub name: String,
+    /// Free-form description, e.g. URL of frontend
+    pub metadata: String,
+}
+
+/// Batch of actions which is executed once all its deposit legs arrive via `ft_on_transfer`,
+/// e.g. to open position funded with two tokens transferred from wallet separately
+#[cfg_attr(feature = "near", derive(BorshDeserialize, BorshSerialize))]
+#[derive(Clone, Debug)]
+pub struct DepositIntent {
+    pub owner: AccountId,
+    pub legs: Vec<DepositLeg>,
+    /// Actions executed on behalf of owner once all legs are received
+    pub actions: Vec<Action<()>>,
+    /// Moment after which deposits to intent are refunded, in seconds
+    pub expires_at: u64,
+}
+
+impl DepositIntent {
+    /// Whether all legs are received in full
+    pub fn is_complete(&self) -> bool {
+        self.legs.iter().all(|leg| leg.received == leg.amount)
+    }
+}
+
+/// Single token deposit expected by `DepositIntent`
+#[cfg_attr(feature = "near", derive(BorshDeserialize, BorshSerialize))]
+#[derive(Clone, Debug)]
+pub struct DepositLeg {
+    pub token_id: TokenId,
+    pub amount: Amount,
+    /// Amount received so far; it's credited to owner's deposits on arrival
+    pub received: Amount,
+}
+
+/// Withdraw protection of account, see `set_vault`
+#[cfg_attr(feature = "near", derive(BorshDeserialize, BorshSerialize))]
+#[derive(Clone, Debug)]
+pub struct Vault {
+    /// Account which may cancel delayed withdrawals and remove vault
+    pub guardian: AccountId,
+    /// Withdrawals of at least specified amounts of tokens are delayed.
+    /// Withdrawals of tokens not listed here are never delayed
+    pub thresholds: Vec<(TokenId, Amount)>,
+    /// Delay of large withdrawals, in seconds
+    pub delay: u64,
+    /// Withdrawals waiting for delay to expire
+    pub delayed_withdrawals: Vec<DelayedWithdrawal>,
+    /// Counter for delayed withdrawals
+    pub next_withdrawal_id: u64,
+}
+
+impl Vault {
+    /// Checks whether withdrawal of `amount` of `token_id` should be delayed
+    pub fn is_triggered(&self, token_id: &TokenId, amount: Amount) -> bool {
+        self.thresholds
+            .iter()
+            .any(|(token, threshold)| token == token_id && amount >= *threshold)
+    }
+}
+
+/// Withdrawal of vault's account which waits for delay to expire
+#[cfg_attr(feature = "near", derive(BorshDeserialize, BorshSerialize))]
+#[derive(Clone, Debug)]
+pub struct DelayedWithdrawal {
+    pub id: u64,
+    pub token_id: TokenId,
+    /// Amount already taken from account's deposits
+    pub amount: Amount,
+    /// Moment since which withdrawal can be executed, in seconds
+    pub execute_after: u64,
+}
+
+/// Limited key which may only swap deposits of account, see `register_session_key`
+#[cfg_attr(feature = "near", derive(BorshDeserialize, BorshSerialize))]
+#[derive(Clone, Debug)]
+pub struct SessionKey {
+    /// Public key of access key, in its string representation
+    pub public_key: String,
+    /// Tokens which key may swap from, with maximal amount swapped per day
+    pub volume_caps: Vec<(TokenId, Amount)>,
+    /// Day which `volumes` are counted for, as number of days since epoch
+    pub day: u64,
+    /// Amounts swapped during `day`, in the same order as `volume_caps`
+    pub volumes: Vec<Amount>,
+}
+
+/// Native tokens paid by account for storage of pool it created
+#[cfg_attr(feature = "near", derive(BorshDeserialize, BorshSerialize))]
+#[derive(Clone, Debug)]
+pub struct PoolStorageDeposit {
+    pub creator: AccountId,
+    /// Amount of native tokens held for pool's storage, e.g. yoctoNEAR
+    pub amount: Amount,
+    /// Storage taken by pool when it was created, in bytes
+    pub storage_bytes: u64,
+}
+
+/// Identifier of LP share token of managed full-range position on pool's fee level
+///
+/// Textual form is `<token>:<token>:<fee_rate>`, e.g. `usdc.near:wrap.near:8`;
+/// tokens may be listed in any order.
+#[derive(Debug, Clone, PartialOrd, Ord, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "near", derive(BorshDeserialize, BorshSerialize))]
+pub struct LpTokenId {
+    pub pool_id: PoolId,
+    pub fee_level: FeeLevel,
+}
+
+impl std::fmt::Display for LpTokenId {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{}:{}:{}",
+            self.pool_id.0,
+            self.pool_id.1,
+            super::dex_impl::fee_rate_ticks(self.fee_level)
+        )
+    }
+}
+
+impl std::str::FromStr for LpTokenId {
+    type Err = DexErrorKind;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut parts = s.split(':');
+        let (Some(token_a), Some(token_b), Some(fee_rate), None) =
+            (parts.next(), parts.next(), parts.next(), parts.next())
+        else {
+            return Err(DexErrorKind::InvalidParams);
+        };
+        let token_a: TokenId = token_a.parse().map_err(|_| DexErrorKind::InvalidParams)?;
+        let token_b: TokenId = token_b.parse().map_err(|_| DexErrorKind::InvalidParams)?;
+        let fee_rate: BasisPoints = fee_rate.parse().map_err(|_| DexErrorKind::IllegalFee)?;
+        let fee_level = super::dex_impl::fee_rates_ticks()
+            .iter()
+            .position(|rate| *rate == fee_rate)
+            .ok_or(DexErrorKind::IllegalFee)?
+            .try_into()
+            .map_err(|_| DexErrorKind::ConvOverflow)?;
+        let (pool_id, _) = PoolId::try_from_pair((token_a, token_b))?;
+        Ok(Self { pool_id, fee_level })
+    }
+}
+
+/// Parameters of Dutch auctions which sell protocol fees for quote token
+#[cfg_attr(
+    feature = "near",
+    derive(BorshDeserialize, BorshSerialize, Serialize, Deserialize)
+)]
+#[cfg_attr(feature = "near", serde(crate = "near_sdk::serde"))]
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct FeeAuctionConfig {
+    /// Token which protocol fees are sold for, e.g. wNEAR or USDC
+    pub quote_token: TokenId,
+    /// Time during which price decays from start price to end price, in seconds
+    pub duration_sec: u64,
+    /// Start price, relative to pool price of sold token, in basis points
+    pub start_price_bps: BasisPoints,
+    /// End price, relative to pool price of sold token, in basis points
+    pub end_price_bps: BasisPoints,
+}
+
+/// Restrictions on freshly created concentrated pools, which protect the pool from being
+/// seeded at a manipulated price and sniped right after
+#[cfg_attr(
+    feature = "near",
+    derive(BorshDeserialize, BorshSerialize, Serialize, Deserialize)
+)]
+#[cfg_attr(feature = "near", serde(crate = "near_sdk::serde"))]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct SeedPeriodConfig {
+    /// Number of blocks after pool creation during which swaps in pool are disabled
+    pub blocks: u64,
+    /// Max distance of position bounds from pool price during seed period, in ticks
+    pub max_width_ticks: u32,
+}
+
+/// Dutch auction of protocol fees accumulated in single token
+#[cfg_attr(feature = "near", derive(BorshDeserialize, BorshSerialize))]
+#[derive(Clone)]
+#[cfg_attr(not(target_arch = "wasm32"), derive(Debug))]
+pub struct FeeAuction {
+    /// Amount of sold token left in auction
+    pub lot: Amount,
+    /// Token which lot is sold for
+    pub quote_token: TokenId,
+    /// Price of sold token in units of quote token, at start of auction
+    pub start_price: Float,
+    /// Price of sold token in units of quote token, since end of auction
+    pub end_price: Float,
+    /// Moment when auction was started, in seconds
+    pub started_at: u64,
+    /// Moment when price stops decaying and auction may be restarted, in seconds
+    pub ends_at: u64,
+}
+
+impl FeeAuction {
+    /// Price of sold token in units of quote token at `now`;
+    /// decays linearly from start price to end price
+    pub fn price_at(&self, now: u64) -> Float {
+        if now >= self.ends_at {
+            return self.end_price;
+        }
+        let elapsed = Float::from(now.saturating_sub(self.started_at));
+        let duration = Float::from(self.ends_at - self.started_at);
+        self.start_price - (self.start_price - self.end_price) * elapsed / duration
+    }
+}
+
+/// Swap description passed to swap hook
+#[cfg_attr(feature = "near", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "near", serde(crate = "near_sdk::serde"))]
+#[derive(Clone, Debug)]
+pub struct SwapNotification {
+    pub account_id: AccountId,
+    pub token_in: TokenId,
+    pub token_out: TokenId,
+    pub amount_in: WasmAmount,
+    pub amount_out: WasmAmount,
 }
 
 #[cfg_attr(not(target_arch = "wasm32"), derive(Debug, PartialEq))]
@@ -253,6 +1202,8 @@ pub struct PositionInfo {
     pub range_ticks: (Tick, Tick),
     pub reward_since_last_withdraw: (Amount, Amount),
     pub reward_since_creation: (Amount, Amount),
+    pub locked_by: Option<AccountId>,
+    pub locked_until: Option<u64>,
 }
 
 #[cfg_attr(not(target_arch = "wasm32"), derive(Debug))]
@@ -278,6 +1229,74 @@ pub struct PoolInfo {
     pub fee_divisor: BasisPoints,
 }
 
+/// Internal pricing state of concentrated pool, for audit and debugging.
+/// Unlike `PoolInfo`, it's always in pool's own order of tokens
+#[cfg_attr(not(target_arch = "wasm32"), derive(Debug))]
+pub struct PoolPricingState {
+    /// Effective sqrtprices of each fee level, for swaps in left and right direction
+    pub eff_sqrtprices: latest::RawFeeLevelsArray<(Float, Float)>,
+
+    /// Next active ticks of each fee level, for swaps in left and right direction
+    pub next_active_ticks: latest::RawFeeLevelsArray<(Option<Tick>, Option<Tick>)>,
+
+    pub top_active_level: FeeLevel,
+
+    pub active_side: Side,
+
+    /// Tick close to current effective sqrtprice in active direction, see `Pool::pivot`
+    pub pivot: EffTick,
+}
+
+/// LP fee accumulators of concentrated pool, for verification of position rewards
+#[cfg_attr(not(target_arch = "wasm32"), derive(Debug))]
+pub struct PoolFeeAccumulators {
+    /// LP fee per unit of fee liquidity, accumulated by swaps with top active level
+    /// equal to each of the fee levels, see `LevelState::acc_lp_fees_per_fee_liquidity`
+    pub acc_lp_fees_per_fee_liquidity:
+        latest::RawFeeLevelsArray<(LPFeePerFeeLiquidity, LPFeePerFeeLiquidity)>,
+
+    /// Total LP fee earned on each of the fee levels since pool creation
+    pub acc_lp_fees: latest::RawFeeLevelsArray<(AmountUFP, AmountUFP)>,
+
+    /// LP fee to be paid out to all LPs
+    pub acc_lp_fee: (AmountUFP, AmountUFP),
+}
+
+/// State of constant product pool, see `PoolKind::ClassicXY`
+#[cfg_attr(not(target_arch = "wasm32"), derive(Debug))]
+pub struct ClassicPoolInfo {
+    /// Amounts of tokens available for swaps, excluding protocol fees
+    pub reserves: (Amount, Amount),
+
+    /// Fee rate scaled up by fee_divisor.
+    pub fee_rate: BasisPoints,
+
+    /// Total amount of issued shares, including locked minimal shares
+    pub total_shares: Amount,
+
+    /// Scale factor for the fee rate.
+    pub fee_divisor: BasisPoints,
+}
+
+/// State of stable swap pool, see `PoolKind::Stable`
+#[cfg_attr(not(target_arch = "wasm32"), derive(Debug))]
+pub struct StablePoolInfo {
+    /// Amounts of tokens available for swaps, excluding protocol fees
+    pub reserves: (Amount, Amount),
+
+    /// Amplification coefficient of the invariant
+    pub amplification: u32,
+
+    /// Fee rate scaled up by fee_divisor.
+    pub fee_rate: BasisPoints,
+
+    /// Total amount of issued shares, including locked minimal shares
+    pub total_shares: Amount,
+
+    /// Scale factor for the fee rate.
+    pub fee_divisor: BasisPoints,
+}
+
 #[derive(Copy, Clone, Debug, PartialEq, Eq)]
 #[cfg_attr(feature = "near", derive(Serialize))]
 pub enum PoolUpdateReason {
@@ -302,3 +1321,125 @@ pub struct Path {
     pub token_out: TokenId,
     pub amount: Amount,
 }
+
+/// Best route between pair of tokens, remembered by swap along it
+/// for subsequent swaps of same order of magnitude
+#[cfg_attr(feature = "near", derive(BorshDeserialize, BorshSerialize))]
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct CachedRoute {
+    pub token_in: TokenId,
+    pub token_out: TokenId,
+    /// Number of significant bits of swapped amount
+    pub amount_bucket: u8,
+    /// Tokens along the route, starting with `token_in` and ending with `token_out`
+    pub tokens: Vec<TokenId>,
+}
+
+/// Fee levels recommended to liquidity providers and traders
+#[cfg_attr(feature = "near", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "near", serde(crate = "near_sdk::serde"))]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct FeeLevelRecommendation {
+    /// Level where opening position is likely most profitable
+    pub position_fee_level: FeeLevel,
+    /// Level where swap would mostly execute; `None` if there's no liquidity to execute it
+    pub swap_fee_level: Option<FeeLevel>,
+}
+
+/// Result of simulated exact-in swap, one point of the effective price curve
+#[derive(Clone, Copy, PartialEq)]
+#[cfg_attr(not(target_arch = "wasm32"), derive(Debug))]
+pub struct PriceCurveSample {
+    /// Amount of input token actually taken by the swap
+    pub amount_in: Amount,
+    /// Amount of output token received
+    pub amount_out: Amount,
+    /// Average price of the swap, as amount in per unit of amount out
+    pub average_price: Float,
+    /// Relative excess of `average_price` over the marginal price before the swap
+    pub price_impact: Float,
+}
+
+/// Result of simulated `open_position`, what position would be opened with on current pool state
+#[cfg_attr(not(target_arch = "wasm32"), derive(Debug))]
+pub struct PositionEstimate {
+    /// Amounts which would be deposited, in the order of tokens passed to estimation
+    pub amounts: (Amount, Amount),
+    /// Accounted net liquidity of the position
+    pub net_liquidity: Liquidity,
+    pub fee_level: FeeLevel,
+    /// Lower and upper ticks of the position, in the order of tokens passed to estimation
+    pub ticks: (Tick, Tick),
+}
+
+/// Result of simulated `close_position`, what position would return if closed now
+#[cfg_attr(not(target_arch = "wasm32"), derive(Debug))]
+pub struct PositionCloseEstimate {
+    /// Pool's tokens, which amounts below are ordered by
+    pub tokens: (TokenId, TokenId),
+    /// Position balance at current price
+    pub amounts: (Amount, Amount),
+    /// LP fees accumulated since last withdrawal
+    pub fees: (Amount, Amount),
+}
+
+/// Value of pool's reserves in reference token, see `Dex::get_tvl`
+#[cfg_attr(not(target_arch = "wasm32"), derive(Debug))]
+pub struct PoolTvl {
+    pub tokens: (TokenId, TokenId),
+    pub tvl: Float,
+}
+
+/// Page of pools valued in reference token, see `Dex::get_tvl`
+#[cfg_attr(not(target_arch = "wasm32"), derive(Debug))]
+pub struct TvlReport {
+    /// Sum of TVL of pools within the page
+    pub total: Float,
+    pub pools: Vec<PoolTvl>,
+    /// Number of pools to skip to get the next page; `None` if this page is the last one
+    pub next_from: Option<u64>,
+}
+
+/// Inconsistency of contract state found by `health_check`
+#[cfg_attr(feature = "near", derive(Serialize, Deserialize))]
+#[cfg_attr(
+    feature = "near",
+    serde(crate = "near_sdk::serde", rename_all = "snake_case", tag = "issue")
+)]
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum HealthIssue {
+    /// Recorded number of pools differs from total number of pools of all kinds
+    PoolCountMismatch { recorded: u64, actual: u64 },
+    /// Position refers to pool which doesn't exist
+    PositionPoolMissing {
+        position_id: PositionId,
+        pool: (TokenId, TokenId),
+    },
+    /// Position refers to pool which doesn't contain it
+    PositionNotInPool {
+        position_id: PositionId,
+        pool: (TokenId, TokenId),
+    },
+    /// Position id isn't below the counter of position ids, so it may be issued again
+    PositionIdNotIssued { position_id: PositionId },
+    /// Fee auctions are running while auctions are disabled
+    FeeAuctionsWithoutConfig { auctions: u64 },
+    /// Multisig requires more approvals than there are admins, so owner actions are impossible
+    AdminThresholdUnreachable { threshold: u16, admins: u64 },
+}
+
+/// Result of `health_check`: cheap invariants of contract state,
+/// with positions checked only for a page of them
+#[cfg_attr(feature = "near", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "near", serde(crate = "near_sdk::serde"))]
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct HealthReport {
+    /// Payable API is suspended by guard
+    pub suspended: bool,
+    /// Withdraw rate limits aren't enforced
+    pub withdraw_limits_disabled: bool,
+    /// Number of positions checked within requested page
+    pub positions_checked: u64,
+    /// Found inconsistencies; empty if state looks healthy
+    pub issues: Vec<HealthIssue>,
+}
@@ -25,6 +25,43 @@ macro_rules! ensure {
     };
 }
 
+/// Records gas used so far by current call, if `gas-profile` feature is enabled
+///
+/// # Example
+/// ```ignore
+/// gas_checkpoint!("action #{index}");
+/// ```
+#[macro_export]
+macro_rules! gas_checkpoint {
+    ($($arg:tt)+) => {
+        #[cfg(feature = "gas-profile")]
+        $crate::chain::gas_profile::checkpoint(format_args!($($arg)+));
+    };
+}
+
+/// Appends trace record if `tracing` feature is enabled and current verbosity is high enough
+///
+/// # Example
+/// ```ignore
+/// trace_here!(Steps, "step: limit={limit_kind:?} in={in_amount_change:?}");
+/// ```
+#[cfg(feature = "tracing")]
+#[macro_export]
+macro_rules! trace_here {
+    ($verbosity:ident, $($arg:tt)+) => {
+        if $crate::dex::tracing::is_enabled($crate::dex::tracing::TraceVerbosity::$verbosity) {
+            $crate::dex::tracing::record(format_args!($($arg)+));
+        }
+    };
+}
+
+/// Without `tracing` feature trace records are not collected, so `trace_here!` expands to nothing
+#[cfg(not(feature = "tracing"))]
+#[macro_export]
+macro_rules! trace_here {
+    ($verbosity:ident, $($arg:tt)+) => {};
+}
+
 /// Assert float values are equal with given relative tolerance.
 ///
 /// Values are considered equal if relative difference is
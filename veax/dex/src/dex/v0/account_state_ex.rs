@@ -1,11 +1,28 @@
 use super::super::errors::{ErrorKind, Result};
-use super::super::{AccountV0, AccountWithdrawTracker, Map, MapRemoveKey, Types};
+use super::super::{
+    Account, AccountExtra, AccountV0, AccountV1, AccountWithdrawTracker, Map, MapRemoveKey, Set,
+    Types,
+};
 use crate::chain::{Amount, TokenId};
 use crate::{ensure_here, error_here};
 #[allow(unused)] // Some impls use it, some don't
 use num_traits::Zero;
 
-impl<T: Types> AccountV0<T> {
+impl<T: Types> From<AccountV0<T>> for Account<T> {
+    fn from(legacy: AccountV0<T>) -> Self {
+        let mut extra: T::AccountExtra = legacy.extra.into();
+        extra.on_migrated(legacy.positions.len());
+        Account::V1(AccountV1 {
+            token_balances: legacy.token_balances,
+            positions: legacy.positions,
+            withdraw_tracker: legacy.withdraw_tracker,
+            extra,
+            registration_cleanup_allowed: false,
+        })
+    }
+}
+
+impl<T: Types> AccountV1<T> {
     #[track_caller]
     #[allow(unused)] // Need to use it in `Dex`, to properly check if account can be unregistered
     pub(in super::super) fn ensure_no_withdraw_in_progress(&self) -> Result<()> {
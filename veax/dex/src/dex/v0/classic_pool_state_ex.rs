@@ -0,0 +1,216 @@
+//! Constant product math of `PoolKind::ClassicXY` pools
+//!
+//! All amounts are integers; rounding always favors the pool.
+use crate::chain::{AccountId, Amount, UIntBig};
+use crate::dex::errors::{ErrorKind, Result};
+use crate::dex::state_types::PoolSharesMap;
+use crate::dex::traits::{Map, MapRemoveKey};
+use crate::dex::util_types::{Exact, Side};
+use crate::dex::{BasisPoints, ClassicPoolV0, Types, BASIS_POINT_DIVISOR};
+use crate::{ensure_here, error_here};
+use num_traits::Zero;
+
+/// Shares which are minted on first deposit into pool and never withdrawn,
+/// so pool can't be fully drained and share price manipulated
+pub const MIN_POOL_SHARES: Amount = 1_000;
+
+impl<T: Types> ClassicPoolV0<T> {
+    /// Swap tokens with pool
+    ///
+    /// # Parameters
+    /// * `side` - side of input token
+    /// * `exact` - whether `amount` is exact input or exact output amount
+    /// * `amount` - amount of input or output token, depending on `exact`
+    /// * `protocol_fee_fraction` - fraction of charged fee which goes to protocol, in basis points
+    ///
+    /// # Returns
+    /// Input and output amounts
+    pub fn swap(
+        &mut self,
+        side: Side,
+        exact: Exact,
+        amount: Amount,
+        protocol_fee_fraction: BasisPoints,
+    ) -> Result<(Amount, Amount)> {
+        ensure_here!(!amount.is_zero(), ErrorKind::SwapAmountTooSmall);
+        let reserve_in = UIntBig::from(self.reserves[side]);
+        let reserve_out = UIntBig::from(self.reserves[side.opposite()]);
+        ensure_here!(
+            !reserve_in.is_zero() && !reserve_out.is_zero(),
+            ErrorKind::InsufficientLiquidity
+        );
+        let divisor = UIntBig::from(BASIS_POINT_DIVISOR);
+        let fee_rate = UIntBig::from(self.fee_rate);
+
+        let (amount_in, amount_out) = match exact {
+            Exact::In => {
+                let amount_in = UIntBig::from(amount);
+                let net_in = amount_in * (divisor - fee_rate) / divisor;
+                let amount_out = reserve_out * net_in / (reserve_in + net_in);
+                ensure_here!(!amount_out.is_zero(), ErrorKind::SwapAmountTooSmall);
+                (amount_in, amount_out)
+            }
+            Exact::Out => {
+                let amount_out = UIntBig::from(amount);
+                ensure_here!(amount_out < reserve_out, ErrorKind::InsufficientLiquidity);
+                let net_in = div_ceil(reserve_in * amount_out, reserve_out - amount_out);
+                let amount_in = div_ceil(net_in * divisor, divisor - fee_rate);
+                (amount_in, amount_out)
+            }
+        };
+        let amount_in = to_amount(amount_in)?;
+        let amount_out = to_amount(amount_out)?;
+
+        // Fee is whatever was charged above net input, so rounding is counted as fee
+        let fee = UIntBig::from(amount_in) * fee_rate / divisor;
+        let protocol_fee = (fee * UIntBig::from(protocol_fee_fraction) / divisor).low_u128();
+
+        self.reserves[side] = self.reserves[side]
+            .checked_add(amount_in - protocol_fee)
+            .ok_or(error_here!(ErrorKind::SwapAmountTooLarge))?;
+        self.reserves[side.opposite()] -= amount_out;
+        self.protocol_fees[side] += protocol_fee;
+        Ok((amount_in, amount_out))
+    }
+
+    /// Deposit liquidity in current reserves ratio, minting shares to provider
+    ///
+    /// # Returns
+    /// Actually deposited amounts and minted shares
+    pub fn add_liquidity(
+        &mut self,
+        account_id: &AccountId,
+        max_amounts: (Amount, Amount),
+    ) -> Result<((Amount, Amount), Amount)> {
+        ensure_here!(
+            !max_amounts.0.is_zero() && !max_amounts.1.is_zero(),
+            ErrorKind::InvalidParams
+        );
+        let (amounts, shares) = if self.total_shares.is_zero() {
+            let shares = (UIntBig::from(max_amounts.0) * UIntBig::from(max_amounts.1)).isqrt();
+            let shares = to_amount(shares)?;
+            ensure_here!(shares > MIN_POOL_SHARES, ErrorKind::DepositTooSmall);
+            // Locked shares aren't owned by anyone
+            self.total_shares = MIN_POOL_SHARES;
+            (max_amounts, shares - MIN_POOL_SHARES)
+        } else {
+            proportional_deposit(self.reserves, self.total_shares, max_amounts)?
+        };
+
+        self.reserves.0 += amounts.0;
+        self.reserves.1 += amounts.1;
+        self.total_shares += shares;
+        credit_shares::<T>(&mut self.shares, account_id, shares)?;
+        Ok((amounts, shares))
+    }
+
+    /// Burn provider's shares, withdrawing proportional part of reserves
+    ///
+    /// # Returns
+    /// Withdrawn amounts
+    pub fn remove_liquidity(
+        &mut self,
+        account_id: &AccountId,
+        shares: Amount,
+    ) -> Result<(Amount, Amount)> {
+        debit_shares::<T>(&mut self.shares, account_id, shares)?;
+        let amounts = proportional_withdrawal(self.reserves, self.total_shares, shares)?;
+        self.reserves.0 -= amounts.0;
+        self.reserves.1 -= amounts.1;
+        self.total_shares -= shares;
+        Ok(amounts)
+    }
+
+    /// Take protocol fees accumulated since last withdrawal
+    pub fn withdraw_protocol_fee(&mut self) -> (Amount, Amount) {
+        std::mem::take(&mut self.protocol_fees)
+    }
+
+    /// Shares owned by liquidity provider
+    pub fn shares_of(&self, account_id: &AccountId) -> Amount {
+        self.shares
+            .inspect(account_id, |shares| *shares)
+            .unwrap_or_default()
+    }
+}
+
+/// Deposit in current reserves ratio into pool which already has liquidity
+///
+/// # Returns
+/// Amounts to deposit, rounded up, and shares to mint, rounded down
+pub(super) fn proportional_deposit(
+    reserves: (Amount, Amount),
+    total_shares: Amount,
+    max_amounts: (Amount, Amount),
+) -> Result<((Amount, Amount), Amount)> {
+    let total_shares = UIntBig::from(total_shares);
+    let shares = std::cmp::min(
+        UIntBig::from(max_amounts.0) * total_shares / UIntBig::from(reserves.0),
+        UIntBig::from(max_amounts.1) * total_shares / UIntBig::from(reserves.1),
+    );
+    ensure_here!(!shares.is_zero(), ErrorKind::DepositTooSmall);
+    let amounts = (
+        to_amount(div_ceil(shares * UIntBig::from(reserves.0), total_shares))?,
+        to_amount(div_ceil(shares * UIntBig::from(reserves.1), total_shares))?,
+    );
+    Ok((amounts, to_amount(shares)?))
+}
+
+/// Amounts withdrawn for burnt `shares`, rounded down
+pub(super) fn proportional_withdrawal(
+    reserves: (Amount, Amount),
+    total_shares: Amount,
+    shares: Amount,
+) -> Result<(Amount, Amount)> {
+    let total_shares = UIntBig::from(total_shares);
+    Ok((
+        to_amount(UIntBig::from(shares) * UIntBig::from(reserves.0) / total_shares)?,
+        to_amount(UIntBig::from(shares) * UIntBig::from(reserves.1) / total_shares)?,
+    ))
+}
+
+pub(super) fn credit_shares<T: Types>(
+    shares_map: &mut PoolSharesMap<T>,
+    account_id: &AccountId,
+    shares: Amount,
+) -> Result<()> {
+    shares_map.update_or_insert(
+        account_id,
+        || Ok(Amount::zero()),
+        |balance, _| {
+            *balance += shares;
+            Ok(())
+        },
+    )
+}
+
+pub(super) fn debit_shares<T: Types>(
+    shares_map: &mut PoolSharesMap<T>,
+    account_id: &AccountId,
+    shares: Amount,
+) -> Result<()> {
+    ensure_here!(!shares.is_zero(), ErrorKind::InvalidParams);
+    let remaining = shares_map.try_update(account_id, |balance| {
+        *balance = balance
+            .checked_sub(shares)
+            .ok_or(error_here!(ErrorKind::InsufficientShares))?;
+        Ok(*balance)
+    })?;
+    if remaining.is_zero() {
+        shares_map.remove(account_id);
+    }
+    Ok(())
+}
+
+fn div_ceil(numerator: UIntBig, denominator: UIntBig) -> UIntBig {
+    let (quotient, remainder) = numerator.div_mod(denominator);
+    if remainder.is_zero() {
+        quotient
+    } else {
+        quotient + 1
+    }
+}
+
+fn to_amount(value: UIntBig) -> Result<Amount> {
+    Amount::try_from(value).map_err(|_| error_here!(ErrorKind::ConvOverflow))
+}
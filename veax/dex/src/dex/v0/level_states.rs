@@ -0,0 +1,129 @@
+use super::{RawFeeLevelsArray, NUM_FEE_LEVELS};
+use crate::dex::traits::Map;
+use crate::dex::{FeeLevel, LevelState, LevelStateV0, Types};
+use once_cell::unsync::OnceCell;
+use std::ops::{Index, IndexMut};
+
+#[cfg(feature = "near")]
+use near_sdk::borsh::{BorshDeserialize, BorshSerialize};
+
+impl<T: Types> Clone for LevelStateV0<T> {
+    fn clone(&self) -> Self {
+        Self {
+            position_reserves: self.position_reserves,
+            acc_lp_fees: self.acc_lp_fees,
+            acc_lp_fees_per_fee_liquidity: self.acc_lp_fees_per_fee_liquidity,
+            eff_sqrtprice: self.eff_sqrtprice,
+            next_active_tick_left: self.next_active_tick_left,
+            next_active_tick_right: self.next_active_tick_right,
+            net_liquidity: self.net_liquidity,
+            phantom_t: self.phantom_t,
+        }
+    }
+}
+
+/// Per-level pool state, where each level is kept in separate storage record.
+///
+/// Records are loaded on first access and cached until pool is dropped.
+/// Records accessed mutably are marked dirty and written back on `flush`,
+/// so operations which touch only some of the levels, like swaps on low levels,
+/// don't rewrite state of the rest. Serializing states with unflushed changes fails.
+pub struct LevelStates<T: Types> {
+    map: T::PoolLevelsMap,
+    cache: RawFeeLevelsArray<OnceCell<LevelStateV0<T>>>,
+    dirty: RawFeeLevelsArray<bool>,
+}
+
+#[cfg(feature = "near")]
+impl<T: Types> BorshSerialize for LevelStates<T> {
+    fn serialize<W: std::io::Write>(&self, writer: &mut W) -> std::io::Result<()> {
+        // Saving pool with unflushed levels would silently lose their changes
+        if self.is_dirty() {
+            return Err(std::io::Error::new(
+                std::io::ErrorKind::InvalidData,
+                "Level states must be flushed before pool is saved",
+            ));
+        }
+        self.map.serialize(writer)
+    }
+}
+
+#[cfg(feature = "near")]
+impl<T: Types> BorshDeserialize for LevelStates<T> {
+    fn deserialize(buf: &mut &[u8]) -> std::io::Result<Self> {
+        BorshDeserialize::deserialize(buf).map(Self::new)
+    }
+}
+
+impl<T: Types> LevelStates<T> {
+    /// Wraps map which must already contain records for all fee levels
+    pub fn new(map: T::PoolLevelsMap) -> Self {
+        Self {
+            map,
+            cache: Default::default(),
+            dirty: [false; NUM_FEE_LEVELS as usize],
+        }
+    }
+
+    /// Write all modified level records back to storage
+    pub fn flush(&mut self) {
+        for level in 0..NUM_FEE_LEVELS {
+            let index = usize::from(level);
+            if !std::mem::take(&mut self.dirty[index]) {
+                continue;
+            }
+            if let Some(state) = self.cache[index].get() {
+                self.map.insert(level, LevelState::V0(state.clone()));
+            }
+        }
+    }
+
+    /// Checks if there are modified level records which weren't written to storage yet
+    pub fn is_dirty(&self) -> bool {
+        self.dirty.iter().any(|dirty| *dirty)
+    }
+
+    /// Iterate over states of levels in specified range, loading them if needed
+    pub fn range(
+        &self,
+        levels: impl Iterator<Item = FeeLevel>,
+    ) -> impl Iterator<Item = &LevelStateV0<T>> {
+        levels.map(|level| &self[level])
+    }
+
+    /// Iterate over states of all levels, loading them if needed
+    pub fn iter(&self) -> impl Iterator<Item = &LevelStateV0<T>> {
+        self.range(0..NUM_FEE_LEVELS)
+    }
+
+    fn load(&self, level: FeeLevel) -> LevelStateV0<T> {
+        let Some(state) = self.map.inspect(&level, |LevelState::V0(state)| state.clone()) else {
+            // Records for all levels are created together with pool,
+            // so if we get here, pool state is irreversibly broken
+            unreachable!()
+        };
+        state
+    }
+}
+
+impl<T: Types> Index<FeeLevel> for LevelStates<T> {
+    type Output = LevelStateV0<T>;
+
+    fn index(&self, level: FeeLevel) -> &Self::Output {
+        self.cache[usize::from(level)].get_or_init(|| self.load(level))
+    }
+}
+
+impl<T: Types> IndexMut<FeeLevel> for LevelStates<T> {
+    fn index_mut(&mut self, level: FeeLevel) -> &mut Self::Output {
+        let index = usize::from(level);
+        if self.cache[index].get().is_none() {
+            let state = self.load(level);
+            // Cell was checked to be empty just above
+            let _ = self.cache[index].set(state);
+        }
+        self.dirty[index] = true;
+        // Cell was initialized just above
+        self.cache[index].get_mut().unwrap()
+    }
+}
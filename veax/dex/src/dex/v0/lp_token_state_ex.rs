@@ -0,0 +1,97 @@
+//! Share accounting of LP tokens of managed full-range positions, see `LpToken`
+//!
+//! Supply follows constant product pools: first mint locks `MIN_POOL_SHARES` forever,
+//! later mints and burns are proportional to token's assets, i.e. balance of managed position
+//! with harvested fees, plus idle amounts. All amounts are integers; rounding always favors holders.
+use super::classic_pool_state_ex::{
+    credit_shares, debit_shares, proportional_deposit, proportional_withdrawal, MIN_POOL_SHARES,
+};
+use crate::chain::{AccountId, Amount, UIntBig};
+use crate::dex::errors::{ErrorKind, Result};
+use crate::dex::traits::Map;
+use crate::dex::{LpTokenV0, Types};
+use crate::{ensure_here, error_here};
+use num_traits::Zero;
+
+impl<T: Types> LpTokenV0<T> {
+    /// Mint LP tokens for deposit in proportion to current assets
+    ///
+    /// # Parameters
+    /// * `assets` - amounts which back current supply
+    /// * `max_amounts` - maximal amounts to deposit; on first mint, they are taken as is
+    ///
+    /// # Returns
+    /// Actually deposited amounts and minted LP tokens
+    pub fn mint(
+        &mut self,
+        account_id: &AccountId,
+        assets: (Amount, Amount),
+        max_amounts: (Amount, Amount),
+    ) -> Result<((Amount, Amount), Amount)> {
+        ensure_here!(
+            !max_amounts.0.is_zero() && !max_amounts.1.is_zero(),
+            ErrorKind::InvalidParams
+        );
+        let (amounts, minted) = if self.total_supply.is_zero() {
+            let minted = (UIntBig::from(max_amounts.0) * UIntBig::from(max_amounts.1)).isqrt();
+            let minted =
+                Amount::try_from(minted).map_err(|_| error_here!(ErrorKind::ConvOverflow))?;
+            ensure_here!(minted > MIN_POOL_SHARES, ErrorKind::DepositTooSmall);
+            // Locked supply isn't owned by anyone
+            self.total_supply = MIN_POOL_SHARES;
+            (max_amounts, minted - MIN_POOL_SHARES)
+        } else {
+            ensure_here!(
+                !assets.0.is_zero() && !assets.1.is_zero(),
+                ErrorKind::InsufficientLiquidity
+            );
+            proportional_deposit(assets, self.total_supply, max_amounts)?
+        };
+
+        self.total_supply += minted;
+        credit_shares::<T>(&mut self.balances, account_id, minted)?;
+        Ok((amounts, minted))
+    }
+
+    /// Burn holder's LP tokens
+    ///
+    /// # Parameters
+    /// * `assets` - amounts which back current supply
+    ///
+    /// # Returns
+    /// Withdrawn part of `assets`
+    pub fn burn(
+        &mut self,
+        account_id: &AccountId,
+        assets: (Amount, Amount),
+        amount: Amount,
+    ) -> Result<(Amount, Amount)> {
+        debit_shares::<T>(&mut self.balances, account_id, amount)?;
+        let amounts = proportional_withdrawal(assets, self.total_supply, amount)?;
+        self.total_supply -= amount;
+        Ok(amounts)
+    }
+
+    /// Move LP tokens between holders
+    pub fn transfer(
+        &mut self,
+        sender_id: &AccountId,
+        receiver_id: &AccountId,
+        amount: Amount,
+    ) -> Result<()> {
+        debit_shares::<T>(&mut self.balances, sender_id, amount)?;
+        credit_shares::<T>(&mut self.balances, receiver_id, amount)
+    }
+
+    /// LP tokens owned by holder
+    pub fn balance_of(&self, account_id: &AccountId) -> Amount {
+        self.balances
+            .inspect(account_id, |balance| *balance)
+            .unwrap_or_default()
+    }
+
+    /// Whether anyone holds LP tokens, besides locked supply
+    pub fn has_holders(&self) -> bool {
+        self.total_supply > MIN_POOL_SHARES
+    }
+}
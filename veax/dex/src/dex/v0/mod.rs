@@ -1,14 +1,21 @@
 use super::{FeeLevel, PoolsNumber};
 
 mod account_state_ex;
+mod classic_pool_state_ex;
+mod level_states;
+mod lp_token_state_ex;
 mod pool_state_ex;
 mod position_state_ex;
+mod stable_pool_state_ex;
 mod util_types;
 
 use super::super::dex;
 
 pub use account_state_ex::*;
+pub use classic_pool_state_ex::*;
+pub use level_states::*;
 pub use pool_state_ex::*;
+pub use stable_pool_state_ex::*;
 pub use util_types::*;
 
 pub const NUM_FEE_LEVELS: FeeLevel = 8;
@@ -16,3 +23,6 @@ pub const NUM_FEE_LEVELS: FeeLevel = 8;
 pub const NUM_TOP_POOLS: PoolsNumber = 8;
 
 pub const NUM_PATHS: PoolsNumber = 16;
+
+/// Max number of best routes remembered by swaps, see `CachedRoute`
+pub const ROUTE_CACHE_SIZE: usize = 8;
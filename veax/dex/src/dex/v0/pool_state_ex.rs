@@ -6,23 +6,30 @@ use dex::dex_impl::{fee_levels, fee_rate_ticks, fee_rates_ticks};
 use dex::errors::{Error, ErrorKind, Result};
 use dex::tick::{EffTick, Tick, PRECALCULATED_TICKS};
 use dex::traits::{Map, MapRemoveKey, OrderedMap};
-use dex::util_types::{Exact, PoolId, PositionInit, Side};
+use dex::util_types::{CleanupCursor, Exact, PoolId, PositionInit, Side};
 use dex::utils::{swap_if, MinSome};
 use dex::{
-    BasisPoints, FeeLevel, Float, PoolInfo, PoolV0, Position, PositionId, PositionInfo, PositionV0,
-    Range, TickState, Types, BASIS_POINT_DIVISOR,
+    BasisPoints, FeeLevel, Float, PoolFeeAccumulators, PoolInfo, PoolPricingState, PoolV1,
+    Position, PositionId, PositionInfo, PositionV1, Range, TickState, Types, BASIS_POINT_DIVISOR,
 };
 use itertools::Itertools;
 use num_traits::Zero;
 use std::cmp::Ordering;
 use std::ops::Neg;
 
-use super::{EffectiveSqrtPrice, RawFeeLevelsArray, NUM_FEE_LEVELS};
-use crate::dex::latest::FeeLevelsArray;
+use super::{EffectiveSqrtPrice, PoolStats, RawFeeLevelsArray, NUM_FEE_LEVELS};
 use crate::dex::v0::position_state_ex::eval_position_balance_ufp;
 use crate::dex::PairExt;
 use crate::dex::Side::{Left, Right};
-use crate::{chain, dex, ensure_here, error_here, fp, LiquiditySFP, MAX_EFF_TICK, MIN_EFF_TICK};
+use crate::{
+    chain, dex, ensure_here, error_here, fp, trace_here, LiquiditySFP, MAX_EFF_TICK, MIN_EFF_TICK,
+};
+
+/// Amounts to deposit, net liquidity and ticks range of position which would be opened
+pub(crate) type OpenPositionEstimate = ((Amount, Amount), NetLiquidityUFP, (Tick, Tick));
+
+/// Removed orphaned ticks, and cursor to continue scan from
+pub(crate) type OrphanedTicksScan = (Vec<(FeeLevel, Tick)>, Option<CleanupCursor>);
 
 #[derive(Clone, Copy, PartialEq, Eq)]
 pub enum AddOrRemove {
@@ -30,14 +37,22 @@ pub enum AddOrRemove {
     Remove,
 }
 
-/// Minimal liquidity required to open a position.
+/// Lower bound of minimal liquidity required to open a position.
 /// Should be not too large to enable opening positions with broad range.
 /// Should be not too small to limit the error of truncation to 32 frational bits.
 /// Current value is chosen to avoid precision loss in conversion to ...X64 types
 /// ```
 /// assert_eq!(((1 << (64 - f64::MANTISSA_DIGITS)) as f64).recip().to_bits(), 0x3f_40_00_00_00_00_00_00_u64);
 /// ```
-const MIN_LIQUIDITY: Float = Float::from_bits(0x3f_40_00_00_00_00_00_00_u64);
+pub const MIN_LIQUIDITY: Float = Float::from_bits(0x3f_40_00_00_00_00_00_00_u64);
+
+/// Upper bound of minimal liquidity required to open a position, which is configured by owner.
+///
+/// Set to 96 bits, so that at least 2^(143-96)=2^47 minimal positions fit into `MAX_LIQUIDITY`.
+/// ```
+/// assert_eq!(96.0f64.exp2().to_bits(), 0x45_f0_00_00_00_00_00_00_u64);
+/// ```
+pub const MAX_MIN_LIQUIDITY: Float = Float::from_bits(0x45_f0_00_00_00_00_00_00_u64);
 
 /// Maximum liquidity required to open a position.
 ///
@@ -49,7 +64,7 @@ const MIN_LIQUIDITY: Float = Float::from_bits(0x3f_40_00_00_00_00_00_00_u64);
 /// assert_eq!(143.0f64.exp2().to_bits(), 0x48_e0_00_00_00_00_00_00_u64);
 /// ```
 #[cfg(feature = "near")]
-const MAX_LIQUIDITY: Float = Float::from_bits(0x48_e0_00_00_00_00_00_00_u64);
+pub const MAX_LIQUIDITY: Float = Float::from_bits(0x48_e0_00_00_00_00_00_00_u64);
 
 /// What fraction of amount-in may be underpaid by a trader in an exact-in swap.
 /// ```
@@ -125,6 +140,20 @@ pub(crate) fn gross_liquidity_from_net_liquidity(
     GrossLiquidityUFP::from(net_liqudity) * one_over_one_minus_fee_rate
 }
 
+/// Liquidity, as it's reported to users:
+/// `
+///     liquidity = net_liquidity / sqrt(1 - fee_rate)
+/// `
+pub fn liquidity_from_net_liquidity(
+    net_liqudity: NetLiquidityUFP,
+    fee_level: FeeLevel,
+) -> Liquidity {
+    // Proven with test below that for any fee_level, less than NUM_FEE_LEVELS, conversion wont fail
+    let one_over_sqrt_one_minus_fee_rate =
+        Liquidity::try_from(one_over_sqrt_one_minus_fee_rate(fee_level)).unwrap();
+    net_liqudity * one_over_sqrt_one_minus_fee_rate
+}
+
 /// Fee liquidity is a factor connecting LP fee and effective sqrtprice shift
 /// `
 ///     fee_liquidity =
@@ -146,16 +175,16 @@ pub(crate) fn fee_liquidity_from_net_liquidity(
 }
 
 #[derive(PartialEq, Eq, Clone, Copy)]
-#[cfg_attr(not(target_arch = "wasm32"), derive(Debug))]
+#[cfg_attr(any(not(target_arch = "wasm32"), feature = "tracing"), derive(Debug))]
 enum StepLimit {
     StepComplete,
     LevelActivation,
     TickCrossing,
 }
 
-impl<T: Types> PoolV0<T> {
+impl<T: Types> PoolV1<T> {
     pub fn eff_sqrtprice(&self, side: Side, fee_level: FeeLevel) -> Float {
-        self.eff_sqrtprices[fee_level].value(side)
+        self.levels[fee_level].eff_sqrtprice.value(side)
     }
 
     pub(crate) fn spot_sqrtprice(&self, side: Side, fee_level: FeeLevel) -> Float {
@@ -172,11 +201,7 @@ impl<T: Types> PoolV0<T> {
     }
 
     fn liquidity(&self, fee_level: FeeLevel) -> Liquidity {
-        // Proven with test below that for any fee_level, less than NUM_FEE_LEVELS, conversion wont fail
-        let one_over_sqrt_one_minus_fee_rate =
-            Liquidity::try_from(one_over_sqrt_one_minus_fee_rate(fee_level)).unwrap();
-
-        self.net_liquidities[fee_level] * one_over_sqrt_one_minus_fee_rate
+        liquidity_from_net_liquidity(self.levels[fee_level].net_liquidity, fee_level)
     }
 
     pub(crate) fn liquidities(&self) -> RawFeeLevelsArray<Liquidity> {
@@ -184,37 +209,27 @@ impl<T: Types> PoolV0<T> {
     }
 
     pub fn gross_liquidity(&self, fee_level: FeeLevel) -> GrossLiquidityUFP {
-        gross_liquidity_from_net_liquidity(self.net_liquidities[fee_level], fee_level)
+        gross_liquidity_from_net_liquidity(self.levels[fee_level].net_liquidity, fee_level)
     }
 
     pub fn fee_liquidity(&self, fee_level: FeeLevel) -> FeeLiquidityUFP {
-        fee_liquidity_from_net_liquidity(self.net_liquidities[fee_level], fee_level)
+        fee_liquidity_from_net_liquidity(self.levels[fee_level].net_liquidity, fee_level)
     }
 
     /// Sum of gross liquidities on levels from 0 to `top_level` (including)
     fn sum_gross_liquidities(&self, top_level: FeeLevel) -> GrossLiquidityUFP {
-        self.net_liquidities[..=top_level]
-            .iter()
-            .enumerate()
-            .map(|(fee_level, net_liquidity)| {
-                gross_liquidity_from_net_liquidity(
-                    *net_liquidity,
-                    FeeLevel::try_from(fee_level).unwrap(),
-                )
+        (0..=top_level)
+            .map(|fee_level| {
+                gross_liquidity_from_net_liquidity(self.levels[fee_level].net_liquidity, fee_level)
             })
             .sum()
     }
 
     /// Sum of fee liquidities on levels from 0 to `top_level` (including)
     fn sum_fee_liquidities(&self, top_level: FeeLevel) -> FeeLiquidityUFP {
-        self.net_liquidities[..=top_level]
-            .iter()
-            .enumerate()
-            .map(|(fee_level, net_liquidity)| {
-                fee_liquidity_from_net_liquidity(
-                    *net_liquidity,
-                    FeeLevel::try_from(fee_level).unwrap(),
-                )
+        (0..=top_level)
+            .map(|fee_level| {
+                fee_liquidity_from_net_liquidity(self.levels[fee_level].net_liquidity, fee_level)
             })
             .sum()
     }
@@ -222,14 +237,96 @@ impl<T: Types> PoolV0<T> {
     fn sum_position_reserves(&self) -> (AmountUFP, AmountUFP) {
         let mut amounts = (AmountUFP::zero(), AmountUFP::zero());
         for level in 0..NUM_FEE_LEVELS {
-            amounts.0 += self.position_reserves[level].0;
-            amounts.1 += self.position_reserves[level].1;
+            amounts.0 += self.levels[level].position_reserves.0;
+            amounts.1 += self.levels[level].position_reserves.1;
         }
         amounts
     }
 
+    /// Recomputes pool invariants after mutation.
+    /// Does nothing unless `invariants` feature is enabled in non-WASM build
+    #[inline]
+    pub(crate) fn check_invariants(&self) -> Result<()> {
+        #[cfg(all(feature = "invariants", not(target_arch = "wasm32")))]
+        self.check_invariants_impl()?;
+        Ok(())
+    }
+
+    /// Checks that:
+    /// * position reserves together with unpaid LP fees fit into total reserves;
+    /// * on each level, net liquidity changes over all ticks sum up to zero,
+    ///   since every position adds liquidity on its lower tick and removes it on upper one;
+    /// * on each level, active net liquidity doesn't exceed total liquidity of all positions
+    #[cfg(all(feature = "invariants", not(target_arch = "wasm32")))]
+    fn check_invariants_impl(&self) -> Result<()> {
+        let position_reserves = self.sum_position_reserves();
+        for side in [Left, Right] {
+            ensure_here!(
+                position_reserves[side] + self.acc_lp_fee[side]
+                    <= AmountUFP::from(self.total_reserves[side]),
+                ErrorKind::InvariantViolation
+            );
+        }
+
+        let mut positions_liquidity = super::FeeLevelsArray::from_value(Liquidity::zero());
+        for (_, position) in self.positions.iter() {
+            let Position::V1(ref position) = *position;
+            positions_liquidity[position.fee_level] += position.net_liquidity;
+        }
+
+        for level in fee_levels() {
+            let ticks_liquidity_change = self.tick_states[level].iter().fold(
+                LiquiditySFP::zero(),
+                |sum, (_, tick_state)| {
+                    let TickState::V0(ref tick_state) = *tick_state;
+                    sum + tick_state.net_liquidity_change
+                },
+            );
+            ensure_here!(
+                ticks_liquidity_change.is_zero(),
+                ErrorKind::InvariantViolation
+            );
+            ensure_here!(
+                self.levels[level].net_liquidity <= positions_liquidity[level],
+                ErrorKind::InvariantViolation
+            );
+        }
+        Ok(())
+    }
+
     pub(crate) fn position_reserves(&self) -> RawFeeLevelsArray<(AmountUFP, AmountUFP)> {
-        RawFeeLevelsArray::from(self.position_reserves)
+        fee_levels().map(|fee_level| self.levels[fee_level].position_reserves)
+    }
+
+    /// Cumulative swap statistics, with tokens ordered according to `side`
+    pub fn pool_stats(&self, side: Side) -> PoolStats {
+        self.stats.swap_if(side == Side::Right)
+    }
+
+    /// Cumulative amounts of tokens paid in by traders, per fee level which absorbed them,
+    /// with tokens ordered according to `side`
+    pub fn level_volumes_in(&self, side: Side) -> RawFeeLevelsArray<(AmountUFP, AmountUFP)> {
+        fee_levels().map(|fee_level| swap_if(side == Side::Right, self.level_volumes_in[fee_level]))
+    }
+
+    /// LP fee accumulators, with tokens ordered according to `side`
+    pub fn fee_accumulators(&self, side: Side) -> PoolFeeAccumulators {
+        let swapped = side == Side::Right;
+        PoolFeeAccumulators {
+            acc_lp_fees_per_fee_liquidity: fee_levels().map(|fee_level| {
+                swap_if(
+                    swapped,
+                    self.levels[fee_level].acc_lp_fees_per_fee_liquidity,
+                )
+            }),
+            acc_lp_fees: fee_levels()
+                .map(|fee_level| swap_if(swapped, self.levels[fee_level].acc_lp_fees)),
+            acc_lp_fee: swap_if(swapped, self.acc_lp_fee),
+        }
+    }
+
+    pub(crate) fn acc_lp_fees_per_level(&self) -> RawFeeLevelsArray<(AmountUFP, AmountUFP)> {
+        fee_levels().map(|fee_level| self.levels[fee_level].acc_lp_fees)
     }
 
     pub fn pool_info(&self, side: Side) -> Result<PoolInfo, Error> {
@@ -248,6 +345,26 @@ impl<T: Types> PoolV0<T> {
         })
     }
 
+    pub fn pricing_state(&self) -> PoolPricingState {
+        PoolPricingState {
+            eff_sqrtprices: fee_levels().map(|fee_level| {
+                (
+                    self.eff_sqrtprice(Left, fee_level),
+                    self.eff_sqrtprice(Right, fee_level),
+                )
+            }),
+            next_active_ticks: fee_levels().map(|fee_level| {
+                (
+                    self.levels[fee_level].next_active_tick_left,
+                    self.levels[fee_level].next_active_tick_right,
+                )
+            }),
+            top_active_level: self.top_active_level,
+            active_side: self.active_side,
+            pivot: self.pivot,
+        }
+    }
+
     #[cfg(feature = "near")]
     #[cfg(feature = "smart-routing")]
     pub(crate) fn primitive_price(&self) -> Liquidity {
@@ -267,7 +384,7 @@ impl<T: Types> PoolV0<T> {
         position_id: PositionId,
     ) -> Result<PositionInfo> {
         self.positions
-            .inspect(&position_id, |Position::V0(ref pos)| {
+            .inspect(&position_id, |Position::V1(ref pos)| {
                 Ok(PositionInfo {
                     tokens_ids: pool_id.as_refs().map(Clone::clone),
                     balance: self.eval_position_balance(pos)?,
@@ -275,15 +392,17 @@ impl<T: Types> PoolV0<T> {
                     range_ticks: pos.tick_bounds,
                     reward_since_last_withdraw: self.position_reward(pos, false)?,
                     reward_since_creation: self.position_reward(pos, true)?,
+                    locked_by: pos.locked_by.clone(),
+                    locked_until: pos.locked_until,
                 })
             })
             .ok_or(error_here!(ErrorKind::PositionDoesNotExist))?
     }
 
-    /// Fast check if pool is not empty. Relies on that `eff_sqrtprices` are reset.
+    /// Fast check if pool is not empty. Relies on that effective prices are reset.
     pub(crate) fn is_spot_price_set(&self) -> bool {
         // When pool is just created, or all positions are deleted,
-        // we set all eff_sqrtprices to zero, which is otherwise invalid.
+        // we set all effective prices to zero, which is otherwise invalid.
         !self.eff_sqrtprice(Side::Left, 0).is_zero()
     }
 
@@ -328,7 +447,7 @@ impl<T: Types> PoolV0<T> {
             .map_err(|e| error_here!(e))?;
             let eff_sqrtprice_this_level = (eff_sqrtprice / self.pivot.eff_sqrtprice())
                 * pivot_opposite_this_level.eff_sqrtprice();
-            self.eff_sqrtprices[i_fee_level] = EffectiveSqrtPrice::from_value(
+            self.levels[i_fee_level].eff_sqrtprice = EffectiveSqrtPrice::from_value(
                 eff_sqrtprice_this_level,
                 side,
                 i_fee_level,
@@ -343,6 +462,22 @@ impl<T: Types> PoolV0<T> {
         Ok(())
     }
 
+    /// Initialize empty pool at explicit spot sqrtprice for left-side swaps, same as of ticks,
+    /// before any position is opened, instead of deriving it from the first position.
+    pub(crate) fn init_pool_from_spot_sqrtprice(&mut self, spot_sqrtprice: Float) -> Result<()> {
+        ensure_here!(!self.is_spot_price_set(), ErrorKind::PoolAlreadyInitialized);
+        ensure_here!(
+            spot_sqrtprice > Tick::MIN.spot_sqrtprice()
+                && spot_sqrtprice < Tick::MAX.spot_sqrtprice(),
+            ErrorKind::InvalidParams
+        );
+        let eff_sqrtprice = eff_sqrtprice_from_spot_sqrtprice(spot_sqrtprice, 0);
+        self.init_pool_from_eff_sqrtprice(eff_sqrtprice, Side::Left, 0)?;
+        self.levels.flush();
+        self.check_invariants()?;
+        Ok(())
+    }
+
     /// Evaluate net liquidity corresponding to `max_amounts` and `tick_bounds` on the given `fee_level`.
     /// Notice: `self.next_active_ticks` must be already updated with `tick_bounds`.
     fn eval_accounted_net_liquidity(
@@ -350,6 +485,7 @@ impl<T: Types> PoolV0<T> {
         max_amounts: (Float, Float),
         (tick_low, tick_high): (Tick, Tick),
         fee_level: FeeLevel,
+        min_liquidity: Float,
     ) -> Result<NetLiquidityUFP> {
         // Determine if the spot price is below, between, or above the position bounds.
         // Here we determine it based on the next ticks to cross. However, if the spot
@@ -449,7 +585,7 @@ impl<T: Types> PoolV0<T> {
         };
 
         ensure_here!(
-            net_liquidity_float >= MIN_LIQUIDITY,
+            net_liquidity_float >= min_liquidity,
             ErrorKind::LiquidityTooSmall
         );
 
@@ -480,7 +616,7 @@ impl<T: Types> PoolV0<T> {
         }
     }
 
-    /// Update `self.next_active_ticks_left` and `self.next_active_ticks_right`
+    /// Update `next_active_tick_left` and `next_active_tick_right` of the level
     /// with newly inserted tick (upon opening a position).
     fn update_next_active_ticks(&mut self, new_tick: Tick, fee_level: FeeLevel) -> Result<()> {
         // The implementation must account, among other, for cases when:
@@ -497,11 +633,12 @@ impl<T: Types> PoolV0<T> {
                 ErrorKind::InternalLogicError
             );
             ensure_here!(
-                Some(new_tick) > self.next_active_ticks_right[fee_level],
+                Some(new_tick) > self.levels[fee_level].next_active_tick_right,
                 ErrorKind::InternalLogicError
             );
-            self.next_active_ticks_left[fee_level] =
-                self.next_active_ticks_left[fee_level].min_some(Some(new_tick));
+            self.levels[fee_level].next_active_tick_left = self.levels[fee_level]
+                .next_active_tick_left
+                .min_some(Some(new_tick));
         } else {
             ensure_here!(
                 self.eff_sqrtprice(Side::Right, fee_level)
@@ -509,15 +646,16 @@ impl<T: Types> PoolV0<T> {
                 ErrorKind::InternalLogicError
             );
 
-            if self.next_active_ticks_left[fee_level] == Some(new_tick) {
+            if self.levels[fee_level].next_active_tick_left == Some(new_tick) {
                 ensure_here!(
                     self.eff_sqrtprice(Side::Left, fee_level)
                         == new_tick.eff_sqrtprice(fee_level, Side::Left),
                     ErrorKind::InternalLogicError
                 );
             } else {
-                self.next_active_ticks_right[fee_level] =
-                    self.next_active_ticks_right[fee_level].max(Some(new_tick));
+                self.levels[fee_level].next_active_tick_right = self.levels[fee_level]
+                    .next_active_tick_right
+                    .max(Some(new_tick));
             }
         }
         Ok(())
@@ -529,7 +667,7 @@ impl<T: Types> PoolV0<T> {
     /// and we must clearly and unambiguously distinguish, whether a tick was already crossed.
     /// Therefore, we compare next active ticks.
     ///
-    /// Notice: `self.next_active_ticks_left` and `self.next_active_ticks_right` must already be updated with `ticks_range` ticks.
+    /// Notice: `next_active_tick_left` and `next_active_tick_right` of the level must already be updated with `ticks_range` ticks.
     ///
     /// # Arguments
     ///
@@ -547,8 +685,8 @@ impl<T: Types> PoolV0<T> {
         ticks_range: (Tick, Tick),
     ) -> Result<Ordering> {
         match (
-            self.next_active_ticks_left[fee_level],
-            self.next_active_ticks_right[fee_level],
+            self.levels[fee_level].next_active_tick_left,
+            self.levels[fee_level].next_active_tick_right,
         ) {
             (Some(next_active_tick_left), Some(next_active_tick_right)) => {
                 if ticks_range[Left] <= next_active_tick_right
@@ -575,6 +713,45 @@ impl<T: Types> PoolV0<T> {
         }
     }
 
+    /// Evaluate amount of `side` token which should be swapped into the opposite one,
+    /// so that the rest of `amount` and the swap output are in the ratio required
+    /// by position in `ticks_range` on `fee_level`.
+    ///
+    /// Price impact of the swap is neglected, so position may take slightly less
+    /// than the swap output.
+    pub(crate) fn eval_auto_balance_swap_amount(
+        &self,
+        side: Side,
+        amount: Amount,
+        ticks_range: (Tick, Tick),
+        fee_level: FeeLevel,
+    ) -> Result<Amount> {
+        ensure_here!(self.is_spot_price_set(), ErrorKind::InsufficientLiquidity);
+        let eff_sqrtprice_in = self.eff_sqrtprice(side, fee_level);
+        // Balances of position with unit liquidity, i.e. required ratio of tokens
+        let unit_balances = eval_position_balance_ufp(
+            NetLiquidityUFP::from(1),
+            ticks_range.0,
+            ticks_range.1,
+            self.eff_sqrtprice(Left, fee_level),
+            self.eff_sqrtprice(Right, fee_level),
+            fee_level,
+        )?
+        .map(Float::from);
+        let (required_in, required_out) = (unit_balances[side], unit_balances[side.opposite()]);
+        if required_out.is_zero() {
+            return Ok(Amount::zero());
+        }
+        // Amount received per unit of input, net of fees
+        let out_per_in = (eff_sqrtprice_in * eff_sqrtprice_in).recip();
+        // Solves `(amount - swapped) / required_in = swapped * out_per_in / required_out`
+        let swapped =
+            Float::from(amount) * required_out / (required_in * out_per_in + required_out);
+        Ok(Amount::try_from(swapped)
+            .map_err(|e| error_here!(e))?
+            .min(amount))
+    }
+
     /// Evaluate amounts of tokens to be deposited in the pool,
     /// and actually accunted net liquidity of the position.
     #[allow(clippy::too_many_lines)] // Refactor?
@@ -584,6 +761,7 @@ impl<T: Types> PoolV0<T> {
         position: PositionInit,
         fee_level: FeeLevel,
         position_id: PositionId,
+        min_liquidity: Float,
         factory: &mut dyn dex::ItemFactory<T>,
     ) -> Result<((Amount, Amount), NetLiquidityUFP)> {
         let PositionInit {
@@ -599,6 +777,7 @@ impl<T: Types> PoolV0<T> {
                     },
                 ),
             ticks_range,
+            ..
         } = position;
         let left_min: Amount = left_min.into();
         let left_max: Amount = left_max.into();
@@ -633,6 +812,7 @@ impl<T: Types> PoolV0<T> {
             (left_max_float, right_max_float),
             (tick_low, tick_high),
             fee_level,
+            min_liquidity,
         )?;
 
         let init_acc_lp_fees_per_fee_liquidity =
@@ -690,8 +870,8 @@ impl<T: Types> PoolV0<T> {
         )?;
 
         // Add the accounted deposit to the pool:
-        self.position_reserves[fee_level].0 += accounted_deposit_ufp.0;
-        self.position_reserves[fee_level].1 += accounted_deposit_ufp.1;
+        self.levels[fee_level].position_reserves.0 += accounted_deposit_ufp.0;
+        self.levels[fee_level].position_reserves.1 += accounted_deposit_ufp.1;
 
         // In case the spot price is within the position range, we need to add up the deposited liquidity
         // to the current active liquidity.
@@ -699,7 +879,7 @@ impl<T: Types> PoolV0<T> {
             .cmp_spot_price_to_position_range(fee_level, (tick_low, tick_high))?
             .is_eq()
         {
-            self.net_liquidities[fee_level] += accounted_net_liquidity;
+            self.levels[fee_level].net_liquidity += accounted_net_liquidity;
         }
 
         // We can't charge LP with a non-integer amount of tokens, so we round the amounts up.
@@ -743,9 +923,82 @@ impl<T: Types> PoolV0<T> {
             .checked_add(actual_deposit.1)
             .ok_or(error_here!(ErrorKind::DepositWouldOverflow))?;
 
+        self.levels.flush();
+        self.check_invariants()?;
         Ok((actual_deposit, accounted_net_liquidity))
     }
 
+    /// Evaluate amounts which `open_position` would charge for `position`, and its accounted
+    /// net liquidity and ticks, without opening it.
+    ///
+    /// Nothing is written to storage, and pool state must be discarded afterwards
+    pub(crate) fn estimate_open_position(
+        &mut self,
+        position: PositionInit,
+        fee_level: FeeLevel,
+        min_liquidity: Float,
+    ) -> Result<OpenPositionEstimate> {
+        let PositionInit {
+            amount_ranges: (left, right),
+            ticks_range,
+            ..
+        } = position;
+        let (left_min, left_max): (Amount, Amount) = (left.min.into(), left.max.into());
+        let (right_min, right_max): (Amount, Amount) = (right.min.into(), right.max.into());
+
+        let (tick_low, tick_high) = Tick::unwrap_range(ticks_range).map_err(|e| error_here!(e))?;
+
+        ensure_here!(left_max >= left_min, ErrorKind::InvalidParams);
+        ensure_here!(right_max >= right_min, ErrorKind::InvalidParams);
+        ensure_here!(tick_high > tick_low, ErrorKind::InvalidParams);
+
+        let left_max_float: Float = next_down(left_max.into());
+        let right_max_float: Float = next_down(right_max.into());
+
+        if !self.is_spot_price_set() {
+            self.init_pool_from_position(
+                left_max_float,
+                right_max_float,
+                tick_low,
+                tick_high,
+                fee_level,
+            )?;
+        }
+        for new_tick in [tick_low, tick_high] {
+            self.update_next_active_ticks(new_tick, fee_level)?;
+        }
+
+        let accounted_net_liquidity = self.eval_accounted_net_liquidity(
+            (left_max_float, right_max_float),
+            (tick_low, tick_high),
+            fee_level,
+            min_liquidity,
+        )?;
+        let accounted_deposit_ufp = eval_position_balance_ufp(
+            accounted_net_liquidity,
+            tick_low,
+            tick_high,
+            self.eff_sqrtprice(Side::Left, fee_level),
+            self.eff_sqrtprice(Side::Right, fee_level),
+            fee_level,
+        )?;
+        let actual_deposit = (
+            Amount::try_from(accounted_deposit_ufp.0.ceil()).map_err(|e| error_here!(e))?,
+            Amount::try_from(accounted_deposit_ufp.1.ceil()).map_err(|e| error_here!(e))?,
+        );
+
+        ensure_here!(actual_deposit.0 <= left_max, ErrorKind::InternalLogicError);
+        ensure_here!(actual_deposit.1 <= right_max, ErrorKind::InternalLogicError);
+        ensure_here!(actual_deposit.0 >= left_min, ErrorKind::WrongRatio);
+        ensure_here!(actual_deposit.1 >= right_min, ErrorKind::WrongRatio);
+
+        Ok((
+            actual_deposit,
+            accounted_net_liquidity,
+            (tick_low, tick_high),
+        ))
+    }
+
     /// Withdraw LP reward fees and close position.
     ///
     /// We intentionally prohibit closing position without withdrawing the reward.
@@ -759,7 +1012,7 @@ impl<T: Types> PoolV0<T> {
 
         let (fee_level, balance_ufp, net_liquidity, ticks_range) = self
             .positions
-            .inspect(&position_id, |Position::V0(ref pos)| {
+            .inspect(&position_id, |Position::V1(ref pos)| {
                 Ok((
                     pos.fee_level,
                     pos.eval_position_balance_ufp(
@@ -781,14 +1034,14 @@ impl<T: Types> PoolV0<T> {
 
         self.total_reserves.0 -= balance.0;
         self.total_reserves.1 -= balance.1;
-        self.position_reserves[fee_level].0 -= balance_ufp.0;
-        self.position_reserves[fee_level].1 -= balance_ufp.1;
+        self.levels[fee_level].position_reserves.0 -= balance_ufp.0;
+        self.levels[fee_level].position_reserves.1 -= balance_ufp.1;
 
         if self
             .cmp_spot_price_to_position_range(fee_level, ticks_range)?
             .is_eq()
         {
-            self.net_liquidities[fee_level] -= net_liquidity;
+            self.levels[fee_level].net_liquidity -= net_liquidity;
         }
 
         for (tick, is_lower) in [(ticks_range.0, true), (ticks_range.1, false)] {
@@ -815,12 +1068,12 @@ impl<T: Types> PoolV0<T> {
                 );
 
                 // update next active tick:
-                if self.next_active_ticks_left[fee_level] == Some(tick) {
-                    self.next_active_ticks_left[fee_level] =
+                if self.levels[fee_level].next_active_tick_left == Some(tick) {
+                    self.levels[fee_level].next_active_tick_left =
                         self.find_next_active_tick_on_level(tick, fee_level, Side::Left);
                 }
-                if self.next_active_ticks_right[fee_level] == Some(tick) {
-                    self.next_active_ticks_right[fee_level] =
+                if self.levels[fee_level].next_active_tick_right == Some(tick) {
+                    self.levels[fee_level].next_active_tick_right =
                         self.find_next_active_tick_on_level(tick, fee_level, Side::Right);
                 }
             }
@@ -828,25 +1081,31 @@ impl<T: Types> PoolV0<T> {
 
         // Reset pool state if the last position is closed
         if !self.contains_any_positions() {
-            self.eff_sqrtprices = FeeLevelsArray::default();
+            for fee_level in fee_levels() {
+                self.levels[fee_level].eff_sqrtprice = EffectiveSqrtPrice::default();
+            }
             self.top_active_level = 0;
             ensure_here!(
-                self.next_active_ticks_left.into_iter().all(Option::is_none),
+                self.levels
+                    .iter()
+                    .all(|level| level.next_active_tick_left.is_none()),
                 ErrorKind::InternalLogicError
             );
             ensure_here!(
-                self.next_active_ticks_right
-                    .into_iter()
-                    .all(Option::is_none),
+                self.levels
+                    .iter()
+                    .all(|level| level.next_active_tick_right.is_none()),
                 ErrorKind::InternalLogicError
             );
         }
 
+        self.levels.flush();
+        self.check_invariants()?;
         Ok((fees, balance))
     }
 
     /// Amount of tokens locked in position
-    fn eval_position_balance(&self, pos: &PositionV0<T>) -> Result<(Amount, Amount), Error> {
+    fn eval_position_balance(&self, pos: &PositionV1<T>) -> Result<(Amount, Amount), Error> {
         let balances_ufp = pos.eval_position_balance_ufp(
             self.eff_sqrtprice(Side::Left, pos.fee_level),
             self.eff_sqrtprice(Side::Right, pos.fee_level),
@@ -859,10 +1118,28 @@ impl<T: Types> PoolV0<T> {
         Ok(balance)
     }
 
+    /// Evaluate amounts which `withdraw_fee_and_close_position` would return
+    /// at current prices, without closing position.
+    ///
+    /// Returns: (`lp_reward_fees`, `position_balance`)
+    pub(crate) fn estimate_close_position(
+        &self,
+        position_id: PositionId,
+    ) -> Result<((Amount, Amount), (Amount, Amount))> {
+        self.positions
+            .inspect(&position_id, |Position::V1(ref pos)| {
+                Ok((
+                    self.position_reward(pos, false)?,
+                    self.eval_position_balance(pos)?,
+                ))
+            })
+            .ok_or(error_here!(ErrorKind::PositionDoesNotExist))?
+    }
+
     pub(crate) fn withdraw_fee(&mut self, position_id: u64) -> Result<(Amount, Amount)> {
         let (reward_ufp, acc_lp_fees_per_fee_liquidity) = self
             .positions
-            .inspect(&position_id, |Position::V0(ref pos)| {
+            .inspect(&position_id, |Position::V1(ref pos)| {
                 let acc_lp_fees_per_fee_liquidity =
                     self.acc_range_lp_fees_per_fee_liquidity(pos.fee_level, pos.tick_bounds)?;
                 let reward_ufp = self.position_reward_ufp(pos, false)?;
@@ -875,7 +1152,7 @@ impl<T: Types> PoolV0<T> {
             .map_err(|e| error_here!(e))?;
 
         self.positions
-            .update(&position_id, |Position::V0(ref mut pos)| {
+            .update(&position_id, |Position::V1(ref mut pos)| {
                 self.total_reserves.0 -= reward.0;
                 self.total_reserves.1 -= reward.1;
                 self.acc_lp_fee.0 -= reward_ufp.0;
@@ -887,6 +1164,8 @@ impl<T: Types> PoolV0<T> {
             })
             .ok_or(error_here!(ErrorKind::PositionDoesNotExist))??;
 
+        self.levels.flush();
+        self.check_invariants()?;
         Ok(reward)
     }
 
@@ -896,7 +1175,8 @@ impl<T: Types> PoolV0<T> {
         top_active_level: FeeLevel,
         lp_fee_per_fee_liquidity: LPFeePerFeeLiquidity,
     ) {
-        self.acc_lp_fees_per_fee_liquidity[top_active_level][side] += lp_fee_per_fee_liquidity;
+        self.levels[top_active_level].acc_lp_fees_per_fee_liquidity[side] +=
+            lp_fee_per_fee_liquidity;
     }
 
     /// Global accumulated LP fee (one side) per net liquidity, since the very beginning of dex operation.
@@ -905,9 +1185,9 @@ impl<T: Types> PoolV0<T> {
         side: Side,
         fee_level: FeeLevel,
     ) -> LPFeePerFeeLiquidity {
-        self.acc_lp_fees_per_fee_liquidity[fee_level..NUM_FEE_LEVELS]
-            .iter()
-            .map(|acc_lp_fees_per_fee_liquidity| acc_lp_fees_per_fee_liquidity[side])
+        self.levels
+            .range(fee_level..NUM_FEE_LEVELS)
+            .map(|level| level.acc_lp_fees_per_fee_liquidity[side])
             .sum()
     }
 
@@ -927,14 +1207,13 @@ impl<T: Types> PoolV0<T> {
         side: Side,
         fee_level: FeeLevel,
         lp_fee_per_fee_liquidity: LPFeePerFeeLiquidity,
-    ) -> Result<()> {
+    ) -> Result<AmountUFP> {
         ensure_here!(
             lp_fee_per_fee_liquidity.non_negative,
             ErrorKind::InternalLogicError
         );
         let lp_fee_per_fee_liquidity = AmountUFP::from(lp_fee_per_fee_liquidity.value);
-        let sum_fee_liquidities =
-            AmountUFP::try_from(self.sum_fee_liquidities(fee_level)).map_err(|e| error_here!(e))?;
+        let sum_fee_liquidities = AmountUFP::from(self.sum_fee_liquidities(fee_level));
         // TODO: provide comments and check on other blockchains
         ensure_here!(
             sum_fee_liquidities.0 .0[0] == 0,
@@ -944,8 +1223,15 @@ impl<T: Types> PoolV0<T> {
             sum_fee_liquidities.0 .0[1] == 0,
             ErrorKind::InternalLogicError
         );
-        self.acc_lp_fee[side] += lp_fee_per_fee_liquidity * sum_fee_liquidities;
-        Ok(())
+        let lp_fee = lp_fee_per_fee_liquidity * sum_fee_liquidities;
+        self.acc_lp_fee[side] += lp_fee;
+        self.stats.lp_fees[side] += lp_fee;
+
+        for level in 0..=fee_level {
+            let fee_liquidity = AmountUFP::from(self.fee_liquidity(level));
+            self.levels[level].acc_lp_fees[side] += lp_fee_per_fee_liquidity * fee_liquidity;
+        }
+        Ok(lp_fee)
     }
 
     fn nearest_active_ticks(
@@ -953,23 +1239,16 @@ impl<T: Types> PoolV0<T> {
         side: Side,
         top_active_level: FeeLevel,
     ) -> Vec<(FeeLevel, Tick)> {
-        // pick next active ticks for the given swap direction:
-        let next_active_ticks = match side {
-            Side::Left => self.next_active_ticks_left,
-            Side::Right => self.next_active_ticks_right,
-        };
-
-        // consider only ticks on the active levels (optimization):
-        let next_active_ticks_on_active_levels =
-            next_active_ticks.iter().take((top_active_level + 1).into());
-
-        // filter out levels where next active tick is None
-        #[allow(clippy::cast_possible_truncation)]
-        let available_next_active_ticks = next_active_ticks_on_active_levels
-            .enumerate()
-            .filter_map(|(level, next_tick)| {
-                next_tick.map(|next_tick| (level as FeeLevel, next_tick))
-            });
+        // consider only ticks on the active levels (optimization),
+        // pick next active ticks for the given swap direction
+        // and filter out levels where next active tick is None
+        let available_next_active_ticks = (0..=top_active_level).filter_map(|level| {
+            let next_tick = match side {
+                Side::Left => self.levels[level].next_active_tick_left,
+                Side::Right => self.levels[level].next_active_tick_right,
+            };
+            next_tick.map(|next_tick| (level, next_tick))
+        });
 
         // function to compare ticks by effective (sqrt)price in the current swap direction:
         let compare_ticks_by_eff_price =
@@ -1095,11 +1374,15 @@ impl<T: Types> PoolV0<T> {
                 / LPFeePerFeeLiquidity::from(1u128 << 48)
         };
 
-        self.accumulate_lp_fee(
+        let lp_fee = self.accumulate_lp_fee(
             self.active_side,
             self.top_active_level,
             lp_fee_per_fee_liquidity,
         )?;
+        // LP fee is `1 - protocol_fee_fraction` part of total fee
+        self.stats.protocol_fees[self.active_side] += lp_fee
+            * AmountUFP::from(u128::from(protocol_fee_fraction))
+            / AmountUFP::from(u128::from(BASIS_POINT_DIVISOR - protocol_fee_fraction));
         self.accumulate_lp_fee_per_fee_liquidity(
             self.active_side,
             self.top_active_level,
@@ -1110,11 +1393,15 @@ impl<T: Types> PoolV0<T> {
     }
 
     /// Returns: `amount_in`, `amount_out`, `step_limit`
+    ///
+    /// `sum_gross_liquidities` must be equal to `self.sum_gross_liquidities(self.top_active_level)`,
+    /// and is kept so on level activation and tick crossing, without recomputing over all levels
     fn try_step_to_price(
         &mut self,
         mut new_eff_sqrtprice: Float,
-        sum_gross_liquidities: Float,
+        sum_gross_liquidities: &mut GrossLiquidityUFP,
         protocol_fee_fraction: BasisPoints,
+        dry_run: bool,
     ) -> Result<(Float, AmountUFP, StepLimit)> {
         ensure_here!(
             new_eff_sqrtprice >= self.eff_sqrtprice(self.active_side, self.top_active_level),
@@ -1155,7 +1442,7 @@ impl<T: Types> PoolV0<T> {
 
         let eff_sqrtprice_shift = new_eff_sqrtprice - init_eff_sqrtprice;
 
-        let in_amount_change = eff_sqrtprice_shift * sum_gross_liquidities;
+        let in_amount_change = eff_sqrtprice_shift * Float::from(*sum_gross_liquidities);
 
         self.pivot = find_pivot(self.pivot, new_eff_sqrtprice).map_err(|e| error_here!(e))?;
 
@@ -1204,6 +1491,12 @@ impl<T: Types> PoolV0<T> {
                 ErrorKind::InternalLogicError
             );
             out_amount_change += out_amount_change_this_level.value;
+
+            // Each active level takes its gross liquidity's share of input
+            let level_amount_in =
+                AmountUFP::try_from(eff_sqrtprice_shift * Float::from(self.gross_liquidity(level)))
+                    .map_err(|e| error_here!(e))?;
+            self.level_volumes_in[level][self.active_side] += level_amount_in;
         }
 
         out_amount_change = out_amount_change.min(
@@ -1213,12 +1506,43 @@ impl<T: Types> PoolV0<T> {
 
         self.accumulate_fees(eff_sqrtprice_shift, protocol_fee_fraction)?;
 
+        trace_here!(
+            Steps,
+            "swap_step: side={:?} top_level={} limit={limit_kind:?} eff_sqrtprice={} \
+            amount_in={} amount_out={}",
+            self.active_side,
+            self.top_active_level,
+            f64::from(new_eff_sqrtprice),
+            f64::from(in_amount_change),
+            f64::from(Float::from(out_amount_change)),
+        );
+
         if limit_kind == StepLimit::LevelActivation {
             self.top_active_level += 1;
+            *sum_gross_liquidities += self.gross_liquidity(self.top_active_level);
+            trace_here!(
+                Crossings,
+                "level_activation: side={:?} level={} liquidity={}",
+                self.active_side,
+                self.top_active_level,
+                f64::from(Float::from(self.liquidity(self.top_active_level))),
+            );
         }
 
         if limit_kind == StepLimit::TickCrossing {
-            self.tick_crossing(nearest_active_ticks, self.active_side);
+            // Fixed-point addition and subtraction are exact, so the cached sum
+            // stays equal to the one recomputed from scratch
+            let crossed_levels = nearest_active_ticks
+                .iter()
+                .map(|(level, _)| *level)
+                .collect::<Vec<_>>();
+            for level in &crossed_levels {
+                *sum_gross_liquidities -= self.gross_liquidity(*level);
+            }
+            self.tick_crossing(nearest_active_ticks, self.active_side, dry_run);
+            for level in &crossed_levels {
+                *sum_gross_liquidities += self.gross_liquidity(*level);
+            }
         }
 
         Ok((in_amount_change, out_amount_change, limit_kind))
@@ -1244,10 +1568,24 @@ impl<T: Types> PoolV0<T> {
         protocol_fee_fraction: BasisPoints,
     ) -> Result<Amount> {
         Ok(self
-            .swap_exact_in_impl(side, amount_in, protocol_fee_fraction, None)?
+            .swap_exact_in_impl(side, amount_in, protocol_fee_fraction, None, false)?
             .1)
     }
 
+    /// Simulates exact-in swap on pool state which is then discarded:
+    /// unlike actual swap, it doesn't write anything to storage, so may be used in views
+    ///
+    /// # Returns
+    /// Amounts in and out
+    pub(crate) fn simulate_swap_exact_in(
+        &mut self,
+        side: Side,
+        amount_in: Amount,
+        protocol_fee_fraction: BasisPoints,
+    ) -> Result<(Amount, Amount)> {
+        self.swap_exact_in_impl(side, amount_in, protocol_fee_fraction, None, true)
+    }
+
     #[allow(unused)]
     pub(crate) fn swap_to_price(
         &mut self,
@@ -1264,15 +1602,18 @@ impl<T: Types> PoolV0<T> {
             max_amount_in,
             protocol_fee_fraction,
             Some(max_eff_sqrtprice),
+            false,
         )
     }
 
+    /// If `dry_run` is set, nothing is written to storage, and pool state must be discarded afterwards
     pub(crate) fn swap_exact_in_impl(
         &mut self,
         side: Side,
         amount_in: Amount,
         protocol_fee_fraction: BasisPoints,
         eff_sqrtprice_limit: Option<Float>,
+        dry_run: bool,
     ) -> Result<(Amount, Amount)> {
         ensure_here!(!amount_in.is_zero(), ErrorKind::InvalidParams);
         ensure_here!(self.is_spot_price_set(), ErrorKind::InsufficientLiquidity);
@@ -1289,13 +1630,11 @@ impl<T: Types> PoolV0<T> {
         let mut remaining_amount_in_float = amount_in_float;
         let mut amount_out_ufp = AmountUFP::zero();
 
+        let mut sum_gross_liquidities = self.sum_gross_liquidities(self.top_active_level);
         loop {
-            let sum_gross_liquidities =
-                Float::from(self.sum_gross_liquidities(self.top_active_level));
-
             let mut new_eff_sqrtprice = self.eval_required_new_eff_sqrtprice_exact_in(
                 remaining_amount_in_float,
-                sum_gross_liquidities,
+                Float::from(sum_gross_liquidities),
             );
 
             if let Some(eff_sqrtprice_limit) = eff_sqrtprice_limit {
@@ -1304,8 +1643,9 @@ impl<T: Types> PoolV0<T> {
 
             let (in_amount_change, out_amount_change, limit_kind) = self.try_step_to_price(
                 new_eff_sqrtprice,
-                sum_gross_liquidities,
+                &mut sum_gross_liquidities,
                 protocol_fee_fraction,
+                dry_run,
             )?;
 
             remaining_amount_in_float -= in_amount_change;
@@ -1348,7 +1688,12 @@ impl<T: Types> PoolV0<T> {
         );
         self.total_reserves[side] += amount_in;
         self.total_reserves[side.opposite()] -= amount_out;
+        self.record_swap_stats(side, amount_in, amount_out);
 
+        if !dry_run {
+            self.levels.flush();
+        }
+        self.check_invariants()?;
         Ok((actual_amount_in, amount_out))
     }
 
@@ -1372,18 +1717,17 @@ impl<T: Types> PoolV0<T> {
         let mut amount_in_float = Float::zero();
         let mut amount_out_sfp = AmountSFP::from(amount_out);
 
+        let mut sum_gross_liquidities = self.sum_gross_liquidities(self.top_active_level);
         while amount_out_sfp > AmountSFP::zero() {
-            let sum_gross_liquidities =
-                Float::from(self.sum_gross_liquidities(self.top_active_level));
-
             let new_eff_sqrtprice = self.eval_required_new_eff_sqrtprice_exact_out(
                 Float::from(amount_out_sfp),
-                sum_gross_liquidities,
+                Float::from(sum_gross_liquidities),
             )?;
             let (in_amount_change, out_amount_change, _limit_kind) = self.try_step_to_price(
                 new_eff_sqrtprice,
-                sum_gross_liquidities,
+                &mut sum_gross_liquidities,
                 protocol_fee_fraction,
+                false,
             )?;
 
             amount_in_float += in_amount_change;
@@ -1413,18 +1757,28 @@ impl<T: Types> PoolV0<T> {
 
         self.total_reserves[side] += amount_in;
         self.total_reserves[side.opposite()] -= amount_out;
+        self.record_swap_stats(side, amount_in, amount_out);
 
+        self.levels.flush();
+        self.check_invariants()?;
         Ok(amount_in)
     }
 
+    fn record_swap_stats(&mut self, side: Side, amount_in: Amount, amount_out: Amount) {
+        self.stats.volume_in[side] = self.stats.volume_in[side].saturating_add(amount_in);
+        self.stats.volume_out[side.opposite()] =
+            self.stats.volume_out[side.opposite()].saturating_add(amount_out);
+        self.stats.swap_count += 1;
+    }
+
     pub(crate) fn update_prices_and_position_reserves(
         &mut self,
         fee_level: FeeLevel,
         eff_sqrtprice: EffectiveSqrtPrice,
     ) -> Result<(AmountSFP, AmountSFP)> {
         let old_eff_sqrtprices = (
-            self.eff_sqrtprices[fee_level].0,
-            self.eff_sqrtprices[fee_level].1,
+            self.levels[fee_level].eff_sqrtprice.0,
+            self.levels[fee_level].eff_sqrtprice.1,
         )
             .try_map_into::<AmountSFP, _>()
             .map_err(|e| error_here!(e))?;
@@ -1433,69 +1787,98 @@ impl<T: Types> PoolV0<T> {
             .try_map_into::<AmountSFP, _>()
             .map_err(|e| error_here!(e))?;
 
-        let net_liquidity =
-            AmountSFP::try_from(self.net_liquidities[fee_level]).map_err(|e| error_here!(e))?;
+        let net_liquidity = AmountSFP::from(self.levels[fee_level].net_liquidity);
 
         let balance_change = (
             (new_eff_sqrtprices.0 - old_eff_sqrtprices.0) * net_liquidity,
             (new_eff_sqrtprices.1 - old_eff_sqrtprices.1) * net_liquidity,
         );
 
-        self.position_reserves[fee_level] = (
-            (AmountSFP::from(self.position_reserves[fee_level].0) + balance_change.0)
+        self.levels[fee_level].position_reserves = (
+            (AmountSFP::from(self.levels[fee_level].position_reserves.0) + balance_change.0)
                 .try_into_unsigned()
                 .map_err(|e: fp::Error| error_here!(e))?,
-            (AmountSFP::from(self.position_reserves[fee_level].1) + balance_change.1)
+            (AmountSFP::from(self.levels[fee_level].position_reserves.1) + balance_change.1)
                 .try_into_unsigned()
                 .map_err(|e: fp::Error| error_here!(e))?,
         );
-        self.eff_sqrtprices[fee_level] = eff_sqrtprice;
+        self.levels[fee_level].eff_sqrtprice = eff_sqrtprice;
 
         Ok(balance_change)
     }
 
+    /// If `dry_run` is set, only liquidities are updated, while tick states are left untouched
     pub(crate) fn tick_crossing(
         &mut self,
         crossed_ticks: Vec<(FeeLevel, Tick)>,
         swap_direction: Side,
+        dry_run: bool,
     ) {
-        for (level, tick) in crossed_ticks {
-            let acc_lp_fees_per_fee_liquidity = self.acc_lp_fees_per_fee_liquidity(level);
-
-            // Update liquidities
-            self.tick_states[level].update(&tick, |TickState::V0(tick_state)| {
+        let apply_net_liquidity_change =
+            |net_liquidity: &mut Liquidity, net_liquidity_change: LiquiditySFP| {
                 let net_liquidity_change = match swap_direction {
-                    Side::Left => tick_state.net_liquidity_change,
-                    Side::Right => tick_state.net_liquidity_change.neg(),
+                    Side::Left => net_liquidity_change,
+                    Side::Right => net_liquidity_change.neg(),
                 };
 
                 if net_liquidity_change.non_negative {
-                    self.net_liquidities[level] += net_liquidity_change.value;
+                    *net_liquidity += net_liquidity_change.value;
                 } else {
-                    self.net_liquidities[level] -= net_liquidity_change.value;
+                    *net_liquidity -= net_liquidity_change.value;
                 };
+            };
 
-                tick_state.acc_lp_fees_per_fee_liquidity_outside.0 = acc_lp_fees_per_fee_liquidity
-                    .0
-                    - tick_state.acc_lp_fees_per_fee_liquidity_outside.0;
-                tick_state.acc_lp_fees_per_fee_liquidity_outside.1 = acc_lp_fees_per_fee_liquidity
-                    .1
-                    - tick_state.acc_lp_fees_per_fee_liquidity_outside.1;
+        for (level, tick) in crossed_ticks {
+            trace_here!(
+                Crossings,
+                "tick_crossing: side={swap_direction:?} level={level} tick={} dry_run={dry_run}",
+                tick.index(),
+            );
+            if dry_run {
+                if let Some(net_liquidity_change) = self.tick_states[level]
+                    .inspect(&tick, |TickState::V0(tick_state)| {
+                        tick_state.net_liquidity_change
+                    })
+                {
+                    apply_net_liquidity_change(
+                        &mut self.levels[level].net_liquidity,
+                        net_liquidity_change,
+                    );
+                }
+            } else {
+                let acc_lp_fees_per_fee_liquidity = self.acc_lp_fees_per_fee_liquidity(level);
+
+                // Update liquidities
+                self.tick_states[level].update(&tick, |TickState::V0(tick_state)| {
+                    apply_net_liquidity_change(
+                        &mut self.levels[level].net_liquidity,
+                        tick_state.net_liquidity_change,
+                    );
+
+                    tick_state.acc_lp_fees_per_fee_liquidity_outside.0 =
+                        acc_lp_fees_per_fee_liquidity.0
+                            - tick_state.acc_lp_fees_per_fee_liquidity_outside.0;
+                    tick_state.acc_lp_fees_per_fee_liquidity_outside.1 =
+                        acc_lp_fees_per_fee_liquidity.1
+                            - tick_state.acc_lp_fees_per_fee_liquidity_outside.1;
 
-                Ok(())
-            });
+                    Ok(())
+                });
+            }
 
             // Update next active ticks:
             let next_active_tick = self.find_next_active_tick_on_level(tick, level, swap_direction);
 
             match swap_direction {
                 Side::Left => {
-                    self.next_active_ticks_right[level] = self.next_active_ticks_left[level];
-                    self.next_active_ticks_left[level] = next_active_tick;
+                    self.levels[level].next_active_tick_right =
+                        self.levels[level].next_active_tick_left;
+                    self.levels[level].next_active_tick_left = next_active_tick;
                 }
                 Side::Right => {
-                    self.next_active_ticks_left[level] = self.next_active_ticks_right[level];
-                    self.next_active_ticks_right[level] = next_active_tick;
+                    self.levels[level].next_active_tick_left =
+                        self.levels[level].next_active_tick_right;
+                    self.levels[level].next_active_tick_right = next_active_tick;
                 }
             };
         }
@@ -1517,9 +1900,154 @@ impl<T: Types> PoolV0<T> {
         self.total_reserves.0 -= payout_x;
         self.total_reserves.1 -= payout_y;
 
+        self.levels.flush();
+        self.check_invariants()?;
         Ok((payout_x, payout_y))
     }
 
+    /// Removes tick states which aren't referenced by any position.
+    /// Normally tick state is removed together with the last position which references it,
+    /// so this only collects leftovers.
+    ///
+    /// # Parameters
+    /// * `cursor` - where to continue scan from; `None` to start from the beginning
+    /// * `max_items` - maximal number of tick states to scan
+    ///
+    /// # Returns
+    /// Removed ticks, and cursor to continue scan from, or `None` if scan is complete
+    pub(crate) fn remove_orphaned_ticks(
+        &mut self,
+        cursor: Option<CleanupCursor>,
+        max_items: usize,
+    ) -> Result<OrphanedTicksScan> {
+        let CleanupCursor {
+            mut fee_level,
+            mut after_tick,
+        } = cursor.unwrap_or_default();
+        let mut removed = Vec::new();
+        let mut scanned = 0;
+        let mut next_cursor = None;
+        while fee_level < NUM_FEE_LEVELS {
+            if scanned == max_items {
+                next_cursor = Some(CleanupCursor {
+                    fee_level,
+                    after_tick,
+                });
+                break;
+            }
+            let inspect_fn = |tick: &Tick, TickState::V0(tick_state): &TickState<T>| {
+                (*tick, tick_state.reference_counter)
+            };
+            let next = match after_tick {
+                None => self.tick_states[fee_level].inspect_min(inspect_fn),
+                Some(ref tick) => self.tick_states[fee_level].inspect_above(tick, inspect_fn),
+            };
+            let Some((tick, reference_counter)) = next else {
+                fee_level += 1;
+                after_tick = None;
+                continue;
+            };
+            scanned += 1;
+            after_tick = Some(tick);
+            if reference_counter != 0 {
+                continue;
+            }
+
+            self.tick_states[fee_level].remove(&tick);
+            if self.levels[fee_level].next_active_tick_left == Some(tick) {
+                self.levels[fee_level].next_active_tick_left =
+                    self.find_next_active_tick_on_level(tick, fee_level, Side::Left);
+            }
+            if self.levels[fee_level].next_active_tick_right == Some(tick) {
+                self.levels[fee_level].next_active_tick_right =
+                    self.find_next_active_tick_on_level(tick, fee_level, Side::Right);
+            }
+            removed.push((fee_level, tick));
+        }
+        self.levels.flush();
+        Ok((removed, next_cursor))
+    }
+
+    /// Fee level where opening position is likely most profitable, i.e. the one
+    /// which has earned most LP fees per unit of its current liquidity.
+    /// Fees in both tokens are valued in right token at current spot price
+    pub(crate) fn most_profitable_fee_level(&self) -> FeeLevel {
+        let spot_price = self.spot_price(Side::Left, 0);
+        let mut best = (0, Float::zero());
+        for fee_level in fee_levels() {
+            let level = &self.levels[fee_level];
+            let lp_fees =
+                Float::from(level.acc_lp_fees.0) / spot_price + Float::from(level.acc_lp_fees.1);
+            let liquidity = Float::from(level.net_liquidity).max(MIN_LIQUIDITY);
+            let lp_fees_per_liquidity = lp_fees / liquidity;
+            if lp_fees_per_liquidity > best.1 {
+                best = (fee_level, lp_fees_per_liquidity);
+            }
+        }
+        best.0
+    }
+
+    /// Estimates fee level on which swap of `amount_in` on `side` would mostly execute,
+    /// i.e. the level which would receive largest part of amount in.
+    /// Liquidity changes on tick crossings are ignored, so estimate is rough for swaps
+    /// which move price far.
+    ///
+    /// # Returns
+    /// `None` if there's no liquidity on levels which swap would reach
+    pub(crate) fn estimate_swap_fee_level(
+        &self,
+        side: Side,
+        amount_in: Amount,
+    ) -> Option<FeeLevel> {
+        let mut top_level = if side == self.active_side {
+            self.top_active_level
+        } else {
+            0
+        };
+        let mut eff_sqrtprice = self.eff_sqrtprice(side, top_level);
+        let mut remaining_amount_in = Float::from(amount_in);
+        let mut level_amounts_in = super::FeeLevelsArray::from_value(Float::zero());
+        loop {
+            let sum_gross_liquidities = Float::from(self.sum_gross_liquidities(top_level));
+            let required_eff_sqrtprice = if sum_gross_liquidities.is_zero() {
+                Float::MAX
+            } else {
+                eff_sqrtprice + remaining_amount_in / sum_gross_liquidities
+            };
+            let next_level_eff_sqrtprice = (top_level < NUM_FEE_LEVELS - 1)
+                .then(|| self.eff_sqrtprice(side, top_level + 1))
+                .filter(|next_level_eff_sqrtprice| {
+                    *next_level_eff_sqrtprice <= required_eff_sqrtprice
+                });
+
+            if !sum_gross_liquidities.is_zero() {
+                let new_eff_sqrtprice = next_level_eff_sqrtprice.unwrap_or(required_eff_sqrtprice);
+                let eff_sqrtprice_shift = new_eff_sqrtprice - eff_sqrtprice;
+                for fee_level in 0..=top_level {
+                    let amount_in =
+                        eff_sqrtprice_shift * Float::from(self.gross_liquidity(fee_level));
+                    level_amounts_in[fee_level] += amount_in;
+                    remaining_amount_in -= amount_in;
+                }
+            }
+
+            let Some(next_level_eff_sqrtprice) = next_level_eff_sqrtprice else {
+                break;
+            };
+            eff_sqrtprice = next_level_eff_sqrtprice;
+            top_level += 1;
+        }
+
+        fee_levels()
+            .into_iter()
+            .filter(|fee_level| level_amounts_in[*fee_level] > Float::zero())
+            .max_by(|a, b| {
+                level_amounts_in[*a]
+                    .partial_cmp(&level_amounts_in[*b])
+                    .unwrap_or(Ordering::Equal)
+            })
+    }
+
     pub fn get_ticks_liquidity_change(
         &self,
         fee_level: FeeLevel,
@@ -1609,7 +2137,7 @@ impl<T: Types> PoolV0<T> {
 
     pub(crate) fn position_reward_ufp(
         &self,
-        pos: &PositionV0<T>,
+        pos: &PositionV1<T>,
         since_creation: bool,
     ) -> Result<(AmountUFP, AmountUFP)> {
         let pos_acc_lp_fees_per_fee_liquidity =
@@ -1635,7 +2163,7 @@ impl<T: Types> PoolV0<T> {
             ErrorKind::InternalLogicError
         );
 
-        let fee_liquidity = AmountUFP::try_from(pos.fee_liquidity()).map_err(|e| error_here!(e))?;
+        let fee_liquidity = AmountUFP::from(pos.fee_liquidity());
         let position_reward_ufp =
             acc_lp_fees_per_fee_liquidity_diff.map(|d| fee_liquidity * AmountUFP::from(d.value));
 
@@ -1644,7 +2172,7 @@ impl<T: Types> PoolV0<T> {
 
     pub(crate) fn position_reward(
         &self,
-        pos: &PositionV0<T>,
+        pos: &PositionV1<T>,
         since_creation: bool,
     ) -> Result<(Amount, Amount)> {
         self.position_reward_ufp(pos, since_creation)?
@@ -3,12 +3,29 @@ use itertools::multizip;
 use num_traits::Zero;
 
 use crate::dex::v0::{fee_liquidity_from_net_liquidity, gross_liquidity_from_net_liquidity};
-use crate::dex::{Error, FeeLevel, PositionV0, Side, Tick, Types};
+use crate::dex::{Error, FeeLevel, Position, PositionV0, PositionV1, Side, Tick, Types};
 use crate::{
     error_here, fp, AmountUFP, FeeLiquidityUFP, Float, GrossLiquidityUFP, NetLiquidityUFP,
 };
 
-impl<T: Types> PositionV0<T> {
+impl<T: Types> From<PositionV0<T>> for Position<T> {
+    fn from(legacy: PositionV0<T>) -> Self {
+        Position::V1(PositionV1 {
+            fee_level: legacy.fee_level,
+            net_liquidity: legacy.net_liquidity,
+            init_acc_lp_fees_per_fee_liquidity: legacy.init_acc_lp_fees_per_fee_liquidity,
+            unwithdrawn_acc_lp_fees_per_fee_liquidity: legacy
+                .unwithdrawn_acc_lp_fees_per_fee_liquidity,
+            init_sqrtprice: legacy.init_sqrtprice,
+            tick_bounds: legacy.tick_bounds,
+            locked_by: None,
+            locked_until: None,
+            phantom_t: legacy.phantom_t,
+        })
+    }
+}
+
+impl<T: Types> PositionV1<T> {
     pub fn net_liquidity(&self) -> NetLiquidityUFP {
         self.net_liquidity
     }
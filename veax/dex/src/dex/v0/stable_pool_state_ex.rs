@@ -0,0 +1,231 @@
+//! Stable swap math of `PoolKind::Stable` pools
+//!
+//! Pool keeps two-token Curve invariant `4A(x + y) + D = 4AD + D^3 / (4xy)`,
+//! where `x`, `y` are reserves and `A` is amplification coefficient.
+//! `D` and swap amounts are found with Newton's method in 512-bit integers;
+//! rounding always favors the pool.
+use super::classic_pool_state_ex::{
+    credit_shares, debit_shares, proportional_deposit, proportional_withdrawal, MIN_POOL_SHARES,
+};
+use crate::chain::{AccountId, Amount};
+use crate::dex::errors::{ErrorKind, Result};
+use crate::dex::traits::Map;
+use crate::dex::util_types::{Exact, Side};
+use crate::dex::{BasisPoints, Float, StablePoolV0, Types, BASIS_POINT_DIVISOR};
+use crate::fp::U512;
+use crate::{ensure_here, error_here};
+use num_traits::Zero;
+
+pub const MIN_AMPLIFICATION: u32 = 1;
+pub const MAX_AMPLIFICATION: u32 = 1_000_000;
+
+/// Newton's method converges in few iterations for any sane reserves,
+/// limit only protects against looping forever on broken state
+const MAX_ITERATIONS: usize = 255;
+
+impl<T: Types> StablePoolV0<T> {
+    /// Invariant `D` of current reserves, i.e. total amount of tokens when pool is balanced
+    pub fn invariant(&self) -> Result<U512> {
+        compute_d(self.reserves, self.amplification)
+    }
+
+    /// Marginal price of left token, in units of right token
+    pub fn spot_price(&self) -> Result<Float> {
+        if self.reserves.0.is_zero() || self.reserves.1.is_zero() {
+            return Ok(Float::zero());
+        }
+        let d = u128::try_from(self.invariant()?).map_or(Float::from(u128::MAX), Float::from);
+        let (x, y) = (Float::from(self.reserves.0), Float::from(self.reserves.1));
+        // Ratio of partial derivatives of invariant, both multiplied by `4 x^2 y^2`
+        let amplified = Float::from(u64::from(self.amplification) * 16) * x * x * y * y;
+        let d_cubed = d * d * d;
+        Ok((amplified + d_cubed * y) / (amplified + d_cubed * x))
+    }
+
+    /// Swap tokens with pool
+    ///
+    /// # Parameters
+    /// * `side` - side of input token
+    /// * `exact` - whether `amount` is exact input or exact output amount
+    /// * `amount` - amount of input or output token, depending on `exact`
+    /// * `protocol_fee_fraction` - fraction of charged fee which goes to protocol, in basis points
+    ///
+    /// # Returns
+    /// Input and output amounts
+    pub fn swap(
+        &mut self,
+        side: Side,
+        exact: Exact,
+        amount: Amount,
+        protocol_fee_fraction: BasisPoints,
+    ) -> Result<(Amount, Amount)> {
+        ensure_here!(!amount.is_zero(), ErrorKind::SwapAmountTooSmall);
+        let reserve_in = U512::from(self.reserves[side]);
+        let reserve_out = U512::from(self.reserves[side.opposite()]);
+        ensure_here!(
+            !reserve_in.is_zero() && !reserve_out.is_zero(),
+            ErrorKind::InsufficientLiquidity
+        );
+        let d = self.invariant()?;
+        let divisor = U512::from(BASIS_POINT_DIVISOR);
+        let fee_rate = U512::from(self.fee_rate);
+
+        let (amount_in, amount_out) = match exact {
+            Exact::In => {
+                let amount_in = U512::from(amount);
+                let net_in = amount_in * (divisor - fee_rate) / divisor;
+                let new_reserve_out = compute_y(reserve_in + net_in, d, self.amplification)?;
+                // Extra unit compensates rounding of `new_reserve_out`
+                let amount_out = reserve_out
+                    .checked_sub(new_reserve_out + 1)
+                    .unwrap_or_default();
+                ensure_here!(!amount_out.is_zero(), ErrorKind::SwapAmountTooSmall);
+                (amount_in, amount_out)
+            }
+            Exact::Out => {
+                let amount_out = U512::from(amount);
+                ensure_here!(amount_out < reserve_out, ErrorKind::InsufficientLiquidity);
+                let new_reserve_in = compute_y(reserve_out - amount_out, d, self.amplification)?;
+                let net_in = new_reserve_in.checked_sub(reserve_in).unwrap_or_default() + 1;
+                let amount_in = div_ceil(net_in * divisor, divisor - fee_rate);
+                (amount_in, amount_out)
+            }
+        };
+        let amount_in = to_amount(amount_in)?;
+        let amount_out = to_amount(amount_out)?;
+
+        let fee = U512::from(amount_in) * fee_rate / divisor;
+        let protocol_fee = (fee * U512::from(protocol_fee_fraction) / divisor).low_u128();
+
+        self.reserves[side] = self.reserves[side]
+            .checked_add(amount_in - protocol_fee)
+            .ok_or(error_here!(ErrorKind::SwapAmountTooLarge))?;
+        self.reserves[side.opposite()] -= amount_out;
+        self.protocol_fees[side] += protocol_fee;
+        Ok((amount_in, amount_out))
+    }
+
+    /// Deposit liquidity in current reserves ratio, minting shares to provider;
+    /// first deposit gets shares equal to invariant `D`
+    ///
+    /// # Returns
+    /// Actually deposited amounts and minted shares
+    pub fn add_liquidity(
+        &mut self,
+        account_id: &AccountId,
+        max_amounts: (Amount, Amount),
+    ) -> Result<((Amount, Amount), Amount)> {
+        ensure_here!(
+            !max_amounts.0.is_zero() && !max_amounts.1.is_zero(),
+            ErrorKind::InvalidParams
+        );
+        let (amounts, shares) = if self.total_shares.is_zero() {
+            let shares = to_amount(compute_d(max_amounts, self.amplification)?)?;
+            ensure_here!(shares > MIN_POOL_SHARES, ErrorKind::DepositTooSmall);
+            // Locked shares aren't owned by anyone
+            self.total_shares = MIN_POOL_SHARES;
+            (max_amounts, shares - MIN_POOL_SHARES)
+        } else {
+            proportional_deposit(self.reserves, self.total_shares, max_amounts)?
+        };
+
+        self.reserves.0 += amounts.0;
+        self.reserves.1 += amounts.1;
+        self.total_shares += shares;
+        credit_shares::<T>(&mut self.shares, account_id, shares)?;
+        Ok((amounts, shares))
+    }
+
+    /// Burn provider's shares, withdrawing proportional part of reserves
+    ///
+    /// # Returns
+    /// Withdrawn amounts
+    pub fn remove_liquidity(
+        &mut self,
+        account_id: &AccountId,
+        shares: Amount,
+    ) -> Result<(Amount, Amount)> {
+        debit_shares::<T>(&mut self.shares, account_id, shares)?;
+        let amounts = proportional_withdrawal(self.reserves, self.total_shares, shares)?;
+        self.reserves.0 -= amounts.0;
+        self.reserves.1 -= amounts.1;
+        self.total_shares -= shares;
+        Ok(amounts)
+    }
+
+    /// Take protocol fees accumulated since last withdrawal
+    pub fn withdraw_protocol_fee(&mut self) -> (Amount, Amount) {
+        std::mem::take(&mut self.protocol_fees)
+    }
+
+    /// Shares owned by liquidity provider
+    pub fn shares_of(&self, account_id: &AccountId) -> Amount {
+        self.shares
+            .inspect(account_id, |shares| *shares)
+            .unwrap_or_default()
+    }
+}
+
+/// Invariant `D` for specified reserves
+fn compute_d(reserves: (Amount, Amount), amplification: u32) -> Result<U512> {
+    let (x, y) = (U512::from(reserves.0), U512::from(reserves.1));
+    let sum = x + y;
+    if sum.is_zero() {
+        return Ok(U512::zero());
+    }
+    ensure_here!(
+        !x.is_zero() && !y.is_zero(),
+        ErrorKind::InsufficientLiquidity
+    );
+    let ann: U512 = U512::from(amplification) * 4;
+    let mut d = sum;
+    for _ in 0..MAX_ITERATIONS {
+        let d_p: U512 = d * d / (x * 2) * d / (y * 2);
+        let prev = d;
+        let numerator = (ann * sum + d_p * 2)
+            .checked_mul(d)
+            .ok_or(error_here!(ErrorKind::ConvOverflow))?;
+        d = numerator / ((ann - 1) * d + d_p * 3);
+        if abs_diff(d, prev) <= U512::one() {
+            return Ok(d);
+        }
+    }
+    Err(error_here!(ErrorKind::InternalLogicError))
+}
+
+/// Reserve of one token which keeps invariant `d`, given reserve `x` of the other one
+fn compute_y(x: U512, d: U512, amplification: u32) -> Result<U512> {
+    let ann: U512 = U512::from(amplification) * 4;
+    let c = d * d / (x * 2) * d / (ann * 2);
+    let b = x + d / ann;
+    let mut y = d;
+    for _ in 0..MAX_ITERATIONS {
+        let prev = y;
+        y = (y * y + c) / (y * 2 + b - d);
+        if abs_diff(y, prev) <= U512::one() {
+            return Ok(y);
+        }
+    }
+    Err(error_here!(ErrorKind::InternalLogicError))
+}
+
+fn abs_diff(a: U512, b: U512) -> U512 {
+    if a > b {
+        a - b
+    } else {
+        b - a
+    }
+}
+
+fn div_ceil(numerator: U512, denominator: U512) -> U512 {
+    let (quotient, remainder) = numerator.div_mod(denominator);
+    if remainder.is_zero() {
+        quotient
+    } else {
+        quotient + 1
+    }
+}
+
+fn to_amount(value: U512) -> Result<Amount> {
+    Amount::try_from(value).map_err(|_| error_here!(ErrorKind::ConvOverflow))
+}
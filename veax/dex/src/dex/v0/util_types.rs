@@ -1,4 +1,6 @@
 use super::{dex, NUM_FEE_LEVELS};
+use crate::chain::{Amount, AmountUFP};
+use crate::dex::utils::swap_if;
 use crate::dex::v0::eff_sqrtprice_opposite_side;
 use crate::dex::{EffTick, ErrorKind, Tick};
 use dex::{FeeLevel, Float, Side};
@@ -150,3 +152,32 @@ impl EffectiveSqrtPrice {
         (self.0, self.1) // todo: transmute?
     }
 }
+
+/// Cumulative statistics of swaps performed in pool since its creation
+#[derive(Clone, Copy, Default)]
+#[cfg_attr(not(target_arch = "wasm32"), derive(Debug))]
+#[cfg_attr(feature = "near", derive(BorshDeserialize, BorshSerialize))]
+pub struct PoolStats {
+    /// Total amounts of tokens paid into the pool by traders
+    pub volume_in: (Amount, Amount),
+    /// Total amounts of tokens paid out of the pool to traders
+    pub volume_out: (Amount, Amount),
+    /// Total LP fees charged in swaps, in respective tokens
+    pub lp_fees: (AmountUFP, AmountUFP),
+    /// Total protocol fees charged in swaps, in respective tokens
+    pub protocol_fees: (AmountUFP, AmountUFP),
+    /// Number of swaps performed
+    pub swap_count: u64,
+}
+
+impl PoolStats {
+    pub fn swap_if(self, is_swap: bool) -> Self {
+        Self {
+            volume_in: swap_if(is_swap, self.volume_in),
+            volume_out: swap_if(is_swap, self.volume_out),
+            lp_fees: swap_if(is_swap, self.lp_fees),
+            protocol_fees: swap_if(is_swap, self.protocol_fees),
+            swap_count: self.swap_count,
+        }
+    }
+}
@@ -46,6 +46,10 @@ impl AccountWithdrawTracker for CountingTracker {
     fn is_token_withdraw_in_progress(&self, _token_id: &TokenId) -> bool {
         self.0 > 0
     }
+
+    fn force_untrack(&mut self, _token_id: &TokenId, _amount: &Amount) -> bool {
+        self.untrack()
+    }
 }
 /// Full withdraw tracker which stores every withdraw as a pair of token id and amount
 ///
@@ -93,4 +97,14 @@ impl AccountWithdrawTracker for FullTracker {
             .binary_search_by_key(&token_id, |(tok, _)| tok)
             .is_ok()
     }
+
+    fn pending_withdrawals(&self) -> Vec<(TokenId, Amount)> {
+        self.0.clone()
+    }
+
+    fn force_untrack(&mut self, token_id: &TokenId, amount: &Amount) -> bool {
+        let tracked = self.is_tracked(token_id, amount);
+        self.untrack(token_id, amount);
+        tracked
+    }
 }
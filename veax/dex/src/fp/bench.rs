@@ -0,0 +1,74 @@
+//! Deterministic inputs for benchmarks of fixed-point arithmetic
+//!
+//! Inputs are produced by simple xorshift generator with fixed seed,
+//! so results of different benchmark runs are comparable.
+use super::{U128X128, U192X64, U256X256};
+use crate::chain::Float;
+
+/// Default seed of sample generator
+pub const SEED: u64 = 0x9E37_79B9_7F4A_7C15;
+
+/// Xorshift64* generator, good enough to produce benchmark inputs
+pub struct SampleRng(u64);
+
+impl Default for SampleRng {
+    fn default() -> Self {
+        Self::new(SEED)
+    }
+}
+
+impl SampleRng {
+    pub fn new(seed: u64) -> Self {
+        // Zero state is a fixed point of xorshift
+        Self(seed.max(1))
+    }
+
+    pub fn next_u64(&mut self) -> u64 {
+        self.0 ^= self.0 >> 12;
+        self.0 ^= self.0 << 25;
+        self.0 ^= self.0 >> 27;
+        self.0.wrapping_mul(0x2545_F491_4F6C_DD1D)
+    }
+
+    /// Array of random words, where upper `zero_words` words are zero;
+    /// used to keep values within range where operations don't overflow
+    fn words<const N: usize>(&mut self, zero_words: usize) -> [u64; N] {
+        let mut words = [0; N];
+        for word in words.iter_mut().take(N.saturating_sub(zero_words)) {
+            *word = self.next_u64();
+        }
+        words
+    }
+}
+
+/// Values which fit into lower half of `U256X256`, so that products of any two don't overflow
+pub fn u256x256_samples(count: usize) -> Vec<U256X256> {
+    let mut rng = SampleRng::default();
+    (0..count)
+        .map(|_| U256X256::from(rng.words::<8>(4)))
+        .collect()
+}
+
+/// Values which fit into lower half of `U192X64`, so that products of any two don't overflow
+pub fn u192x64_samples(count: usize) -> Vec<U192X64> {
+    let mut rng = SampleRng::default();
+    (0..count)
+        .map(|_| U192X64::from(rng.words::<4>(2)))
+        .collect()
+}
+
+/// Values which fit into lower half of `U128X128`, so that products of any two don't overflow
+pub fn u128x128_samples(count: usize) -> Vec<U128X128> {
+    let mut rng = SampleRng::default();
+    (0..count)
+        .map(|_| U128X128::from(rng.words::<4>(2)))
+        .collect()
+}
+
+/// Positive finite floats, spanning wide range of magnitudes
+pub fn float_samples(count: usize) -> Vec<Float> {
+    let mut rng = SampleRng::default();
+    (0..count)
+        .map(|_| Float::from(rng.next_u64() | 1) / Float::from((rng.next_u64() >> 32) | 1))
+        .collect()
+}
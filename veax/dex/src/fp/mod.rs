@@ -1,3 +1,5 @@
+#[cfg(feature = "bench")]
+pub mod bench;
 #[cfg(not(target_arch = "wasm32"))]
 pub(crate) mod display;
 mod error;
@@ -8,6 +10,7 @@ mod i256x128;
 mod i256x256;
 mod i256x320;
 mod i320x64;
+mod roots;
 mod signed;
 mod traits;
 mod try_float_to_ufp;
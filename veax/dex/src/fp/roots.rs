@@ -0,0 +1,190 @@
+//! Integer roots of wide unsigned integers
+//!
+//! Both roots use Newton iteration, which converges from above monotonically
+//! as long as initial guess is not less than the actual root. The guess is computed
+//! in `f64` from the topmost 64 bits of the argument, located with leading zeros count,
+//! so it's already precise to about 30 bits. This leaves only 3-5 full-width divisions
+//! for the widest types, compared to 8-10 when seeding with power of two.
+#![allow(
+    clippy::cast_possible_truncation,
+    clippy::cast_precision_loss,
+    clippy::cast_sign_loss
+)]
+
+use super::types::{U128, U256, U320, U384, U448, U512, U576, U768, U896};
+
+/// Integer square root of `u64`, i.e. `floor(sqrt(value))`
+fn isqrt_u64(value: u64) -> u64 {
+    let mut root = (value as f64).sqrt() as u64;
+    // `f64` has only 53 bits of mantissa, so result may be off by one in either direction
+    while u128::from(root) * u128::from(root) > u128::from(value) {
+        root -= 1;
+    }
+    while u128::from(root + 1) * u128::from(root + 1) <= u128::from(value) {
+        root += 1;
+    }
+    root
+}
+
+/// Integer cube root of `u64`, i.e. `floor(cbrt(value))`
+fn icbrt_u64(value: u64) -> u64 {
+    let cube = |v: u64| u128::from(v) * u128::from(v) * u128::from(v);
+    let mut root = (value as f64).cbrt() as u64;
+    while cube(root) > u128::from(value) {
+        root -= 1;
+    }
+    while cube(root + 1) <= u128::from(value) {
+        root += 1;
+    }
+    root
+}
+
+macro_rules! impl_roots {
+    ($($name:ident),+) => {$(
+        impl $name {
+            /// Integer square root, i.e. `floor(sqrt(self))`
+            pub fn isqrt(self) -> Self {
+                let bits = self.bits();
+                if bits <= 64 {
+                    return Self::from(isqrt_u64(self.low_u64()));
+                }
+                // Even shift, such that shifted value has 63 or 64 significant bits
+                let shift = (bits - 63) & !1;
+                let top = (self >> shift).low_u64();
+                // sqrt(self) < sqrt(top + 1) * 2^(shift / 2), margin covers `f64` rounding
+                let mut root = Self::from(isqrt_u64(top) + 1) << (shift / 2);
+                loop {
+                    let next = (root + self / root) >> 1;
+                    if next >= root {
+                        return root;
+                    }
+                    root = next;
+                }
+            }
+
+            /// Integer cube root, i.e. `floor(cbrt(self))`
+            pub fn icbrt(self) -> Self {
+                let bits = self.bits();
+                if bits <= 64 {
+                    return Self::from(icbrt_u64(self.low_u64()));
+                }
+                // Shift which is multiple of 3, such that shifted value has 61 to 63 significant bits
+                let shift = (bits - 61) / 3 * 3;
+                let top = (self >> shift).low_u64();
+                // cbrt(self) < cbrt(top + 1) * 2^(shift / 3), margin covers `f64` rounding
+                let mut root = Self::from(icbrt_u64(top) + 1) << (shift / 3);
+                let two = Self::from(2);
+                let three = Self::from(3);
+                loop {
+                    let next = (root * two + self / (root * root)) / three;
+                    if next >= root {
+                        return root;
+                    }
+                    root = next;
+                }
+            }
+        }
+    )+};
+}
+
+impl_roots!(U128, U256, U320, U384, U448, U512, U576, U768, U896);
+
+#[cfg(test)]
+mod tests {
+    use super::{U128, U256};
+
+    /// Checks `root` is `floor(value^(1/power))`; powers which overflow are greater than any value
+    fn assert_root(value: U256, root: U256, power: u32) {
+        let pow = |v: U256| (1..power).try_fold(v, |acc, _| acc.checked_mul(v));
+        assert!(
+            matches!(pow(root), Some(p) if p <= value),
+            "{root} is too big for {value}"
+        );
+        assert!(
+            !matches!(pow(root + 1), Some(p) if p <= value),
+            "{root} is too small for {value}"
+        );
+    }
+
+    /// Values around powers of 2 and around U256 limit, i.e. where shifts and `f64` rounding change
+    fn edge_values() -> Vec<U256> {
+        let mut values = vec![U256::zero(), U256::one(), U256::MAX, U256::MAX - 1];
+        for shift in 1..256 {
+            let power_of_two = U256::one() << shift;
+            values.extend([power_of_two - 1, power_of_two, power_of_two + 1]);
+        }
+        values
+    }
+
+    /// Roots around powers of 2, up to `2^max_bits`
+    fn edge_roots(max_bits: usize) -> Vec<U256> {
+        let mut roots = vec![U256::one(), U256::from(2), U256::from(3)];
+        for shift in 1..max_bits {
+            let power_of_two = U256::one() << shift;
+            roots.extend([power_of_two - 1, power_of_two, power_of_two + 1]);
+        }
+        roots
+    }
+
+    #[test]
+    fn isqrt_of_small_values() {
+        let roots = [0, 1, 1, 1, 2, 2, 2, 2, 2, 3];
+        for (value, root) in roots.into_iter().enumerate() {
+            assert_eq!(U256::from(value).isqrt(), U256::from(root));
+            assert_eq!(U128::from(value).isqrt(), U128::from(root));
+        }
+    }
+
+    #[test]
+    fn icbrt_of_small_values() {
+        let roots = [0, 1, 1, 1, 1, 1, 1, 1, 2, 2];
+        for (value, root) in roots.into_iter().enumerate() {
+            assert_eq!(U256::from(value).icbrt(), U256::from(root));
+            assert_eq!(U128::from(value).icbrt(), U128::from(root));
+        }
+    }
+
+    #[test]
+    fn isqrt_around_perfect_squares() {
+        for root in edge_roots(128) {
+            let square = root * root;
+            assert_eq!(square.isqrt(), root);
+            assert_eq!((square - 1).isqrt(), root - 1);
+            assert_eq!((square + 1).isqrt(), root);
+        }
+    }
+
+    #[test]
+    fn icbrt_around_perfect_cubes() {
+        for root in edge_roots(85) {
+            let cube = root * root * root;
+            assert_eq!(cube.icbrt(), root);
+            assert_eq!((cube - 1).icbrt(), root - 1);
+            assert_eq!((cube + 1).icbrt(), root);
+        }
+    }
+
+    #[test]
+    fn roots_around_powers_of_two() {
+        for value in edge_values() {
+            assert_root(value, value.isqrt(), 2);
+            assert_root(value, value.icbrt(), 3);
+        }
+    }
+
+    #[test]
+    fn roots_at_limits() {
+        assert_eq!(U256::MAX.isqrt(), U256::from(u128::MAX));
+        assert_eq!(U128::MAX.isqrt(), U128::from(u64::MAX));
+        assert_eq!(U256::from(u128::MAX).isqrt(), U256::from(u64::MAX));
+        // 2642245^3 < 2^64 <= 2642246^3
+        assert_eq!(U256::from(u64::MAX).icbrt(), U256::from(2_642_245));
+        // 6981463658331^3 < 2^128 <= 6981463658332^3
+        assert_eq!(U128::MAX.icbrt(), U128::from(6_981_463_658_331u64));
+        assert_eq!(
+            U256::from(u128::MAX).icbrt(),
+            U256::from(6_981_463_658_331u64)
+        );
+        assert_root(U256::MAX, U256::MAX.icbrt(), 3);
+    }
+}
@@ -85,7 +85,7 @@ macro_rules! impl_uint {
 
         impl IntegerSqrt for $name {
             fn integer_sqrt(&self) -> Self {
-                self.integer_sqrt()
+                self.isqrt()
             }
         }
 
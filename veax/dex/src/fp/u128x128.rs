@@ -50,7 +50,7 @@ impl U128X128 {
     }
 
     pub fn integer_sqrt(self) -> Self {
-        let integer_sqrt = self.0.integer_sqrt();
+        let integer_sqrt = self.0.isqrt();
         // as we taking the sqaure root of a fraction
         // it's denominator, namely 2^64 also gets a square root
         // which is 2^64, therefore to compensate this
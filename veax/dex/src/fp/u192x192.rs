@@ -51,27 +51,13 @@ impl U192X192 {
         // which is 2^96, therefore to compensate this
         // we need to multiply by 2^96, which is the same
         // as to make 32 left shifts
-        U192X192(self.0.integer_sqrt() << 96)
+        U192X192(self.0.isqrt() << 96)
     }
 
-    #[allow(unused_assignments)]
     pub fn integer_cbrt(self) -> Self {
-        let mut inner = self.0;
-
-        let mut s = 255;
-        let mut y = U384::zero();
-        let mut b = U384::zero();
-        let one = U384::one();
-        while s >= 0 {
-            y += y;
-            b = U384::from(3) * y * (y + one) + one;
-            if (inner >> s) >= b {
-                inner -= b << s;
-                y += one;
-            }
-            s -= 3;
-        }
-        U192X192(U384::from([0, 0, y.0[0], y.0[1], y.0[2], y.0[3]]))
+        // similarly to square root, denominator 2^192 turns into 2^64,
+        // so result is multiplied by 2^128 to compensate
+        U192X192(self.0.icbrt() << 128)
     }
 }
 
@@ -70,7 +70,7 @@ impl U256X320 {
         // which is 2^160, therefore to compensate this
         // we need to multiply by 2^160, which is the same
         // as to make 160 left shifts
-        U256X320(self.0.integer_sqrt() << 160)
+        U256X320(self.0.isqrt() << 160)
     }
 
     pub fn lower_part(self) -> U320 {
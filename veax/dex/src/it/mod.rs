@@ -0,0 +1,230 @@
+//! Helpers for integration tests against contract deployed to local NEAR sandbox
+//!
+//! Lets downstream integrators drive common flows in a few lines:
+//! ```ignore
+//! let sandbox = Sandbox::new(&std::fs::read(wasm_path())?).await?;
+//! let user = sandbox.create_user().await?;
+//! let token_a = sandbox.deploy_token(&ft_wasm, &[&user]).await?;
+//! let token_b = sandbox.deploy_token(&ft_wasm, &[&user]).await?;
+//! sandbox.register_tokens(&user, &[&token_a, &token_b]).await?;
+//! sandbox.deposit(&user, &token_a, 1_000_000).await?;
+//! sandbox.deposit(&user, &token_b, 1_000_000).await?;
+//! let position = sandbox.open_position(&user, &token_a, &token_b, 1, 500_000).await?;
+//! let swapped = sandbox.swap_exact_in(&user, &token_a, &token_b, 1_000).await?;
+//! sandbox.close_position(&user, position.position_id.0).await?;
+//! ```
+//! Token mocks are deployed from NEP-141 WASM with `new_default_meta` initializer,
+//! e.g. `fungible-token` example of near-sdk, which isn't shipped with this crate.
+use crate::chain::{AmountInOut, OpenPositionResult};
+use near_sdk::json_types::U128;
+use near_workspaces::network::Sandbox as SandboxNetwork;
+use near_workspaces::result::ExecutionFinalResult;
+use near_workspaces::types::NearToken;
+use near_workspaces::{Account, AccountId, Contract, Worker};
+use serde_json::json;
+use std::path::PathBuf;
+
+pub use anyhow::Result;
+
+/// Location of contract WASM built with `build-wasm-release.sh`, relative to crate root
+const DEFAULT_WASM: &str = "../../res/veax_dex.wasm";
+/// Protocol fee fraction of deployed contract, in basis points
+const PROTOCOL_FEE_FRACTION: u16 = 1300;
+
+/// Path to contract WASM, which may be overridden with `VEAX_WASM` environment variable
+pub fn wasm_path() -> PathBuf {
+    std::env::var_os("VEAX_WASM").map_or_else(
+        || PathBuf::from(env!("CARGO_MANIFEST_DIR")).join(DEFAULT_WASM),
+        PathBuf::from,
+    )
+}
+
+/// Sandbox with deployed and initialized contract
+pub struct Sandbox {
+    pub worker: Worker<SandboxNetwork>,
+    pub dex: Contract,
+    pub owner: Account,
+}
+
+impl Sandbox {
+    /// Start sandbox, deploy contract WASM and initialize it with new owner account
+    pub async fn new(wasm: &[u8]) -> Result<Self> {
+        let worker = near_workspaces::sandbox().await?;
+        let owner = worker.dev_create_account().await?;
+        let dex = worker.dev_deploy(wasm).await?;
+        dex.call("new")
+            .args_json(json!({
+                "owner_id": owner.id(),
+                "protocol_fee_fraction": PROTOCOL_FEE_FRACTION,
+            }))
+            .transact()
+            .await?
+            .into_result()?;
+        Ok(Self { worker, dex, owner })
+    }
+
+    /// Create account and register it in contract, with storage deposit of 1 NEAR
+    pub async fn create_user(&self) -> Result<Account> {
+        let user = self.worker.dev_create_account().await?;
+        user.call(self.dex.id(), "storage_deposit")
+            .args_json(json!({}))
+            .deposit(NearToken::from_near(1))
+            .transact()
+            .await?
+            .into_result()?;
+        Ok(user)
+    }
+
+    /// Deploy fungible token mock, register `holders` and contract in it,
+    /// and give each holder a quarter of the total supply
+    pub async fn deploy_token(&self, ft_wasm: &[u8], holders: &[&Account]) -> Result<Contract> {
+        let token = self.worker.dev_deploy(ft_wasm).await?;
+        token
+            .call("new_default_meta")
+            .args_json(json!({ "owner_id": token.id(), "total_supply": u128::MAX.to_string() }))
+            .transact()
+            .await?
+            .into_result()?;
+        for account_id in holders.iter().map(|h| h.id()).chain([self.dex.id()]) {
+            token
+                .call("storage_deposit")
+                .args_json(json!({ "account_id": account_id }))
+                .deposit(NearToken::from_millinear(10))
+                .transact()
+                .await?
+                .into_result()?;
+        }
+        for holder in holders {
+            token
+                .call("ft_transfer")
+                .args_json(
+                    json!({ "receiver_id": holder.id(), "amount": (u128::MAX / 4).to_string() }),
+                )
+                .deposit(NearToken::from_yoctonear(1))
+                .transact()
+                .await?
+                .into_result()?;
+        }
+        Ok(token)
+    }
+
+    /// Register tokens in user's account
+    pub async fn register_tokens(&self, user: &Account, tokens: &[&Contract]) -> Result<()> {
+        let token_ids: Vec<&AccountId> = tokens.iter().map(|token| token.id()).collect();
+        user.call(self.dex.id(), "register_tokens")
+            .args_json(json!({ "token_ids": token_ids }))
+            .deposit(NearToken::from_yoctonear(1))
+            .transact()
+            .await?
+            .into_result()?;
+        Ok(())
+    }
+
+    /// Deposit tokens to user's account with `ft_transfer_call`
+    pub async fn deposit(&self, user: &Account, token: &Contract, amount: u128) -> Result<()> {
+        user.call(token.id(), "ft_transfer_call")
+            .args_json(json!({
+                "receiver_id": self.dex.id(),
+                "amount": amount.to_string(),
+                "msg": "",
+            }))
+            .deposit(NearToken::from_yoctonear(1))
+            .max_gas()
+            .transact()
+            .await?
+            .into_result()?;
+        Ok(())
+    }
+
+    /// Open full-range position, depositing up to `max_amount` of each token
+    pub async fn open_position(
+        &self,
+        user: &Account,
+        token_a: &Contract,
+        token_b: &Contract,
+        fee_rate: u16,
+        max_amount: u128,
+    ) -> Result<OpenPositionResult> {
+        let outcome = user
+            .call(self.dex.id(), "open_position")
+            .args_json(json!({
+                "token_a": token_a.id(),
+                "token_b": token_b.id(),
+                "fee_rate": fee_rate,
+                "position": {
+                    "amount_ranges": [
+                        { "min": "0", "max": max_amount.to_string() },
+                        { "min": "0", "max": max_amount.to_string() },
+                    ],
+                    "ticks_range": [null, null],
+                },
+            }))
+            .deposit(NearToken::from_millinear(100))
+            .max_gas()
+            .transact()
+            .await?;
+        Ok(outcome.into_result()?.json()?)
+    }
+
+    /// Swap exactly `amount_in` of `token_in`, without limit on amount out
+    pub async fn swap_exact_in(
+        &self,
+        user: &Account,
+        token_in: &Contract,
+        token_out: &Contract,
+        amount_in: u128,
+    ) -> Result<AmountInOut> {
+        let outcome = user
+            .call(self.dex.id(), "swap_exact_in")
+            .args_json(json!({
+                "tokens": [token_in.id(), token_out.id()],
+                "amount_in": amount_in.to_string(),
+                "min_amount_out": "0",
+            }))
+            .deposit(NearToken::from_yoctonear(1))
+            .max_gas()
+            .transact()
+            .await?;
+        Ok(outcome.into_result()?.json()?)
+    }
+
+    /// Close user's position, crediting its tokens and fees to user's deposits
+    pub async fn close_position(&self, user: &Account, position_id: u64) -> Result<()> {
+        user.call(self.dex.id(), "close_position")
+            .args_json(json!({ "position_id": position_id.to_string() }))
+            .deposit(NearToken::from_yoctonear(1))
+            .max_gas()
+            .transact()
+            .await?
+            .into_result()?;
+        Ok(())
+    }
+
+    /// Withdraw tokens from user's deposits; outcome is returned as is,
+    /// since failed transfer is reported by logs rather than by failure of the call
+    pub async fn withdraw(
+        &self,
+        user: &Account,
+        token: &Contract,
+        amount: u128,
+    ) -> Result<ExecutionFinalResult> {
+        Ok(user
+            .call(self.dex.id(), "withdraw")
+            .args_json(json!({ "token_id": token.id(), "amount": amount.to_string() }))
+            .deposit(NearToken::from_yoctonear(1))
+            .max_gas()
+            .transact()
+            .await?)
+    }
+
+    /// Deposit of token in user's account
+    pub async fn balance(&self, user: &Account, token: &Contract) -> Result<u128> {
+        let balance: U128 = self
+            .dex
+            .view("get_deposit")
+            .args_json(json!({ "account_id": user.id(), "token_id": token.id() }))
+            .await?
+            .json()?;
+        Ok(balance.0)
+    }
+}
@@ -9,6 +9,10 @@ mod dex;
 pub mod fp;
 #[cfg(target = "wasm32")]
 mod fp;
+#[cfg(feature = "integration-tests")]
+pub mod it;
+#[cfg(feature = "native")]
+pub mod native;
 
 pub use chain::wasm::*;
 
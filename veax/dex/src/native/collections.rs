@@ -0,0 +1,411 @@
+//! Persistent collections over key-value storage, which implement
+//! `dex::Map`, `dex::OrderedMap` and `dex::Set`
+//!
+//! Each collection owns unique fixed-size key prefix; its entries are stored under
+//! prefix followed by order-preserving key encoding, so ordered map queries
+//! are served directly by storage.
+use super::store::{with_store, KvStore};
+use crate::chain::AccountId;
+use crate::dex::collection_helpers::{StorageRef, StorageRefIter, StorageRefPairIter};
+use crate::dex::tick::Tick;
+#[cfg(feature = "lp-tokens")]
+use crate::dex::LpTokenId;
+use crate::dex::{KeyAt, Map, MapRemoveKey, OrderedMap, PoolId, Result, Set};
+use near_sdk::borsh::{self, BorshDeserialize, BorshSerialize};
+use std::marker::PhantomData;
+use std::ops::Bound;
+
+/// Length of collection key prefix, in bytes
+pub const PREFIX_LEN: usize = 8;
+
+pub type Prefix = [u8; PREFIX_LEN];
+
+/// Encoding of collection keys into storage keys
+///
+/// Byte-wise order of encoded keys must match order of original keys
+/// for keys used in ordered maps.
+pub trait KvKey: Sized {
+    fn encode_key(&self) -> Vec<u8>;
+    fn decode_key(bytes: &[u8]) -> Option<Self>;
+}
+
+impl KvKey for u8 {
+    fn encode_key(&self) -> Vec<u8> {
+        vec![*self]
+    }
+
+    fn decode_key(bytes: &[u8]) -> Option<Self> {
+        match bytes {
+            [value] => Some(*value),
+            _ => None,
+        }
+    }
+}
+
+impl KvKey for u32 {
+    fn encode_key(&self) -> Vec<u8> {
+        self.to_be_bytes().to_vec()
+    }
+
+    fn decode_key(bytes: &[u8]) -> Option<Self> {
+        bytes.try_into().ok().map(u32::from_be_bytes)
+    }
+}
+
+impl KvKey for u64 {
+    fn encode_key(&self) -> Vec<u8> {
+        self.to_be_bytes().to_vec()
+    }
+
+    fn decode_key(bytes: &[u8]) -> Option<Self> {
+        bytes.try_into().ok().map(u64::from_be_bytes)
+    }
+}
+
+impl KvKey for Tick {
+    fn encode_key(&self) -> Vec<u8> {
+        // Flipping sign bit makes big-endian representation ordered like signed value
+        #[allow(clippy::cast_sign_loss)]
+        let biased = (self.index() as u32) ^ 0x8000_0000;
+        biased.to_be_bytes().to_vec()
+    }
+
+    fn decode_key(bytes: &[u8]) -> Option<Self> {
+        let biased = u32::from_be_bytes(bytes.try_into().ok()?);
+        #[allow(clippy::cast_possible_wrap)]
+        Tick::new((biased ^ 0x8000_0000) as i32).ok()
+    }
+}
+
+impl KvKey for [u8; 32] {
+    fn encode_key(&self) -> Vec<u8> {
+        self.to_vec()
+    }
+
+    fn decode_key(bytes: &[u8]) -> Option<Self> {
+        bytes.try_into().ok()
+    }
+}
+
+impl KvKey for AccountId {
+    fn encode_key(&self) -> Vec<u8> {
+        self.as_str().as_bytes().to_vec()
+    }
+
+    fn decode_key(bytes: &[u8]) -> Option<Self> {
+        std::str::from_utf8(bytes).ok()?.parse().ok()
+    }
+}
+
+impl KvKey for String {
+    fn encode_key(&self) -> Vec<u8> {
+        self.as_bytes().to_vec()
+    }
+
+    fn decode_key(bytes: &[u8]) -> Option<Self> {
+        String::from_utf8(bytes.to_vec()).ok()
+    }
+}
+
+impl KvKey for PoolId {
+    fn encode_key(&self) -> Vec<u8> {
+        // Serialization into memory buffer never fails
+        let Ok(bytes) = self.try_to_vec() else { unreachable!() };
+        bytes
+    }
+
+    fn decode_key(bytes: &[u8]) -> Option<Self> {
+        Self::try_from_slice(bytes).ok()
+    }
+}
+
+#[cfg(feature = "lp-tokens")]
+impl KvKey for LpTokenId {
+    fn encode_key(&self) -> Vec<u8> {
+        // Serialization into memory buffer never fails
+        let Ok(bytes) = self.try_to_vec() else { unreachable!() };
+        bytes
+    }
+
+    fn decode_key(bytes: &[u8]) -> Option<Self> {
+        Self::try_from_slice(bytes).ok()
+    }
+}
+
+/// Persistent map; only its prefix and length are stored inline
+#[derive(BorshSerialize, BorshDeserialize)]
+pub struct KvMap<K, V> {
+    prefix: Prefix,
+    len: u64,
+    #[borsh_skip]
+    phantom: PhantomData<(K, V)>,
+}
+
+impl<K: KvKey, V: BorshSerialize + BorshDeserialize> KvMap<K, V> {
+    pub fn new(prefix: Prefix) -> Self {
+        Self {
+            prefix,
+            len: 0,
+            phantom: PhantomData,
+        }
+    }
+
+    fn storage_key(&self, key: &K) -> Vec<u8> {
+        let mut storage_key = self.prefix.to_vec();
+        storage_key.extend(key.encode_key());
+        storage_key
+    }
+
+    fn load(&self, key: &K) -> Option<V> {
+        with_store(|store| store.get(&self.storage_key(key))).map(|bytes| decode_value(&bytes))
+    }
+
+    fn save(&mut self, key: &K, value: &V) {
+        // Serialization into memory buffer never fails
+        let Ok(bytes) = value.try_to_vec() else { unreachable!() };
+        let storage_key = self.storage_key(key);
+        let existed = with_store(|store| {
+            let existed = store.get(&storage_key).is_some();
+            store.set(&storage_key, &bytes);
+            existed
+        });
+        if !existed {
+            self.len += 1;
+        }
+    }
+
+    /// Find entry at specified position, using storage order
+    fn entry_at(&self, at: KeyAt<&K>) -> Option<(K, V)> {
+        let start = self.prefix.to_vec();
+        let end = next_prefix(&self.prefix);
+        let (range, reverse) = match at {
+            KeyAt::Min => ((Bound::Included(start), Bound::Excluded(end)), false),
+            KeyAt::Max => ((Bound::Included(start), Bound::Excluded(end)), true),
+            KeyAt::Above(key) => (
+                (Bound::Excluded(self.storage_key(key)), Bound::Excluded(end)),
+                false,
+            ),
+            KeyAt::Below(key) => (
+                (
+                    Bound::Included(start),
+                    Bound::Excluded(self.storage_key(key)),
+                ),
+                true,
+            ),
+        };
+        self.first_in(range, reverse)
+    }
+
+    fn first_in(&self, range: (Bound<Vec<u8>>, Bound<Vec<u8>>), reverse: bool) -> Option<(K, V)> {
+        fn as_refs(bound: &Bound<Vec<u8>>) -> Bound<&[u8]> {
+            match bound {
+                Bound::Included(key) => Bound::Included(key.as_slice()),
+                Bound::Excluded(key) => Bound::Excluded(key.as_slice()),
+                Bound::Unbounded => Bound::Unbounded,
+            }
+        }
+        let (storage_key, bytes) =
+            with_store(|store| store.first_in((as_refs(&range.0), as_refs(&range.1)), reverse))?;
+        let Some(key) = K::decode_key(&storage_key[PREFIX_LEN..]) else {
+            // If we get here, storage is irreversibly broken anyway
+            unreachable!()
+        };
+        Some((key, decode_value(&bytes)))
+    }
+}
+
+impl<K: KvKey, V: BorshSerialize + BorshDeserialize> Map for KvMap<K, V> {
+    type Key = K;
+    type Value = V;
+    type KeyRef<'a> = StorageRef<'a, K> where Self: 'a;
+    type ValueRef<'a> = StorageRef<'a, V> where Self: 'a;
+    type Iter<'a> = StorageRefPairIter<'a, K, V, KvMapIter<'a, K, V>> where Self: 'a;
+
+    fn iter(&self) -> Self::Iter<'_> {
+        StorageRefPairIter::new(KvMapIter {
+            map: self,
+            last_key: None,
+        })
+    }
+
+    fn clear(&mut self) {
+        while let Some((key, _)) = self.entry_at(KeyAt::Min) {
+            self.remove(&key);
+        }
+    }
+
+    fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    #[allow(clippy::cast_possible_truncation)]
+    fn len(&self) -> usize {
+        self.len as usize
+    }
+
+    fn contains_key(&self, key: &K) -> bool {
+        with_store(|store| store.get(&self.storage_key(key)).is_some())
+    }
+
+    fn inspect<R, F: FnOnce(&V) -> R>(&self, key: &K, inspect_fn: F) -> Option<R> {
+        self.load(key).map(|value| inspect_fn(&value))
+    }
+
+    fn update<R, F: FnOnce(&mut V) -> Result<R>>(
+        &mut self,
+        key: &K,
+        update_fn: F,
+    ) -> Option<Result<R>> {
+        self.load(key).map(|mut value| {
+            update_fn(&mut value).map(|result| {
+                self.save(key, &value);
+                result
+            })
+        })
+    }
+
+    fn update_or_insert<R, F, U>(&mut self, key: &K, factory_fn: F, update_fn: U) -> Result<R>
+    where
+        F: FnOnce() -> Result<V>,
+        U: FnOnce(&mut V, /* exists */ bool) -> Result<R>,
+    {
+        let (value, exists) = self
+            .load(key)
+            .map_or_else(|| (factory_fn(), false), |value| (Ok(value), true));
+        let mut value = value?;
+        let result = update_fn(&mut value, exists)?;
+        self.save(key, &value);
+        Ok(result)
+    }
+
+    fn insert(&mut self, key: K, value: V) {
+        self.save(&key, &value);
+    }
+}
+
+impl<K: KvKey, V: BorshSerialize + BorshDeserialize> MapRemoveKey for KvMap<K, V> {
+    fn remove(&mut self, key: &K) {
+        let storage_key = self.storage_key(key);
+        let existed = with_store(|store| {
+            let existed = store.get(&storage_key).is_some();
+            store.remove(&storage_key);
+            existed
+        });
+        if existed {
+            self.len -= 1;
+        }
+    }
+}
+
+impl<K: KvKey, V: BorshSerialize + BorshDeserialize> OrderedMap for KvMap<K, V> {
+    fn inspect_at<R, F: FnOnce(&K, &V) -> R>(&self, at: KeyAt<&K>, inspect_fn: F) -> Option<R> {
+        self.entry_at(at)
+            .map(|(key, value)| inspect_fn(&key, &value))
+    }
+
+    fn update_at<R, F: FnOnce(&K, &mut V) -> Result<R>>(
+        &mut self,
+        at: KeyAt<&K>,
+        update_fn: F,
+    ) -> Option<Result<R>> {
+        self.entry_at(at).map(|(key, mut value)| {
+            update_fn(&key, &mut value).map(|result| {
+                self.save(&key, &value);
+                result
+            })
+        })
+    }
+}
+
+/// Iterates over map entries in storage order, reading one entry at a time
+pub struct KvMapIter<'a, K, V> {
+    map: &'a KvMap<K, V>,
+    last_key: Option<Vec<u8>>,
+}
+
+impl<'a, K: KvKey, V: BorshSerialize + BorshDeserialize> Iterator for KvMapIter<'a, K, V> {
+    type Item = (K, V);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let start = match self.last_key.take() {
+            Some(last_key) => Bound::Excluded(last_key),
+            None => Bound::Included(self.map.prefix.to_vec()),
+        };
+        let end = Bound::Excluded(next_prefix(&self.map.prefix));
+        let (key, value) = self.map.first_in((start, end), false)?;
+        self.last_key = Some(self.map.storage_key(&key));
+        Some((key, value))
+    }
+}
+
+/// Persistent set; only its prefix and length are stored inline
+#[derive(BorshSerialize, BorshDeserialize)]
+pub struct KvSet<I>(KvMap<I, ()>);
+
+impl<I: KvKey> KvSet<I> {
+    pub fn new(prefix: Prefix) -> Self {
+        Self(KvMap::new(prefix))
+    }
+}
+
+impl<I: KvKey> Set for KvSet<I> {
+    type Item = I;
+    type Ref<'a> = StorageRef<'a, I> where Self: 'a;
+    type Iter<'a> = StorageRefIter<'a, I, KvSetIter<'a, I>> where Self: 'a;
+
+    fn iter(&self) -> Self::Iter<'_> {
+        StorageRefIter::new(KvSetIter(KvMapIter {
+            map: &self.0,
+            last_key: None,
+        }))
+    }
+
+    fn clear(&mut self) {
+        self.0.clear();
+    }
+
+    fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    fn len(&self) -> usize {
+        self.0.len()
+    }
+
+    fn contains_item(&self, item: &I) -> bool {
+        self.0.contains_key(item)
+    }
+
+    fn add_item(&mut self, item: I) {
+        self.0.insert(item, ());
+    }
+
+    fn remove_item(&mut self, item: &I) {
+        self.0.remove(item);
+    }
+}
+
+/// Iterates over set items in storage order
+pub struct KvSetIter<'a, I>(KvMapIter<'a, I, ()>);
+
+impl<'a, I: KvKey> Iterator for KvSetIter<'a, I> {
+    type Item = I;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.0.next().map(|(item, ())| item)
+    }
+}
+
+/// Smallest key which is greater than all keys starting with `prefix`
+fn next_prefix(prefix: &Prefix) -> Vec<u8> {
+    let next = u64::from_be_bytes(*prefix) + 1;
+    next.to_be_bytes().to_vec()
+}
+
+fn decode_value<V: BorshDeserialize>(bytes: &[u8]) -> V {
+    let Ok(value) = V::try_from_slice(bytes) else {
+        // If we get here, storage is irreversibly broken anyway
+        unreachable!()
+    };
+    value
+}
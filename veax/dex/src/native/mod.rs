@@ -0,0 +1,542 @@
+//! Native chain backend: runs contract as ordinary process with persistent key-value storage
+//!
+//! Serves as second implementation of `dex::Types`, `dex::State` and `dex::StateMut`
+//! besides NEAR one, and keeps chain-specific assumptions from leaking into `dex`.
+//! Token transfers are synchronous and only recorded, so embedding application
+//! is responsible for actually moving tokens.
+//!
+//! Collections access storage installed for current thread, see `store::install`;
+//! thus only one `NativeState` may be used on a thread at a time.
+use crate::chain::events::Logger as EventsLogger;
+use crate::chain::{AccountId, Amount, TokenId};
+use crate::dex::tick::Tick;
+use crate::dex::withdraw_trackers::NoopTracker;
+use crate::dex::{
+    self, Account, AccountExtra, ActionHash, AdminLogEntry, AutoHarvestQueue, BasisPoints,
+    ClassicPool, ConditionalOrder, Contract, DepositIntent, Dex, FeeAuction, FeeLevel, Integration,
+    IntegrationId, ItemFactory, LevelState, PendingApproval, Pool, PoolId, PoolStorageDeposit,
+    Position, PositionId, ReconciledBalance, Result, ScheduledAction, SessionKey, StablePool,
+    StateMembersMut, StateMut, SwapGuard, SwapNotification, TickState, Vault, WithdrawLimit,
+};
+use near_sdk::borsh::{self, BorshDeserialize, BorshSerialize};
+
+pub mod collections;
+pub mod store;
+
+pub use collections::{KvKey, KvMap, KvSet};
+pub use store::{KvStore, MemoryStore};
+
+use collections::Prefix;
+
+// Backend's own entries use all-zero prefix; collection prefixes start from 1
+/// Key of entry where prefix counter is stored
+const NEXT_PREFIX_KEY: &[u8] = b"\0\0\0\0\0\0\0\0next_prefix";
+/// Key of entry where contract root record is stored
+const CONTRACT_KEY: &[u8] = b"\0\0\0\0\0\0\0\0contract";
+
+/// Set of types which parametrize native contract state
+pub struct NativeTypes;
+
+pub type NativeContract = Contract<NativeTypes>;
+
+/// Account extra data; native backend doesn't charge for storage
+#[derive(Default, BorshSerialize, BorshDeserialize)]
+pub struct NativeAccountExtra;
+
+impl AccountExtra for NativeAccountExtra {}
+
+impl dex::Types for NativeTypes {
+    type Bound = ();
+    type ContractExtra = ();
+    type AccountsMap = KvMap<AccountId, Account<Self>>;
+    type TickStatesMap = KvMap<Tick, TickState<Self>>;
+    type AccountTokenBalancesMap = KvMap<TokenId, Amount>;
+    type AccountWithdrawTracker = NoopTracker;
+    type AccountExtra = NativeAccountExtra;
+    type AccountExtraV0 = NativeAccountExtra;
+    type PoolsMap = KvMap<PoolId, Pool<Self>>;
+    type PoolPositionsMap = KvMap<PositionId, Position<Self>>;
+    type PoolLevelsMap = KvMap<FeeLevel, LevelState<Self>>;
+    type AccountPositionsSet = KvSet<PositionId>;
+    type VerifiedTokensSet = KvSet<TokenId>;
+    type PositionToPoolMap = KvMap<PositionId, PoolId>;
+    type PositionOwnersMap = KvMap<PositionId, AccountId>;
+    type ScheduledActionsMap = KvMap<u64, ScheduledAction>;
+    type PendingApprovalsMap = KvMap<ActionHash, PendingApproval>;
+    type WithdrawLimitsMap = KvMap<TokenId, WithdrawLimit>;
+    type SwapGuardsMap = KvMap<PoolId, SwapGuard>;
+    type InsuranceReserveMap = KvMap<TokenId, Amount>;
+    type BannedTokensSet = KvSet<TokenId>;
+    type MinDepositAmountsMap = KvMap<PoolId, (Amount, Amount)>;
+    type ClassicPoolsMap = KvMap<PoolId, ClassicPool<Self>>;
+    type StablePoolsMap = KvMap<PoolId, StablePool<Self>>;
+    type PoolSharesMap = KvMap<AccountId, Amount>;
+    type OrdersMap = KvMap<u64, ConditionalOrder>;
+    type FeeAuctionsMap = KvMap<TokenId, FeeAuction>;
+    type IntegrationsMap = KvMap<IntegrationId, Integration>;
+    type DepositIntentsMap = KvMap<u64, DepositIntent>;
+    type AutoHarvestMap = KvMap<PoolId, AutoHarvestQueue>;
+    type ViewGrantsMap = KvMap<AccountId, Vec<AccountId>>;
+    type PoolAliasesMap = KvMap<String, PoolId>;
+    type AliasesByPoolMap = KvMap<PoolId, String>;
+    type PoolStorageDepositsMap = KvMap<PoolId, PoolStorageDeposit>;
+    type AdminLogMap = KvMap<u64, AdminLogEntry>;
+    type VaultsMap = KvMap<AccountId, Vault>;
+    type SessionKeysMap = KvMap<AccountId, Vec<SessionKey>>;
+    type ReconciledTokensMap = KvMap<TokenId, ReconciledBalance>;
+    type TokenInterfacesMap = KvMap<TokenId, bool>;
+    #[cfg(feature = "lp-tokens")]
+    type LpTokensMap = KvMap<dex::LpTokenId, dex::LpToken<Self>>;
+    type AccountIdSet = KvSet<AccountId>;
+    #[cfg(feature = "smart-routing")]
+    type TokenConnectionsMap = KvMap<TokenId, Self::TokensSet>;
+    #[cfg(feature = "smart-routing")]
+    type TokensSet = KvSet<TokenId>;
+    #[cfg(feature = "smart-routing")]
+    type TokensArraySet = KvSet<TokenId>;
+    #[cfg(feature = "smart-routing")]
+    type TopPoolsMap = KvMap<TokenId, Self::TokensArraySet>;
+}
+
+/// Creates collections with unique prefixes, allocated from counter kept in storage
+#[derive(Default)]
+pub struct NativeItemFactory;
+
+impl NativeItemFactory {
+    fn next_prefix() -> Prefix {
+        store::with_store(|store| {
+            let next = store.get(NEXT_PREFIX_KEY).map_or(1, |bytes| {
+                let Ok(next) = u64::try_from_slice(&bytes) else {
+                    // If we get here, storage is irreversibly broken anyway
+                    unreachable!()
+                };
+                next
+            });
+            store.set(NEXT_PREFIX_KEY, &(next + 1).to_le_bytes());
+            next.to_be_bytes()
+        })
+    }
+
+    fn new_map<K: KvKey, V: BorshSerialize + BorshDeserialize>() -> KvMap<K, V> {
+        KvMap::new(Self::next_prefix())
+    }
+
+    fn new_set<I: KvKey>() -> KvSet<I> {
+        KvSet::new(Self::next_prefix())
+    }
+}
+
+impl ItemFactory<NativeTypes> for NativeItemFactory {
+    fn new_accounts_map(&mut self) -> KvMap<AccountId, Account<NativeTypes>> {
+        Self::new_map()
+    }
+
+    fn new_tick_states_map(&mut self) -> KvMap<Tick, TickState<NativeTypes>> {
+        Self::new_map()
+    }
+
+    fn new_account_token_balances_map(&mut self) -> KvMap<TokenId, Amount> {
+        Self::new_map()
+    }
+
+    fn new_account_withdraw_tracker(&mut self) -> NoopTracker {
+        NoopTracker
+    }
+
+    fn new_pools_map(&mut self) -> KvMap<PoolId, Pool<NativeTypes>> {
+        Self::new_map()
+    }
+
+    fn new_pool_positions_map(&mut self) -> KvMap<PositionId, Position<NativeTypes>> {
+        Self::new_map()
+    }
+
+    fn new_pool_levels_map(&mut self) -> KvMap<FeeLevel, LevelState<NativeTypes>> {
+        Self::new_map()
+    }
+
+    fn new_account_positions_set(&mut self) -> KvSet<PositionId> {
+        Self::new_set()
+    }
+
+    fn new_verified_tokens_set(&mut self) -> KvSet<TokenId> {
+        Self::new_set()
+    }
+
+    fn new_position_to_pool_map(&mut self) -> KvMap<PositionId, PoolId> {
+        Self::new_map()
+    }
+
+    fn new_position_owners_map(&mut self) -> KvMap<PositionId, AccountId> {
+        Self::new_map()
+    }
+
+    fn new_guards(&mut self) -> KvSet<AccountId> {
+        Self::new_set()
+    }
+
+    fn new_scheduled_actions_map(&mut self) -> KvMap<u64, ScheduledAction> {
+        Self::new_map()
+    }
+
+    fn new_admins(&mut self) -> KvSet<AccountId> {
+        Self::new_set()
+    }
+
+    fn new_pending_approvals_map(&mut self) -> KvMap<ActionHash, PendingApproval> {
+        Self::new_map()
+    }
+
+    fn new_withdraw_limits_map(&mut self) -> KvMap<TokenId, WithdrawLimit> {
+        Self::new_map()
+    }
+
+    fn new_swap_guards_map(&mut self) -> KvMap<PoolId, SwapGuard> {
+        Self::new_map()
+    }
+
+    fn new_insurance_reserve_map(&mut self) -> KvMap<TokenId, Amount> {
+        Self::new_map()
+    }
+
+    fn new_banned_tokens_set(&mut self) -> KvSet<TokenId> {
+        Self::new_set()
+    }
+
+    fn new_min_deposit_amounts_map(&mut self) -> KvMap<PoolId, (Amount, Amount)> {
+        Self::new_map()
+    }
+
+    fn new_classic_pools_map(&mut self) -> KvMap<PoolId, ClassicPool<NativeTypes>> {
+        Self::new_map()
+    }
+
+    fn new_stable_pools_map(&mut self) -> KvMap<PoolId, StablePool<NativeTypes>> {
+        Self::new_map()
+    }
+
+    fn new_pool_shares_map(&mut self) -> KvMap<AccountId, Amount> {
+        Self::new_map()
+    }
+
+    fn new_orders_map(&mut self) -> KvMap<u64, ConditionalOrder> {
+        Self::new_map()
+    }
+
+    fn new_fee_auctions_map(&mut self) -> KvMap<TokenId, FeeAuction> {
+        Self::new_map()
+    }
+
+    fn new_integrations_map(&mut self) -> KvMap<IntegrationId, Integration> {
+        Self::new_map()
+    }
+
+    fn new_deposit_intents_map(&mut self) -> KvMap<u64, DepositIntent> {
+        Self::new_map()
+    }
+
+    fn new_auto_harvest_map(&mut self) -> KvMap<PoolId, AutoHarvestQueue> {
+        Self::new_map()
+    }
+
+    fn new_view_grants_map(&mut self) -> KvMap<AccountId, Vec<AccountId>> {
+        Self::new_map()
+    }
+
+    fn new_pool_aliases_map(&mut self) -> KvMap<String, PoolId> {
+        Self::new_map()
+    }
+
+    fn new_aliases_by_pool_map(&mut self) -> KvMap<PoolId, String> {
+        Self::new_map()
+    }
+
+    fn new_pool_storage_deposits_map(&mut self) -> KvMap<PoolId, PoolStorageDeposit> {
+        Self::new_map()
+    }
+
+    fn new_admin_log_map(&mut self) -> KvMap<u64, AdminLogEntry> {
+        Self::new_map()
+    }
+
+    fn new_vaults_map(&mut self) -> KvMap<AccountId, Vault> {
+        Self::new_map()
+    }
+
+    fn new_session_keys_map(&mut self) -> KvMap<AccountId, Vec<SessionKey>> {
+        Self::new_map()
+    }
+
+    fn new_reconciled_tokens_map(&mut self) -> KvMap<TokenId, ReconciledBalance> {
+        Self::new_map()
+    }
+
+    fn new_token_interfaces_map(&mut self) -> KvMap<TokenId, bool> {
+        Self::new_map()
+    }
+
+    #[cfg(feature = "lp-tokens")]
+    fn new_lp_tokens_map(&mut self) -> KvMap<dex::LpTokenId, dex::LpToken<NativeTypes>> {
+        Self::new_map()
+    }
+
+    #[cfg(feature = "smart-routing")]
+    fn new_token_connections_map(&mut self) -> KvMap<TokenId, KvSet<TokenId>> {
+        Self::new_map()
+    }
+
+    #[cfg(feature = "smart-routing")]
+    fn new_tokens_set(&mut self) -> KvSet<TokenId> {
+        Self::new_set()
+    }
+
+    #[cfg(feature = "smart-routing")]
+    fn new_top_pools_map(&mut self) -> KvMap<TokenId, KvSet<TokenId>> {
+        Self::new_map()
+    }
+
+    #[cfg(feature = "smart-routing")]
+    fn new_tokens_array_set(&mut self) -> KvSet<TokenId> {
+        Self::new_set()
+    }
+}
+
+/// Token transfer performed by contract to some account
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct NativeTransfer {
+    pub account_id: AccountId,
+    pub token_id: TokenId,
+    pub amount: Amount,
+}
+
+/// Parameters of block in which calls are performed
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct NativeBlock {
+    /// Block time, in seconds
+    pub timestamp: u64,
+    pub height: u64,
+    pub random_seed: [u8; 32],
+}
+
+/// Contract state persisted in key-value storage
+///
+/// Events are emitted in the same format as on NEAR, through `chain::log`.
+pub struct NativeState {
+    contract: NativeContract,
+    item_factory: NativeItemFactory,
+    logger: EventsLogger,
+    /// Account which performs current call
+    caller_id: AccountId,
+    /// Current block parameters, set by embedding application
+    block: NativeBlock,
+    /// Token transfers performed by contract, in order; drained by embedding application
+    pub transfers: Vec<NativeTransfer>,
+    /// Notifications sent to swap hook, in order; drained by embedding application
+    pub swap_notifications: Vec<(AccountId, SwapNotification)>,
+}
+
+impl dex::State<NativeTypes> for NativeState {
+    fn contract(&self) -> &NativeContract {
+        &self.contract
+    }
+}
+
+impl StateMut<NativeTypes> for NativeState {
+    type SendTokensResult = ();
+    type SendTokensExtraParam = ();
+
+    fn members_mut(&mut self) -> StateMembersMut<'_, NativeTypes> {
+        let Contract::V1(ref contract) = self.contract;
+        self.logger.set_filter(contract.event_filter);
+        StateMembersMut {
+            contract: &mut self.contract,
+            item_factory: &mut self.item_factory,
+            logger: &mut self.logger,
+        }
+    }
+
+    fn send_tokens(
+        &mut self,
+        account_id: &AccountId,
+        token_id: &TokenId,
+        amount: Amount,
+        unregister_token: bool,
+        _extra: (),
+    ) {
+        self.transfers.push(NativeTransfer {
+            account_id: account_id.clone(),
+            token_id: token_id.clone(),
+            amount,
+        });
+        if unregister_token {
+            // Transfer is recorded unconditionally, so token can be unregistered right away.
+            // Unregistration is best-effort, its failure doesn't affect transfer
+            let account_id = account_id.clone();
+            let _ = self.as_dex_mut().unregister_tokens(&account_id, [token_id]);
+        }
+    }
+
+    fn notify_swap_hook(&mut self, hook_id: &AccountId, notification: SwapNotification) {
+        self.swap_notifications
+            .push((hook_id.clone(), notification));
+    }
+
+    fn get_initiator_id(&self) -> AccountId {
+        self.caller_id.clone()
+    }
+
+    fn get_caller_id(&self) -> AccountId {
+        self.caller_id.clone()
+    }
+
+    fn get_block_timestamp(&self) -> u64 {
+        self.block.timestamp
+    }
+
+    fn get_block_height(&self) -> u64 {
+        self.block.height
+    }
+
+    fn get_random_seed(&self) -> [u8; 32] {
+        self.block.random_seed
+    }
+
+    fn get_signer_public_key(&self) -> Option<String> {
+        None
+    }
+}
+
+impl NativeState {
+    /// Install `store` for current thread and create new contract in it, owned by `owner_id`
+    ///
+    /// Any contract previously stored in `store` is abandoned.
+    pub fn new(
+        store: Box<dyn KvStore>,
+        owner_id: AccountId,
+        protocol_fee_fraction: BasisPoints,
+    ) -> Result<Self> {
+        store::install(store);
+        let mut item_factory = NativeItemFactory;
+        let contract = item_factory.new_contract(
+            owner_id.clone(),
+            protocol_fee_fraction,
+            [1, 2, 4, 8, 16, 32, 64, 128],
+        );
+        let contract = match contract {
+            Ok(contract) => contract,
+            Err(error) => {
+                store::uninstall();
+                return Err(error);
+            }
+        };
+        let state = Self::with_contract(contract, owner_id);
+        state.save_contract();
+        store::with_store(store::TransactionalStore::commit);
+        Ok(state)
+    }
+
+    /// Install `store` for current thread and load contract from it
+    ///
+    /// # Returns
+    /// * `Some(state)` - if contract was found in storage
+    /// * `None` - if storage contains no contract; storage is uninstalled in this case
+    pub fn open(store: Box<dyn KvStore>) -> Option<Self> {
+        store::install(store);
+        let Some(contract) = Self::load_contract() else {
+            store::uninstall();
+            return None;
+        };
+        let Contract::V1(ref contract_v0) = contract;
+        let owner_id = contract_v0.owner_id.clone();
+        Some(Self::with_contract(contract, owner_id))
+    }
+
+    /// Uninstall storage from current thread and return it
+    pub fn close(self) -> Box<dyn KvStore> {
+        let Some(store) = store::uninstall() else {
+            // Storage is installed for the whole lifetime of state
+            unreachable!()
+        };
+        store
+    }
+
+    /// Set block parameters reported for subsequent calls
+    pub fn set_block(&mut self, block: NativeBlock) {
+        self.block = block;
+    }
+
+    /// Perform call on behalf of `caller_id`
+    ///
+    /// If call succeeds, all changes are committed to storage at once.
+    /// Otherwise, storage, contract root record, transfers and notifications are rolled back,
+    /// the same way blockchain reverts failed transaction.
+    pub fn call<R>(
+        &mut self,
+        caller_id: &AccountId,
+        call_fn: impl FnOnce(&mut Dex<NativeTypes, Self, &mut Self>) -> Result<R>,
+    ) -> Result<R> {
+        let (transfers, notifications) = (self.transfers.len(), self.swap_notifications.len());
+
+        self.caller_id = caller_id.clone();
+        let result = call_fn(&mut self.as_dex_mut());
+
+        if result.is_ok() {
+            self.save_contract();
+            store::with_store(store::TransactionalStore::commit);
+        } else {
+            store::with_store(store::TransactionalStore::rollback);
+            let Some(contract) = Self::load_contract() else {
+                // Contract root was committed when state was created
+                unreachable!()
+            };
+            self.contract = contract;
+            self.transfers.truncate(transfers);
+            self.swap_notifications.truncate(notifications);
+        }
+        result
+    }
+
+    /// Credit tokens, transferred into contract by embedding application, to account's deposit,
+    /// registering account and token if needed
+    pub fn deposit(
+        &mut self,
+        account_id: &AccountId,
+        token_id: &TokenId,
+        amount: Amount,
+    ) -> Result<Amount> {
+        self.call(account_id, |dex| {
+            dex.register_account_and_then(account_id.clone(), |_, Account::V1(account), _| {
+                account.register_tokens([token_id]);
+                Ok(())
+            })?;
+            dex.deposit(account_id, token_id, amount)
+        })
+    }
+
+    fn with_contract(contract: NativeContract, caller_id: AccountId) -> Self {
+        Self {
+            contract,
+            item_factory: NativeItemFactory,
+            logger: EventsLogger::new(),
+            caller_id,
+            block: NativeBlock::default(),
+            transfers: Vec::new(),
+            swap_notifications: Vec::new(),
+        }
+    }
+
+    fn save_contract(&self) {
+        // Serialization into memory buffer never fails
+        let Ok(bytes) = self.contract.try_to_vec() else { unreachable!() };
+        store::with_store(|store| store.set(CONTRACT_KEY, &bytes));
+    }
+
+    fn load_contract() -> Option<NativeContract> {
+        let bytes = store::with_store(|store| store.get(CONTRACT_KEY))?;
+        let Ok(contract) = NativeContract::try_from_slice(&bytes) else {
+            // If we get here, storage is irreversibly broken anyway
+            unreachable!()
+        };
+        Some(contract)
+    }
+}
@@ -0,0 +1,197 @@
+//! Key-value storage used by native backend collections
+//!
+//! Collections don't keep reference to storage, the same way NEAR collections
+//! don't keep reference to blockchain environment. Instead, storage is installed
+//! for current thread and accessed through `with_store`.
+use std::cell::RefCell;
+use std::collections::BTreeMap;
+use std::ops::Bound;
+
+/// Range of keys, as pair of bounds
+pub type KeyRange<'a> = (Bound<&'a [u8]>, Bound<&'a [u8]>);
+
+/// Ordered key-value storage
+///
+/// Keys are compared lexicographically, as byte strings
+pub trait KvStore {
+    /// Read value stored under key
+    fn get(&self, key: &[u8]) -> Option<Vec<u8>>;
+    /// Write value under key, replacing existing one
+    fn set(&mut self, key: &[u8], value: &[u8]);
+    /// Remove value stored under key, if any
+    fn remove(&mut self, key: &[u8]);
+    /// Find first entry within range, or last one if `reverse` is true
+    fn first_in(&self, range: KeyRange<'_>, reverse: bool) -> Option<(Vec<u8>, Vec<u8>)>;
+}
+
+/// In-memory storage, primarily for tests and short-lived processes
+#[derive(Clone, Debug, Default)]
+pub struct MemoryStore(BTreeMap<Vec<u8>, Vec<u8>>);
+
+impl KvStore for MemoryStore {
+    fn get(&self, key: &[u8]) -> Option<Vec<u8>> {
+        self.0.get(key).cloned()
+    }
+
+    fn set(&mut self, key: &[u8], value: &[u8]) {
+        self.0.insert(key.to_vec(), value.to_vec());
+    }
+
+    fn remove(&mut self, key: &[u8]) {
+        self.0.remove(key);
+    }
+
+    fn first_in(&self, range: KeyRange<'_>, reverse: bool) -> Option<(Vec<u8>, Vec<u8>)> {
+        let mut entries = self.0.range::<[u8], _>(range);
+        let entry = if reverse {
+            entries.next_back()
+        } else {
+            entries.next()
+        };
+        entry.map(|(key, value)| (key.clone(), value.clone()))
+    }
+}
+
+/// Storage with pending changes, which are either written into underlying storage
+/// at once or discarded
+///
+/// Plays the role of blockchain transaction: failed contract call must leave
+/// no traces in storage.
+pub struct TransactionalStore {
+    base: Box<dyn KvStore>,
+    /// Changes since last commit; `None` marks removed entry
+    changes: BTreeMap<Vec<u8>, Option<Vec<u8>>>,
+}
+
+impl TransactionalStore {
+    pub fn new(base: Box<dyn KvStore>) -> Self {
+        Self {
+            base,
+            changes: BTreeMap::new(),
+        }
+    }
+
+    /// Write all pending changes into underlying storage
+    pub fn commit(&mut self) {
+        for (key, value) in std::mem::take(&mut self.changes) {
+            match value {
+                Some(value) => self.base.set(&key, &value),
+                None => self.base.remove(&key),
+            }
+        }
+    }
+
+    /// Discard all pending changes
+    pub fn rollback(&mut self) {
+        self.changes.clear();
+    }
+
+    /// Discard pending changes and return underlying storage
+    pub fn into_inner(self) -> Box<dyn KvStore> {
+        self.base
+    }
+}
+
+impl KvStore for TransactionalStore {
+    fn get(&self, key: &[u8]) -> Option<Vec<u8>> {
+        match self.changes.get(key) {
+            Some(change) => change.clone(),
+            None => self.base.get(key),
+        }
+    }
+
+    fn set(&mut self, key: &[u8], value: &[u8]) {
+        self.changes.insert(key.to_vec(), Some(value.to_vec()));
+    }
+
+    fn remove(&mut self, key: &[u8]) {
+        self.changes.insert(key.to_vec(), None);
+    }
+
+    fn first_in(&self, range: KeyRange<'_>, reverse: bool) -> Option<(Vec<u8>, Vec<u8>)> {
+        // Candidates from base storage and from pending changes are merged;
+        // pending change always wins, and removed entries are skipped
+        let mut range = (to_owned_bound(range.0), to_owned_bound(range.1));
+        loop {
+            let as_refs = (as_ref_bound(&range.0), as_ref_bound(&range.1));
+            let base = self.base.first_in(as_refs, reverse);
+            let mut changes = self.changes.range::<[u8], _>(as_refs);
+            let change = if reverse {
+                changes.next_back()
+            } else {
+                changes.next()
+            };
+
+            let (key, value) = match (base, change) {
+                (None, None) => return None,
+                (Some((key, value)), None) => return Some((key, value)),
+                (None, Some((key, value))) => (key.clone(), value.clone()),
+                (Some((base_key, base_value)), Some((key, value))) => {
+                    let change_first = if reverse {
+                        *key >= base_key
+                    } else {
+                        *key <= base_key
+                    };
+                    if !change_first {
+                        return Some((base_key, base_value));
+                    }
+                    (key.clone(), value.clone())
+                }
+            };
+            match value {
+                Some(value) => return Some((key, value)),
+                None if reverse => range.1 = Bound::Excluded(key),
+                None => range.0 = Bound::Excluded(key),
+            }
+        }
+    }
+}
+
+fn to_owned_bound(bound: Bound<&[u8]>) -> Bound<Vec<u8>> {
+    match bound {
+        Bound::Included(key) => Bound::Included(key.to_vec()),
+        Bound::Excluded(key) => Bound::Excluded(key.to_vec()),
+        Bound::Unbounded => Bound::Unbounded,
+    }
+}
+
+fn as_ref_bound(bound: &Bound<Vec<u8>>) -> Bound<&[u8]> {
+    match bound {
+        Bound::Included(key) => Bound::Included(key),
+        Bound::Excluded(key) => Bound::Excluded(key),
+        Bound::Unbounded => Bound::Unbounded,
+    }
+}
+
+thread_local! {
+    static STORE: RefCell<Option<TransactionalStore>> = const { RefCell::new(None) };
+}
+
+/// Make storage current for this thread, returning previously installed one
+pub fn install(store: Box<dyn KvStore>) -> Option<Box<dyn KvStore>> {
+    STORE.with(|cell| {
+        cell.replace(Some(TransactionalStore::new(store)))
+            .map(TransactionalStore::into_inner)
+    })
+}
+
+/// Remove current storage from this thread, discarding uncommitted changes
+pub fn uninstall() -> Option<Box<dyn KvStore>> {
+    STORE.with(|cell| cell.take().map(TransactionalStore::into_inner))
+}
+
+/// Access current storage
+///
+/// Callback must not access storage recursively.
+///
+/// # Panics
+/// If no storage is installed for current thread
+pub fn with_store<R>(store_fn: impl FnOnce(&mut TransactionalStore) -> R) -> R {
+    STORE.with(|cell| {
+        let mut store = cell.borrow_mut();
+        let Some(store) = store.as_mut() else {
+            panic!("No key-value storage installed for current thread");
+        };
+        store_fn(store)
+    })
+}
@@ -1,11 +1,21 @@
+use std::collections::BTreeMap;
+use std::iter::Take;
 use std::marker::PhantomData;
+use std::ops::Range;
 
 use near_sdk::borsh::{self, BorshDeserialize, BorshSerialize};
-use near_sdk::{env, IntoStorageKey};
+use near_sdk::IntoStorageKey;
 
 use crate::raw_storage_key;
+use crate::storage_cache::StorageCache;
 
 /// Key-value map that allows iteration and removing entries.
+///
+/// Entries are iterated from the most recently inserted one; order of keys isn't maintained.
+///
+/// Nodes are cached for the duration of transaction, so each node is read from storage at most once,
+/// and storage reads listed in methods' docs happen only on first access to the node.
+/// Writes go to storage right away.
 #[derive(BorshSerialize, BorshDeserialize)]
 pub struct DoublyLinkedListMap<K, V>
 where
@@ -24,6 +34,10 @@ where
     head: Option<(K, Vec<u8>)>,
 
     _phantom_data: PhantomData<V>,
+
+    /// Nodes read or written during current transaction
+    #[borsh_skip]
+    cache: StorageCache,
 }
 
 #[derive(BorshSerialize, BorshDeserialize)]
@@ -47,6 +61,7 @@ where
             length: 0,
             head: None,
             _phantom_data: PhantomData,
+            cache: StorageCache::default(),
         }
     }
 
@@ -84,6 +99,58 @@ where
         }
     }
 
+    /// Inserts all key-value pairs into map; later pairs override earlier ones with the same key.
+    ///
+    /// New entries are linked in front of the list in a single pass, so that each node is written once,
+    /// instead of pushing the head node into storage on every insertion.
+    /// Performs up to 1 storage read and up to 1 storage write per pair.
+    pub fn extend<I: IntoIterator<Item = (K, V)>>(&mut self, pairs: I) {
+        // Most recently inserted node, which becomes the head;
+        // it's written once its `prev_key` is known, i.e. when the next new node arrives
+        let mut pending: Option<(K, Node<K, V>)> = None;
+
+        for (key, value) in pairs {
+            if let Some((pending_key, pending_node)) = pending.as_mut() {
+                if *pending_key == key {
+                    pending_node.value = value;
+                    continue;
+                }
+            }
+            if let Some(mut node) = self.get_node(&key) {
+                // Update value if the map already contains the key.
+                node.value = value;
+                self.set_node(&key, &node);
+                continue;
+            }
+
+            let next_key = match pending.take() {
+                Some((pending_key, mut pending_node)) => {
+                    pending_node.prev_key = Some(key.clone());
+                    self.set_stored_node(&pending_key, &pending_node);
+                    Some(pending_key)
+                }
+                None => self.head.take().map(|(head_key, head_node_bytes)| {
+                    let mut head_node = Self::deserialize_node(&head_node_bytes);
+                    // Push head node into storage.
+                    head_node.prev_key = Some(key.clone());
+                    self.set_stored_node(&head_key, &head_node);
+                    head_key
+                }),
+            };
+            let node = Node {
+                value,
+                prev_key: None,
+                next_key,
+            };
+            pending = Some((key, node));
+            self.length += 1;
+        }
+
+        if let Some((key, node)) = pending {
+            self.head = Some((key, Self::serialize_node(&node)));
+        }
+    }
+
     /// Checks whether the map contains a key.
     ///
     /// Performs up to 1 storage read.
@@ -144,6 +211,91 @@ where
         Some(node.value)
     }
 
+    /// Removes all specified keys from the map, returns number of removed entries.
+    ///
+    /// Neighbours of each run of adjacent removed entries are relinked once per run,
+    /// rather than once per removed entry.
+    /// Performs up to 1 storage read and up to 1 storage remove per key,
+    /// plus up to 2 storage reads and up to 2 storage writes per run of adjacent removed entries.
+    pub fn remove_many<'k, I>(&mut self, keys: I) -> usize
+    where
+        I: IntoIterator<Item = &'k K>,
+        K: 'k,
+    {
+        // Removed nodes by raw storage key, which allows to check if any key is being removed
+        let mut removed = BTreeMap::new();
+        for key in keys {
+            let raw_key = raw_storage_key(&self.key_prefix, key);
+            if removed.contains_key(&raw_key) {
+                continue;
+            }
+            if let Some(node) = self.get_node(key) {
+                removed.insert(raw_key, (key.clone(), node));
+            }
+        }
+
+        // Nearest kept neighbours of each run of adjacent removed nodes
+        let mut links = Vec::new();
+        for (_key, node) in removed.values() {
+            let is_removed =
+                |key: &K| removed.contains_key(&raw_storage_key(&self.key_prefix, key));
+            if node.prev_key.as_ref().map_or(false, is_removed) {
+                // Not the first node in run
+                continue;
+            }
+            let mut next_key = node.next_key.clone();
+            while let Some((_next_key, next_node)) = next_key
+                .as_ref()
+                .and_then(|key| removed.get(&raw_storage_key(&self.key_prefix, key)))
+            {
+                next_key = next_node.next_key.clone();
+            }
+            links.push((node.prev_key.clone(), next_key));
+        }
+
+        let head_key = self
+            .head
+            .as_ref()
+            .map(|(head_key, _head_node)| head_key.clone());
+        for (key, _node) in removed.values() {
+            // Head node isn't in storage, it's replaced when relinking
+            if head_key.as_ref() != Some(key) {
+                self.remove_stored_node(key);
+            }
+        }
+
+        for (prev_key, next_key) in links {
+            match (prev_key, next_key) {
+                (Some(prev_key), next_key) => {
+                    let mut prev_node = self.get_node(&prev_key).unwrap();
+                    prev_node.next_key = next_key.clone();
+                    self.set_node(&prev_key, &prev_node);
+
+                    if let Some(next_key) = next_key {
+                        let mut next_node = self.get_node(&next_key).unwrap();
+                        next_node.prev_key = Some(prev_key);
+                        self.set_node(&next_key, &next_node);
+                    }
+                }
+                (None, Some(next_key)) => {
+                    // Run started at head node, pop the next kept node from the storage.
+                    let mut next_node = self.get_stored_node(&next_key).unwrap();
+                    self.remove_stored_node(&next_key);
+
+                    next_node.prev_key = None;
+                    self.head = Some((next_key, Self::serialize_node(&next_node)));
+                }
+                (None, None) => {
+                    // All nodes were removed, clear the list.
+                    self.head = None;
+                }
+            }
+        }
+
+        self.length -= removed.len() as u64;
+        removed.len()
+    }
+
     /// Removes and returns a key-value pair from the map.
     ///
     /// Performs up to 1 storage read and up to 1 storage remove.
@@ -160,17 +312,71 @@ where
         DoublyLinkedListMapIter {
             map: self,
             key: self.head.as_ref().map(|head| head.0.clone()),
+            end: None,
         }
     }
 
+    /// Returns iterator over key-value pairs, starting from specified key (inclusive)
+    /// and continuing in iteration order. Iterator is empty if map doesn't contain the key.
+    ///
+    /// Performs up to 1 storage read, and up to 1 storage read per `.next()` call.
+    pub fn iter_from(&self, key: &K) -> DoublyLinkedListMapIter<'_, K, V> {
+        DoublyLinkedListMapIter {
+            map: self,
+            key: self.contains_key(key).then(|| key.clone()),
+            end: None,
+        }
+    }
+
+    /// Returns iterator over key-value pairs, starting from `range.start` key (inclusive)
+    /// and stopping before `range.end` key, in iteration order.
+    /// Iterator is empty if map doesn't contain start key,
+    /// and runs until the last entry if map doesn't contain end key.
+    ///
+    /// NB: bounds are positions in iteration order, i.e. from the most recently inserted entry
+    /// to the least recent one, not in order of keys. Entries whose keys lie between the bounds
+    /// aren't yielded unless they were inserted between the bounds' entries, and if `range.end`
+    /// was inserted after `range.start`, iterator runs until the last entry.
+    ///
+    /// Performs up to 1 storage read, and up to 1 storage read per `.next()` call.
+    pub fn range(&self, range: Range<K>) -> DoublyLinkedListMapIter<'_, K, V> {
+        DoublyLinkedListMapIter {
+            end: Some(range.end),
+            ..self.iter_from(&range.start)
+        }
+    }
+
+    /// Returns iterator over key-value pairs, which serialized keys start with serialized `prefix`,
+    /// in iteration order; e.g. for keys `(AccountId, TokenId)` and `prefix` of `AccountId`,
+    /// iterator yields all entries of the account.
+    ///
+    /// Since entries aren't ordered by keys, the whole map is walked.
+    /// Performs up to 1 storage read per entry of the map.
+    pub fn iter_prefix<P: BorshSerialize>(&self, prefix: &P) -> impl Iterator<Item = (K, V)> + '_ {
+        let prefix = prefix.try_to_vec().unwrap();
+        self.iter()
+            .filter(move |(key, _value)| key.try_to_vec().unwrap().starts_with(&prefix))
+    }
+
+    /// Returns iterator over at most `n` first key-value pairs.
+    ///
+    /// Performs up to 1 storage read per `.next()` call.
+    pub fn take_n(&self, n: usize) -> Take<DoublyLinkedListMapIter<'_, K, V>> {
+        self.iter().take(n)
+    }
+
     /// Removes all entries.
     ///
     /// Performs up to 1 storage read and uo to 1 storage remove per entry.
     pub fn clear(&mut self) {
-        let mut next_key = self.head.take().map(|(key, _node)| key);
+        // Head node isn't in storage, only the rest of nodes are removed from it
+        let mut next_key = self
+            .head
+            .take()
+            .and_then(|(_key, head_node_bytes)| Self::deserialize_node(&head_node_bytes).next_key);
 
         while let Some(key) = next_key {
-            let node = self.get_node(&key).unwrap();
+            let node = self.get_stored_node(&key).unwrap();
             self.remove_stored_node(&key);
             next_key = node.next_key;
         }
@@ -215,24 +421,26 @@ where
 
     fn get_stored_node(&self, key: &K) -> Option<Node<K, V>> {
         let raw_key = raw_storage_key(&self.key_prefix, key);
-        env::storage_read(&raw_key).map(|bytes| Node::deserialize(&mut bytes.as_slice()).unwrap())
+        self.cache
+            .read(&raw_key)
+            .map(|bytes| Node::deserialize(&mut bytes.as_slice()).unwrap())
     }
 
     fn set_stored_node(&mut self, key: &K, node: &Node<K, V>) {
         let raw_key = raw_storage_key(&self.key_prefix, key);
         let mut node_bytes = Vec::new();
         node.serialize(&mut node_bytes).unwrap();
-        env::storage_write(&raw_key, &node_bytes);
+        self.cache.write(raw_key, node_bytes);
     }
 
     fn remove_stored_node(&mut self, key: &K) {
         let raw_key = raw_storage_key(&self.key_prefix, key);
-        env::storage_remove(&raw_key);
+        self.cache.remove(raw_key);
     }
 
     fn contains_stored_node(&self, key: &K) -> bool {
         let raw_key = raw_storage_key(&self.key_prefix, key);
-        env::storage_has_key(&raw_key)
+        self.cache.has_key(&raw_key)
     }
 
     fn serialize_node(node: &Node<K, V>) -> Vec<u8> {
@@ -252,6 +460,8 @@ where
 {
     map: &'a DoublyLinkedListMap<K, V>,
     key: Option<K>,
+    /// Key at which iteration stops, if any
+    end: Option<K>,
 }
 
 impl<'a, K, V> Iterator for DoublyLinkedListMapIter<'a, K, V>
@@ -263,6 +473,9 @@ where
 
     fn next(&mut self) -> Option<Self::Item> {
         let key = self.key.take()?;
+        if self.end.as_ref() == Some(&key) {
+            return None;
+        }
         let node = self.map.get_node(&key).unwrap();
         self.key = node.next_key;
         Some((key, node.value))
@@ -282,3 +495,187 @@ where
         self.iter()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use near_sdk::borsh::{BorshDeserialize, BorshSerialize};
+    use near_sdk::env;
+
+    use super::DoublyLinkedListMap;
+
+    type TestMap = DoublyLinkedListMap<u32, u32>;
+
+    /// Map as the next transaction reads it, i.e. without nodes cached by current one
+    fn reload(map: &TestMap) -> TestMap {
+        TestMap::try_from_slice(&map.try_to_vec().unwrap()).unwrap()
+    }
+
+    #[test]
+    fn writes_persist_without_flush() {
+        let mut map = TestMap::new(b"m".to_vec());
+        let storage_usage = env::storage_usage();
+        for key in 0..4 {
+            map.insert(&key, key * 10);
+        }
+        // Head node is kept in-place, the rest are already in storage
+        assert!(env::storage_usage() > storage_usage);
+        map.insert(&1, 11);
+        map.remove(&2);
+
+        let reloaded = reload(&map);
+        // Map is never dropped, so nothing may depend on flushing it
+        std::mem::forget(map);
+        assert_eq!(
+            reloaded.iter().collect::<Vec<_>>(),
+            vec![(3, 30), (1, 11), (0, 0)]
+        );
+        assert_eq!(reloaded.len(), 3);
+    }
+
+    /// Map with keys `0..len`, which iterates them in reverse order;
+    /// maps of one test need distinct prefixes, as they share storage
+    fn filled(prefix: &[u8], len: u32) -> TestMap {
+        let mut map = TestMap::new(prefix);
+        for key in 0..len {
+            map.insert(&key, key * 10);
+        }
+        map
+    }
+
+    fn keys(iter: impl Iterator<Item = (u32, u32)>) -> Vec<u32> {
+        iter.map(|(key, _value)| key).collect()
+    }
+
+    #[test]
+    fn iter_from() {
+        let map = filled(b"m", 5);
+        assert_eq!(keys(map.iter_from(&4)), vec![4, 3, 2, 1, 0]);
+        assert_eq!(keys(map.iter_from(&2)), vec![2, 1, 0]);
+        assert_eq!(keys(map.iter_from(&0)), vec![0]);
+        assert!(keys(map.iter_from(&5)).is_empty());
+    }
+
+    #[test]
+    fn range_follows_iteration_order() {
+        let map = filled(b"m", 5);
+        assert_eq!(keys(map.range(3..1)), vec![3, 2]);
+        assert!(keys(map.range(3..3)).is_empty());
+        // Not a range of keys: end entry precedes start entry, so iteration runs to the end
+        assert_eq!(keys(map.range(1..3)), vec![1, 0]);
+        assert_eq!(keys(map.range(2..7)), vec![2, 1, 0]);
+        assert!(keys(map.range(7..2)).is_empty());
+    }
+
+    #[test]
+    fn take_n() {
+        let map = filled(b"m", 5);
+        assert_eq!(keys(map.take_n(2)), vec![4, 3]);
+        assert!(keys(map.take_n(0)).is_empty());
+        assert_eq!(keys(map.take_n(7)), vec![4, 3, 2, 1, 0]);
+    }
+
+    #[test]
+    fn iter_prefix() {
+        let mut map = DoublyLinkedListMap::<(u32, u32), u32>::new(b"m".to_vec());
+        for key in [(1, 1), (2, 1), (1, 2), (3, 3), (1, 3)] {
+            map.insert(&key, key.0 + key.1);
+        }
+        assert_eq!(
+            map.iter_prefix(&1u32).collect::<Vec<_>>(),
+            vec![((1, 3), 4), ((1, 2), 3), ((1, 1), 2)]
+        );
+        assert_eq!(map.iter_prefix(&(2u32, 1u32)).count(), 1);
+        assert_eq!(map.iter_prefix(&4u32).count(), 0);
+        assert_eq!(map.iter_prefix(&()).count(), 5);
+    }
+
+    /// Keys in iteration order, checking that nodes are consistently linked backwards
+    fn linked_keys(map: &TestMap) -> Vec<u32> {
+        let keys = keys(map.iter());
+        let mut prev_key = None;
+        for key in &keys {
+            assert_eq!(map.get_node(key).unwrap().prev_key, prev_key);
+            prev_key = Some(*key);
+        }
+        assert_eq!(map.len(), keys.len());
+        keys
+    }
+
+    #[test]
+    fn clear() {
+        let mut map = filled(b"m", 3);
+        let storage_usage = env::storage_usage();
+        map.clear();
+        assert!(env::storage_usage() < storage_usage);
+        assert!(map.is_empty());
+        assert!(linked_keys(&reload(&map)).is_empty());
+        map.insert(&0, 0);
+        assert_eq!(linked_keys(&map), vec![0]);
+    }
+
+    #[test]
+    fn remove_many() {
+        for (index, (removed, count, kept)) in [
+            // Head, and run starting at head
+            (vec![5], 1, vec![4, 3, 2, 1, 0]),
+            (vec![5, 4], 2, vec![3, 2, 1, 0]),
+            // Tail, and run ending at tail
+            (vec![0], 1, vec![5, 4, 3, 2, 1]),
+            (vec![0, 1], 2, vec![5, 4, 3, 2]),
+            // Adjacent nodes in the middle, in either order
+            (vec![3, 2], 2, vec![5, 4, 1, 0]),
+            (vec![1, 3, 2], 3, vec![5, 4, 0]),
+            // Separate nodes
+            (vec![4, 2, 0], 3, vec![5, 3, 1]),
+            (vec![5, 3, 0], 3, vec![4, 2, 1]),
+            // Missing and repeated keys
+            (vec![7, 3, 3], 1, vec![5, 4, 2, 1, 0]),
+            (vec![], 0, vec![5, 4, 3, 2, 1, 0]),
+            (vec![0, 1, 2, 3, 4, 5], 6, vec![]),
+        ]
+        .into_iter()
+        .enumerate()
+        {
+            let mut map = filled(&[index as u8], 6);
+            assert_eq!(map.remove_many(&removed), count, "removing {removed:?}");
+            assert_eq!(linked_keys(&map), kept, "removing {removed:?}");
+            assert_eq!(linked_keys(&reload(&map)), kept, "removing {removed:?}");
+
+            map.insert(&9, 90);
+            assert_eq!(linked_keys(&map)[0], 9);
+            assert_eq!(map.len(), kept.len() + 1);
+        }
+    }
+
+    #[test]
+    fn extend_empty() {
+        let mut map = filled(b"m", 0);
+        map.extend([(0, 0), (1, 10), (1, 11), (2, 20)]);
+        assert_eq!(
+            reload(&map).iter().collect::<Vec<_>>(),
+            vec![(2, 20), (1, 11), (0, 0)]
+        );
+        assert_eq!(linked_keys(&map), vec![2, 1, 0]);
+
+        let mut map = filled(b"n", 0);
+        map.extend([]);
+        assert!(map.is_empty());
+        assert!(linked_keys(&reload(&map)).is_empty());
+    }
+
+    #[test]
+    fn extend_non_empty() {
+        let mut map = filled(b"m", 3);
+        map.extend([(3, 30), (1, 11), (4, 40), (4, 44), (3, 33)]);
+        assert_eq!(
+            reload(&map).iter().collect::<Vec<_>>(),
+            vec![(4, 44), (3, 33), (2, 20), (1, 11), (0, 0)]
+        );
+        assert_eq!(linked_keys(&map), vec![4, 3, 2, 1, 0]);
+        assert_eq!(linked_keys(&reload(&map)), vec![4, 3, 2, 1, 0]);
+
+        let mut map = filled(b"n", 3);
+        map.extend([]);
+        assert_eq!(linked_keys(&reload(&map)), vec![2, 1, 0]);
+    }
+}
@@ -2,9 +2,12 @@ use near_sdk::borsh::BorshSerialize;
 
 mod doubly_linked_list_map;
 mod linked_list_map;
+mod recently_written_map;
+mod storage_cache;
 
 pub use doubly_linked_list_map::*;
 pub use linked_list_map::*;
+pub use recently_written_map::*;
 
 pub(crate) fn raw_storage_key(prefix: impl AsRef<[u8]>, key: &impl BorshSerialize) -> Vec<u8> {
     let mut key_buff = Vec::new();
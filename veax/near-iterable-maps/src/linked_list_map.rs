@@ -1,13 +1,23 @@
+use std::collections::BTreeSet;
+use std::iter::Take;
 use std::marker::PhantomData;
+use std::ops::Range;
 
 use near_sdk::borsh::{self, BorshDeserialize, BorshSerialize};
-use near_sdk::{env, IntoStorageKey};
+use near_sdk::IntoStorageKey;
 
 use crate::raw_storage_key;
+use crate::storage_cache::StorageCache;
 
 /// Key-value map that allows iteration.
 ///
 /// This map doesn't allow removing random entries, but allows to drain itself using `LinkedListMap::pop` mehtod.
+///
+/// Entries are iterated from the most recently inserted one; order of keys isn't maintained.
+///
+/// Nodes are cached for the duration of transaction, so each node is read from storage at most once,
+/// and storage reads listed in methods' docs happen only on first access to the node.
+/// Writes go to storage right away.
 #[derive(BorshSerialize, BorshDeserialize)]
 pub struct LinkedListMap<K, V>
 where
@@ -18,6 +28,10 @@ where
     length: u64,
     next_key: Option<K>,
     _phantom_data: PhantomData<V>,
+
+    /// Nodes read or written during current transaction
+    #[borsh_skip]
+    cache: StorageCache,
 }
 
 #[derive(Debug, BorshDeserialize)]
@@ -46,6 +60,7 @@ where
             key_prefix: key_prefix.into_storage_key(),
             next_key: None,
             _phantom_data: PhantomData,
+            cache: StorageCache::default(),
         }
     }
 
@@ -73,6 +88,16 @@ where
         }
     }
 
+    /// Inserts all key-value pairs into map; later pairs override earlier ones with the same key.
+    ///
+    /// New entries are linked in front of the list, so existing nodes aren't rewritten.
+    /// Performs 1 storage read and 1 storage write per pair.
+    pub fn extend<I: IntoIterator<Item = (K, V)>>(&mut self, pairs: I) {
+        for (key, value) in pairs {
+            self.insert(&key, &value);
+        }
+    }
+
     /// Checks whether the map contains a key.
     ///
     /// Performs up to 1 storage read.
@@ -99,6 +124,54 @@ where
         Some((key, node.value))
     }
 
+    /// Removes all specified keys from the map, returns number of removed entries.
+    ///
+    /// Since nodes aren't linked backwards, the list is walked from its beginning
+    /// up to the kept node following the last removed one, and each kept node
+    /// whose successor was removed is relinked once.
+    /// Performs up to 1 storage read per key, 1 storage read per walked node,
+    /// 1 storage remove per removed entry and 1 storage write per run of adjacent removed entries.
+    pub fn remove_many<'k, I>(&mut self, keys: I) -> usize
+    where
+        I: IntoIterator<Item = &'k K>,
+        K: 'k,
+    {
+        let mut pending: BTreeSet<Vec<u8>> = keys
+            .into_iter()
+            .filter(|key| self.contains_node(key))
+            .map(|key| raw_storage_key(&self.key_prefix, key))
+            .collect();
+        let removed = pending.len();
+
+        // Last kept node, which must be linked to the next kept one if there were removed nodes in between
+        let mut last_kept: Option<(K, Node<K, V>)> = None;
+        let mut relink = false;
+        let mut cursor = self.next_key.clone();
+        while let Some(key) = cursor {
+            if pending.is_empty() && !relink {
+                break;
+            }
+            let node = self.get_node(&key).unwrap();
+            cursor = node.next_key.clone();
+
+            if pending.remove(&raw_storage_key(&self.key_prefix, &key)) {
+                self.remove_node(&key);
+                relink = true;
+                continue;
+            }
+            if std::mem::take(&mut relink) {
+                self.link(last_kept.take(), Some(key.clone()));
+            }
+            last_kept = Some((key, node));
+        }
+        if relink {
+            self.link(last_kept, None);
+        }
+
+        self.length -= removed as u64;
+        removed
+    }
+
     /// Returns iterator over key-value pairs.
     ///
     /// Performs up to 1 storage read per `.next()` call.
@@ -106,9 +179,59 @@ where
         LinkedListMapIter {
             map: self,
             key: self.next_key.clone(),
+            end: None,
         }
     }
 
+    /// Returns iterator over key-value pairs, starting from specified key (inclusive)
+    /// and continuing in iteration order. Iterator is empty if map doesn't contain the key.
+    ///
+    /// Performs up to 1 storage read, and up to 1 storage read per `.next()` call.
+    pub fn iter_from(&self, key: &K) -> LinkedListMapIter<'_, K, V> {
+        LinkedListMapIter {
+            map: self,
+            key: self.contains_key(key).then(|| key.clone()),
+            end: None,
+        }
+    }
+
+    /// Returns iterator over key-value pairs, starting from `range.start` key (inclusive)
+    /// and stopping before `range.end` key, in iteration order.
+    /// Iterator is empty if map doesn't contain start key,
+    /// and runs until the last entry if map doesn't contain end key.
+    ///
+    /// NB: bounds are positions in iteration order, i.e. from the most recently inserted entry
+    /// to the least recent one, not in order of keys. Entries whose keys lie between the bounds
+    /// aren't yielded unless they were inserted between the bounds' entries, and if `range.end`
+    /// was inserted after `range.start`, iterator runs until the last entry.
+    ///
+    /// Performs up to 1 storage read, and up to 1 storage read per `.next()` call.
+    pub fn range(&self, range: Range<K>) -> LinkedListMapIter<'_, K, V> {
+        LinkedListMapIter {
+            end: Some(range.end),
+            ..self.iter_from(&range.start)
+        }
+    }
+
+    /// Returns iterator over key-value pairs, which serialized keys start with serialized `prefix`,
+    /// in iteration order; e.g. for keys `(AccountId, TokenId)` and `prefix` of `AccountId`,
+    /// iterator yields all entries of the account.
+    ///
+    /// Since entries aren't ordered by keys, the whole map is walked.
+    /// Performs up to 1 storage read per entry of the map.
+    pub fn iter_prefix<P: BorshSerialize>(&self, prefix: &P) -> impl Iterator<Item = (K, V)> + '_ {
+        let prefix = prefix.try_to_vec().unwrap();
+        self.iter()
+            .filter(move |(key, _value)| key.try_to_vec().unwrap().starts_with(&prefix))
+    }
+
+    /// Returns iterator over at most `n` first key-value pairs.
+    ///
+    /// Performs up to 1 storage read per `.next()` call.
+    pub fn take_n(&self, n: usize) -> Take<LinkedListMapIter<'_, K, V>> {
+        self.iter().take(n)
+    }
+
     /// Removes all entries.
     ///
     /// Performs up to 1 storage read and uo to 1 storage remove per entry.
@@ -130,26 +253,42 @@ where
         self.length == 0
     }
 
+    /// Points specified node, or the list beginning if none, to the new next key
+    fn link(&mut self, node: Option<(K, Node<K, V>)>, next_key: Option<K>) {
+        match node {
+            Some((key, node)) => self.set_node(
+                &key,
+                &NodeRef {
+                    value: &node.value,
+                    next_key,
+                },
+            ),
+            None => self.next_key = next_key,
+        }
+    }
+
     fn get_node(&self, key: &K) -> Option<Node<K, V>> {
         let raw_key = raw_storage_key(&self.key_prefix, key);
-        env::storage_read(&raw_key).map(|bytes| Node::deserialize(&mut bytes.as_slice()).unwrap())
+        self.cache
+            .read(&raw_key)
+            .map(|bytes| Node::deserialize(&mut bytes.as_slice()).unwrap())
     }
 
     fn set_node(&mut self, key: &K, node: &NodeRef<K, V>) {
         let raw_key = raw_storage_key(&self.key_prefix, key);
         let mut node_bytes = Vec::new();
         node.serialize(&mut node_bytes).unwrap();
-        env::storage_write(&raw_key, &node_bytes);
+        self.cache.write(raw_key, node_bytes);
     }
 
     fn remove_node(&mut self, key: &K) {
         let raw_key = raw_storage_key(&self.key_prefix, key);
-        env::storage_remove(&raw_key);
+        self.cache.remove(raw_key);
     }
 
     fn contains_node(&self, key: &K) -> bool {
         let raw_key = raw_storage_key(&self.key_prefix, key);
-        env::storage_has_key(&raw_key)
+        self.cache.has_key(&raw_key)
     }
 }
 
@@ -160,6 +299,8 @@ where
 {
     map: &'a LinkedListMap<K, V>,
     key: Option<K>,
+    /// Key at which iteration stops, if any
+    end: Option<K>,
 }
 
 impl<'a, K, V> Iterator for LinkedListMapIter<'a, K, V>
@@ -171,6 +312,9 @@ where
 
     fn next(&mut self) -> Option<Self::Item> {
         let key = self.key.take()?;
+        if self.end.as_ref() == Some(&key) {
+            return None;
+        }
         let node = self.map.get_node(&key).unwrap();
         self.key = node.next_key;
         Some((key, node.value))
@@ -190,3 +334,169 @@ where
         self.iter()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use near_sdk::borsh::{BorshDeserialize, BorshSerialize};
+    use near_sdk::env;
+
+    use super::LinkedListMap;
+
+    type TestMap = LinkedListMap<u32, u32>;
+
+    /// Map as the next transaction reads it, i.e. without nodes cached by current one
+    fn reload(map: &TestMap) -> TestMap {
+        TestMap::try_from_slice(&map.try_to_vec().unwrap()).unwrap()
+    }
+
+    #[test]
+    fn writes_persist_without_flush() {
+        let mut map = TestMap::new(b"m".to_vec());
+        let storage_usage = env::storage_usage();
+        for key in 0..4 {
+            map.insert(&key, &(key * 10));
+        }
+        assert!(env::storage_usage() > storage_usage);
+        map.insert(&1, &11);
+        map.pop();
+
+        let reloaded = reload(&map);
+        // Map is never dropped, so nothing may depend on flushing it
+        std::mem::forget(map);
+        assert_eq!(
+            reloaded.iter().collect::<Vec<_>>(),
+            vec![(2, 20), (1, 11), (0, 0)]
+        );
+        assert_eq!(reloaded.len(), 3);
+    }
+
+    /// Map with keys `0..len`, which iterates them in reverse order;
+    /// maps of one test need distinct prefixes, as they share storage
+    fn filled(prefix: &[u8], len: u32) -> TestMap {
+        let mut map = TestMap::new(prefix);
+        for key in 0..len {
+            map.insert(&key, &(key * 10));
+        }
+        map
+    }
+
+    fn keys(iter: impl Iterator<Item = (u32, u32)>) -> Vec<u32> {
+        iter.map(|(key, _value)| key).collect()
+    }
+
+    #[test]
+    fn iter_from() {
+        let map = filled(b"m", 5);
+        assert_eq!(keys(map.iter_from(&4)), vec![4, 3, 2, 1, 0]);
+        assert_eq!(keys(map.iter_from(&2)), vec![2, 1, 0]);
+        assert_eq!(keys(map.iter_from(&0)), vec![0]);
+        assert!(keys(map.iter_from(&5)).is_empty());
+    }
+
+    #[test]
+    fn range_follows_iteration_order() {
+        let map = filled(b"m", 5);
+        assert_eq!(keys(map.range(3..1)), vec![3, 2]);
+        assert!(keys(map.range(3..3)).is_empty());
+        // Not a range of keys: end entry precedes start entry, so iteration runs to the end
+        assert_eq!(keys(map.range(1..3)), vec![1, 0]);
+        assert_eq!(keys(map.range(2..7)), vec![2, 1, 0]);
+        assert!(keys(map.range(7..2)).is_empty());
+    }
+
+    #[test]
+    fn take_n() {
+        let map = filled(b"m", 5);
+        assert_eq!(keys(map.take_n(2)), vec![4, 3]);
+        assert!(keys(map.take_n(0)).is_empty());
+        assert_eq!(keys(map.take_n(7)), vec![4, 3, 2, 1, 0]);
+    }
+
+    #[test]
+    fn iter_prefix() {
+        let mut map = LinkedListMap::<(u32, u32), u32>::new(b"m".to_vec());
+        for key in [(1, 1), (2, 1), (1, 2), (3, 3), (1, 3)] {
+            map.insert(&key, &(key.0 + key.1));
+        }
+        assert_eq!(
+            map.iter_prefix(&1u32).collect::<Vec<_>>(),
+            vec![((1, 3), 4), ((1, 2), 3), ((1, 1), 2)]
+        );
+        assert_eq!(map.iter_prefix(&(2u32, 1u32)).count(), 1);
+        assert_eq!(map.iter_prefix(&4u32).count(), 0);
+        assert_eq!(map.iter_prefix(&()).count(), 5);
+    }
+
+    /// Keys in iteration order, checking that length is consistent with them
+    fn linked_keys(map: &TestMap) -> Vec<u32> {
+        let keys = keys(map.iter());
+        assert_eq!(map.len(), keys.len());
+        keys
+    }
+
+    #[test]
+    fn remove_many() {
+        for (index, (removed, count, kept)) in [
+            // Head, and run starting at head
+            (vec![5], 1, vec![4, 3, 2, 1, 0]),
+            (vec![5, 4], 2, vec![3, 2, 1, 0]),
+            // Tail, and run ending at tail
+            (vec![0], 1, vec![5, 4, 3, 2, 1]),
+            (vec![0, 1], 2, vec![5, 4, 3, 2]),
+            // Adjacent nodes in the middle, in either order
+            (vec![3, 2], 2, vec![5, 4, 1, 0]),
+            (vec![1, 3, 2], 3, vec![5, 4, 0]),
+            // Separate nodes
+            (vec![4, 2, 0], 3, vec![5, 3, 1]),
+            (vec![5, 3, 0], 3, vec![4, 2, 1]),
+            // Missing and repeated keys
+            (vec![7, 3, 3], 1, vec![5, 4, 2, 1, 0]),
+            (vec![], 0, vec![5, 4, 3, 2, 1, 0]),
+            (vec![0, 1, 2, 3, 4, 5], 6, vec![]),
+        ]
+        .into_iter()
+        .enumerate()
+        {
+            let mut map = filled(&[index as u8], 6);
+            assert_eq!(map.remove_many(&removed), count, "removing {removed:?}");
+            assert_eq!(linked_keys(&map), kept, "removing {removed:?}");
+            assert_eq!(linked_keys(&reload(&map)), kept, "removing {removed:?}");
+
+            map.insert(&9, &90);
+            assert_eq!(linked_keys(&map)[0], 9);
+            assert_eq!(map.len(), kept.len() + 1);
+        }
+    }
+
+    #[test]
+    fn extend_empty() {
+        let mut map = filled(b"m", 0);
+        map.extend([(0, 0), (1, 10), (1, 11), (2, 20)]);
+        assert_eq!(
+            reload(&map).iter().collect::<Vec<_>>(),
+            vec![(2, 20), (1, 11), (0, 0)]
+        );
+        assert_eq!(linked_keys(&map), vec![2, 1, 0]);
+
+        let mut map = filled(b"n", 0);
+        map.extend([]);
+        assert!(map.is_empty());
+        assert!(linked_keys(&reload(&map)).is_empty());
+    }
+
+    #[test]
+    fn extend_non_empty() {
+        let mut map = filled(b"m", 3);
+        map.extend([(3, 30), (1, 11), (4, 40), (4, 44), (3, 33)]);
+        assert_eq!(
+            reload(&map).iter().collect::<Vec<_>>(),
+            vec![(4, 44), (3, 33), (2, 20), (1, 11), (0, 0)]
+        );
+        assert_eq!(linked_keys(&map), vec![4, 3, 2, 1, 0]);
+        assert_eq!(linked_keys(&reload(&map)), vec![4, 3, 2, 1, 0]);
+
+        let mut map = filled(b"n", 3);
+        map.extend([]);
+        assert_eq!(linked_keys(&reload(&map)), vec![2, 1, 0]);
+    }
+}
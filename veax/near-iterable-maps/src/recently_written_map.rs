@@ -0,0 +1,338 @@
+use std::slice;
+
+use near_sdk::borsh::{self, BorshDeserialize, BorshSerialize};
+use near_sdk::IntoStorageKey;
+
+use crate::{DoublyLinkedListMap, DoublyLinkedListMapIter};
+
+/// Key-value map that keeps up to `capacity` most recently written entries in-place,
+/// and spills older ones to backing `DoublyLinkedListMap`.
+///
+/// In-place entries are serialized together with the map itself, i.e. they live in the same storage record
+/// as the structure which owns the map, so accessing them doesn't perform any storage operations.
+/// On the other hand, every in-place entry adds its size to each read and write of that record,
+/// so `capacity` should only cover entries which are accessed at least once per few dozen
+/// writes of that record.
+///
+/// NB: this isn't an LRU map. Only insertions and updates make entry the most recent one;
+/// lookups take `&self` and don't change entries order, so that read-only calls never have to write storage.
+/// Entries which are often read but rarely written are evicted regardless of reads.
+#[derive(BorshSerialize, BorshDeserialize)]
+pub struct RecentlyWrittenMap<K, V>
+where
+    K: Eq + Clone + BorshSerialize + BorshDeserialize,
+    V: BorshSerialize + BorshDeserialize,
+{
+    capacity: u32,
+
+    /// In-place entries, ordered from least to most recently written.
+    ///
+    /// Values are kept serialized, same as head node of `DoublyLinkedListMap`,
+    /// so values don't need to implement `Clone`.
+    hot: Vec<(K, Vec<u8>)>,
+
+    /// Entries evicted from in-place storage
+    cold: DoublyLinkedListMap<K, V>,
+}
+
+impl<K, V> RecentlyWrittenMap<K, V>
+where
+    K: Eq + Clone + BorshSerialize + BorshDeserialize,
+    V: BorshSerialize + BorshDeserialize,
+{
+    /// Creates map which keeps up to `capacity` entries in-place
+    pub fn new<S>(key_prefix: S, capacity: u32) -> Self
+    where
+        S: IntoStorageKey,
+    {
+        Self {
+            capacity,
+            hot: Vec::new(),
+            cold: DoublyLinkedListMap::new(key_prefix),
+        }
+    }
+
+    /// Wraps existing map, which entries stay in it until they're inserted again.
+    ///
+    /// Doesn't access storage.
+    pub fn from_map(map: DoublyLinkedListMap<K, V>, capacity: u32) -> Self {
+        Self {
+            capacity,
+            hot: Vec::new(),
+            cold: map,
+        }
+    }
+
+    /// Inserts new value into map, making it the most recently written entry.
+    ///
+    /// Doesn't access storage if the key is already kept in-place.
+    /// Otherwise performs operations of `DoublyLinkedListMap::remove` for the key,
+    /// and of `DoublyLinkedListMap::insert` for the evicted entry, if any.
+    pub fn insert(&mut self, key: &K, value: V) -> Option<V> {
+        let old_value = match self.hot_position(key) {
+            Some(index) => {
+                let (_key, value_bytes) = self.hot.remove(index);
+                Some(Self::deserialize_value(&value_bytes))
+            }
+            None => self.cold.remove(key),
+        };
+        self.hot.push((key.clone(), Self::serialize_value(&value)));
+        self.evict();
+        old_value
+    }
+
+    /// Checks whether the map contains a key.
+    ///
+    /// Performs up to 1 storage read.
+    pub fn contains_key(&self, key: &K) -> bool {
+        self.hot_position(key).is_some() || self.cold.contains_key(key)
+    }
+
+    /// Returns respective value for the specified key.
+    ///
+    /// Performs up to 1 storage read.
+    pub fn get(&self, key: &K) -> Option<V> {
+        match self.hot_position(key) {
+            Some(index) => Some(Self::deserialize_value(&self.hot[index].1)),
+            None => self.cold.get(key),
+        }
+    }
+
+    /// Removes key and respective value from the map.
+    ///
+    /// Doesn't access storage if the key is kept in-place,
+    /// otherwise performs operations of `DoublyLinkedListMap::remove`.
+    pub fn remove(&mut self, key: &K) -> Option<V> {
+        match self.hot_position(key) {
+            Some(index) => {
+                let (_key, value_bytes) = self.hot.remove(index);
+                Some(Self::deserialize_value(&value_bytes))
+            }
+            None => self.cold.remove(key),
+        }
+    }
+
+    /// Returns iterator over key-value pairs; in-place entries go first,
+    /// from least to most recently written, followed by entries of backing map.
+    ///
+    /// Performs up to 1 storage read per `.next()` call.
+    pub fn iter(&self) -> RecentlyWrittenMapIter<'_, K, V> {
+        RecentlyWrittenMapIter {
+            hot: self.hot.iter(),
+            cold: self.cold.iter(),
+        }
+    }
+
+    /// Removes all entries.
+    ///
+    /// Performs operations of `DoublyLinkedListMap::clear` for entries which aren't kept in-place.
+    pub fn clear(&mut self) {
+        self.hot.clear();
+        self.cold.clear();
+    }
+
+    /// Returns number of entries in the collection.
+    pub fn len(&self) -> usize {
+        self.hot.len() + self.cold.len()
+    }
+
+    /// Checks if the collection is empty.
+    ///
+    /// Doesn't access storage.
+    pub fn is_empty(&self) -> bool {
+        self.hot.is_empty() && self.cold.is_empty()
+    }
+
+    /// Moves least recently written entries to backing map until in-place entries fit into capacity
+    fn evict(&mut self) {
+        while self.hot.len() > self.capacity as usize {
+            let (key, value_bytes) = self.hot.remove(0);
+            self.cold
+                .insert(&key, Self::deserialize_value(&value_bytes));
+        }
+    }
+
+    fn hot_position(&self, key: &K) -> Option<usize> {
+        self.hot.iter().position(|(hot_key, _value)| hot_key == key)
+    }
+
+    fn serialize_value(value: &V) -> Vec<u8> {
+        let mut bytes = Vec::new();
+        value.serialize(&mut bytes).unwrap();
+        bytes
+    }
+
+    fn deserialize_value(mut bytes: &[u8]) -> V {
+        V::deserialize(&mut bytes).unwrap()
+    }
+}
+
+pub struct RecentlyWrittenMapIter<'a, K, V>
+where
+    K: Eq + Clone + BorshSerialize + BorshDeserialize,
+    V: BorshSerialize + BorshDeserialize,
+{
+    hot: slice::Iter<'a, (K, Vec<u8>)>,
+    cold: DoublyLinkedListMapIter<'a, K, V>,
+}
+
+impl<'a, K, V> Iterator for RecentlyWrittenMapIter<'a, K, V>
+where
+    K: Eq + Clone + BorshSerialize + BorshDeserialize,
+    V: BorshSerialize + BorshDeserialize,
+{
+    type Item = (K, V);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        match self.hot.next() {
+            Some((key, value_bytes)) => Some((
+                key.clone(),
+                RecentlyWrittenMap::<K, V>::deserialize_value(value_bytes),
+            )),
+            None => self.cold.next(),
+        }
+    }
+}
+
+impl<'a, K, V> IntoIterator for &'a RecentlyWrittenMap<K, V>
+where
+    K: Eq + Clone + BorshSerialize + BorshDeserialize,
+    V: BorshSerialize + BorshDeserialize,
+{
+    type Item = (K, V);
+
+    type IntoIter = RecentlyWrittenMapIter<'a, K, V>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use near_sdk::borsh::{BorshDeserialize, BorshSerialize};
+    use near_sdk::env;
+
+    use super::RecentlyWrittenMap;
+    use crate::DoublyLinkedListMap;
+
+    type TestMap = RecentlyWrittenMap<u32, u32>;
+
+    /// Map as the next transaction reads it
+    fn reload(map: &TestMap) -> TestMap {
+        TestMap::try_from_slice(&map.try_to_vec().unwrap()).unwrap()
+    }
+
+    fn hot_keys(map: &TestMap) -> Vec<u32> {
+        map.hot.iter().map(|(key, _value)| *key).collect()
+    }
+
+    #[test]
+    fn insert_evicts_least_recently_written() {
+        let mut map = TestMap::new(b"m".to_vec(), 2);
+        let storage_usage = env::storage_usage();
+        assert_eq!(map.insert(&0, 0), None);
+        assert_eq!(map.insert(&1, 10), None);
+        // Both entries fit in-place
+        assert_eq!(env::storage_usage(), storage_usage);
+        assert_eq!(hot_keys(&map), vec![0, 1]);
+
+        // Update makes entry the most recently written one
+        assert_eq!(map.insert(&0, 1), Some(0));
+        assert_eq!(hot_keys(&map), vec![1, 0]);
+        assert_eq!(map.insert(&2, 20), None);
+        assert_eq!(hot_keys(&map), vec![0, 2]);
+
+        // Lookups don't
+        assert_eq!(map.get(&0), Some(1));
+        assert!(map.contains_key(&0));
+        assert_eq!(map.insert(&3, 30), None);
+        assert_eq!(hot_keys(&map), vec![2, 3]);
+        // Evicted entries are in backing map, which keeps only its head node in-place
+        assert!(env::storage_usage() > storage_usage);
+
+        // Evicted entry is moved back in-place once written
+        assert_eq!(map.insert(&1, 11), Some(10));
+        assert_eq!(hot_keys(&map), vec![3, 1]);
+        assert_eq!(map.len(), 4);
+
+        let reloaded = reload(&map);
+        assert_eq!(hot_keys(&reloaded), vec![3, 1]);
+        for (key, value) in [(0, 1), (1, 11), (2, 20), (3, 30)] {
+            assert_eq!(reloaded.get(&key), Some(value));
+        }
+        assert_eq!(reloaded.get(&4), None);
+    }
+
+    #[test]
+    fn remove() {
+        let mut map = TestMap::new(b"m".to_vec(), 2);
+        for key in 0..4 {
+            map.insert(&key, key * 10);
+        }
+        let storage_usage = env::storage_usage();
+        // In-place entry
+        assert_eq!(map.remove(&3), Some(30));
+        assert_eq!(env::storage_usage(), storage_usage);
+        assert_eq!(hot_keys(&map), vec![2]);
+        // Evicted entry
+        assert_eq!(map.remove(&0), Some(0));
+        assert!(env::storage_usage() < storage_usage);
+        assert_eq!(map.remove(&0), None);
+        assert_eq!(map.remove(&4), None);
+        assert_eq!(map.len(), 2);
+
+        let reloaded = reload(&map);
+        assert_eq!(reloaded.iter().collect::<Vec<_>>(), vec![(2, 20), (1, 10)]);
+
+        map.clear();
+        assert!(map.is_empty());
+        assert_eq!(reload(&map).iter().count(), 0);
+    }
+
+    #[test]
+    fn iter_order() {
+        let mut map = TestMap::new(b"m".to_vec(), 2);
+        for key in 0..5 {
+            map.insert(&key, key * 10);
+        }
+        map.insert(&3, 33);
+        // In-place entries from least to most recently written,
+        // then evicted ones from most to least recently evicted
+        let entries = vec![(4, 40), (3, 33), (2, 20), (1, 10), (0, 0)];
+        assert_eq!(map.iter().collect::<Vec<_>>(), entries);
+        assert_eq!(reload(&map).iter().collect::<Vec<_>>(), entries);
+    }
+
+    #[test]
+    fn zero_capacity() {
+        let mut map = TestMap::new(b"m".to_vec(), 0);
+        let storage_usage = env::storage_usage();
+        map.insert(&0, 0);
+        map.insert(&1, 10);
+        assert!(hot_keys(&map).is_empty());
+        assert!(env::storage_usage() > storage_usage);
+        assert_eq!(map.get(&1), Some(10));
+        assert_eq!(map.len(), 2);
+    }
+
+    #[test]
+    fn from_map() {
+        let mut backing = DoublyLinkedListMap::new(b"m".to_vec());
+        backing.insert(&0, 0);
+        backing.insert(&1, 10);
+        let mut map = TestMap::from_map(backing, 2);
+        // Entries of wrapped map stay in it until written
+        assert!(hot_keys(&map).is_empty());
+        assert_eq!(map.get(&0), Some(0));
+        assert_eq!(map.len(), 2);
+
+        map.insert(&0, 1);
+        assert_eq!(hot_keys(&map), vec![0]);
+        assert_eq!(map.len(), 2);
+        assert_eq!(
+            reload(&map).iter().collect::<Vec<_>>(),
+            vec![(0, 1), (1, 10)]
+        );
+    }
+}
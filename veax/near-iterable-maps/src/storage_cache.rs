@@ -0,0 +1,48 @@
+use std::cell::RefCell;
+use std::collections::BTreeMap;
+
+use near_sdk::env;
+
+/// In-transaction write-through cache of raw storage records.
+///
+/// Records are read from storage at most once. Modifications are written to storage right away
+/// and kept in memory for subsequent reads, so storage usage is always up to date.
+#[derive(Default)]
+pub(crate) struct StorageCache {
+    /// Record bytes, `None` if record was removed or doesn't exist
+    entries: RefCell<BTreeMap<Vec<u8>, Option<Vec<u8>>>>,
+}
+
+impl StorageCache {
+    /// Returns record bytes, reading storage only on first access to the key
+    pub fn read(&self, key: &[u8]) -> Option<Vec<u8>> {
+        if let Some(value) = self.entries.borrow().get(key) {
+            return value.clone();
+        }
+        let value = env::storage_read(key);
+        self.entries
+            .borrow_mut()
+            .insert(key.to_vec(), value.clone());
+        value
+    }
+
+    /// Checks whether record exists, without reading its bytes if it's not cached
+    pub fn has_key(&self, key: &[u8]) -> bool {
+        match self.entries.borrow().get(key) {
+            Some(value) => value.is_some(),
+            None => env::storage_has_key(key),
+        }
+    }
+
+    /// Replaces record bytes
+    pub fn write(&mut self, key: Vec<u8>, value: Vec<u8>) {
+        env::storage_write(&key, &value);
+        self.entries.get_mut().insert(key, Some(value));
+    }
+
+    /// Removes record
+    pub fn remove(&mut self, key: Vec<u8>) {
+        env::storage_remove(&key);
+        self.entries.get_mut().insert(key, None);
+    }
+}